@@ -35,6 +35,7 @@ impl JsKeySet {
                 id: *id.deref(),
                 unit: CurrencyUnit::from(&unit),
                 keys: keys.deref().clone(),
+                final_expiry: None,
             },
         }
     }
@@ -50,14 +50,14 @@ impl JsSwapRequest {
 
     /// Proofs Amount
     #[wasm_bindgen(js_name = proofsAmount)]
-    pub fn proofs_amount(&self) -> JsAmount {
-        self.inner.input_amount().into()
+    pub fn proofs_amount(&self) -> Result<JsAmount> {
+        Ok(self.inner.input_amount().map_err(into_err)?.into())
     }
 
     /// Output Amount
     #[wasm_bindgen(js_name = outputAmount)]
-    pub fn output_amount(&self) -> JsAmount {
-        self.inner.output_amount().into()
+    pub fn output_amount(&self) -> Result<JsAmount> {
+        Ok(self.inner.output_amount().map_err(into_err)?.into())
     }
 }
 
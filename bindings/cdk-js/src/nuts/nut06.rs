@@ -77,6 +77,7 @@ impl JsMintInfo {
         contact: JsValue,
         nuts: JsValue,
         motd: Option<String>,
+        icon_url: Option<String>,
     ) -> Result<JsMintInfo> {
         Ok(JsMintInfo {
             inner: MintInfo {
@@ -88,6 +89,8 @@ impl JsMintInfo {
                 contact: serde_wasm_bindgen::from_value(contact).map_err(into_err)?,
                 nuts: serde_wasm_bindgen::from_value(nuts).map_err(into_err)?,
                 motd,
+                icon_url,
+                time: None,
             },
         })
     }
@@ -139,4 +142,16 @@ impl JsMintInfo {
     pub fn motd(&self) -> Option<String> {
         self.inner.motd.clone()
     }
+
+    /// Get icon url
+    #[wasm_bindgen(getter)]
+    pub fn icon_url(&self) -> Option<String> {
+        self.inner.icon_url.clone()
+    }
+
+    /// Get server time
+    #[wasm_bindgen(getter)]
+    pub fn time(&self) -> Option<u64> {
+        self.inner.time
+    }
 }
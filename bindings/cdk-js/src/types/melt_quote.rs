@@ -1,5 +1,6 @@
 use std::ops::Deref;
 
+use cdk::nuts::MeltQuoteState;
 use cdk::types::MeltQuote;
 use wasm_bindgen::prelude::*;
 
@@ -53,7 +54,12 @@ impl JsMeltQuote {
 
     #[wasm_bindgen(getter)]
     pub fn paid(&self) -> bool {
-        self.inner.paid
+        self.inner.state == MeltQuoteState::Paid
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn state(&self) -> String {
+        self.inner.state.to_string()
     }
 
     #[wasm_bindgen(getter)]
@@ -5,8 +5,8 @@ use std::str::FromStr;
 use std::sync::Arc;
 
 use cdk::amount::SplitTarget;
+use cdk::mint_url::MintUrl;
 use cdk::nuts::Proofs;
-use cdk::url::UncheckedUrl;
 use cdk::wallet::Wallet;
 use cdk::Amount;
 use cdk_rexie::RexieWalletDatabase;
@@ -98,7 +98,7 @@ impl JsWallet {
         unit: Option<JsCurrencyUnit>,
     ) -> Result<JsAmount> {
         let mint_url = match mint_url {
-            Some(url) => Some(UncheckedUrl::from_str(&url).map_err(into_err)?),
+            Some(url) => Some(MintUrl::from_str(&url).map_err(into_err)?),
             None => None,
         };
 
@@ -119,7 +119,7 @@ impl JsWallet {
 
     #[wasm_bindgen(js_name = addMint)]
     pub async fn add_mint(&self, mint_url: String) -> Result<Option<JsMintInfo>> {
-        let mint_url = UncheckedUrl::from_str(&mint_url).map_err(into_err)?;
+        let mint_url = MintUrl::from_str(&mint_url).map_err(into_err)?;
 
         Ok(self
             .inner
@@ -131,7 +131,7 @@ impl JsWallet {
 
     #[wasm_bindgen(js_name = refreshMint)]
     pub async fn refresh_mint_keys(&self, mint_url: String) -> Result<()> {
-        let mint_url = UncheckedUrl::from_str(&mint_url).map_err(into_err)?;
+        let mint_url = MintUrl::from_str(&mint_url).map_err(into_err)?;
         self.inner
             .refresh_mint_keys(&mint_url)
             .await
@@ -141,15 +141,16 @@ impl JsWallet {
 
     #[wasm_bindgen(js_name = mintQuote)]
     pub async fn mint_quote(
-        &mut self,
+        &self,
         mint_url: String,
         amount: u64,
         unit: JsCurrencyUnit,
+        description: Option<String>,
     ) -> Result<JsMintQuote> {
-        let mint_url = UncheckedUrl::from_str(&mint_url).map_err(into_err)?;
+        let mint_url = MintUrl::from_str(&mint_url).map_err(into_err)?;
         let quote = self
             .inner
-            .mint_quote(mint_url, amount.into(), unit.into())
+            .mint_quote(mint_url, amount.into(), unit.into(), description)
             .await
             .map_err(into_err)?;
 
@@ -162,7 +163,7 @@ impl JsWallet {
         mint_url: String,
         quote_id: String,
     ) -> Result<JsMintQuoteBolt11Response> {
-        let mint_url = UncheckedUrl::from_str(&mint_url).map_err(into_err)?;
+        let mint_url = MintUrl::from_str(&mint_url).map_err(into_err)?;
 
         let quote = self
             .inner
@@ -182,7 +183,7 @@ impl JsWallet {
 
     #[wasm_bindgen(js_name = mint)]
     pub async fn mint(
-        &mut self,
+        &self,
         mint_url: String,
         quote_id: String,
         p2pk_condition: Option<JsP2PKSpendingConditions>,
@@ -192,7 +193,7 @@ impl JsWallet {
         let target = split_target_amount
             .map(|a| SplitTarget::Value(*a.deref()))
             .unwrap_or_default();
-        let mint_url = UncheckedUrl::from_str(&mint_url).map_err(into_err)?;
+        let mint_url = MintUrl::from_str(&mint_url).map_err(into_err)?;
         let conditions = match (p2pk_condition, htlc_condition) {
             (Some(_), Some(_)) => {
                 return Err(JsValue::from_str(
@@ -214,12 +215,12 @@ impl JsWallet {
 
     #[wasm_bindgen(js_name = meltQuote)]
     pub async fn melt_quote(
-        &mut self,
+        &self,
         mint_url: String,
         unit: JsCurrencyUnit,
         request: String,
     ) -> Result<JsMeltQuote> {
-        let mint_url = UncheckedUrl::from_str(&mint_url).map_err(into_err)?;
+        let mint_url = MintUrl::from_str(&mint_url).map_err(into_err)?;
         let melt_quote = self
             .inner
             .melt_quote(mint_url, unit.into(), request)
@@ -235,7 +236,7 @@ impl JsWallet {
         mint_url: String,
         quote_id: String,
     ) -> Result<JsMeltQuoteBolt11Response> {
-        let mint_url = UncheckedUrl::from_str(&mint_url).map_err(into_err)?;
+        let mint_url = MintUrl::from_str(&mint_url).map_err(into_err)?;
 
         let quote = self
             .inner
@@ -248,7 +249,7 @@ impl JsWallet {
 
     #[wasm_bindgen(js_name = melt)]
     pub async fn melt(
-        &mut self,
+        &self,
         mint_url: String,
         quote_id: String,
         split_target_amount: Option<JsAmount>,
@@ -256,7 +257,7 @@ impl JsWallet {
         let target = split_target_amount
             .map(|a| SplitTarget::Value(*a.deref()))
             .unwrap_or_default();
-        let mint_url = UncheckedUrl::from_str(&mint_url).map_err(into_err)?;
+        let mint_url = MintUrl::from_str(&mint_url).map_err(into_err)?;
 
         let melted = self
             .inner
@@ -268,7 +269,7 @@ impl JsWallet {
     }
 
     #[wasm_bindgen(js_name = receive)]
-    pub async fn receive(&mut self, encoded_token: String, preimages: JsValue) -> Result<JsAmount> {
+    pub async fn receive(&self, encoded_token: String, preimages: JsValue) -> Result<JsAmount> {
         let preimages: Option<Vec<String>> = serde_wasm_bindgen::from_value(preimages)?;
 
         Ok(self
@@ -282,7 +283,7 @@ impl JsWallet {
     #[allow(clippy::too_many_arguments)]
     #[wasm_bindgen(js_name = send)]
     pub async fn send(
-        &mut self,
+        &self,
         mint_url: String,
         unit: JsCurrencyUnit,
         memo: Option<String>,
@@ -302,7 +303,7 @@ impl JsWallet {
             (None, None) => None,
         };
 
-        let mint_url = UncheckedUrl::from_str(&mint_url).map_err(into_err)?;
+        let mint_url = MintUrl::from_str(&mint_url).map_err(into_err)?;
 
         let target = split_target_amount
             .map(|a| SplitTarget::Value(*a.deref()))
@@ -323,7 +324,7 @@ impl JsWallet {
     #[allow(clippy::too_many_arguments)]
     #[wasm_bindgen(js_name = swap)]
     pub async fn swap(
-        &mut self,
+        &self,
         mint_url: String,
         unit: JsCurrencyUnit,
         amount: u64,
@@ -343,7 +344,7 @@ impl JsWallet {
             (None, None) => None,
         };
 
-        let mint_url = UncheckedUrl::from_str(&mint_url).map_err(into_err)?;
+        let mint_url = MintUrl::from_str(&mint_url).map_err(into_err)?;
 
         let proofs: Proofs = input_proofs.iter().map(|p| p.deref()).cloned().collect();
 
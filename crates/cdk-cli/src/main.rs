@@ -11,8 +11,11 @@ use cdk_sqlite::WalletSQLiteDatabase;
 use clap::{Parser, Subcommand};
 use rand::Rng;
 
+mod output;
 mod sub_commands;
 
+use output::Output;
+
 /// Simple CLI application to interact with cashu
 #[derive(Parser)]
 #[command(name = "cashu-tool")]
@@ -29,6 +32,9 @@ struct Cli {
     /// File Path to save proofs
     #[arg(short, long)]
     db_path: Option<String>,
+    /// Print results as JSON
+    #[arg(long, global = true)]
+    json: bool,
     #[command(subcommand)]
     command: Commands,
 }
@@ -38,10 +44,14 @@ const DEFAULT_SQLITE_DB_PATH: &str = "./cashu_tool.sqlite";
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Decode a token or bolt11 invoice without contacting the mint
+    Decode(sub_commands::decode::DecodeSubCommand),
     /// Decode a token
     DecodeToken(sub_commands::decode_token::DecodeTokenSubCommand),
     /// Balance
     Balance,
+    /// List or export the transaction history
+    History(sub_commands::history::HistorySubCommand),
     /// Pay bolt11 invoice
     Melt(sub_commands::melt::MeltSubCommand),
     /// Claim pending mints
@@ -51,7 +61,7 @@ enum Commands {
     /// Send
     Send(sub_commands::send::SendSubCommand),
     /// Check if wallet balance is spendable
-    CheckSpendable,
+    CheckSpendable(sub_commands::check_spent::CheckSpentSubCommand),
     /// View mint info
     MintInfo(sub_commands::mint_info::MintInfoSubcommand),
     /// Mint proofs via bolt11
@@ -60,6 +70,8 @@ enum Commands {
     Burn(sub_commands::burn::BurnSubCommand),
     /// Restore proofs from seed
     Restore(sub_commands::restore::RestoreSubCommand),
+    /// Serve the wallet over Nostr Wallet Connect
+    Nwc(sub_commands::nwc::NwcSubCommand),
 }
 
 #[tokio::main]
@@ -70,7 +82,18 @@ async fn main() -> Result<()> {
 
     // Parse input
     let args: Cli = Cli::parse();
+    let output = Output::new(args.json);
+
+    match run(args, output).await {
+        Ok(()) => Ok(()),
+        Err(err) => {
+            output.error(&err);
+            std::process::exit(1);
+        }
+    }
+}
 
+async fn run(args: Cli, output: Output) -> Result<()> {
     let localstore: Arc<dyn WalletDatabase<Err = cdk_database::Error> + Send + Sync> =
         match args.engine.as_str() {
             "sqlite" => {
@@ -103,32 +126,43 @@ async fn main() -> Result<()> {
     let wallet = Wallet::new(localstore, &mnemonic.to_seed_normalized(""), vec![]);
 
     match &args.command {
+        Commands::Decode(sub_command_args) => {
+            sub_commands::decode::decode(sub_command_args, output)
+        }
         Commands::DecodeToken(sub_command_args) => {
-            sub_commands::decode_token::decode_token(sub_command_args)
+            sub_commands::decode_token::decode_token(sub_command_args, output)
+        }
+        Commands::Balance => sub_commands::balance::balance(wallet, output).await,
+        Commands::History(sub_command_args) => {
+            sub_commands::history::history(wallet, sub_command_args, output).await
         }
-        Commands::Balance => sub_commands::balance::balance(wallet).await,
         Commands::Melt(sub_command_args) => {
-            sub_commands::melt::melt(wallet, sub_command_args).await
+            sub_commands::melt::melt(wallet, sub_command_args, output).await
         }
         Commands::Receive(sub_command_args) => {
-            sub_commands::receive::receive(wallet, sub_command_args).await
+            sub_commands::receive::receive(wallet, sub_command_args, output).await
         }
         Commands::Send(sub_command_args) => {
-            sub_commands::send::send(wallet, sub_command_args).await
+            sub_commands::send::send(wallet, sub_command_args, output).await
+        }
+        Commands::CheckSpendable(sub_command_args) => {
+            sub_commands::check_spent::check_spent(wallet, sub_command_args, output).await
         }
-        Commands::CheckSpendable => sub_commands::check_spent::check_spent(wallet).await,
         Commands::MintInfo(sub_command_args) => {
-            sub_commands::mint_info::mint_info(sub_command_args).await
+            sub_commands::mint_info::mint_info(sub_command_args, output).await
         }
         Commands::Mint(sub_command_args) => {
-            sub_commands::mint::mint(wallet, sub_command_args).await
+            sub_commands::mint::mint(wallet, sub_command_args, output).await
         }
-        Commands::PendingMint => sub_commands::pending_mints::pending_mints(wallet).await,
+        Commands::PendingMint => sub_commands::pending_mints::pending_mints(wallet, output).await,
         Commands::Burn(sub_command_args) => {
-            sub_commands::burn::burn(wallet, sub_command_args).await
+            sub_commands::burn::burn(wallet, sub_command_args, output).await
         }
         Commands::Restore(sub_command_args) => {
-            sub_commands::restore::restore(wallet, sub_command_args).await
+            sub_commands::restore::restore(wallet, sub_command_args, output).await
+        }
+        Commands::Nwc(sub_command_args) => {
+            sub_commands::nwc::nwc(wallet, sub_command_args, output).await
         }
     }
 }
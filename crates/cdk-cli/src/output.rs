@@ -0,0 +1,58 @@
+//! Command output
+//!
+//! With `--json` every command prints exactly one JSON object per result line to
+//! stdout and prompts and progress messages go to stderr. Failures print
+//! `{"error": "<message>"}` and exit with status 1. The schemas are listed in
+//! the README.
+
+use std::fmt::Display;
+
+use anyhow::Result;
+use serde::Serialize;
+
+/// Where command output goes
+#[derive(Debug, Clone, Copy)]
+pub struct Output {
+    json: bool,
+}
+
+impl Output {
+    pub fn new(json: bool) -> Self {
+        Self { json }
+    }
+
+    /// Print prompt or progress message
+    pub fn info<T>(&self, message: T)
+    where
+        T: Display,
+    {
+        match self.json {
+            true => eprintln!("{message}"),
+            false => println!("{message}"),
+        }
+    }
+
+    /// Print command result
+    ///
+    /// `human` prints the result when JSON output is disabled.
+    pub fn result<T, F>(&self, result: &T, human: F) -> Result<()>
+    where
+        T: Serialize,
+        F: FnOnce(&T),
+    {
+        match self.json {
+            true => println!("{}", serde_json::to_string(result)?),
+            false => human(result),
+        }
+
+        Ok(())
+    }
+
+    /// Print error
+    pub fn error(&self, error: &anyhow::Error) {
+        match self.json {
+            true => println!("{}", serde_json::json!({ "error": error.to_string() })),
+            false => eprintln!("Error: {error:?}"),
+        }
+    }
+}
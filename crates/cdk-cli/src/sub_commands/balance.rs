@@ -1,29 +1,74 @@
 use std::collections::HashMap;
 
 use anyhow::Result;
+use cdk::mint_url::MintUrl;
 use cdk::nuts::CurrencyUnit;
-use cdk::url::UncheckedUrl;
 use cdk::wallet::Wallet;
 use cdk::Amount;
+use serde::Serialize;
 
-pub async fn balance(wallet: Wallet) -> Result<()> {
-    let _ = mint_balances(&wallet).await;
-    Ok(())
+use crate::output::Output;
+
+#[derive(Serialize)]
+struct BalanceOutput {
+    balances: Vec<MintBalance>,
+}
+
+#[derive(Serialize)]
+struct MintBalance {
+    mint_url: MintUrl,
+    unit: CurrencyUnit,
+    amount: Amount,
+    /// Proofs are split into many small denominations
+    swap_recommended: bool,
+}
+
+pub async fn balance(wallet: Wallet, output: Output) -> Result<()> {
+    let mints_amounts: Vec<(MintUrl, HashMap<_, _>)> =
+        wallet.mint_balances().await?.into_iter().collect();
+
+    let mut balances = Vec::new();
+    for (mint_url, balance) in &mints_amounts {
+        for (unit, amount) in balance {
+            let stats = wallet.denomination_stats(mint_url, unit).await?;
+            balances.push(MintBalance {
+                mint_url: mint_url.clone(),
+                unit: unit.clone(),
+                amount: *amount,
+                swap_recommended: stats.swap_recommended(),
+            });
+        }
+    }
+
+    output.result(&BalanceOutput { balances }, |o| {
+        print_mint_balances(&mints_amounts, output);
+        for balance in o.balances.iter().filter(|b| b.swap_recommended) {
+            output.info(format!(
+                "Swap recommended for {} at {}: proofs are fragmented",
+                balance.unit, balance.mint_url
+            ));
+        }
+    })
 }
 
 pub async fn mint_balances(
     wallet: &Wallet,
-) -> Result<Vec<(UncheckedUrl, HashMap<CurrencyUnit, Amount>)>> {
-    let mints_amounts: Vec<(UncheckedUrl, HashMap<_, _>)> =
+    output: Output,
+) -> Result<Vec<(MintUrl, HashMap<CurrencyUnit, Amount>)>> {
+    let mints_amounts: Vec<(MintUrl, HashMap<_, _>)> =
         wallet.mint_balances().await?.into_iter().collect();
 
+    print_mint_balances(&mints_amounts, output);
+
+    Ok(mints_amounts)
+}
+
+fn print_mint_balances(mints_amounts: &[(MintUrl, HashMap<CurrencyUnit, Amount>)], output: Output) {
     for (i, (mint, balance)) in mints_amounts.iter().enumerate() {
-        println!("{i}: {mint}:");
+        output.info(format!("{i}: {mint}:"));
         for (unit, amount) in balance {
-            println!("- {amount} {unit}");
+            output.info(format!("- {amount} {unit}"));
         }
-        println!("---------");
+        output.info("---------");
     }
-
-    Ok(mints_amounts)
 }
@@ -1,19 +1,30 @@
 use anyhow::Result;
+use cdk::mint_url::MintUrl;
 use cdk::wallet::Wallet;
+use cdk::Amount;
 use clap::Args;
+use serde::Serialize;
+
+use crate::output::Output;
 
 #[derive(Args)]
 pub struct BurnSubCommand {
     /// Token Memo
     #[arg(short, long)]
-    mint_url: Option<String>,
+    mint_url: Option<MintUrl>,
+}
+
+#[derive(Serialize)]
+struct BurnOutput {
+    amount_burnt: Amount,
 }
 
-pub async fn burn(wallet: Wallet, sub_command_args: &BurnSubCommand) -> Result<()> {
+pub async fn burn(wallet: Wallet, sub_command_args: &BurnSubCommand, output: Output) -> Result<()> {
     let amount_burnt = wallet
-        .check_all_pending_proofs(sub_command_args.mint_url.clone().map(|u| u.into()), None)
+        .check_all_pending_proofs(sub_command_args.mint_url.clone(), None)
         .await?;
 
-    println!("{amount_burnt} burned");
-    Ok(())
+    output.result(&BurnOutput { amount_burnt }, |o| {
+        println!("{} burned", o.amount_burnt)
+    })
 }
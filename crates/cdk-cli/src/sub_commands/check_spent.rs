@@ -1,41 +1,68 @@
-use std::collections::HashMap;
 use std::io::Write;
 use std::{io, println};
 
 use anyhow::{bail, Result};
-use cdk::url::UncheckedUrl;
+use cdk::mint_url::MintUrl;
+use cdk::nuts::ProofState;
 use cdk::wallet::Wallet;
+use clap::Args;
+use serde::Serialize;
 
-pub async fn check_spent(wallet: Wallet) -> Result<()> {
-    let mints_amounts: Vec<(UncheckedUrl, HashMap<_, _>)> =
-        wallet.mint_balances().await?.into_iter().collect();
+use crate::output::Output;
+use crate::sub_commands::balance::mint_balances;
 
-    for (i, (mint, amount)) in mints_amounts.iter().enumerate() {
-        println!("{}: {}, {:?} sats", i, mint, amount);
-    }
+#[derive(Args)]
+pub struct CheckSpentSubCommand {
+    /// Mint to check proofs of, prompted for if not set
+    #[arg(short, long)]
+    mint_url: Option<MintUrl>,
+}
+
+#[derive(Serialize)]
+struct CheckSpentOutput {
+    mint_url: MintUrl,
+    states: Vec<ProofState>,
+}
 
-    println!("Enter mint number to create token");
+pub async fn check_spent(
+    wallet: Wallet,
+    sub_command_args: &CheckSpentSubCommand,
+    output: Output,
+) -> Result<()> {
+    let mint_url = match &sub_command_args.mint_url {
+        Some(mint_url) => mint_url.clone(),
+        None => {
+            let mints_amounts = mint_balances(&wallet, output).await?;
 
-    let mut user_input = String::new();
-    let stdin = io::stdin();
-    io::stdout().flush().unwrap();
-    stdin.read_line(&mut user_input)?;
+            output.info("Enter mint number to check proofs of");
 
-    let mint_number: usize = user_input.trim().parse()?;
+            let mut user_input = String::new();
+            let stdin = io::stdin();
+            io::stdout().flush().unwrap();
+            stdin.read_line(&mut user_input)?;
 
-    if mint_number.gt(&(mints_amounts.len() - 1)) {
-        bail!("Invalid mint number");
-    }
+            let mint_number: usize = user_input.trim().parse()?;
 
-    let mint_url = mints_amounts[mint_number].0.clone();
+            if mint_number.gt(&(mints_amounts.len() - 1)) {
+                bail!("Invalid mint number");
+            }
 
-    let proofs = wallet.get_proofs(mint_url.clone()).await?.unwrap();
+            mints_amounts[mint_number].0.clone()
+        }
+    };
 
-    let send_proofs = wallet.check_proofs_spent(mint_url, proofs.to_vec()).await?;
+    let proofs = wallet
+        .get_proofs(mint_url.clone())
+        .await?
+        .unwrap_or_default();
 
-    for proof in send_proofs {
-        println!("{:#?}", proof);
-    }
+    let states = wallet
+        .check_proofs_spent(mint_url.clone(), proofs.to_vec())
+        .await?;
 
-    Ok(())
+    output.result(&CheckSpentOutput { mint_url, states }, |o| {
+        for state in o.states.iter() {
+            println!("{:#?}", state);
+        }
+    })
 }
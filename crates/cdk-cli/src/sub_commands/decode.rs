@@ -0,0 +1,116 @@
+use anyhow::Result;
+use cdk::inspect::{
+    inspect, Bolt11Inspection, Inspection, PaymentRequestInspection, TokenInspection,
+};
+use cdk::nuts::SpendingConditions;
+use clap::Args;
+
+use crate::output::Output;
+
+#[derive(Args)]
+pub struct DecodeSubCommand {
+    /// Cashu token, bolt11 invoice or payment request
+    input: String,
+}
+
+pub fn decode(sub_command_args: &DecodeSubCommand, output: Output) -> Result<()> {
+    let inspection = inspect(&sub_command_args.input)?;
+
+    output.result(&inspection, |inspection| match inspection {
+        Inspection::Token(token) => print_token(token),
+        Inspection::Bolt11(invoice) => print_bolt11(invoice),
+        Inspection::PaymentRequest(request) => print_payment_request(request),
+    })
+}
+
+fn print_token(token: &TokenInspection) {
+    let unit = token.unit.clone().unwrap_or_default();
+
+    println!("Token: {} {}", token.amount, unit);
+    if let Some(memo) = &token.memo {
+        println!("Memo: {}", memo);
+    }
+
+    for mint in token.mints.iter() {
+        println!("Mint: {} ({} {})", mint.mint_url, mint.amount, unit);
+        for keyset in mint.keysets.iter() {
+            println!(
+                "  Keyset {}: {} {} in {} proofs",
+                keyset.id, keyset.amount, unit, keyset.proof_count
+            );
+        }
+        for proof in mint.proofs.iter() {
+            let conditions = match &proof.spending_conditions {
+                Some(SpendingConditions::P2PKConditions { data, .. }) => {
+                    format!("locked to {}", data)
+                }
+                Some(SpendingConditions::HTLCConditions { data, .. }) => {
+                    format!("HTLC with hash {}", data)
+                }
+                None => "unlocked".to_string(),
+            };
+
+            println!(
+                "  Proof {} ({}): {}, DLEQ {}",
+                proof.amount,
+                proof.keyset_id,
+                conditions,
+                match proof.has_dleq {
+                    true => "included",
+                    false => "missing",
+                }
+            );
+        }
+    }
+}
+
+fn print_bolt11(invoice: &Bolt11Inspection) {
+    match invoice.amount_msat {
+        Some(amount_msat) => println!("Invoice: {} msat", amount_msat),
+        None => println!("Invoice: any amount"),
+    }
+    if let Some(description) = &invoice.description {
+        println!("Description: {}", description);
+    }
+    println!("Payment hash: {}", invoice.payment_hash);
+    if let Some(payee) = &invoice.payee {
+        println!("Payee: {}", payee);
+    }
+    println!("Network: {}", invoice.network);
+    println!(
+        "Expires: {} (unix time)",
+        invoice.timestamp + invoice.expiry
+    );
+}
+
+fn print_payment_request(request: &PaymentRequestInspection) {
+    let unit = request.unit.clone().unwrap_or_default();
+
+    match request.amount {
+        Some(amount) => println!("Payment request: {} {}", amount, unit),
+        None => println!("Payment request: any amount"),
+    }
+    if let Some(description) = &request.description {
+        println!("Description: {}", description);
+    }
+    if let Some(payment_id) = &request.payment_id {
+        println!("Payment id: {}", payment_id);
+    }
+    if request.single_use {
+        println!("Single use");
+    }
+    match request.mints.is_empty() {
+        true => println!("Mints: any"),
+        false => {
+            for mint in request.mints.iter() {
+                println!("Mint: {}", mint);
+            }
+        }
+    }
+    for transport in request.transports.iter() {
+        println!(
+            "Transport {}: {}",
+            transport.transport_type, transport.target
+        );
+    }
+}
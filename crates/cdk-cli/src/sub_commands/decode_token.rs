@@ -4,15 +4,19 @@ use anyhow::Result;
 use cdk::nuts::Token;
 use clap::Args;
 
+use crate::output::Output;
+
 #[derive(Args)]
 pub struct DecodeTokenSubCommand {
     /// Cashu Token
     token: String,
 }
 
-pub fn decode_token(sub_command_args: &DecodeTokenSubCommand) -> Result<()> {
+pub fn decode_token(sub_command_args: &DecodeTokenSubCommand, output: Output) -> Result<()> {
     let token = Token::from_str(&sub_command_args.token)?;
 
-    println!("{:}", serde_json::to_string_pretty(&token)?);
-    Ok(())
+    output.result(&token, |token| match serde_json::to_string_pretty(token) {
+        Ok(token) => println!("{token}"),
+        Err(err) => eprintln!("Could not serialize token: {err}"),
+    })
 }
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use cdk::types::Transaction;
+use cdk::wallet::history::HistoryFormat;
+use cdk::wallet::Wallet;
+use clap::Args;
+use serde::Serialize;
+
+use crate::output::Output;
+
+#[derive(Args)]
+pub struct HistorySubCommand {
+    /// Export format, csv or json
+    #[arg(short, long)]
+    format: Option<HistoryFormat>,
+    /// Unix time of the first transaction
+    #[arg(long, default_value_t = 0)]
+    start: u64,
+    /// Unix time after the last transaction
+    #[arg(long, default_value_t = u64::MAX)]
+    end: u64,
+}
+
+#[derive(Serialize)]
+struct HistoryOutput {
+    transactions: Vec<Transaction>,
+}
+
+pub async fn history(
+    wallet: Wallet,
+    sub_command_args: &HistorySubCommand,
+    output: Output,
+) -> Result<()> {
+    if let Some(format) = sub_command_args.format {
+        let export = wallet
+            .export_history(format, sub_command_args.start, sub_command_args.end)
+            .await?;
+        print!("{}", export);
+        return Ok(());
+    }
+
+    let transactions = wallet
+        .transactions(sub_command_args.start, sub_command_args.end)
+        .await?;
+
+    output.result(&HistoryOutput { transactions }, |o| {
+        for t in &o.transactions {
+            println!(
+                "{} {} {} {} (fee {}) {}{}",
+                t.timestamp,
+                t.kind,
+                t.amount,
+                t.unit,
+                t.fee,
+                t.mint_url,
+                t.memo
+                    .as_ref()
+                    .map(|memo| format!(" \"{}\"", memo))
+                    .unwrap_or_default()
+            );
+        }
+    })
+}
@@ -4,52 +4,98 @@ use std::{io, println};
 
 use anyhow::{bail, Result};
 use cdk::amount::SplitTarget;
+use cdk::mint_url::MintUrl;
 use cdk::nuts::CurrencyUnit;
 use cdk::wallet::Wallet;
-use cdk::Bolt11Invoice;
+use cdk::{Amount, Bolt11Invoice};
 use clap::Args;
+use serde::Serialize;
 
+use crate::output::Output;
 use crate::sub_commands::balance::mint_balances;
 
 #[derive(Args)]
-pub struct MeltSubCommand {}
+pub struct MeltSubCommand {
+    /// Mint to melt from, prompted for if not set
+    #[arg(short, long)]
+    mint_url: Option<MintUrl>,
+    /// Bolt11 invoice to pay, prompted for if not set
+    #[arg(short, long)]
+    invoice: Option<Bolt11Invoice>,
+}
 
-pub async fn melt(wallet: Wallet, _sub_command_args: &MeltSubCommand) -> Result<()> {
-    let mints_amounts = mint_balances(&wallet).await?;
+#[derive(Serialize)]
+struct MeltOutput {
+    mint_url: MintUrl,
+    quote_id: String,
+    paid: bool,
+    preimage: Option<String>,
+    change_amount: Amount,
+}
 
-    println!("Enter mint number to create token");
+pub async fn melt(wallet: Wallet, sub_command_args: &MeltSubCommand, output: Output) -> Result<()> {
+    let mints_amounts = match sub_command_args.mint_url {
+        Some(_) => wallet.mint_balances().await?.into_iter().collect(),
+        None => mint_balances(&wallet, output).await?,
+    };
 
-    let mut user_input = String::new();
-    let stdin = io::stdin();
-    io::stdout().flush().unwrap();
-    stdin.read_line(&mut user_input)?;
+    let mint_number = match &sub_command_args.mint_url {
+        Some(mint_url) => match mints_amounts.iter().position(|(m, _)| m == mint_url) {
+            Some(mint_number) => mint_number,
+            None => bail!("No funds at mint {mint_url}"),
+        },
+        None => {
+            output.info("Enter mint number to melt from");
 
-    let mint_number: usize = user_input.trim().parse()?;
+            let mut user_input = String::new();
+            let stdin = io::stdin();
+            io::stdout().flush().unwrap();
+            stdin.read_line(&mut user_input)?;
 
-    if mint_number.gt(&(mints_amounts.len() - 1)) {
-        bail!("Invalid mint number");
-    }
+            let mint_number: usize = user_input.trim().parse()?;
+
+            if mint_number.gt(&(mints_amounts.len() - 1)) {
+                bail!("Invalid mint number");
+            }
+
+            mint_number
+        }
+    };
 
     let mint_url = mints_amounts[mint_number].0.clone();
 
-    println!("Enter bolt11 invoice request");
-
-    let mut user_input = String::new();
-    let stdin = io::stdin();
-    io::stdout().flush().unwrap();
-    stdin.read_line(&mut user_input)?;
-    let bolt11 = Bolt11Invoice::from_str(user_input.trim())?;
-
-    if bolt11
-        .amount_milli_satoshis()
-        .unwrap()
-        .gt(&(<cdk::Amount as Into<u64>>::into(
-            *mints_amounts[mint_number]
-                .1
-                .get(&CurrencyUnit::Sat)
-                .unwrap(),
-        ) * 1000_u64))
-    {
+    let bolt11 = match &sub_command_args.invoice {
+        Some(bolt11) => bolt11.clone(),
+        None => {
+            output.info("Enter bolt11 invoice request");
+
+            let mut user_input = String::new();
+            let stdin = io::stdin();
+            io::stdout().flush().unwrap();
+            stdin.read_line(&mut user_input)?;
+            Bolt11Invoice::from_str(user_input.trim())?
+        }
+    };
+
+    let available = mints_amounts[mint_number]
+        .1
+        .get(&CurrencyUnit::Sat)
+        .copied()
+        .unwrap_or_default()
+        .convert_unit(&CurrencyUnit::Sat, &CurrencyUnit::Msat)?;
+
+    let details = wallet.check_invoice(&bolt11.to_string())?;
+    let invoice_amount = details.amount_msat.unwrap_or_default();
+    output.info(format!(
+        "Paying {} msat for {}",
+        invoice_amount,
+        details
+            .description
+            .as_deref()
+            .unwrap_or("invoice without description")
+    ));
+
+    if invoice_amount > u64::from(available) {
         bail!("Not enough funds");
     }
     let quote = wallet
@@ -62,13 +108,23 @@ pub async fn melt(wallet: Wallet, _sub_command_args: &MeltSubCommand) -> Result<
 
     let melt = wallet
         .melt(&mint_url, &quote.id, SplitTarget::default())
-        .await
-        .unwrap();
+        .await?;
 
-    println!("Paid invoice: {}", melt.paid);
-    if let Some(preimage) = melt.preimage {
-        println!("Payment preimage: {}", preimage);
-    }
+    let melt_output = MeltOutput {
+        mint_url,
+        quote_id: quote.id,
+        paid: melt.paid,
+        preimage: melt.preimage,
+        change_amount: melt
+            .change
+            .map(|change| change.iter().map(|p| p.amount).sum())
+            .unwrap_or_default(),
+    };
 
-    Ok(())
+    output.result(&melt_output, |o| {
+        println!("Paid invoice: {}", o.paid);
+        if let Some(preimage) = &o.preimage {
+            println!("Payment preimage: {}", preimage);
+        }
+    })
 }
@@ -1,14 +1,21 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use cdk::amount::SplitTarget;
+use cdk::mint_url::MintUrl;
 use cdk::nuts::CurrencyUnit;
-use cdk::url::UncheckedUrl;
 use cdk::wallet::Wallet;
 use cdk::Amount;
 use clap::Args;
+use nostr_sdk::nips::nip47::NostrWalletConnectURI;
+use nostr_sdk::NWC;
+use serde::Serialize;
 use tokio::time::sleep;
 
+use crate::output::Output;
+
 #[derive(Args)]
 pub struct MintSubCommand {
     /// Amount
@@ -16,26 +23,129 @@ pub struct MintSubCommand {
     amount: u64,
     /// Currency unit e.g. sat
     #[arg(short, long)]
-    unit: String,
+    unit: CurrencyUnit,
     /// Mint url
     #[arg(short, long)]
-    mint_url: UncheckedUrl,
+    mint_url: MintUrl,
+    /// Description of the invoice
+    #[arg(short, long)]
+    description: Option<String>,
+    /// Pay the invoice with a CLN RPC socket path or a NWC uri
+    #[arg(long)]
+    pay_with: Option<PayWith>,
+}
+
+/// Lightning wallet paying the invoice of the quote
+#[derive(Debug, Clone)]
+pub enum PayWith {
+    /// Core Lightning RPC socket
+    Cln(PathBuf),
+    /// Nostr Wallet Connect uri
+    Nwc(NostrWalletConnectURI),
+}
+
+impl FromStr for PayWith {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.starts_with("nostr+walletconnect:") {
+            true => Ok(Self::Nwc(NostrWalletConnectURI::from_str(s)?)),
+            false => Ok(Self::Cln(PathBuf::from(s))),
+        }
+    }
+}
+
+impl PayWith {
+    /// Pay `invoice`, returns the preimage
+    async fn pay(&self, invoice: &str) -> Result<String> {
+        match self {
+            Self::Cln(socket) => cln_pay(socket, invoice).await,
+            Self::Nwc(uri) => {
+                let nwc = NWC::new(uri.clone()).await?;
+                let preimage = nwc.pay_invoice(invoice).await;
+                nwc.shutdown().await?;
+                Ok(preimage?)
+            }
+        }
+    }
+}
+
+/// Pay `invoice` with the `pay` command of the CLN JSON-RPC
+#[cfg(unix)]
+async fn cln_pay(socket: &Path, invoice: &str) -> Result<String> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::UnixStream;
+
+    let mut stream = UnixStream::connect(socket).await?;
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "pay",
+        "params": { "bolt11": invoice },
+    });
+    stream.write_all(&serde_json::to_vec(&request)?).await?;
+
+    // The socket stays open after the response, read until it is complete
+    let mut buf = Vec::new();
+    let response: serde_json::Value = loop {
+        let mut chunk = [0; 4096];
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            bail!("CLN closed the connection");
+        }
+        buf.extend_from_slice(&chunk[..read]);
+
+        if let Ok(response) = serde_json::from_slice(&buf) {
+            break response;
+        }
+    };
+
+    if let Some(error) = response.get("error") {
+        bail!("CLN pay failed: {}", error["message"]);
+    }
+
+    response["result"]["payment_preimage"]
+        .as_str()
+        .map(|preimage| preimage.to_string())
+        .ok_or_else(|| anyhow!("Unexpected CLN response: {}", response))
+}
+
+#[cfg(not(unix))]
+async fn cln_pay(_socket: &Path, _invoice: &str) -> Result<String> {
+    bail!("CLN RPC sockets are only supported on unix")
 }
 
-pub async fn mint(wallet: Wallet, sub_command_args: &MintSubCommand) -> Result<()> {
+#[derive(Serialize)]
+struct MintOutput {
+    mint_url: MintUrl,
+    quote_id: String,
+    amount: Amount,
+    unit: CurrencyUnit,
+}
+
+pub async fn mint(wallet: Wallet, sub_command_args: &MintSubCommand, output: Output) -> Result<()> {
     let mint_url = sub_command_args.mint_url.clone();
 
     let quote = wallet
         .mint_quote(
             mint_url.clone(),
             Amount::from(sub_command_args.amount),
-            CurrencyUnit::from(&sub_command_args.unit),
+            sub_command_args.unit.clone(),
+            sub_command_args.description.clone(),
         )
         .await?;
 
-    println!("Quote: {:#?}", quote);
+    // The quote is printed before waiting so the invoice can be paid
+    output.result(&quote, |quote| {
+        println!("Quote: {:#?}", quote);
+        println!("Please pay: {}", quote.request);
+    })?;
 
-    println!("Please pay: {}", quote.request);
+    if let Some(pay_with) = &sub_command_args.pay_with {
+        output.info("Paying invoice");
+        let preimage = pay_with.pay(&quote.request).await?;
+        output.info(format!("Paid invoice, preimage: {}", preimage));
+    }
 
     loop {
         let status = wallet
@@ -49,11 +159,18 @@ pub async fn mint(wallet: Wallet, sub_command_args: &MintSubCommand) -> Result<(
         sleep(Duration::from_secs(2)).await;
     }
 
-    let receive_amount = wallet
+    let amount = wallet
         .mint(mint_url.clone(), &quote.id, SplitTarget::default(), None)
         .await?;
 
-    println!("Received {receive_amount} from mint {mint_url}");
+    let mint_output = MintOutput {
+        mint_url,
+        quote_id: quote.id,
+        amount,
+        unit: quote.unit,
+    };
 
-    Ok(())
+    output.result(&mint_output, |o| {
+        println!("Received {} from mint {}", o.amount, o.mint_url)
+    })
 }
@@ -1,23 +1,24 @@
 use anyhow::Result;
-use cdk::url::UncheckedUrl;
+use cdk::mint_url::MintUrl;
+use cdk::wallet::client::KeysConnector;
 use cdk::HttpClient;
 use clap::Args;
 
+use crate::output::Output;
+
 #[derive(Args)]
 pub struct MintInfoSubcommand {
     /// Cashu Token
     #[arg(short, long)]
-    mint_url: UncheckedUrl,
+    mint_url: MintUrl,
 }
 
-pub async fn mint_info(sub_command_args: &MintInfoSubcommand) -> Result<()> {
+pub async fn mint_info(sub_command_args: &MintInfoSubcommand, output: Output) -> Result<()> {
     let client = HttpClient::default();
 
     let info = client
         .get_mint_info(sub_command_args.mint_url.clone().try_into()?)
         .await?;
 
-    println!("{:#?}", info);
-
-    Ok(())
+    output.result(&info, |info| println!("{:#?}", info))
 }
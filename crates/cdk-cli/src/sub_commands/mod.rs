@@ -1,10 +1,13 @@
 pub mod balance;
 pub mod burn;
 pub mod check_spent;
+pub mod decode;
 pub mod decode_token;
+pub mod history;
 pub mod melt;
 pub mod mint;
 pub mod mint_info;
+pub mod nwc;
 pub mod pending_mints;
 pub mod receive;
 pub mod restore;
@@ -0,0 +1,78 @@
+use anyhow::{bail, Result};
+use cdk::mint_url::MintUrl;
+use cdk::nuts::SecretKey;
+use cdk::wallet::nwc::NwcService;
+use cdk::wallet::Wallet;
+use clap::Args;
+use serde::Serialize;
+use url::Url;
+
+use crate::output::Output;
+
+#[derive(Args)]
+pub struct NwcSubCommand {
+    /// Mint to melt and mint against
+    #[arg(short, long)]
+    mint_url: MintUrl,
+    /// Nostr relay
+    #[arg(short, long, action = clap::ArgAction::Append)]
+    relay: Vec<Url>,
+    /// Wallet service key, a new key is generated if not set
+    #[arg(long)]
+    service_key: Option<SecretKey>,
+    /// Connection secret of the app, a new secret is generated if not set
+    #[arg(long)]
+    app_secret: Option<SecretKey>,
+}
+
+#[derive(Serialize)]
+struct NwcOutput {
+    service_key: String,
+    uri: String,
+}
+
+pub async fn nwc(wallet: Wallet, sub_command_args: &NwcSubCommand, output: Output) -> Result<()> {
+    if sub_command_args.relay.is_empty() {
+        bail!("At least one relay is required");
+    }
+
+    let service_key = match &sub_command_args.service_key {
+        Some(service_key) => service_key.clone(),
+        None => SecretKey::generate(),
+    };
+    let app_secret = match &sub_command_args.app_secret {
+        Some(app_secret) => app_secret.clone(),
+        None => SecretKey::generate(),
+    };
+
+    wallet
+        .add_nostr_relays(
+            sub_command_args
+                .relay
+                .iter()
+                .map(|r| r.to_string())
+                .collect(),
+        )
+        .await?;
+
+    let service = NwcService::new(
+        wallet,
+        sub_command_args.mint_url.clone(),
+        &service_key,
+        &app_secret,
+    )?;
+
+    let nwc_output = NwcOutput {
+        service_key: service_key.to_secret_hex(),
+        uri: service.uri(sub_command_args.relay[0].clone())?.to_string(),
+    };
+
+    output.result(&nwc_output, |o| {
+        println!("Service key: {}", o.service_key);
+        println!("{}", o.uri);
+    })?;
+
+    service.run().await?;
+
+    Ok(())
+}
@@ -1,9 +1,19 @@
 use anyhow::Result;
 use cdk::wallet::Wallet;
+use cdk::Amount;
+use serde::Serialize;
 
-pub async fn pending_mints(wallet: Wallet) -> Result<()> {
-    let amount_claimed = wallet.check_all_mint_quotes().await?;
+use crate::output::Output;
 
-    println!("Amount minted: {amount_claimed}");
-    Ok(())
+#[derive(Serialize)]
+struct PendingMintsOutput {
+    amount_minted: Amount,
+}
+
+pub async fn pending_mints(wallet: Wallet, output: Output) -> Result<()> {
+    let amount_minted = wallet.check_all_mint_quotes().await?;
+
+    output.result(&PendingMintsOutput { amount_minted }, |o| {
+        println!("Amount minted: {}", o.amount_minted)
+    })
 }
@@ -3,8 +3,13 @@ use std::str::FromStr;
 use anyhow::{anyhow, Result};
 use cdk::amount::SplitTarget;
 use cdk::nuts::SecretKey;
+use cdk::wallet::nostr::nostr_signing_key;
 use cdk::wallet::Wallet;
+use cdk::Amount;
 use clap::Args;
+use serde::Serialize;
+
+use crate::output::Output;
 
 #[derive(Args)]
 pub struct ReceiveSubCommand {
@@ -13,7 +18,7 @@ pub struct ReceiveSubCommand {
     /// Signing Key
     #[arg(short, long, action = clap::ArgAction::Append)]
     signing_key: Vec<String>,
-    /// Nostr key
+    /// Nostr key (nsec or hex), tokens locked to its pubkey are signed with it
     #[arg(short, long)]
     nostr_key: Option<String>,
     /// Nostr relay
@@ -27,10 +32,28 @@ pub struct ReceiveSubCommand {
     preimage: Vec<String>,
 }
 
-pub async fn receive(wallet: Wallet, sub_command_args: &ReceiveSubCommand) -> Result<()> {
+#[derive(Serialize)]
+struct ReceiveOutput {
+    amount: Amount,
+    /// Tokens received over nostr
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    nostr: Vec<NostrReceived>,
+}
+
+#[derive(Serialize)]
+struct NostrReceived {
+    amount: Amount,
+    sender: String,
+}
+
+pub async fn receive(
+    wallet: Wallet,
+    sub_command_args: &ReceiveSubCommand,
+    output: Output,
+) -> Result<()> {
     let nostr_key = match sub_command_args.nostr_key.as_ref() {
         Some(nostr_key) => {
-            let secret_key = SecretKey::from_str(nostr_key)?;
+            let secret_key = nostr_signing_key(nostr_key)?;
             wallet.add_p2pk_signing_key(secret_key.clone()).await;
             Some(secret_key)
         }
@@ -54,15 +77,26 @@ pub async fn receive(wallet: Wallet, sub_command_args: &ReceiveSubCommand) -> Re
         false => Some(sub_command_args.preimage.clone()),
     };
 
+    let mut nostr = Vec::new();
     let amount = match nostr_key {
         Some(nostr_key) => {
             assert!(!sub_command_args.relay.is_empty());
             wallet
                 .add_nostr_relays(sub_command_args.relay.clone())
                 .await?;
-            wallet
+            let received = wallet
                 .nostr_receive(nostr_key, sub_command_args.since, SplitTarget::default())
-                .await?
+                .await?;
+
+            nostr = received
+                .iter()
+                .map(|r| NostrReceived {
+                    amount: r.amount,
+                    sender: r.sender.to_string(),
+                })
+                .collect();
+
+            received.iter().map(|r| r.amount).sum()
         }
         None => {
             wallet
@@ -78,7 +112,10 @@ pub async fn receive(wallet: Wallet, sub_command_args: &ReceiveSubCommand) -> Re
         }
     };
 
-    println!("Received: {}", amount);
-
-    Ok(())
+    output.result(&ReceiveOutput { amount, nostr }, |o| {
+        for received in o.nostr.iter() {
+            println!("Received {} from {}", received.amount, received.sender);
+        }
+        println!("Received: {}", o.amount);
+    })
 }
@@ -1,21 +1,35 @@
 use anyhow::Result;
-use cdk::url::UncheckedUrl;
+use cdk::mint_url::MintUrl;
 use cdk::wallet::Wallet;
+use cdk::Amount;
 use clap::Args;
+use serde::Serialize;
+
+use crate::output::Output;
 
 #[derive(Args)]
 pub struct RestoreSubCommand {
     /// Mint Url
     #[arg(short, long)]
-    mint_url: UncheckedUrl,
+    mint_url: MintUrl,
 }
 
-pub async fn restore(wallet: Wallet, sub_command_args: &RestoreSubCommand) -> Result<()> {
-    let mint_url = sub_command_args.mint_url.clone();
+#[derive(Serialize)]
+struct RestoreOutput {
+    mint_url: MintUrl,
+    amount: Amount,
+}
 
-    let amount = wallet.restore(mint_url).await?;
+pub async fn restore(
+    wallet: Wallet,
+    sub_command_args: &RestoreSubCommand,
+    output: Output,
+) -> Result<()> {
+    let mint_url = sub_command_args.mint_url.clone();
 
-    println!("Restored {}", amount);
+    let amount = wallet.restore(mint_url.clone()).await?;
 
-    Ok(())
+    output.result(&RestoreOutput { mint_url, amount }, |o| {
+        println!("Restored {}", o.amount)
+    })
 }
@@ -1,21 +1,35 @@
+use std::collections::BTreeMap;
 use std::io::Write;
 use std::str::FromStr;
 use std::{io, println};
 
 use anyhow::{bail, Result};
 use cdk::amount::SplitTarget;
-use cdk::nuts::{Conditions, CurrencyUnit, PublicKey, SpendingConditions};
+use cdk::mint_url::MintUrl;
+use cdk::nuts::{Conditions, CurrencyUnit, PublicKey, SecretKey, SpendingConditions};
+use cdk::wallet::nostr::{nostr_p2pk_conditions, NostrDirectMessage};
 use cdk::wallet::Wallet;
 use cdk::Amount;
 use clap::Args;
+use serde::Serialize;
 
+use crate::output::Output;
 use crate::sub_commands::balance::mint_balances;
 
 #[derive(Args)]
 pub struct SendSubCommand {
+    /// Mint to send from, prompted for if not set
+    #[arg(long)]
+    mint_url: Option<MintUrl>,
+    /// Value of token in sats, prompted for if not set
+    #[arg(short, long)]
+    amount: Option<u64>,
     /// Token Memo
     #[arg(short, long)]
     memo: Option<String>,
+    /// Metadata `key=value` to attach to the token, can be repeated
+    #[arg(long, action = clap::ArgAction::Append)]
+    metadata: Vec<String>,
     /// Preimage
     #[arg(long)]
     preimage: Option<String>,
@@ -31,44 +45,102 @@ pub struct SendSubCommand {
     /// Publey to lock proofs to
     #[arg(long, action = clap::ArgAction::Append)]
     refund_keys: Vec<String>,
+    /// Nostr pubkey (npub or hex) to send the token to
+    #[arg(long)]
+    nostr_pubkey: Option<String>,
+    /// Lock the token to the nostr pubkey it is sent to
+    #[arg(long, requires = "nostr_pubkey", conflicts_with_all = ["preimage", "pubkey"])]
+    lock: bool,
+    /// Nostr key to send the token from, a new key is generated if not set
+    #[arg(long)]
+    nostr_key: Option<String>,
+    /// Nostr relay
+    #[arg(short, long, action = clap::ArgAction::Append)]
+    relay: Vec<String>,
+    /// Send token as a NIP-04 direct message instead of NIP-17
+    #[arg(long)]
+    nip04: bool,
+}
+
+#[derive(Serialize)]
+struct SendOutput {
+    mint_url: MintUrl,
+    amount: Amount,
+    token: String,
+    /// Nostr event the token was sent in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nostr_event_id: Option<String>,
 }
 
-pub async fn send(wallet: Wallet, sub_command_args: &SendSubCommand) -> Result<()> {
-    let mints_amounts = mint_balances(&wallet).await?;
+pub async fn send(wallet: Wallet, sub_command_args: &SendSubCommand, output: Output) -> Result<()> {
+    let mints_amounts = match sub_command_args.mint_url {
+        Some(_) => wallet.mint_balances().await?.into_iter().collect(),
+        None => mint_balances(&wallet, output).await?,
+    };
 
-    println!("Enter mint number to create token");
+    let mint_number = match &sub_command_args.mint_url {
+        Some(mint_url) => match mints_amounts.iter().position(|(m, _)| m == mint_url) {
+            Some(mint_number) => mint_number,
+            None => bail!("No funds at mint {mint_url}"),
+        },
+        None => {
+            output.info("Enter mint number to create token");
 
-    let mut user_input = String::new();
-    let stdin = io::stdin();
-    io::stdout().flush().unwrap();
-    stdin.read_line(&mut user_input)?;
+            let mut user_input = String::new();
+            let stdin = io::stdin();
+            io::stdout().flush().unwrap();
+            stdin.read_line(&mut user_input)?;
 
-    let mint_number: usize = user_input.trim().parse()?;
+            let mint_number: usize = user_input.trim().parse()?;
 
-    if mint_number.gt(&(mints_amounts.len() - 1)) {
-        bail!("Invalid mint number");
-    }
+            if mint_number.gt(&(mints_amounts.len() - 1)) {
+                bail!("Invalid mint number");
+            }
+
+            mint_number
+        }
+    };
 
     let mint_url = mints_amounts[mint_number].0.clone();
 
-    println!("Enter value of token in sats");
+    let token_amount = match sub_command_args.amount {
+        Some(amount) => Amount::from(amount),
+        None => {
+            output.info("Enter value of token in sats");
 
-    let mut user_input = String::new();
-    let stdin = io::stdin();
-    io::stdout().flush().unwrap();
-    stdin.read_line(&mut user_input)?;
-    let token_amount = Amount::from(user_input.trim().parse::<u64>()?);
+            let mut user_input = String::new();
+            let stdin = io::stdin();
+            io::stdout().flush().unwrap();
+            stdin.read_line(&mut user_input)?;
+            Amount::from(user_input.trim().parse::<u64>()?)
+        }
+    };
 
-    if token_amount.gt(mints_amounts[mint_number]
+    let available = mints_amounts[mint_number]
         .1
         .get(&CurrencyUnit::Sat)
-        .unwrap())
-    {
+        .copied()
+        .unwrap_or_default();
+
+    if token_amount.gt(&available) {
         bail!("Not enough funds");
     }
 
-    let conditions = match &sub_command_args.preimage {
-        Some(preimage) => {
+    let conditions = match (&sub_command_args.preimage, &sub_command_args.nostr_pubkey) {
+        (_, Some(nostr_pubkey)) if sub_command_args.lock => {
+            let refund_keys = sub_command_args
+                .refund_keys
+                .iter()
+                .map(|p| PublicKey::from_str(p))
+                .collect::<Result<Vec<PublicKey>, _>>()?;
+            let refund_keys = (!refund_keys.is_empty()).then_some(refund_keys);
+
+            let conditions =
+                Conditions::new(sub_command_args.locktime, None, refund_keys, None, None)?;
+
+            Some(nostr_p2pk_conditions(nostr_pubkey, conditions)?)
+        }
+        (Some(preimage), _) => {
             let pubkeys = match sub_command_args.pubkey.is_empty() {
                 true => None,
                 false => Some(
@@ -102,7 +174,7 @@ pub async fn send(wallet: Wallet, sub_command_args: &SendSubCommand) -> Result<(
 
             Some(SpendingConditions::new_htlc(preimage.clone(), conditions)?)
         }
-        None => match sub_command_args.pubkey.is_empty() {
+        (None, _) => match sub_command_args.pubkey.is_empty() {
             true => None,
             false => {
                 let pubkeys: Vec<PublicKey> = sub_command_args
@@ -142,18 +214,63 @@ pub async fn send(wallet: Wallet, sub_command_args: &SendSubCommand) -> Result<(
         },
     };
 
+    let mut metadata = BTreeMap::new();
+    for entry in &sub_command_args.metadata {
+        match entry.split_once('=') {
+            Some((key, value)) => metadata.insert(key.to_string(), value.to_string()),
+            None => bail!("Metadata must be given as key=value: {}", entry),
+        };
+    }
+
     let token = wallet
-        .send(
+        .send_with_metadata(
             &mint_url,
             CurrencyUnit::Sat,
             sub_command_args.memo.clone(),
+            metadata,
             token_amount,
             &SplitTarget::default(),
             conditions,
         )
         .await?;
 
-    println!("{}", token);
+    let nostr_event_id = match &sub_command_args.nostr_pubkey {
+        Some(nostr_pubkey) => {
+            if sub_command_args.relay.is_empty() {
+                bail!("At least one relay is required to send over nostr");
+            }
+
+            let nostr_key = match &sub_command_args.nostr_key {
+                Some(nostr_key) => SecretKey::from_str(nostr_key)?,
+                None => SecretKey::generate(),
+            };
+
+            let protocol = match sub_command_args.nip04 {
+                true => NostrDirectMessage::Nip04,
+                false => NostrDirectMessage::Nip17,
+            };
+
+            wallet
+                .add_nostr_relays(sub_command_args.relay.clone())
+                .await?;
+            let event_id = wallet
+                .nostr_send(&nostr_key, nostr_pubkey, &token, protocol)
+                .await?;
+
+            Some(event_id.to_string())
+        }
+        None => None,
+    };
+
+    let send_output = SendOutput {
+        mint_url,
+        amount: token_amount,
+        token,
+        nostr_event_id,
+    };
 
-    Ok(())
+    output.result(&send_output, |o| match &o.nostr_event_id {
+        Some(event_id) => println!("Sent token in nostr event {}", event_id),
+        None => println!("{}", o.token),
+    })
 }
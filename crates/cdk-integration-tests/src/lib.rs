@@ -0,0 +1,1757 @@
+//! In-process mint for wallet integration tests
+//!
+//! [`DirectMintConnection`] wires a [`Wallet`] directly to a [`Mint`] backed
+//! by memory storage, so wallet flows can be tested end to end without running
+//! a mint server. Lightning is faked: invoices of mint quotes are paid as soon
+//! as they are created and every melt succeeds.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use bitcoin::hashes::Hash;
+use cdk::amount::SplitTarget;
+use cdk::cdk_database::mint_memory::MintMemoryDatabase;
+use cdk::cdk_database::WalletMemoryDatabase;
+use cdk::error::ErrorResponse;
+use cdk::exchange_rate::{sat_to_unit, unit_to_sat};
+use cdk::lightning_invoice::{Bolt11InvoiceDescription, Currency, InvoiceBuilder, PaymentSecret};
+use cdk::mint::Mint;
+use cdk::mint_url::MintUrl;
+use cdk::nuts::{
+    BlindedMessage, CheckStateRequest, CheckStateResponse, CurrencyUnit, Id, KeySet,
+    KeysetResponse, MeltBolt11Request, MeltBolt11Response, MeltQuoteBolt11Response,
+    MintBolt11Request, MintBolt11Response, MintInfo, MintQuoteBolt11Response,
+    PaymentRequestPayload, PreMintSecrets, Proof, PublicKey, RestoreRequest, RestoreResponse,
+    SecretKey, SwapRequest, SwapResponse,
+};
+use cdk::util::unix_time;
+use cdk::wallet::client::{
+    CheckStateConnector, IssueConnector, KeysConnector, MeltConnector, PaymentRequestConnector,
+    QuoteConnector, RestoreConnector, SwapConnector,
+};
+use cdk::wallet::error::Error;
+use cdk::wallet::Wallet;
+use cdk::{Amount, Bolt11Invoice, HttpClient, Sha256, SECP256K1};
+use url::Url;
+
+/// Mint url wallets connected with [`DirectMintConnection`] use
+pub const MINT_URL: &str = "http://127.0.0.1:3338";
+
+/// Seconds quotes are valid for
+const QUOTE_TTL: u64 = 3600;
+
+/// Create a [`Mint`] with memory storage and a random seed
+pub async fn create_mint() -> Result<Mint, cdk::mint::error::Error> {
+    let localstore = MintMemoryDatabase::new(
+        HashMap::new(),
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        HashMap::new(),
+    )?;
+
+    Mint::new(
+        SecretKey::generate().as_secret_bytes(),
+        MintInfo::default(),
+        Arc::new(localstore),
+        Amount::ZERO,
+        0.0,
+    )
+    .await
+}
+
+/// Create a [`Wallet`] with memory storage connected to `mint`
+pub fn create_wallet(mint: Mint) -> Wallet {
+    let localstore = WalletMemoryDatabase::default();
+
+    let mut wallet = Wallet::new(
+        Arc::new(localstore),
+        SecretKey::generate().as_secret_bytes(),
+        vec![],
+    );
+    wallet.client = Arc::new(DirectMintConnection::new(mint));
+
+    wallet
+}
+
+/// Mint `amount` sat to `wallet` from the mint at [`MINT_URL`]
+pub async fn fund_wallet(wallet: &Wallet, amount: Amount) -> Result<Amount, Error> {
+    let mint_url = MintUrl::from_str(MINT_URL)?;
+    let quote = wallet
+        .mint_quote(mint_url.clone(), amount, CurrencyUnit::Sat, None)
+        .await?;
+
+    wallet
+        .mint(mint_url, &quote.id, SplitTarget::default(), None)
+        .await
+}
+
+/// Invoices with this description fail to be paid
+pub const FAILING_INVOICE: &str = "fail";
+
+/// Payments of invoices with this description stay in flight until
+/// [`DirectMintConnection::fail_payments_in_flight`] is called
+pub const PENDING_INVOICE: &str = "pending";
+
+/// [`Client`] calling a [`Mint`] in the same process
+///
+/// External invoices are paid by a fake Lightning backend without fees,
+/// see [`FAILING_INVOICE`] and [`PENDING_INVOICE`] to simulate failures.
+#[derive(Clone)]
+pub struct DirectMintConnection {
+    mint: Mint,
+    in_flight: Arc<Mutex<Vec<MeltBolt11Request>>>,
+}
+
+impl DirectMintConnection {
+    /// Create new [`DirectMintConnection`]
+    pub fn new(mint: Mint) -> Self {
+        Self {
+            mint,
+            in_flight: Arc::default(),
+        }
+    }
+
+    /// Fail all payments in flight, releasing the melt inputs
+    pub async fn fail_payments_in_flight(&self) -> Result<(), cdk::mint::error::Error> {
+        let in_flight: Vec<MeltBolt11Request> = self
+            .in_flight
+            .lock()
+            .expect("Lock poisoned")
+            .drain(..)
+            .collect();
+
+        for request in in_flight {
+            self.mint
+                .process_unpaid_melt(&request, "Payment failed")
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Create a signed invoice for `amount` sats
+    fn create_invoice(amount: Amount, description: String) -> Result<Bolt11Invoice, Error> {
+        let node_key = SecretKey::generate();
+        let preimage = SecretKey::generate();
+        let payment_hash = Sha256::hash(preimage.as_secret_bytes());
+
+        InvoiceBuilder::new(Currency::Regtest)
+            .description(description)
+            .payment_hash(payment_hash)
+            .payment_secret(PaymentSecret([0; 32]))
+            .current_timestamp()
+            .min_final_cltv_expiry_delta(144)
+            .amount_milli_satoshis(u64::from(amount) * 1000)
+            .build_signed(|hash| SECP256K1.sign_ecdsa_recoverable(hash, &node_key))
+            .map_err(|err| Error::Custom(err.to_string()))
+    }
+}
+
+/// Map a mint error to the error a wallet gets from the HTTP api
+fn into_wallet_error(err: cdk::mint::error::Error) -> Error {
+    ErrorResponse::from(err).into()
+}
+
+#[async_trait]
+impl KeysConnector for DirectMintConnection {
+    async fn get_mint_keys(&self, _mint_url: Url) -> Result<Vec<KeySet>, Error> {
+        let keys = self.mint.pubkeys().await.map_err(into_wallet_error)?;
+        Ok(keys.keysets)
+    }
+
+    async fn get_mint_keyset(&self, _mint_url: Url, keyset_id: Id) -> Result<KeySet, Error> {
+        self.mint
+            .keyset(&keyset_id)
+            .await
+            .map_err(into_wallet_error)?
+            .ok_or(Error::KeysetNotFound)
+    }
+
+    async fn get_mint_keysets(&self, _mint_url: Url) -> Result<KeysetResponse, Error> {
+        self.mint.keysets().await.map_err(into_wallet_error)
+    }
+
+    async fn get_mint_info(&self, _mint_url: Url) -> Result<MintInfo, Error> {
+        self.mint.mint_info().await.map_err(into_wallet_error)
+    }
+}
+
+#[async_trait]
+impl QuoteConnector for DirectMintConnection {
+    async fn post_mint_quote(
+        &self,
+        mint_url: Url,
+        amount: Amount,
+        unit: CurrencyUnit,
+        description: Option<String>,
+    ) -> Result<MintQuoteBolt11Response, Error> {
+        let description = self
+            .mint
+            .invoice_description(&unit, description)
+            .await
+            .map_err(into_wallet_error)?;
+        let exchange_rate = self
+            .mint
+            .lock_exchange_rate(&unit)
+            .await
+            .map_err(into_wallet_error)?;
+        let invoice_amount = match exchange_rate {
+            Some(per_btc) => {
+                unit_to_sat(amount, per_btc).map_err(|err| Error::Custom(err.to_string()))?
+            }
+            None => amount,
+        };
+        let invoice = Self::create_invoice(invoice_amount, description.unwrap_or_default())?;
+
+        let mut quote = self
+            .mint
+            .new_mint_quote(
+                MintUrl::from_str(mint_url.as_str())?,
+                invoice.to_string(),
+                unit,
+                amount,
+                unix_time() + QUOTE_TTL,
+                exchange_rate,
+            )
+            .await
+            .map_err(into_wallet_error)?;
+
+        // The fake lightning backend is paid immediately
+        quote.paid = true;
+        self.mint
+            .update_mint_quote(quote.clone())
+            .await
+            .map_err(into_wallet_error)?;
+
+        Ok(quote.into())
+    }
+
+    async fn get_mint_quote_status(
+        &self,
+        _mint_url: Url,
+        quote_id: &str,
+    ) -> Result<MintQuoteBolt11Response, Error> {
+        self.mint
+            .check_mint_quote(quote_id)
+            .await
+            .map_err(into_wallet_error)
+    }
+
+    async fn post_melt_quote(
+        &self,
+        _mint_url: Url,
+        unit: CurrencyUnit,
+        request: Bolt11Invoice,
+    ) -> Result<MeltQuoteBolt11Response, Error> {
+        let amount = request
+            .amount_milli_satoshis()
+            .map(|msat| Amount::from(msat / 1000))
+            .ok_or(Error::Custom("Amountless invoice".to_string()))?;
+
+        let exchange_rate = self
+            .mint
+            .lock_exchange_rate(&unit)
+            .await
+            .map_err(into_wallet_error)?;
+        let fee_unit = match exchange_rate {
+            Some(_) => CurrencyUnit::Sat,
+            None => unit.clone(),
+        };
+
+        let fee_reserve = self
+            .mint
+            .melt_fee_reserve(&request, amount, &fee_unit)
+            .await
+            .map_err(into_wallet_error)?;
+
+        let (amount, fee_reserve) = match exchange_rate {
+            Some(per_btc) => (
+                sat_to_unit(amount, per_btc).map_err(|err| Error::Custom(err.to_string()))?,
+                sat_to_unit(fee_reserve, per_btc).map_err(|err| Error::Custom(err.to_string()))?,
+            ),
+            None => (amount, fee_reserve),
+        };
+
+        let quote = self
+            .mint
+            .new_melt_quote(
+                request.to_string(),
+                unit,
+                amount,
+                fee_reserve,
+                unix_time() + QUOTE_TTL,
+                exchange_rate,
+            )
+            .await
+            .map_err(into_wallet_error)?;
+
+        Ok(quote.into())
+    }
+
+    async fn get_melt_quote_status(
+        &self,
+        _mint_url: Url,
+        quote_id: &str,
+    ) -> Result<MeltQuoteBolt11Response, Error> {
+        self.mint
+            .check_melt_quote(quote_id)
+            .await
+            .map_err(into_wallet_error)
+    }
+}
+
+#[async_trait]
+impl IssueConnector for DirectMintConnection {
+    async fn post_mint(
+        &self,
+        _mint_url: Url,
+        quote: &str,
+        premint_secrets: PreMintSecrets,
+    ) -> Result<MintBolt11Response, Error> {
+        let request = MintBolt11Request {
+            quote: quote.to_string(),
+            outputs: premint_secrets.blinded_messages(),
+        };
+
+        self.mint
+            .process_mint_request(request)
+            .await
+            .map_err(into_wallet_error)
+    }
+}
+
+#[async_trait]
+impl MeltConnector for DirectMintConnection {
+    async fn post_melt(
+        &self,
+        _mint_url: Url,
+        quote: String,
+        inputs: Vec<Proof>,
+        outputs: Option<Vec<BlindedMessage>>,
+    ) -> Result<MeltBolt11Response, Error> {
+        let request = MeltBolt11Request {
+            quote,
+            inputs,
+            outputs,
+        };
+
+        if let Some(response) = self
+            .mint
+            .completed_melt_response(&request)
+            .await
+            .map_err(into_wallet_error)?
+        {
+            return Ok(response);
+        }
+
+        let melt_quote = self
+            .mint
+            .set_melt_quote_pending(&request)
+            .await
+            .map_err(into_wallet_error)?;
+
+        let (preimage, total_spent) = match self.mint.handle_internal_melt_mint(&melt_quote).await {
+            Ok(Some(amount)) => (None, amount),
+            Ok(None) => {
+                let invoice = Bolt11Invoice::from_str(&melt_quote.request)?;
+                let description = match invoice.description() {
+                    Bolt11InvoiceDescription::Direct(description) => description.to_string(),
+                    Bolt11InvoiceDescription::Hash(_) => String::new(),
+                };
+
+                match description.as_str() {
+                    FAILING_INVOICE => {
+                        self.mint
+                            .process_unpaid_melt(&request, "Payment failed")
+                            .await
+                            .map_err(into_wallet_error)?;
+                        return Err(Error::Custom("Payment failed".to_string()));
+                    }
+                    PENDING_INVOICE => {
+                        self.in_flight.lock().expect("Lock poisoned").push(request);
+                        return Err(Error::Custom("Payment pending".to_string()));
+                    }
+                    _ => (
+                        Some(SecretKey::generate().to_secret_hex()),
+                        melt_quote.amount,
+                    ),
+                }
+            }
+            Err(err) => {
+                self.mint
+                    .process_unpaid_melt(&request, &err.to_string())
+                    .await
+                    .map_err(into_wallet_error)?;
+                return Err(into_wallet_error(err));
+            }
+        };
+
+        self.mint
+            .process_melt_request(&request, preimage.as_deref(), total_spent)
+            .await
+            .map_err(into_wallet_error)
+    }
+}
+
+#[async_trait]
+impl SwapConnector for DirectMintConnection {
+    async fn post_swap(
+        &self,
+        _mint_url: Url,
+        swap_request: SwapRequest,
+    ) -> Result<SwapResponse, Error> {
+        self.mint
+            .process_swap_request(swap_request)
+            .await
+            .map_err(into_wallet_error)
+    }
+}
+
+#[async_trait]
+impl CheckStateConnector for DirectMintConnection {
+    async fn post_check_state(
+        &self,
+        _mint_url: Url,
+        ys: Vec<PublicKey>,
+    ) -> Result<CheckStateResponse, Error> {
+        self.mint
+            .check_state(&CheckStateRequest { ys })
+            .await
+            .map_err(into_wallet_error)
+    }
+}
+
+#[async_trait]
+impl RestoreConnector for DirectMintConnection {
+    async fn post_restore(
+        &self,
+        _mint_url: Url,
+        request: RestoreRequest,
+    ) -> Result<RestoreResponse, Error> {
+        self.mint.restore(request).await.map_err(into_wallet_error)
+    }
+}
+
+/// Payments to payment requests are still delivered over HTTP
+#[async_trait]
+impl PaymentRequestConnector for DirectMintConnection {
+    async fn post_payment_request(
+        &self,
+        url: Url,
+        payload: PaymentRequestPayload,
+    ) -> Result<(), Error> {
+        HttpClient::new().post_payment_request(url, payload).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    use bitcoin::bip32::{ChildNumber, DerivationPath};
+    use cdk::amount::SplitTarget;
+    use cdk::nuts::nut00::METADATA_SENDER;
+    use cdk::nuts::{
+        Conditions, MeltQuoteState, PaymentRequest, SpendingConditions, State, Token, Transport,
+        TransportType,
+    };
+    use cdk::types::{PendingMint, TransactionKind};
+    use cdk::wallet::events::WalletEvent;
+    use cdk::wallet::history::HistoryFormat;
+    use cdk::wallet::payment_request;
+    use cdk::wallet::policy::{self, SpendPolicy};
+    use cdk::wallet::privacy::PrivacyMode;
+    use futures::StreamExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mint_send_receive_melt() {
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+
+        let alice = create_wallet(mint.clone());
+        let bob = create_wallet(mint.clone());
+
+        let minted = fund_wallet(&alice, Amount::from(100)).await.unwrap();
+        assert_eq!(minted, Amount::from(100));
+
+        let token = alice
+            .send(
+                &mint_url,
+                CurrencyUnit::Sat,
+                None,
+                Amount::from(30),
+                &SplitTarget::default(),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            alice.unit_balance(CurrencyUnit::Sat).await.unwrap(),
+            Amount::from(70)
+        );
+
+        let received = bob
+            .receive(&token, &SplitTarget::default(), None)
+            .await
+            .unwrap();
+        assert_eq!(received, Amount::from(30));
+
+        // The token cannot be claimed twice
+        assert!(alice
+            .receive(&token, &SplitTarget::default(), None)
+            .await
+            .is_err());
+
+        let invoice =
+            DirectMintConnection::create_invoice(Amount::from(20), String::new()).unwrap();
+        let quote = bob
+            .melt_quote(mint_url.clone(), CurrencyUnit::Sat, invoice.to_string())
+            .await
+            .unwrap();
+        let melted = bob
+            .melt(&mint_url, &quote.id, SplitTarget::default())
+            .await
+            .unwrap();
+        assert!(melted.paid);
+        assert_eq!(
+            bob.unit_balance(CurrencyUnit::Sat).await.unwrap(),
+            Amount::from(10)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_spend_policy() {
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+
+        let mut wallet = create_wallet(mint);
+        wallet.spend_policy = Some(Arc::new(SpendPolicy::new().daily_limit(Amount::from(40))));
+
+        fund_wallet(&wallet, Amount::from(100)).await.unwrap();
+
+        let send = |amount: u64| {
+            let wallet = wallet.clone();
+            let mint_url = mint_url.clone();
+            async move {
+                wallet
+                    .send(
+                        &mint_url,
+                        CurrencyUnit::Sat,
+                        None,
+                        Amount::from(amount),
+                        &SplitTarget::default(),
+                        None,
+                    )
+                    .await
+            }
+        };
+
+        send(30).await.unwrap();
+        assert!(matches!(
+            send(20).await,
+            Err(Error::SpendPolicy(policy::Error::DailyLimitExceeded(_, _)))
+        ));
+        send(10).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_receive_token_twice() {
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+        let alice = create_wallet(mint.clone());
+        let bob = create_wallet(mint);
+
+        fund_wallet(&alice, Amount::from(64)).await.unwrap();
+
+        let token = alice
+            .send(
+                &mint_url,
+                CurrencyUnit::Sat,
+                None,
+                Amount::from(10),
+                &SplitTarget::default(),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let received = bob
+            .receive(&token, &SplitTarget::default(), None)
+            .await
+            .unwrap();
+        assert_eq!(received, Amount::from(10));
+
+        assert!(matches!(
+            bob.receive(&token, &SplitTarget::default(), None).await,
+            Err(Error::AlreadyReceived)
+        ));
+        assert_eq!(
+            bob.total_balance().await.unwrap()[&CurrencyUnit::Sat],
+            Amount::from(10)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_melt_keeps_proofs() {
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+        let wallet = create_wallet(mint.clone());
+
+        fund_wallet(&wallet, Amount::from(64)).await.unwrap();
+
+        // Make the mint reject the melt request
+        let config = mint.config().await;
+        let mut strict_config = config.clone();
+        strict_config.limits.max_inputs = 0;
+        mint.reload_config(strict_config).await;
+
+        let invoice =
+            DirectMintConnection::create_invoice(Amount::from(20), String::new()).unwrap();
+        let quote = wallet
+            .melt_quote(mint_url.clone(), CurrencyUnit::Sat, invoice.to_string())
+            .await
+            .unwrap();
+        assert!(wallet
+            .melt(&mint_url, &quote.id, SplitTarget::default())
+            .await
+            .is_err());
+
+        // Inputs are released again instead of staying pending
+        assert_eq!(
+            wallet.unit_balance(CurrencyUnit::Sat).await.unwrap(),
+            Amount::from(64)
+        );
+        assert_eq!(
+            wallet
+                .pending_unit_balance(CurrencyUnit::Sat)
+                .await
+                .unwrap(),
+            Amount::ZERO
+        );
+
+        mint.reload_config(config).await;
+        let melted = wallet
+            .melt(&mint_url, &quote.id, SplitTarget::default())
+            .await
+            .unwrap();
+        assert!(melted.paid);
+        assert_eq!(
+            wallet
+                .pending_unit_balance(CurrencyUnit::Sat)
+                .await
+                .unwrap(),
+            Amount::ZERO
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_payment_releases_inputs() {
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+        let connection = DirectMintConnection::new(mint.clone());
+        let mut wallet = create_wallet(mint);
+        wallet.client = Arc::new(connection.clone());
+
+        fund_wallet(&wallet, Amount::from(64)).await.unwrap();
+
+        // Payment fails right away
+        let invoice =
+            DirectMintConnection::create_invoice(Amount::from(20), FAILING_INVOICE.to_string())
+                .unwrap();
+        let quote = wallet
+            .melt_quote(mint_url.clone(), CurrencyUnit::Sat, invoice.to_string())
+            .await
+            .unwrap();
+        assert!(wallet
+            .melt(&mint_url, &quote.id, SplitTarget::default())
+            .await
+            .is_err());
+        assert_eq!(
+            wallet
+                .melt_quote_status(mint_url.clone(), &quote.id)
+                .await
+                .unwrap()
+                .state,
+            MeltQuoteState::Failed
+        );
+        assert_eq!(
+            wallet.unit_balance(CurrencyUnit::Sat).await.unwrap(),
+            Amount::from(64)
+        );
+
+        // Payment stays in flight, inputs can not be used until it failed
+        let invoice =
+            DirectMintConnection::create_invoice(Amount::from(20), PENDING_INVOICE.to_string())
+                .unwrap();
+        let quote = wallet
+            .melt_quote(mint_url.clone(), CurrencyUnit::Sat, invoice.to_string())
+            .await
+            .unwrap();
+        assert!(wallet
+            .melt(&mint_url, &quote.id, SplitTarget::default())
+            .await
+            .is_err());
+        assert!(matches!(
+            wallet
+                .melt(&mint_url, &quote.id, SplitTarget::default())
+                .await,
+            Err(Error::QuotePending)
+        ));
+        assert_eq!(
+            wallet
+                .melt_quote_status(mint_url.clone(), &quote.id)
+                .await
+                .unwrap()
+                .state,
+            MeltQuoteState::Pending
+        );
+        let pending = wallet
+            .pending_unit_balance(CurrencyUnit::Sat)
+            .await
+            .unwrap();
+        assert!(pending >= Amount::from(20));
+
+        connection.fail_payments_in_flight().await.unwrap();
+        assert_eq!(
+            wallet
+                .melt_quote_status(mint_url.clone(), &quote.id)
+                .await
+                .unwrap()
+                .state,
+            MeltQuoteState::Failed
+        );
+        assert_eq!(
+            wallet
+                .pending_unit_balance(CurrencyUnit::Sat)
+                .await
+                .unwrap(),
+            Amount::ZERO
+        );
+        assert_eq!(
+            wallet.unit_balance(CurrencyUnit::Sat).await.unwrap(),
+            Amount::from(64)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_melt() {
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+        let connection = DirectMintConnection::new(mint.clone());
+        let mut wallet = create_wallet(mint);
+        wallet.client = Arc::new(connection.clone());
+
+        fund_wallet(&wallet, Amount::from(64)).await.unwrap();
+
+        // Paid quote yields its state once, with the preimage
+        let invoice =
+            DirectMintConnection::create_invoice(Amount::from(10), String::new()).unwrap();
+        let quote = wallet
+            .melt_quote(mint_url.clone(), CurrencyUnit::Sat, invoice.to_string())
+            .await
+            .unwrap();
+        let melted = wallet
+            .melt(&mint_url, &quote.id, SplitTarget::default())
+            .await
+            .unwrap();
+        let states: Vec<_> = wallet
+            .wait_for_melt(mint_url.clone(), &quote.id, Duration::from_secs(5))
+            .collect()
+            .await;
+        assert_eq!(states.len(), 1);
+        let paid = states[0].as_ref().unwrap();
+        assert_eq!(paid.state, MeltQuoteState::Paid);
+        assert_eq!(paid.payment_preimage, melted.preimage);
+        assert!(paid.payment_preimage.is_some());
+
+        // Payment in flight is reported pending until it fails
+        let invoice =
+            DirectMintConnection::create_invoice(Amount::from(10), PENDING_INVOICE.to_string())
+                .unwrap();
+        let quote = wallet
+            .melt_quote(mint_url.clone(), CurrencyUnit::Sat, invoice.to_string())
+            .await
+            .unwrap();
+        assert!(wallet
+            .melt(&mint_url, &quote.id, SplitTarget::default())
+            .await
+            .is_err());
+
+        let mut states =
+            Box::pin(wallet.wait_for_melt(mint_url.clone(), &quote.id, Duration::from_secs(5)));
+        assert_eq!(
+            states.next().await.unwrap().unwrap().state,
+            MeltQuoteState::Pending
+        );
+        connection.fail_payments_in_flight().await.unwrap();
+        assert_eq!(
+            states.next().await.unwrap().unwrap().state,
+            MeltQuoteState::Failed
+        );
+        assert!(states.next().await.is_none());
+
+        // Unsettled payment times out
+        let invoice =
+            DirectMintConnection::create_invoice(Amount::from(10), PENDING_INVOICE.to_string())
+                .unwrap();
+        let quote = wallet
+            .melt_quote(mint_url.clone(), CurrencyUnit::Sat, invoice.to_string())
+            .await
+            .unwrap();
+        assert!(wallet
+            .melt(&mint_url, &quote.id, SplitTarget::default())
+            .await
+            .is_err());
+        let states: Vec<_> = wallet
+            .wait_for_melt(mint_url.clone(), &quote.id, Duration::from_millis(100))
+            .collect()
+            .await;
+        assert_eq!(states.len(), 2);
+        assert!(matches!(states[1], Err(Error::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn test_melt_quote_single_use() {
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+        let connection = DirectMintConnection::new(mint.clone());
+        let wallet = create_wallet(mint);
+
+        for _ in 0..2 {
+            fund_wallet(&wallet, Amount::from(32)).await.unwrap();
+        }
+        let proofs = wallet.get_proofs(mint_url.clone()).await.unwrap().unwrap();
+        assert_eq!(proofs.len(), 2);
+
+        let invoice =
+            DirectMintConnection::create_invoice(Amount::from(20), String::new()).unwrap();
+        let quote = wallet
+            .melt_quote(mint_url.clone(), CurrencyUnit::Sat, invoice.to_string())
+            .await
+            .unwrap();
+        let url = Url::try_from(&mint_url).unwrap();
+
+        let melted = connection
+            .post_melt(url.clone(), quote.id.clone(), vec![proofs[0].clone()], None)
+            .await
+            .unwrap();
+        assert!(melted.paid);
+
+        // A retry returns the original result
+        let retried = connection
+            .post_melt(url.clone(), quote.id.clone(), vec![proofs[0].clone()], None)
+            .await
+            .unwrap();
+        assert_eq!(retried, melted);
+
+        // The quote can not be paid again with other inputs
+        assert!(connection
+            .post_melt(url, quote.id, vec![proofs[1].clone()], None)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_sends() {
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+
+        let alice = create_wallet(mint.clone());
+        let bob = create_wallet(mint);
+
+        for _ in 0..2 {
+            fund_wallet(&alice, Amount::from(16)).await.unwrap();
+        }
+
+        let send = || {
+            alice.send(
+                &mint_url,
+                CurrencyUnit::Sat,
+                None,
+                Amount::from(16),
+                &SplitTarget::None,
+                None,
+            )
+        };
+        let (first, second) = tokio::join!(send(), send());
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert_eq!(
+            alice.unit_balance(CurrencyUnit::Sat).await.unwrap(),
+            Amount::ZERO
+        );
+
+        // Both tokens hold different proofs
+        for token in [first, second] {
+            assert_eq!(
+                bob.receive(&token, &SplitTarget::default(), None)
+                    .await
+                    .unwrap(),
+                Amount::from(16)
+            );
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_shared_wallet() {
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+        let wallet = create_wallet(mint);
+
+        let tasks: Vec<_> = (0..4)
+            .map(|_| {
+                let wallet = wallet.clone();
+                let mint_url = mint_url.clone();
+                tokio::spawn(async move {
+                    fund_wallet(&wallet, Amount::from(8)).await?;
+                    wallet
+                        .send(
+                            &mint_url,
+                            CurrencyUnit::Sat,
+                            None,
+                            Amount::from(8),
+                            &SplitTarget::default(),
+                            None,
+                        )
+                        .await
+                })
+            })
+            .collect();
+
+        let mut tokens = Vec::new();
+        for task in tasks {
+            tokens.push(task.await.unwrap().unwrap());
+        }
+
+        // Clones share storage
+        assert_eq!(
+            wallet.unit_balance(CurrencyUnit::Sat).await.unwrap(),
+            Amount::ZERO
+        );
+        for token in tokens {
+            assert_eq!(
+                wallet
+                    .receive(&token, &SplitTarget::default(), None)
+                    .await
+                    .unwrap(),
+                Amount::from(8)
+            );
+        }
+        assert_eq!(
+            wallet.unit_balance(CurrencyUnit::Sat).await.unwrap(),
+            Amount::from(32)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recover_paid_mint_quotes() {
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+        let wallet = create_wallet(mint);
+
+        // Quotes paid before the wallet stopped, one of them expired since
+        wallet
+            .mint_quote(mint_url.clone(), Amount::from(16), CurrencyUnit::Sat, None)
+            .await
+            .unwrap();
+        let mut expired = wallet
+            .mint_quote(mint_url.clone(), Amount::from(8), CurrencyUnit::Sat, None)
+            .await
+            .unwrap();
+        expired.expiry = unix_time() - 1;
+        wallet.localstore.add_mint_quote(expired).await.unwrap();
+
+        // Quote the mint does not know is kept for the next check
+        let mut unknown = wallet
+            .mint_quote(mint_url.clone(), Amount::from(4), CurrencyUnit::Sat, None)
+            .await
+            .unwrap();
+        unknown.id = "unknown".to_string();
+        wallet
+            .localstore
+            .add_mint_quote(unknown.clone())
+            .await
+            .unwrap();
+
+        let minted = wallet.check_all_mint_quotes().await.unwrap();
+        assert_eq!(minted, Amount::from(28));
+        assert_eq!(
+            wallet.unit_balance(CurrencyUnit::Sat).await.unwrap(),
+            Amount::from(28)
+        );
+
+        let quotes = wallet.localstore.get_mint_quotes().await.unwrap();
+        assert_eq!(quotes, vec![unknown]);
+    }
+
+    #[tokio::test]
+    async fn test_recover_pending_mints() {
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+        let wallet = create_wallet(mint.clone());
+        let keyset_id = wallet.get_mint_keysets(&mint_url).await.unwrap()[0].id;
+
+        let mut pending_mints = Vec::new();
+        for amount in [16, 8] {
+            let quote = wallet
+                .mint_quote(
+                    mint_url.clone(),
+                    Amount::from(amount),
+                    CurrencyUnit::Sat,
+                    None,
+                )
+                .await
+                .unwrap();
+            let pending_mint = PendingMint {
+                quote_id: quote.id,
+                mint_url: mint_url.clone(),
+                unit: CurrencyUnit::Sat,
+                premint_secrets: PreMintSecrets::random(
+                    keyset_id,
+                    Amount::from(amount),
+                    &SplitTarget::default(),
+                )
+                .unwrap(),
+            };
+            wallet
+                .localstore
+                .add_pending_mint(pending_mint.clone())
+                .await
+                .unwrap();
+            pending_mints.push(pending_mint);
+        }
+
+        // The wallet stopped after the mint signed the outputs of the first
+        // quote, and before it sent those of the second
+        mint.process_mint_request(MintBolt11Request {
+            quote: pending_mints[0].quote_id.clone(),
+            outputs: pending_mints[0].premint_secrets.blinded_messages(),
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            wallet.recover_pending_mints().await.unwrap(),
+            Amount::from(24)
+        );
+        assert_eq!(
+            wallet.unit_balance(CurrencyUnit::Sat).await.unwrap(),
+            Amount::from(24)
+        );
+        assert!(wallet
+            .localstore
+            .get_pending_mints()
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(wallet
+            .localstore
+            .get_mint_quotes()
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Proofs of the restored outputs can be spent
+        let token = wallet
+            .send(
+                &mint_url,
+                CurrencyUnit::Sat,
+                None,
+                Amount::from(24),
+                &SplitTarget::default(),
+                None,
+            )
+            .await
+            .unwrap();
+        let bob = create_wallet(mint);
+        assert_eq!(
+            bob.receive(&token, &SplitTarget::default(), None)
+                .await
+                .unwrap(),
+            Amount::from(24)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_history() {
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+        let alice = create_wallet(mint.clone());
+        let bob = create_wallet(mint);
+
+        fund_wallet(&alice, Amount::from(64)).await.unwrap();
+        let token = alice
+            .send(
+                &mint_url,
+                CurrencyUnit::Sat,
+                Some("lunch, thanks".to_string()),
+                Amount::from(16),
+                &SplitTarget::default(),
+                None,
+            )
+            .await
+            .unwrap();
+
+        bob.receive(&token, &SplitTarget::default(), None)
+            .await
+            .unwrap();
+        let invoice = DirectMintConnection::create_invoice(Amount::from(8), String::new()).unwrap();
+        let quote = bob
+            .melt_quote(mint_url.clone(), CurrencyUnit::Sat, invoice.to_string())
+            .await
+            .unwrap();
+        bob.melt(&mint_url, &quote.id, SplitTarget::default())
+            .await
+            .unwrap();
+
+        let csv = alice
+            .export_history(HistoryFormat::Csv, 0, u64::MAX)
+            .await
+            .unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[1].contains(",mint,") && rows[1].ends_with(",64,0,"));
+        assert!(rows[2].contains(",send,") && rows[2].ends_with(",16,0,\"lunch, thanks\""));
+
+        let json = bob
+            .export_history(HistoryFormat::Json, 0, u64::MAX)
+            .await
+            .unwrap();
+        assert!(json.contains(r#""kind":"receive""#) && json.contains(r#""memo":"lunch, thanks""#));
+
+        let transactions = bob.transactions(0, u64::MAX).await.unwrap();
+        let kinds: Vec<TransactionKind> = transactions.iter().map(|t| t.kind).collect();
+        assert_eq!(kinds, vec![TransactionKind::Receive, TransactionKind::Melt]);
+        assert_eq!(transactions[0].memo, Some("lunch, thanks".to_string()));
+        assert_eq!(transactions[1].amount, Amount::from(8));
+        assert_eq!(transactions[1].fee, Amount::ZERO);
+
+        assert!(bob
+            .export_history(HistoryFormat::Json, 0, 1)
+            .await
+            .unwrap()
+            .eq("[]"));
+    }
+
+    #[tokio::test]
+    async fn test_token_metadata() {
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+        let alice = create_wallet(mint.clone());
+        let bob = create_wallet(mint);
+
+        fund_wallet(&alice, Amount::from(16)).await.unwrap();
+
+        let metadata = BTreeMap::from([(METADATA_SENDER.to_string(), "alice".to_string())]);
+        let token = alice
+            .send_with_metadata(
+                &mint_url,
+                CurrencyUnit::Sat,
+                Some("coffee".to_string()),
+                metadata.clone(),
+                Amount::from(8),
+                &SplitTarget::default(),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(Token::from_str(&token).unwrap().sender(), Some("alice"));
+
+        bob.receive(&token, &SplitTarget::default(), None)
+            .await
+            .unwrap();
+
+        let sent = alice.transactions(0, u64::MAX).await.unwrap();
+        assert_eq!(sent[1].metadata, metadata);
+        let received = bob.transactions(0, u64::MAX).await.unwrap();
+        assert_eq!(received[0].memo, Some("coffee".to_string()));
+        assert_eq!(received[0].metadata, metadata);
+    }
+
+    #[tokio::test]
+    async fn test_check_proofs_spent_chunked() {
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+        let wallet = create_wallet(mint.clone());
+
+        let quote = wallet
+            .mint_quote(mint_url.clone(), Amount::from(250), CurrencyUnit::Sat, None)
+            .await
+            .unwrap();
+        wallet
+            .mint(
+                mint_url.clone(),
+                &quote.id,
+                SplitTarget::Values(vec![Amount::from(1); 250]),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let proofs = wallet.get_proofs(mint_url.clone()).await.unwrap().unwrap();
+        assert_eq!(proofs.len(), 250);
+
+        // Spend some of the proofs behind the back of the wallet
+        let spent: Vec<Proof> = proofs.iter().skip(110).take(20).cloned().collect();
+        let keyset_id = spent[0].keyset_id;
+        let premint =
+            PreMintSecrets::random(keyset_id, Amount::from(20), &SplitTarget::None).unwrap();
+        mint.process_swap_request(SwapRequest::new(spent.clone(), premint.blinded_messages()))
+            .await
+            .unwrap();
+
+        let states = wallet
+            .check_proofs_spent(mint_url.clone(), proofs.clone())
+            .await
+            .unwrap();
+        assert_eq!(states.len(), 250);
+        for (proof, state) in proofs.iter().zip(&states) {
+            assert_eq!(proof.y().unwrap(), state.y);
+        }
+        assert_eq!(
+            states.iter().filter(|s| s.state == State::Spent).count(),
+            20
+        );
+
+        // Spent proofs are no longer counted in the balance
+        assert_eq!(
+            wallet.unit_balance(CurrencyUnit::Sat).await.unwrap(),
+            Amount::from(230)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_restore_change_of_locked_send() {
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+        let seed = SecretKey::generate().as_secret_bytes().to_vec();
+        let wallet_from_seed = |mint: Mint| {
+            let mut wallet = Wallet::new(Arc::new(WalletMemoryDatabase::default()), &seed, vec![]);
+            wallet.client = Arc::new(DirectMintConnection::new(mint));
+            wallet
+        };
+        let wallet = wallet_from_seed(mint.clone());
+
+        fund_wallet(&wallet, Amount::from(64)).await.unwrap();
+        let keyset_id = wallet.get_proofs(mint_url.clone()).await.unwrap().unwrap()[0].keyset_id;
+        let counter = wallet
+            .localstore
+            .get_keyset_counter(&keyset_id)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let locked =
+            SpendingConditions::new_p2pk(SecretKey::generate().public_key(), Conditions::default());
+        wallet
+            .send(
+                &mint_url,
+                CurrencyUnit::Sat,
+                None,
+                Amount::from(5),
+                &SplitTarget::default(),
+                Some(locked),
+            )
+            .await
+            .unwrap();
+
+        // Only the change outputs 59 = 32 + 16 + 8 + 2 + 1 used the counter
+        assert_eq!(
+            wallet
+                .localstore
+                .get_keyset_counter(&keyset_id)
+                .await
+                .unwrap(),
+            Some(counter + 5)
+        );
+
+        let restored = wallet_from_seed(mint);
+        assert_eq!(
+            restored.restore(mint_url.clone()).await.unwrap(),
+            Amount::from(59)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_many() {
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+        let alice = create_wallet(mint.clone());
+        let bob = create_wallet(mint.clone());
+        let carol = create_wallet(mint);
+
+        fund_wallet(&alice, Amount::from(64)).await.unwrap();
+
+        let carol_key = SecretKey::generate();
+        carol.add_p2pk_signing_key(carol_key.clone()).await;
+        let locked = SpendingConditions::new_p2pk(carol_key.public_key(), Conditions::default());
+
+        let tokens = alice
+            .send_many(
+                &mint_url,
+                CurrencyUnit::Sat,
+                vec![
+                    (Amount::from(5), None, Some("bob".to_string())),
+                    (Amount::from(12), Some(locked), None),
+                ],
+                &SplitTarget::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(
+            Token::from_str(&tokens[0]).unwrap().memo,
+            Some("bob".to_string())
+        );
+
+        // Locked token can only be redeemed by carol
+        assert!(bob
+            .receive(&tokens[1], &SplitTarget::default(), None)
+            .await
+            .is_err());
+        assert_eq!(
+            bob.receive(&tokens[0], &SplitTarget::default(), None)
+                .await
+                .unwrap(),
+            Amount::from(5)
+        );
+        assert_eq!(
+            carol
+                .receive(&tokens[1], &SplitTarget::default(), None)
+                .await
+                .unwrap(),
+            Amount::from(12)
+        );
+
+        assert_eq!(
+            alice.unit_balance(CurrencyUnit::Sat).await.unwrap(),
+            Amount::from(47)
+        );
+        assert_eq!(alice.transactions(0, u64::MAX).await.unwrap().len(), 3);
+
+        assert!(alice
+            .send_many(
+                &mint_url,
+                CurrencyUnit::Sat,
+                vec![(Amount::from(64), None, None)],
+                &SplitTarget::default(),
+            )
+            .await
+            .is_err());
+        assert_eq!(
+            alice.unit_balance(CurrencyUnit::Sat).await.unwrap(),
+            Amount::from(47)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_private() {
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+        let alice = create_wallet(mint.clone());
+        let bob = create_wallet(mint);
+
+        fund_wallet(&alice, Amount::from(64)).await.unwrap();
+        let minted = alice.get_proofs(mint_url.clone()).await.unwrap().unwrap();
+
+        // Swapped even though the minted proof pays the amount exactly
+        let token = alice
+            .send_private(
+                &mint_url,
+                CurrencyUnit::Sat,
+                None,
+                Amount::from(64),
+                None,
+                PrivacyMode::default(),
+            )
+            .await
+            .unwrap();
+        let sent = Token::from_str(&token).unwrap().token[0].proofs.clone();
+        assert_eq!(sent.len(), 1);
+        assert_ne!(sent[0].secret, minted[0].secret);
+        assert_eq!(
+            bob.receive(&token, &SplitTarget::default(), None)
+                .await
+                .unwrap(),
+            Amount::from(64)
+        );
+
+        // 56 change as 16 + 16 + 8 + 8 + 4 + 4 instead of 32 + 16 + 8
+        let token = bob
+            .send_private(
+                &mint_url,
+                CurrencyUnit::Sat,
+                None,
+                Amount::from(8),
+                None,
+                PrivacyMode { decoy_change: true },
+            )
+            .await
+            .unwrap();
+        assert_eq!(Token::from_str(&token).unwrap().token[0].proofs.len(), 1);
+        let mut change: Vec<u64> = bob
+            .get_proofs(mint_url.clone())
+            .await
+            .unwrap()
+            .unwrap()
+            .iter()
+            .map(|p| u64::from(p.amount))
+            .collect();
+        change.sort();
+        assert_eq!(change, vec![4, 4, 8, 8, 16, 16]);
+        assert_eq!(
+            alice
+                .receive(&token, &SplitTarget::default(), None)
+                .await
+                .unwrap(),
+            Amount::from(8)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_receive_foreign_token() {
+        use base64::engine::general_purpose;
+        use base64::Engine as _;
+        use cdk::nuts::nut02::legacy_keyset_id;
+
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+        let alice = create_wallet(mint.clone());
+        let bob = create_wallet(mint);
+
+        fund_wallet(&alice, Amount::from(64)).await.unwrap();
+        let token = alice
+            .send(
+                &mint_url,
+                CurrencyUnit::Sat,
+                None,
+                Amount::from(10),
+                &SplitTarget::default(),
+                None,
+            )
+            .await
+            .unwrap();
+
+        // Re-encoded the way older nutshell wallets did
+        let mut json = serde_json::to_value(Token::from_str(&token).unwrap()).unwrap();
+        for proof in json["token"][0]["proofs"].as_array_mut().unwrap() {
+            let keyset_id = Id::from_str(proof["id"].as_str().unwrap()).unwrap();
+            let keys = alice.get_keyset_keys(&mint_url, keyset_id).await.unwrap();
+            proof["id"] = legacy_keyset_id(&keys).unwrap().into();
+            proof["witness"] = "".into();
+        }
+        json["unit"] = "SAT".into();
+        let foreign = format!(
+            "cashuA{}",
+            general_purpose::STANDARD.encode(json.to_string())
+        );
+        assert!(Token::from_str(&foreign).is_err());
+
+        let decoded = bob.decode_token(&foreign).await.unwrap();
+        assert_eq!(decoded, Token::from_str(&token).unwrap());
+        assert_eq!(
+            bob.receive(&foreign, &SplitTarget::default(), None)
+                .await
+                .unwrap(),
+            Amount::from(10)
+        );
+        assert!(matches!(
+            bob.receive(&foreign, &SplitTarget::default(), None).await,
+            Err(Error::AlreadyReceived)
+        ));
+    }
+
+    /// Accept one HTTP request on `listener`, answer `200` and return its body
+    /// Accept one request, return its lowercased headers and its body
+    async fn serve_once(listener: tokio::net::TcpListener) -> (String, String) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0; 4096];
+        let body_start = loop {
+            let n = stream.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+            if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+        };
+        let headers = String::from_utf8_lossy(&request[..body_start]).to_lowercase();
+        let content_length: usize = headers
+            .lines()
+            .find_map(|l| l.strip_prefix("content-length: "))
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        while request.len() < body_start + content_length {
+            let n = stream.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+        }
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+            .await
+            .unwrap();
+
+        let body = String::from_utf8(request[body_start..].to_vec()).unwrap();
+        (headers, body)
+    }
+
+    #[tokio::test]
+    async fn test_pay_request() {
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+        let alice = create_wallet(mint.clone());
+
+        fund_wallet(&alice, Amount::from(64)).await.unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(serve_once(listener));
+
+        let request = PaymentRequest {
+            payment_id: Some("order-1".to_string()),
+            amount: Some(Amount::from(10)),
+            unit: Some(CurrencyUnit::Sat),
+            single_use: None,
+            mints: Some(vec![mint_url.clone()]),
+            description: Some("invoice 1".to_string()),
+            transports: vec![
+                // Nothing listens on port 1, delivery falls through to the next transport
+                Transport {
+                    _type: TransportType::HttpPost,
+                    target: "http://127.0.0.1:1/pay".to_string(),
+                    tags: None,
+                },
+                Transport {
+                    _type: TransportType::HttpPost,
+                    target: format!("http://{}/pay", address),
+                    tags: None,
+                },
+            ],
+        };
+        let request = PaymentRequest::from_str(&request.to_string()).unwrap();
+
+        let outcome = alice.pay_request(&request, None).await.unwrap();
+        assert!(outcome.delivered());
+        assert_eq!(outcome.attempts.len(), 2);
+        assert!(!outcome.attempts[0].delivered());
+        assert_eq!(outcome.amount, Amount::from(10));
+
+        let (headers, body) = server.await.unwrap();
+        assert!(headers.contains("user-agent: cdk/"));
+        assert!(body.contains(r#""id":"order-1""#) && body.contains(r#""proofs":["#));
+        assert_eq!(
+            alice.unit_balance(CurrencyUnit::Sat).await.unwrap(),
+            Amount::from(54)
+        );
+
+        let other_mint = PaymentRequest {
+            mints: Some(vec![MintUrl::from_str("https://other.mint").unwrap()]),
+            ..request.clone()
+        };
+        assert!(matches!(
+            alice.pay_request(&other_mint, None).await,
+            Err(payment_request::Error::NoAcceptedMint(_, _))
+        ));
+        assert!(matches!(
+            alice.pay_request(&request, Some(Amount::from(5))).await,
+            Err(payment_request::Error::AmountMismatch(_, _))
+        ));
+    }
+
+    struct FixedRate(u64);
+
+    #[async_trait]
+    impl cdk::exchange_rate::ExchangeRate for FixedRate {
+        async fn rate(
+            &self,
+            unit: &CurrencyUnit,
+        ) -> Result<cdk::exchange_rate::Rate, cdk::exchange_rate::Error> {
+            Ok(cdk::exchange_rate::Rate {
+                unit: unit.clone(),
+                per_btc: self.0,
+                timestamp: unix_time(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multi_unit_wallet() {
+        let mut mint = create_mint().await.unwrap();
+        let usd_path = DerivationPath::from(vec![ChildNumber::from_hardened_idx(1).unwrap()]);
+        mint.rotate_keyset(CurrencyUnit::Usd, usd_path, 32)
+            .await
+            .unwrap();
+        // 60,000 USD per bitcoin in cents
+        let rate = Arc::new(FixedRate(6_000_000));
+        mint.exchange_rate = Some(rate.clone());
+
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+        let mut wallet = create_wallet(mint);
+
+        for (amount, unit) in [(1000, CurrencyUnit::Sat), (100, CurrencyUnit::Usd)] {
+            let quote = wallet
+                .mint_quote(mint_url.clone(), Amount::from(amount), unit, None)
+                .await
+                .unwrap();
+            wallet
+                .mint(mint_url.clone(), &quote.id, SplitTarget::default(), None)
+                .await
+                .unwrap();
+        }
+
+        let balances = wallet.mint_balances().await.unwrap();
+        assert_eq!(balances[&mint_url][&CurrencyUnit::Sat], Amount::from(1000));
+        assert_eq!(balances[&mint_url][&CurrencyUnit::Usd], Amount::from(100));
+
+        // Selection only picks proofs of the requested unit
+        let token = wallet
+            .send(
+                &mint_url,
+                CurrencyUnit::Usd,
+                None,
+                Amount::from(30),
+                &SplitTarget::default(),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            Token::from_str(&token).unwrap().unit,
+            Some(CurrencyUnit::Usd)
+        );
+        assert_eq!(
+            wallet.unit_balance(CurrencyUnit::Usd).await.unwrap(),
+            Amount::from(70)
+        );
+        assert_eq!(
+            wallet.unit_balance(CurrencyUnit::Sat).await.unwrap(),
+            Amount::from(1000)
+        );
+
+        // Proofs of different units cannot be spent together
+        let proofs = wallet.get_proofs(mint_url.clone()).await.unwrap().unwrap();
+        assert!(matches!(
+            wallet
+                .swap(
+                    &mint_url,
+                    &CurrencyUnit::Sat,
+                    None,
+                    &SplitTarget::default(),
+                    proofs,
+                    None,
+                )
+                .await,
+            Err(Error::UnitMismatch(CurrencyUnit::Sat))
+        ));
+
+        assert_eq!(
+            wallet.total_fiat_balance(&CurrencyUnit::Usd).await.unwrap(),
+            None
+        );
+        wallet.exchange_rate = Some(rate);
+        assert_eq!(
+            wallet
+                .fiat_value(Amount::from(1000), &CurrencyUnit::Sat, &CurrencyUnit::Usd)
+                .await
+                .unwrap(),
+            Some(Amount::from(60))
+        );
+        assert_eq!(
+            wallet.total_fiat_balance(&CurrencyUnit::Usd).await.unwrap(),
+            Some(Amount::from(130))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_keyset_rotation() {
+        let mint = create_mint().await.unwrap();
+        let mint_url = MintUrl::from_str(MINT_URL).unwrap();
+        let mut wallet = create_wallet(mint.clone());
+        let mut events = wallet.events.subscribe();
+
+        fund_wallet(&wallet, Amount::from(100)).await.unwrap();
+        let first = wallet.get_mint_keysets(&mint_url).await.unwrap()[0].id;
+
+        mint.rotate_next_keyset(CurrencyUnit::Sat, 32)
+            .await
+            .unwrap();
+
+        // Minting with the stale keyset fails and refreshes the keysets
+        let quote = wallet
+            .mint_quote(mint_url.clone(), Amount::from(10), CurrencyUnit::Sat, None)
+            .await
+            .unwrap();
+        assert!(wallet
+            .mint(mint_url.clone(), &quote.id, SplitTarget::default(), None)
+            .await
+            .is_err());
+        let second = match events.try_recv().unwrap() {
+            WalletEvent::KeysetRotated {
+                inactive,
+                active,
+                swapped: None,
+                ..
+            } => {
+                assert_eq!(inactive, first);
+                active
+            }
+            event => panic!("Unexpected event {:?}", event),
+        };
+        wallet
+            .mint(mint_url.clone(), &quote.id, SplitTarget::default(), None)
+            .await
+            .unwrap();
+
+        // Proofs of the deactivated keyset are swapped into the active one
+        mint.rotate_next_keyset(CurrencyUnit::Sat, 32)
+            .await
+            .unwrap();
+        wallet.swap_inactive_keysets = true;
+        let rotated = wallet.check_keyset_rotation(&mint_url).await.unwrap();
+        let third = match rotated.as_slice() {
+            [WalletEvent::KeysetRotated {
+                inactive,
+                active,
+                swapped: Some(swapped),
+                ..
+            }] => {
+                assert_eq!(*inactive, second);
+                assert_eq!(*swapped, Amount::from(10));
+                *active
+            }
+            rotated => panic!("Unexpected events {:?}", rotated),
+        };
+        assert_eq!(events.try_recv().unwrap(), rotated[0]);
+
+        let proofs = wallet.get_proofs(mint_url.clone()).await.unwrap().unwrap();
+        assert!(proofs.iter().all(|p| p.keyset_id != second));
+        assert_eq!(
+            proofs
+                .iter()
+                .filter(|p| p.keyset_id == third)
+                .map(|p| p.amount)
+                .sum::<Amount>(),
+            Amount::from(10)
+        );
+        assert_eq!(
+            wallet.unit_balance(CurrencyUnit::Sat).await.unwrap(),
+            Amount::from(110)
+        );
+
+        // Nothing changed since the last check
+        assert!(wallet
+            .check_keyset_rotation(&mint_url)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
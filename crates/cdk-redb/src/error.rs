@@ -46,6 +46,9 @@ pub enum Error {
     /// Unknown Proof Y
     #[error("Unknown Proof Y")]
     UnknownY,
+    /// Backup file already exists
+    #[error("Backup file already exists")]
+    BackupExists,
 }
 
 impl From<Error> for cdk::cdk_database::Error {
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::path::Path;
 use std::str::FromStr;
 use std::sync::Arc;
 
@@ -7,10 +8,14 @@ use cdk::cdk_database;
 use cdk::cdk_database::MintDatabase;
 use cdk::dhke::hash_to_curve;
 use cdk::mint::MintKeySetInfo;
-use cdk::nuts::{BlindSignature, CurrencyUnit, Id, Proof, PublicKey};
+use cdk::nuts::{BlindSignature, CurrencyUnit, Id, Proof, Proofs, PublicKey};
 use cdk::secret::Secret;
-use cdk::types::{MeltQuote, MintQuote};
-use redb::{Database, ReadableTable, TableDefinition};
+use cdk::types::{CompletedMelt, MeltQuote, MintQuote};
+use cdk::Amount;
+use redb::{
+    Database, Key, ReadTransaction, ReadableTable, ReadableTableMetadata, TableDefinition, Value,
+    WriteTransaction,
+};
 use tokio::sync::Mutex;
 use tracing::debug;
 
@@ -19,10 +24,19 @@ use super::error::Error;
 const ACTIVE_KEYSETS_TABLE: TableDefinition<&str, &str> = TableDefinition::new("active_keysets");
 const KEYSETS_TABLE: TableDefinition<&str, &str> = TableDefinition::new("keysets");
 const MINT_QUOTES_TABLE: TableDefinition<&str, &str> = TableDefinition::new("mint_quotes");
+// Payment hash - Mint quote id
+const MINT_QUOTE_HASHES_TABLE: TableDefinition<&str, &str> =
+    TableDefinition::new("mint_quote_hashes");
 const MELT_QUOTES_TABLE: TableDefinition<&str, &str> = TableDefinition::new("melt_quotes");
+const COMPLETED_MELTS_TABLE: TableDefinition<&str, &str> = TableDefinition::new("completed_melts");
 const PENDING_PROOFS_TABLE: TableDefinition<[u8; 33], &str> =
     TableDefinition::new("pending_proofs");
 const SPENT_PROOFS_TABLE: TableDefinition<[u8; 33], &str> = TableDefinition::new("spent_proofs");
+// Key is the y of an archived spent proof
+const ARCHIVED_PROOFS_TABLE: TableDefinition<[u8; 33], ()> =
+    TableDefinition::new("archived_proofs");
+// Key is keyset id value is the amount of its archived proofs
+const ARCHIVED_AMOUNTS_TABLE: TableDefinition<&str, u64> = TableDefinition::new("archived_amounts");
 const CONFIG_TABLE: TableDefinition<&str, &str> = TableDefinition::new("config");
 // Key is hex blinded_message B_ value is blinded_signature
 const BLINDED_SIGNATURES: TableDefinition<[u8; 33], &str> =
@@ -55,15 +69,23 @@ impl MintRedbDatabase {
                         // Database needs to be upgraded
                         todo!()
                     }
+                    let _ = write_txn.open_table(COMPLETED_MELTS_TABLE)?;
+                    let _ = write_txn.open_table(ARCHIVED_PROOFS_TABLE)?;
+                    let _ = write_txn.open_table(ARCHIVED_AMOUNTS_TABLE)?;
+                    index_mint_quote_hashes(&write_txn)?;
                 }
                 None => {
                     // Open all tables to init a new db
                     let _ = write_txn.open_table(ACTIVE_KEYSETS_TABLE)?;
                     let _ = write_txn.open_table(KEYSETS_TABLE)?;
                     let _ = write_txn.open_table(MINT_QUOTES_TABLE)?;
+                    let _ = write_txn.open_table(MINT_QUOTE_HASHES_TABLE)?;
                     let _ = write_txn.open_table(MELT_QUOTES_TABLE)?;
+                    let _ = write_txn.open_table(COMPLETED_MELTS_TABLE)?;
                     let _ = write_txn.open_table(PENDING_PROOFS_TABLE)?;
                     let _ = write_txn.open_table(SPENT_PROOFS_TABLE)?;
+                    let _ = write_txn.open_table(ARCHIVED_PROOFS_TABLE)?;
+                    let _ = write_txn.open_table(ARCHIVED_AMOUNTS_TABLE)?;
                     let _ = write_txn.open_table(BLINDED_SIGNATURES)?;
 
                     table.insert("db_version", "0")?;
@@ -76,6 +98,81 @@ impl MintRedbDatabase {
             db: Arc::new(Mutex::new(db)),
         })
     }
+
+    /// Write a consistent snapshot of the database to `path`
+    ///
+    /// Tables are copied from a single read transaction, so the mint keeps
+    /// serving requests while the backup is written. Fails if `path` already
+    /// exists.
+    pub async fn backup(&self, path: &str) -> Result<(), Error> {
+        if Path::new(path).exists() {
+            return Err(Error::BackupExists);
+        }
+
+        let read_txn = {
+            let db = self.db.lock().await;
+            db.begin_read()?
+        };
+
+        let backup = Database::create(path)?;
+        let write_txn = backup.begin_write()?;
+
+        copy_table(&read_txn, &write_txn, CONFIG_TABLE)?;
+        copy_table(&read_txn, &write_txn, ACTIVE_KEYSETS_TABLE)?;
+        copy_table(&read_txn, &write_txn, KEYSETS_TABLE)?;
+        copy_table(&read_txn, &write_txn, MINT_QUOTES_TABLE)?;
+        copy_table(&read_txn, &write_txn, MINT_QUOTE_HASHES_TABLE)?;
+        copy_table(&read_txn, &write_txn, MELT_QUOTES_TABLE)?;
+        copy_table(&read_txn, &write_txn, COMPLETED_MELTS_TABLE)?;
+        copy_table(&read_txn, &write_txn, PENDING_PROOFS_TABLE)?;
+        copy_table(&read_txn, &write_txn, SPENT_PROOFS_TABLE)?;
+        copy_table(&read_txn, &write_txn, ARCHIVED_PROOFS_TABLE)?;
+        copy_table(&read_txn, &write_txn, ARCHIVED_AMOUNTS_TABLE)?;
+        copy_table(&read_txn, &write_txn, BLINDED_SIGNATURES)?;
+
+        write_txn.commit()?;
+
+        Ok(())
+    }
+}
+
+fn copy_table<K, V>(
+    read_txn: &ReadTransaction,
+    write_txn: &WriteTransaction,
+    definition: TableDefinition<K, V>,
+) -> Result<(), Error>
+where
+    K: Key + 'static,
+    V: Value + 'static,
+{
+    let source = read_txn.open_table(definition)?;
+    let mut destination = write_txn.open_table(definition)?;
+
+    for entry in source.iter()? {
+        let (key, value) = entry?;
+        destination.insert(key.value(), value.value())?;
+    }
+
+    Ok(())
+}
+
+/// Index the payment hashes of mint quotes stored before the index existed
+fn index_mint_quote_hashes(write_txn: &WriteTransaction) -> Result<(), Error> {
+    let quotes = write_txn.open_table(MINT_QUOTES_TABLE)?;
+    let mut hashes = write_txn.open_table(MINT_QUOTE_HASHES_TABLE)?;
+    if !hashes.is_empty()? {
+        return Ok(());
+    }
+
+    for entry in quotes.iter()? {
+        let (id, quote) = entry?;
+        let quote: MintQuote = serde_json::from_str(quote.value())?;
+        if let Some(hash) = quote.payment_hash() {
+            hashes.insert(hash.as_str(), id.value())?;
+        }
+    }
+
+    Ok(())
 }
 
 #[async_trait]
@@ -193,6 +290,25 @@ impl MintDatabase for MintRedbDatabase {
             let mut table = write_txn
                 .open_table(MINT_QUOTES_TABLE)
                 .map_err(Error::from)?;
+            let mut hashes = write_txn
+                .open_table(MINT_QUOTE_HASHES_TABLE)
+                .map_err(Error::from)?;
+
+            let old_hash = match table.get(quote.id.as_str()).map_err(Error::from)? {
+                Some(old) => serde_json::from_str::<MintQuote>(old.value())
+                    .map_err(Error::from)?
+                    .payment_hash(),
+                None => None,
+            };
+            if let Some(old_hash) = old_hash {
+                hashes.remove(old_hash.as_str()).map_err(Error::from)?;
+            }
+            if let Some(hash) = quote.payment_hash() {
+                hashes
+                    .insert(hash.as_str(), quote.id.as_str())
+                    .map_err(Error::from)?;
+            }
+
             table
                 .insert(
                     quote.id.as_str(),
@@ -218,6 +334,39 @@ impl MintDatabase for MintRedbDatabase {
         }
     }
 
+    async fn get_mint_quote_by_request(
+        &self,
+        request: &str,
+    ) -> Result<Option<MintQuote>, Self::Err> {
+        let quotes = self.get_mint_quotes().await?;
+
+        Ok(quotes.into_iter().find(|q| q.request.eq(request)))
+    }
+
+    async fn get_mint_quote_by_payment_hash(
+        &self,
+        payment_hash: &str,
+    ) -> Result<Option<MintQuote>, Self::Err> {
+        let db = self.db.lock().await;
+        let read_txn = db.begin_read().map_err(Error::from)?;
+        let hashes = read_txn
+            .open_table(MINT_QUOTE_HASHES_TABLE)
+            .map_err(Error::from)?;
+        let table = read_txn
+            .open_table(MINT_QUOTES_TABLE)
+            .map_err(Error::from)?;
+
+        let quote_id = match hashes.get(payment_hash).map_err(Error::from)? {
+            Some(quote_id) => quote_id.value().to_string(),
+            None => return Ok(None),
+        };
+
+        match table.get(quote_id.as_str()).map_err(Error::from)? {
+            Some(quote) => Ok(serde_json::from_str(quote.value()).map_err(Error::from)?),
+            None => Ok(None),
+        }
+    }
+
     async fn get_mint_quotes(&self) -> Result<Vec<MintQuote>, Self::Err> {
         let db = self.db.lock().await;
         let read_txn = db.begin_read().map_err(Error::from)?;
@@ -245,7 +394,19 @@ impl MintDatabase for MintRedbDatabase {
             let mut table = write_txn
                 .open_table(MINT_QUOTES_TABLE)
                 .map_err(Error::from)?;
-            table.remove(quote_id).map_err(Error::from)?;
+            let mut hashes = write_txn
+                .open_table(MINT_QUOTE_HASHES_TABLE)
+                .map_err(Error::from)?;
+
+            let hash = match table.remove(quote_id).map_err(Error::from)? {
+                Some(quote) => serde_json::from_str::<MintQuote>(quote.value())
+                    .map_err(Error::from)?
+                    .payment_hash(),
+                None => None,
+            };
+            if let Some(hash) = hash {
+                hashes.remove(hash.as_str()).map_err(Error::from)?;
+            }
         }
         write_txn.commit().map_err(Error::from)?;
 
@@ -319,6 +480,58 @@ impl MintDatabase for MintRedbDatabase {
         Ok(())
     }
 
+    async fn add_completed_melt(&self, melt: CompletedMelt) -> Result<(), Self::Err> {
+        let db = self.db.lock().await;
+
+        let write_txn = db.begin_write().map_err(Error::from)?;
+
+        {
+            let mut table = write_txn
+                .open_table(COMPLETED_MELTS_TABLE)
+                .map_err(Error::from)?;
+            table
+                .insert(
+                    melt.quote_id.as_str(),
+                    serde_json::to_string(&melt).map_err(Error::from)?.as_str(),
+                )
+                .map_err(Error::from)?;
+        }
+        write_txn.commit().map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    async fn get_completed_melt(&self, quote_id: &str) -> Result<Option<CompletedMelt>, Self::Err> {
+        let db = self.db.lock().await;
+        let read_txn = db.begin_read().map_err(Error::from)?;
+        let table = read_txn
+            .open_table(COMPLETED_MELTS_TABLE)
+            .map_err(Error::from)?;
+
+        match table.get(quote_id).map_err(Error::from)? {
+            Some(melt) => Ok(Some(
+                serde_json::from_str(melt.value()).map_err(Error::from)?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_completed_melts(&self) -> Result<Vec<CompletedMelt>, Self::Err> {
+        let db = self.db.lock().await;
+        let read_txn = db.begin_read().map_err(Error::from)?;
+        let table = read_txn
+            .open_table(COMPLETED_MELTS_TABLE)
+            .map_err(Error::from)?;
+
+        let mut melts = Vec::new();
+
+        for (_quote_id, melt) in (table.iter().map_err(Error::from)?).flatten() {
+            melts.push(serde_json::from_str(melt.value()).map_err(Error::from)?);
+        }
+
+        Ok(melts)
+    }
+
     async fn add_spent_proof(&self, proof: Proof) -> Result<(), Self::Err> {
         let db = self.db.lock().await;
 
@@ -355,6 +568,97 @@ impl MintDatabase for MintRedbDatabase {
         }
     }
 
+    async fn get_spent_proofs(&self) -> Result<Proofs, Self::Err> {
+        let db = self.db.lock().await;
+        let read_txn = db.begin_read().map_err(Error::from)?;
+        let table = read_txn
+            .open_table(SPENT_PROOFS_TABLE)
+            .map_err(Error::from)?;
+
+        let mut proofs = Vec::new();
+
+        for (_y, proof) in (table.iter().map_err(Error::from)?).flatten() {
+            let proof = serde_json::from_str(proof.value()).map_err(Error::from)?;
+
+            proofs.push(proof)
+        }
+
+        Ok(proofs)
+    }
+
+    async fn archive_spent_proofs(&self, keyset_id: &Id) -> Result<u64, Self::Err> {
+        let db = self.db.lock().await;
+
+        let write_txn = db.begin_write().map_err(Error::from)?;
+        let archived = {
+            let mut spent_table = write_txn
+                .open_table(SPENT_PROOFS_TABLE)
+                .map_err(Error::from)?;
+
+            let mut proofs = Vec::new();
+            for (y, proof) in (spent_table.iter().map_err(Error::from)?).flatten() {
+                let proof: Proof = serde_json::from_str(proof.value()).map_err(Error::from)?;
+                if proof.keyset_id.eq(keyset_id) {
+                    proofs.push((y.value(), proof.amount));
+                }
+            }
+
+            let mut archived_table = write_txn
+                .open_table(ARCHIVED_PROOFS_TABLE)
+                .map_err(Error::from)?;
+            let mut amount = 0;
+            for (y, proof_amount) in &proofs {
+                spent_table.remove(y).map_err(Error::from)?;
+                archived_table.insert(y, ()).map_err(Error::from)?;
+                amount += u64::from(*proof_amount);
+            }
+
+            let mut amounts_table = write_txn
+                .open_table(ARCHIVED_AMOUNTS_TABLE)
+                .map_err(Error::from)?;
+            let keyset_id = keyset_id.to_string();
+            let previous = amounts_table
+                .get(keyset_id.as_str())
+                .map_err(Error::from)?
+                .map(|a| a.value())
+                .unwrap_or_default();
+            amounts_table
+                .insert(keyset_id.as_str(), previous + amount)
+                .map_err(Error::from)?;
+
+            proofs.len() as u64
+        };
+        write_txn.commit().map_err(Error::from)?;
+
+        Ok(archived)
+    }
+
+    async fn is_archived_spent_proof(&self, y: &PublicKey) -> Result<bool, Self::Err> {
+        let db = self.db.lock().await;
+        let read_txn = db.begin_read().map_err(Error::from)?;
+        let table = read_txn
+            .open_table(ARCHIVED_PROOFS_TABLE)
+            .map_err(Error::from)?;
+
+        Ok(table.get(y.to_bytes()).map_err(Error::from)?.is_some())
+    }
+
+    async fn get_archived_amounts(&self) -> Result<HashMap<Id, Amount>, Self::Err> {
+        let db = self.db.lock().await;
+        let read_txn = db.begin_read().map_err(Error::from)?;
+        let table = read_txn
+            .open_table(ARCHIVED_AMOUNTS_TABLE)
+            .map_err(Error::from)?;
+
+        let mut amounts = HashMap::new();
+        for (keyset_id, amount) in (table.iter().map_err(Error::from)?).flatten() {
+            let keyset_id = Id::from_str(keyset_id.value()).map_err(Error::from)?;
+            amounts.insert(keyset_id, Amount::from(amount.value()));
+        }
+
+        Ok(amounts)
+    }
+
     async fn get_spent_proof_by_secret(&self, secret: &Secret) -> Result<Option<Proof>, Self::Err> {
         let db = self.db.lock().await;
         let read_txn = db.begin_read().map_err(Error::from)?;
@@ -507,4 +811,225 @@ impl MintDatabase for MintRedbDatabase {
 
         Ok(signatures)
     }
+
+    async fn get_all_blinded_signatures(&self) -> Result<Vec<BlindSignature>, Self::Err> {
+        let db = self.db.lock().await;
+        let read_txn = db.begin_read().map_err(Error::from)?;
+        let table = read_txn
+            .open_table(BLINDED_SIGNATURES)
+            .map_err(Error::from)?;
+
+        let mut signatures = Vec::new();
+
+        for (_blinded_message, signature) in (table.iter().map_err(Error::from)?).flatten() {
+            let signature = serde_json::from_str(signature.value()).map_err(Error::from)?;
+
+            signatures.push(signature)
+        }
+
+        Ok(signatures)
+    }
+
+    async fn add_spent_proofs_and_signatures(
+        &self,
+        proofs: Proofs,
+        blinded_signatures: Vec<(PublicKey, BlindSignature)>,
+    ) -> Result<(), Self::Err> {
+        let db = self.db.lock().await;
+        let write_txn = db.begin_write().map_err(Error::from)?;
+
+        {
+            let archived = write_txn
+                .open_table(ARCHIVED_PROOFS_TABLE)
+                .map_err(Error::from)?;
+            let mut table = write_txn
+                .open_table(SPENT_PROOFS_TABLE)
+                .map_err(Error::from)?;
+            for proof in &proofs {
+                let y: PublicKey = hash_to_curve(&proof.secret.to_bytes()).map_err(Error::from)?;
+                if table.get(y.to_bytes()).map_err(Error::from)?.is_some()
+                    || archived.get(y.to_bytes()).map_err(Error::from)?.is_some()
+                {
+                    return Err(cdk_database::Error::ProofAlreadySpent);
+                }
+                table
+                    .insert(
+                        y.to_bytes(),
+                        serde_json::to_string(proof).map_err(Error::from)?.as_str(),
+                    )
+                    .map_err(Error::from)?;
+            }
+        }
+
+        {
+            let mut table = write_txn
+                .open_table(BLINDED_SIGNATURES)
+                .map_err(Error::from)?;
+            for (blinded_message, blinded_signature) in &blinded_signatures {
+                table
+                    .insert(
+                        blinded_message.to_bytes(),
+                        serde_json::to_string(blinded_signature)
+                            .map_err(Error::from)?
+                            .as_str(),
+                    )
+                    .map_err(Error::from)?;
+            }
+        }
+
+        // Dropping the transaction on error aborts it
+        write_txn.commit().map_err(Error::from)?;
+        debug!("Added {} spent proofs", proofs.len());
+
+        Ok(())
+    }
+
+    async fn issue_mint_quote(
+        &self,
+        quote_id: &str,
+        blinded_signatures: Vec<(PublicKey, BlindSignature)>,
+    ) -> Result<(), Self::Err> {
+        let db = self.db.lock().await;
+        let write_txn = db.begin_write().map_err(Error::from)?;
+
+        {
+            let mut table = write_txn
+                .open_table(MINT_QUOTES_TABLE)
+                .map_err(Error::from)?;
+            let mut hashes = write_txn
+                .open_table(MINT_QUOTE_HASHES_TABLE)
+                .map_err(Error::from)?;
+
+            let hash = match table.remove(quote_id).map_err(Error::from)? {
+                Some(quote) => serde_json::from_str::<MintQuote>(quote.value())
+                    .map_err(Error::from)?
+                    .payment_hash(),
+                None => return Err(cdk_database::Error::UnknownQuote),
+            };
+            if let Some(hash) = hash {
+                hashes.remove(hash.as_str()).map_err(Error::from)?;
+            }
+        }
+
+        {
+            let mut table = write_txn
+                .open_table(BLINDED_SIGNATURES)
+                .map_err(Error::from)?;
+            for (blinded_message, blinded_signature) in &blinded_signatures {
+                table
+                    .insert(
+                        blinded_message.to_bytes(),
+                        serde_json::to_string(blinded_signature)
+                            .map_err(Error::from)?
+                            .as_str(),
+                    )
+                    .map_err(Error::from)?;
+            }
+        }
+
+        // Dropping the transaction on error aborts it
+        write_txn.commit().map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    async fn complete_melt(
+        &self,
+        inputs: Proofs,
+        blinded_signatures: Vec<(PublicKey, BlindSignature)>,
+        melt: CompletedMelt,
+        quote: MeltQuote,
+        mint_quote: Option<MintQuote>,
+    ) -> Result<(), Self::Err> {
+        let db = self.db.lock().await;
+        let write_txn = db.begin_write().map_err(Error::from)?;
+
+        {
+            let archived = write_txn
+                .open_table(ARCHIVED_PROOFS_TABLE)
+                .map_err(Error::from)?;
+            let mut pending = write_txn
+                .open_table(PENDING_PROOFS_TABLE)
+                .map_err(Error::from)?;
+            let mut table = write_txn
+                .open_table(SPENT_PROOFS_TABLE)
+                .map_err(Error::from)?;
+            for proof in &inputs {
+                let y: PublicKey = hash_to_curve(&proof.secret.to_bytes()).map_err(Error::from)?;
+                if table.get(y.to_bytes()).map_err(Error::from)?.is_some()
+                    || archived.get(y.to_bytes()).map_err(Error::from)?.is_some()
+                {
+                    return Err(cdk_database::Error::ProofAlreadySpent);
+                }
+                pending.remove(y.to_bytes()).map_err(Error::from)?;
+                table
+                    .insert(
+                        y.to_bytes(),
+                        serde_json::to_string(proof).map_err(Error::from)?.as_str(),
+                    )
+                    .map_err(Error::from)?;
+            }
+        }
+
+        {
+            let mut table = write_txn
+                .open_table(BLINDED_SIGNATURES)
+                .map_err(Error::from)?;
+            for (blinded_message, blinded_signature) in &blinded_signatures {
+                table
+                    .insert(
+                        blinded_message.to_bytes(),
+                        serde_json::to_string(blinded_signature)
+                            .map_err(Error::from)?
+                            .as_str(),
+                    )
+                    .map_err(Error::from)?;
+            }
+        }
+
+        {
+            let mut table = write_txn
+                .open_table(COMPLETED_MELTS_TABLE)
+                .map_err(Error::from)?;
+            table
+                .insert(
+                    melt.quote_id.as_str(),
+                    serde_json::to_string(&melt).map_err(Error::from)?.as_str(),
+                )
+                .map_err(Error::from)?;
+        }
+
+        {
+            let mut table = write_txn
+                .open_table(MELT_QUOTES_TABLE)
+                .map_err(Error::from)?;
+            table
+                .insert(
+                    quote.id.as_str(),
+                    serde_json::to_string(&quote).map_err(Error::from)?.as_str(),
+                )
+                .map_err(Error::from)?;
+        }
+
+        // The request and so the payment hash of the mint quote are unchanged
+        if let Some(mint_quote) = &mint_quote {
+            let mut table = write_txn
+                .open_table(MINT_QUOTES_TABLE)
+                .map_err(Error::from)?;
+            table
+                .insert(
+                    mint_quote.id.as_str(),
+                    serde_json::to_string(mint_quote)
+                        .map_err(Error::from)?
+                        .as_str(),
+                )
+                .map_err(Error::from)?;
+        }
+
+        // Dropping the transaction on error aborts it
+        write_txn.commit().map_err(Error::from)?;
+        debug!("Completed melt {}", melt.quote_id);
+
+        Ok(())
+    }
 }
@@ -5,11 +5,12 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use cdk::cdk_database;
 use cdk::cdk_database::WalletDatabase;
+use cdk::mint_url::MintUrl;
 use cdk::nuts::{
     CurrencyUnit, Id, KeySetInfo, Keys, MintInfo, Proofs, PublicKey, SpendingConditions, State,
 };
-use cdk::types::{MeltQuote, MintQuote, ProofInfo};
-use cdk::url::UncheckedUrl;
+use cdk::types::{MeltQuote, MintQuote, PendingMint, ProofInfo, Transaction};
+use cdk::util::unix_time;
 use redb::{Database, MultimapTableDefinition, ReadableTable, TableDefinition};
 use tokio::sync::Mutex;
 use tracing::instrument;
@@ -29,6 +30,11 @@ const MINT_KEYS_TABLE: TableDefinition<&str, &str> = TableDefinition::new("mint_
 const PROOFS_TABLE: TableDefinition<&[u8], &str> = TableDefinition::new("proofs");
 const CONFIG_TABLE: TableDefinition<&str, &str> = TableDefinition::new("config");
 const KEYSET_COUNTER: TableDefinition<&str, u32> = TableDefinition::new("keyset_counter");
+// <Token fingerprint, Unix time received>
+const RECEIVED_TOKENS_TABLE: TableDefinition<&str, u64> = TableDefinition::new("received_tokens");
+const TRANSACTIONS_TABLE: TableDefinition<&str, &str> = TableDefinition::new("transactions");
+// <Quote_id, Pending mint>
+const PENDING_MINTS_TABLE: TableDefinition<&str, &str> = TableDefinition::new("pending_mints");
 #[cfg(feature = "nostr")]
 const NOSTR_LAST_CHECKED: TableDefinition<&str, u32> = TableDefinition::new("keyset_counter");
 
@@ -60,6 +66,9 @@ impl RedbWalletDatabase {
                         todo!()
                     }
                     let _ = write_txn.open_table(KEYSET_COUNTER)?;
+                    let _ = write_txn.open_table(RECEIVED_TOKENS_TABLE)?;
+                    let _ = write_txn.open_table(TRANSACTIONS_TABLE)?;
+                    let _ = write_txn.open_table(PENDING_MINTS_TABLE)?;
                 }
                 None => {
                     // Open all tables to init a new db
@@ -71,6 +80,9 @@ impl RedbWalletDatabase {
                     let _ = write_txn.open_table(MINT_KEYS_TABLE)?;
                     let _ = write_txn.open_table(PROOFS_TABLE)?;
                     let _ = write_txn.open_table(KEYSET_COUNTER)?;
+                    let _ = write_txn.open_table(RECEIVED_TOKENS_TABLE)?;
+                    let _ = write_txn.open_table(TRANSACTIONS_TABLE)?;
+                    let _ = write_txn.open_table(PENDING_MINTS_TABLE)?;
                     #[cfg(feature = "nostr")]
                     let _ = write_txn.open_table(NOSTR_LAST_CHECKED)?;
                     table.insert("db_version", "0")?;
@@ -92,7 +104,7 @@ impl WalletDatabase for RedbWalletDatabase {
     #[instrument(skip(self))]
     async fn add_mint(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         mint_info: Option<MintInfo>,
     ) -> Result<(), Self::Err> {
         let db = self.db.lock().await;
@@ -116,7 +128,7 @@ impl WalletDatabase for RedbWalletDatabase {
     }
 
     #[instrument(skip(self))]
-    async fn get_mint(&self, mint_url: UncheckedUrl) -> Result<Option<MintInfo>, Self::Err> {
+    async fn get_mint(&self, mint_url: MintUrl) -> Result<Option<MintInfo>, Self::Err> {
         let db = self.db.lock().await;
         let read_txn = db.begin_read().map_err(Into::<Error>::into)?;
         let table = read_txn.open_table(MINTS_TABLE).map_err(Error::from)?;
@@ -132,7 +144,7 @@ impl WalletDatabase for RedbWalletDatabase {
     }
 
     #[instrument(skip(self))]
-    async fn get_mints(&self) -> Result<HashMap<UncheckedUrl, Option<MintInfo>>, Self::Err> {
+    async fn get_mints(&self) -> Result<HashMap<MintUrl, Option<MintInfo>>, Self::Err> {
         let db = self.db.lock().await;
         let read_txn = db.begin_read().map_err(Error::from)?;
         let table = read_txn.open_table(MINTS_TABLE).map_err(Error::from)?;
@@ -142,7 +154,7 @@ impl WalletDatabase for RedbWalletDatabase {
             .flatten()
             .map(|(mint, mint_info)| {
                 (
-                    UncheckedUrl::from_str(mint.value()).unwrap(),
+                    MintUrl::from_str(mint.value()).unwrap(),
                     serde_json::from_str(mint_info.value()).ok(),
                 )
             })
@@ -154,7 +166,7 @@ impl WalletDatabase for RedbWalletDatabase {
     #[instrument(skip(self))]
     async fn add_mint_keysets(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         keysets: Vec<KeySetInfo>,
     ) -> Result<(), Self::Err> {
         let db = self.db.lock().await;
@@ -183,7 +195,7 @@ impl WalletDatabase for RedbWalletDatabase {
     #[instrument(skip(self))]
     async fn get_mint_keysets(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
     ) -> Result<Option<Vec<KeySetInfo>>, Self::Err> {
         let db = self.db.lock().await;
         let read_txn = db.begin_read().map_err(Into::<Error>::into)?;
@@ -372,7 +384,10 @@ impl WalletDatabase for RedbWalletDatabase {
             let mut table = write_txn.open_table(MINT_KEYS_TABLE).map_err(Error::from)?;
             table
                 .insert(
-                    Id::from(&keys).to_string().as_str(),
+                    Id::try_from(&keys)
+                        .map_err(Error::from)?
+                        .to_string()
+                        .as_str(),
                     serde_json::to_string(&keys).map_err(Error::from)?.as_str(),
                 )
                 .map_err(Error::from)?;
@@ -440,7 +455,7 @@ impl WalletDatabase for RedbWalletDatabase {
     #[instrument(skip(self))]
     async fn get_proofs(
         &self,
-        mint_url: Option<UncheckedUrl>,
+        mint_url: Option<MintUrl>,
         unit: Option<CurrencyUnit>,
         state: Option<Vec<State>>,
         spending_conditions: Option<Vec<SpendingConditions>>,
@@ -458,14 +473,9 @@ impl WalletDatabase for RedbWalletDatabase {
                 let mut proof = None;
 
                 if let Ok(proof_info) = serde_json::from_str::<ProofInfo>(v.value()) {
-                    match proof_info.matches_conditions(
-                        &mint_url,
-                        &unit,
-                        &state,
-                        &spending_conditions,
-                    ) {
-                        true => proof = Some(proof_info),
-                        false => (),
+                    if proof_info.matches_conditions(&mint_url, &unit, &state, &spending_conditions)
+                    {
+                        proof = Some(proof_info)
                     }
                 }
 
@@ -499,6 +509,39 @@ impl WalletDatabase for RedbWalletDatabase {
         Ok(())
     }
 
+    #[instrument(skip(self, added, removed_ys))]
+    async fn update_proofs(
+        &self,
+        added: Vec<ProofInfo>,
+        removed_ys: Vec<PublicKey>,
+    ) -> Result<(), Self::Err> {
+        let db = self.db.lock().await;
+
+        let write_txn = db.begin_write().map_err(Error::from)?;
+
+        {
+            let mut table = write_txn.open_table(PROOFS_TABLE).map_err(Error::from)?;
+
+            for proof_info in added.iter() {
+                table
+                    .insert(
+                        proof_info.y.to_bytes().as_slice(),
+                        serde_json::to_string(&proof_info)
+                            .map_err(Error::from)?
+                            .as_str(),
+                    )
+                    .map_err(Error::from)?;
+            }
+
+            for y in removed_ys {
+                table.remove(y.to_bytes().as_slice()).map_err(Error::from)?;
+            }
+        }
+        write_txn.commit().map_err(Error::from)?;
+
+        Ok(())
+    }
+
     #[instrument(skip(self))]
     async fn set_proof_state(&self, y: PublicKey, state: State) -> Result<(), Self::Err> {
         let db = self.db.lock().await;
@@ -580,6 +623,145 @@ impl WalletDatabase for RedbWalletDatabase {
         Ok(counter.map(|c| c.value()))
     }
 
+    #[instrument(skip(self))]
+    async fn add_received_token(&self, fingerprint: String) -> Result<(), Self::Err> {
+        let db = self.db.lock().await;
+
+        let write_txn = db.begin_write().map_err(Error::from)?;
+        {
+            let mut table = write_txn
+                .open_table(RECEIVED_TOKENS_TABLE)
+                .map_err(Error::from)?;
+            table
+                .insert(fingerprint.as_str(), unix_time())
+                .map_err(Error::from)?;
+        }
+        write_txn.commit().map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn is_token_received(&self, fingerprint: &str) -> Result<bool, Self::Err> {
+        let db = self.db.lock().await;
+        let read_txn = db.begin_read().map_err(Error::from)?;
+        let table = read_txn
+            .open_table(RECEIVED_TOKENS_TABLE)
+            .map_err(Error::from)?;
+
+        Ok(table.get(fingerprint).map_err(Error::from)?.is_some())
+    }
+
+    #[instrument(skip_all)]
+    async fn add_transaction(&self, transaction: Transaction) -> Result<(), Self::Err> {
+        let db = self.db.lock().await;
+
+        let write_txn = db.begin_write().map_err(Error::from)?;
+        {
+            let mut table = write_txn
+                .open_table(TRANSACTIONS_TABLE)
+                .map_err(Error::from)?;
+            table
+                .insert(
+                    transaction.id.as_str(),
+                    serde_json::to_string(&transaction)
+                        .map_err(Error::from)?
+                        .as_str(),
+                )
+                .map_err(Error::from)?;
+        }
+        write_txn.commit().map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_transactions(&self) -> Result<Vec<Transaction>, Self::Err> {
+        let db = self.db.lock().await;
+        let read_txn = db.begin_read().map_err(Error::from)?;
+        let table = read_txn
+            .open_table(TRANSACTIONS_TABLE)
+            .map_err(Error::from)?;
+
+        Ok(table
+            .iter()
+            .map_err(Error::from)?
+            .flatten()
+            .flat_map(|(_id, transaction)| serde_json::from_str(transaction.value()))
+            .collect())
+    }
+
+    #[instrument(skip_all)]
+    async fn add_pending_mint(&self, pending_mint: PendingMint) -> Result<(), Self::Err> {
+        let db = self.db.lock().await;
+
+        let write_txn = db.begin_write().map_err(Error::from)?;
+        {
+            let mut table = write_txn
+                .open_table(PENDING_MINTS_TABLE)
+                .map_err(Error::from)?;
+            table
+                .insert(
+                    pending_mint.quote_id.as_str(),
+                    serde_json::to_string(&pending_mint)
+                        .map_err(Error::from)?
+                        .as_str(),
+                )
+                .map_err(Error::from)?;
+        }
+        write_txn.commit().map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_pending_mint(&self, quote_id: &str) -> Result<Option<PendingMint>, Self::Err> {
+        let db = self.db.lock().await;
+        let read_txn = db.begin_read().map_err(Error::from)?;
+        let table = read_txn
+            .open_table(PENDING_MINTS_TABLE)
+            .map_err(Error::from)?;
+
+        if let Some(pending_mint) = table.get(quote_id).map_err(Error::from)? {
+            return Ok(serde_json::from_str(pending_mint.value()).map_err(Error::from)?);
+        }
+
+        Ok(None)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_pending_mints(&self) -> Result<Vec<PendingMint>, Self::Err> {
+        let db = self.db.lock().await;
+        let read_txn = db.begin_read().map_err(Error::from)?;
+        let table = read_txn
+            .open_table(PENDING_MINTS_TABLE)
+            .map_err(Error::from)?;
+
+        Ok(table
+            .iter()
+            .map_err(Error::from)?
+            .flatten()
+            .flat_map(|(_id, pending_mint)| serde_json::from_str(pending_mint.value()))
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    async fn remove_pending_mint(&self, quote_id: &str) -> Result<(), Self::Err> {
+        let db = self.db.lock().await;
+        let write_txn = db.begin_write().map_err(Error::from)?;
+
+        {
+            let mut table = write_txn
+                .open_table(PENDING_MINTS_TABLE)
+                .map_err(Error::from)?;
+            table.remove(quote_id).map_err(Error::from)?;
+        }
+
+        write_txn.commit().map_err(Error::from)?;
+
+        Ok(())
+    }
+
     #[cfg(feature = "nostr")]
     #[instrument(skip(self))]
     async fn get_nostr_last_checked(
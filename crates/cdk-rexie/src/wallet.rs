@@ -4,11 +4,12 @@ use std::result::Result;
 
 use async_trait::async_trait;
 use cdk::cdk_database::WalletDatabase;
+use cdk::mint_url::MintUrl;
 use cdk::nuts::{
     CurrencyUnit, Id, KeySetInfo, Keys, MintInfo, Proofs, PublicKey, SpendingConditions, State,
 };
-use cdk::types::{MeltQuote, MintQuote, ProofInfo};
-use cdk::url::UncheckedUrl;
+use cdk::types::{MeltQuote, MintQuote, PendingMint, ProofInfo, Transaction};
+use cdk::util::unix_time;
 use rexie::*;
 use thiserror::Error;
 use tokio::sync::Mutex;
@@ -23,8 +24,11 @@ const MELT_QUOTES: &str = "melt_quotes";
 const PROOFS: &str = "proofs";
 const CONFIG: &str = "config";
 const KEYSET_COUNTER: &str = "keyset_counter";
+const RECEIVED_TOKENS: &str = "received_tokens";
+const TRANSACTIONS: &str = "transactions";
+const PENDING_MINTS: &str = "pending_mints";
 
-const DATABASE_VERSION: u32 = 2;
+const DATABASE_VERSION: u32 = 5;
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -39,6 +43,9 @@ pub enum Error {
     SerdeBindgen(#[from] serde_wasm_bindgen::Error),
     #[error(transparent)]
     NUT00(cdk::nuts::nut00::Error),
+    /// NUT02 Error
+    #[error(transparent)]
+    NUT02(#[from] cdk::nuts::nut02::Error),
 }
 impl From<Error> for cdk::cdk_database::Error {
     fn from(e: Error) -> Self {
@@ -86,6 +93,9 @@ impl RexieWalletDatabase {
             .add_object_store(ObjectStore::new(MELT_QUOTES))
             .add_object_store(ObjectStore::new(CONFIG))
             .add_object_store(ObjectStore::new(KEYSET_COUNTER))
+            .add_object_store(ObjectStore::new(RECEIVED_TOKENS))
+            .add_object_store(ObjectStore::new(TRANSACTIONS))
+            .add_object_store(ObjectStore::new(PENDING_MINTS))
             // Build the database
             .build()
             .await
@@ -104,7 +114,7 @@ impl WalletDatabase for RexieWalletDatabase {
 
     async fn add_mint(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         mint_info: Option<MintInfo>,
     ) -> Result<(), Self::Err> {
         let rexie = self.db.lock().await;
@@ -128,7 +138,7 @@ impl WalletDatabase for RexieWalletDatabase {
         Ok(())
     }
 
-    async fn get_mint(&self, mint_url: UncheckedUrl) -> Result<Option<MintInfo>, Self::Err> {
+    async fn get_mint(&self, mint_url: MintUrl) -> Result<Option<MintInfo>, Self::Err> {
         let rexie = self.db.lock().await;
 
         let transaction = rexie
@@ -146,7 +156,7 @@ impl WalletDatabase for RexieWalletDatabase {
         Ok(mint_info)
     }
 
-    async fn get_mints(&self) -> Result<HashMap<UncheckedUrl, Option<MintInfo>>, Self::Err> {
+    async fn get_mints(&self) -> Result<HashMap<MintUrl, Option<MintInfo>>, Self::Err> {
         let rexie = self.db.lock().await;
 
         let transaction = rexie
@@ -160,7 +170,7 @@ impl WalletDatabase for RexieWalletDatabase {
             .await
             .map_err(Error::from)?;
 
-        let mints: HashMap<UncheckedUrl, Option<MintInfo>> = mints
+        let mints: HashMap<MintUrl, Option<MintInfo>> = mints
             .into_iter()
             .map(|(url, info)| {
                 (
@@ -175,7 +185,7 @@ impl WalletDatabase for RexieWalletDatabase {
 
     async fn add_mint_keysets(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         keysets: Vec<KeySetInfo>,
     ) -> Result<(), Self::Err> {
         let rexie = self.db.lock().await;
@@ -228,7 +238,7 @@ impl WalletDatabase for RexieWalletDatabase {
 
     async fn get_mint_keysets(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
     ) -> Result<Option<Vec<KeySetInfo>>, Self::Err> {
         let rexie = self.db.lock().await;
 
@@ -430,7 +440,8 @@ impl WalletDatabase for RexieWalletDatabase {
 
         let keys_store = transaction.store(MINT_KEYS).map_err(Error::from)?;
 
-        let keyset_id = serde_wasm_bindgen::to_value(&Id::from(&keys)).map_err(Error::from)?;
+        let keyset_id = Id::try_from(&keys).map_err(Error::from)?;
+        let keyset_id = serde_wasm_bindgen::to_value(&keyset_id).map_err(Error::from)?;
         let keys = serde_wasm_bindgen::to_value(&keys).map_err(Error::from)?;
 
         keys_store
@@ -502,7 +513,7 @@ impl WalletDatabase for RexieWalletDatabase {
 
     async fn get_proofs(
         &self,
-        mint_url: Option<UncheckedUrl>,
+        mint_url: Option<MintUrl>,
         unit: Option<CurrencyUnit>,
         state: Option<Vec<State>>,
         spending_conditions: Option<Vec<SpendingConditions>>,
@@ -570,6 +581,41 @@ impl WalletDatabase for RexieWalletDatabase {
         Ok(())
     }
 
+    async fn update_proofs(
+        &self,
+        added: Vec<ProofInfo>,
+        removed_ys: Vec<PublicKey>,
+    ) -> Result<(), Self::Err> {
+        let rexie = self.db.lock().await;
+
+        let transaction = rexie
+            .transaction(&[PROOFS], TransactionMode::ReadWrite)
+            .map_err(Error::from)?;
+
+        let proofs_store = transaction.store(PROOFS).map_err(Error::from)?;
+
+        for proof in added {
+            let y = proof.y;
+            let y = serde_wasm_bindgen::to_value(&y).map_err(Error::from)?;
+            let proof = serde_wasm_bindgen::to_value(&proof).map_err(Error::from)?;
+
+            proofs_store
+                .put(&proof, Some(&y))
+                .await
+                .map_err(Error::from)?;
+        }
+
+        for y in removed_ys {
+            let y = serde_wasm_bindgen::to_value(&y).map_err(Error::from)?;
+
+            proofs_store.delete(&y).await.map_err(Error::from)?;
+        }
+
+        transaction.done().await.map_err(Error::from)?;
+
+        Ok(())
+    }
+
     async fn set_proof_state(&self, y: PublicKey, state: State) -> Result<(), Self::Err> {
         let rexie = self.db.lock().await;
 
@@ -644,4 +690,172 @@ impl WalletDatabase for RexieWalletDatabase {
 
         Ok(current_count)
     }
+
+    async fn add_received_token(&self, fingerprint: String) -> Result<(), Self::Err> {
+        let rexie = self.db.lock().await;
+
+        let transaction = rexie
+            .transaction(&[RECEIVED_TOKENS], TransactionMode::ReadWrite)
+            .map_err(Error::from)?;
+
+        let received_tokens_store = transaction.store(RECEIVED_TOKENS).map_err(Error::from)?;
+
+        let fingerprint = serde_wasm_bindgen::to_value(&fingerprint).map_err(Error::from)?;
+        let received_at = serde_wasm_bindgen::to_value(&unix_time()).map_err(Error::from)?;
+
+        received_tokens_store
+            .put(&received_at, Some(&fingerprint))
+            .await
+            .map_err(Error::from)?;
+
+        transaction.done().await.map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    async fn is_token_received(&self, fingerprint: &str) -> Result<bool, Self::Err> {
+        let rexie = self.db.lock().await;
+
+        let transaction = rexie
+            .transaction(&[RECEIVED_TOKENS], TransactionMode::ReadOnly)
+            .map_err(Error::from)?;
+
+        let received_tokens_store = transaction.store(RECEIVED_TOKENS).map_err(Error::from)?;
+
+        let fingerprint = serde_wasm_bindgen::to_value(fingerprint).map_err(Error::from)?;
+        let received_at = received_tokens_store
+            .get(&fingerprint)
+            .await
+            .map_err(Error::from)?;
+
+        transaction.done().await.map_err(Error::from)?;
+
+        Ok(!received_at.is_undefined())
+    }
+
+    async fn add_transaction(&self, transaction: Transaction) -> Result<(), Self::Err> {
+        let rexie = self.db.lock().await;
+
+        let db_transaction = rexie
+            .transaction(&[TRANSACTIONS], TransactionMode::ReadWrite)
+            .map_err(Error::from)?;
+
+        let transactions_store = db_transaction.store(TRANSACTIONS).map_err(Error::from)?;
+
+        let id = serde_wasm_bindgen::to_value(&transaction.id).map_err(Error::from)?;
+        let transaction = serde_wasm_bindgen::to_value(&transaction).map_err(Error::from)?;
+
+        transactions_store
+            .put(&transaction, Some(&id))
+            .await
+            .map_err(Error::from)?;
+
+        db_transaction.done().await.map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    async fn get_transactions(&self) -> Result<Vec<Transaction>, Self::Err> {
+        let rexie = self.db.lock().await;
+
+        let transaction = rexie
+            .transaction(&[TRANSACTIONS], TransactionMode::ReadOnly)
+            .map_err(Error::from)?;
+
+        let transactions_store = transaction.store(TRANSACTIONS).map_err(Error::from)?;
+
+        let transactions = transactions_store
+            .get_all(None, None, None, None)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(transactions
+            .into_iter()
+            .flat_map(|(_id, t)| serde_wasm_bindgen::from_value(t))
+            .collect())
+    }
+
+    async fn add_pending_mint(&self, pending_mint: PendingMint) -> Result<(), Self::Err> {
+        let rexie = self.db.lock().await;
+
+        let transaction = rexie
+            .transaction(&[PENDING_MINTS], TransactionMode::ReadWrite)
+            .map_err(Error::from)?;
+
+        let pending_mints_store = transaction.store(PENDING_MINTS).map_err(Error::from)?;
+
+        let quote_id = serde_wasm_bindgen::to_value(&pending_mint.quote_id).map_err(Error::from)?;
+        let pending_mint = serde_wasm_bindgen::to_value(&pending_mint).map_err(Error::from)?;
+
+        pending_mints_store
+            .put(&pending_mint, Some(&quote_id))
+            .await
+            .map_err(Error::from)?;
+
+        transaction.done().await.map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    async fn get_pending_mint(&self, quote_id: &str) -> Result<Option<PendingMint>, Self::Err> {
+        let rexie = self.db.lock().await;
+
+        let transaction = rexie
+            .transaction(&[PENDING_MINTS], TransactionMode::ReadOnly)
+            .map_err(Error::from)?;
+
+        let pending_mints_store = transaction.store(PENDING_MINTS).map_err(Error::from)?;
+
+        let quote_id = serde_wasm_bindgen::to_value(&quote_id).map_err(Error::from)?;
+        let pending_mint = pending_mints_store
+            .get(&quote_id)
+            .await
+            .map_err(Error::from)?;
+
+        let pending_mint: Option<PendingMint> =
+            serde_wasm_bindgen::from_value(pending_mint).map_err(Error::from)?;
+
+        Ok(pending_mint)
+    }
+
+    async fn get_pending_mints(&self) -> Result<Vec<PendingMint>, Self::Err> {
+        let rexie = self.db.lock().await;
+
+        let transaction = rexie
+            .transaction(&[PENDING_MINTS], TransactionMode::ReadOnly)
+            .map_err(Error::from)?;
+
+        let pending_mints_store = transaction.store(PENDING_MINTS).map_err(Error::from)?;
+
+        let pending_mints = pending_mints_store
+            .get_all(None, None, None, None)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(pending_mints
+            .into_iter()
+            .flat_map(|(_id, p)| serde_wasm_bindgen::from_value(p))
+            .collect())
+    }
+
+    async fn remove_pending_mint(&self, quote_id: &str) -> Result<(), Self::Err> {
+        let rexie = self.db.lock().await;
+
+        let transaction = rexie
+            .transaction(&[PENDING_MINTS], TransactionMode::ReadWrite)
+            .map_err(Error::from)?;
+
+        let pending_mints_store = transaction.store(PENDING_MINTS).map_err(Error::from)?;
+
+        let quote_id = serde_wasm_bindgen::to_value(&quote_id).map_err(Error::from)?;
+
+        pending_mints_store
+            .delete(&quote_id)
+            .await
+            .map_err(Error::from)?;
+
+        transaction.done().await.map_err(Error::from)?;
+
+        Ok(())
+    }
 }
@@ -5,6 +5,12 @@ pub enum Error {
     /// SQLX Error
     #[error(transparent)]
     SQLX(#[from] sqlx::Error),
+    /// Serde Error
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+    /// NUT05 Error
+    #[error(transparent)]
+    CDKNUT05(#[from] cdk::nuts::nut05::Error),
     /// NUT02 Error
     #[error(transparent)]
     CDKNUT02(#[from] cdk::nuts::nut02::Error),
@@ -14,6 +20,9 @@ pub enum Error {
     /// Secret Error
     #[error(transparent)]
     CDKSECRET(#[from] cdk::secret::Error),
+    /// Mint Url Error
+    #[error(transparent)]
+    MintUrl(#[from] cdk::mint_url::Error),
     /// BIP32 Error
     #[error(transparent)]
     BIP32(#[from] bitcoin::bip32::Error),
@@ -0,0 +1,3 @@
+-- Exchange rate locked for fiat quotes
+ALTER TABLE mint_quote ADD COLUMN exchange_rate INTEGER;
+ALTER TABLE melt_quote ADD COLUMN exchange_rate INTEGER;
@@ -0,0 +1,6 @@
+-- Responses of executed melts, a melt quote is only executed once
+CREATE TABLE IF NOT EXISTS completed_melt (
+    quote_id TEXT PRIMARY KEY,
+    inputs TEXT NOT NULL,
+    response TEXT NOT NULL
+);
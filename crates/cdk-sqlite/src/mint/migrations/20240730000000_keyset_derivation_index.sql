@@ -0,0 +1 @@
+ALTER TABLE keyset ADD COLUMN derivation_path_index INTEGER;
@@ -0,0 +1,5 @@
+-- Fees of executed melts for accounting
+ALTER TABLE completed_melt ADD COLUMN keyset_id TEXT;
+ALTER TABLE completed_melt ADD COLUMN fee_paid INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE completed_melt ADD COLUMN burnt_change INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE completed_melt ADD COLUMN time INTEGER NOT NULL DEFAULT 0;
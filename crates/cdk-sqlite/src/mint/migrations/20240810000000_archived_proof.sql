@@ -0,0 +1,10 @@
+-- Spent proofs of old keysets moved out of the proof table
+CREATE TABLE IF NOT EXISTS archived_proof (
+y BLOB PRIMARY KEY
+);
+
+-- Amount of the archived proofs of each keyset for accounting
+CREATE TABLE IF NOT EXISTS archived_amount (
+keyset_id TEXT PRIMARY KEY,
+amount INTEGER NOT NULL
+);
@@ -0,0 +1,3 @@
+-- Payment hash of the bolt11 request, to settle quotes of paid invoices
+ALTER TABLE mint_quote ADD COLUMN payment_hash TEXT;
+CREATE INDEX IF NOT EXISTS mint_quote_payment_hash_index ON mint_quote(payment_hash);
@@ -0,0 +1,2 @@
+-- Ys of the inputs a melt quote is pending with, as a JSON array
+ALTER TABLE melt_quote ADD COLUMN pending_inputs TEXT;
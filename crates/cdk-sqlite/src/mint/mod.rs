@@ -7,9 +7,10 @@ use async_trait::async_trait;
 use bitcoin::bip32::DerivationPath;
 use cdk::cdk_database::{self, MintDatabase};
 use cdk::mint::MintKeySetInfo;
-use cdk::nuts::{BlindSignature, CurrencyUnit, Id, Proof, PublicKey};
+use cdk::mint_url::MintUrl;
+use cdk::nuts::{BlindSignature, CurrencyUnit, Id, MeltQuoteState, Proof, Proofs, PublicKey};
 use cdk::secret::Secret;
-use cdk::types::{MeltQuote, MintQuote};
+use cdk::types::{CompletedMelt, MeltQuote, MintQuote};
 use cdk::Amount;
 use error::Error;
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqliteRow};
@@ -43,6 +44,18 @@ impl MintSqliteDatabase {
             .await
             .expect("Could not run migrations");
     }
+
+    /// Write a consistent snapshot of the database to `path`
+    ///
+    /// Safe to call while the mint is running. Fails if `path` already exists.
+    pub async fn backup(&self, path: &str) -> Result<(), Error> {
+        sqlx::query("VACUUM INTO ?;")
+            .bind(path)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -120,17 +133,19 @@ WHERE active = 1
         sqlx::query(
             r#"
 INSERT OR REPLACE INTO mint_quote
-(id, mint_url, amount, unit, request, paid, expiry)
-VALUES (?, ?, ?, ?, ?, ?, ?);
+(id, mint_url, amount, unit, request, paid, expiry, exchange_rate, payment_hash)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?);
         "#,
         )
         .bind(quote.id.to_string())
         .bind(quote.mint_url.to_string())
         .bind(u64::from(quote.amount) as i64)
         .bind(quote.unit.to_string())
-        .bind(quote.request)
+        .bind(quote.request.clone())
         .bind(quote.paid)
         .bind(quote.expiry as i64)
+        .bind(quote.exchange_rate.map(|r| r as i64))
+        .bind(quote.payment_hash())
         .execute(&self.pool)
         .await
         // TODO: should check if error is not found and return none
@@ -160,6 +175,73 @@ WHERE id=?;
 
         Ok(Some(sqlite_row_to_mint_quote(rec)?))
     }
+    async fn get_mint_quote_by_request(
+        &self,
+        request: &str,
+    ) -> Result<Option<MintQuote>, Self::Err> {
+        let rec = sqlx::query(
+            r#"
+SELECT *
+FROM mint_quote
+WHERE request=?;
+        "#,
+        )
+        .bind(request)
+        .fetch_one(&self.pool)
+        .await;
+
+        let rec = match rec {
+            Ok(rec) => rec,
+            Err(err) => match err {
+                sqlx::Error::RowNotFound => return Ok(None),
+                _ => return Err(Error::SQLX(err).into()),
+            },
+        };
+
+        Ok(Some(sqlite_row_to_mint_quote(rec)?))
+    }
+    async fn get_mint_quote_by_payment_hash(
+        &self,
+        payment_hash: &str,
+    ) -> Result<Option<MintQuote>, Self::Err> {
+        let rec = sqlx::query(
+            r#"
+SELECT *
+FROM mint_quote
+WHERE payment_hash=?;
+        "#,
+        )
+        .bind(payment_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::from)?;
+
+        if let Some(rec) = rec {
+            return Ok(Some(sqlite_row_to_mint_quote(rec)?));
+        }
+
+        // Quotes stored before the payment hash column was added
+        let recs = sqlx::query(
+            r#"
+SELECT *
+FROM mint_quote
+WHERE payment_hash IS NULL;
+        "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::from)?;
+
+        for rec in recs {
+            let quote = sqlite_row_to_mint_quote(rec)?;
+            if quote.payment_hash().as_deref() == Some(payment_hash) {
+                return Ok(Some(quote));
+            }
+        }
+
+        Ok(None)
+    }
+
     async fn get_mint_quotes(&self) -> Result<Vec<MintQuote>, Self::Err> {
         let rec = sqlx::query(
             r#"
@@ -194,8 +276,8 @@ WHERE id=?
         sqlx::query(
             r#"
 INSERT OR REPLACE INTO melt_quote
-(id, unit, amount, request, fee_reserve, paid, expiry)
-VALUES (?, ?, ?, ?, ?, ?, ?);
+(id, unit, amount, request, fee_reserve, paid, state, expiry, exchange_rate, pending_inputs)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
         "#,
         )
         .bind(quote.id.to_string())
@@ -203,8 +285,11 @@ VALUES (?, ?, ?, ?, ?, ?, ?);
         .bind(u64::from(quote.amount) as i64)
         .bind(quote.request)
         .bind(u64::from(quote.fee_reserve) as i64)
-        .bind(quote.paid)
+        .bind(quote.state == MeltQuoteState::Paid)
+        .bind(quote.state.to_string())
         .bind(quote.expiry as i64)
+        .bind(quote.exchange_rate.map(|r| r as i64))
+        .bind(serde_json::to_string(&quote.pending_inputs).map_err(Error::from)?)
         .execute(&self.pool)
         .await
         .map_err(Error::from)?;
@@ -263,12 +348,76 @@ WHERE id=?
         Ok(())
     }
 
-    async fn add_keyset_info(&self, keyset: MintKeySetInfo) -> Result<(), Self::Err> {
+    async fn add_completed_melt(&self, melt: CompletedMelt) -> Result<(), Self::Err> {
         sqlx::query(
             r#"
-INSERT INTO keyset
-(id, unit, active, valid_from, valid_to, derivation_path, max_order)
+INSERT OR REPLACE INTO completed_melt
+(quote_id, inputs, response, keyset_id, fee_paid, burnt_change, time)
 VALUES (?, ?, ?, ?, ?, ?, ?);
+        "#,
+        )
+        .bind(melt.quote_id)
+        .bind(serde_json::to_string(&melt.inputs).map_err(Error::from)?)
+        .bind(serde_json::to_string(&melt.response).map_err(Error::from)?)
+        .bind(melt.change_keyset_id.map(|id| id.to_string()))
+        .bind(u64::from(melt.fee_paid) as i64)
+        .bind(u64::from(melt.burnt_change) as i64)
+        .bind(melt.time as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    async fn get_completed_melt(&self, quote_id: &str) -> Result<Option<CompletedMelt>, Self::Err> {
+        let rec = sqlx::query(
+            r#"
+SELECT *
+FROM completed_melt
+WHERE quote_id=?;
+        "#,
+        )
+        .bind(quote_id)
+        .fetch_one(&self.pool)
+        .await;
+
+        let rec = match rec {
+            Ok(rec) => rec,
+            Err(err) => match err {
+                sqlx::Error::RowNotFound => return Ok(None),
+                _ => return Err(Error::SQLX(err).into()),
+            },
+        };
+
+        Ok(Some(sqlite_row_to_completed_melt(rec)?))
+    }
+
+    async fn get_completed_melts(&self) -> Result<Vec<CompletedMelt>, Self::Err> {
+        let recs = sqlx::query(
+            r#"
+SELECT *
+FROM completed_melt;
+        "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::from)?;
+
+        let melts = recs
+            .into_iter()
+            .map(sqlite_row_to_completed_melt)
+            .collect::<Result<Vec<CompletedMelt>, _>>()?;
+
+        Ok(melts)
+    }
+
+    async fn add_keyset_info(&self, keyset: MintKeySetInfo) -> Result<(), Self::Err> {
+        sqlx::query(
+            r#"
+INSERT OR REPLACE INTO keyset
+(id, unit, active, valid_from, valid_to, derivation_path, max_order, derivation_path_index)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?);
         "#,
         )
         .bind(keyset.id.to_string())
@@ -278,6 +427,7 @@ VALUES (?, ?, ?, ?, ?, ?, ?);
         .bind(keyset.valid_to.map(|v| v as i64))
         .bind(keyset.derivation_path.to_string())
         .bind(keyset.max_order)
+        .bind(keyset.derivation_path_index.map(|i| i as i64))
         .execute(&self.pool)
         .await
         .map_err(Error::from)?;
@@ -391,6 +541,129 @@ AND state="SPENT";
         Ok(Some(sqlite_row_to_proof(rec)?))
     }
 
+    async fn get_spent_proofs(&self) -> Result<Proofs, Self::Err> {
+        let rec = sqlx::query(
+            r#"
+SELECT *
+FROM proof
+WHERE state="SPENT";
+        "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::from)?;
+
+        let proofs = rec
+            .into_iter()
+            .map(sqlite_row_to_proof)
+            .collect::<Result<Proofs, _>>()?;
+
+        Ok(proofs)
+    }
+
+    async fn archive_spent_proofs(&self, keyset_id: &Id) -> Result<u64, Self::Err> {
+        // The transaction is rolled back when dropped without commit
+        let mut transaction = self.pool.begin().await.map_err(Error::from)?;
+
+        let rec = sqlx::query(
+            r#"
+SELECT COUNT(*) AS count, COALESCE(SUM(amount), 0) AS amount
+FROM proof
+WHERE keyset_id=?
+AND state="SPENT";
+        "#,
+        )
+        .bind(keyset_id.to_string())
+        .fetch_one(&mut transaction)
+        .await
+        .map_err(Error::from)?;
+        let count: i64 = rec.try_get("count").map_err(Error::from)?;
+        let amount: i64 = rec.try_get("amount").map_err(Error::from)?;
+
+        sqlx::query(
+            r#"
+INSERT OR IGNORE INTO archived_proof (y)
+SELECT y
+FROM proof
+WHERE keyset_id=?
+AND state="SPENT";
+        "#,
+        )
+        .bind(keyset_id.to_string())
+        .execute(&mut transaction)
+        .await
+        .map_err(Error::from)?;
+
+        sqlx::query(
+            r#"
+DELETE FROM proof
+WHERE keyset_id=?
+AND state="SPENT";
+        "#,
+        )
+        .bind(keyset_id.to_string())
+        .execute(&mut transaction)
+        .await
+        .map_err(Error::from)?;
+
+        sqlx::query(
+            r#"
+INSERT INTO archived_amount (keyset_id, amount)
+VALUES (?, ?)
+ON CONFLICT(keyset_id) DO UPDATE SET amount = amount + excluded.amount;
+        "#,
+        )
+        .bind(keyset_id.to_string())
+        .bind(amount)
+        .execute(&mut transaction)
+        .await
+        .map_err(Error::from)?;
+
+        transaction.commit().await.map_err(Error::from)?;
+
+        Ok(count as u64)
+    }
+
+    async fn is_archived_spent_proof(&self, y: &PublicKey) -> Result<bool, Self::Err> {
+        let rec = sqlx::query(
+            r#"
+SELECT y
+FROM archived_proof
+WHERE y=?;
+        "#,
+        )
+        .bind(y.to_bytes().to_vec())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::from)?;
+
+        Ok(rec.is_some())
+    }
+
+    async fn get_archived_amounts(&self) -> Result<HashMap<Id, Amount>, Self::Err> {
+        let recs = sqlx::query(
+            r#"
+SELECT *
+FROM archived_amount;
+        "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::from)?;
+
+        let mut amounts = HashMap::new();
+        for rec in recs {
+            let keyset_id: String = rec.try_get("keyset_id").map_err(Error::from)?;
+            let amount: i64 = rec.try_get("amount").map_err(Error::from)?;
+            amounts.insert(
+                Id::from_str(&keyset_id).map_err(Error::from)?,
+                Amount::from(amount as u64),
+            );
+        }
+
+        Ok(amounts)
+    }
+
     async fn add_pending_proof(&self, proof: Proof) -> Result<(), Self::Err> {
         sqlx::query(
             r#"
@@ -551,6 +824,271 @@ WHERE y=?;
 
         Ok(signatures)
     }
+
+    async fn get_all_blinded_signatures(&self) -> Result<Vec<BlindSignature>, Self::Err> {
+        let rec = sqlx::query(
+            r#"
+SELECT *
+FROM blind_signature;
+        "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::from)?;
+
+        let signatures = rec
+            .into_iter()
+            .map(sqlite_row_to_blind_signature)
+            .collect::<Result<Vec<BlindSignature>, _>>()?;
+
+        Ok(signatures)
+    }
+
+    async fn add_spent_proofs_and_signatures(
+        &self,
+        proofs: Proofs,
+        blinded_signatures: Vec<(PublicKey, BlindSignature)>,
+    ) -> Result<(), Self::Err> {
+        // The transaction is rolled back when dropped without commit
+        let mut transaction = self.pool.begin().await.map_err(Error::from)?;
+
+        for proof in proofs {
+            let y = proof.y()?.to_bytes().to_vec();
+
+            let archived = sqlx::query(
+                r#"
+SELECT y
+FROM archived_proof
+WHERE y=?;
+        "#,
+            )
+            .bind(&y)
+            .fetch_optional(&mut transaction)
+            .await
+            .map_err(Error::from)?;
+            if archived.is_some() {
+                return Err(cdk_database::Error::ProofAlreadySpent);
+            }
+
+            // Ignored if the proof is already stored, as spent or pending
+            let result = sqlx::query(
+                r#"
+INSERT OR IGNORE INTO proof
+(y, amount, keyset_id, secret, c, witness, state)
+VALUES (?, ?, ?, ?, ?, ?, ?);
+        "#,
+            )
+            .bind(y)
+            .bind(u64::from(proof.amount) as i64)
+            .bind(proof.keyset_id.to_string())
+            .bind(proof.secret.to_string())
+            .bind(proof.c.to_bytes().to_vec())
+            .bind(proof.witness.map(|w| serde_json::to_string(&w).unwrap()))
+            .bind("SPENT")
+            .execute(&mut transaction)
+            .await
+            .map_err(Error::from)?;
+            if result.rows_affected() != 1 {
+                return Err(cdk_database::Error::ProofAlreadySpent);
+            }
+        }
+
+        for (blinded_message, blinded_signature) in blinded_signatures {
+            sqlx::query(
+                r#"
+INSERT INTO blind_signature
+(y, amount, keyset_id, c)
+VALUES (?, ?, ?, ?);
+        "#,
+            )
+            .bind(blinded_message.to_bytes().to_vec())
+            .bind(u64::from(blinded_signature.amount) as i64)
+            .bind(blinded_signature.keyset_id.to_string())
+            .bind(blinded_signature.c.to_bytes().to_vec())
+            .execute(&mut transaction)
+            .await
+            .map_err(Error::from)?;
+        }
+
+        transaction.commit().await.map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    async fn issue_mint_quote(
+        &self,
+        quote_id: &str,
+        blinded_signatures: Vec<(PublicKey, BlindSignature)>,
+    ) -> Result<(), Self::Err> {
+        // The transaction is rolled back when dropped without commit
+        let mut transaction = self.pool.begin().await.map_err(Error::from)?;
+
+        let result = sqlx::query(
+            r#"
+DELETE FROM mint_quote
+WHERE id=?
+        "#,
+        )
+        .bind(quote_id)
+        .execute(&mut transaction)
+        .await
+        .map_err(Error::from)?;
+        if result.rows_affected() != 1 {
+            return Err(cdk_database::Error::UnknownQuote);
+        }
+
+        for (blinded_message, blinded_signature) in blinded_signatures {
+            sqlx::query(
+                r#"
+INSERT INTO blind_signature
+(y, amount, keyset_id, c)
+VALUES (?, ?, ?, ?);
+        "#,
+            )
+            .bind(blinded_message.to_bytes().to_vec())
+            .bind(u64::from(blinded_signature.amount) as i64)
+            .bind(blinded_signature.keyset_id.to_string())
+            .bind(blinded_signature.c.to_bytes().to_vec())
+            .execute(&mut transaction)
+            .await
+            .map_err(Error::from)?;
+        }
+
+        transaction.commit().await.map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    async fn complete_melt(
+        &self,
+        inputs: Proofs,
+        blinded_signatures: Vec<(PublicKey, BlindSignature)>,
+        melt: CompletedMelt,
+        quote: MeltQuote,
+        mint_quote: Option<MintQuote>,
+    ) -> Result<(), Self::Err> {
+        // The transaction is rolled back when dropped without commit
+        let mut transaction = self.pool.begin().await.map_err(Error::from)?;
+
+        for proof in inputs {
+            let y = proof.y()?.to_bytes().to_vec();
+
+            let archived = sqlx::query(
+                r#"
+SELECT y
+FROM archived_proof
+WHERE y=?;
+        "#,
+            )
+            .bind(&y)
+            .fetch_optional(&mut transaction)
+            .await
+            .map_err(Error::from)?;
+            if archived.is_some() {
+                return Err(cdk_database::Error::ProofAlreadySpent);
+            }
+
+            // A pending input is marked spent, a spent one is left unchanged
+            let result = sqlx::query(
+                r#"
+INSERT INTO proof
+(y, amount, keyset_id, secret, c, witness, state)
+VALUES (?, ?, ?, ?, ?, ?, ?)
+ON CONFLICT(y) DO UPDATE SET state=excluded.state
+WHERE state=?;
+        "#,
+            )
+            .bind(y)
+            .bind(u64::from(proof.amount) as i64)
+            .bind(proof.keyset_id.to_string())
+            .bind(proof.secret.to_string())
+            .bind(proof.c.to_bytes().to_vec())
+            .bind(proof.witness.map(|w| serde_json::to_string(&w).unwrap()))
+            .bind("SPENT")
+            .bind("PENDING")
+            .execute(&mut transaction)
+            .await
+            .map_err(Error::from)?;
+            if result.rows_affected() != 1 {
+                return Err(cdk_database::Error::ProofAlreadySpent);
+            }
+        }
+
+        for (blinded_message, blinded_signature) in blinded_signatures {
+            sqlx::query(
+                r#"
+INSERT INTO blind_signature
+(y, amount, keyset_id, c)
+VALUES (?, ?, ?, ?);
+        "#,
+            )
+            .bind(blinded_message.to_bytes().to_vec())
+            .bind(u64::from(blinded_signature.amount) as i64)
+            .bind(blinded_signature.keyset_id.to_string())
+            .bind(blinded_signature.c.to_bytes().to_vec())
+            .execute(&mut transaction)
+            .await
+            .map_err(Error::from)?;
+        }
+
+        sqlx::query(
+            r#"
+INSERT OR REPLACE INTO completed_melt
+(quote_id, inputs, response, keyset_id, fee_paid, burnt_change, time)
+VALUES (?, ?, ?, ?, ?, ?, ?);
+        "#,
+        )
+        .bind(melt.quote_id)
+        .bind(serde_json::to_string(&melt.inputs).map_err(Error::from)?)
+        .bind(serde_json::to_string(&melt.response).map_err(Error::from)?)
+        .bind(melt.change_keyset_id.map(|id| id.to_string()))
+        .bind(u64::from(melt.fee_paid) as i64)
+        .bind(u64::from(melt.burnt_change) as i64)
+        .bind(melt.time as i64)
+        .execute(&mut transaction)
+        .await
+        .map_err(Error::from)?;
+
+        sqlx::query(
+            r#"
+INSERT OR REPLACE INTO melt_quote
+(id, unit, amount, request, fee_reserve, paid, state, expiry, exchange_rate, pending_inputs)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
+        "#,
+        )
+        .bind(quote.id.to_string())
+        .bind(quote.unit.to_string())
+        .bind(u64::from(quote.amount) as i64)
+        .bind(quote.request)
+        .bind(u64::from(quote.fee_reserve) as i64)
+        .bind(quote.state == MeltQuoteState::Paid)
+        .bind(quote.state.to_string())
+        .bind(quote.expiry as i64)
+        .bind(quote.exchange_rate.map(|r| r as i64))
+        .bind(serde_json::to_string(&quote.pending_inputs).map_err(Error::from)?)
+        .execute(&mut transaction)
+        .await
+        .map_err(Error::from)?;
+
+        if let Some(mint_quote) = mint_quote {
+            sqlx::query(
+                r#"
+UPDATE mint_quote
+SET paid=?
+WHERE id=?;
+        "#,
+            )
+            .bind(mint_quote.paid)
+            .bind(mint_quote.id)
+            .execute(&mut transaction)
+            .await
+            .map_err(Error::from)?;
+        }
+
+        transaction.commit().await.map_err(Error::from)?;
+
+        Ok(())
+    }
 }
 
 fn sqlite_row_to_keyset_info(row: SqliteRow) -> Result<MintKeySetInfo, Error> {
@@ -561,6 +1099,8 @@ fn sqlite_row_to_keyset_info(row: SqliteRow) -> Result<MintKeySetInfo, Error> {
     let row_valid_to: Option<i64> = row.try_get("valid_to").map_err(Error::from)?;
     let row_derivation_path: String = row.try_get("derivation_path").map_err(Error::from)?;
     let row_max_order: u8 = row.try_get("max_order").map_err(Error::from)?;
+    let row_derivation_path_index: Option<i64> =
+        row.try_get("derivation_path_index").map_err(Error::from)?;
 
     Ok(MintKeySetInfo {
         id: Id::from_str(&row_id).map_err(Error::from)?,
@@ -569,6 +1109,7 @@ fn sqlite_row_to_keyset_info(row: SqliteRow) -> Result<MintKeySetInfo, Error> {
         valid_from: row_valid_from as u64,
         valid_to: row_valid_to.map(|v| v as u64),
         derivation_path: DerivationPath::from_str(&row_derivation_path).map_err(Error::from)?,
+        derivation_path_index: row_derivation_path_index.map(|i| i as u32),
         max_order: row_max_order,
     })
 }
@@ -581,15 +1122,17 @@ fn sqlite_row_to_mint_quote(row: SqliteRow) -> Result<MintQuote, Error> {
     let row_request: String = row.try_get("request").map_err(Error::from)?;
     let row_paid: bool = row.try_get("paid").map_err(Error::from)?;
     let row_expiry: i64 = row.try_get("expiry").map_err(Error::from)?;
+    let row_exchange_rate: Option<i64> = row.try_get("exchange_rate").map_err(Error::from)?;
 
     Ok(MintQuote {
         id: row_id,
-        mint_url: row_mint_url.into(),
+        mint_url: MintUrl::from_str(&row_mint_url)?,
         amount: Amount::from(row_amount as u64),
         unit: CurrencyUnit::from(row_unit),
         request: row_request,
         paid: row_paid,
         expiry: row_expiry as u64,
+        exchange_rate: row_exchange_rate.map(|r| r as u64),
     })
 }
 
@@ -599,8 +1142,10 @@ fn sqlite_row_to_melt_quote(row: SqliteRow) -> Result<MeltQuote, Error> {
     let row_amount: i64 = row.try_get("amount").map_err(Error::from)?;
     let row_request: String = row.try_get("request").map_err(Error::from)?;
     let row_fee_reserve: i64 = row.try_get("fee_reserve").map_err(Error::from)?;
-    let row_paid: bool = row.try_get("paid").map_err(Error::from)?;
+    let row_state: String = row.try_get("state").map_err(Error::from)?;
     let row_expiry: i64 = row.try_get("expiry").map_err(Error::from)?;
+    let row_exchange_rate: Option<i64> = row.try_get("exchange_rate").map_err(Error::from)?;
+    let row_pending_inputs: Option<String> = row.try_get("pending_inputs").map_err(Error::from)?;
 
     Ok(MeltQuote {
         id: row_id,
@@ -608,8 +1153,36 @@ fn sqlite_row_to_melt_quote(row: SqliteRow) -> Result<MeltQuote, Error> {
         unit: CurrencyUnit::from(row_unit),
         request: row_request,
         fee_reserve: Amount::from(row_fee_reserve as u64),
-        paid: row_paid,
+        state: MeltQuoteState::from_str(&row_state)?,
         expiry: row_expiry as u64,
+        exchange_rate: row_exchange_rate.map(|r| r as u64),
+        pending_inputs: row_pending_inputs
+            .map(|inputs| serde_json::from_str(&inputs))
+            .transpose()?
+            .unwrap_or_default(),
+    })
+}
+
+fn sqlite_row_to_completed_melt(row: SqliteRow) -> Result<CompletedMelt, Error> {
+    let row_quote_id: String = row.try_get("quote_id").map_err(Error::from)?;
+    let row_inputs: String = row.try_get("inputs").map_err(Error::from)?;
+    let row_response: String = row.try_get("response").map_err(Error::from)?;
+    let row_keyset_id: Option<String> = row.try_get("keyset_id").map_err(Error::from)?;
+    let row_fee_paid: i64 = row.try_get("fee_paid").map_err(Error::from)?;
+    let row_burnt_change: i64 = row.try_get("burnt_change").map_err(Error::from)?;
+    let row_time: i64 = row.try_get("time").map_err(Error::from)?;
+
+    Ok(CompletedMelt {
+        quote_id: row_quote_id,
+        inputs: serde_json::from_str(&row_inputs)?,
+        response: serde_json::from_str(&row_response)?,
+        change_keyset_id: row_keyset_id
+            .map(|id| Id::from_str(&id))
+            .transpose()
+            .map_err(Error::from)?,
+        fee_paid: Amount::from(row_fee_paid as u64),
+        burnt_change: Amount::from(row_burnt_change as u64),
+        time: row_time as u64,
     })
 }
 
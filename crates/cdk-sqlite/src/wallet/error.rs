@@ -14,6 +14,9 @@ pub enum Error {
     /// NUT07 Error
     #[error(transparent)]
     CDKNUT07(#[from] cdk::nuts::nut07::Error),
+    /// NUT05 Error
+    #[error(transparent)]
+    CDKNUT05(#[from] cdk::nuts::nut05::Error),
     /// NUT02 Error
     #[error(transparent)]
     CDKNUT02(#[from] cdk::nuts::nut02::Error),
@@ -23,6 +26,9 @@ pub enum Error {
     /// Secret Error
     #[error(transparent)]
     CDKSECRET(#[from] cdk::secret::Error),
+    /// Mint Url Error
+    #[error(transparent)]
+    MintUrl(#[from] cdk::mint_url::Error),
     /// BIP32 Error
     #[error(transparent)]
     BIP32(#[from] bitcoin::bip32::Error),
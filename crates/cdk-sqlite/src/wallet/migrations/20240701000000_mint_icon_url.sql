@@ -0,0 +1 @@
+ALTER TABLE mint ADD COLUMN icon_url TEXT;
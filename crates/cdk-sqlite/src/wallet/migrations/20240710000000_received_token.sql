@@ -0,0 +1,5 @@
+-- Fingerprints of received tokens
+CREATE TABLE IF NOT EXISTS received_token (
+    fingerprint TEXT PRIMARY KEY,
+    received_at INTEGER NOT NULL
+);
@@ -0,0 +1,3 @@
+-- State of melt quotes, `paid` is kept in sync for older readers
+ALTER TABLE melt_quote ADD COLUMN state TEXT CHECK ( state IN ('UNPAID', 'PENDING', 'PAID', 'FAILED' ) ) NOT NULL DEFAULT 'UNPAID';
+UPDATE melt_quote SET state = 'PAID' WHERE paid = TRUE;
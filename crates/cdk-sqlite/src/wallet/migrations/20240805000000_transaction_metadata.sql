@@ -0,0 +1,2 @@
+-- Token metadata of sent and received transactions as JSON object
+ALTER TABLE transactions ADD COLUMN metadata TEXT;
@@ -0,0 +1,7 @@
+-- Outputs of mint requests that may have been signed
+CREATE TABLE IF NOT EXISTS pending_mint (
+    quote_id TEXT PRIMARY KEY,
+    mint_url TEXT NOT NULL,
+    unit TEXT NOT NULL,
+    premint_secrets TEXT NOT NULL
+);
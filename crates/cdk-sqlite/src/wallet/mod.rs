@@ -1,17 +1,18 @@
 //! SQLite Wallet Database
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::str::FromStr;
 
 use async_trait::async_trait;
 use cdk::cdk_database::{self, WalletDatabase};
+use cdk::mint_url::MintUrl;
 use cdk::nuts::{
-    CurrencyUnit, Id, KeySetInfo, Keys, MintInfo, Proof, Proofs, PublicKey, SpendingConditions,
-    State,
+    CurrencyUnit, Id, KeySetInfo, Keys, MeltQuoteState, MintInfo, Proof, Proofs, PublicKey,
+    SpendingConditions, State,
 };
 use cdk::secret::Secret;
-use cdk::types::{MeltQuote, MintQuote, ProofInfo};
-use cdk::url::UncheckedUrl;
+use cdk::types::{MeltQuote, MintQuote, PendingMint, ProofInfo, Transaction};
+use cdk::util::unix_time;
 use cdk::Amount;
 use error::Error;
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqliteRow};
@@ -53,10 +54,10 @@ impl WalletDatabase for WalletSQLiteDatabase {
 
     async fn add_mint(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         mint_info: Option<MintInfo>,
     ) -> Result<(), Self::Err> {
-        let (name, pubkey, version, description, description_long, contact, nuts, motd) =
+        let (name, pubkey, version, description, description_long, contact, nuts, motd, icon_url) =
             match mint_info {
                 Some(mint_info) => {
                     let MintInfo {
@@ -68,6 +69,8 @@ impl WalletDatabase for WalletSQLiteDatabase {
                         contact,
                         nuts,
                         motd,
+                        icon_url,
+                        time: _,
                     } = mint_info;
 
                     (
@@ -79,16 +82,17 @@ impl WalletDatabase for WalletSQLiteDatabase {
                         contact.map(|c| serde_json::to_string(&c).ok()),
                         serde_json::to_string(&nuts).ok(),
                         motd,
+                        icon_url,
                     )
                 }
-                None => (None, None, None, None, None, None, None, None),
+                None => (None, None, None, None, None, None, None, None, None),
             };
 
         sqlx::query(
             r#"
 INSERT OR REPLACE INTO mint
-(mint_url, name, pubkey, version, description, description_long, contact, nuts, motd)
-VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?);
+(mint_url, name, pubkey, version, description, description_long, contact, nuts, motd, icon_url)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
         "#,
         )
         .bind(mint_url.to_string())
@@ -100,13 +104,14 @@ VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?);
         .bind(contact)
         .bind(nuts)
         .bind(motd)
+        .bind(icon_url)
         .execute(&self.pool)
         .await
         .map_err(Error::from)?;
 
         Ok(())
     }
-    async fn get_mint(&self, mint_url: UncheckedUrl) -> Result<Option<MintInfo>, Self::Err> {
+    async fn get_mint(&self, mint_url: MintUrl) -> Result<Option<MintInfo>, Self::Err> {
         let rec = sqlx::query(
             r#"
 SELECT *
@@ -128,7 +133,7 @@ WHERE mint_url=?;
 
         Ok(Some(sqlite_row_to_mint_info(&rec)?))
     }
-    async fn get_mints(&self) -> Result<HashMap<UncheckedUrl, Option<MintInfo>>, Self::Err> {
+    async fn get_mints(&self) -> Result<HashMap<MintUrl, Option<MintInfo>>, Self::Err> {
         let rec = sqlx::query(
             r#"
 SELECT *
@@ -146,30 +151,31 @@ FROM mint
 
                 let mint_info = sqlite_row_to_mint_info(&row).ok();
 
-                (mint_url.into(), mint_info)
+                Ok((MintUrl::from_str(&mint_url)?, mint_info))
             })
-            .collect();
+            .collect::<Result<_, Error>>()?;
 
         Ok(mints)
     }
 
     async fn add_mint_keysets(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         keysets: Vec<KeySetInfo>,
     ) -> Result<(), Self::Err> {
         for keyset in keysets {
             sqlx::query(
                 r#"
 INSERT OR REPLACE INTO keyset
-(mint_url, id, unit, active)
-VALUES (?, ?, ?, ?);
+(mint_url, id, unit, active, final_expiry)
+VALUES (?, ?, ?, ?, ?);
         "#,
             )
             .bind(mint_url.to_string())
             .bind(keyset.id.to_string())
             .bind(keyset.unit.to_string())
             .bind(keyset.active)
+            .bind(keyset.final_expiry.map(|e| e as i64))
             .execute(&self.pool)
             .await
             .map_err(Error::from)?;
@@ -179,7 +185,7 @@ VALUES (?, ?, ?, ?);
     }
     async fn get_mint_keysets(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
     ) -> Result<Option<Vec<KeySetInfo>>, Self::Err> {
         let recs = sqlx::query(
             r#"
@@ -234,8 +240,8 @@ WHERE id=?
         sqlx::query(
             r#"
 INSERT OR REPLACE INTO mint_quote
-(id, mint_url, amount, unit, request, paid, expiry)
-VALUES (?, ?, ?, ?, ?, ?, ?);
+(id, mint_url, amount, unit, request, paid, expiry, exchange_rate)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?);
         "#,
         )
         .bind(quote.id.to_string())
@@ -245,6 +251,7 @@ VALUES (?, ?, ?, ?, ?, ?, ?);
         .bind(quote.request)
         .bind(quote.paid)
         .bind(quote.expiry as i64)
+        .bind(quote.exchange_rate.map(|r| r as i64))
         .execute(&self.pool)
         .await
         .map_err(Error::from)?;
@@ -307,8 +314,8 @@ WHERE id=?
         sqlx::query(
             r#"
 INSERT OR REPLACE INTO melt_quote
-(id, unit, amount, request, fee_reserve, paid, expiry)
-VALUES (?, ?, ?, ?, ?, ?, ?);
+(id, unit, amount, request, fee_reserve, paid, state, expiry, exchange_rate)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?);
         "#,
         )
         .bind(quote.id.to_string())
@@ -316,8 +323,10 @@ VALUES (?, ?, ?, ?, ?, ?, ?);
         .bind(u64::from(quote.amount) as i64)
         .bind(quote.request)
         .bind(u64::from(quote.fee_reserve) as i64)
-        .bind(quote.paid)
+        .bind(quote.state == MeltQuoteState::Paid)
+        .bind(quote.state.to_string())
         .bind(quote.expiry as i64)
+        .bind(quote.exchange_rate.map(|r| r as i64))
         .execute(&self.pool)
         .await
         .map_err(Error::from)?;
@@ -369,7 +378,7 @@ INSERT OR REPLACE INTO key
 VALUES (?, ?);
         "#,
         )
-        .bind(Id::from(&keys).to_string())
+        .bind(Id::try_from(&keys).map_err(Error::from)?.to_string())
         .bind(serde_json::to_string(&keys).map_err(Error::from)?)
         .execute(&self.pool)
         .await
@@ -453,7 +462,7 @@ VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
     }
     async fn get_proofs(
         &self,
-        mint_url: Option<UncheckedUrl>,
+        mint_url: Option<MintUrl>,
         unit: Option<CurrencyUnit>,
         state: Option<Vec<State>>,
         spending_conditions: Option<Vec<SpendingConditions>>,
@@ -524,6 +533,64 @@ WHERE y = ?
         Ok(())
     }
 
+    async fn update_proofs(
+        &self,
+        added: Vec<ProofInfo>,
+        removed_ys: Vec<PublicKey>,
+    ) -> Result<(), Self::Err> {
+        // The transaction is rolled back when dropped without commit
+        let mut transaction = self.pool.begin().await.map_err(Error::from)?;
+
+        for proof in added {
+            sqlx::query(
+                r#"
+INSERT OR REPLACE INTO proof
+(y, mint_url, state, spending_condition, unit, amount, keyset_id, secret, c, witness)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
+        "#,
+            )
+            .bind(proof.y.to_bytes().to_vec())
+            .bind(proof.mint_url.to_string())
+            .bind(proof.state.to_string())
+            .bind(
+                proof
+                    .spending_condition
+                    .map(|s| serde_json::to_string(&s).ok()),
+            )
+            .bind(proof.unit.to_string())
+            .bind(u64::from(proof.proof.amount) as i64)
+            .bind(proof.proof.keyset_id.to_string())
+            .bind(proof.proof.secret.to_string())
+            .bind(proof.proof.c.to_bytes().to_vec())
+            .bind(
+                proof
+                    .proof
+                    .witness
+                    .map(|w| serde_json::to_string(&w).unwrap()),
+            )
+            .execute(&mut transaction)
+            .await
+            .map_err(Error::from)?;
+        }
+
+        for y in removed_ys {
+            sqlx::query(
+                r#"
+DELETE FROM proof
+WHERE y = ?
+        "#,
+            )
+            .bind(y.to_bytes().to_vec())
+            .execute(&mut transaction)
+            .await
+            .map_err(Error::from)?;
+        }
+
+        transaction.commit().await.map_err(Error::from)?;
+
+        Ok(())
+    }
+
     async fn set_proof_state(&self, y: PublicKey, state: State) -> Result<(), Self::Err> {
         sqlx::query(
             r#"
@@ -583,6 +650,161 @@ WHERE id=?;
         Ok(count)
     }
 
+    async fn add_received_token(&self, fingerprint: String) -> Result<(), Self::Err> {
+        sqlx::query(
+            r#"
+INSERT OR IGNORE INTO received_token
+(fingerprint, received_at)
+VALUES (?, ?);
+        "#,
+        )
+        .bind(fingerprint)
+        .bind(unix_time() as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    async fn is_token_received(&self, fingerprint: &str) -> Result<bool, Self::Err> {
+        let rec = sqlx::query(
+            r#"
+SELECT fingerprint
+FROM received_token
+WHERE fingerprint=?;
+        "#,
+        )
+        .bind(fingerprint)
+        .fetch_one(&self.pool)
+        .await;
+
+        match rec {
+            Ok(_) => Ok(true),
+            Err(sqlx::Error::RowNotFound) => Ok(false),
+            Err(err) => Err(Error::SQLX(err).into()),
+        }
+    }
+
+    async fn add_transaction(&self, transaction: Transaction) -> Result<(), Self::Err> {
+        sqlx::query(
+            r#"
+INSERT OR REPLACE INTO transactions
+(id, mint_url, kind, amount, fee, unit, memo, metadata, timestamp)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?);
+        "#,
+        )
+        .bind(transaction.id)
+        .bind(transaction.mint_url.to_string())
+        .bind(transaction.kind.to_string())
+        .bind(u64::from(transaction.amount) as i64)
+        .bind(u64::from(transaction.fee) as i64)
+        .bind(transaction.unit.to_string())
+        .bind(transaction.memo)
+        .bind(serde_json::to_string(&transaction.metadata).map_err(Error::from)?)
+        .bind(transaction.timestamp as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    async fn get_transactions(&self) -> Result<Vec<Transaction>, Self::Err> {
+        let recs = sqlx::query(
+            r#"
+SELECT *
+FROM transactions;
+        "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::from)?;
+
+        let transactions = recs
+            .iter()
+            .map(sqlite_row_to_transaction)
+            .collect::<Result<Vec<Transaction>, _>>()?;
+
+        Ok(transactions)
+    }
+
+    async fn add_pending_mint(&self, pending_mint: PendingMint) -> Result<(), Self::Err> {
+        sqlx::query(
+            r#"
+INSERT OR REPLACE INTO pending_mint
+(quote_id, mint_url, unit, premint_secrets)
+VALUES (?, ?, ?, ?);
+        "#,
+        )
+        .bind(pending_mint.quote_id)
+        .bind(pending_mint.mint_url.to_string())
+        .bind(pending_mint.unit.to_string())
+        .bind(serde_json::to_string(&pending_mint.premint_secrets).map_err(Error::from)?)
+        .execute(&self.pool)
+        .await
+        .map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    async fn get_pending_mint(&self, quote_id: &str) -> Result<Option<PendingMint>, Self::Err> {
+        let rec = sqlx::query(
+            r#"
+SELECT *
+FROM pending_mint
+WHERE quote_id=?;
+        "#,
+        )
+        .bind(quote_id)
+        .fetch_one(&self.pool)
+        .await;
+
+        let rec = match rec {
+            Ok(rec) => rec,
+            Err(err) => match err {
+                sqlx::Error::RowNotFound => return Ok(None),
+                _ => return Err(Error::SQLX(err).into()),
+            },
+        };
+
+        Ok(Some(sqlite_row_to_pending_mint(&rec)?))
+    }
+
+    async fn get_pending_mints(&self) -> Result<Vec<PendingMint>, Self::Err> {
+        let recs = sqlx::query(
+            r#"
+SELECT *
+FROM pending_mint;
+        "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::from)?;
+
+        let pending_mints = recs
+            .iter()
+            .map(sqlite_row_to_pending_mint)
+            .collect::<Result<Vec<PendingMint>, _>>()?;
+
+        Ok(pending_mints)
+    }
+
+    async fn remove_pending_mint(&self, quote_id: &str) -> Result<(), Self::Err> {
+        sqlx::query(
+            r#"
+DELETE FROM pending_mint
+WHERE quote_id=?
+        "#,
+        )
+        .bind(quote_id)
+        .execute(&self.pool)
+        .await
+        .map_err(Error::from)?;
+
+        Ok(())
+    }
+
     #[cfg(feature = "nostr")]
     async fn get_nostr_last_checked(
         &self,
@@ -644,6 +866,7 @@ fn sqlite_row_to_mint_info(row: &SqliteRow) -> Result<MintInfo, Error> {
     let row_contact: Option<String> = row.try_get("contact").map_err(Error::from)?;
     let row_nuts: Option<String> = row.try_get("nuts").map_err(Error::from)?;
     let motd: Option<String> = row.try_get("motd").map_err(Error::from)?;
+    let icon_url: Option<String> = row.try_get("icon_url").map_err(Error::from)?;
 
     Ok(MintInfo {
         name,
@@ -656,6 +879,8 @@ fn sqlite_row_to_mint_info(row: &SqliteRow) -> Result<MintInfo, Error> {
             .and_then(|n| serde_json::from_str(&n).ok())
             .unwrap_or_default(),
         motd,
+        icon_url,
+        time: None,
     })
 }
 
@@ -663,11 +888,13 @@ fn sqlite_row_to_keyset(row: &SqliteRow) -> Result<KeySetInfo, Error> {
     let row_id: String = row.try_get("id").map_err(Error::from)?;
     let row_unit: String = row.try_get("unit").map_err(Error::from)?;
     let active: bool = row.try_get("active").map_err(Error::from)?;
+    let final_expiry: Option<i64> = row.try_get("final_expiry").map_err(Error::from)?;
 
     Ok(KeySetInfo {
         id: Id::from_str(&row_id)?,
         unit: CurrencyUnit::from(row_unit),
         active,
+        final_expiry: final_expiry.map(|e| e as u64),
     })
 }
 
@@ -679,15 +906,58 @@ fn sqlite_row_to_mint_quote(row: &SqliteRow) -> Result<MintQuote, Error> {
     let row_request: String = row.try_get("request").map_err(Error::from)?;
     let row_paid: bool = row.try_get("paid").map_err(Error::from)?;
     let row_expiry: i64 = row.try_get("expiry").map_err(Error::from)?;
+    let row_exchange_rate: Option<i64> = row.try_get("exchange_rate").map_err(Error::from)?;
 
     Ok(MintQuote {
         id: row_id,
-        mint_url: row_mint_url.into(),
+        mint_url: MintUrl::from_str(&row_mint_url)?,
         amount: Amount::from(row_amount as u64),
         unit: CurrencyUnit::from(row_unit),
         request: row_request,
         paid: row_paid,
         expiry: row_expiry as u64,
+        exchange_rate: row_exchange_rate.map(|r| r as u64),
+    })
+}
+
+fn sqlite_row_to_transaction(row: &SqliteRow) -> Result<Transaction, Error> {
+    let row_id: String = row.try_get("id").map_err(Error::from)?;
+    let row_mint_url: String = row.try_get("mint_url").map_err(Error::from)?;
+    let row_kind: String = row.try_get("kind").map_err(Error::from)?;
+    let row_amount: i64 = row.try_get("amount").map_err(Error::from)?;
+    let row_fee: i64 = row.try_get("fee").map_err(Error::from)?;
+    let row_unit: String = row.try_get("unit").map_err(Error::from)?;
+    let row_memo: Option<String> = row.try_get("memo").map_err(Error::from)?;
+    let row_metadata: Option<String> = row.try_get("metadata").map_err(Error::from)?;
+    let row_timestamp: i64 = row.try_get("timestamp").map_err(Error::from)?;
+
+    Ok(Transaction {
+        id: row_id,
+        mint_url: MintUrl::from_str(&row_mint_url)?,
+        kind: serde_json::from_value(serde_json::Value::String(row_kind))?,
+        amount: Amount::from(row_amount as u64),
+        fee: Amount::from(row_fee as u64),
+        unit: CurrencyUnit::from(row_unit),
+        memo: row_memo,
+        metadata: match row_metadata {
+            Some(metadata) => serde_json::from_str(&metadata)?,
+            None => BTreeMap::new(),
+        },
+        timestamp: row_timestamp as u64,
+    })
+}
+
+fn sqlite_row_to_pending_mint(row: &SqliteRow) -> Result<PendingMint, Error> {
+    let row_quote_id: String = row.try_get("quote_id").map_err(Error::from)?;
+    let row_mint_url: String = row.try_get("mint_url").map_err(Error::from)?;
+    let row_unit: String = row.try_get("unit").map_err(Error::from)?;
+    let row_premint_secrets: String = row.try_get("premint_secrets").map_err(Error::from)?;
+
+    Ok(PendingMint {
+        quote_id: row_quote_id,
+        mint_url: MintUrl::from_str(&row_mint_url)?,
+        unit: CurrencyUnit::from(row_unit),
+        premint_secrets: serde_json::from_str(&row_premint_secrets)?,
     })
 }
 
@@ -697,8 +967,9 @@ fn sqlite_row_to_melt_quote(row: &SqliteRow) -> Result<MeltQuote, Error> {
     let row_amount: i64 = row.try_get("amount").map_err(Error::from)?;
     let row_request: String = row.try_get("request").map_err(Error::from)?;
     let row_fee_reserve: i64 = row.try_get("fee_reserve").map_err(Error::from)?;
-    let row_paid: bool = row.try_get("paid").map_err(Error::from)?;
+    let row_state: String = row.try_get("state").map_err(Error::from)?;
     let row_expiry: i64 = row.try_get("expiry").map_err(Error::from)?;
+    let row_exchange_rate: Option<i64> = row.try_get("exchange_rate").map_err(Error::from)?;
 
     Ok(MeltQuote {
         id: row_id,
@@ -706,8 +977,10 @@ fn sqlite_row_to_melt_quote(row: &SqliteRow) -> Result<MeltQuote, Error> {
         unit: CurrencyUnit::from(row_unit),
         request: row_request,
         fee_reserve: Amount::from(row_fee_reserve as u64),
-        paid: row_paid,
+        state: MeltQuoteState::from_str(&row_state)?,
         expiry: row_expiry as u64,
+        exchange_rate: row_exchange_rate.map(|r| r as u64),
+        pending_inputs: Vec::new(),
     })
 }
 
@@ -737,7 +1010,7 @@ fn sqlite_row_to_proof_info(row: &SqliteRow) -> Result<ProofInfo, Error> {
     Ok(ProofInfo {
         proof,
         y: PublicKey::from_slice(&y)?,
-        mint_url: row_mint_url.into(),
+        mint_url: MintUrl::from_str(&row_mint_url)?,
         state: State::from_str(&row_state)?,
         spending_condition: row_spending_condition.and_then(|r| serde_json::from_str(&r).ok()),
         unit: CurrencyUnit::from(row_unit),
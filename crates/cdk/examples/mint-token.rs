@@ -7,7 +7,7 @@ use cdk::cdk_database::WalletMemoryDatabase;
 use cdk::error::Error;
 use cdk::nuts::CurrencyUnit;
 use cdk::wallet::Wallet;
-use cdk::{Amount, UncheckedUrl};
+use cdk::{Amount, MintUrl};
 use rand::Rng;
 use tokio::time::sleep;
 
@@ -16,14 +16,14 @@ async fn main() -> Result<(), Error> {
     let localstore = WalletMemoryDatabase::default();
     let seed = rand::thread_rng().gen::<[u8; 32]>();
 
-    let mint_url = UncheckedUrl::from_str("https://testnut.cashu.space").unwrap();
+    let mint_url = MintUrl::from_str("https://testnut.cashu.space").unwrap();
     let unit = CurrencyUnit::Sat;
     let amount = Amount::from(10);
 
     let wallet = Wallet::new(Arc::new(localstore), &seed, vec![]);
 
     let quote = wallet
-        .mint_quote(mint_url.clone(), amount, unit.clone())
+        .mint_quote(mint_url.clone(), amount, unit.clone(), None)
         .await
         .unwrap();
 
@@ -1,15 +1,66 @@
 use std::fmt;
 
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::error::Error;
+use crate::nuts::CurrencyUnit;
 
 /// Number of satoshis
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+///
+/// Serialized as a number. Numeric strings are accepted too, as some mints
+/// send amounts as strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
 #[serde(transparent)]
 pub struct Amount(u64);
 
 impl Amount {
     pub const ZERO: Amount = Amount(0);
 
+    /// Convert amount of `current_unit` to `target_unit`
+    ///
+    /// Only sat and msat can be converted. Converting msat to sat rounds down.
+    pub fn convert_unit(
+        &self,
+        current_unit: &CurrencyUnit,
+        target_unit: &CurrencyUnit,
+    ) -> Result<Amount, Error> {
+        match (current_unit, target_unit) {
+            (current, target) if current.eq(target) => Ok(*self),
+            (CurrencyUnit::Sat, CurrencyUnit::Msat) => self
+                .0
+                .checked_mul(1000)
+                .map(Amount)
+                .ok_or(Error::AmountOverflow),
+            (CurrencyUnit::Msat, CurrencyUnit::Sat) => Ok(Amount(self.0 / 1000)),
+            _ => Err(Error::CannotConvertUnits),
+        }
+    }
+
+    /// Checked addition, `None` on overflow
+    pub fn checked_add(self, other: Amount) -> Option<Amount> {
+        self.0.checked_add(other.0).map(Amount)
+    }
+
+    /// Checked subtraction, `None` if `other` is larger
+    pub fn checked_sub(self, other: Amount) -> Option<Amount> {
+        self.0.checked_sub(other.0).map(Amount)
+    }
+
+    /// Checked multiplication, `None` on overflow
+    pub fn checked_mul(self, other: Amount) -> Option<Amount> {
+        self.0.checked_mul(other.0).map(Amount)
+    }
+
+    /// Sum of amounts that errors instead of overflowing
+    pub fn try_sum<I>(iter: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = Self>,
+    {
+        iter.into_iter().try_fold(Amount::ZERO, |acc, x| {
+            acc.checked_add(x).ok_or(Error::AmountOverflow)
+        })
+    }
+
     /// Split into parts that are powers of two
     pub fn split(&self) -> Vec<Self> {
         let sats = self.0;
@@ -23,12 +74,12 @@ impl Amount {
     }
 
     /// Split into parts that are powers of two by target
-    pub fn split_targeted(&self, target: &SplitTarget) -> Vec<Self> {
-        let mut parts = match *target {
+    pub fn split_targeted(&self, target: &SplitTarget) -> Result<Vec<Self>, Error> {
+        let mut parts = match target {
             SplitTarget::None => self.split(),
             SplitTarget::Value(amount) => {
-                if self.le(&amount) {
-                    return self.split();
+                if self.le(amount) {
+                    return Ok(self.split());
                 }
 
                 let mut parts_total = Amount::ZERO;
@@ -54,25 +105,68 @@ impl Amount {
                     }
                 }
 
+                parts
+            }
+            SplitTarget::Values(values) => {
+                let values_total = Amount::try_sum(values.iter().copied())?;
+
+                let amount_left = self
+                    .checked_sub(values_total)
+                    .ok_or(Error::SplitValuesGreater)?;
+
+                // Values that are not a power of two are split so every part
+                // can be signed by a keyset key
+                let mut parts: Vec<Self> = values.iter().flat_map(|v| v.split()).collect();
+                parts.extend(amount_left.split());
+
                 parts
             }
         };
 
         parts.sort();
-        parts
+        Ok(parts)
+    }
+
+    /// Split by target using at most `max_outputs` parts
+    ///
+    /// When the target produces too many parts, the smallest parts of equal
+    /// value are combined until the split fits.
+    pub fn split_with_max_outputs(
+        &self,
+        target: &SplitTarget,
+        max_outputs: usize,
+    ) -> Result<Vec<Self>, Error> {
+        let mut parts = self.split_targeted(target)?;
+
+        while parts.len() > max_outputs {
+            // Parts are sorted so equal values are adjacent
+            let pair = parts.windows(2).position(|w| w[0] == w[1]);
+
+            match pair {
+                Some(index) => {
+                    let combined = parts[index] + parts[index + 1];
+                    parts.splice(index..index + 2, [combined]);
+                    parts.sort();
+                }
+                None => return Err(Error::SplitOutputsExceeded(max_outputs)),
+            }
+        }
+
+        Ok(parts)
     }
 }
 
 /// Kinds of targeting that are supported
-#[derive(
-    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize,
-)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
 pub enum SplitTarget {
     /// Default target; least amount of proofs
     #[default]
     None,
     /// Target amount for wallet to have most proofs that add up to value
     Value(Amount),
+    /// Specific amounts to split into; any remainder is split into powers of
+    /// two
+    Values(Vec<Amount>),
 }
 
 impl Default for Amount {
@@ -99,25 +193,83 @@ impl From<Amount> for u64 {
     }
 }
 
+/// # Panics
+///
+/// Panics on overflow, use [`Amount::checked_add`] for amounts that are not
+/// known to be in range.
 impl std::ops::Add for Amount {
     type Output = Amount;
 
     fn add(self, rhs: Amount) -> Self::Output {
-        Amount(self.0 + rhs.0)
+        self.checked_add(rhs).expect("Amount addition overflow")
     }
 }
 
+/// # Panics
+///
+/// Panics on overflow, use [`Amount::checked_add`] for amounts that are not
+/// known to be in range.
 impl std::ops::AddAssign for Amount {
     fn add_assign(&mut self, rhs: Self) {
-        self.0 += rhs.0;
+        *self = *self + rhs;
     }
 }
 
+/// # Panics
+///
+/// Panics if `rhs` is larger, use [`Amount::checked_sub`] for amounts that
+/// are not known to be in range.
 impl std::ops::Sub for Amount {
     type Output = Amount;
 
     fn sub(self, rhs: Amount) -> Self::Output {
-        Amount(self.0 - rhs.0)
+        self.checked_sub(rhs).expect("Amount subtraction underflow")
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct AmountVisitor;
+
+        impl<'de> de::Visitor<'de> for AmountVisitor {
+            type Value = Amount;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a non-negative integer or numeric string")
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Amount(value))
+            }
+
+            fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                u64::try_from(value)
+                    .map(Amount)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Signed(value), &self))
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                value
+                    .trim()
+                    .parse::<u64>()
+                    .map(Amount)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+            }
+        }
+
+        deserializer.deserialize_any(AmountVisitor)
     }
 }
 
@@ -127,10 +279,13 @@ impl fmt::Display for Amount {
     }
 }
 
+/// # Panics
+///
+/// Panics on overflow, use [`Amount::try_sum`] for amounts that are not
+/// known to be in range.
 impl core::iter::Sum for Amount {
     fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
-        let sats: u64 = iter.map(|amt| amt.0).sum();
-        Amount::from(sats)
+        iter.fold(Amount::ZERO, |acc, x| acc + x)
     }
 }
 
@@ -138,6 +293,135 @@ impl core::iter::Sum for Amount {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_amount_serde() {
+        assert_eq!(serde_json::from_str::<Amount>("21").unwrap(), Amount(21));
+        assert_eq!(
+            serde_json::from_str::<Amount>("\"21\"").unwrap(),
+            Amount(21)
+        );
+        assert!(serde_json::from_str::<Amount>("-1").is_err());
+        assert!(serde_json::from_str::<Amount>("\"21 sat\"").is_err());
+        assert_eq!(serde_json::to_string(&Amount(21)).unwrap(), "21");
+
+        // Extra fields from other mints are ignored
+        let quote: crate::nuts::MeltQuoteBolt11Response = serde_json::from_str(
+            r#"{"quote":"q","amount":"64","fee_reserve":2,"paid":false,"state":"UNPAID","expiry":0,"payment_preimage":null}"#,
+        )
+        .unwrap();
+        assert_eq!(quote.amount, Amount(64));
+    }
+
+    #[test]
+    fn test_try_sum() {
+        let amounts = vec![Amount::from(1), Amount::from(2), Amount::from(4)];
+        assert_eq!(Amount::try_sum(amounts).unwrap(), Amount::from(7));
+
+        let amounts = vec![Amount::from(u64::MAX), Amount::from(2)];
+        assert!(matches!(
+            Amount::try_sum(amounts),
+            Err(Error::AmountOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_split_values() {
+        let amount = Amount::from(10);
+
+        let split = amount
+            .split_targeted(&SplitTarget::Values(vec![Amount(2), Amount(2)]))
+            .unwrap();
+        assert_eq!(vec![Amount(2), Amount(2), Amount(2), Amount(4)], split);
+
+        // Values that are not a power of two are split further
+        let split = amount
+            .split_targeted(&SplitTarget::Values(vec![Amount(3)]))
+            .unwrap();
+        assert_eq!(
+            vec![Amount(1), Amount(1), Amount(2), Amount(2), Amount(4)],
+            split
+        );
+
+        assert!(matches!(
+            amount.split_targeted(&SplitTarget::Values(vec![Amount(8), Amount(4)])),
+            Err(Error::SplitValuesGreater)
+        ));
+    }
+
+    #[test]
+    fn test_split_with_max_outputs() {
+        let amount = Amount::from(64);
+        let target = SplitTarget::Value(Amount(8));
+
+        assert_eq!(amount.split_targeted(&target).unwrap().len(), 8);
+
+        let split = amount.split_with_max_outputs(&target, 4).unwrap();
+        assert_eq!(vec![Amount(16), Amount(16), Amount(16), Amount(16)], split);
+
+        let split = amount.split_with_max_outputs(&target, 5).unwrap();
+        assert_eq!(split.len(), 5);
+        assert_eq!(split.iter().copied().sum::<Amount>(), amount);
+
+        assert!(matches!(
+            Amount::from(7).split_with_max_outputs(&SplitTarget::None, 2),
+            Err(Error::SplitOutputsExceeded(2))
+        ));
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let amount = Amount::from(10);
+
+        assert_eq!(amount.checked_add(Amount::from(5)), Some(Amount::from(15)));
+        assert_eq!(amount.checked_add(Amount::from(u64::MAX)), None);
+        assert_eq!(amount.checked_sub(Amount::from(4)), Some(Amount::from(6)));
+        assert_eq!(amount.checked_sub(Amount::from(11)), None);
+        assert_eq!(amount.checked_mul(Amount::from(3)), Some(Amount::from(30)));
+        assert_eq!(Amount::from(u64::MAX).checked_mul(amount), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Amount subtraction underflow")]
+    fn test_sub_underflow_panics() {
+        let _ = Amount::from(1) - Amount::from(2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Amount addition overflow")]
+    fn test_sum_overflow_panics() {
+        let _: Amount = [Amount::from(u64::MAX), Amount::from(1)].into_iter().sum();
+    }
+
+    #[test]
+    fn test_convert_unit() {
+        let amount = Amount::from(1500);
+
+        assert_eq!(
+            amount
+                .convert_unit(&CurrencyUnit::Sat, &CurrencyUnit::Msat)
+                .unwrap(),
+            Amount::from(1_500_000)
+        );
+        assert_eq!(
+            amount
+                .convert_unit(&CurrencyUnit::Msat, &CurrencyUnit::Sat)
+                .unwrap(),
+            Amount::from(1)
+        );
+        assert_eq!(
+            amount
+                .convert_unit(&CurrencyUnit::Usd, &CurrencyUnit::Usd)
+                .unwrap(),
+            amount
+        );
+        assert!(amount
+            .convert_unit(&CurrencyUnit::Sat, &CurrencyUnit::Usd)
+            .is_err());
+        assert!(Amount::from(u64::MAX)
+            .convert_unit(&CurrencyUnit::Sat, &CurrencyUnit::Msat)
+            .is_err());
+    }
+
     #[test]
     fn test_split_amount() {
         assert_eq!(Amount::from(1).split(), vec![Amount::from(1)]);
@@ -159,12 +443,16 @@ mod tests {
     fn test_split_target_amount() {
         let amount = Amount(65);
 
-        let split = amount.split_targeted(&SplitTarget::Value(Amount(32)));
+        let split = amount
+            .split_targeted(&SplitTarget::Value(Amount(32)))
+            .unwrap();
         assert_eq!(vec![Amount(1), Amount(32), Amount(32)], split);
 
         let amount = Amount(150);
 
-        let split = amount.split_targeted(&SplitTarget::Value(Amount::from(50)));
+        let split = amount
+            .split_targeted(&SplitTarget::Value(Amount::from(50)))
+            .unwrap();
         assert_eq!(
             vec![
                 Amount(2),
@@ -182,7 +470,9 @@ mod tests {
 
         let amount = Amount::from(63);
 
-        let split = amount.split_targeted(&SplitTarget::Value(Amount::from(32)));
+        let split = amount
+            .split_targeted(&SplitTarget::Value(Amount::from(32)))
+            .unwrap();
         assert_eq!(
             vec![
                 Amount(1),
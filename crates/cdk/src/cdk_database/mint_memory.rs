@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -9,16 +9,22 @@ use crate::dhke::hash_to_curve;
 use crate::mint::MintKeySetInfo;
 use crate::nuts::{BlindSignature, CurrencyUnit, Id, Proof, Proofs, PublicKey};
 use crate::secret::Secret;
-use crate::types::{MeltQuote, MintQuote};
+use crate::types::{CompletedMelt, MeltQuote, MintQuote};
+use crate::Amount;
 
 #[derive(Debug, Clone)]
 pub struct MintMemoryDatabase {
     active_keysets: Arc<RwLock<HashMap<CurrencyUnit, Id>>>,
     keysets: Arc<RwLock<HashMap<Id, MintKeySetInfo>>>,
     mint_quotes: Arc<RwLock<HashMap<String, MintQuote>>>,
+    /// Mint quote id by payment hash
+    mint_quote_hashes: Arc<RwLock<HashMap<String, String>>>,
     melt_quotes: Arc<RwLock<HashMap<String, MeltQuote>>>,
+    completed_melts: Arc<RwLock<HashMap<String, CompletedMelt>>>,
     pending_proofs: Arc<RwLock<HashMap<[u8; 33], Proof>>>,
     spent_proofs: Arc<RwLock<HashMap<[u8; 33], Proof>>>,
+    archived_proofs: Arc<RwLock<HashSet<[u8; 33]>>>,
+    archived_amounts: Arc<RwLock<HashMap<Id, Amount>>>,
     blinded_signatures: Arc<RwLock<HashMap<[u8; 33], BlindSignature>>>,
 }
 
@@ -38,12 +44,19 @@ impl MintMemoryDatabase {
             keysets: Arc::new(RwLock::new(
                 keysets.into_iter().map(|k| (k.id, k)).collect(),
             )),
+            mint_quote_hashes: Arc::new(RwLock::new(
+                mint_quotes
+                    .iter()
+                    .filter_map(|q| Some((q.payment_hash()?, q.id.clone())))
+                    .collect(),
+            )),
             mint_quotes: Arc::new(RwLock::new(
                 mint_quotes.into_iter().map(|q| (q.id.clone(), q)).collect(),
             )),
             melt_quotes: Arc::new(RwLock::new(
                 melt_quotes.into_iter().map(|q| (q.id.clone(), q)).collect(),
             )),
+            completed_melts: Arc::new(RwLock::new(HashMap::new())),
             pending_proofs: Arc::new(RwLock::new(
                 pending_proofs
                     .into_iter()
@@ -56,6 +69,8 @@ impl MintMemoryDatabase {
                     .map(|p| (hash_to_curve(&p.secret.to_bytes()).unwrap().to_bytes(), p))
                     .collect(),
             )),
+            archived_proofs: Arc::new(RwLock::new(HashSet::new())),
+            archived_amounts: Arc::new(RwLock::new(HashMap::new())),
             blinded_signatures: Arc::new(RwLock::new(blinded_signatures)),
         })
     }
@@ -92,10 +107,17 @@ impl MintDatabase for MintMemoryDatabase {
     }
 
     async fn add_mint_quote(&self, quote: MintQuote) -> Result<(), Self::Err> {
-        self.mint_quotes
-            .write()
-            .await
-            .insert(quote.id.clone(), quote);
+        let mut quotes = self.mint_quotes.write().await;
+        let mut hashes = self.mint_quote_hashes.write().await;
+
+        if let Some(hash) = quotes.get(&quote.id).and_then(|q| q.payment_hash()) {
+            hashes.remove(&hash);
+        }
+        if let Some(hash) = quote.payment_hash() {
+            hashes.insert(hash, quote.id.clone());
+        }
+        quotes.insert(quote.id.clone(), quote);
+
         Ok(())
     }
 
@@ -103,12 +125,43 @@ impl MintDatabase for MintMemoryDatabase {
         Ok(self.mint_quotes.read().await.get(quote_id).cloned())
     }
 
+    async fn get_mint_quote_by_request(
+        &self,
+        request: &str,
+    ) -> Result<Option<MintQuote>, Self::Err> {
+        Ok(self
+            .mint_quotes
+            .read()
+            .await
+            .values()
+            .find(|q| q.request.eq(request))
+            .cloned())
+    }
+
+    async fn get_mint_quote_by_payment_hash(
+        &self,
+        payment_hash: &str,
+    ) -> Result<Option<MintQuote>, Self::Err> {
+        let quotes = self.mint_quotes.read().await;
+        let hashes = self.mint_quote_hashes.read().await;
+
+        Ok(hashes
+            .get(payment_hash)
+            .and_then(|id| quotes.get(id))
+            .cloned())
+    }
+
     async fn get_mint_quotes(&self) -> Result<Vec<MintQuote>, Self::Err> {
         Ok(self.mint_quotes.read().await.values().cloned().collect())
     }
 
     async fn remove_mint_quote(&self, quote_id: &str) -> Result<(), Self::Err> {
-        self.mint_quotes.write().await.remove(quote_id);
+        let mut quotes = self.mint_quotes.write().await;
+        let mut hashes = self.mint_quote_hashes.write().await;
+
+        if let Some(hash) = quotes.remove(quote_id).and_then(|q| q.payment_hash()) {
+            hashes.remove(&hash);
+        }
 
         Ok(())
     }
@@ -135,6 +188,29 @@ impl MintDatabase for MintMemoryDatabase {
         Ok(())
     }
 
+    async fn add_completed_melt(&self, melt: CompletedMelt) -> Result<(), Self::Err> {
+        self.completed_melts
+            .write()
+            .await
+            .insert(melt.quote_id.clone(), melt);
+
+        Ok(())
+    }
+
+    async fn get_completed_melt(&self, quote_id: &str) -> Result<Option<CompletedMelt>, Self::Err> {
+        Ok(self.completed_melts.read().await.get(quote_id).cloned())
+    }
+
+    async fn get_completed_melts(&self) -> Result<Vec<CompletedMelt>, Self::Err> {
+        Ok(self
+            .completed_melts
+            .read()
+            .await
+            .values()
+            .cloned()
+            .collect())
+    }
+
     async fn add_spent_proof(&self, proof: Proof) -> Result<(), Self::Err> {
         let secret_point = hash_to_curve(&proof.secret.to_bytes())?;
         self.spent_proofs
@@ -157,6 +233,41 @@ impl MintDatabase for MintMemoryDatabase {
         Ok(self.spent_proofs.read().await.get(&y.to_bytes()).cloned())
     }
 
+    async fn get_spent_proofs(&self) -> Result<Proofs, Self::Err> {
+        Ok(self.spent_proofs.read().await.values().cloned().collect())
+    }
+
+    async fn archive_spent_proofs(&self, keyset_id: &Id) -> Result<u64, Self::Err> {
+        let mut spent_proofs = self.spent_proofs.write().await;
+        let mut archived_proofs = self.archived_proofs.write().await;
+        let mut archived_amounts = self.archived_amounts.write().await;
+
+        let ys: Vec<[u8; 33]> = spent_proofs
+            .iter()
+            .filter(|(_, proof)| proof.keyset_id.eq(keyset_id))
+            .map(|(y, _)| *y)
+            .collect();
+
+        let mut amount = Amount::ZERO;
+        for y in &ys {
+            if let Some(proof) = spent_proofs.remove(y) {
+                amount += proof.amount;
+                archived_proofs.insert(*y);
+            }
+        }
+        *archived_amounts.entry(*keyset_id).or_default() += amount;
+
+        Ok(ys.len() as u64)
+    }
+
+    async fn is_archived_spent_proof(&self, y: &PublicKey) -> Result<bool, Self::Err> {
+        Ok(self.archived_proofs.read().await.contains(&y.to_bytes()))
+    }
+
+    async fn get_archived_amounts(&self) -> Result<HashMap<Id, Amount>, Self::Err> {
+        Ok(self.archived_amounts.read().await.clone())
+    }
+
     async fn add_pending_proof(&self, proof: Proof) -> Result<(), Self::Err> {
         self.pending_proofs
             .write()
@@ -231,4 +342,108 @@ impl MintDatabase for MintMemoryDatabase {
 
         Ok(signatures)
     }
+
+    async fn get_all_blinded_signatures(&self) -> Result<Vec<BlindSignature>, Self::Err> {
+        Ok(self
+            .blinded_signatures
+            .read()
+            .await
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    async fn add_spent_proofs_and_signatures(
+        &self,
+        proofs: Proofs,
+        blinded_signatures: Vec<(PublicKey, BlindSignature)>,
+    ) -> Result<(), Self::Err> {
+        let ys = proofs
+            .iter()
+            .map(|p| hash_to_curve(&p.secret.to_bytes()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut spent_proofs = self.spent_proofs.write().await;
+        let archived_proofs = self.archived_proofs.read().await;
+        let mut signatures = self.blinded_signatures.write().await;
+
+        if ys.iter().any(|y| {
+            spent_proofs.contains_key(&y.to_bytes()) || archived_proofs.contains(&y.to_bytes())
+        }) {
+            return Err(Error::ProofAlreadySpent);
+        }
+
+        for (y, proof) in ys.into_iter().zip(proofs) {
+            spent_proofs.insert(y.to_bytes(), proof);
+        }
+        for (blinded_message, blinded_signature) in blinded_signatures {
+            signatures.insert(blinded_message.to_bytes(), blinded_signature);
+        }
+
+        Ok(())
+    }
+
+    async fn issue_mint_quote(
+        &self,
+        quote_id: &str,
+        blinded_signatures: Vec<(PublicKey, BlindSignature)>,
+    ) -> Result<(), Self::Err> {
+        let mut quotes = self.mint_quotes.write().await;
+        let mut hashes = self.mint_quote_hashes.write().await;
+        let mut signatures = self.blinded_signatures.write().await;
+
+        let quote = quotes.remove(quote_id).ok_or(Error::UnknownQuote)?;
+        if let Some(hash) = quote.payment_hash() {
+            hashes.remove(&hash);
+        }
+        for (blinded_message, blinded_signature) in blinded_signatures {
+            signatures.insert(blinded_message.to_bytes(), blinded_signature);
+        }
+
+        Ok(())
+    }
+
+    async fn complete_melt(
+        &self,
+        inputs: Proofs,
+        blinded_signatures: Vec<(PublicKey, BlindSignature)>,
+        melt: CompletedMelt,
+        quote: MeltQuote,
+        mint_quote: Option<MintQuote>,
+    ) -> Result<(), Self::Err> {
+        let ys = inputs
+            .iter()
+            .map(|p| hash_to_curve(&p.secret.to_bytes()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut spent_proofs = self.spent_proofs.write().await;
+        let archived_proofs = self.archived_proofs.read().await;
+        let mut pending_proofs = self.pending_proofs.write().await;
+        let mut signatures = self.blinded_signatures.write().await;
+        let mut completed_melts = self.completed_melts.write().await;
+        let mut melt_quotes = self.melt_quotes.write().await;
+        let mut mint_quotes = self.mint_quotes.write().await;
+
+        if ys.iter().any(|y| {
+            spent_proofs.contains_key(&y.to_bytes()) || archived_proofs.contains(&y.to_bytes())
+        }) {
+            return Err(Error::ProofAlreadySpent);
+        }
+
+        for (y, proof) in ys.into_iter().zip(inputs) {
+            pending_proofs.remove(&y.to_bytes());
+            spent_proofs.insert(y.to_bytes(), proof);
+        }
+        for (blinded_message, blinded_signature) in blinded_signatures {
+            signatures.insert(blinded_message.to_bytes(), blinded_signature);
+        }
+        completed_melts.insert(melt.quote_id.clone(), melt);
+        melt_quotes.insert(quote.id.clone(), quote);
+        // The request and so the payment hash of the mint quote are unchanged
+        if let Some(mint_quote) = mint_quote {
+            mint_quotes.insert(mint_quote.id.clone(), mint_quote);
+        }
+
+        Ok(())
+    }
 }
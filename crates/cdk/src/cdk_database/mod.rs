@@ -10,21 +10,25 @@ use thiserror::Error;
 #[cfg(feature = "mint")]
 use crate::mint::MintKeySetInfo;
 #[cfg(feature = "wallet")]
+use crate::mint_url::MintUrl;
+#[cfg(feature = "wallet")]
 use crate::nuts::State;
 #[cfg(feature = "mint")]
 use crate::nuts::{BlindSignature, Proof};
 #[cfg(any(feature = "wallet", feature = "mint"))]
-use crate::nuts::{CurrencyUnit, Id, PublicKey};
+use crate::nuts::{CurrencyUnit, Id, Proofs, PublicKey};
 #[cfg(feature = "wallet")]
-use crate::nuts::{KeySetInfo, Keys, MintInfo, Proofs, SpendingConditions};
+use crate::nuts::{KeySetInfo, Keys, MintInfo, SpendingConditions};
 #[cfg(feature = "mint")]
 use crate::secret::Secret;
-#[cfg(feature = "wallet")]
-use crate::types::ProofInfo;
+#[cfg(feature = "mint")]
+use crate::types::CompletedMelt;
 #[cfg(any(feature = "wallet", feature = "mint"))]
 use crate::types::{MeltQuote, MintQuote};
 #[cfg(feature = "wallet")]
-use crate::url::UncheckedUrl;
+use crate::types::{PendingMint, ProofInfo, Transaction};
+#[cfg(feature = "mint")]
+use crate::Amount;
 
 #[cfg(feature = "mint")]
 pub mod mint_memory;
@@ -42,6 +46,12 @@ pub enum Error {
     Cdk(#[from] crate::error::Error),
     #[error(transparent)]
     NUT01(#[from] crate::nuts::nut00::Error),
+    /// Proof to mark spent is already spent
+    #[error("Proof already spent")]
+    ProofAlreadySpent,
+    /// Mint quote to issue is unknown or already issued
+    #[error("Unknown quote")]
+    UnknownQuote,
 }
 
 #[cfg(feature = "wallet")]
@@ -52,20 +62,20 @@ pub trait WalletDatabase {
 
     async fn add_mint(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         mint_info: Option<MintInfo>,
     ) -> Result<(), Self::Err>;
-    async fn get_mint(&self, mint_url: UncheckedUrl) -> Result<Option<MintInfo>, Self::Err>;
-    async fn get_mints(&self) -> Result<HashMap<UncheckedUrl, Option<MintInfo>>, Self::Err>;
+    async fn get_mint(&self, mint_url: MintUrl) -> Result<Option<MintInfo>, Self::Err>;
+    async fn get_mints(&self) -> Result<HashMap<MintUrl, Option<MintInfo>>, Self::Err>;
 
     async fn add_mint_keysets(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         keysets: Vec<KeySetInfo>,
     ) -> Result<(), Self::Err>;
     async fn get_mint_keysets(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
     ) -> Result<Option<Vec<KeySetInfo>>, Self::Err>;
     async fn get_keyset_by_id(&self, keyset_id: &Id) -> Result<Option<KeySetInfo>, Self::Err>;
 
@@ -85,18 +95,39 @@ pub trait WalletDatabase {
     async fn add_proofs(&self, proof_info: Vec<ProofInfo>) -> Result<(), Self::Err>;
     async fn get_proofs(
         &self,
-        mint_url: Option<UncheckedUrl>,
+        mint_url: Option<MintUrl>,
         unit: Option<CurrencyUnit>,
         state: Option<Vec<State>>,
         spending_conditions: Option<Vec<SpendingConditions>>,
     ) -> Result<Option<Vec<ProofInfo>>, Self::Err>;
     async fn remove_proofs(&self, proofs: &Proofs) -> Result<(), Self::Err>;
+    /// Add `added` and remove the proofs with Ys `removed_ys` in a single
+    /// transaction
+    async fn update_proofs(
+        &self,
+        added: Vec<ProofInfo>,
+        removed_ys: Vec<PublicKey>,
+    ) -> Result<(), Self::Err>;
 
     async fn set_proof_state(&self, y: PublicKey, state: State) -> Result<(), Self::Err>;
 
     async fn increment_keyset_counter(&self, keyset_id: &Id, count: u32) -> Result<(), Self::Err>;
     async fn get_keyset_counter(&self, keyset_id: &Id) -> Result<Option<u32>, Self::Err>;
 
+    /// Remember that the token with `fingerprint` has been received
+    async fn add_received_token(&self, fingerprint: String) -> Result<(), Self::Err>;
+    /// Whether the token with `fingerprint` has been received
+    async fn is_token_received(&self, fingerprint: &str) -> Result<bool, Self::Err>;
+
+    async fn add_transaction(&self, transaction: Transaction) -> Result<(), Self::Err>;
+    async fn get_transactions(&self) -> Result<Vec<Transaction>, Self::Err>;
+
+    /// Store the outputs of a mint request, replacing those of the quote
+    async fn add_pending_mint(&self, pending_mint: PendingMint) -> Result<(), Self::Err>;
+    async fn get_pending_mint(&self, quote_id: &str) -> Result<Option<PendingMint>, Self::Err>;
+    async fn get_pending_mints(&self) -> Result<Vec<PendingMint>, Self::Err>;
+    async fn remove_pending_mint(&self, quote_id: &str) -> Result<(), Self::Err>;
+
     #[cfg(feature = "nostr")]
     async fn get_nostr_last_checked(
         &self,
@@ -121,6 +152,15 @@ pub trait MintDatabase {
 
     async fn add_mint_quote(&self, quote: MintQuote) -> Result<(), Self::Err>;
     async fn get_mint_quote(&self, quote_id: &str) -> Result<Option<MintQuote>, Self::Err>;
+    async fn get_mint_quote_by_request(
+        &self,
+        request: &str,
+    ) -> Result<Option<MintQuote>, Self::Err>;
+    /// Mint quote whose request has `payment_hash`, see [`MintQuote::payment_hash`]
+    async fn get_mint_quote_by_payment_hash(
+        &self,
+        payment_hash: &str,
+    ) -> Result<Option<MintQuote>, Self::Err>;
     async fn get_mint_quotes(&self) -> Result<Vec<MintQuote>, Self::Err>;
     async fn remove_mint_quote(&self, quote_id: &str) -> Result<(), Self::Err>;
 
@@ -129,6 +169,10 @@ pub trait MintDatabase {
     async fn get_melt_quotes(&self) -> Result<Vec<MeltQuote>, Self::Err>;
     async fn remove_melt_quote(&self, quote_id: &str) -> Result<(), Self::Err>;
 
+    async fn add_completed_melt(&self, melt: CompletedMelt) -> Result<(), Self::Err>;
+    async fn get_completed_melt(&self, quote_id: &str) -> Result<Option<CompletedMelt>, Self::Err>;
+    async fn get_completed_melts(&self) -> Result<Vec<CompletedMelt>, Self::Err>;
+
     async fn add_keyset_info(&self, keyset: MintKeySetInfo) -> Result<(), Self::Err>;
     async fn get_keyset_info(&self, id: &Id) -> Result<Option<MintKeySetInfo>, Self::Err>;
     async fn get_keyset_infos(&self) -> Result<Vec<MintKeySetInfo>, Self::Err>;
@@ -136,6 +180,17 @@ pub trait MintDatabase {
     async fn add_spent_proof(&self, proof: Proof) -> Result<(), Self::Err>;
     async fn get_spent_proof_by_secret(&self, secret: &Secret) -> Result<Option<Proof>, Self::Err>;
     async fn get_spent_proof_by_y(&self, y: &PublicKey) -> Result<Option<Proof>, Self::Err>;
+    async fn get_spent_proofs(&self) -> Result<Proofs, Self::Err>;
+
+    /// Move the spent proofs of `keyset_id` to the archive
+    ///
+    /// Only the `Y`s of archived proofs are kept, and their total amount per
+    /// keyset. Returns the number of proofs archived.
+    async fn archive_spent_proofs(&self, keyset_id: &Id) -> Result<u64, Self::Err>;
+    /// Check if the proof with `y` is spent and archived
+    async fn is_archived_spent_proof(&self, y: &PublicKey) -> Result<bool, Self::Err>;
+    /// Total amount of the archived proofs of each keyset
+    async fn get_archived_amounts(&self) -> Result<HashMap<Id, Amount>, Self::Err>;
 
     async fn add_pending_proof(&self, proof: Proof) -> Result<(), Self::Err>;
     async fn get_pending_proof_by_secret(
@@ -158,4 +213,45 @@ pub trait MintDatabase {
         &self,
         blinded_messages: Vec<PublicKey>,
     ) -> Result<Vec<Option<BlindSignature>>, Self::Err>;
+    async fn get_all_blinded_signatures(&self) -> Result<Vec<BlindSignature>, Self::Err>;
+
+    /// Mark `proofs` spent and store `blinded_signatures` in a single
+    /// transaction
+    ///
+    /// Either everything is written or nothing is. Fails with
+    /// [`Error::ProofAlreadySpent`] if one of the proofs is already spent,
+    /// also when it is spent concurrently.
+    async fn add_spent_proofs_and_signatures(
+        &self,
+        proofs: Proofs,
+        blinded_signatures: Vec<(PublicKey, BlindSignature)>,
+    ) -> Result<(), Self::Err>;
+
+    /// Issue a mint quote in a single transaction
+    ///
+    /// Removes the quote and stores the `blinded_signatures` of its outputs.
+    /// Either everything is written or nothing is. Fails with
+    /// [`Error::UnknownQuote`] if the quote does not exist, also when it is
+    /// issued concurrently.
+    async fn issue_mint_quote(
+        &self,
+        quote_id: &str,
+        blinded_signatures: Vec<(PublicKey, BlindSignature)>,
+    ) -> Result<(), Self::Err>;
+
+    /// Complete a melt in a single transaction
+    ///
+    /// Marks the inputs spent, pending ones included, and stores the change
+    /// signatures, the completed melt and the paid `quote`. `mint_quote` is
+    /// the mint quote paid by an internal melt. Either everything is written
+    /// or nothing is. Fails with [`Error::ProofAlreadySpent`] if one of the
+    /// inputs is already spent.
+    async fn complete_melt(
+        &self,
+        inputs: Proofs,
+        blinded_signatures: Vec<(PublicKey, BlindSignature)>,
+        melt: CompletedMelt,
+        quote: MeltQuote,
+        mint_quote: Option<MintQuote>,
+    ) -> Result<(), Self::Err>;
 }
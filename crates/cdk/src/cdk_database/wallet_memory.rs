@@ -8,22 +8,25 @@ use tokio::sync::RwLock;
 
 use super::WalletDatabase;
 use crate::cdk_database::Error;
+use crate::mint_url::MintUrl;
 use crate::nuts::{
     CurrencyUnit, Id, KeySetInfo, Keys, MintInfo, Proofs, PublicKey, SpendingConditions, State,
 };
-use crate::types::{MeltQuote, MintQuote, ProofInfo};
-use crate::url::UncheckedUrl;
+use crate::types::{MeltQuote, MintQuote, PendingMint, ProofInfo, Transaction};
 
 #[derive(Default, Debug, Clone)]
 pub struct WalletMemoryDatabase {
-    mints: Arc<RwLock<HashMap<UncheckedUrl, Option<MintInfo>>>>,
-    mint_keysets: Arc<RwLock<HashMap<UncheckedUrl, HashSet<Id>>>>,
+    mints: Arc<RwLock<HashMap<MintUrl, Option<MintInfo>>>>,
+    mint_keysets: Arc<RwLock<HashMap<MintUrl, HashSet<Id>>>>,
     keysets: Arc<RwLock<HashMap<Id, KeySetInfo>>>,
     mint_quotes: Arc<RwLock<HashMap<String, MintQuote>>>,
     melt_quotes: Arc<RwLock<HashMap<String, MeltQuote>>>,
     mint_keys: Arc<RwLock<HashMap<Id, Keys>>>,
     proofs: Arc<RwLock<HashMap<PublicKey, ProofInfo>>>,
     keyset_counter: Arc<RwLock<HashMap<Id, u32>>>,
+    received_tokens: Arc<RwLock<HashSet<String>>>,
+    transactions: Arc<RwLock<Vec<Transaction>>>,
+    pending_mints: Arc<RwLock<HashMap<String, PendingMint>>>,
     #[cfg(feature = "nostr")]
     nostr_last_checked: Arc<RwLock<HashMap<PublicKey, u32>>>,
 }
@@ -47,10 +50,16 @@ impl WalletMemoryDatabase {
                 melt_quotes.into_iter().map(|q| (q.id.clone(), q)).collect(),
             )),
             mint_keys: Arc::new(RwLock::new(
-                mint_keys.into_iter().map(|k| (Id::from(&k), k)).collect(),
+                mint_keys
+                    .into_iter()
+                    .filter_map(|k| Some((Id::try_from(&k).ok()?, k)))
+                    .collect(),
             )),
             proofs: Arc::new(RwLock::new(HashMap::new())),
             keyset_counter: Arc::new(RwLock::new(keyset_counter)),
+            received_tokens: Arc::new(RwLock::new(HashSet::new())),
+            transactions: Arc::new(RwLock::new(Vec::new())),
+            pending_mints: Arc::new(RwLock::new(HashMap::new())),
             #[cfg(feature = "nostr")]
             nostr_last_checked: Arc::new(RwLock::new(nostr_last_checked)),
         }
@@ -64,24 +73,24 @@ impl WalletDatabase for WalletMemoryDatabase {
 
     async fn add_mint(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         mint_info: Option<MintInfo>,
     ) -> Result<(), Self::Err> {
         self.mints.write().await.insert(mint_url, mint_info);
         Ok(())
     }
 
-    async fn get_mint(&self, mint_url: UncheckedUrl) -> Result<Option<MintInfo>, Self::Err> {
+    async fn get_mint(&self, mint_url: MintUrl) -> Result<Option<MintInfo>, Self::Err> {
         Ok(self.mints.read().await.get(&mint_url).cloned().flatten())
     }
 
-    async fn get_mints(&self) -> Result<HashMap<UncheckedUrl, Option<MintInfo>>, Error> {
+    async fn get_mints(&self) -> Result<HashMap<MintUrl, Option<MintInfo>>, Error> {
         Ok(self.mints.read().await.clone())
     }
 
     async fn add_mint_keysets(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         keysets: Vec<KeySetInfo>,
     ) -> Result<(), Error> {
         let mut current_mint_keysets = self.mint_keysets.write().await;
@@ -101,10 +110,7 @@ impl WalletDatabase for WalletMemoryDatabase {
         Ok(())
     }
 
-    async fn get_mint_keysets(
-        &self,
-        mint_url: UncheckedUrl,
-    ) -> Result<Option<Vec<KeySetInfo>>, Error> {
+    async fn get_mint_keysets(&self, mint_url: MintUrl) -> Result<Option<Vec<KeySetInfo>>, Error> {
         match self.mint_keysets.read().await.get(&mint_url) {
             Some(keyset_ids) => {
                 let mut keysets = vec![];
@@ -169,7 +175,8 @@ impl WalletDatabase for WalletMemoryDatabase {
     }
 
     async fn add_keys(&self, keys: Keys) -> Result<(), Error> {
-        self.mint_keys.write().await.insert(Id::from(&keys), keys);
+        let id = Id::try_from(&keys).map_err(crate::error::Error::from)?;
+        self.mint_keys.write().await.insert(id, keys);
         Ok(())
     }
 
@@ -194,7 +201,7 @@ impl WalletDatabase for WalletMemoryDatabase {
 
     async fn get_proofs(
         &self,
-        mint_url: Option<UncheckedUrl>,
+        mint_url: Option<MintUrl>,
         unit: Option<CurrencyUnit>,
         state: Option<Vec<State>>,
         spending_conditions: Option<Vec<SpendingConditions>>,
@@ -230,6 +237,24 @@ impl WalletDatabase for WalletMemoryDatabase {
         Ok(())
     }
 
+    async fn update_proofs(
+        &self,
+        added: Vec<ProofInfo>,
+        removed_ys: Vec<PublicKey>,
+    ) -> Result<(), Error> {
+        let mut all_proofs = self.proofs.write().await;
+
+        for proof_info in added.into_iter() {
+            all_proofs.insert(proof_info.y, proof_info);
+        }
+
+        for y in removed_ys {
+            all_proofs.remove(&y);
+        }
+
+        Ok(())
+    }
+
     async fn set_proof_state(&self, y: PublicKey, state: State) -> Result<(), Self::Err> {
         let mint_proofs = self.proofs.read().await;
 
@@ -264,6 +289,45 @@ impl WalletDatabase for WalletMemoryDatabase {
         Ok(self.keyset_counter.read().await.get(id).cloned())
     }
 
+    async fn add_received_token(&self, fingerprint: String) -> Result<(), Error> {
+        self.received_tokens.write().await.insert(fingerprint);
+        Ok(())
+    }
+
+    async fn is_token_received(&self, fingerprint: &str) -> Result<bool, Error> {
+        Ok(self.received_tokens.read().await.contains(fingerprint))
+    }
+
+    async fn add_transaction(&self, transaction: Transaction) -> Result<(), Error> {
+        self.transactions.write().await.push(transaction);
+        Ok(())
+    }
+
+    async fn get_transactions(&self) -> Result<Vec<Transaction>, Error> {
+        Ok(self.transactions.read().await.clone())
+    }
+
+    async fn add_pending_mint(&self, pending_mint: PendingMint) -> Result<(), Error> {
+        self.pending_mints
+            .write()
+            .await
+            .insert(pending_mint.quote_id.clone(), pending_mint);
+        Ok(())
+    }
+
+    async fn get_pending_mint(&self, quote_id: &str) -> Result<Option<PendingMint>, Error> {
+        Ok(self.pending_mints.read().await.get(quote_id).cloned())
+    }
+
+    async fn get_pending_mints(&self) -> Result<Vec<PendingMint>, Error> {
+        Ok(self.pending_mints.read().await.values().cloned().collect())
+    }
+
+    async fn remove_pending_mint(&self, quote_id: &str) -> Result<(), Error> {
+        self.pending_mints.write().await.remove(quote_id);
+        Ok(())
+    }
+
     #[cfg(feature = "nostr")]
     async fn get_nostr_last_checked(
         &self,
@@ -17,6 +17,18 @@ pub enum Error {
     /// Amount is not what expected
     #[error("Amount miss match")]
     Amount,
+    /// Amount overflow
+    #[error("Amount overflow")]
+    AmountOverflow,
+    /// Amount cannot be converted between units
+    #[error("Cannot convert units")]
+    CannotConvertUnits,
+    /// Split values are more than the amount being split
+    #[error("Split values must be less than or equal to amount")]
+    SplitValuesGreater,
+    /// Amount cannot be split into the allowed number of outputs
+    #[error("Amount cannot be split into `{0}` outputs")]
+    SplitOutputsExceeded(usize),
     /// Token is already spent
     #[error("Token already spent")]
     TokenSpent,
@@ -77,10 +89,93 @@ pub enum Error {
     CustomError(String),
 }
 
+impl Error {
+    /// NUT-00 error code of the error, [`ErrorCode::Unknown`] if none applies
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            Self::TokenSpent => ErrorCode::TokenAlreadySpent,
+            Self::TokenNotVerified | Self::NUT11(_) => ErrorCode::TokenNotVerified,
+            Self::Amount => ErrorCode::TransactionUnbalanced,
+            _ => ErrorCode::Unknown(9999),
+        }
+    }
+
+    /// Kind of failure
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Self::TokenSpent
+            | Self::TokenNotVerified
+            | Self::MissingProofField
+            | Self::NUT11(_)
+            | Self::Base64Error(_)
+            | Self::Utf8ParseError(_) => ErrorCategory::InvalidToken,
+            Self::AmountKey
+            | Self::Amount
+            | Self::AmountOverflow
+            | Self::CannotConvertUnits
+            | Self::SplitValuesGreater
+            | Self::SplitOutputsExceeded(_)
+            | Self::InvoiceAmountUndefined
+            | Self::ParseInt(_)
+            | Self::UrlParseError(_) => ErrorCategory::InvalidInput,
+            #[cfg(feature = "wallet")]
+            Self::ReqwestError(_) => ErrorCategory::Network,
+            Self::SerdeJsonError(_) => ErrorCategory::Protocol,
+            Self::NoValidPoint
+            | Self::Secp256k1(_)
+            | Self::Secret(_)
+            | Self::Bip32(_)
+            | Self::HexError(_)
+            | Self::NUT01(_)
+            | Self::NUT02(_)
+            | Self::CustomError(_) => ErrorCategory::Internal,
+        }
+    }
+}
+
+/// Kind of a failure
+///
+/// Coarser than [`ErrorCode`] and stable across the errors of the crate, so
+/// apps and bindings can handle errors by kind without matching every variant.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// Mint could not be reached or the connection failed
+    Network,
+    /// Mint rejected the request or sent an unexpected response
+    Protocol,
+    /// Not enough funds for the operation
+    InsufficientFunds,
+    /// Token or proofs are malformed, spent or do not verify
+    InvalidToken,
+    /// Arguments or settings given by the caller are invalid
+    InvalidInput,
+    /// Storage backend failed
+    Database,
+    /// Anything else, likely a bug
+    Internal,
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Network => write!(f, "network"),
+            Self::Protocol => write!(f, "protocol"),
+            Self::InsufficientFunds => write!(f, "insufficient_funds"),
+            Self::InvalidToken => write!(f, "invalid_token"),
+            Self::InvalidInput => write!(f, "invalid_input"),
+            Self::Database => write!(f, "database"),
+            Self::Internal => write!(f, "internal"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ErrorResponse {
     pub code: ErrorCode,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub detail: Option<String>,
 }
 
@@ -115,27 +210,117 @@ impl ErrorResponse {
     }
 }
 
+/// Error codes defined in NUT-00
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub enum ErrorCode {
+    /// Blinded message of output already signed
+    BlindedMessageAlreadySigned,
+    /// Token could not be verified
+    TokenNotVerified,
     TokenAlreadySpent,
-    QuoteNotPaid,
+    /// Inputs and outputs are not balanced
+    TransactionUnbalanced,
+    /// Proof secret or witness longer than the mint accepts
+    SecretTooLong,
+    UnitUnsupported,
+    /// Amount outside of limit range
+    AmountOutofLimitRange,
+    DuplicateInputs,
+    DuplicateOutputs,
+    /// Inputs or outputs of multiple units
+    MultipleUnits,
     KeysetNotFound,
+    /// Keyset is inactive, cannot sign messages
+    KeysetInactive,
+    QuoteNotPaid,
+    /// Tokens have already been issued for quote
+    TokensAlreadyIssued,
+    MintingDisabled,
+    QuotePending,
+    InvoiceAlreadyPaid,
+    QuoteExpired,
+    /// Too many inputs or outputs in request
+    ///
+    /// Not defined in NUT-00.
+    RequestTooLarge,
+    /// Endpoint disabled by the mint operator
+    ///
+    /// Not defined in NUT-00, disabled minting uses [`Self::MintingDisabled`].
+    EndpointDisabled,
     Unknown(u16),
 }
 
+impl ErrorCode {
+    pub fn from_code(code: u16) -> Self {
+        match code {
+            10002 => Self::BlindedMessageAlreadySigned,
+            10003 => Self::TokenNotVerified,
+            11001 => Self::TokenAlreadySpent,
+            11002 => Self::TransactionUnbalanced,
+            11003 => Self::SecretTooLong,
+            11005 => Self::UnitUnsupported,
+            11006 => Self::AmountOutofLimitRange,
+            11007 => Self::DuplicateInputs,
+            11008 => Self::DuplicateOutputs,
+            11009 => Self::MultipleUnits,
+            12001 => Self::KeysetNotFound,
+            12002 => Self::KeysetInactive,
+            20001 => Self::QuoteNotPaid,
+            20002 => Self::TokensAlreadyIssued,
+            20003 => Self::MintingDisabled,
+            20005 => Self::QuotePending,
+            20006 => Self::InvoiceAlreadyPaid,
+            20007 => Self::QuoteExpired,
+            19001 => Self::RequestTooLarge,
+            19002 => Self::EndpointDisabled,
+            c => Self::Unknown(c),
+        }
+    }
+
+    /// Kind of failure the mint reported
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Self::BlindedMessageAlreadySigned
+            | Self::TokenNotVerified
+            | Self::TokenAlreadySpent
+            | Self::DuplicateInputs => ErrorCategory::InvalidToken,
+            _ => ErrorCategory::Protocol,
+        }
+    }
+
+    pub fn to_code(&self) -> u16 {
+        match self {
+            Self::BlindedMessageAlreadySigned => 10002,
+            Self::TokenNotVerified => 10003,
+            Self::TokenAlreadySpent => 11001,
+            Self::TransactionUnbalanced => 11002,
+            Self::SecretTooLong => 11003,
+            Self::UnitUnsupported => 11005,
+            Self::AmountOutofLimitRange => 11006,
+            Self::DuplicateInputs => 11007,
+            Self::DuplicateOutputs => 11008,
+            Self::MultipleUnits => 11009,
+            Self::KeysetNotFound => 12001,
+            Self::KeysetInactive => 12002,
+            Self::QuoteNotPaid => 20001,
+            Self::TokensAlreadyIssued => 20002,
+            Self::MintingDisabled => 20003,
+            Self::QuotePending => 20005,
+            Self::InvoiceAlreadyPaid => 20006,
+            Self::QuoteExpired => 20007,
+            Self::RequestTooLarge => 19001,
+            Self::EndpointDisabled => 19002,
+            Self::Unknown(code) => *code,
+        }
+    }
+}
+
 impl Serialize for ErrorCode {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let code = match self {
-            ErrorCode::TokenAlreadySpent => 11001,
-            ErrorCode::QuoteNotPaid => 20001,
-            ErrorCode::KeysetNotFound => 12001,
-            ErrorCode::Unknown(code) => *code,
-        };
-
-        serializer.serialize_u16(code)
+        serializer.serialize_u16(self.to_code())
     }
 }
 
@@ -146,25 +331,12 @@ impl<'de> Deserialize<'de> for ErrorCode {
     {
         let code = u16::deserialize(deserializer)?;
 
-        let error_code = match code {
-            11001 => ErrorCode::TokenAlreadySpent,
-            20001 => ErrorCode::QuoteNotPaid,
-            12001 => ErrorCode::KeysetNotFound,
-            c => ErrorCode::Unknown(c),
-        };
-
-        Ok(error_code)
+        Ok(Self::from_code(code))
     }
 }
 
 impl fmt::Display for ErrorCode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let code = match self {
-            Self::TokenAlreadySpent => 11001,
-            Self::QuoteNotPaid => 20001,
-            Self::KeysetNotFound => 12001,
-            Self::Unknown(code) => *code,
-        };
-        write!(f, "{}", code)
+        write!(f, "{}", self.to_code())
     }
 }
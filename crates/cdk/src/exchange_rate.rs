@@ -0,0 +1,384 @@
+//! Exchange rates for fiat units
+//!
+//! Amounts in fiat units like usd are denominated in the smallest unit of the
+//! currency (cents). An [`ExchangeRate`] provider returns the price of bitcoin
+//! in such a unit. The mint uses it to lock the conversion of fiat quotes,
+//! wallets can use it to show the value of a balance.
+//!
+//! Providers compose: [`MedianExchangeRate`] aggregates several sources and
+//! drops stale rates, [`CachedExchangeRate`] avoids querying a source more
+//! often than needed.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+use crate::nuts::CurrencyUnit;
+use crate::util::unix_time;
+use crate::Amount;
+
+/// Sats per bitcoin
+const SATS_PER_BTC: u128 = 100_000_000;
+
+/// Exchange Rate Error
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Provider has no rate for unit
+    #[error("No exchange rate for `{0}`")]
+    UnsupportedUnit(CurrencyUnit),
+    /// Rate is zero or could not be parsed
+    #[error("Invalid exchange rate")]
+    InvalidRate,
+    /// No source returned a rate recent enough
+    #[error("No fresh exchange rate for `{0}`")]
+    NoFreshRate(CurrencyUnit),
+    /// Converted amount does not fit
+    #[error("Amount overflow")]
+    Overflow,
+    /// Reqwest Error
+    #[cfg(feature = "exchange-rate")]
+    #[error(transparent)]
+    Reqwest(#[from] reqwest::Error),
+}
+
+/// Price of one bitcoin
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Rate {
+    pub unit: CurrencyUnit,
+    /// Price of one bitcoin in the smallest denomination of `unit`
+    pub per_btc: u64,
+    /// Unix time of the rate
+    pub timestamp: u64,
+}
+
+impl Rate {
+    /// Seconds since the rate was taken
+    pub fn age(&self) -> u64 {
+        unix_time().saturating_sub(self.timestamp)
+    }
+}
+
+/// Provider of bitcoin prices
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait ExchangeRate {
+    /// Current price of one bitcoin in `unit`
+    async fn rate(&self, unit: &CurrencyUnit) -> Result<Rate, Error>;
+}
+
+/// Convert `amount` of a unit priced at `per_btc` to sats
+///
+/// Rounds up so the invoice always covers the quote.
+pub fn unit_to_sat(amount: Amount, per_btc: u64) -> Result<Amount, Error> {
+    if per_btc == 0 {
+        return Err(Error::InvalidRate);
+    }
+
+    let sats = div_ceil(u64::from(amount) as u128 * SATS_PER_BTC, per_btc as u128);
+
+    Ok(Amount::from(
+        u64::try_from(sats).map_err(|_| Error::Overflow)?,
+    ))
+}
+
+/// Convert `sats` to a unit priced at `per_btc`
+///
+/// Rounds up so the quote always covers the invoice.
+pub fn sat_to_unit(sats: Amount, per_btc: u64) -> Result<Amount, Error> {
+    if per_btc == 0 {
+        return Err(Error::InvalidRate);
+    }
+
+    let amount = div_ceil(u64::from(sats) as u128 * per_btc as u128, SATS_PER_BTC);
+
+    Ok(Amount::from(
+        u64::try_from(amount).map_err(|_| Error::Overflow)?,
+    ))
+}
+
+fn div_ceil(a: u128, b: u128) -> u128 {
+    (a + b - 1) / b
+}
+
+/// Median of several sources
+///
+/// Sources that fail or return a rate older than `max_age` seconds are
+/// ignored, the rate fails only if no source has a fresh rate.
+pub struct MedianExchangeRate {
+    sources: Vec<Arc<dyn ExchangeRate + Send + Sync>>,
+    max_age: u64,
+}
+
+impl MedianExchangeRate {
+    /// Create new [`MedianExchangeRate`]
+    pub fn new(sources: Vec<Arc<dyn ExchangeRate + Send + Sync>>, max_age: u64) -> Self {
+        Self { sources, max_age }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl ExchangeRate for MedianExchangeRate {
+    async fn rate(&self, unit: &CurrencyUnit) -> Result<Rate, Error> {
+        let mut rates = Vec::with_capacity(self.sources.len());
+        for source in self.sources.iter() {
+            match source.rate(unit).await {
+                Ok(rate) if rate.age() <= self.max_age && rate.per_btc > 0 => rates.push(rate),
+                Ok(rate) => tracing::warn!("Ignoring stale exchange rate from {}", rate.timestamp),
+                Err(err) => tracing::warn!("Exchange rate source failed: {}", err),
+            }
+        }
+
+        if rates.is_empty() {
+            return Err(Error::NoFreshRate(unit.clone()));
+        }
+
+        rates.sort_by_key(|rate| rate.per_btc);
+        let middle = rates.len() / 2;
+        let per_btc = match rates.len() % 2 {
+            0 => (rates[middle - 1].per_btc + rates[middle].per_btc) / 2,
+            _ => rates[middle].per_btc,
+        };
+
+        Ok(Rate {
+            unit: unit.clone(),
+            per_btc,
+            // The median is as old as the oldest rate it is taken from
+            timestamp: rates.iter().map(|r| r.timestamp).min().unwrap_or_default(),
+        })
+    }
+}
+
+/// Cache in front of a provider
+///
+/// Rates are served from the cache until they are `ttl` seconds old.
+pub struct CachedExchangeRate<P> {
+    inner: P,
+    ttl: u64,
+    cache: RwLock<HashMap<CurrencyUnit, Rate>>,
+}
+
+impl<P> CachedExchangeRate<P>
+where
+    P: ExchangeRate,
+{
+    /// Create new [`CachedExchangeRate`]
+    pub fn new(inner: P, ttl: u64) -> Self {
+        Self {
+            inner,
+            ttl,
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<P> ExchangeRate for CachedExchangeRate<P>
+where
+    P: ExchangeRate + Send + Sync,
+{
+    async fn rate(&self, unit: &CurrencyUnit) -> Result<Rate, Error> {
+        if let Some(rate) = self.cache.read().await.get(unit) {
+            if rate.age() < self.ttl {
+                return Ok(rate.clone());
+            }
+        }
+
+        let rate = self.inner.rate(unit).await?;
+        self.cache.write().await.insert(unit.clone(), rate.clone());
+
+        Ok(rate)
+    }
+}
+
+/// Sample HTTP provider using the Coinbase spot price API
+#[cfg(feature = "exchange-rate")]
+#[derive(Debug, Clone)]
+pub struct HttpExchangeRate {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+#[cfg(feature = "exchange-rate")]
+impl HttpExchangeRate {
+    /// Coinbase API url
+    pub const COINBASE_URL: &'static str = "https://api.coinbase.com/v2/prices";
+
+    /// Create new [`HttpExchangeRate`] querying `base_url`
+    ///
+    /// Prices are fetched from `{base_url}/BTC-{CURRENCY}/spot`.
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "exchange-rate")]
+impl Default for HttpExchangeRate {
+    fn default() -> Self {
+        Self::new(Self::COINBASE_URL)
+    }
+}
+
+#[cfg(feature = "exchange-rate")]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl ExchangeRate for HttpExchangeRate {
+    async fn rate(&self, unit: &CurrencyUnit) -> Result<Rate, Error> {
+        #[derive(Deserialize)]
+        struct SpotPrice {
+            amount: String,
+        }
+
+        #[derive(Deserialize)]
+        struct SpotResponse {
+            data: SpotPrice,
+        }
+
+        let currency = match unit {
+            CurrencyUnit::Usd => "USD",
+            CurrencyUnit::Eur => "EUR",
+            unit => return Err(Error::UnsupportedUnit(unit.clone())),
+        };
+
+        let response: SpotResponse = self
+            .client
+            .get(format!("{}/BTC-{}/spot", self.base_url, currency))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let price: f64 = response
+            .data
+            .amount
+            .parse()
+            .map_err(|_| Error::InvalidRate)?;
+
+        // Both supported currencies have 100 cents
+        let per_btc = (price * 100.0).round();
+        if !per_btc.is_finite() || per_btc < 1.0 {
+            return Err(Error::InvalidRate);
+        }
+
+        Ok(Rate {
+            unit: unit.clone(),
+            per_btc: per_btc as u64,
+            timestamp: unix_time(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    struct FixedRate {
+        per_btc: u64,
+        age: u64,
+        calls: AtomicUsize,
+    }
+
+    impl FixedRate {
+        fn new(per_btc: u64, age: u64) -> Self {
+            Self {
+                per_btc,
+                age,
+                calls: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ExchangeRate for FixedRate {
+        async fn rate(&self, unit: &CurrencyUnit) -> Result<Rate, Error> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+
+            Ok(Rate {
+                unit: unit.clone(),
+                per_btc: self.per_btc,
+                timestamp: unix_time() - self.age,
+            })
+        }
+    }
+
+    struct Failing;
+
+    #[async_trait]
+    impl ExchangeRate for Failing {
+        async fn rate(&self, unit: &CurrencyUnit) -> Result<Rate, Error> {
+            Err(Error::UnsupportedUnit(unit.clone()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_median() {
+        let median = MedianExchangeRate::new(
+            vec![
+                Arc::new(FixedRate::new(6_000_000, 0)),
+                Arc::new(FixedRate::new(6_200_000, 10)),
+                Arc::new(FixedRate::new(5_900_000, 0)),
+                // Stale and failing sources are ignored
+                Arc::new(FixedRate::new(1, 600)),
+                Arc::new(Failing),
+            ],
+            60,
+        );
+
+        let rate = median.rate(&CurrencyUnit::Usd).await.unwrap();
+        assert_eq!(rate.per_btc, 6_000_000);
+        assert!(rate.age() >= 10);
+
+        let stale = MedianExchangeRate::new(vec![Arc::new(FixedRate::new(1, 600))], 60);
+        assert!(matches!(
+            stale.rate(&CurrencyUnit::Usd).await,
+            Err(Error::NoFreshRate(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cache() {
+        let cached = CachedExchangeRate::new(FixedRate::new(6_000_000, 0), 60);
+
+        cached.rate(&CurrencyUnit::Usd).await.unwrap();
+        cached.rate(&CurrencyUnit::Usd).await.unwrap();
+        assert_eq!(cached.inner.calls.load(Ordering::SeqCst), 1);
+
+        // Units are cached separately
+        cached.rate(&CurrencyUnit::Eur).await.unwrap();
+        assert_eq!(cached.inner.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_conversion() {
+        // $60,000.00 per bitcoin
+        let per_btc = 6_000_000;
+
+        assert_eq!(
+            unit_to_sat(Amount::from(100), per_btc).unwrap(),
+            Amount::from(1667)
+        );
+        assert_eq!(
+            sat_to_unit(Amount::from(1667), per_btc).unwrap(),
+            Amount::from(101)
+        );
+        assert_eq!(
+            sat_to_unit(Amount::from(100_000_000), per_btc).unwrap(),
+            Amount::from(per_btc)
+        );
+        assert!(matches!(
+            unit_to_sat(Amount::from(1), 0),
+            Err(Error::InvalidRate)
+        ));
+    }
+}
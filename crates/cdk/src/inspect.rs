@@ -0,0 +1,337 @@
+//! Offline inspection
+//!
+//! [`inspect`] decodes cashu tokens, bolt11 invoices and payment requests into
+//! a summary that can be shown to a user before receiving or paying. Nothing is checked against
+//! the mint, so proofs may be spent and DLEQ proofs are only reported, not
+//! verified.
+
+use std::str::FromStr;
+
+use lightning_invoice::Bolt11Invoice;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::mint_url::MintUrl;
+use crate::nuts::{
+    CurrencyUnit, Id, PaymentRequest, Proof, SpendingConditions, Token, TransportType,
+};
+use crate::Amount;
+
+/// Inspect Error
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Input is neither a token, an invoice nor a payment request
+    #[error("Unknown format")]
+    UnknownFormat,
+    /// CDK Error
+    #[error(transparent)]
+    Cdk(#[from] crate::error::Error),
+    /// NUT00 Error
+    #[error(transparent)]
+    NUT00(#[from] crate::nuts::nut00::Error),
+    /// NUT18 Error
+    #[error(transparent)]
+    NUT18(#[from] crate::nuts::nut18::Error),
+    /// Invoice Error
+    #[error(transparent)]
+    Invoice(#[from] lightning_invoice::ParseOrSemanticError),
+}
+
+/// Decoded input
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Inspection {
+    /// Cashu token
+    Token(TokenInspection),
+    /// Bolt11 invoice
+    Bolt11(Bolt11Inspection),
+    /// NUT-18 payment request
+    PaymentRequest(PaymentRequestInspection),
+}
+
+/// Summary of a cashu token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenInspection {
+    pub amount: Amount,
+    pub unit: Option<CurrencyUnit>,
+    pub memo: Option<String>,
+    pub mints: Vec<MintInspection>,
+}
+
+/// Proofs of a token from one mint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MintInspection {
+    pub mint_url: MintUrl,
+    pub amount: Amount,
+    pub keysets: Vec<KeysetInspection>,
+    pub proofs: Vec<ProofInspection>,
+}
+
+/// Value of a token per keyset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeysetInspection {
+    pub id: Id,
+    pub amount: Amount,
+    pub proof_count: usize,
+}
+
+/// Summary of a single proof
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofInspection {
+    pub amount: Amount,
+    pub keyset_id: Id,
+    /// NUT-10 spending conditions of the secret
+    pub spending_conditions: Option<SpendingConditions>,
+    pub has_dleq: bool,
+    pub has_witness: bool,
+}
+
+impl From<&Proof> for ProofInspection {
+    fn from(proof: &Proof) -> Self {
+        Self {
+            amount: proof.amount,
+            keyset_id: proof.keyset_id,
+            spending_conditions: SpendingConditions::try_from(&proof.secret).ok(),
+            has_dleq: proof.dleq.is_some(),
+            has_witness: proof.witness.is_some(),
+        }
+    }
+}
+
+impl TryFrom<&Token> for TokenInspection {
+    type Error = Error;
+
+    /// Fails if the amounts of the token overflow
+    fn try_from(token: &Token) -> Result<Self, Self::Error> {
+        let mut mints = Vec::with_capacity(token.token.len());
+        for mint_proofs in token.token.iter() {
+            let mut keysets: Vec<KeysetInspection> = Vec::new();
+            for proof in mint_proofs.proofs.iter() {
+                match keysets.iter_mut().find(|k| k.id == proof.keyset_id) {
+                    Some(keyset) => {
+                        keyset.amount = keyset
+                            .amount
+                            .checked_add(proof.amount)
+                            .ok_or(crate::error::Error::AmountOverflow)?;
+                        keyset.proof_count += 1;
+                    }
+                    None => keysets.push(KeysetInspection {
+                        id: proof.keyset_id,
+                        amount: proof.amount,
+                        proof_count: 1,
+                    }),
+                }
+            }
+
+            mints.push(MintInspection {
+                mint_url: mint_proofs.mint.clone(),
+                amount: Amount::try_sum(mint_proofs.proofs.iter().map(|p| p.amount))?,
+                keysets,
+                proofs: mint_proofs.proofs.iter().map(Into::into).collect(),
+            });
+        }
+
+        Ok(Self {
+            amount: Amount::try_sum(mints.iter().map(|m| m.amount))?,
+            unit: token.unit.clone(),
+            memo: token.memo.clone(),
+            mints,
+        })
+    }
+}
+
+/// Summary of a bolt11 invoice
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bolt11Inspection {
+    pub amount_msat: Option<u64>,
+    pub payment_hash: String,
+    pub description: Option<String>,
+    pub payee: Option<String>,
+    pub network: String,
+    /// Unix time the invoice was created
+    pub timestamp: u64,
+    /// Seconds after `timestamp` the invoice expires
+    pub expiry: u64,
+}
+
+impl From<&Bolt11Invoice> for Bolt11Inspection {
+    fn from(invoice: &Bolt11Invoice) -> Self {
+        let description = match invoice.description() {
+            lightning_invoice::Bolt11InvoiceDescription::Direct(description) => {
+                Some(description.to_string())
+            }
+            lightning_invoice::Bolt11InvoiceDescription::Hash(_) => None,
+        };
+
+        Self {
+            amount_msat: invoice.amount_milli_satoshis(),
+            payment_hash: invoice.payment_hash().to_string(),
+            description,
+            payee: invoice.payee_pub_key().map(|p| p.to_string()),
+            network: invoice.network().to_string(),
+            timestamp: invoice.duration_since_epoch().as_secs(),
+            expiry: invoice.expiry_time().as_secs(),
+        }
+    }
+}
+
+/// Summary of a NUT-18 payment request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaymentRequestInspection {
+    pub payment_id: Option<String>,
+    pub amount: Option<Amount>,
+    pub unit: Option<CurrencyUnit>,
+    pub single_use: bool,
+    /// Mints the payment is accepted from, any mint if empty
+    pub mints: Vec<MintUrl>,
+    pub description: Option<String>,
+    /// Transports in order of preference, none if the payment is handed over
+    /// out of band
+    pub transports: Vec<TransportInspection>,
+}
+
+/// Way a payment request is paid
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransportInspection {
+    pub transport_type: TransportType,
+    pub target: String,
+}
+
+impl From<&PaymentRequest> for PaymentRequestInspection {
+    fn from(request: &PaymentRequest) -> Self {
+        Self {
+            payment_id: request.payment_id.clone(),
+            amount: request.amount,
+            unit: request.unit.clone(),
+            single_use: request.single_use.unwrap_or_default(),
+            mints: request.mints.clone().unwrap_or_default(),
+            description: request.description.clone(),
+            transports: request
+                .transports
+                .iter()
+                .map(|transport| TransportInspection {
+                    transport_type: transport._type,
+                    target: transport.target.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Decode a token, bolt11 invoice or payment request without contacting the
+/// mint
+pub fn inspect(input: &str) -> Result<Inspection, Error> {
+    let input = input.trim();
+
+    if input.starts_with("cashu") {
+        let token = Token::from_str(input)?;
+        return Ok(Inspection::Token(TokenInspection::try_from(&token)?));
+    }
+
+    if input.starts_with("creq") {
+        let request = PaymentRequest::from_str(input)?;
+        return Ok(Inspection::PaymentRequest((&request).into()));
+    }
+
+    let invoice = input
+        .strip_prefix("lightning:")
+        .unwrap_or(input)
+        .to_lowercase();
+    if invoice.starts_with("ln") {
+        let invoice = Bolt11Invoice::from_str(&invoice)?;
+        return Ok(Inspection::Bolt11((&invoice).into()));
+    }
+
+    Err(Error::UnknownFormat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "cashuAeyJ0b2tlbiI6W3sibWludCI6Imh0dHBzOi8vODMzMy5zcGFjZTozMzM4IiwicHJvb2ZzIjpbeyJhbW91bnQiOjIsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6IjQwNzkxNWJjMjEyYmU2MWE3N2UzZTZkMmFlYjRjNzI3OTgwYmRhNTFjZDA2YTZhZmMyOWUyODYxNzY4YTc4MzciLCJDIjoiMDJiYzkwOTc5OTdkODFhZmIyY2M3MzQ2YjVlNDM0NWE5MzQ2YmQyYTUwNmViNzk1ODU5OGE3MmYwY2Y4NTE2M2VhIn0seyJhbW91bnQiOjgsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6ImZlMTUxMDkzMTRlNjFkNzc1NmIwZjhlZTBmMjNhNjI0YWNhYTNmNGUwNDJmNjE0MzNjNzI4YzcwNTdiOTMxYmUiLCJDIjoiMDI5ZThlNTA1MGI4OTBhN2Q2YzA5NjhkYjE2YmMxZDVkNWZhMDQwZWExZGUyODRmNmVjNjlkNjEyOTlmNjcxMDU5In1dfV0sInVuaXQiOiJzYXQiLCJtZW1vIjoiVGhhbmsgeW91LiJ9";
+    const INVOICE: &str = "lnbc25m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5vdhkven9v5sxyetpdeessp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygs9q5sqqqqqqqqqqqqqqqpqsq67gye39hfg3zd8rgc80k32tvy9xk2xunwm5lzexnvpx6fd77en8qaq424dxgt56cag2dpt359k3ssyhetktkpqh24jqnjyw6uqd08sgptq44qu";
+
+    #[test]
+    fn test_inspect_token() {
+        let inspection = match inspect(TOKEN).unwrap() {
+            Inspection::Token(inspection) => inspection,
+            inspection => panic!("Expected token, got {:?}", inspection),
+        };
+
+        assert_eq!(inspection.amount, Amount::from(10));
+        assert_eq!(inspection.unit, Some(CurrencyUnit::Sat));
+        assert_eq!(inspection.memo, Some("Thank you.".to_string()));
+
+        let mint = &inspection.mints[0];
+        assert_eq!(
+            mint.keysets,
+            vec![KeysetInspection {
+                id: Id::from_str("009a1f293253e41e").unwrap(),
+                amount: Amount::from(10),
+                proof_count: 2,
+            }]
+        );
+        assert!(mint
+            .proofs
+            .iter()
+            .all(|p| !p.has_dleq && p.spending_conditions.is_none()));
+    }
+
+    #[test]
+    fn test_inspect_invoice() {
+        let inspection = match inspect(&format!("lightning:{}", INVOICE)).unwrap() {
+            Inspection::Bolt11(inspection) => inspection,
+            inspection => panic!("Expected invoice, got {:?}", inspection),
+        };
+
+        assert_eq!(inspection.amount_msat, Some(2_500_000_000));
+        assert_eq!(inspection.description, Some("coffee beans".to_string()));
+
+        assert!(matches!(inspect("hello"), Err(Error::UnknownFormat)));
+    }
+
+    #[test]
+    fn test_inspect_token_overflow() {
+        let mut token = Token::from_str(TOKEN).unwrap();
+        token.token[0].proofs[0].amount = Amount::from(u64::MAX);
+
+        assert!(matches!(
+            TokenInspection::try_from(&token),
+            Err(Error::Cdk(crate::error::Error::AmountOverflow))
+        ));
+    }
+
+    #[test]
+    fn test_inspect_payment_request() {
+        let request = PaymentRequest {
+            payment_id: Some("b7a90176".to_string()),
+            amount: Some(Amount::from(10)),
+            unit: Some(CurrencyUnit::Sat),
+            single_use: Some(true),
+            mints: None,
+            description: Some("Coffee".to_string()),
+            transports: vec![crate::nuts::Transport {
+                _type: TransportType::HttpPost,
+                target: "https://shop.example.com/pay".to_string(),
+                tags: None,
+            }],
+        };
+
+        let inspection = match inspect(&request.to_string()).unwrap() {
+            Inspection::PaymentRequest(inspection) => inspection,
+            inspection => panic!("Expected payment request, got {:?}", inspection),
+        };
+
+        assert_eq!(inspection.amount, Some(Amount::from(10)));
+        assert!(inspection.single_use);
+        assert!(inspection.mints.is_empty());
+        assert_eq!(
+            inspection.transports,
+            vec![TransportInspection {
+                transport_type: TransportType::HttpPost,
+                target: "https://shop.example.com/pay".to_string(),
+            }]
+        );
+    }
+}
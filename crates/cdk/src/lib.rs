@@ -10,18 +10,21 @@ pub mod amount;
 pub mod cdk_database;
 pub mod dhke;
 pub mod error;
+pub mod exchange_rate;
+pub mod inspect;
 #[cfg(feature = "mint")]
 pub mod mint;
+pub mod mint_url;
 pub mod nuts;
 pub mod secret;
 pub mod types;
-pub mod url;
 pub mod util;
 #[cfg(feature = "wallet")]
 pub mod wallet;
+pub mod wire;
 
 pub use self::amount::Amount;
-pub use self::url::UncheckedUrl;
+pub use self::mint_url::MintUrl;
 pub use self::util::SECP256K1;
 #[cfg(feature = "wallet")]
 pub use self::wallet::client::HttpClient;
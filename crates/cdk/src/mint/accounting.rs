@@ -0,0 +1,190 @@
+//! Mint accounting
+//!
+//! Issued and redeemed totals derived from the blind signatures and spent,
+//! including archived, proofs persisted by the mint, and fees earned derived
+//! from the completed melts.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Error, Mint};
+use crate::nuts::{CurrencyUnit, Id};
+use crate::Amount;
+
+/// Balance of a single keyset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeysetBalance {
+    pub id: Id,
+    pub unit: CurrencyUnit,
+    /// Sum of all blind signatures issued by the keyset
+    pub issued: Amount,
+    /// Sum of all proofs of the keyset that have been spent
+    pub redeemed: Amount,
+    /// Ecash of the keyset still in circulation
+    pub outstanding: Amount,
+}
+
+/// Balance of all keysets of a unit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnitBalance {
+    pub unit: CurrencyUnit,
+    pub issued: Amount,
+    pub redeemed: Amount,
+    /// Ecash of the unit still in circulation the mint is liable for
+    pub outstanding: Amount,
+}
+
+/// Accounting report
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountingReport {
+    pub keysets: Vec<KeysetBalance>,
+    pub units: Vec<UnitBalance>,
+}
+
+/// Fees of the melts whose change is signed by a keyset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeysetFees {
+    pub id: Id,
+    pub unit: CurrencyUnit,
+    /// Number of melts
+    pub melts: u64,
+    /// Lightning fees paid for the invoices, covered by the fee reserve
+    pub lightning_fees: Amount,
+    /// Change not returned because too few outputs were provided, this is
+    /// what the mint earned
+    pub burnt_change: Amount,
+}
+
+/// Fees of the melts executed in a period
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeesReport {
+    /// Unix time the period starts, inclusive
+    pub start: u64,
+    /// Unix time the period ends, exclusive
+    pub end: u64,
+    pub keysets: Vec<KeysetFees>,
+}
+
+impl Mint {
+    /// Totals issued, redeemed and outstanding per keyset and unit
+    pub async fn accounting_report(&self) -> Result<AccountingReport, Error> {
+        let keyset_infos = self.localstore.get_keyset_infos().await?;
+
+        let mut totals: HashMap<Id, (Amount, Amount)> = keyset_infos
+            .iter()
+            .map(|info| (info.id, (Amount::ZERO, Amount::ZERO)))
+            .collect();
+
+        for signature in self.localstore.get_all_blinded_signatures().await? {
+            match totals.get_mut(&signature.keyset_id) {
+                Some((issued, _)) => *issued += signature.amount,
+                None => {
+                    tracing::warn!("Blind signature for unknown keyset {}", signature.keyset_id)
+                }
+            }
+        }
+
+        for proof in self.localstore.get_spent_proofs().await? {
+            match totals.get_mut(&proof.keyset_id) {
+                Some((_, redeemed)) => *redeemed += proof.amount,
+                None => tracing::warn!("Spent proof for unknown keyset {}", proof.keyset_id),
+            }
+        }
+
+        for (keyset_id, amount) in self.localstore.get_archived_amounts().await? {
+            match totals.get_mut(&keyset_id) {
+                Some((_, redeemed)) => *redeemed += amount,
+                None => tracing::warn!("Archived proofs for unknown keyset {}", keyset_id),
+            }
+        }
+
+        let mut report = AccountingReport::default();
+
+        for info in keyset_infos {
+            let (issued, redeemed) = totals.get(&info.id).copied().unwrap_or_default();
+            let outstanding = outstanding_amount(issued, redeemed);
+
+            match report.units.iter_mut().find(|b| b.unit.eq(&info.unit)) {
+                Some(unit_balance) => {
+                    unit_balance.issued += issued;
+                    unit_balance.redeemed += redeemed;
+                    unit_balance.outstanding =
+                        outstanding_amount(unit_balance.issued, unit_balance.redeemed);
+                }
+                None => report.units.push(UnitBalance {
+                    unit: info.unit.clone(),
+                    issued,
+                    redeemed,
+                    outstanding,
+                }),
+            }
+
+            report.keysets.push(KeysetBalance {
+                id: info.id,
+                unit: info.unit,
+                issued,
+                redeemed,
+                outstanding,
+            });
+        }
+
+        Ok(report)
+    }
+
+    /// Fees of the melts executed from `start` until `end` per change keyset
+    ///
+    /// Fees are accounted to the keyset the change is signed with, not to the
+    /// keysets of the inputs. Melts without a keyset, executed before fees
+    /// were recorded, are not included.
+    pub async fn fees_report(&self, start: u64, end: u64) -> Result<FeesReport, Error> {
+        let keyset_infos = self.localstore.get_keyset_infos().await?;
+
+        let mut report = FeesReport {
+            start,
+            end,
+            keysets: Vec::new(),
+        };
+
+        for melt in self.localstore.get_completed_melts().await? {
+            if melt.time < start || melt.time >= end {
+                continue;
+            }
+
+            let keyset_id = match melt.change_keyset_id {
+                Some(keyset_id) => keyset_id,
+                None => continue,
+            };
+
+            match report.keysets.iter_mut().find(|k| k.id == keyset_id) {
+                Some(fees) => {
+                    fees.melts += 1;
+                    fees.lightning_fees += melt.fee_paid;
+                    fees.burnt_change += melt.burnt_change;
+                }
+                None => match keyset_infos.iter().find(|info| info.id == keyset_id) {
+                    Some(info) => report.keysets.push(KeysetFees {
+                        id: keyset_id,
+                        unit: info.unit.clone(),
+                        melts: 1,
+                        lightning_fees: melt.fee_paid,
+                        burnt_change: melt.burnt_change,
+                    }),
+                    None => tracing::warn!("Completed melt for unknown keyset {}", keyset_id),
+                },
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+fn outstanding_amount(issued: Amount, redeemed: Amount) -> Amount {
+    match issued.checked_sub(redeemed) {
+        Some(outstanding) => outstanding,
+        None => {
+            tracing::warn!("Redeemed {} is more than issued {}", redeemed, issued);
+            Amount::ZERO
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! Spent proof archival
+//!
+//! The mint keeps every spent proof to reject double spends, so the spent set
+//! grows forever. Once a keyset no longer redeems proofs, its spent proofs
+//! only need to answer "is this `Y` spent", so [`Mint::archive_spent_proofs`]
+//! moves them to an archive that keeps only their `Y`s and total amount.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Error, Mint};
+use crate::nuts::Id;
+use crate::util::unix_time;
+
+/// Which spent proofs [`Mint::archive_spent_proofs`] archives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivePolicy {
+    /// Seconds after the final expiry of an inactive keyset its spent proofs
+    /// are archived
+    ///
+    /// The final expiry is the end of the validity of the keyset plus the
+    /// keyset grace period. Keysets without an end of validity are never
+    /// archived.
+    pub min_age: u64,
+}
+
+impl ArchivePolicy {
+    /// Archive the spent proofs of keysets expired for `days`
+    pub fn after_days(days: u64) -> Self {
+        Self {
+            min_age: days.saturating_mul(86_400),
+        }
+    }
+}
+
+/// Spent proofs archived of a keyset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedKeyset {
+    pub id: Id,
+    /// Number of proofs archived
+    pub proofs: u64,
+}
+
+impl Mint {
+    /// Archive the spent proofs of the keysets `policy` applies to
+    ///
+    /// Archived proofs are still reported spent and rejected as inputs, and
+    /// counted as redeemed in the accounting report. Returns the keysets
+    /// proofs were archived of.
+    pub async fn archive_spent_proofs(
+        &self,
+        policy: &ArchivePolicy,
+    ) -> Result<Vec<ArchivedKeyset>, Error> {
+        let grace_period = self.config.read().await.keyset_grace_period;
+        let now = unix_time();
+
+        let mut archived = Vec::new();
+
+        for keyset_info in self.localstore.get_keyset_infos().await? {
+            if keyset_info.active {
+                continue;
+            }
+
+            let expired = keyset_info
+                .final_expiry(grace_period)
+                .is_some_and(|final_expiry| final_expiry.saturating_add(policy.min_age) <= now);
+            if !expired {
+                continue;
+            }
+
+            let proofs = self
+                .localstore
+                .archive_spent_proofs(&keyset_info.id)
+                .await?;
+
+            if proofs > 0 {
+                tracing::info!(
+                    "Archived {} spent proofs of keyset {}",
+                    proofs,
+                    keyset_info.id
+                );
+                archived.push(ArchivedKeyset {
+                    id: keyset_info.id,
+                    proofs,
+                });
+            }
+        }
+
+        Ok(archived)
+    }
+}
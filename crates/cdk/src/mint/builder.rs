@@ -0,0 +1,321 @@
+//! Mint builder
+//!
+//! [`MintBuilder`] creates a [`Mint`] from named settings with defaults, and
+//! checks them once in [`MintBuilder::build`] instead of in every constructor.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use tokio::sync::RwLock;
+use zeroize::Zeroize;
+
+use super::error::Error;
+use super::fee_estimator::FeeEstimator;
+use super::lightning::{LightningRouter, LnBackend, LnKey};
+use super::proof_cache::VerifiedProofCache;
+use super::signatory::{MemorySignatory, Signatory};
+use super::{events, Endpoint, FeeReserve, Mint, MintConfig, RequestLimits};
+use crate::cdk_database::mint_memory::MintMemoryDatabase;
+use crate::cdk_database::{self, MintDatabase};
+use crate::exchange_rate::ExchangeRate;
+use crate::nuts::{ConditionRegistry, ConditionVerifier, CurrencyUnit, MintInfo, PaymentMethod};
+use crate::{Amount, Mnemonic};
+
+/// Seed lengths allowed by BIP-32
+const SEED_LEN: std::ops::RangeInclusive<usize> = 16..=64;
+
+/// Builder for [`Mint`]
+///
+/// Only the keys are required, set with [`MintBuilder::seed`],
+/// [`MintBuilder::mnemonic`] or [`MintBuilder::signatory`]. The mint stores
+/// its data in memory unless a [`MintBuilder::localstore`] is set.
+#[derive(Default)]
+pub struct MintBuilder {
+    signatory: Option<Arc<dyn Signatory + Send + Sync>>,
+    invalid_seed_len: Option<usize>,
+    mint_info: MintInfo,
+    localstore: Option<Arc<dyn MintDatabase<Err = cdk_database::Error> + Send + Sync>>,
+    fee_reserve: FeeReserve,
+    fee_reserves: HashMap<LnKey, FeeReserve>,
+    limits: RequestLimits,
+    invoice_description: Option<String>,
+    keyset_grace_period: u64,
+    disabled_endpoints: HashSet<Endpoint>,
+    exchange_rate: Option<Arc<dyn ExchangeRate + Send + Sync>>,
+    fee_estimator: Option<Arc<dyn FeeEstimator + Send + Sync>>,
+    lightning: LightningRouter,
+    custom_conditions: ConditionRegistry,
+    verified_proofs: Option<usize>,
+}
+
+impl MintBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Derive the keys of the mint from `seed`
+    pub fn seed(mut self, seed: &[u8]) -> Self {
+        match SEED_LEN.contains(&seed.len()) {
+            true => {
+                self.signatory = Some(Arc::new(MemorySignatory::new(seed)));
+                self.invalid_seed_len = None;
+            }
+            false => self.invalid_seed_len = Some(seed.len()),
+        }
+        self
+    }
+
+    /// Derive the keys of the mint from a BIP-39 mnemonic
+    pub fn mnemonic(self, mnemonic: &Mnemonic) -> Self {
+        let mut seed = mnemonic.to_seed_normalized("");
+        let builder = self.seed(&seed);
+        seed.zeroize();
+        builder
+    }
+
+    /// Sign with keys held by `signatory`
+    pub fn signatory(mut self, signatory: Arc<dyn Signatory + Send + Sync>) -> Self {
+        self.signatory = Some(signatory);
+        self.invalid_seed_len = None;
+        self
+    }
+
+    pub fn mint_info(mut self, mint_info: MintInfo) -> Self {
+        self.mint_info = mint_info;
+        self
+    }
+
+    pub fn localstore(
+        mut self,
+        localstore: Arc<dyn MintDatabase<Err = cdk_database::Error> + Send + Sync>,
+    ) -> Self {
+        self.localstore = Some(localstore);
+        self
+    }
+
+    /// Fee reserve of melt quotes, [`FeeReserve::default`] if not set
+    pub fn fee_reserve(mut self, min_fee_reserve: Amount, percent_fee_reserve: f32) -> Self {
+        self.fee_reserve = FeeReserve {
+            min_fee_reserve,
+            percent_fee_reserve,
+            max_fee_reserve: None,
+        };
+        self
+    }
+
+    /// Fee reserve of melt quotes of `unit` paid with `method`
+    pub fn method_fee_reserve(
+        mut self,
+        unit: CurrencyUnit,
+        method: PaymentMethod,
+        fee_reserve: FeeReserve,
+    ) -> Self {
+        self.fee_reserves
+            .insert(LnKey::new(unit, method), fee_reserve);
+        self
+    }
+
+    pub fn limits(mut self, limits: RequestLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// See [`MintConfig::invoice_description`]
+    pub fn invoice_description<S>(mut self, description: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.invoice_description = Some(description.into());
+        self
+    }
+
+    /// See [`MintConfig::keyset_grace_period`]
+    pub fn keyset_grace_period(mut self, seconds: u64) -> Self {
+        self.keyset_grace_period = seconds;
+        self
+    }
+
+    /// See [`MintConfig::disabled_endpoints`]
+    pub fn disable_endpoint(mut self, endpoint: Endpoint) -> Self {
+        self.disabled_endpoints.insert(endpoint);
+        self
+    }
+
+    pub fn exchange_rate(mut self, exchange_rate: Arc<dyn ExchangeRate + Send + Sync>) -> Self {
+        self.exchange_rate = Some(exchange_rate);
+        self
+    }
+
+    pub fn fee_estimator(mut self, fee_estimator: Arc<dyn FeeEstimator + Send + Sync>) -> Self {
+        self.fee_estimator = Some(fee_estimator);
+        self
+    }
+
+    /// Use `backend` for quotes of `unit` paid with `method`
+    pub fn lightning(
+        mut self,
+        unit: CurrencyUnit,
+        method: PaymentMethod,
+        backend: LnBackend,
+    ) -> Self {
+        self.lightning.register(LnKey::new(unit, method), backend);
+        self
+    }
+
+    /// Enforce the spending condition kind of `verifier`
+    pub fn condition_verifier(
+        mut self,
+        verifier: Arc<dyn ConditionVerifier + Send + Sync>,
+    ) -> Self {
+        self.custom_conditions.register(verifier);
+        self
+    }
+
+    /// Cache up to `capacity` proofs with a verified signature
+    pub fn verified_proof_cache(mut self, capacity: usize) -> Self {
+        self.verified_proofs = Some(capacity);
+        self
+    }
+
+    /// Check the settings and create the [`Mint`]
+    ///
+    /// Creates a sat keyset if the database has none.
+    pub async fn build(self) -> Result<Mint, Error> {
+        if let Some(len) = self.invalid_seed_len {
+            return Err(Error::InvalidBuilder(format!(
+                "Seed of {} bytes, expected 16 to 64",
+                len
+            )));
+        }
+        let signatory = self
+            .signatory
+            .ok_or_else(|| Error::InvalidBuilder("Seed or signatory required".to_string()))?;
+
+        let backend_fee_reserves = self
+            .lightning
+            .values()
+            .filter_map(|backend| backend.fee_reserve.as_ref());
+        let fee_reserves = std::iter::once(&self.fee_reserve)
+            .chain(self.fee_reserves.values())
+            .chain(backend_fee_reserves);
+        for fee_reserve in fee_reserves {
+            let percent_fee_reserve = fee_reserve.percent_fee_reserve;
+            if !(0.0..=100.0).contains(&percent_fee_reserve) {
+                return Err(Error::InvalidBuilder(format!(
+                    "Percent fee reserve {} outside of 0 to 100",
+                    percent_fee_reserve
+                )));
+            }
+            if let Some(max_fee_reserve) = fee_reserve.max_fee_reserve {
+                if max_fee_reserve < fee_reserve.min_fee_reserve {
+                    return Err(Error::InvalidBuilder(format!(
+                        "Maximum fee reserve {} below minimum {}",
+                        max_fee_reserve, fee_reserve.min_fee_reserve
+                    )));
+                }
+            }
+        }
+        if self.limits.max_inputs == 0 || self.limits.max_outputs == 0 {
+            return Err(Error::InvalidBuilder(
+                "Request limits must allow inputs and outputs".to_string(),
+            ));
+        }
+        if self.limits.max_secret_length == 0 || self.limits.max_body_size == 0 {
+            return Err(Error::InvalidBuilder(
+                "Request limits must allow secrets and bodies".to_string(),
+            ));
+        }
+
+        let localstore = match self.localstore {
+            Some(localstore) => localstore,
+            None => Arc::new(MintMemoryDatabase::new(
+                HashMap::new(),
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                HashMap::new(),
+            )?),
+        };
+
+        let keysets = Mint::init_keysets(signatory.as_ref(), localstore.as_ref()).await?;
+
+        Ok(Mint {
+            config: Arc::new(RwLock::new(MintConfig {
+                mint_info: self.mint_info,
+                fee_reserve: self.fee_reserve,
+                fee_reserves: self.fee_reserves,
+                limits: self.limits,
+                invoice_description: self.invoice_description,
+                keyset_grace_period: self.keyset_grace_period,
+                disabled_endpoints: self.disabled_endpoints,
+            })),
+            keysets: Arc::new(RwLock::new(keysets)),
+            signatory,
+            events: events::EventBus::default(),
+            exchange_rate: self.exchange_rate,
+            fee_estimator: self.fee_estimator,
+            lightning: self.lightning,
+            custom_conditions: self.custom_conditions,
+            verified_proofs: self.verified_proofs.map(VerifiedProofCache::new),
+            localstore,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mint_builder() {
+        let mint = MintBuilder::new()
+            .seed(&[1u8; 32])
+            .fee_reserve(Amount::from(4), 2.0)
+            .disable_endpoint(Endpoint::Restore)
+            .verified_proof_cache(10)
+            .build()
+            .await
+            .unwrap();
+
+        let config = mint.config().await;
+        assert_eq!(config.fee_reserve.min_fee_reserve, Amount::from(4));
+        assert!(!config.is_enabled(Endpoint::Restore));
+        assert!(mint.verified_proofs.is_some());
+        assert_eq!(mint.pubkeys().await.unwrap().keysets.len(), 1);
+
+        assert!(matches!(
+            MintBuilder::new().build().await,
+            Err(Error::InvalidBuilder(_))
+        ));
+        assert!(matches!(
+            MintBuilder::new().seed(&[1u8; 8]).build().await,
+            Err(Error::InvalidBuilder(_))
+        ));
+        assert!(matches!(
+            MintBuilder::new()
+                .seed(&[1u8; 32])
+                .fee_reserve(Amount::ZERO, 150.0)
+                .build()
+                .await,
+            Err(Error::InvalidBuilder(_))
+        ));
+        assert!(matches!(
+            MintBuilder::new()
+                .seed(&[1u8; 32])
+                .method_fee_reserve(
+                    CurrencyUnit::Sat,
+                    PaymentMethod::Bolt11,
+                    FeeReserve {
+                        min_fee_reserve: Amount::from(10),
+                        percent_fee_reserve: 1.0,
+                        max_fee_reserve: Some(Amount::from(5)),
+                    }
+                )
+                .build()
+                .await,
+            Err(Error::InvalidBuilder(_))
+        ));
+    }
+}
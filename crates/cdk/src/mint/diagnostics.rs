@@ -0,0 +1,158 @@
+//! Database diagnostics
+//!
+//! Checks of the mint database for operators, without reading the internal
+//! schemas of the storage backends. Together with [`Mint::keysets`],
+//! [`Mint::mint_quotes`] and [`Mint::accounting_report`] they cover what is
+//! needed to find out what went wrong with a mint.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Error, Mint};
+use crate::nuts::{CurrencyUnit, Id};
+use crate::Amount;
+
+/// Inconsistency found by [`Mint::check_database`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DatabaseIssue {
+    /// Keys derived from the seed at the path of the keyset have another id,
+    /// the mint runs with another seed than the one that created the keyset
+    KeysetDerivationMismatch { id: Id, derived: Id },
+    /// Active keyset of a unit without keyset info, or marked inactive
+    ActiveKeysetInvalid { unit: CurrencyUnit, id: Id },
+    /// Blind signatures or spent proofs of a keyset without keyset info
+    UnknownKeyset { id: Id },
+    /// More redeemed than issued by a keyset
+    RedeemedExceedsIssued {
+        id: Id,
+        issued: Amount,
+        redeemed: Amount,
+    },
+}
+
+impl Mint {
+    /// Number of spent proofs per keyset
+    ///
+    /// Archived proofs are not included, only their amounts are kept.
+    pub async fn spent_proof_counts(&self) -> Result<HashMap<Id, u64>, Error> {
+        let mut counts = HashMap::new();
+        for proof in self.localstore.get_spent_proofs().await? {
+            *counts.entry(proof.keyset_id).or_insert(0) += 1;
+        }
+
+        Ok(counts)
+    }
+
+    /// Id of the keys derived from the seed for keyset `id`
+    ///
+    /// Equal to `id` unless the seed or the stored derivation path changed.
+    pub async fn derive_keyset_id(&self, id: &Id) -> Result<Id, Error> {
+        let keyset_info = self
+            .localstore
+            .get_keyset_info(id)
+            .await?
+            .ok_or(Error::UnknownKeySet)?;
+        let keyset = self.signatory.keyset(&keyset_info).await?;
+
+        Ok(Id::try_from(&keyset.keys).map_err(crate::error::Error::from)?)
+    }
+
+    /// Check the invariants of the mint database
+    ///
+    /// Returns the issues found, empty if the database is consistent.
+    pub async fn check_database(&self) -> Result<Vec<DatabaseIssue>, Error> {
+        let mut issues = Vec::new();
+        let keyset_infos = self.localstore.get_keyset_infos().await?;
+
+        for info in &keyset_infos {
+            let derived = self.derive_keyset_id(&info.id).await?;
+            if derived != info.id {
+                issues.push(DatabaseIssue::KeysetDerivationMismatch {
+                    id: info.id,
+                    derived,
+                });
+            }
+        }
+
+        for (unit, id) in self.localstore.get_active_keysets().await? {
+            match keyset_infos.iter().find(|info| info.id == id) {
+                Some(info) if info.active => (),
+                _ => issues.push(DatabaseIssue::ActiveKeysetInvalid { unit, id }),
+            }
+        }
+
+        let unknown: HashSet<Id> = self
+            .localstore
+            .get_all_blinded_signatures()
+            .await?
+            .into_iter()
+            .map(|s| s.keyset_id)
+            .chain(self.spent_proof_counts().await?.into_keys())
+            .chain(self.localstore.get_archived_amounts().await?.into_keys())
+            .filter(|id| !keyset_infos.iter().any(|info| info.id == *id))
+            .collect();
+        let mut unknown: Vec<Id> = unknown.into_iter().collect();
+        unknown.sort_by_key(|id| id.to_string());
+        issues.extend(
+            unknown
+                .into_iter()
+                .map(|id| DatabaseIssue::UnknownKeyset { id }),
+        );
+
+        for keyset in self.accounting_report().await?.keysets {
+            if keyset.redeemed > keyset.issued {
+                issues.push(DatabaseIssue::RedeemedExceedsIssued {
+                    id: keyset.id,
+                    issued: keyset.issued,
+                    redeemed: keyset.redeemed,
+                });
+            }
+        }
+
+        Ok(issues)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::SplitTarget;
+    use crate::mint::tests::{active_keyset_id, create_mint, mint_proofs};
+    use crate::nuts::{MintInfo, PreMintSecrets, SwapRequest};
+
+    #[tokio::test]
+    async fn test_check_database() {
+        let mint = create_mint().await;
+        let proofs = mint_proofs(&mint, Amount::from(8)).await;
+        let keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+
+        let premint =
+            PreMintSecrets::random(keyset_id, Amount::from(8), &SplitTarget::None).unwrap();
+        mint.process_swap_request(SwapRequest::new(proofs, premint.blinded_messages()))
+            .await
+            .unwrap();
+
+        assert_eq!(mint.spent_proof_counts().await.unwrap()[&keyset_id], 1);
+        assert_eq!(mint.derive_keyset_id(&keyset_id).await.unwrap(), keyset_id);
+        assert!(mint.check_database().await.unwrap().is_empty());
+
+        // Mint started with another seed on the same database
+        let other = Mint::new(
+            &[1; 32],
+            MintInfo::default(),
+            mint.localstore.clone(),
+            Amount::ZERO,
+            0.0,
+        )
+        .await
+        .unwrap();
+
+        let issues = other.check_database().await.unwrap();
+        assert!(matches!(
+            issues.as_slice(),
+            [DatabaseIssue::KeysetDerivationMismatch { id, .. }] if *id == keyset_id
+        ));
+    }
+}
@@ -2,7 +2,7 @@ use http::StatusCode;
 use thiserror::Error;
 
 use crate::cdk_database;
-use crate::error::{ErrorCode, ErrorResponse};
+use crate::error::{ErrorCategory, ErrorCode, ErrorResponse};
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -12,12 +12,21 @@ pub enum Error {
     /// Inactive Keyset
     #[error("Inactive Keyset")]
     InactiveKeyset,
+    #[error("Keyset expired")]
+    ExpiredKeyset,
+    #[error("Invalid keyset parameters: `{0}`")]
+    InvalidKeysetParameters(String),
+    /// Setting of a [`MintBuilder`](super::MintBuilder) missing or invalid
+    #[error("Invalid mint builder: `{0}`")]
+    InvalidBuilder(String),
     #[error("No key for amount")]
     AmountKey,
     #[error("Amount")]
     Amount,
     #[error("Duplicate proofs")]
     DuplicateProofs,
+    #[error("Duplicate outputs")]
+    DuplicateOutputs,
     #[error("Token Already Spent")]
     TokenAlreadySpent,
     #[error("Token Pending")]
@@ -30,8 +39,45 @@ pub enum Error {
     UnknownSecretKind,
     #[error("Cannot have multiple units")]
     MultipleUnits,
+    #[error("Unit not supported")]
+    UnsupportedUnit,
     #[error("Blinded Message is already signed")]
     BlindedMessageAlreadySigned,
+    #[error("Request already paid")]
+    RequestAlreadyPaid,
+    #[error("Quote pending")]
+    PendingQuote,
+    #[error("Too many inputs: `{0}`, max `{1}`")]
+    TooManyInputs(usize, usize),
+    #[error("Too many outputs: `{0}`, max `{1}`")]
+    TooManyOutputs(usize, usize),
+    #[error("Secret too long: `{0}` bytes, max `{1}`")]
+    SecretTooLong(usize, usize),
+    #[error("Witness too long: `{0}` bytes, max `{1}`")]
+    WitnessTooLong(usize, usize),
+    #[error("Request body too large: `{0}` bytes, max `{1}`")]
+    BodyTooLarge(usize, usize),
+    /// Amount outside the min and max amount of the payment method
+    #[error("Amount `{0}` outside of limit range")]
+    AmountOutOfLimitRange(crate::Amount),
+    /// Endpoint disabled in the [`MintConfig`](super::MintConfig)
+    #[error("`{0}` is not supported by this mint")]
+    EndpointDisabled(super::Endpoint),
+    #[error("No Lightning backend for `{0}` `{1}`")]
+    LightningBackendUnavailable(crate::nuts::CurrencyUnit, crate::nuts::PaymentMethod),
+    #[error("No exchange rate provider")]
+    ExchangeRateUnavailable,
+    #[error("Invoice description not supported")]
+    InvoiceDescriptionNotSupported,
+    #[error("Invoice description longer than `{0}` bytes")]
+    InvoiceDescriptionTooLong(usize),
+    #[error("Signing policy violated: `{0}`")]
+    SigningPolicy(String),
+    /// Proof does not meet its custom spending condition
+    #[error("Spending condition not met: {0}")]
+    SpendingConditionNotMet(crate::error::Error),
+    #[error(transparent)]
+    ExchangeRate(#[from] crate::exchange_rate::Error),
     #[error(transparent)]
     Cashu(#[from] crate::error::Error),
     #[error(transparent)]
@@ -46,37 +92,114 @@ pub enum Error {
     Nut14(#[from] crate::nuts::nut14::Error),
     /// Database Error
     #[error(transparent)]
-    Database(#[from] cdk_database::Error),
+    Database(cdk_database::Error),
+    /// Serde Error
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+    /// WebSocket Error
+    #[cfg(all(feature = "ws", not(target_arch = "wasm32")))]
+    #[error(transparent)]
+    WebSocket(Box<tokio_tungstenite::tungstenite::Error>),
     #[error("`{0}`")]
     Custom(String),
 }
 
+#[cfg(all(feature = "ws", not(target_arch = "wasm32")))]
+impl From<tokio_tungstenite::tungstenite::Error> for Error {
+    fn from(err: tokio_tungstenite::tungstenite::Error) -> Self {
+        Self::WebSocket(Box::new(err))
+    }
+}
+
+impl From<cdk_database::Error> for Error {
+    fn from(e: cdk_database::Error) -> Self {
+        match e {
+            cdk_database::Error::ProofAlreadySpent => Self::TokenAlreadySpent,
+            cdk_database::Error::UnknownQuote => Self::UnknownQuote,
+            e => Self::Database(e),
+        }
+    }
+}
+
 impl From<Error> for cdk_database::Error {
     fn from(e: Error) -> Self {
         Self::Database(Box::new(e))
     }
 }
 
+impl Error {
+    /// NUT-00 error code of the error
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            Self::BlindedMessageAlreadySigned => ErrorCode::BlindedMessageAlreadySigned,
+            Self::NUT11(_)
+            | Self::Nut12(_)
+            | Self::Nut14(_)
+            | Self::SpendingConditionNotMet(_)
+            | Self::Cashu(crate::error::Error::TokenNotVerified) => ErrorCode::TokenNotVerified,
+            Self::TokenAlreadySpent => ErrorCode::TokenAlreadySpent,
+            Self::Amount => ErrorCode::TransactionUnbalanced,
+            Self::UnsupportedUnit | Self::LightningBackendUnavailable(_, _) => {
+                ErrorCode::UnitUnsupported
+            }
+            Self::AmountOutOfLimitRange(_) => ErrorCode::AmountOutofLimitRange,
+            Self::DuplicateProofs => ErrorCode::DuplicateInputs,
+            Self::DuplicateOutputs => ErrorCode::DuplicateOutputs,
+            Self::MultipleUnits => ErrorCode::MultipleUnits,
+            Self::UnknownKeySet => ErrorCode::KeysetNotFound,
+            Self::InactiveKeyset | Self::ExpiredKeyset => ErrorCode::KeysetInactive,
+            Self::UnpaidQuote => ErrorCode::QuoteNotPaid,
+            Self::RequestAlreadyPaid => ErrorCode::InvoiceAlreadyPaid,
+            Self::PendingQuote => ErrorCode::QuotePending,
+            Self::TooManyInputs(_, _) | Self::TooManyOutputs(_, _) | Self::BodyTooLarge(_, _) => {
+                ErrorCode::RequestTooLarge
+            }
+            Self::SecretTooLong(_, _) | Self::WitnessTooLong(_, _) => ErrorCode::SecretTooLong,
+            Self::EndpointDisabled(super::Endpoint::Mint) => ErrorCode::MintingDisabled,
+            Self::EndpointDisabled(_) => ErrorCode::EndpointDisabled,
+            _ => ErrorCode::Unknown(9999),
+        }
+    }
+
+    /// Kind of failure
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Self::Database(_) => ErrorCategory::Database,
+            Self::Cashu(err) => err.category(),
+            Self::ExchangeRateUnavailable | Self::ExchangeRate(_) => ErrorCategory::Network,
+            #[cfg(all(feature = "ws", not(target_arch = "wasm32")))]
+            Self::WebSocket(_) => ErrorCategory::Network,
+            Self::TokenPending
+            | Self::UnknownSecretKind
+            | Self::DuplicateProofs
+            | Self::Secret(_)
+            | Self::NUT00(_) => ErrorCategory::InvalidToken,
+            Self::InvalidKeysetParameters(_) | Self::InvalidBuilder(_) | Self::Custom(_) => {
+                ErrorCategory::Internal
+            }
+            _ => self.error_code().category(),
+        }
+    }
+
+    /// Body returned to wallets `{ "detail": .., "code": .. }`
+    pub fn to_error_response(&self) -> ErrorResponse {
+        ErrorResponse {
+            code: self.error_code(),
+            error: None,
+            detail: Some(self.to_string()),
+        }
+    }
+}
+
 impl From<Error> for ErrorResponse {
     fn from(err: Error) -> ErrorResponse {
-        match err {
-            Error::TokenAlreadySpent => ErrorResponse {
-                code: ErrorCode::TokenAlreadySpent,
-                error: Some(err.to_string()),
-                detail: None,
-            },
-            _ => ErrorResponse {
-                code: ErrorCode::Unknown(9999),
-                error: Some(err.to_string()),
-                detail: None,
-            },
-        }
+        err.to_error_response()
     }
 }
 
 impl From<Error> for (StatusCode, ErrorResponse) {
     fn from(err: Error) -> (StatusCode, ErrorResponse) {
-        (StatusCode::NOT_FOUND, err.into())
+        (StatusCode::BAD_REQUEST, err.into())
     }
 }
 
@@ -97,4 +220,42 @@ mod tests {
 
         assert_eq!(response.code, error_response.code);
     }
+
+    #[test]
+    fn test_error_response_codes() {
+        let response = Error::InactiveKeyset.to_error_response();
+
+        assert_eq!(
+            serde_json::to_string(&response).unwrap(),
+            r#"{"code":12002,"detail":"Inactive Keyset"}"#
+        );
+
+        assert_eq!(Error::DuplicateOutputs.error_code().to_code(), 11008);
+        assert_eq!(Error::UnpaidQuote.error_code().to_code(), 20001);
+        assert_eq!(
+            Error::Cashu(crate::error::Error::TokenNotVerified).error_code(),
+            ErrorCode::TokenNotVerified
+        );
+    }
+
+    #[test]
+    fn test_error_category() {
+        assert_eq!(
+            Error::TokenAlreadySpent.category(),
+            ErrorCategory::InvalidToken
+        );
+        assert_eq!(Error::UnpaidQuote.category(), ErrorCategory::Protocol);
+        assert_eq!(
+            Error::Cashu(crate::error::Error::AmountOverflow).category(),
+            ErrorCategory::InvalidInput
+        );
+        assert_eq!(
+            Error::Database(cdk_database::Error::Database("closed".into())).category(),
+            ErrorCategory::Database
+        );
+        assert_eq!(
+            serde_json::to_string(&ErrorCategory::InsufficientFunds).unwrap(),
+            format!("\"{}\"", ErrorCategory::InsufficientFunds)
+        );
+    }
 }
@@ -0,0 +1,125 @@
+//! Mint events
+//!
+//! The [`Mint`](super::Mint) publishes an event on its [`EventBus`] whenever a
+//! quote changes state, proofs are spent or a keyset is rotated. Webhooks,
+//! subscriptions and metrics subscribe to the bus instead of hooking into the
+//! request handlers.
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::nuts::{CurrencyUnit, Id, PublicKey};
+use crate::Amount;
+
+/// Events buffered per subscriber before it starts lagging
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Mint event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum MintEvent {
+    /// Mint quote has been paid
+    MintQuotePaid {
+        quote: String,
+        amount: Amount,
+        unit: CurrencyUnit,
+    },
+    /// Ecash has been issued for a mint quote
+    MintQuoteIssued {
+        quote: String,
+        amount: Amount,
+        unit: CurrencyUnit,
+    },
+    /// Melt request has been processed
+    MeltCompleted {
+        quote: String,
+        amount: Amount,
+        fee_paid: Amount,
+        unit: CurrencyUnit,
+    },
+    /// Melt request has been rejected
+    MeltFailed { quote: String, reason: String },
+    /// Proofs have been spent in a swap or melt
+    ProofsSpent { ys: Vec<PublicKey> },
+    /// Proofs are pending in a melt
+    ProofsPending { ys: Vec<PublicKey> },
+    /// Proofs of a failed melt are unspent again
+    ProofsReleased { ys: Vec<PublicKey> },
+    /// New active keyset
+    KeysetRotated { id: Id, unit: CurrencyUnit },
+}
+
+/// In process publish/subscribe channel for [`MintEvent`]s
+///
+/// Every subscriber receives every event published after it subscribed.
+/// Subscribers that fall more than the channel capacity behind miss the oldest
+/// events and get [`broadcast::error::RecvError::Lagged`].
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<MintEvent>,
+}
+
+impl EventBus {
+    /// Create new [`EventBus`] buffering `capacity` events per subscriber
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+
+        Self { sender }
+    }
+
+    /// Publish event to all current subscribers
+    pub fn publish(&self, event: MintEvent) {
+        tracing::trace!("Publishing mint event: {:?}", event);
+
+        // Sending only fails when there are no subscribers
+        let _ = self.sender.send(event);
+    }
+
+    /// Receive events published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<MintEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Number of current subscribers
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_publish_subscribe() {
+        let bus = EventBus::new(2);
+
+        // Publishing without subscribers is not an error
+        bus.publish(MintEvent::MeltFailed {
+            quote: "quote".to_string(),
+            reason: "Unknown quote".to_string(),
+        });
+
+        let mut first = bus.subscribe();
+        let mut second = bus.subscribe();
+        assert_eq!(bus.subscriber_count(), 2);
+
+        let event = MintEvent::KeysetRotated {
+            id: Id::from_str("009a1f293253e41e").unwrap(),
+            unit: CurrencyUnit::Sat,
+        };
+        bus.publish(event.clone());
+
+        assert_eq!(first.recv().await.unwrap(), event);
+        assert_eq!(second.recv().await.unwrap(), event);
+        assert!(first.try_recv().is_err());
+    }
+}
@@ -0,0 +1,22 @@
+//! Fee estimation
+//!
+//! The configured [`FeeReserve`](super::FeeReserve) has to cover the worst
+//! case route, so most melts return a large part of the reserve as NUT-08
+//! change. A Lightning backend that can probe or query a route implements
+//! [`FeeEstimator`] to give [`Mint::melt_fee_reserve`](super::Mint::melt_fee_reserve)
+//! a route-aware estimate instead.
+
+use async_trait::async_trait;
+
+use super::Error;
+use crate::{Amount, Bolt11Invoice};
+
+/// Routing fee estimate of a Lightning backend
+#[async_trait]
+pub trait FeeEstimator {
+    /// Fee in sats the backend expects to pay for `invoice`
+    ///
+    /// Returns `None` if no route could be estimated. The backend should cap
+    /// the fee of the payment at the estimate, it is used as fee reserve.
+    async fn estimate_fee(&self, invoice: &Bolt11Invoice) -> Result<Option<Amount>, Error>;
+}
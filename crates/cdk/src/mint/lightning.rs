@@ -0,0 +1,290 @@
+//! Lightning backend
+//!
+//! A backend implementing [`MintLightning`] streams the payment hashes of the
+//! invoices it receives a payment for. [`Mint::wait_for_paid_invoices`] marks
+//! the mint quotes of these invoices paid as soon as the payment arrives, which
+//! publishes [`MintEvent::MintQuotePaid`](super::events::MintEvent::MintQuotePaid)
+//! to NUT-17 subscribers, instead of checking the node for every quote.
+//!
+//! A mint can use a different backend for each unit and payment method, e.g.
+//! a node for sat bolt11 payments and an exchange for usd. [`LightningRouter`]
+//! holds the backend of each pair, with the fee reserve of melts paid with it.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use super::{Error, FeeReserve, Mint};
+use crate::nuts::{CurrencyUnit, PaymentMethod};
+
+/// Payment hashes of paid invoices, hex encoded
+pub type PaidInvoiceStream = Pin<Box<dyn Stream<Item = String> + Send>>;
+
+/// Lightning backend of the mint
+#[async_trait]
+pub trait MintLightning {
+    /// Stream of invoices paid from now on
+    ///
+    /// The stream ends when the connection to the node is lost.
+    async fn wait_any_invoice(&self) -> Result<PaidInvoiceStream, Error>;
+}
+
+/// Unit and payment method a backend is used for
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LnKey {
+    pub unit: CurrencyUnit,
+    pub method: PaymentMethod,
+}
+
+impl LnKey {
+    pub fn new(unit: CurrencyUnit, method: PaymentMethod) -> Self {
+        Self { unit, method }
+    }
+}
+
+/// Lightning backend and its settings
+#[derive(Clone)]
+pub struct LnBackend {
+    pub lightning: Arc<dyn MintLightning + Send + Sync>,
+    /// Fee reserve of melts paid with the backend
+    ///
+    /// The [`MintConfig::fee_reserve`](super::MintConfig::fee_reserve) if `None`.
+    pub fee_reserve: Option<FeeReserve>,
+}
+
+impl LnBackend {
+    pub fn new(lightning: Arc<dyn MintLightning + Send + Sync>) -> Self {
+        Self {
+            lightning,
+            fee_reserve: None,
+        }
+    }
+
+    /// Use `fee_reserve` for melts paid with the backend
+    pub fn fee_reserve(mut self, fee_reserve: FeeReserve) -> Self {
+        self.fee_reserve = Some(fee_reserve);
+        self
+    }
+}
+
+/// Lightning backends of the mint by unit and payment method
+#[derive(Clone, Default)]
+pub struct LightningRouter {
+    backends: HashMap<LnKey, LnBackend>,
+}
+
+impl LightningRouter {
+    /// Use `backend` for `key`, replacing the backend it had
+    pub fn register(&mut self, key: LnKey, backend: LnBackend) {
+        self.backends.insert(key, backend);
+    }
+
+    /// Backend of `unit` and `method`
+    pub fn get(&self, unit: &CurrencyUnit, method: &PaymentMethod) -> Option<&LnBackend> {
+        self.backends.get(&LnKey::new(unit.clone(), method.clone()))
+    }
+
+    /// Units and payment methods with a backend
+    pub fn keys(&self) -> Vec<LnKey> {
+        self.backends.keys().cloned().collect()
+    }
+
+    /// Registered backends, once even if used for several keys
+    pub fn backends(&self) -> Vec<Arc<dyn MintLightning + Send + Sync>> {
+        let mut backends: Vec<Arc<dyn MintLightning + Send + Sync>> = Vec::new();
+        for backend in self.backends.values() {
+            if !backends.iter().any(|b| Arc::ptr_eq(b, &backend.lightning)) {
+                backends.push(backend.lightning.clone());
+            }
+        }
+
+        backends
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.backends.is_empty()
+    }
+
+    pub(crate) fn values(&self) -> impl Iterator<Item = &LnBackend> {
+        self.backends.values()
+    }
+}
+
+impl fmt::Debug for LightningRouter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LightningRouter")
+            .field("keys", &self.keys())
+            .finish()
+    }
+}
+
+impl Mint {
+    /// Backend for quotes of `unit` paid with `method`
+    pub fn lightning_backend(
+        &self,
+        unit: &CurrencyUnit,
+        method: &PaymentMethod,
+    ) -> Result<&LnBackend, Error> {
+        self.lightning
+            .get(unit, method)
+            .ok_or_else(|| Error::LightningBackendUnavailable(unit.clone(), method.clone()))
+    }
+
+    /// Settle the mint quotes of invoices paid to any backend of
+    /// [`Mint::lightning`]
+    ///
+    /// Runs until the streams of all backends end or one fails.
+    pub async fn wait_for_all_paid_invoices(&self) -> Result<(), Error> {
+        let tasks = self
+            .lightning
+            .backends()
+            .into_iter()
+            .map(|backend| self.wait_for_paid_invoices(backend));
+        futures::future::try_join_all(tasks).await?;
+
+        Ok(())
+    }
+
+    /// Settle the mint quotes of invoices paid to `backend`
+    ///
+    /// Runs until the stream of the backend ends, the caller should spawn it
+    /// and call it again to reconnect. Payments for invoices without a quote
+    /// are ignored.
+    pub async fn wait_for_paid_invoices(
+        &self,
+        backend: Arc<dyn MintLightning + Send + Sync>,
+    ) -> Result<(), Error> {
+        let mut invoices = backend.wait_any_invoice().await?;
+
+        while let Some(payment_hash) = invoices.next().await {
+            match self.pay_mint_quote_by_payment_hash(&payment_hash).await {
+                Ok(Some(quote)) => tracing::debug!("Mint quote {} paid", quote.id),
+                Ok(None) => tracing::debug!("No mint quote for invoice {}", payment_hash),
+                Err(err) => tracing::error!(
+                    "Could not settle mint quote of invoice {}: {}",
+                    payment_hash,
+                    err
+                ),
+            }
+        }
+
+        tracing::warn!("Stream of paid invoices ended");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::mint::events::MintEvent;
+    use crate::mint::tests::create_mint;
+    use crate::mint_url::MintUrl;
+    use crate::nuts::CurrencyUnit;
+    use crate::util::unix_time;
+    use crate::{Amount, Bolt11Invoice};
+
+    const REQUEST: &str = "lnbc25m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5vdhkven9v5sxyetpdeessp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygs9q5sqqqqqqqqqqqqqqqpqsq67gye39hfg3zd8rgc80k32tvy9xk2xunwm5lzexnvpx6fd77en8qaq424dxgt56cag2dpt359k3ssyhetktkpqh24jqnjyw6uqd08sgptq44qu";
+
+    struct PaidInvoices(Vec<String>);
+
+    #[async_trait]
+    impl MintLightning for PaidInvoices {
+        async fn wait_any_invoice(&self) -> Result<PaidInvoiceStream, Error> {
+            Ok(futures::stream::iter(self.0.clone()).boxed())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lightning_router() {
+        let mut mint = create_mint().await;
+        let sat_backend: Arc<dyn MintLightning + Send + Sync> = Arc::new(PaidInvoices(vec![]));
+        let usd_backend: Arc<dyn MintLightning + Send + Sync> = Arc::new(PaidInvoices(vec![]));
+        let sat_fee_reserve = FeeReserve {
+            min_fee_reserve: Amount::ZERO,
+            percent_fee_reserve: 0.5,
+            max_fee_reserve: None,
+        };
+
+        mint.lightning.register(
+            LnKey::new(CurrencyUnit::Sat, PaymentMethod::Bolt11),
+            LnBackend::new(sat_backend.clone()).fee_reserve(sat_fee_reserve),
+        );
+        mint.lightning.register(
+            LnKey::new(CurrencyUnit::Msat, PaymentMethod::Bolt11),
+            LnBackend::new(sat_backend.clone()),
+        );
+        mint.lightning.register(
+            LnKey::new(CurrencyUnit::Usd, PaymentMethod::Bolt11),
+            LnBackend::new(usd_backend.clone()),
+        );
+
+        let backend = mint
+            .lightning_backend(&CurrencyUnit::Usd, &PaymentMethod::Bolt11)
+            .unwrap();
+        assert!(Arc::ptr_eq(&backend.lightning, &usd_backend));
+        assert!(matches!(
+            mint.lightning_backend(&CurrencyUnit::Sat, &PaymentMethod::from("bolt12")),
+            Err(Error::LightningBackendUnavailable(_, _))
+        ));
+
+        // Fee reserve of the sat backend, the configured 1% for msat
+        let invoice = Bolt11Invoice::from_str(REQUEST).unwrap();
+        assert_eq!(
+            mint.melt_fee_reserve(&invoice, Amount::from(2_500_000), &CurrencyUnit::Sat)
+                .await
+                .unwrap(),
+            Amount::from(12_500)
+        );
+        assert_eq!(
+            mint.melt_fee_reserve(&invoice, Amount::from(2_500_000_000), &CurrencyUnit::Msat)
+                .await
+                .unwrap(),
+            Amount::from(25_000_000)
+        );
+
+        assert_eq!(mint.lightning.keys().len(), 3);
+        assert_eq!(mint.lightning.backends().len(), 2);
+        mint.wait_for_all_paid_invoices().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_paid_invoices() {
+        let mint = create_mint().await;
+        let quote = mint
+            .new_mint_quote(
+                MintUrl::from_str("https://mint.example").unwrap(),
+                REQUEST.to_string(),
+                CurrencyUnit::Sat,
+                Amount::from(2_500_000),
+                unix_time() + 600,
+                None,
+            )
+            .await
+            .unwrap();
+        let mut events = mint.events.subscribe();
+
+        let backend = PaidInvoices(vec!["00".repeat(32), quote.payment_hash().unwrap()]);
+        mint.wait_for_paid_invoices(Arc::new(backend))
+            .await
+            .unwrap();
+
+        assert!(mint.check_mint_quote(&quote.id).await.unwrap().paid);
+        assert_eq!(
+            events.recv().await.unwrap(),
+            MintEvent::MintQuotePaid {
+                quote: quote.id,
+                amount: Amount::from(2_500_000),
+                unit: CurrencyUnit::Sat,
+            }
+        );
+        assert!(events.try_recv().is_err());
+    }
+}
@@ -1,35 +1,78 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::sync::Arc;
 
-use bitcoin::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey};
-use bitcoin::secp256k1::{self, Secp256k1};
+use bitcoin::bip32::{ChildNumber, DerivationPath};
 use error::Error;
+use fee_estimator::FeeEstimator;
+use lightning::{LightningRouter, LnKey};
+use proof_cache::VerifiedProofCache;
 use serde::{Deserialize, Serialize};
+use signatory::{MemorySignatory, Signatory};
 use tokio::sync::RwLock;
 use tracing::{debug, error, info};
+use zeroize::Zeroize;
 
 use crate::cdk_database::{self, MintDatabase};
-use crate::dhke::{hash_to_curve, sign_message, verify_message};
+use crate::dhke::hash_to_curve;
+use crate::exchange_rate;
+use crate::mint_url::MintUrl;
 use crate::nuts::nut11::enforce_sig_flag;
 use crate::nuts::*;
-use crate::types::{MeltQuote, MintQuote};
-use crate::url::UncheckedUrl;
+use crate::types::{CompletedMelt, MeltQuote, MintQuote};
 use crate::util::unix_time;
-use crate::Amount;
+use crate::{Amount, Bolt11Invoice, Mnemonic};
 
+pub mod accounting;
+pub mod archive;
+pub mod builder;
+pub mod diagnostics;
 pub mod error;
+pub mod events;
+pub mod fee_estimator;
+pub mod lightning;
+#[cfg(all(feature = "ws", not(target_arch = "wasm32")))]
+pub mod payment_processor;
+pub mod proof_cache;
+pub mod signatory;
+#[cfg(feature = "webhooks")]
+pub mod webhook;
+#[cfg(all(feature = "ws", not(target_arch = "wasm32")))]
+pub mod ws;
+
+pub use builder::MintBuilder;
+
+/// Longest description a bolt11 invoice can carry
+const MAX_INVOICE_DESCRIPTION_LEN: usize = 639;
 
 #[derive(Clone)]
 pub struct Mint {
-    mint_info: MintInfo,
-    keysets: Arc<RwLock<HashMap<Id, MintKeySet>>>,
-    secp_ctx: Secp256k1<secp256k1::All>,
-    xpriv: ExtendedPrivKey,
-    pub fee_reserve: FeeReserve,
+    /// Settings that can be reloaded while the mint is running
+    config: Arc<RwLock<MintConfig>>,
+    /// Public keys of the keysets
+    keysets: Arc<RwLock<HashMap<Id, KeySet>>>,
+    signatory: Arc<dyn Signatory + Send + Sync>,
+    /// Events published by the mint
+    pub events: events::EventBus,
+    /// Price source for quotes in fiat units
+    pub exchange_rate: Option<Arc<dyn exchange_rate::ExchangeRate + Send + Sync>>,
+    /// Route-aware fee estimates of the Lightning backend
+    pub fee_estimator: Option<Arc<dyn FeeEstimator + Send + Sync>>,
+    /// Lightning backends by unit and payment method
+    pub lightning: LightningRouter,
+    /// Spending condition kinds enforced beside P2PK and HTLC
+    pub custom_conditions: ConditionRegistry,
+    /// Proofs with a verified signature, cleared when keysets change
+    pub verified_proofs: Option<VerifiedProofCache>,
     pub localstore: Arc<dyn MintDatabase<Err = cdk_database::Error> + Send + Sync>,
 }
 
 impl Mint {
+    /// Builder with defaults for everything but the keys
+    pub fn builder() -> MintBuilder {
+        MintBuilder::new()
+    }
+
     pub async fn new(
         seed: &[u8],
         mint_info: MintInfo,
@@ -37,53 +80,171 @@ impl Mint {
         min_fee_reserve: Amount,
         percent_fee_reserve: f32,
     ) -> Result<Self, Error> {
-        let secp_ctx = Secp256k1::new();
-        let xpriv =
-            ExtendedPrivKey::new_master(bitcoin::Network::Bitcoin, seed).expect("RNG busted");
+        Self::new_with_signatory(
+            Arc::new(MemorySignatory::new(seed)),
+            mint_info,
+            localstore,
+            min_fee_reserve,
+            percent_fee_reserve,
+        )
+        .await
+    }
+
+    /// Create mint that signs with keys held by `signatory`
+    pub async fn new_with_signatory(
+        signatory: Arc<dyn Signatory + Send + Sync>,
+        mint_info: MintInfo,
+        localstore: Arc<dyn MintDatabase<Err = cdk_database::Error> + Send + Sync>,
+        min_fee_reserve: Amount,
+        percent_fee_reserve: f32,
+    ) -> Result<Self, Error> {
+        let keysets = Self::init_keysets(signatory.as_ref(), localstore.as_ref()).await?;
+
+        Ok(Self {
+            keysets: Arc::new(RwLock::new(keysets)),
+            signatory,
+            localstore,
+            config: Arc::new(RwLock::new(MintConfig {
+                mint_info,
+                fee_reserve: FeeReserve {
+                    min_fee_reserve,
+                    percent_fee_reserve,
+                    max_fee_reserve: None,
+                },
+                fee_reserves: HashMap::new(),
+                limits: RequestLimits::default(),
+                invoice_description: None,
+                keyset_grace_period: 0,
+                disabled_endpoints: HashSet::new(),
+            })),
+            events: events::EventBus::default(),
+            exchange_rate: None,
+            fee_estimator: None,
+            lightning: LightningRouter::default(),
+            custom_conditions: ConditionRegistry::default(),
+            verified_proofs: None,
+        })
+    }
 
+    /// Create a sat keyset if `localstore` has no keysets
+    async fn init_keysets(
+        signatory: &(dyn Signatory + Send + Sync),
+        localstore: &(dyn MintDatabase<Err = cdk_database::Error> + Send + Sync),
+    ) -> Result<HashMap<Id, KeySet>, Error> {
         let mut keysets = HashMap::new();
-        let keysets_info = localstore.get_keyset_infos().await?;
-        if keysets_info.is_empty() {
-            let derivation_path = DerivationPath::from(vec![
-                ChildNumber::from_hardened_idx(0).expect("0 is a valid index")
-            ]);
-            let (keyset, keyset_info) =
-                create_new_keyset(&secp_ctx, xpriv, derivation_path, CurrencyUnit::Sat, 64);
+        if localstore.get_keyset_infos().await?.is_empty() {
+            let derivation_path = derivation_path_from_unit(&CurrencyUnit::Sat, 0)?;
+            let mut keyset_info = signatory
+                .create_keyset(CurrencyUnit::Sat, derivation_path, 64)
+                .await?;
+            keyset_info.derivation_path_index = Some(0);
+            let keyset = signatory.keyset(&keyset_info).await?;
             let id = keyset_info.id;
             localstore.add_keyset_info(keyset_info).await?;
             localstore.add_active_keyset(CurrencyUnit::Sat, id).await?;
             keysets.insert(id, keyset);
         }
 
-        Ok(Self {
-            keysets: Arc::new(RwLock::new(keysets)),
-            secp_ctx,
-            xpriv,
-            localstore,
-            fee_reserve: FeeReserve {
-                min_fee_reserve,
-                percent_fee_reserve,
-            },
+        Ok(keysets)
+    }
+
+    /// Create mint with keys derived from a BIP-39 mnemonic
+    ///
+    /// The same mnemonic and database regenerate the same keysets.
+    pub async fn from_mnemonic(
+        mnemonic: &Mnemonic,
+        mint_info: MintInfo,
+        localstore: Arc<dyn MintDatabase<Err = cdk_database::Error> + Send + Sync>,
+        min_fee_reserve: Amount,
+        percent_fee_reserve: f32,
+    ) -> Result<Self, Error> {
+        let mut seed = mnemonic.to_seed_normalized("");
+
+        let mint = Self::new(
+            &seed,
             mint_info,
-        })
+            localstore,
+            min_fee_reserve,
+            percent_fee_reserve,
+        )
+        .await;
+
+        seed.zeroize();
+
+        mint
     }
 
+    /// Create mint quote
+    ///
+    /// `exchange_rate` is the rate from [`Mint::lock_exchange_rate`] the
+    /// invoice amount was converted with.
     pub async fn new_mint_quote(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         request: String,
         unit: CurrencyUnit,
         amount: Amount,
         expiry: u64,
+        exchange_rate: Option<u64>,
     ) -> Result<MintQuote, Error> {
-        let quote = MintQuote::new(mint_url, request, unit, amount, expiry);
+        let config = self.config.read().await;
+        config.check_enabled(Endpoint::Mint)?;
+        let method = config.mint_method(&unit);
+        check_amount_limits(amount, method.min_amount, method.max_amount)?;
+        drop(config);
+        self.ensure_unit_supported(&unit).await?;
+
+        let mut quote = MintQuote::new(mint_url, request, unit, amount, expiry);
+        quote.exchange_rate = exchange_rate;
 
         self.localstore.add_mint_quote(quote.clone()).await?;
 
         Ok(quote)
     }
 
+    /// Description for the invoice of a mint quote
+    ///
+    /// Returns the description requested by the wallet, or the configured
+    /// [`MintConfig::invoice_description`] when the request does not set one.
+    /// Lightning backends should put it in the invoice they create for the
+    /// quote.
+    pub async fn invoice_description(
+        &self,
+        unit: &CurrencyUnit,
+        description: Option<String>,
+    ) -> Result<Option<String>, Error> {
+        let config = self.config.read().await;
+
+        let description = match description {
+            Some(description) => {
+                if !config.mint_method(unit).description {
+                    return Err(Error::InvoiceDescriptionNotSupported);
+                }
+
+                Some(description)
+            }
+            None => config.invoice_description.as_ref().map(|template| {
+                template.replace(
+                    "{name}",
+                    config.mint_info.name.as_deref().unwrap_or_default(),
+                )
+            }),
+        };
+
+        if let Some(description) = &description {
+            if description.len() > MAX_INVOICE_DESCRIPTION_LEN {
+                return Err(Error::InvoiceDescriptionTooLong(
+                    MAX_INVOICE_DESCRIPTION_LEN,
+                ));
+            }
+        }
+
+        Ok(description)
+    }
+
     pub async fn check_mint_quote(&self, quote_id: &str) -> Result<MintQuoteBolt11Response, Error> {
+        self.config.read().await.check_enabled(Endpoint::Mint)?;
+
         let quote = self
             .localstore
             .get_mint_quote(quote_id)
@@ -95,11 +256,30 @@ impl Mint {
             request: quote.request,
             paid: quote.paid,
             expiry: Some(quote.expiry),
+            exchange_rate: quote.exchange_rate,
         })
     }
 
     pub async fn update_mint_quote(&self, quote: MintQuote) -> Result<(), Error> {
+        let newly_paid = quote.paid
+            && !self
+                .localstore
+                .get_mint_quote(&quote.id)
+                .await?
+                .map(|q| q.paid)
+                .unwrap_or_default();
+
+        // Published once stored, so subscribers looking up the quote see it paid
+        let event = events::MintEvent::MintQuotePaid {
+            quote: quote.id.clone(),
+            amount: quote.amount,
+            unit: quote.unit.clone(),
+        };
         self.localstore.add_mint_quote(quote).await?;
+        if newly_paid {
+            self.events.publish(event);
+        }
+
         Ok(())
     }
 
@@ -108,12 +288,54 @@ impl Mint {
         Ok(quotes)
     }
 
+    /// Mint quote of the invoice with `payment_hash`
+    pub async fn quote_by_payment_hash(
+        &self,
+        payment_hash: &str,
+    ) -> Result<Option<MintQuote>, Error> {
+        let quote = self
+            .localstore
+            .get_mint_quote_by_payment_hash(payment_hash)
+            .await?;
+        Ok(quote)
+    }
+
+    /// Mark the mint quote of the invoice with `payment_hash` as paid
+    ///
+    /// For Lightning backends to settle quotes of invoices they received a
+    /// payment for. Returns the quote, or `None` if no quote was issued for
+    /// the invoice.
+    pub async fn pay_mint_quote_by_payment_hash(
+        &self,
+        payment_hash: &str,
+    ) -> Result<Option<MintQuote>, Error> {
+        let mut quote = match self.quote_by_payment_hash(payment_hash).await? {
+            Some(quote) => quote,
+            None => return Ok(None),
+        };
+
+        if !quote.paid {
+            quote.paid = true;
+            self.update_mint_quote(quote.clone()).await?;
+        }
+
+        Ok(Some(quote))
+    }
+
     pub async fn remove_mint_quote(&self, quote_id: &str) -> Result<(), Error> {
         self.localstore.remove_mint_quote(quote_id).await?;
 
         Ok(())
     }
 
+    /// Create melt quote
+    ///
+    /// If the request is an invoice issued by this mint for one of its own
+    /// mint quotes the payment will be settled internally, so no fee reserve
+    /// is required.
+    ///
+    /// `exchange_rate` is the rate from [`Mint::lock_exchange_rate`] the
+    /// invoice amount was converted with.
     pub async fn new_melt_quote(
         &self,
         request: String,
@@ -121,14 +343,122 @@ impl Mint {
         amount: Amount,
         fee_reserve: Amount,
         expiry: u64,
+        exchange_rate: Option<u64>,
     ) -> Result<MeltQuote, Error> {
-        let quote = MeltQuote::new(request, unit, amount, fee_reserve, expiry);
+        let config = self.config.read().await;
+        config.check_enabled(Endpoint::Melt)?;
+        let method = config.melt_method(&unit);
+        check_amount_limits(amount, method.min_amount, method.max_amount)?;
+        drop(config);
+        self.ensure_unit_supported(&unit).await?;
+
+        // Only an invoice of an open mint quote is settled internally
+        let fee_reserve = match self.localstore.get_mint_quote_by_request(&request).await? {
+            Some(mint_quote) if !mint_quote.paid && mint_quote.expiry > unix_time() => Amount::ZERO,
+            _ => fee_reserve,
+        };
+
+        let mut quote = MeltQuote::new(request, unit, amount, fee_reserve, expiry);
+        quote.exchange_rate = exchange_rate;
 
         self.localstore.add_melt_quote(quote.clone()).await?;
 
         Ok(quote)
     }
 
+    /// Price of one bitcoin in `unit` to create a quote with
+    ///
+    /// Returns `None` for bitcoin units. For fiat units the rate is fetched
+    /// from [`Mint::exchange_rate`]; pass it to [`Mint::new_mint_quote`] or
+    /// [`Mint::new_melt_quote`] so it stays fixed for the lifetime of the
+    /// quote. Convert amounts with [`exchange_rate::unit_to_sat`] and
+    /// [`exchange_rate::sat_to_unit`].
+    pub async fn lock_exchange_rate(&self, unit: &CurrencyUnit) -> Result<Option<u64>, Error> {
+        match unit {
+            CurrencyUnit::Sat | CurrencyUnit::Msat => Ok(None),
+            unit => {
+                let provider = self
+                    .exchange_rate
+                    .as_ref()
+                    .ok_or(Error::ExchangeRateUnavailable)?;
+
+                let rate = provider.rate(unit).await?;
+                tracing::debug!("Locked exchange rate {} {} per BTC", rate.per_btc, unit);
+
+                Ok(Some(rate.per_btc))
+            }
+        }
+    }
+
+    /// Fee reserve of a melt quote paying `invoice`
+    ///
+    /// `amount` is the invoice amount in `unit`, a bitcoin unit. Uses the
+    /// estimate of [`Mint::fee_estimator`], within `min_fee_reserve` and
+    /// `max_fee_reserve`, and falls back to the [`FeeReserve`] when no
+    /// estimator is set or it cannot estimate a route. The fee reserve is the
+    /// one of [`MintConfig::fee_reserves`] for `unit`, else the one of the
+    /// Lightning backend of `unit`, else [`MintConfig::fee_reserve`].
+    pub async fn melt_fee_reserve(
+        &self,
+        invoice: &Bolt11Invoice,
+        amount: Amount,
+        unit: &CurrencyUnit,
+    ) -> Result<Amount, Error> {
+        let config = self.config.read().await;
+        let fee_reserve = config
+            .fee_reserves
+            .get(&LnKey::new(unit.clone(), PaymentMethod::Bolt11))
+            .or_else(|| {
+                self.lightning
+                    .get(unit, &PaymentMethod::Bolt11)
+                    .and_then(|backend| backend.fee_reserve.as_ref())
+            })
+            .unwrap_or(&config.fee_reserve)
+            .clone();
+        drop(config);
+
+        let estimate = match &self.fee_estimator {
+            Some(estimator) => match estimator.estimate_fee(invoice).await {
+                Ok(estimate) => estimate,
+                Err(err) => {
+                    tracing::warn!("Could not estimate fee: {}", err);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        match estimate {
+            Some(estimate) => {
+                let estimate = estimate.convert_unit(&CurrencyUnit::Sat, unit)?;
+                tracing::debug!("Estimated fee of {} {}", estimate, unit);
+
+                fee_reserve.clamp(estimate, unit)
+            }
+            None => fee_reserve.fee_reserve(amount, unit),
+        }
+    }
+
+    /// Units the mint has an active keyset for
+    pub async fn units(&self) -> Result<Vec<CurrencyUnit>, Error> {
+        let mut units: Vec<CurrencyUnit> = self
+            .localstore
+            .get_active_keysets()
+            .await?
+            .into_keys()
+            .collect();
+        units.sort_by_key(|unit| unit.to_string());
+
+        Ok(units)
+    }
+
+    async fn ensure_unit_supported(&self, unit: &CurrencyUnit) -> Result<(), Error> {
+        match self.localstore.get_active_keyset_id(unit).await? {
+            Some(_) => Ok(()),
+            None => Err(Error::UnsupportedUnit),
+        }
+    }
+
     /// Retrieve the public keys of the active keyset for distribution to
     /// wallet clients
     pub async fn keyset_pubkeys(&self, keyset_id: &Id) -> Result<KeysResponse, Error> {
@@ -136,7 +466,7 @@ impl Mint {
         let keysets = self.keysets.read().await;
         let keyset = keysets.get(keyset_id).ok_or(Error::UnknownKeySet)?.clone();
         Ok(KeysResponse {
-            keysets: vec![keyset.into()],
+            keysets: vec![keyset],
         })
     }
 
@@ -149,7 +479,7 @@ impl Mint {
         }
         let keysets = self.keysets.read().await;
         Ok(KeysResponse {
-            keysets: keysets.values().map(|k| k.clone().into()).collect(),
+            keysets: keysets.values().cloned().collect(),
         })
     }
 
@@ -164,12 +494,15 @@ impl Mint {
             .cloned()
             .collect();
 
+        let grace_period = self.config.read().await.keyset_grace_period;
+
         let keysets = keysets
             .into_iter()
             .map(|k| KeySetInfo {
                 id: k.id,
-                unit: k.unit,
                 active: active_keysets.contains(&k.id),
+                final_expiry: k.final_expiry(grace_period),
+                unit: k.unit,
             })
             .collect();
 
@@ -179,10 +512,89 @@ impl Mint {
     pub async fn keyset(&self, id: &Id) -> Result<Option<KeySet>, Error> {
         self.ensure_keyset_loaded(id).await?;
         let keysets = self.keysets.read().await;
-        let keyset = keysets.get(id).map(|k| k.clone().into());
+        let keyset = keysets.get(id).cloned();
         Ok(keyset)
     }
 
+    /// Generate new active keyset for unit at the next derivation index
+    ///
+    /// Keys are derived at `m/0'/{unit}'/{index}'` where index is one above
+    /// the highest index of the unit's keysets, so rotation never derives
+    /// keys that were used before.
+    pub async fn rotate_next_keyset(&self, unit: CurrencyUnit, max_order: u8) -> Result<(), Error> {
+        self.generate_keyset(unit, max_order, None).await?;
+
+        Ok(())
+    }
+
+    /// Generate new active keyset for `unit`
+    ///
+    /// Like [`Mint::rotate_next_keyset`], with the end of the validity of the
+    /// new keyset set to `valid_to`. The keyset signs amounts up to
+    /// `2^(max_order - 1)`.
+    pub async fn generate_keyset(
+        &self,
+        unit: CurrencyUnit,
+        max_order: u8,
+        valid_to: Option<u64>,
+    ) -> Result<MintKeySetInfo, Error> {
+        if !(1..=64).contains(&max_order) {
+            return Err(Error::InvalidKeysetParameters(format!(
+                "max order {} not in 1..=64",
+                max_order
+            )));
+        }
+        if let Some(valid_to) = valid_to {
+            if valid_to <= unix_time() {
+                return Err(Error::InvalidKeysetParameters(format!(
+                    "valid to {} is in the past",
+                    valid_to
+                )));
+            }
+        }
+
+        let index = self.next_derivation_index(&unit).await?;
+        let derivation_path = derivation_path_from_unit(&unit, index)?;
+
+        self.add_keyset(unit, derivation_path, max_order, Some(index), valid_to)
+            .await
+    }
+
+    /// Derivation index of the next keyset of `unit`
+    async fn next_derivation_index(&self, unit: &CurrencyUnit) -> Result<u32, Error> {
+        let highest = self
+            .localstore
+            .get_keyset_infos()
+            .await?
+            .into_iter()
+            .filter(|keyset_info| keyset_info.unit.eq(unit))
+            .filter_map(|keyset_info| {
+                keyset_info
+                    .derivation_path_index
+                    .or_else(|| derivation_index_from_path(unit, &keyset_info.derivation_path))
+            })
+            .max();
+
+        Ok(highest.map_or(0, |highest| highest.saturating_add(1)))
+    }
+
+    /// Set the end of the validity of a keyset
+    ///
+    /// From `valid_to` on the keyset no longer signs, its proofs are redeemed
+    /// until the end of the configured grace period.
+    pub async fn set_keyset_valid_to(&self, id: &Id, valid_to: Option<u64>) -> Result<(), Error> {
+        let mut keyset_info = self
+            .localstore
+            .get_keyset_info(id)
+            .await?
+            .ok_or(Error::UnknownKeySet)?;
+        keyset_info.valid_to = valid_to;
+        self.localstore.add_keyset_info(keyset_info).await?;
+        self.clear_verified_proofs();
+
+        Ok(())
+    }
+
     /// Add current keyset to inactive keysets
     /// Generate new keyset
     pub async fn rotate_keyset(
@@ -191,41 +603,74 @@ impl Mint {
         derivation_path: DerivationPath,
         max_order: u8,
     ) -> Result<(), Error> {
-        let (keyset, keyset_info) = create_new_keyset(
-            &self.secp_ctx,
-            self.xpriv,
-            derivation_path,
-            unit.clone(),
-            max_order,
-        );
+        let index = derivation_index_from_path(&unit, &derivation_path);
+
+        self.add_keyset(unit, derivation_path, max_order, index, None)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Generate keyset and make it the only active keyset of `unit`
+    async fn add_keyset(
+        &self,
+        unit: CurrencyUnit,
+        derivation_path: DerivationPath,
+        max_order: u8,
+        derivation_path_index: Option<u32>,
+        valid_to: Option<u64>,
+    ) -> Result<MintKeySetInfo, Error> {
+        let mut keyset_info = self
+            .signatory
+            .create_keyset(unit.clone(), derivation_path, max_order)
+            .await?;
+        keyset_info.derivation_path_index = derivation_path_index;
+        keyset_info.valid_to = valid_to;
+        let keyset = self.signatory.keyset(&keyset_info).await?;
         let id = keyset_info.id;
-        self.localstore.add_keyset_info(keyset_info).await?;
-        self.localstore.add_active_keyset(unit, id).await?;
+
+        if self.localstore.get_keyset_info(&id).await?.is_some() {
+            return Err(Error::InvalidKeysetParameters(format!(
+                "keyset {} already exists",
+                id
+            )));
+        }
+
+        let previous = match self.localstore.get_active_keyset_id(&unit).await? {
+            Some(previous) => self.localstore.get_keyset_info(&previous).await?,
+            None => None,
+        };
+
+        self.localstore.add_keyset_info(keyset_info.clone()).await?;
+        self.localstore.add_active_keyset(unit.clone(), id).await?;
+
+        if let Some(mut previous) = previous {
+            previous.active = false;
+            self.localstore.add_keyset_info(previous).await?;
+        }
 
         let mut keysets = self.keysets.write().await;
         keysets.insert(id, keyset);
+        self.clear_verified_proofs();
 
-        Ok(())
+        self.events
+            .publish(events::MintEvent::KeysetRotated { id, unit });
+
+        Ok(keyset_info)
     }
 
     pub async fn process_mint_request(
         &self,
         mint_request: nut04::MintBolt11Request,
     ) -> Result<nut04::MintBolt11Response, Error> {
-        for blinded_message in &mint_request.outputs {
-            if self
-                .localstore
-                .get_blinded_signature(&blinded_message.blinded_secret)
-                .await?
-                .is_some()
-            {
-                error!(
-                    "Output has already been signed: {}",
-                    blinded_message.blinded_secret
-                );
-                return Err(Error::BlindedMessageAlreadySigned);
-            }
-        }
+        let config = self.config.read().await;
+        config.check_enabled(Endpoint::Mint)?;
+        config.limits.check_outputs(mint_request.outputs.len())?;
+        config
+            .limits
+            .check_output_witnesses(&mint_request.outputs)?;
+        drop(config);
+        self.check_outputs_unsigned(&mint_request.outputs).await?;
 
         let quote = self
             .localstore
@@ -237,37 +682,150 @@ impl Mint {
             return Err(Error::UnpaidQuote);
         }
 
+        let output_unit = self.verify_outputs_keyset(&mint_request.outputs).await?;
+
+        if output_unit.is_some() && output_unit.ne(&Some(quote.unit.clone())) {
+            return Err(Error::MultipleUnits);
+        }
+
+        let output_total = Amount::try_sum(mint_request.outputs.iter().map(|o| o.amount))?;
+
+        if output_total > quote.amount {
+            debug!(
+                "Outputs of {} are more than quote amount {}",
+                output_total, quote.amount
+            );
+            return Err(Error::Amount);
+        }
+
         let mut blind_signatures = Vec::with_capacity(mint_request.outputs.len());
 
-        for blinded_message in mint_request.outputs.into_iter() {
-            let blinded_signature = self.blind_sign(&blinded_message).await?;
-            self.localstore
-                .add_blinded_signature(blinded_message.blinded_secret, blinded_signature.clone())
-                .await?;
-            blind_signatures.push(blinded_signature);
+        for blinded_message in mint_request.outputs.iter() {
+            blind_signatures.push(self.blind_sign(blinded_message).await?);
         }
 
+        // Signatures are stored and the quote consumed together, a concurrent
+        // request for the same quote fails with `UnknownQuote`
         self.localstore
-            .remove_mint_quote(&mint_request.quote)
+            .issue_mint_quote(
+                &mint_request.quote,
+                mint_request
+                    .outputs
+                    .iter()
+                    .map(|o| o.blinded_secret)
+                    .zip(blind_signatures.iter().cloned())
+                    .collect(),
+            )
             .await?;
 
+        self.events.publish(events::MintEvent::MintQuoteIssued {
+            quote: quote.id,
+            amount: output_total,
+            unit: quote.unit,
+        });
+
         Ok(nut04::MintBolt11Response {
             signatures: blind_signatures,
         })
     }
 
-    async fn blind_sign(&self, blinded_message: &BlindedMessage) -> Result<BlindSignature, Error> {
-        let BlindedMessage {
-            amount,
-            blinded_secret,
-            keyset_id,
-            ..
-        } = blinded_message;
-        self.ensure_keyset_loaded(keyset_id).await?;
+    /// Unit of the keysets of the inputs
+    ///
+    /// All inputs must be from keysets known to the mint and of the same unit.
+    /// Returns `None` if there are no inputs.
+    async fn verify_inputs_keyset(&self, inputs: &Proofs) -> Result<Option<CurrencyUnit>, Error> {
+        let keyset_ids: HashSet<Id> = inputs.iter().map(|p| p.keyset_id).collect();
+
+        let mut keyset_units = HashSet::with_capacity(keyset_ids.len());
+
+        for id in keyset_ids {
+            let keyset = self
+                .localstore
+                .get_keyset_info(&id)
+                .await?
+                .ok_or(Error::UnknownKeySet)?;
+            keyset_units.insert(keyset.unit);
+        }
+
+        if keyset_units.len().gt(&1) {
+            error!("Inputs of multiple units: {:?}", keyset_units);
+            return Err(Error::MultipleUnits);
+        }
 
+        Ok(keyset_units.into_iter().next())
+    }
+
+    /// Unit of the keysets of the outputs
+    ///
+    /// All outputs must be for the active keyset of a single unit.
+    /// Returns `None` if there are no outputs.
+    async fn verify_outputs_keyset(
+        &self,
+        outputs: &[BlindedMessage],
+    ) -> Result<Option<CurrencyUnit>, Error> {
+        let keyset_ids: HashSet<Id> = outputs.iter().map(|b| b.keyset_id).collect();
+
+        let mut keyset_units = HashSet::with_capacity(keyset_ids.len());
+
+        for id in keyset_ids {
+            let keyset = self
+                .localstore
+                .get_keyset_info(&id)
+                .await?
+                .ok_or(Error::UnknownKeySet)?;
+
+            // Get the active keyset for the unit
+            let active_keyset_id = self
+                .localstore
+                .get_active_keyset_id(&keyset.unit)
+                .await?
+                .ok_or(Error::InactiveKeyset)?;
+
+            // Check output is for current active keyset
+            if id.ne(&active_keyset_id) {
+                return Err(Error::InactiveKeyset);
+            }
+
+            keyset_units.insert(keyset.unit);
+        }
+
+        if keyset_units.len().gt(&1) {
+            error!("Outputs of multiple units: {:?}", keyset_units);
+            return Err(Error::MultipleUnits);
+        }
+
+        Ok(keyset_units.into_iter().next())
+    }
+
+    /// Check that outputs are unique within the request and that the mint has
+    /// not already signed any of them
+    async fn check_outputs_unsigned(&self, outputs: &[BlindedMessage]) -> Result<(), Error> {
+        let blinded_secrets: Vec<PublicKey> = outputs.iter().map(|b| b.blinded_secret).collect();
+
+        let unique_secrets: HashSet<&PublicKey> = blinded_secrets.iter().collect();
+        if unique_secrets.len().ne(&blinded_secrets.len()) {
+            return Err(Error::DuplicateOutputs);
+        }
+
+        let signatures = self
+            .localstore
+            .get_blinded_signatures(blinded_secrets.clone())
+            .await?;
+
+        for (blinded_secret, signature) in blinded_secrets.iter().zip(signatures) {
+            if signature.is_some() {
+                error!("Output has already been signed: {}", blinded_secret);
+                return Err(Error::BlindedMessageAlreadySigned);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn blind_sign(&self, blinded_message: &BlindedMessage) -> Result<BlindSignature, Error> {
         let keyset_info = self
             .localstore
-            .get_keyset_info(keyset_id)
+            .get_keyset_info(&blinded_message.keyset_id)
             .await?
             .ok_or(Error::UnknownKeySet)?;
 
@@ -282,48 +840,32 @@ impl Mint {
             return Err(Error::InactiveKeyset);
         }
 
-        let keysets = self.keysets.read().await;
-        let keyset = keysets.get(keyset_id).ok_or(Error::UnknownKeySet)?;
-        let Some(key_pair) = keyset.keys.get(amount) else {
-            // No key for amount
-            return Err(Error::AmountKey);
-        };
-
-        let c = sign_message(&key_pair.secret_key, blinded_secret)?;
-
-        let blinded_signature = BlindSignature::new(
-            *amount,
-            c,
-            keyset_info.id,
-            &blinded_message.blinded_secret,
-            key_pair.secret_key.clone(),
-        )?;
+        if !keyset_info.is_valid_at(unix_time()) {
+            return Err(Error::ExpiredKeyset);
+        }
 
-        Ok(blinded_signature)
+        self.signatory
+            .blind_sign(&keyset_info, blinded_message)
+            .await
     }
 
     pub async fn process_swap_request(
         &self,
         swap_request: SwapRequest,
     ) -> Result<SwapResponse, Error> {
-        for blinded_message in &swap_request.outputs {
-            if self
-                .localstore
-                .get_blinded_signature(&blinded_message.blinded_secret)
-                .await?
-                .is_some()
-            {
-                error!(
-                    "Output has already been signed: {}",
-                    blinded_message.blinded_secret
-                );
-                return Err(Error::BlindedMessageAlreadySigned);
-            }
-        }
+        let config = self.config.read().await;
+        config.check_enabled(Endpoint::Swap)?;
+        let limits = config.limits;
+        drop(config);
+        limits.check_inputs(swap_request.inputs.len())?;
+        limits.check_proofs(&swap_request.inputs)?;
+        limits.check_outputs(swap_request.outputs.len())?;
+        limits.check_output_witnesses(&swap_request.outputs)?;
+        self.check_outputs_unsigned(&swap_request.outputs).await?;
 
-        let proofs_total = swap_request.input_amount();
+        let proofs_total = swap_request.input_amount()?;
 
-        let output_total = swap_request.output_amount();
+        let output_total = swap_request.output_amount()?;
 
         if proofs_total != output_total {
             return Err(Error::Amount);
@@ -343,46 +885,25 @@ impl Mint {
             return Err(Error::DuplicateProofs);
         }
 
+        let input_unit = self.verify_inputs_keyset(&swap_request.inputs).await?;
+        let output_unit = self.verify_outputs_keyset(&swap_request.outputs).await?;
+
+        // Check that all inputs and outputs are the same unit
+        // in the future it maybe possible to support multiple units but unsupported for
+        // now
+        if input_unit.ne(&output_unit) {
+            error!(
+                "Only one unit is allowed in request: {:?} {:?}",
+                input_unit, output_unit
+            );
+            return Err(Error::MultipleUnits);
+        }
+
         for proof in &swap_request.inputs {
             self.verify_proof(proof).await?
         }
 
-        let input_keyset_ids: HashSet<Id> =
-            swap_request.inputs.iter().map(|p| p.keyset_id).collect();
-
-        let mut keyset_units = HashSet::with_capacity(input_keyset_ids.capacity());
-
-        for id in input_keyset_ids {
-            let keyset = self
-                .localstore
-                .get_keyset_info(&id)
-                .await?
-                .ok_or(Error::UnknownKeySet)?;
-            keyset_units.insert(keyset.unit);
-        }
-
-        let output_keyset_ids: HashSet<Id> =
-            swap_request.outputs.iter().map(|p| p.keyset_id).collect();
-
-        for id in &output_keyset_ids {
-            let keyset = self
-                .localstore
-                .get_keyset_info(id)
-                .await?
-                .ok_or(Error::UnknownKeySet)?;
-
-            keyset_units.insert(keyset.unit);
-        }
-
-        // Check that all proofs are the same unit
-        // in the future it maybe possible to support multiple units but unsupported for
-        // now
-        if keyset_units.len().gt(&1) {
-            error!("Only one unit is allowed in request: {:?}", keyset_units);
-            return Err(Error::MultipleUnits);
-        }
-
-        let (sig_flag, pubkeys) = enforce_sig_flag(swap_request.inputs.clone());
+        let (sig_flag, pubkeys) = enforce_sig_flag(swap_request.inputs.clone());
 
         if sig_flag.eq(&SigFlag::SigAll) {
             let pubkeys = pubkeys.into_iter().collect();
@@ -391,20 +912,28 @@ impl Mint {
             }
         }
 
-        for proof in swap_request.inputs {
-            self.localstore.add_spent_proof(proof).await?;
-        }
+        let ys = swap_request
+            .inputs
+            .iter()
+            .map(|p| p.y())
+            .collect::<Result<Vec<PublicKey>, _>>()?;
 
+        // Sign all outputs before anything is stored so that a signing error
+        // leaves the inputs unspent
         let mut promises = Vec::with_capacity(swap_request.outputs.len());
+        let mut blinded_signatures = Vec::with_capacity(swap_request.outputs.len());
 
         for blinded_message in swap_request.outputs {
             let blinded_signature = self.blind_sign(&blinded_message).await?;
-            self.localstore
-                .add_blinded_signature(blinded_message.blinded_secret, blinded_signature.clone())
-                .await?;
+            blinded_signatures.push((blinded_message.blinded_secret, blinded_signature.clone()));
             promises.push(blinded_signature);
         }
 
+        self.localstore
+            .add_spent_proofs_and_signatures(swap_request.inputs, blinded_signatures)
+            .await?;
+        self.events.publish(events::MintEvent::ProofsSpent { ys });
+
         Ok(SwapResponse::new(promises))
     }
 
@@ -428,9 +957,15 @@ impl Mint {
             }
         }
 
+        self.custom_conditions
+            .verify(proof)
+            .map_err(Error::SpendingConditionNotMet)?;
+
         let y: PublicKey = hash_to_curve(&proof.secret.to_bytes())?;
 
-        if self.localstore.get_spent_proof_by_y(&y).await?.is_some() {
+        if self.localstore.get_spent_proof_by_y(&y).await?.is_some()
+            || self.localstore.is_archived_spent_proof(&y).await?
+        {
             return Err(Error::TokenAlreadySpent);
         }
 
@@ -438,26 +973,55 @@ impl Mint {
             return Err(Error::TokenPending);
         }
 
-        self.ensure_keyset_loaded(&proof.keyset_id).await?;
-        let keysets = self.keysets.read().await;
-        let keyset = keysets.get(&proof.keyset_id).ok_or(Error::UnknownKeySet)?;
-        let Some(keypair) = keyset.keys.get(&proof.amount) else {
-            return Err(Error::AmountKey);
-        };
+        let keyset_info = self
+            .localstore
+            .get_keyset_info(&proof.keyset_id)
+            .await?
+            .ok_or(Error::UnknownKeySet)?;
+
+        let grace_period = self.config.read().await.keyset_grace_period;
+        if let Some(final_expiry) = keyset_info.final_expiry(grace_period) {
+            if final_expiry <= unix_time() {
+                return Err(Error::ExpiredKeyset);
+            }
+        }
 
-        verify_message(&keypair.secret_key, proof.c, proof.secret.as_bytes())?;
+        if let Some(cache) = &self.verified_proofs {
+            if cache.contains(&y, proof) {
+                return Ok(());
+            }
+        }
+
+        self.signatory.verify_proof(&keyset_info, proof).await?;
+
+        if let Some(cache) = &self.verified_proofs {
+            cache.insert(y, proof);
+        }
 
         Ok(())
     }
 
+    fn clear_verified_proofs(&self) {
+        if let Some(cache) = &self.verified_proofs {
+            cache.clear();
+        }
+    }
+
     pub async fn check_state(
         &self,
         check_state: &CheckStateRequest,
     ) -> Result<CheckStateResponse, Error> {
+        let config = self.config.read().await;
+        config.check_enabled(Endpoint::CheckState)?;
+        config.limits.check_inputs(check_state.ys.len())?;
+        drop(config);
+
         let mut states = Vec::with_capacity(check_state.ys.len());
 
         for y in &check_state.ys {
-            let state = if self.localstore.get_spent_proof_by_y(y).await?.is_some() {
+            let state = if self.localstore.get_spent_proof_by_y(y).await?.is_some()
+                || self.localstore.is_archived_spent_proof(y).await?
+            {
                 State::Spent
             } else if self.localstore.get_pending_proof_by_y(y).await?.is_some() {
                 State::Pending
@@ -478,15 +1042,35 @@ impl Mint {
         &self,
         melt_request: &MeltBolt11Request,
     ) -> Result<MeltQuote, Error> {
+        let config = self.config.read().await;
+        config.check_enabled(Endpoint::Melt)?;
+        let limits = config.limits;
+        drop(config);
+        limits.check_inputs(melt_request.inputs.len())?;
+        limits.check_proofs(&melt_request.inputs)?;
+        if let Some(outputs) = &melt_request.outputs {
+            limits.check_outputs(outputs.len())?;
+            limits.check_output_witnesses(outputs)?;
+        }
+
         let quote = self
             .localstore
             .get_melt_quote(&melt_request.quote)
             .await?
             .ok_or(Error::UnknownQuote)?;
 
-        let proofs_total = melt_request.proofs_amount();
+        match quote.state {
+            MeltQuoteState::Unpaid | MeltQuoteState::Failed => (),
+            MeltQuoteState::Pending => return Err(Error::PendingQuote),
+            MeltQuoteState::Paid => return Err(Error::RequestAlreadyPaid),
+        }
+
+        let proofs_total = melt_request.proofs_amount()?;
 
-        let required_total = quote.amount + quote.fee_reserve;
+        let required_total = quote
+            .amount
+            .checked_add(quote.fee_reserve)
+            .ok_or(crate::error::Error::AmountOverflow)?;
 
         if proofs_total < required_total {
             debug!(
@@ -496,18 +1080,11 @@ impl Mint {
             return Err(Error::Amount);
         }
 
-        let input_keyset_ids: HashSet<Id> =
-            melt_request.inputs.iter().map(|p| p.keyset_id).collect();
-
-        let mut keyset_units = HashSet::with_capacity(input_keyset_ids.capacity());
+        let input_unit = self.verify_inputs_keyset(&melt_request.inputs).await?;
 
-        for id in input_keyset_ids {
-            let keyset = self
-                .localstore
-                .get_keyset_info(&id)
-                .await?
-                .ok_or(Error::UnknownKeySet)?;
-            keyset_units.insert(keyset.unit);
+        // Check that inputs are of the quote unit
+        if input_unit.as_ref().ne(&Some(&quote.unit)) {
+            return Err(Error::MultipleUnits);
         }
 
         if let Some(outputs) = &melt_request.outputs {
@@ -520,32 +1097,14 @@ impl Mint {
                 }
             }
 
-            let output_keysets_ids: HashSet<Id> = outputs.iter().map(|b| b.keyset_id).collect();
-            for id in output_keysets_ids {
-                let keyset = self
-                    .localstore
-                    .get_keyset_info(&id)
-                    .await?
-                    .ok_or(Error::UnknownKeySet)?;
-
-                // Get the active keyset for the unit
-                let active_keyset_id = self
-                    .localstore
-                    .get_active_keyset_id(&keyset.unit)
-                    .await?
-                    .ok_or(Error::InactiveKeyset)?;
-
-                // Check output is for current active keyset
-                if id.ne(&active_keyset_id) {
-                    return Err(Error::InactiveKeyset);
-                }
-                keyset_units.insert(keyset.unit);
+            let output_unit = self.verify_outputs_keyset(outputs).await?;
+
+            // Check that change outputs are the same unit as the inputs
+            if output_unit.is_some() && output_unit.ne(&input_unit) {
+                return Err(Error::MultipleUnits);
             }
-        }
 
-        // Check that all input and output proofs are the same unit
-        if keyset_units.len().gt(&1) {
-            return Err(Error::MultipleUnits);
+            self.check_outputs_unsigned(outputs).await?;
         }
 
         let secrets: HashSet<[u8; 33]> = melt_request
@@ -567,39 +1126,246 @@ impl Mint {
         Ok(quote)
     }
 
+    /// Check that the inputs of a melt request are the ones `quote` is pending
+    /// with, and still pending
+    async fn verify_inputs_pending(
+        &self,
+        melt_request: &MeltBolt11Request,
+        quote: &MeltQuote,
+    ) -> Result<(), Error> {
+        let ys = melt_request
+            .inputs
+            .iter()
+            .map(|p| p.y())
+            .collect::<Result<HashSet<PublicKey>, _>>()?;
+        if ys.len() != melt_request.inputs.len()
+            || ys != quote.pending_inputs.iter().copied().collect()
+        {
+            return Err(Error::Custom(format!(
+                "Inputs differ from the inputs of pending quote {}",
+                melt_request.quote
+            )));
+        }
+
+        for input in &melt_request.inputs {
+            if self
+                .localstore
+                .get_pending_proof_by_secret(&input.secret)
+                .await?
+                .is_none()
+            {
+                return Err(Error::Custom(format!(
+                    "Input of pending quote {} is not pending",
+                    melt_request.quote
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Verify melt request and mark the quote as pending
+    ///
+    /// Call before paying the request. The inputs are pending until the melt
+    /// either completes with [`Mint::process_melt_request`] or definitively
+    /// fails with [`Mint::process_unpaid_melt`], they can not be spent in
+    /// another swap or melt in the meantime.
+    pub async fn set_melt_quote_pending(
+        &self,
+        melt_request: &MeltBolt11Request,
+    ) -> Result<MeltQuote, Error> {
+        let mut quote = match self.verify_melt_request(melt_request).await {
+            Ok(quote) => quote,
+            Err(err) => {
+                self.events.publish(events::MintEvent::MeltFailed {
+                    quote: melt_request.quote.clone(),
+                    reason: err.to_string(),
+                });
+                return Err(err);
+            }
+        };
+
+        for input in &melt_request.inputs {
+            self.localstore.add_pending_proof(input.clone()).await?;
+        }
+
+        let ys = melt_request
+            .inputs
+            .iter()
+            .map(|p| p.y())
+            .collect::<Result<Vec<PublicKey>, _>>()?;
+
+        quote.state = MeltQuoteState::Pending;
+        quote.pending_inputs = ys.clone();
+        self.localstore.add_melt_quote(quote.clone()).await?;
+
+        self.events.publish(events::MintEvent::ProofsPending { ys });
+
+        Ok(quote)
+    }
+
+    /// Release the inputs of a melt whose payment definitively failed
+    ///
+    /// The quote is marked as failed and can be melted again, wallets see the
+    /// state when they look up the quote and can use the inputs again. Only
+    /// call this when the backend is sure the payment will not complete, a
+    /// payment in flight must stay pending.
+    pub async fn process_unpaid_melt(
+        &self,
+        melt_request: &MeltBolt11Request,
+        reason: &str,
+    ) -> Result<(), Error> {
+        let mut quote = self
+            .localstore
+            .get_melt_quote(&melt_request.quote)
+            .await?
+            .ok_or(Error::UnknownQuote)?;
+
+        if quote.state == MeltQuoteState::Paid {
+            return Err(Error::RequestAlreadyPaid);
+        }
+        if quote.state == MeltQuoteState::Pending {
+            self.verify_inputs_pending(melt_request, &quote).await?;
+        }
+
+        for input in &melt_request.inputs {
+            self.localstore.remove_pending_proof(&input.secret).await?;
+        }
+
+        quote.state = MeltQuoteState::Failed;
+        quote.pending_inputs.clear();
+        self.localstore.add_melt_quote(quote).await?;
+
+        self.events.publish(events::MintEvent::ProofsReleased {
+            ys: melt_request
+                .inputs
+                .iter()
+                .map(|p| p.y())
+                .collect::<Result<Vec<PublicKey>, _>>()?,
+        });
+        self.events.publish(events::MintEvent::MeltFailed {
+            quote: melt_request.quote.clone(),
+            reason: reason.to_string(),
+        });
+
+        Ok(())
+    }
+
+    /// Settle a melt internally if its request was issued by this mint
+    ///
+    /// When the melt quote request matches an unpaid mint quote of this mint
+    /// the amount spent is returned. The Lightning backend must not be used to
+    /// pay the request in that case, [`Mint::process_melt_request`] marks the
+    /// mint quote as paid together with the melt. Returns `None` if the
+    /// request has to be paid externally.
+    pub async fn handle_internal_melt_mint(
+        &self,
+        melt_quote: &MeltQuote,
+    ) -> Result<Option<Amount>, Error> {
+        Ok(self
+            .internal_mint_quote(melt_quote)
+            .await?
+            .map(|mint_quote| mint_quote.amount))
+    }
+
+    /// Unpaid mint quote settled by `melt_quote`, if its request is internal
+    async fn internal_mint_quote(
+        &self,
+        melt_quote: &MeltQuote,
+    ) -> Result<Option<MintQuote>, Error> {
+        let mint_quote = match self
+            .localstore
+            .get_mint_quote_by_request(&melt_quote.request)
+            .await?
+        {
+            Some(mint_quote) => mint_quote,
+            None => return Ok(None),
+        };
+
+        if mint_quote.paid {
+            return Err(Error::RequestAlreadyPaid);
+        }
+
+        if mint_quote.unit.ne(&melt_quote.unit) {
+            return Err(Error::MultipleUnits);
+        }
+
+        if melt_quote.amount.lt(&mint_quote.amount) {
+            debug!(
+                "Melt quote amount {} is less then mint quote amount {}",
+                melt_quote.amount, mint_quote.amount
+            );
+            return Err(Error::Amount);
+        }
+
+        Ok(Some(mint_quote))
+    }
+
+    /// Process melt request
+    ///
+    /// Completes a melt whose request has been paid and marks the quote as
+    /// paid. A quote set pending with [`Mint::set_melt_quote_pending`] was
+    /// verified then, other requests are verified now. `preimage` is `None`
+    /// when the request was settled internally with
+    /// [`Mint::handle_internal_melt_mint`].
     pub async fn process_melt_request(
         &self,
         melt_request: &MeltBolt11Request,
-        preimage: &str,
+        preimage: Option<&str>,
         total_spent: Amount,
     ) -> Result<MeltBolt11Response, Error> {
-        self.verify_melt_request(melt_request).await?;
+        self.config.read().await.check_enabled(Endpoint::Melt)?;
 
-        if let Some(outputs) = &melt_request.outputs {
-            for blinded_message in outputs {
-                if self
-                    .localstore
-                    .get_blinded_signature(&blinded_message.blinded_secret)
-                    .await?
-                    .is_some()
-                {
-                    error!(
-                        "Output has already been signed: {}",
-                        blinded_message.blinded_secret
-                    );
-                    return Err(Error::BlindedMessageAlreadySigned);
-                }
-            }
+        if let Some(response) = self.completed_melt_response(melt_request).await? {
+            return Ok(response);
         }
 
-        for input in &melt_request.inputs {
-            self.localstore.add_spent_proof(input.clone()).await?;
+        let pending_quote = self
+            .localstore
+            .get_melt_quote(&melt_request.quote)
+            .await?
+            .filter(|quote| quote.state == MeltQuoteState::Pending);
+
+        let verified = async {
+            let quote = match pending_quote {
+                Some(quote) => {
+                    self.verify_inputs_pending(melt_request, &quote).await?;
+                    quote
+                }
+                None => self.verify_melt_request(melt_request).await?,
+            };
+            let mint_quote = self.internal_mint_quote(&quote).await?;
+
+            Ok::<_, Error>((quote, mint_quote))
         }
+        .await;
+
+        let (mut quote, mint_quote) = match verified {
+            Ok(verified) => verified,
+            Err(err) => {
+                self.events.publish(events::MintEvent::MeltFailed {
+                    quote: melt_request.quote.clone(),
+                    reason: err.to_string(),
+                });
+                return Err(err);
+            }
+        };
+
+        let change_target = melt_request
+            .proofs_amount()?
+            .checked_sub(total_spent)
+            .ok_or(Error::Amount)?;
+
+        let ys = melt_request
+            .inputs
+            .iter()
+            .map(|p| p.y())
+            .collect::<Result<Vec<PublicKey>, _>>()?;
 
         let mut change = None;
+        let mut blinded_signatures = Vec::new();
 
         if let Some(outputs) = melt_request.outputs.clone() {
-            let change_target = melt_request.proofs_amount() - total_spent;
             let mut amounts = change_target.split();
             let mut change_sigs = Vec::with_capacity(amounts.len());
 
@@ -621,59 +1387,179 @@ impl Mint {
                 blinded_message.amount = *amount;
 
                 let blinded_signature = self.blind_sign(&blinded_message).await?;
-                self.localstore
-                    .add_blinded_signature(
-                        blinded_message.blinded_secret,
-                        blinded_signature.clone(),
-                    )
-                    .await?;
+                blinded_signatures
+                    .push((blinded_message.blinded_secret, blinded_signature.clone()));
                 change_sigs.push(blinded_signature)
             }
 
             change = Some(change_sigs);
         } else {
             info!(
-                "No change outputs provided. Burnt: {:?} sats",
-                (melt_request.proofs_amount() - total_spent)
+                "No change outputs provided. Burnt: {:?} {}",
+                change_target, quote.unit
             );
         }
 
-        Ok(MeltBolt11Response {
+        let change_amount =
+            Amount::try_sum(change.iter().flatten().map(|signature| signature.amount))?;
+        let fee_paid = total_spent.checked_sub(quote.amount).unwrap_or_default();
+
+        let event = events::MintEvent::MeltCompleted {
+            quote: quote.id.clone(),
+            amount: quote.amount,
+            fee_paid,
+            unit: quote.unit.clone(),
+        };
+
+        let response = MeltBolt11Response {
             paid: true,
-            payment_preimage: Some(preimage.to_string()),
+            payment_preimage: preimage.map(|p| p.to_string()),
             change,
-        })
+        };
+
+        let completed = CompletedMelt {
+            quote_id: quote.id.clone(),
+            inputs: ys.clone(),
+            response: response.clone(),
+            change_keyset_id: self.localstore.get_active_keyset_id(&quote.unit).await?,
+            fee_paid,
+            burnt_change: change_target.checked_sub(change_amount).unwrap_or_default(),
+            time: unix_time(),
+        };
+        quote.state = MeltQuoteState::Paid;
+        quote.pending_inputs.clear();
+
+        let mint_quote = mint_quote.map(|mut mint_quote| {
+            mint_quote.paid = true;
+            mint_quote
+        });
+        let mint_quote_event =
+            mint_quote
+                .as_ref()
+                .map(|mint_quote| events::MintEvent::MintQuotePaid {
+                    quote: mint_quote.id.clone(),
+                    amount: mint_quote.amount,
+                    unit: mint_quote.unit.clone(),
+                });
+
+        // Inputs, change and quote states are written together so a failure
+        // can not leave the inputs spent without the change stored
+        self.localstore
+            .complete_melt(
+                melt_request.inputs.clone(),
+                blinded_signatures,
+                completed,
+                quote,
+                mint_quote,
+            )
+            .await?;
+
+        self.events.publish(events::MintEvent::ProofsSpent { ys });
+        if let Some(mint_quote_event) = mint_quote_event {
+            self.events.publish(mint_quote_event);
+        }
+        self.events.publish(event);
+
+        Ok(response)
+    }
+
+    /// Response of an already executed melt
+    ///
+    /// A melt quote is only executed once. Returns the original response if
+    /// the request is a retry with the same inputs and `None` if the quote has
+    /// not been executed. Fails if the quote was executed with other inputs.
+    pub async fn completed_melt_response(
+        &self,
+        melt_request: &MeltBolt11Request,
+    ) -> Result<Option<MeltBolt11Response>, Error> {
+        let completed = match self
+            .localstore
+            .get_completed_melt(&melt_request.quote)
+            .await?
+        {
+            Some(completed) => completed,
+            None => return Ok(None),
+        };
+
+        let inputs = melt_request
+            .inputs
+            .iter()
+            .map(|p| p.y())
+            .collect::<Result<HashSet<PublicKey>, _>>()?;
+
+        if inputs.len() != completed.inputs.len()
+            || !completed.inputs.iter().all(|y| inputs.contains(y))
+        {
+            return Err(Error::RequestAlreadyPaid);
+        }
+
+        Ok(Some(completed.response))
     }
 
     pub async fn check_melt_quote(&self, quote_id: &str) -> Result<MeltQuoteBolt11Response, Error> {
+        self.config.read().await.check_enabled(Endpoint::Melt)?;
+
         let quote = self
             .localstore
             .get_melt_quote(quote_id)
             .await?
             .ok_or(Error::UnknownQuote)?;
 
-        Ok(MeltQuoteBolt11Response {
-            quote: quote.id,
-            paid: quote.paid,
-            expiry: quote.expiry,
-            amount: quote.amount,
-            fee_reserve: quote.fee_reserve,
-        })
+        let mut response: MeltQuoteBolt11Response = quote.into();
+        if response.state == MeltQuoteState::Paid {
+            response.payment_preimage = self
+                .localstore
+                .get_completed_melt(quote_id)
+                .await?
+                .and_then(|melt| melt.response.payment_preimage);
+        }
+
+        Ok(response)
     }
 
     /// Set Mint Info
-    pub fn set_mint_info(&mut self, mint_info: MintInfo) {
-        self.mint_info = mint_info;
+    pub async fn set_mint_info(&self, mint_info: MintInfo) {
+        self.config.write().await.mint_info = mint_info;
+    }
+
+    /// Current settings
+    pub async fn config(&self) -> MintConfig {
+        self.config.read().await.clone()
+    }
+
+    /// Replace the settings of a running mint
+    ///
+    /// Takes effect for all clones of the mint from the next request on.
+    /// Existing melt quotes keep the fee reserve they were created with.
+    pub async fn reload_config(&self, config: MintConfig) {
+        tracing::info!("Reloading mint config");
+        *self.config.write().await = config;
     }
 
     /// Get Mint Info
-    pub fn mint_info(&self) -> Result<MintInfo, Error> {
-        Ok(self.mint_info.clone())
+    ///
+    /// The NUT settings are derived from the running mint, see
+    /// [`MintConfig::nuts`], and `time` is set to the server time.
+    pub async fn mint_info(&self) -> Result<MintInfo, Error> {
+        let units = self.units().await?;
+
+        let config = self.config.read().await;
+        let mut mint_info = config.mint_info.clone();
+        mint_info.nuts = config.nuts(&units);
+        drop(config);
+
+        mint_info.time = Some(unix_time());
+
+        Ok(mint_info)
     }
 
     /// Restore
     pub async fn restore(&self, request: RestoreRequest) -> Result<RestoreResponse, Error> {
         let output_len = request.outputs.len();
+        let config = self.config.read().await;
+        config.check_enabled(Endpoint::Restore)?;
+        config.limits.check_outputs(output_len)?;
+        drop(config);
 
         let mut outputs = Vec::with_capacity(output_len);
         let mut signatures = Vec::with_capacity(output_len);
@@ -715,67 +1601,1936 @@ impl Mint {
             .get_keyset_info(id)
             .await?
             .ok_or(Error::UnknownKeySet)?;
-        let id = keyset_info.id;
+        let keyset = self.signatory.keyset(&keyset_info).await?;
         let mut keysets = self.keysets.write().await;
-        keysets.insert(id, self.generate_keyset(keyset_info));
+        keysets.insert(keyset_info.id, keyset);
         Ok(())
     }
+}
+
+/// Mint settings that can be changed without restarting the mint
+#[derive(Debug, Clone, PartialEq)]
+pub struct MintConfig {
+    pub mint_info: MintInfo,
+    /// Fee reserve of melts without one in [`MintConfig::fee_reserves`]
+    pub fee_reserve: FeeReserve,
+    /// Fee reserves of melts by unit and payment method
+    pub fee_reserves: HashMap<LnKey, FeeReserve>,
+    pub limits: RequestLimits,
+    /// Description of mint quote invoices when the request does not set one
+    ///
+    /// `{name}` is replaced with the name of the mint.
+    pub invoice_description: Option<String>,
+    /// Seconds after the end of its validity a keyset's proofs are still redeemed
+    pub keyset_grace_period: u64,
+    /// Endpoints that return [`Error::EndpointDisabled`]
+    ///
+    /// E.g. [`Endpoint::Melt`] for a mint-only mint, or everything but
+    /// [`Endpoint::Swap`] for an exchange. Disabled endpoints are marked as
+    /// unsupported in the mint info.
+    pub disabled_endpoints: HashSet<Endpoint>,
+}
+
+impl MintConfig {
+    /// Check if `endpoint` is enabled
+    ///
+    /// Minting and melting are also disabled by the `disabled` flag of their
+    /// settings in [`MintConfig::mint_info`].
+    pub fn is_enabled(&self, endpoint: Endpoint) -> bool {
+        let nuts = &self.mint_info.nuts;
+        let disabled = match endpoint {
+            Endpoint::Mint => nuts.nut04.disabled,
+            Endpoint::Melt => nuts.nut05.disabled,
+            _ => false,
+        };
+
+        !disabled && !self.disabled_endpoints.contains(&endpoint)
+    }
+
+    /// Settings of the NUTs the mint supports with this config
+    ///
+    /// Bolt11 mint and melt methods are listed for each of `units`, with the
+    /// amount limits and description support configured for them in
+    /// [`MintConfig::mint_info`], and no limits if none are configured.
+    /// Disabled endpoints are marked as disabled or unsupported, the spending
+    /// conditions and DLEQ proofs are always supported.
+    pub fn nuts(&self, units: &[CurrencyUnit]) -> Nuts {
+        let supported = |endpoint| match self.is_enabled(endpoint) {
+            true => nut06::SupportedSettings::default(),
+            false => nut06::SupportedSettings::unsupported(),
+        };
+
+        let mint_enabled = self.is_enabled(Endpoint::Mint);
+        let melt_enabled = self.is_enabled(Endpoint::Melt);
+
+        Nuts {
+            nut04: nut04::Settings {
+                methods: match mint_enabled {
+                    true => units.iter().map(|unit| self.mint_method(unit)).collect(),
+                    false => vec![],
+                },
+                disabled: !mint_enabled,
+            },
+            nut05: nut05::Settings {
+                methods: match melt_enabled {
+                    true => units.iter().map(|unit| self.melt_method(unit)).collect(),
+                    false => vec![],
+                },
+                disabled: !melt_enabled,
+            },
+            nut07: supported(Endpoint::CheckState),
+            nut09: supported(Endpoint::Restore),
+            ..Default::default()
+        }
+    }
+
+    /// Bolt11 mint settings of `unit`
+    fn mint_method(&self, unit: &CurrencyUnit) -> nut04::MintMethodSettings {
+        self.mint_info
+            .nuts
+            .nut04
+            .methods
+            .iter()
+            .find(|m| &m.unit == unit && m.method == PaymentMethod::Bolt11)
+            .cloned()
+            .unwrap_or_else(|| nut04::MintMethodSettings {
+                method: PaymentMethod::Bolt11,
+                unit: unit.clone(),
+                min_amount: None,
+                max_amount: None,
+                description: true,
+            })
+    }
+
+    /// Bolt11 melt settings of `unit`
+    fn melt_method(&self, unit: &CurrencyUnit) -> nut05::MeltMethodSettings {
+        self.mint_info
+            .nuts
+            .nut05
+            .methods
+            .iter()
+            .find(|m| &m.unit == unit && m.method == PaymentMethod::Bolt11)
+            .cloned()
+            .unwrap_or_else(|| nut05::MeltMethodSettings {
+                method: PaymentMethod::Bolt11,
+                unit: unit.clone(),
+                min_amount: None,
+                max_amount: None,
+            })
+    }
+
+    fn check_enabled(&self, endpoint: Endpoint) -> Result<(), Error> {
+        if !self.is_enabled(endpoint) {
+            tracing::debug!("Request to disabled endpoint {}", endpoint);
+            return Err(Error::EndpointDisabled(endpoint));
+        }
 
-    fn generate_keyset(&self, keyset_info: MintKeySetInfo) -> MintKeySet {
-        MintKeySet::generate_from_xpriv(&self.secp_ctx, self.xpriv, keyset_info)
+        Ok(())
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct FeeReserve {
-    pub min_fee_reserve: Amount,
-    pub percent_fee_reserve: f32,
+fn check_amount_limits(
+    amount: Amount,
+    min_amount: Option<Amount>,
+    max_amount: Option<Amount>,
+) -> Result<(), Error> {
+    let below_min = min_amount.is_some_and(|min| amount < min);
+    let above_max = max_amount.is_some_and(|max| amount > max);
+
+    if below_min || above_max {
+        tracing::debug!(
+            "Amount {} outside of {:?} - {:?}",
+            amount,
+            min_amount,
+            max_amount
+        );
+        return Err(Error::AmountOutOfLimitRange(amount));
+    }
+
+    Ok(())
 }
 
-#[derive(Debug, Hash, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct MintKeySetInfo {
-    pub id: Id,
-    pub unit: CurrencyUnit,
-    pub active: bool,
-    pub valid_from: u64,
-    pub valid_to: Option<u64>,
-    pub derivation_path: DerivationPath,
-    pub max_order: u8,
+/// Capability of the mint that can be disabled in [`MintConfig`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Endpoint {
+    /// Mint quotes and minting, NUT-04
+    Mint,
+    /// Melt quotes and melting, NUT-05
+    Melt,
+    /// Swap, NUT-03
+    Swap,
+    /// Check state, NUT-07
+    CheckState,
+    /// Restore, NUT-09
+    Restore,
 }
 
-impl From<MintKeySetInfo> for KeySetInfo {
-    fn from(keyset_info: MintKeySetInfo) -> Self {
-        Self {
-            id: keyset_info.id,
-            unit: keyset_info.unit,
-            active: keyset_info.active,
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mint => write!(f, "mint"),
+            Self::Melt => write!(f, "melt"),
+            Self::Swap => write!(f, "swap"),
+            Self::CheckState => write!(f, "check_state"),
+            Self::Restore => write!(f, "restore"),
         }
     }
 }
 
-fn create_new_keyset<C: secp256k1::Signing>(
-    secp: &secp256k1::Secp256k1<C>,
-    xpriv: ExtendedPrivKey,
-    derivation_path: DerivationPath,
-    unit: CurrencyUnit,
-    max_order: u8,
-) -> (MintKeySet, MintKeySetInfo) {
-    let keyset = MintKeySet::generate(
-        secp,
-        xpriv
-            .derive_priv(secp, &derivation_path)
-            .expect("RNG busted"),
-        unit,
-        max_order,
-    );
-    let keyset_info = MintKeySetInfo {
-        id: keyset.id,
-        unit: keyset.unit.clone(),
-        active: true,
-        valid_from: unix_time(),
-        valid_to: None,
-        derivation_path,
-        max_order,
-    };
-    (keyset, keyset_info)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeeReserve {
+    /// Minimum fee reserve in sats
+    pub min_fee_reserve: Amount,
+    pub percent_fee_reserve: f32,
+    /// Maximum fee reserve in sats, no maximum if `None`
+    #[serde(default)]
+    pub max_fee_reserve: Option<Amount>,
+}
+
+impl Default for FeeReserve {
+    fn default() -> Self {
+        Self {
+            min_fee_reserve: Amount::from(2),
+            percent_fee_reserve: 1.0,
+            max_fee_reserve: None,
+        }
+    }
+}
+
+impl FeeReserve {
+    /// Fee reserve for paying `amount` of `unit`
+    ///
+    /// `percent_fee_reserve` percent of `amount` rounded up, but at least
+    /// `min_fee_reserve` and at most `max_fee_reserve`, converted to `unit`.
+    pub fn fee_reserve(&self, amount: Amount, unit: &CurrencyUnit) -> Result<Amount, Error> {
+        let percent_fee_reserve = Amount::from(
+            (u64::from(amount) as f64 * self.percent_fee_reserve as f64 / 100.0).ceil() as u64,
+        );
+
+        self.clamp(percent_fee_reserve, unit)
+    }
+
+    /// Limit `fee_reserve` of `unit` to `min_fee_reserve` and `max_fee_reserve`
+    pub fn clamp(&self, fee_reserve: Amount, unit: &CurrencyUnit) -> Result<Amount, Error> {
+        let min_fee_reserve = self
+            .min_fee_reserve
+            .convert_unit(&CurrencyUnit::Sat, unit)?;
+        let fee_reserve = fee_reserve.max(min_fee_reserve);
+
+        match self.max_fee_reserve {
+            Some(max_fee_reserve) => {
+                let max_fee_reserve = max_fee_reserve.convert_unit(&CurrencyUnit::Sat, unit)?;
+                Ok(fee_reserve.min(max_fee_reserve))
+            }
+            None => Ok(fee_reserve),
+        }
+    }
+}
+
+/// Maximum size of a single request
+///
+/// The number of inputs and outputs applies to swap, mint, melt, check state
+/// and restore requests. Secrets and witnesses are limited so that long P2PK
+/// tag lists cannot bloat the spent proofs the mint stores forever.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RequestLimits {
+    pub max_inputs: usize,
+    pub max_outputs: usize,
+    /// Maximum length of a proof secret in bytes
+    pub max_secret_length: usize,
+    /// Maximum length of the serialized witness of a proof or output in bytes
+    pub max_witness_length: usize,
+    /// Maximum size of a request body in bytes
+    ///
+    /// Checked by the server with [`RequestLimits::check_body_size`] before
+    /// the body is parsed, and for NUT-17 WebSocket messages.
+    pub max_body_size: usize,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_inputs: 1000,
+            max_outputs: 1000,
+            max_secret_length: 1024,
+            max_witness_length: 4096,
+            max_body_size: 1024 * 1024,
+        }
+    }
+}
+
+impl RequestLimits {
+    fn check_inputs(&self, len: usize) -> Result<(), Error> {
+        if len > self.max_inputs {
+            tracing::debug!("Request has {} inputs, max is {}", len, self.max_inputs);
+            return Err(Error::TooManyInputs(len, self.max_inputs));
+        }
+
+        Ok(())
+    }
+
+    fn check_outputs(&self, len: usize) -> Result<(), Error> {
+        if len > self.max_outputs {
+            tracing::debug!("Request has {} outputs, max is {}", len, self.max_outputs);
+            return Err(Error::TooManyOutputs(len, self.max_outputs));
+        }
+
+        Ok(())
+    }
+
+    /// Check the secrets and witnesses of `proofs`
+    fn check_proofs(&self, proofs: &Proofs) -> Result<(), Error> {
+        for proof in proofs {
+            let len = proof.secret.as_bytes().len();
+            if len > self.max_secret_length {
+                tracing::debug!(
+                    "Proof secret is {} bytes, max is {}",
+                    len,
+                    self.max_secret_length
+                );
+                return Err(Error::SecretTooLong(len, self.max_secret_length));
+            }
+
+            if let Some(witness) = &proof.witness {
+                self.check_witness(witness)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check the witnesses of `outputs`
+    fn check_output_witnesses(&self, outputs: &[BlindedMessage]) -> Result<(), Error> {
+        for witness in outputs.iter().filter_map(|o| o.witness.as_ref()) {
+            self.check_witness(witness)?;
+        }
+
+        Ok(())
+    }
+
+    fn check_witness(&self, witness: &Witness) -> Result<(), Error> {
+        let len = serde_json::to_string(witness)
+            .map_err(|err| Error::Custom(err.to_string()))?
+            .len();
+        if len > self.max_witness_length {
+            tracing::debug!(
+                "Witness is {} bytes, max is {}",
+                len,
+                self.max_witness_length
+            );
+            return Err(Error::WitnessTooLong(len, self.max_witness_length));
+        }
+
+        Ok(())
+    }
+
+    /// Check the size of a request body of `len` bytes
+    pub fn check_body_size(&self, len: usize) -> Result<(), Error> {
+        if len > self.max_body_size {
+            tracing::debug!(
+                "Request body is {} bytes, max is {}",
+                len,
+                self.max_body_size
+            );
+            return Err(Error::BodyTooLarge(len, self.max_body_size));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Hash, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MintKeySetInfo {
+    pub id: Id,
+    pub unit: CurrencyUnit,
+    pub active: bool,
+    pub valid_from: u64,
+    pub valid_to: Option<u64>,
+    pub derivation_path: DerivationPath,
+    /// Index of the keyset in the derivation scheme of its unit
+    ///
+    /// `None` for keysets derived at custom paths.
+    #[serde(default)]
+    pub derivation_path_index: Option<u32>,
+    pub max_order: u8,
+}
+
+impl MintKeySetInfo {
+    /// Check if the keyset may sign at unix time `time`
+    pub fn is_valid_at(&self, time: u64) -> bool {
+        self.valid_from <= time && self.valid_to.map_or(true, |valid_to| time < valid_to)
+    }
+
+    /// Time from which proofs of the keyset are no longer redeemed
+    ///
+    /// `grace_period` seconds after the keyset stopped signing, `None` if it
+    /// does not expire.
+    pub fn final_expiry(&self, grace_period: u64) -> Option<u64> {
+        self.valid_to
+            .map(|valid_to| valid_to.saturating_add(grace_period))
+    }
+}
+
+/// Index of `derivation_path` in the derivation scheme of `unit`
+fn derivation_index_from_path(
+    unit: &CurrencyUnit,
+    derivation_path: &DerivationPath,
+) -> Option<u32> {
+    match derivation_path.as_ref().last()? {
+        ChildNumber::Hardened { index } => (derivation_path_from_unit(unit, *index)
+            .ok()?
+            .eq(derivation_path))
+        .then_some(*index),
+        ChildNumber::Normal { .. } => None,
+    }
+}
+
+/// Derivation path of the keyset at `index` for `unit`
+fn derivation_path_from_unit(unit: &CurrencyUnit, index: u32) -> Result<DerivationPath, Error> {
+    let unit_index = match unit {
+        CurrencyUnit::Sat => 0,
+        CurrencyUnit::Msat => 1,
+        CurrencyUnit::Usd => 2,
+        CurrencyUnit::Eur => 3,
+        CurrencyUnit::Custom(_) => return Err(Error::UnsupportedUnit),
+    };
+
+    Ok(DerivationPath::from(vec![
+        ChildNumber::from_hardened_idx(0).expect("0 is a valid index"),
+        ChildNumber::from_hardened_idx(unit_index).expect("unit index is valid"),
+        ChildNumber::from_hardened_idx(index).map_err(|e| Error::Custom(e.to_string()))?,
+    ]))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::HashMap;
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::amount::SplitTarget;
+    use crate::cdk_database::mint_memory::MintMemoryDatabase;
+    use crate::dhke::construct_proofs;
+
+    pub(crate) async fn create_mint() -> Mint {
+        let localstore = MintMemoryDatabase::new(
+            HashMap::new(),
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            HashMap::new(),
+        )
+        .unwrap();
+
+        Mint::new(
+            &[0; 32],
+            MintInfo::default(),
+            Arc::new(localstore),
+            Amount::from(1),
+            1.0,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_internal_melt_mint() {
+        let mint = create_mint().await;
+        let proofs = mint_proofs(&mint, Amount::from(10)).await;
+        let mut events = mint.events.subscribe();
+
+        let mint_quote = mint
+            .new_mint_quote(
+                MintUrl::from_str("https://mint.example").unwrap(),
+                "lnbc100n1internal".to_string(),
+                CurrencyUnit::Sat,
+                Amount::from(10),
+                unix_time() + 600,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let melt_quote = mint
+            .new_melt_quote(
+                mint_quote.request.clone(),
+                CurrencyUnit::Sat,
+                Amount::from(10),
+                Amount::from(2),
+                unix_time() + 600,
+                None,
+            )
+            .await
+            .unwrap();
+
+        // No fee is charged for internal payments
+        assert_eq!(melt_quote.fee_reserve, Amount::ZERO);
+
+        let melt_request = MeltBolt11Request {
+            quote: melt_quote.id.clone(),
+            inputs: proofs,
+            outputs: None,
+        };
+        mint.set_melt_quote_pending(&melt_request).await.unwrap();
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            events::MintEvent::ProofsPending { .. }
+        ));
+
+        let spent = mint.handle_internal_melt_mint(&melt_quote).await.unwrap();
+        assert_eq!(spent, Some(Amount::from(10)));
+
+        // The mint quote is only paid once the melt completes
+        assert!(!mint.check_mint_quote(&mint_quote.id).await.unwrap().paid);
+
+        mint.process_melt_request(&melt_request, None, spent.unwrap())
+            .await
+            .unwrap();
+
+        let status = mint.check_mint_quote(&mint_quote.id).await.unwrap();
+        assert!(status.paid);
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            events::MintEvent::ProofsSpent { .. }
+        ));
+        assert_eq!(
+            events.try_recv().unwrap(),
+            events::MintEvent::MintQuotePaid {
+                quote: mint_quote.id.clone(),
+                amount: Amount::from(10),
+                unit: CurrencyUnit::Sat,
+            }
+        );
+
+        // The same invoice cannot be settled twice
+        assert!(matches!(
+            mint.handle_internal_melt_mint(&melt_quote).await,
+            Err(Error::RequestAlreadyPaid)
+        ));
+
+        // A paid mint quote is not settled internally, the fee reserve is kept
+        let melt_quote = mint
+            .new_melt_quote(
+                mint_quote.request.clone(),
+                CurrencyUnit::Sat,
+                Amount::from(10),
+                Amount::from(2),
+                unix_time() + 600,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(melt_quote.fee_reserve, Amount::from(2));
+    }
+
+    #[tokio::test]
+    async fn test_external_melt() {
+        let mint = create_mint().await;
+
+        let melt_quote = mint
+            .new_melt_quote(
+                "lnbc100n1external".to_string(),
+                CurrencyUnit::Sat,
+                Amount::from(10),
+                Amount::from(2),
+                unix_time() + 600,
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(melt_quote.fee_reserve, Amount::from(2));
+        assert_eq!(
+            mint.handle_internal_melt_mint(&melt_quote).await.unwrap(),
+            None
+        );
+    }
+
+    async fn paid_mint_quote(mint: &Mint, amount: Amount) -> MintQuote {
+        let mut quote = mint
+            .new_mint_quote(
+                MintUrl::from_str("https://mint.example").unwrap(),
+                crate::secret::Secret::generate().to_string(),
+                CurrencyUnit::Sat,
+                amount,
+                unix_time() + 600,
+                None,
+            )
+            .await
+            .unwrap();
+        quote.paid = true;
+        mint.update_mint_quote(quote.clone()).await.unwrap();
+
+        quote
+    }
+
+    #[tokio::test]
+    async fn test_reject_signed_outputs() {
+        let mint = create_mint().await;
+        let keyset_id = mint.keysets().await.unwrap().keysets[0].id;
+
+        let premint =
+            PreMintSecrets::random(keyset_id, Amount::from(8), &SplitTarget::None).unwrap();
+
+        let quote = paid_mint_quote(&mint, Amount::from(8)).await;
+        mint.process_mint_request(MintBolt11Request {
+            quote: quote.id,
+            outputs: premint.blinded_messages(),
+        })
+        .await
+        .unwrap();
+
+        let quote = paid_mint_quote(&mint, Amount::from(8)).await;
+        assert!(matches!(
+            mint.process_mint_request(MintBolt11Request {
+                quote: quote.id,
+                outputs: premint.blinded_messages(),
+            })
+            .await,
+            Err(Error::BlindedMessageAlreadySigned)
+        ));
+
+        // Melt change outputs are checked before the request is paid
+        let melt_quote = mint
+            .new_melt_quote(
+                "lnbc80n1signed".to_string(),
+                CurrencyUnit::Sat,
+                Amount::from(4),
+                Amount::from(4),
+                unix_time() + 600,
+                None,
+            )
+            .await
+            .unwrap();
+        let melt_request = MeltBolt11Request {
+            quote: melt_quote.id,
+            inputs: mint_proofs(&mint, Amount::from(8)).await,
+            outputs: Some(premint.blinded_messages()),
+        };
+        assert!(matches!(
+            mint.set_melt_quote_pending(&melt_request).await,
+            Err(Error::BlindedMessageAlreadySigned)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_pending_melt_requires_its_inputs() {
+        let mint = create_mint().await;
+
+        let mut quotes = Vec::new();
+        for (request, amount) in [("lnbc200n1first", 24), ("lnbc40n1second", 8)] {
+            let quote = mint
+                .new_melt_quote(
+                    request.to_string(),
+                    CurrencyUnit::Sat,
+                    Amount::from(amount - 4),
+                    Amount::from(4),
+                    unix_time() + 600,
+                    None,
+                )
+                .await
+                .unwrap();
+            let melt_request = MeltBolt11Request {
+                quote: quote.id,
+                inputs: mint_proofs(&mint, Amount::from(amount)).await,
+                outputs: None,
+            };
+            mint.set_melt_quote_pending(&melt_request).await.unwrap();
+            quotes.push(melt_request);
+        }
+        let (first, second) = (&quotes[0], &quotes[1]);
+        assert_eq!(first.inputs.len(), 2);
+
+        // A subset of the pending inputs
+        let subset = MeltBolt11Request {
+            inputs: first.inputs[..1].to_vec(),
+            ..first.clone()
+        };
+        assert!(matches!(
+            mint.process_melt_request(&subset, None, Amount::from(16))
+                .await,
+            Err(Error::Custom(_))
+        ));
+
+        // Inputs pending for another quote
+        let other = MeltBolt11Request {
+            inputs: [&first.inputs[..1], &second.inputs[..]].concat(),
+            ..first.clone()
+        };
+        assert!(matches!(
+            mint.process_melt_request(&other, None, Amount::from(20))
+                .await,
+            Err(Error::Custom(_))
+        ));
+        assert!(matches!(
+            mint.process_unpaid_melt(&other, "failed").await,
+            Err(Error::Custom(_))
+        ));
+
+        mint.process_melt_request(first, None, Amount::from(20))
+            .await
+            .unwrap();
+        mint.process_melt_request(second, None, Amount::from(4))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_mint_quote_issued_once() {
+        let mint = create_mint().await;
+        let keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+        let quote = paid_mint_quote(&mint, Amount::from(8)).await;
+
+        let first = PreMintSecrets::random(keyset_id, Amount::from(8), &SplitTarget::None).unwrap();
+        let second =
+            PreMintSecrets::random(keyset_id, Amount::from(8), &SplitTarget::None).unwrap();
+        let request = |premint: &PreMintSecrets| MintBolt11Request {
+            quote: quote.id.clone(),
+            outputs: premint.blinded_messages(),
+        };
+
+        let (first_result, second_result) = tokio::join!(
+            mint.process_mint_request(request(&first)),
+            mint.process_mint_request(request(&second))
+        );
+        let (issued, rejected, err) = match (first_result, second_result) {
+            (Ok(_), Err(err)) => (&first, &second, err),
+            (Err(err), Ok(_)) => (&second, &first, err),
+            _ => panic!("Quote issued twice or not at all"),
+        };
+        assert!(matches!(err, Error::UnknownQuote));
+
+        // Only the outputs of the issued request are signed
+        let signed = |premint: &PreMintSecrets| {
+            mint.localstore.get_blinded_signatures(
+                premint
+                    .blinded_messages()
+                    .iter()
+                    .map(|o| o.blinded_secret)
+                    .collect(),
+            )
+        };
+        assert!(signed(issued).await.unwrap().iter().all(Option::is_some));
+        assert!(signed(rejected).await.unwrap().iter().all(Option::is_none));
+    }
+
+    #[tokio::test]
+    async fn test_reject_duplicate_outputs() {
+        let mint = create_mint().await;
+        let keyset_id = mint.keysets().await.unwrap().keysets[0].id;
+
+        let premint =
+            PreMintSecrets::random(keyset_id, Amount::from(4), &SplitTarget::None).unwrap();
+        let outputs = [premint.blinded_messages(), premint.blinded_messages()].concat();
+
+        let quote = paid_mint_quote(&mint, Amount::from(8)).await;
+        assert!(matches!(
+            mint.process_mint_request(MintBolt11Request {
+                quote: quote.id,
+                outputs,
+            })
+            .await,
+            Err(Error::DuplicateOutputs)
+        ));
+    }
+
+    pub(crate) async fn active_keyset_id(mint: &Mint, unit: &CurrencyUnit) -> Id {
+        mint.localstore
+            .get_active_keyset_id(unit)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    pub(crate) async fn mint_proofs(mint: &Mint, amount: Amount) -> Proofs {
+        let keyset_id = active_keyset_id(mint, &CurrencyUnit::Sat).await;
+        let premint = PreMintSecrets::random(keyset_id, amount, &SplitTarget::None).unwrap();
+
+        let quote = paid_mint_quote(mint, amount).await;
+        let response = mint
+            .process_mint_request(MintBolt11Request {
+                quote: quote.id,
+                outputs: premint.blinded_messages(),
+            })
+            .await
+            .unwrap();
+
+        let keys = mint.keyset_pubkeys(&keyset_id).await.unwrap().keysets[0]
+            .keys
+            .clone();
+
+        construct_proofs(response.signatures, premint.rs(), premint.secrets(), &keys).unwrap()
+    }
+
+    async fn assert_unspent(mint: &Mint, proofs: &Proofs) {
+        let ys = proofs.iter().map(|p| p.y().unwrap()).collect();
+        let states = mint.check_state(&CheckStateRequest { ys }).await.unwrap();
+        assert!(states.states.iter().all(|s| s.state.eq(&State::Unspent)));
+    }
+
+    #[tokio::test]
+    async fn test_swap_signing_error_keeps_inputs_unspent() {
+        let mint = create_mint().await;
+        let proofs = mint_proofs(&mint, Amount::from(8)).await;
+
+        let keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+        let premint =
+            PreMintSecrets::random(keyset_id, Amount::from(7), &SplitTarget::None).unwrap();
+
+        // 4 + 3 + 1: the first output can be signed, there is no key for 3
+        let mut outputs = premint.blinded_messages();
+        assert_eq!(outputs[1].amount, Amount::from(2));
+        outputs[1].amount = Amount::from(3);
+
+        assert!(mint
+            .process_swap_request(SwapRequest::new(proofs.clone(), outputs.clone()))
+            .await
+            .is_err());
+
+        assert_unspent(&mint, &proofs).await;
+        let blinded_messages = outputs.iter().map(|b| b.blinded_secret).collect();
+        assert!(mint
+            .localstore
+            .get_blinded_signatures(blinded_messages)
+            .await
+            .unwrap()
+            .iter()
+            .all(|s| s.is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_swaps_spend_once() {
+        let mint = create_mint().await;
+        let proofs = mint_proofs(&mint, Amount::from(8)).await;
+        let keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+        let swap = || {
+            let premint =
+                PreMintSecrets::random(keyset_id, Amount::from(8), &SplitTarget::None).unwrap();
+            mint.process_swap_request(SwapRequest::new(proofs.clone(), premint.blinded_messages()))
+        };
+
+        let (first, second) = tokio::join!(swap(), swap());
+        assert!(first.is_ok() != second.is_ok());
+        assert!(matches!(first.and(second), Err(Error::TokenAlreadySpent)));
+
+        // Spending the inputs again is rejected by the database itself
+        assert!(matches!(
+            mint.localstore
+                .add_spent_proofs_and_signatures(proofs, vec![])
+                .await
+                .map_err(Error::from),
+            Err(Error::TokenAlreadySpent)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_melt_stores_change_with_inputs() {
+        let mint = create_mint().await;
+        let proofs = mint_proofs(&mint, Amount::from(16)).await;
+        let keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+
+        let quote = mint
+            .new_melt_quote(
+                "lnbc80n1change".to_string(),
+                CurrencyUnit::Sat,
+                Amount::from(8),
+                Amount::from(4),
+                unix_time() + 3600,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let premint = PreMintSecrets::blank(keyset_id, Amount::from(8)).unwrap();
+        let melt_request = MeltBolt11Request {
+            quote: quote.id.clone(),
+            inputs: proofs.clone(),
+            outputs: Some(premint.blinded_messages()),
+        };
+        mint.set_melt_quote_pending(&melt_request).await.unwrap();
+        let response = mint
+            .process_melt_request(&melt_request, None, Amount::from(8))
+            .await
+            .unwrap();
+
+        let change = response.change.unwrap();
+        assert_eq!(
+            Amount::try_sum(change.iter().map(|s| s.amount)).unwrap(),
+            Amount::from(8)
+        );
+        let ys = proofs.iter().map(|p| p.y().unwrap()).collect();
+        let states = mint.check_state(&CheckStateRequest { ys }).await.unwrap();
+        assert!(states.states.iter().all(|s| s.state.eq(&State::Spent)));
+        let blinded_messages = premint
+            .blinded_messages()
+            .iter()
+            .take(change.len())
+            .map(|b| b.blinded_secret)
+            .collect();
+        assert!(mint
+            .localstore
+            .get_blinded_signatures(blinded_messages)
+            .await
+            .unwrap()
+            .iter()
+            .all(|s| s.is_some()));
+
+        // The inputs are spent, completing the melt again is rejected
+        let completed = mint
+            .localstore
+            .get_completed_melt(&quote.id)
+            .await
+            .unwrap()
+            .unwrap();
+        let quote = mint.localstore.get_melt_quote(&quote.id).await.unwrap();
+        assert_eq!(quote.as_ref().unwrap().state, MeltQuoteState::Paid);
+        assert!(matches!(
+            mint.localstore
+                .complete_melt(proofs, vec![], completed, quote.unwrap(), None)
+                .await
+                .map_err(Error::from),
+            Err(Error::TokenAlreadySpent)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_verified_proof_cache() {
+        let mut mint = create_mint().await;
+        mint.verified_proofs = Some(VerifiedProofCache::new(16));
+        let proofs = mint_proofs(&mint, Amount::from(8)).await;
+        let cache = mint.verified_proofs.clone().unwrap();
+
+        mint.verify_proof(&proofs[0]).await.unwrap();
+        assert!(cache.contains(&proofs[0].y().unwrap(), &proofs[0]));
+
+        // A cached secret with another signature is still rejected
+        let mut forged = proofs[0].clone();
+        forged.c = proofs[0].y().unwrap();
+        assert!(mint.verify_proof(&forged).await.is_err());
+
+        // So is a cached secret and signature with a larger amount
+        let mut forged = proofs[0].clone();
+        forged.amount = Amount::from(64);
+        assert!(mint.verify_proof(&forged).await.is_err());
+        assert!(!cache.contains(&forged.y().unwrap(), &forged));
+
+        mint.rotate_next_keyset(CurrencyUnit::Sat, 32)
+            .await
+            .unwrap();
+        assert!(cache.is_empty());
+    }
+
+    /// Condition met by proofs with the data as witness preimage
+    struct Password;
+
+    impl ConditionVerifier for Password {
+        fn kind(&self) -> &str {
+            "PASSWORD"
+        }
+
+        fn verify(&self, secret: &CustomSecret, proof: &Proof) -> Result<(), crate::error::Error> {
+            match proof.witness.as_ref().and_then(|w| w.preimage()) {
+                Some(preimage) if preimage == secret.secret_data.data => Ok(()),
+                _ => Err(crate::error::Error::TokenNotVerified),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_condition() {
+        let mut mint = create_mint().await;
+        mint.custom_conditions.register(Arc::new(Password));
+
+        let keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+        let condition = CustomSecret::new("PASSWORD", "hunter2", vec![]);
+        let premint = PreMintSecrets::with_custom_condition(
+            keyset_id,
+            Amount::from(4),
+            &SplitTarget::None,
+            &condition,
+        )
+        .unwrap();
+
+        let quote = paid_mint_quote(&mint, Amount::from(4)).await;
+        let response = mint
+            .process_mint_request(MintBolt11Request {
+                quote: quote.id,
+                outputs: premint.blinded_messages(),
+            })
+            .await
+            .unwrap();
+        let keys = mint.keyset_pubkeys(&keyset_id).await.unwrap().keysets[0]
+            .keys
+            .clone();
+        let mut proofs =
+            construct_proofs(response.signatures, premint.rs(), premint.secrets(), &keys).unwrap();
+
+        let outputs = PreMintSecrets::random(keyset_id, Amount::from(4), &SplitTarget::None)
+            .unwrap()
+            .blinded_messages();
+
+        let err = mint
+            .process_swap_request(SwapRequest::new(proofs.clone(), outputs.clone()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::SpendingConditionNotMet(_)));
+
+        for proof in proofs.iter_mut() {
+            proof.witness = Some(Witness::HTLCWitness(nut14::HTLCWitness {
+                preimage: "hunter2".to_string(),
+                signatures: None,
+            }));
+        }
+        mint.process_swap_request(SwapRequest::new(proofs, outputs))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_swap_rejects_other_unit_outputs() {
+        let mint = create_mint().await;
+        let proofs = mint_proofs(&mint, Amount::from(8)).await;
+
+        let usd_path = DerivationPath::from(vec![ChildNumber::from_hardened_idx(1).unwrap()]);
+        mint.rotate_keyset(CurrencyUnit::Usd, usd_path, 32)
+            .await
+            .unwrap();
+        let usd_keyset_id = active_keyset_id(&mint, &CurrencyUnit::Usd).await;
+
+        let premint =
+            PreMintSecrets::random(usd_keyset_id, Amount::from(8), &SplitTarget::None).unwrap();
+
+        assert!(matches!(
+            mint.process_swap_request(SwapRequest::new(proofs.clone(), premint.blinded_messages()))
+                .await,
+            Err(Error::MultipleUnits)
+        ));
+
+        assert_unspent(&mint, &proofs).await;
+    }
+
+    #[tokio::test]
+    async fn test_swap_rejects_inactive_keyset_outputs() {
+        let mint = create_mint().await;
+        let proofs = mint_proofs(&mint, Amount::from(8)).await;
+        let old_keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+
+        let new_path = DerivationPath::from(vec![ChildNumber::from_hardened_idx(1).unwrap()]);
+        mint.rotate_keyset(CurrencyUnit::Sat, new_path, 32)
+            .await
+            .unwrap();
+
+        let premint =
+            PreMintSecrets::random(old_keyset_id, Amount::from(8), &SplitTarget::None).unwrap();
+
+        assert!(matches!(
+            mint.process_swap_request(SwapRequest::new(proofs.clone(), premint.blinded_messages()))
+                .await,
+            Err(Error::InactiveKeyset)
+        ));
+
+        assert_unspent(&mint, &proofs).await;
+    }
+
+    #[tokio::test]
+    async fn test_keyset_validity() {
+        let mint = create_mint().await;
+        let proofs = mint_proofs(&mint, Amount::from(3)).await;
+        let (first, rest) = proofs.split_at(1);
+        let keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+
+        let mut config = mint.config().await;
+        config.keyset_grace_period = 3600;
+        mint.reload_config(config.clone()).await;
+
+        let valid_to = unix_time() - 10;
+        mint.set_keyset_valid_to(&keyset_id, Some(valid_to))
+            .await
+            .unwrap();
+
+        // Expired keysets do not sign
+        let premint =
+            PreMintSecrets::random(keyset_id, first[0].amount, &SplitTarget::None).unwrap();
+        assert!(matches!(
+            mint.process_swap_request(SwapRequest::new(first.to_vec(), premint.blinded_messages()))
+                .await,
+            Err(Error::ExpiredKeyset)
+        ));
+
+        // Proofs are redeemed within the grace period
+        let new_path = DerivationPath::from(vec![ChildNumber::from_hardened_idx(1).unwrap()]);
+        mint.rotate_keyset(CurrencyUnit::Sat, new_path, 32)
+            .await
+            .unwrap();
+        let new_keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+        let premint =
+            PreMintSecrets::random(new_keyset_id, first[0].amount, &SplitTarget::None).unwrap();
+        mint.process_swap_request(SwapRequest::new(first.to_vec(), premint.blinded_messages()))
+            .await
+            .unwrap();
+
+        let keysets = mint.keysets().await.unwrap().keysets;
+        let keyset = keysets.iter().find(|k| k.id == keyset_id).unwrap();
+        assert_eq!(keyset.final_expiry, Some(valid_to + 3600));
+
+        config.keyset_grace_period = 0;
+        mint.reload_config(config).await;
+        let amount = rest.iter().map(|p| p.amount).sum();
+        let premint = PreMintSecrets::random(new_keyset_id, amount, &SplitTarget::None).unwrap();
+        assert!(matches!(
+            mint.process_swap_request(SwapRequest::new(rest.to_vec(), premint.blinded_messages()))
+                .await,
+            Err(Error::ExpiredKeyset)
+        ));
+
+        // A long grace period does not overflow the expiry
+        let mut config = mint.config().await;
+        config.keyset_grace_period = u64::MAX;
+        mint.reload_config(config).await;
+        let keysets = mint.keysets().await.unwrap().keysets;
+        let keyset = keysets.iter().find(|k| k.id == keyset_id).unwrap();
+        assert_eq!(keyset.final_expiry, Some(u64::MAX));
+    }
+
+    #[tokio::test]
+    async fn test_generate_keyset() {
+        let mint = create_mint().await;
+        let first_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+
+        assert!(matches!(
+            mint.generate_keyset(CurrencyUnit::Sat, 0, None).await,
+            Err(Error::InvalidKeysetParameters(_))
+        ));
+        assert!(matches!(
+            mint.generate_keyset(CurrencyUnit::Sat, 32, Some(unix_time() - 1))
+                .await,
+            Err(Error::InvalidKeysetParameters(_))
+        ));
+
+        let valid_to = unix_time() + 3600;
+        let keyset_info = mint
+            .generate_keyset(CurrencyUnit::Sat, 16, Some(valid_to))
+            .await
+            .unwrap();
+        assert_eq!(keyset_info.valid_to, Some(valid_to));
+        assert_eq!(keyset_info.max_order, 16);
+        assert_eq!(keyset_info.derivation_path_index, Some(1));
+        assert_eq!(
+            active_keyset_id(&mint, &CurrencyUnit::Sat).await,
+            keyset_info.id
+        );
+
+        // The previous keyset is deactivated
+        let active: Vec<Id> = mint
+            .localstore
+            .get_keyset_infos()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|k| k.unit == CurrencyUnit::Sat && k.active)
+            .map(|k| k.id)
+            .collect();
+        assert_eq!(active, vec![keyset_info.id]);
+        assert_ne!(first_id, keyset_info.id);
+    }
+
+    #[tokio::test]
+    async fn test_request_limits() {
+        let mint = create_mint().await;
+        let proofs = mint_proofs(&mint, Amount::from(7)).await;
+        assert_eq!(proofs.len(), 3);
+
+        let mut config = mint.config().await;
+        config.limits = RequestLimits {
+            max_inputs: 2,
+            max_outputs: 2,
+            ..Default::default()
+        };
+        mint.reload_config(config).await;
+
+        let keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+        let premint =
+            PreMintSecrets::random(keyset_id, Amount::from(7), &SplitTarget::None).unwrap();
+
+        assert!(matches!(
+            mint.process_swap_request(SwapRequest::new(proofs, premint.blinded_messages()))
+                .await,
+            Err(Error::TooManyInputs(3, 2))
+        ));
+
+        let quote = paid_mint_quote(&mint, Amount::from(7)).await;
+        assert!(matches!(
+            mint.process_mint_request(MintBolt11Request {
+                quote: quote.id,
+                outputs: premint.blinded_messages(),
+            })
+            .await,
+            Err(Error::TooManyOutputs(3, 2))
+        ));
+
+        assert!(matches!(
+            mint.restore(RestoreRequest {
+                outputs: premint.blinded_messages(),
+            })
+            .await,
+            Err(Error::TooManyOutputs(3, 2))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_secret_limits() {
+        let mint = create_mint().await;
+        let mut proofs = mint_proofs(&mint, Amount::from(8)).await;
+        let keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+        let premint =
+            PreMintSecrets::random(keyset_id, Amount::from(8), &SplitTarget::None).unwrap();
+        let swap = |proofs: Proofs| SwapRequest::new(proofs, premint.blinded_messages());
+
+        let mut config = mint.config().await;
+        config.limits.max_secret_length = 32;
+        mint.reload_config(config.clone()).await;
+
+        let err = mint
+            .process_swap_request(swap(proofs.clone()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::SecretTooLong(64, 32)));
+        assert_eq!(err.error_code(), crate::error::ErrorCode::SecretTooLong);
+
+        config.limits = RequestLimits::default();
+        mint.reload_config(config).await;
+
+        proofs[0].witness = Some(Witness::P2PKWitness(P2PKWitness {
+            signatures: vec!["00".repeat(64); 64],
+        }));
+        assert!(matches!(
+            mint.process_swap_request(swap(proofs)).await,
+            Err(Error::WitnessTooLong(_, 4096))
+        ));
+
+        let limits = RequestLimits::default();
+        assert!(limits.check_body_size(1024 * 1024).is_ok());
+        assert!(matches!(
+            limits.check_body_size(1024 * 1024 + 1),
+            Err(Error::BodyTooLarge(_, _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_disabled_endpoints() {
+        let mint = create_mint().await;
+        let proofs = mint_proofs(&mint, Amount::from(8)).await;
+
+        // Exchange mode: only swaps
+        let mut config = mint.config().await;
+        config.disabled_endpoints = HashSet::from([
+            Endpoint::Mint,
+            Endpoint::Melt,
+            Endpoint::CheckState,
+            Endpoint::Restore,
+        ]);
+        mint.reload_config(config).await;
+
+        let err = mint
+            .new_mint_quote(
+                MintUrl::from_str("https://mint.example").unwrap(),
+                "lnbc100n1disabled".to_string(),
+                CurrencyUnit::Sat,
+                Amount::from(10),
+                unix_time() + 600,
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::EndpointDisabled(Endpoint::Mint)));
+        assert_eq!(err.error_code(), crate::error::ErrorCode::MintingDisabled);
+
+        let err = mint
+            .new_melt_quote(
+                "lnbc100n1disabled".to_string(),
+                CurrencyUnit::Sat,
+                Amount::from(10),
+                Amount::from(2),
+                unix_time() + 600,
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.error_code(), crate::error::ErrorCode::EndpointDisabled);
+
+        let ys = proofs.iter().map(|p| p.y().unwrap()).collect();
+        assert!(matches!(
+            mint.check_state(&CheckStateRequest { ys }).await,
+            Err(Error::EndpointDisabled(Endpoint::CheckState))
+        ));
+
+        let info = mint.mint_info().await.unwrap();
+        assert!(info.nuts.nut04.disabled);
+        assert!(info.nuts.nut04.methods.is_empty());
+        assert!(info.nuts.nut05.disabled);
+        assert!(!info.nuts.nut07.is_supported());
+        assert!(!info.nuts.nut09.is_supported());
+
+        let keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+        let premint =
+            PreMintSecrets::random(keyset_id, Amount::from(8), &SplitTarget::None).unwrap();
+        mint.process_swap_request(SwapRequest::new(proofs, premint.blinded_messages()))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_archive_spent_proofs() {
+        let mint = create_mint().await;
+        let proofs = mint_proofs(&mint, Amount::from(8)).await;
+        let old_keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+
+        let premint =
+            PreMintSecrets::random(old_keyset_id, Amount::from(8), &SplitTarget::None).unwrap();
+        mint.process_swap_request(SwapRequest::new(proofs.clone(), premint.blinded_messages()))
+            .await
+            .unwrap();
+
+        let policy = archive::ArchivePolicy::after_days(1);
+
+        // The keyset is still active
+        assert!(mint.archive_spent_proofs(&policy).await.unwrap().is_empty());
+
+        mint.rotate_next_keyset(CurrencyUnit::Sat, 32)
+            .await
+            .unwrap();
+        mint.set_keyset_valid_to(&old_keyset_id, Some(unix_time() - 2 * 24 * 60 * 60))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            mint.archive_spent_proofs(&policy).await.unwrap(),
+            vec![archive::ArchivedKeyset {
+                id: old_keyset_id,
+                proofs: 1,
+            }]
+        );
+        assert!(mint.archive_spent_proofs(&policy).await.unwrap().is_empty());
+
+        let ys = proofs.iter().map(|p| p.y().unwrap()).collect();
+        let states = mint.check_state(&CheckStateRequest { ys }).await.unwrap();
+        assert_eq!(states.states[0].state, State::Spent);
+
+        let keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+        let premint =
+            PreMintSecrets::random(keyset_id, Amount::from(8), &SplitTarget::None).unwrap();
+        assert!(matches!(
+            mint.process_swap_request(SwapRequest::new(proofs, premint.blinded_messages()))
+                .await,
+            Err(Error::TokenAlreadySpent)
+        ));
+
+        let report = mint.accounting_report().await.unwrap();
+        assert_eq!(report.units[0].redeemed, Amount::from(8));
+    }
+
+    #[tokio::test]
+    async fn test_accounting_report() {
+        let mint = create_mint().await;
+        let proofs = mint_proofs(&mint, Amount::from(8)).await;
+
+        let keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+        let premint =
+            PreMintSecrets::random(keyset_id, Amount::from(8), &SplitTarget::None).unwrap();
+        mint.process_swap_request(SwapRequest::new(proofs, premint.blinded_messages()))
+            .await
+            .unwrap();
+
+        let report = mint.accounting_report().await.unwrap();
+
+        assert_eq!(
+            report.units,
+            vec![accounting::UnitBalance {
+                unit: CurrencyUnit::Sat,
+                issued: Amount::from(16),
+                redeemed: Amount::from(8),
+                outstanding: Amount::from(8),
+            }]
+        );
+        assert_eq!(report.keysets.len(), 1);
+        assert_eq!(report.keysets[0].id, keyset_id);
+        assert_eq!(report.keysets[0].outstanding, Amount::from(8));
+    }
+
+    #[tokio::test]
+    async fn test_fees_report() {
+        let mint = create_mint().await;
+        let proofs = mint_proofs(&mint, Amount::from(16)).await;
+        let keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+
+        let quote = mint
+            .new_melt_quote(
+                "lnbc80n1fees".to_string(),
+                CurrencyUnit::Sat,
+                Amount::from(8),
+                Amount::from(4),
+                unix_time() + 3600,
+                None,
+            )
+            .await
+            .unwrap();
+
+        // 7 change needs 3 outputs, with one the 4 is returned and 3 is burnt
+        let premint =
+            PreMintSecrets::random(keyset_id, Amount::from(1), &SplitTarget::None).unwrap();
+        let melt_request = MeltBolt11Request {
+            quote: quote.id,
+            inputs: proofs,
+            outputs: Some(premint.blinded_messages()),
+        };
+        mint.process_melt_request(&melt_request, None, Amount::from(9))
+            .await
+            .unwrap();
+
+        let report = mint.fees_report(0, u64::MAX).await.unwrap();
+        assert_eq!(
+            report.keysets,
+            vec![accounting::KeysetFees {
+                id: keyset_id,
+                unit: CurrencyUnit::Sat,
+                melts: 1,
+                lightning_fees: Amount::from(1),
+                burnt_change: Amount::from(3),
+            }]
+        );
+
+        let report = mint.fees_report(0, 1).await.unwrap();
+        assert!(report.keysets.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_mint_info_methods() {
+        let mint = create_mint().await;
+
+        let mint_info = mint.mint_info().await.unwrap();
+
+        assert!(mint_info.time.is_some());
+        assert_eq!(mint_info.nuts.nut04.methods.len(), 1);
+        assert_eq!(mint_info.nuts.nut04.methods[0].unit, CurrencyUnit::Sat);
+        assert_eq!(mint_info.nuts.nut05.methods.len(), 1);
+        assert_eq!(
+            mint_info.nuts.nut05.methods[0].method,
+            PaymentMethod::Bolt11
+        );
+
+        // Configured limits apply to the units of the mint only
+        let mut config = mint.config().await;
+        config.mint_info.nuts.nut04.methods = [CurrencyUnit::Sat, CurrencyUnit::Usd]
+            .into_iter()
+            .map(|unit| nut04::MintMethodSettings {
+                method: PaymentMethod::Bolt11,
+                unit,
+                min_amount: None,
+                max_amount: Some(Amount::from(100)),
+                description: false,
+            })
+            .collect();
+        config.mint_info.nuts.nut05.disabled = true;
+        config.mint_info.nuts.nut12 = nut06::SupportedSettings::unsupported();
+        mint.reload_config(config).await;
+
+        let nuts = mint.mint_info().await.unwrap().nuts;
+        assert_eq!(nuts.nut04.methods.len(), 1);
+        assert_eq!(nuts.nut04.methods[0].unit, CurrencyUnit::Sat);
+        assert_eq!(nuts.nut04.methods[0].max_amount, Some(Amount::from(100)));
+        assert!(nuts.nut05.disabled);
+        assert!(nuts.nut05.methods.is_empty());
+        assert!(nuts.nut12.is_supported());
+
+        let result = mint
+            .new_mint_quote(
+                MintUrl::from_str("https://mint.example").unwrap(),
+                "lnbc1u1limit".to_string(),
+                CurrencyUnit::Sat,
+                Amount::from(101),
+                unix_time() + 600,
+                None,
+            )
+            .await;
+        assert!(matches!(result, Err(Error::AmountOutOfLimitRange(_))));
+
+        let result = mint
+            .new_melt_quote(
+                "lnbc1u1limit".to_string(),
+                CurrencyUnit::Sat,
+                Amount::from(10),
+                Amount::from(2),
+                unix_time() + 600,
+                None,
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::EndpointDisabled(Endpoint::Melt))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rotate_next_keyset() {
+        let mnemonic = Mnemonic::from_str(
+            "half depart obvious quality work element tank gorilla view sugar picture humble",
+        )
+        .unwrap();
+
+        let localstore = Arc::new(
+            MintMemoryDatabase::new(
+                HashMap::new(),
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                HashMap::new(),
+            )
+            .unwrap(),
+        );
+
+        let mint = Mint::from_mnemonic(
+            &mnemonic,
+            MintInfo::default(),
+            localstore.clone(),
+            Amount::from(1),
+            1.0,
+        )
+        .await
+        .unwrap();
+        let first_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+
+        mint.rotate_next_keyset(CurrencyUnit::Sat, 32)
+            .await
+            .unwrap();
+        let second_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+        assert_ne!(first_id, second_id);
+
+        let second_info = localstore
+            .get_keyset_info(&second_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            second_info.derivation_path,
+            DerivationPath::from_str("m/0'/0'/1'").unwrap()
+        );
+        assert_eq!(second_info.derivation_path_index, Some(1));
+
+        // Indexes of keysets at paths of the scheme are never reused
+        mint.rotate_keyset(
+            CurrencyUnit::Sat,
+            DerivationPath::from_str("m/0'/0'/5'").unwrap(),
+            32,
+        )
+        .await
+        .unwrap();
+        mint.rotate_next_keyset(CurrencyUnit::Sat, 32)
+            .await
+            .unwrap();
+        let next_info = localstore
+            .get_keyset_info(&active_keyset_id(&mint, &CurrencyUnit::Sat).await)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(next_info.derivation_path_index, Some(6));
+
+        // A new mint from the same mnemonic and database regenerates the keys
+        let restored = Mint::from_mnemonic(
+            &mnemonic,
+            MintInfo::default(),
+            localstore,
+            Amount::from(1),
+            1.0,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            restored.keyset(&first_id).await.unwrap(),
+            mint.keyset(&first_id).await.unwrap()
+        );
+        assert_eq!(
+            restored.keyset(&second_id).await.unwrap(),
+            mint.keyset(&second_id).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_quotes_for_units_with_active_keyset() {
+        let mint = create_mint().await;
+
+        assert!(matches!(
+            mint.new_mint_quote(
+                MintUrl::from_str("https://mint.example").unwrap(),
+                "request".to_string(),
+                CurrencyUnit::Usd,
+                Amount::from(10),
+                unix_time() + 600,
+                None,
+            )
+            .await,
+            Err(Error::UnsupportedUnit)
+        ));
+
+        mint.rotate_next_keyset(CurrencyUnit::Usd, 32)
+            .await
+            .unwrap();
+        assert_eq!(
+            mint.units().await.unwrap(),
+            vec![CurrencyUnit::Sat, CurrencyUnit::Usd]
+        );
+
+        let quote = mint
+            .new_mint_quote(
+                MintUrl::from_str("https://mint.example").unwrap(),
+                "request".to_string(),
+                CurrencyUnit::Usd,
+                Amount::from(10),
+                unix_time() + 600,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(quote.unit, CurrencyUnit::Usd);
+
+        let proofs = mint_proofs(&mint, Amount::from(8)).await;
+        assert_eq!(proofs.len(), 1);
+        let sat_keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+        assert_eq!(proofs[0].keyset_id, sat_keyset_id);
+    }
+
+    #[tokio::test]
+    async fn test_reload_config() {
+        let mint = create_mint().await;
+        let server = mint.clone();
+
+        let mut config = mint.config().await;
+        config.mint_info.motd = Some("Maintenance at noon".to_string());
+        config.fee_reserve.min_fee_reserve = Amount::from(5);
+        mint.reload_config(config.clone()).await;
+
+        assert_eq!(server.config().await, config);
+        assert_eq!(
+            server.mint_info().await.unwrap().motd.as_deref(),
+            Some("Maintenance at noon")
+        );
+    }
+
+    struct FixedRate(u64);
+
+    #[async_trait::async_trait]
+    impl exchange_rate::ExchangeRate for FixedRate {
+        async fn rate(
+            &self,
+            unit: &CurrencyUnit,
+        ) -> Result<exchange_rate::Rate, exchange_rate::Error> {
+            Ok(exchange_rate::Rate {
+                unit: unit.clone(),
+                per_btc: self.0,
+                timestamp: unix_time(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fiat_quote_locks_rate() {
+        let mut mint = create_mint().await;
+        let usd_path = DerivationPath::from(vec![ChildNumber::from_hardened_idx(1).unwrap()]);
+        mint.rotate_keyset(CurrencyUnit::Usd, usd_path, 32)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            mint.lock_exchange_rate(&CurrencyUnit::Usd).await,
+            Err(Error::ExchangeRateUnavailable)
+        ));
+        assert_eq!(
+            mint.lock_exchange_rate(&CurrencyUnit::Sat).await.unwrap(),
+            None
+        );
+
+        mint.exchange_rate = Some(Arc::new(FixedRate(6_000_000)));
+        let exchange_rate = mint.lock_exchange_rate(&CurrencyUnit::Usd).await.unwrap();
+        assert_eq!(exchange_rate, Some(6_000_000));
+
+        let quote = mint
+            .new_mint_quote(
+                MintUrl::from_str("https://mint.example").unwrap(),
+                "lnbc16670n1fiat".to_string(),
+                CurrencyUnit::Usd,
+                Amount::from(100),
+                unix_time() + 600,
+                exchange_rate,
+            )
+            .await
+            .unwrap();
+
+        // Price changes do not affect the quote
+        mint.exchange_rate = Some(Arc::new(FixedRate(7_000_000)));
+        assert_eq!(
+            mint.check_mint_quote(&quote.id)
+                .await
+                .unwrap()
+                .exchange_rate,
+            Some(6_000_000)
+        );
+    }
+
+    struct FixedFee(Option<u64>);
+
+    #[async_trait::async_trait]
+    impl FeeEstimator for FixedFee {
+        async fn estimate_fee(&self, _invoice: &Bolt11Invoice) -> Result<Option<Amount>, Error> {
+            Ok(self.0.map(Amount::from))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_melt_fee_reserve() {
+        let mut mint = create_mint().await;
+        let invoice = Bolt11Invoice::from_str("lnbc25m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5vdhkven9v5sxyetpdeessp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygs9q5sqqqqqqqqqqqqqqqpqsq67gye39hfg3zd8rgc80k32tvy9xk2xunwm5lzexnvpx6fd77en8qaq424dxgt56cag2dpt359k3ssyhetktkpqh24jqnjyw6uqd08sgptq44qu").unwrap();
+        let amount = Amount::from(2_500_000);
+        let sat = CurrencyUnit::Sat;
+
+        // 1% of the amount without an estimator
+        assert_eq!(
+            mint.melt_fee_reserve(&invoice, amount, &sat).await.unwrap(),
+            Amount::from(25_000)
+        );
+
+        mint.fee_estimator = Some(Arc::new(FixedFee(Some(40))));
+        assert_eq!(
+            mint.melt_fee_reserve(&invoice, amount, &sat).await.unwrap(),
+            Amount::from(40)
+        );
+        assert_eq!(
+            mint.melt_fee_reserve(&invoice, Amount::from(2_500_000_000), &CurrencyUnit::Msat)
+                .await
+                .unwrap(),
+            Amount::from(40_000)
+        );
+
+        // Never below the minimum fee reserve
+        mint.fee_estimator = Some(Arc::new(FixedFee(Some(0))));
+        assert_eq!(
+            mint.melt_fee_reserve(&invoice, amount, &sat).await.unwrap(),
+            Amount::from(1)
+        );
+
+        mint.fee_estimator = Some(Arc::new(FixedFee(None)));
+        assert_eq!(
+            mint.melt_fee_reserve(&invoice, amount, &sat).await.unwrap(),
+            Amount::from(25_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_quote_by_payment_hash() {
+        let mint = create_mint().await;
+        let request = "lnbc25m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5vdhkven9v5sxyetpdeessp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygs9q5sqqqqqqqqqqqqqqqpqsq67gye39hfg3zd8rgc80k32tvy9xk2xunwm5lzexnvpx6fd77en8qaq424dxgt56cag2dpt359k3ssyhetktkpqh24jqnjyw6uqd08sgptq44qu";
+        let payment_hash = "0001020304050607080900010203040506070809000102030405060708090102";
+
+        let quote = mint
+            .new_mint_quote(
+                MintUrl::from_str("https://mint.example").unwrap(),
+                request.to_string(),
+                CurrencyUnit::Sat,
+                Amount::from(2_500_000),
+                unix_time() + 600,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(quote.payment_hash().as_deref(), Some(payment_hash));
+
+        let found = mint.quote_by_payment_hash(payment_hash).await.unwrap();
+        assert_eq!(found.map(|q| q.id), Some(quote.id.clone()));
+        assert!(mint
+            .quote_by_payment_hash(&"00".repeat(32))
+            .await
+            .unwrap()
+            .is_none());
+
+        let paid = mint
+            .pay_mint_quote_by_payment_hash(payment_hash)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(paid.paid);
+        assert!(mint.check_mint_quote(&quote.id).await.unwrap().paid);
+
+        mint.remove_mint_quote(&quote.id).await.unwrap();
+        assert!(mint
+            .quote_by_payment_hash(payment_hash)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_invoice_description() {
+        let mint = create_mint().await;
+        let sat = CurrencyUnit::Sat;
+
+        assert_eq!(mint.invoice_description(&sat, None).await.unwrap(), None);
+
+        let mut config = mint.config().await;
+        config.mint_info.name = Some("Mint X".to_string());
+        config.invoice_description = Some("{name} - ecash mint".to_string());
+        mint.reload_config(config.clone()).await;
+
+        assert_eq!(
+            mint.invoice_description(&sat, None).await.unwrap(),
+            Some("Mint X - ecash mint".to_string())
+        );
+        assert_eq!(
+            mint.invoice_description(&sat, Some("Coffee".to_string()))
+                .await
+                .unwrap(),
+            Some("Coffee".to_string())
+        );
+        assert!(matches!(
+            mint.invoice_description(&sat, Some("a".repeat(640))).await,
+            Err(Error::InvoiceDescriptionTooLong(_))
+        ));
+
+        config.mint_info.nuts.nut04.methods = vec![nut04::MintMethodSettings {
+            method: PaymentMethod::Bolt11,
+            unit: sat.clone(),
+            min_amount: None,
+            max_amount: None,
+            description: false,
+        }];
+        mint.reload_config(config).await;
+
+        assert!(matches!(
+            mint.invoice_description(&sat, Some("Coffee".to_string()))
+                .await,
+            Err(Error::InvoiceDescriptionNotSupported)
+        ));
+    }
+
+    #[test]
+    fn test_fee_reserve_units() {
+        let mut fee_reserve = FeeReserve {
+            min_fee_reserve: Amount::from(2),
+            percent_fee_reserve: 1.0,
+            max_fee_reserve: None,
+        };
+
+        assert_eq!(
+            fee_reserve
+                .fee_reserve(Amount::from(100), &CurrencyUnit::Sat)
+                .unwrap(),
+            Amount::from(2)
+        );
+        assert_eq!(
+            fee_reserve
+                .fee_reserve(Amount::from(1000), &CurrencyUnit::Sat)
+                .unwrap(),
+            Amount::from(10)
+        );
+        assert_eq!(
+            fee_reserve
+                .fee_reserve(Amount::from(100_500), &CurrencyUnit::Msat)
+                .unwrap(),
+            Amount::from(2000)
+        );
+        assert_eq!(
+            fee_reserve
+                .fee_reserve(Amount::from(1_000_001), &CurrencyUnit::Msat)
+                .unwrap(),
+            Amount::from(10_001)
+        );
+
+        fee_reserve.max_fee_reserve = Some(Amount::from(5));
+        assert_eq!(
+            fee_reserve
+                .fee_reserve(Amount::from(1000), &CurrencyUnit::Sat)
+                .unwrap(),
+            Amount::from(5)
+        );
+        assert_eq!(
+            fee_reserve
+                .fee_reserve(Amount::from(1_000_001), &CurrencyUnit::Msat)
+                .unwrap(),
+            Amount::from(5000)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_method_fee_reserve() {
+        let mint = create_mint().await;
+        let invoice = Bolt11Invoice::from_str("lnbc25m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5vdhkven9v5sxyetpdeessp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygs9q5sqqqqqqqqqqqqqqqpqsq67gye39hfg3zd8rgc80k32tvy9xk2xunwm5lzexnvpx6fd77en8qaq424dxgt56cag2dpt359k3ssyhetktkpqh24jqnjyw6uqd08sgptq44qu").unwrap();
+
+        let mut config = mint.config().await;
+        config.fee_reserves.insert(
+            LnKey::new(CurrencyUnit::Msat, PaymentMethod::Bolt11),
+            FeeReserve {
+                min_fee_reserve: Amount::from(1),
+                percent_fee_reserve: 2.0,
+                max_fee_reserve: Some(Amount::from(30_000)),
+            },
+        );
+        mint.reload_config(config).await;
+
+        assert_eq!(
+            mint.melt_fee_reserve(&invoice, Amount::from(2_500_000), &CurrencyUnit::Sat)
+                .await
+                .unwrap(),
+            Amount::from(25_000)
+        );
+        assert_eq!(
+            mint.melt_fee_reserve(&invoice, Amount::from(2_500_000_000), &CurrencyUnit::Msat)
+                .await
+                .unwrap(),
+            Amount::from(30_000_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_swap_amount_overflow() {
+        let mint = create_mint().await;
+        let proofs = mint_proofs(&mint, Amount::from(8)).await;
+
+        let keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+        let premint =
+            PreMintSecrets::random(keyset_id, Amount::from(3), &SplitTarget::None).unwrap();
+        let mut outputs = premint.blinded_messages();
+        // Wrapping sum of the outputs equals the inputs
+        outputs[0].amount = Amount::from(u64::MAX);
+        outputs[1].amount = Amount::from(9);
+
+        assert!(matches!(
+            mint.process_swap_request(SwapRequest::new(proofs.clone(), outputs))
+                .await,
+            Err(Error::Cashu(crate::error::Error::AmountOverflow))
+        ));
+
+        assert_unspent(&mint, &proofs).await;
+    }
+
+    #[tokio::test]
+    async fn test_mint_more_than_quote() {
+        let mint = create_mint().await;
+        let keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+
+        let quote = paid_mint_quote(&mint, Amount::from(8)).await;
+        let premint =
+            PreMintSecrets::random(keyset_id, Amount::from(16), &SplitTarget::None).unwrap();
+
+        assert!(matches!(
+            mint.process_mint_request(MintBolt11Request {
+                quote: quote.id,
+                outputs: premint.blinded_messages(),
+            })
+            .await,
+            Err(Error::Amount)
+        ));
+    }
 }
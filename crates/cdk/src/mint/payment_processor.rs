@@ -0,0 +1,177 @@
+//! Payment processor in a separate process
+//!
+//! [`serve_payment_processor`] serves a [`MintLightning`] backend over a
+//! WebSocket connection and [`RemoteLightning`] is the backend of the mint
+//! connected to it. The node credentials then only live on the host of the
+//! payment processor, not on the one serving the mint, and the backend can be
+//! swapped without redeploying the mint.
+//!
+//! Messages are JSON text. The mint asks for the paid invoices with
+//! `{"method": "wait_any_invoice"}`, the processor answers with a
+//! `{"payment_hash": ".."}` message for every invoice paid from then on and
+//! closes the connection when the stream of its backend ends.
+
+use futures::{future, Sink, SinkExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio_tungstenite::tungstenite::{self, Message};
+use url::Url;
+
+use super::lightning::{MintLightning, PaidInvoiceStream};
+use super::Error;
+
+/// Request of the mint to the payment processor
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+enum ProcessorRequest {
+    WaitAnyInvoice,
+}
+
+/// Invoice paid to the backend of the payment processor
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct PaidInvoice {
+    payment_hash: String,
+}
+
+/// Serve `backend` to the mint connected over `socket`
+///
+/// Runs until the mint closes the connection or the stream of paid invoices
+/// of the backend ends.
+pub async fn serve_payment_processor<S>(
+    backend: &(dyn MintLightning + Send + Sync),
+    socket: S,
+) -> Result<(), Error>
+where
+    S: Stream<Item = Result<Message, tungstenite::Error>>
+        + Sink<Message, Error = tungstenite::Error>
+        + Unpin,
+{
+    let (mut sink, mut stream) = socket.split();
+
+    loop {
+        match stream.next().await.transpose()? {
+            Some(Message::Text(text)) => match serde_json::from_str(&text)? {
+                ProcessorRequest::WaitAnyInvoice => break,
+            },
+            Some(Message::Close(_)) | None => return Ok(()),
+            Some(_) => (),
+        }
+    }
+
+    let mut invoices = backend.wait_any_invoice().await?;
+
+    loop {
+        tokio::select! {
+            payment_hash = invoices.next() => match payment_hash {
+                Some(payment_hash) => {
+                    let paid = serde_json::to_string(&PaidInvoice { payment_hash })?;
+                    sink.send(Message::Text(paid)).await?;
+                }
+                None => {
+                    sink.send(Message::Close(None)).await?;
+                    return Ok(());
+                }
+            },
+            message = stream.next() => match message.transpose()? {
+                Some(Message::Close(_)) | None => return Ok(()),
+                Some(_) => (),
+            },
+        }
+    }
+}
+
+/// [`MintLightning`] backend served by [`serve_payment_processor`]
+#[derive(Debug, Clone)]
+pub struct RemoteLightning {
+    url: Url,
+}
+
+impl RemoteLightning {
+    /// Connect to the payment processor at `url`, `ws://` or `wss://`
+    pub fn new(url: Url) -> Self {
+        Self { url }
+    }
+}
+
+#[async_trait::async_trait]
+impl MintLightning for RemoteLightning {
+    async fn wait_any_invoice(&self) -> Result<PaidInvoiceStream, Error> {
+        let (mut socket, _) = tokio_tungstenite::connect_async(self.url.as_str()).await?;
+        let request = serde_json::to_string(&ProcessorRequest::WaitAnyInvoice)?;
+        socket.send(Message::Text(request)).await?;
+
+        // The stream ends with the connection
+        Ok(socket
+            .take_while(|message| {
+                future::ready(matches!(message, Ok(message) if !message.is_close()))
+            })
+            .filter_map(|message| {
+                future::ready(match message {
+                    Ok(Message::Text(text)) => match serde_json::from_str::<PaidInvoice>(&text) {
+                        Ok(paid) => Some(paid.payment_hash),
+                        Err(err) => {
+                            tracing::warn!("Invalid message of payment processor: {}", err);
+                            None
+                        }
+                    },
+                    _ => None,
+                })
+            })
+            .boxed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::mint::tests::create_mint;
+    use crate::mint_url::MintUrl;
+    use crate::nuts::CurrencyUnit;
+    use crate::util::unix_time;
+    use crate::Amount;
+
+    const REQUEST: &str = "lnbc25m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5vdhkven9v5sxyetpdeessp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygs9q5sqqqqqqqqqqqqqqqpqsq67gye39hfg3zd8rgc80k32tvy9xk2xunwm5lzexnvpx6fd77en8qaq424dxgt56cag2dpt359k3ssyhetktkpqh24jqnjyw6uqd08sgptq44qu";
+
+    struct PaidInvoices(Vec<String>);
+
+    #[async_trait::async_trait]
+    impl MintLightning for PaidInvoices {
+        async fn wait_any_invoice(&self) -> Result<PaidInvoiceStream, Error> {
+            Ok(futures::stream::iter(self.0.clone()).boxed())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remote_lightning() {
+        let mint = create_mint().await;
+        let quote = mint
+            .new_mint_quote(
+                MintUrl::from_str("https://mint.example").unwrap(),
+                REQUEST.to_string(),
+                CurrencyUnit::Sat,
+                Amount::from(2_500_000),
+                unix_time() + 600,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("ws://{}", listener.local_addr().unwrap())).unwrap();
+        let backend = PaidInvoices(vec!["00".repeat(32), quote.payment_hash().unwrap()]);
+        let processor = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            serve_payment_processor(&backend, socket).await
+        });
+
+        mint.wait_for_paid_invoices(Arc::new(RemoteLightning::new(url)))
+            .await
+            .unwrap();
+        processor.await.unwrap().unwrap();
+
+        assert!(mint.check_mint_quote(&quote.id).await.unwrap().paid);
+    }
+}
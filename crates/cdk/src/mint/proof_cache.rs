@@ -0,0 +1,154 @@
+//! Verified proof cache
+//!
+//! Checking the signature of a proof is the most expensive part of a request,
+//! and a wallet commonly sends the same proofs twice, e.g. to check a melt
+//! before executing it. [`VerifiedProofCache`] remembers the most recently
+//! verified proofs, so [`Mint`](super::Mint) only checks their signature once.
+//!
+//! Entries are keyed by the `Y` of the proof and hold its signature `C`,
+//! amount and keyset id: a proof with a known secret that differs in any of
+//! them is verified again.
+//! Spent and pending state, keyset validity and spending conditions are
+//! checked on every request.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use crate::nuts::{Id, Proof, PublicKey};
+use crate::Amount;
+
+/// Bounded cache of proofs with a valid signature
+#[derive(Debug, Clone)]
+pub struct VerifiedProofCache {
+    capacity: usize,
+    inner: Arc<Mutex<Entries>>,
+}
+
+/// Fields of a proof covered by its signature, besides the secret
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct VerifiedProof {
+    c: PublicKey,
+    amount: Amount,
+    keyset_id: Id,
+}
+
+impl From<&Proof> for VerifiedProof {
+    fn from(proof: &Proof) -> Self {
+        Self {
+            c: proof.c,
+            amount: proof.amount,
+            keyset_id: proof.keyset_id,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    /// Verified fields of the proof by `Y`
+    verified: HashMap<PublicKey, VerifiedProof>,
+    /// `Y`s oldest first
+    order: VecDeque<PublicKey>,
+}
+
+impl VerifiedProofCache {
+    /// Create cache holding up to `capacity` proofs
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Arc::new(Mutex::new(Entries::default())),
+        }
+    }
+
+    /// Check if `proof` with `y` was verified with the same signature, amount
+    /// and keyset
+    pub fn contains(&self, y: &PublicKey, proof: &Proof) -> bool {
+        let entries = self.inner.lock().expect("Lock poisoned");
+        entries.verified.get(y) == Some(&VerifiedProof::from(proof))
+    }
+
+    /// Remember that `proof` with `y` is valid
+    ///
+    /// Evicts the oldest proof once the cache is full.
+    pub fn insert(&self, y: PublicKey, proof: &Proof) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.inner.lock().expect("Lock poisoned");
+        if entries.verified.insert(y, proof.into()).is_some() {
+            return;
+        }
+        entries.order.push_back(y);
+
+        while entries.order.len() > self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.verified.remove(&oldest);
+            }
+        }
+    }
+
+    /// Forget all proofs
+    pub fn clear(&self) {
+        let mut entries = self.inner.lock().expect("Lock poisoned");
+        entries.verified.clear();
+        entries.order.clear();
+    }
+
+    /// Number of cached proofs
+    pub fn len(&self) -> usize {
+        self.inner.lock().expect("Lock poisoned").order.len()
+    }
+
+    /// Check if no proof is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::dhke::hash_to_curve;
+    use crate::secret::Secret;
+
+    fn proof(secret: &str) -> Proof {
+        Proof::new(
+            Amount::from(1),
+            Id::from_str("009a1f293253e41e").unwrap(),
+            Secret::new(secret),
+            hash_to_curve(b"c").unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_verified_proof_cache() {
+        let cache = VerifiedProofCache::new(2);
+        let (p1, p2, p3) = (proof("1"), proof("2"), proof("3"));
+        let (y1, y2, y3) = (p1.y().unwrap(), p2.y().unwrap(), p3.y().unwrap());
+
+        cache.insert(y1, &p1);
+        assert!(cache.contains(&y1, &p1));
+
+        // Same secret with another signature, amount or keyset is not verified
+        let mut other = p1.clone();
+        other.c = hash_to_curve(b"other").unwrap();
+        assert!(!cache.contains(&y1, &other));
+        let mut other = p1.clone();
+        other.amount = Amount::from(1 << 20);
+        assert!(!cache.contains(&y1, &other));
+        let mut other = p1.clone();
+        other.keyset_id = Id::from_str("000f01df73ea149a").unwrap();
+        assert!(!cache.contains(&y1, &other));
+
+        cache.insert(y2, &p2);
+        cache.insert(y3, &p3);
+        assert_eq!(cache.len(), 2);
+        assert!(!cache.contains(&y1, &p1));
+        assert!(cache.contains(&y3, &p3));
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,421 @@
+//! Signatory
+//!
+//! Holds the private keys of the mint. The [`Mint`](super::Mint) only keeps
+//! the public keys and forwards blind signing and proof verification, so the
+//! keys can live in a separate process or an HSM. [`PolicySignatory`] wraps
+//! any signatory and refuses signatures that exceed a [`SigningPolicy`].
+
+use std::collections::{HashMap, VecDeque};
+
+use async_trait::async_trait;
+use bitcoin::bip32::{DerivationPath, ExtendedPrivKey};
+use bitcoin::secp256k1::{self, Secp256k1};
+use tokio::sync::{Mutex, RwLock};
+
+use super::{Error, MintKeySetInfo};
+use crate::dhke::{sign_message, verify_message};
+use crate::nuts::{BlindSignature, BlindedMessage, CurrencyUnit, Id, KeySet, MintKeySet, Proof};
+use crate::util::unix_time;
+use crate::Amount;
+
+#[async_trait]
+pub trait Signatory {
+    /// Generate new keyset
+    async fn create_keyset(
+        &self,
+        unit: CurrencyUnit,
+        derivation_path: DerivationPath,
+        max_order: u8,
+    ) -> Result<MintKeySetInfo, Error>;
+
+    /// Public keys of keyset
+    async fn keyset(&self, keyset_info: &MintKeySetInfo) -> Result<KeySet, Error>;
+
+    /// Sign blinded message with the key of its amount
+    async fn blind_sign(
+        &self,
+        keyset_info: &MintKeySetInfo,
+        blinded_message: &BlindedMessage,
+    ) -> Result<BlindSignature, Error>;
+
+    /// Verify the signature of proof
+    async fn verify_proof(&self, keyset_info: &MintKeySetInfo, proof: &Proof) -> Result<(), Error>;
+}
+
+/// Signatory with keys derived in process from a seed
+pub struct MemorySignatory {
+    secp_ctx: Secp256k1<secp256k1::All>,
+    xpriv: ExtendedPrivKey,
+    keysets: RwLock<HashMap<Id, MintKeySet>>,
+}
+
+impl Drop for MemorySignatory {
+    fn drop(&mut self) {
+        self.xpriv.private_key.non_secure_erase();
+    }
+}
+
+impl MemorySignatory {
+    pub fn new(seed: &[u8]) -> Self {
+        Self {
+            secp_ctx: Secp256k1::new(),
+            xpriv: ExtendedPrivKey::new_master(bitcoin::Network::Bitcoin, seed)
+                .expect("RNG busted"),
+            keysets: RwLock::new(HashMap::new()),
+        }
+    }
+
+    async fn ensure_keyset_loaded(&self, keyset_info: &MintKeySetInfo) {
+        let keysets = self.keysets.read().await;
+        if keysets.contains_key(&keyset_info.id) {
+            return;
+        }
+        drop(keysets);
+
+        let keyset =
+            MintKeySet::generate_from_xpriv(&self.secp_ctx, self.xpriv, keyset_info.clone());
+        let mut keysets = self.keysets.write().await;
+        keysets.insert(keyset_info.id, keyset);
+    }
+}
+
+#[async_trait]
+impl Signatory for MemorySignatory {
+    async fn create_keyset(
+        &self,
+        unit: CurrencyUnit,
+        derivation_path: DerivationPath,
+        max_order: u8,
+    ) -> Result<MintKeySetInfo, Error> {
+        let (keyset, keyset_info) =
+            create_new_keyset(&self.secp_ctx, self.xpriv, derivation_path, unit, max_order);
+
+        let mut keysets = self.keysets.write().await;
+        keysets.insert(keyset_info.id, keyset);
+
+        Ok(keyset_info)
+    }
+
+    async fn keyset(&self, keyset_info: &MintKeySetInfo) -> Result<KeySet, Error> {
+        self.ensure_keyset_loaded(keyset_info).await;
+        let keysets = self.keysets.read().await;
+        let keyset = keysets
+            .get(&keyset_info.id)
+            .ok_or(Error::UnknownKeySet)?
+            .clone();
+
+        Ok(keyset.into())
+    }
+
+    async fn blind_sign(
+        &self,
+        keyset_info: &MintKeySetInfo,
+        blinded_message: &BlindedMessage,
+    ) -> Result<BlindSignature, Error> {
+        self.ensure_keyset_loaded(keyset_info).await;
+        let keysets = self.keysets.read().await;
+        let keyset = keysets
+            .get(&blinded_message.keyset_id)
+            .ok_or(Error::UnknownKeySet)?;
+        let Some(key_pair) = keyset.keys.get(&blinded_message.amount) else {
+            // No key for amount
+            return Err(Error::AmountKey);
+        };
+
+        let c = sign_message(&key_pair.secret_key, &blinded_message.blinded_secret)?;
+
+        let blinded_signature = BlindSignature::new(
+            blinded_message.amount,
+            c,
+            keyset_info.id,
+            &blinded_message.blinded_secret,
+            key_pair.secret_key.clone(),
+        )?;
+
+        Ok(blinded_signature)
+    }
+
+    async fn verify_proof(&self, keyset_info: &MintKeySetInfo, proof: &Proof) -> Result<(), Error> {
+        self.ensure_keyset_loaded(keyset_info).await;
+        let keysets = self.keysets.read().await;
+        let keyset = keysets.get(&proof.keyset_id).ok_or(Error::UnknownKeySet)?;
+        let Some(keypair) = keyset.keys.get(&proof.amount) else {
+            return Err(Error::AmountKey);
+        };
+
+        verify_message(&keypair.secret_key, proof.c, proof.secret.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+/// Limits on blind signing
+#[derive(Debug, Clone, Default)]
+pub struct SigningPolicy {
+    /// Largest amount of a single signature
+    pub max_amount: Option<Amount>,
+    /// Largest total amount signed within `window`
+    pub max_window_amount: Option<Amount>,
+    /// Most signatures within `window`
+    pub max_window_signatures: Option<usize>,
+    /// Length of the rate limit window in seconds
+    pub window: u64,
+}
+
+/// Signatory enforcing a [`SigningPolicy`]
+///
+/// Signatures refused by the policy fail with [`Error::SigningPolicy`] and
+/// do not count towards the limits.
+pub struct PolicySignatory<S> {
+    inner: S,
+    policy: SigningPolicy,
+    /// Time and amount of the signatures within the window
+    signed: Mutex<VecDeque<(u64, Amount)>>,
+}
+
+impl<S> PolicySignatory<S>
+where
+    S: Signatory + Send + Sync,
+{
+    pub fn new(inner: S, policy: SigningPolicy) -> Self {
+        Self {
+            inner,
+            policy,
+            signed: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Check `amount` against the policy and count it as signed
+    ///
+    /// Returns the time the signature is counted at, to [`Self::release`] it
+    /// if signing fails.
+    async fn reserve(&self, amount: Amount) -> Result<u64, Error> {
+        if let Some(max_amount) = self.policy.max_amount {
+            if amount > max_amount {
+                return Err(Error::SigningPolicy(format!(
+                    "amount {} exceeds {}",
+                    amount, max_amount
+                )));
+            }
+        }
+
+        let now = unix_time();
+        let mut signed = self.signed.lock().await;
+        while signed
+            .front()
+            .is_some_and(|(time, _)| time.saturating_add(self.policy.window) <= now)
+        {
+            signed.pop_front();
+        }
+
+        if let Some(max_signatures) = self.policy.max_window_signatures {
+            if signed.len() >= max_signatures {
+                return Err(Error::SigningPolicy(format!(
+                    "more than {} signatures in {}s",
+                    max_signatures, self.policy.window
+                )));
+            }
+        }
+
+        if let Some(max_window_amount) = self.policy.max_window_amount {
+            let window_amount = signed
+                .iter()
+                .try_fold(amount, |total, (_, amount)| total.checked_add(*amount));
+            if window_amount.map_or(true, |window_amount| window_amount > max_window_amount) {
+                return Err(Error::SigningPolicy(format!(
+                    "more than {} signed in {}s",
+                    max_window_amount, self.policy.window
+                )));
+            }
+        }
+
+        signed.push_back((now, amount));
+
+        Ok(now)
+    }
+
+    /// Stop counting a signature of [`Self::reserve`] that was not produced
+    async fn release(&self, time: u64, amount: Amount) {
+        let mut signed = self.signed.lock().await;
+        if let Some(index) = signed.iter().rposition(|entry| *entry == (time, amount)) {
+            signed.remove(index);
+        }
+    }
+}
+
+#[async_trait]
+impl<S> Signatory for PolicySignatory<S>
+where
+    S: Signatory + Send + Sync,
+{
+    async fn create_keyset(
+        &self,
+        unit: CurrencyUnit,
+        derivation_path: DerivationPath,
+        max_order: u8,
+    ) -> Result<MintKeySetInfo, Error> {
+        self.inner
+            .create_keyset(unit, derivation_path, max_order)
+            .await
+    }
+
+    async fn keyset(&self, keyset_info: &MintKeySetInfo) -> Result<KeySet, Error> {
+        self.inner.keyset(keyset_info).await
+    }
+
+    async fn blind_sign(
+        &self,
+        keyset_info: &MintKeySetInfo,
+        blinded_message: &BlindedMessage,
+    ) -> Result<BlindSignature, Error> {
+        let time = self.reserve(blinded_message.amount).await?;
+        let signature = self.inner.blind_sign(keyset_info, blinded_message).await;
+        if signature.is_err() {
+            self.release(time, blinded_message.amount).await;
+        }
+
+        signature
+    }
+
+    async fn verify_proof(&self, keyset_info: &MintKeySetInfo, proof: &Proof) -> Result<(), Error> {
+        self.inner.verify_proof(keyset_info, proof).await
+    }
+}
+
+fn create_new_keyset<C: secp256k1::Signing>(
+    secp: &secp256k1::Secp256k1<C>,
+    xpriv: ExtendedPrivKey,
+    derivation_path: DerivationPath,
+    unit: CurrencyUnit,
+    max_order: u8,
+) -> (MintKeySet, MintKeySetInfo) {
+    let keyset = MintKeySet::generate(
+        secp,
+        xpriv
+            .derive_priv(secp, &derivation_path)
+            .expect("RNG busted"),
+        unit,
+        max_order,
+    );
+    let keyset_info = MintKeySetInfo {
+        id: keyset.id,
+        unit: keyset.unit.clone(),
+        active: true,
+        valid_from: unix_time(),
+        valid_to: None,
+        derivation_path,
+        derivation_path_index: None,
+        max_order,
+    };
+    (keyset, keyset_info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nuts::PreMintSecrets;
+
+    async fn blinded_messages(
+        signatory: &impl Signatory,
+        amount: u64,
+    ) -> (MintKeySetInfo, Vec<BlindedMessage>) {
+        let keyset_info = signatory
+            .create_keyset(CurrencyUnit::Sat, DerivationPath::default(), 8)
+            .await
+            .unwrap();
+        let pre_mint =
+            PreMintSecrets::random(keyset_info.id, Amount::from(amount), &Default::default())
+                .unwrap();
+
+        (keyset_info, pre_mint.blinded_messages())
+    }
+
+    #[tokio::test]
+    async fn test_signing_policy() {
+        let signatory = PolicySignatory::new(
+            MemorySignatory::new(&[0; 32]),
+            SigningPolicy {
+                max_amount: Some(Amount::from(64)),
+                max_window_amount: Some(Amount::from(100)),
+                max_window_signatures: Some(3),
+                window: 3600,
+            },
+        );
+
+        // 128
+        let (keyset_info, messages) = blinded_messages(&signatory, 128).await;
+        assert!(matches!(
+            signatory.blind_sign(&keyset_info, &messages[0]).await,
+            Err(Error::SigningPolicy(_))
+        ));
+
+        // 64 + 32 + 4
+        let (keyset_info, messages) = blinded_messages(&signatory, 100).await;
+        for message in &messages {
+            signatory.blind_sign(&keyset_info, message).await.unwrap();
+        }
+
+        let (keyset_info, messages) = blinded_messages(&signatory, 1).await;
+        assert!(matches!(
+            signatory.blind_sign(&keyset_info, &messages[0]).await,
+            Err(Error::SigningPolicy(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_failed_signature_not_counted() {
+        let signatory = PolicySignatory::new(
+            MemorySignatory::new(&[0; 32]),
+            SigningPolicy {
+                max_amount: None,
+                max_window_amount: Some(Amount::from(64)),
+                max_window_signatures: Some(1),
+                window: 3600,
+            },
+        );
+
+        // The inner signatory has no key for the amount
+        let (keyset_info, messages) = blinded_messages(&signatory, 64).await;
+        let mut message = messages[0].clone();
+        message.amount = Amount::from(63);
+        assert!(matches!(
+            signatory.blind_sign(&keyset_info, &message).await,
+            Err(Error::AmountKey)
+        ));
+
+        signatory
+            .blind_sign(&keyset_info, &messages[0])
+            .await
+            .unwrap();
+        assert!(matches!(
+            signatory.blind_sign(&keyset_info, &messages[0]).await,
+            Err(Error::SigningPolicy(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_window_amount_overflow() {
+        let signatory = PolicySignatory::new(
+            MemorySignatory::new(&[0; 32]),
+            SigningPolicy {
+                max_amount: None,
+                max_window_amount: Some(Amount::from(u64::MAX)),
+                max_window_signatures: None,
+                window: 3600,
+            },
+        );
+
+        let (keyset_info, messages) = blinded_messages(&signatory, 1).await;
+        signatory
+            .blind_sign(&keyset_info, &messages[0])
+            .await
+            .unwrap();
+
+        let mut message = messages[0].clone();
+        message.amount = Amount::from(u64::MAX);
+        assert!(matches!(
+            signatory.blind_sign(&keyset_info, &message).await,
+            Err(Error::SigningPolicy(_))
+        ));
+    }
+}
@@ -0,0 +1,170 @@
+//! Webhook notifications
+//!
+//! Events are posted as JSON to every configured endpoint. The body is signed
+//! with HMAC-SHA256 using the endpoint secret and the hex encoded signature is
+//! sent in the [`SIGNATURE_HEADER`] header.
+
+use bitcoin::hashes::hmac::{Hmac, HmacEngine};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use url::Url;
+
+use super::events::EventBus;
+pub use super::events::MintEvent;
+use crate::util::unix_time;
+
+/// Header containing the hex encoded HMAC-SHA256 of the body
+pub const SIGNATURE_HEADER: &str = "X-Cashu-Signature";
+
+/// Body posted to webhook endpoints
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    /// Unix timestamp of the event
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub event: MintEvent,
+}
+
+/// Webhook endpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Webhook {
+    pub url: Url,
+    /// Secret used to sign the body
+    pub secret: String,
+}
+
+impl Webhook {
+    /// Hex encoded HMAC-SHA256 of `body`
+    pub fn sign(&self, body: &[u8]) -> String {
+        let mut engine = HmacEngine::<sha256::Hash>::new(self.secret.as_bytes());
+        engine.input(body);
+
+        Hmac::<sha256::Hash>::from_engine(engine).to_string()
+    }
+}
+
+/// Configured webhook endpoints
+#[derive(Debug, Clone, Default)]
+pub struct Webhooks {
+    client: reqwest::Client,
+    endpoints: Vec<Webhook>,
+}
+
+impl Webhooks {
+    pub fn new(endpoints: Vec<Webhook>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoints,
+        }
+    }
+
+    pub fn add(&mut self, webhook: Webhook) {
+        self.endpoints.push(webhook);
+    }
+
+    pub fn endpoints(&self) -> &[Webhook] {
+        &self.endpoints
+    }
+
+    /// Post every event published on `events` to all endpoints
+    ///
+    /// Runs in the background until the bus is dropped.
+    pub fn listen(self, events: &EventBus) -> tokio::task::JoinHandle<()> {
+        let mut receiver = events.subscribe();
+
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => self.notify(event),
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Webhooks skipped {} mint events", skipped)
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
+    /// Post event to all endpoints
+    ///
+    /// Requests are sent in the background, failures are logged.
+    pub fn notify(&self, event: MintEvent) {
+        if self.endpoints.is_empty() {
+            return;
+        }
+
+        let payload = WebhookPayload {
+            timestamp: unix_time(),
+            event,
+        };
+
+        let body = match serde_json::to_vec(&payload) {
+            Ok(body) => body,
+            Err(err) => {
+                tracing::error!("Could not serialize webhook payload: {}", err);
+                return;
+            }
+        };
+
+        for webhook in self.endpoints.iter().cloned() {
+            let client = self.client.clone();
+            let body = body.clone();
+
+            tokio::spawn(async move {
+                let signature = webhook.sign(&body);
+
+                let res = client
+                    .post(webhook.url.clone())
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .header(SIGNATURE_HEADER, signature)
+                    .body(body)
+                    .send()
+                    .await
+                    .and_then(|res| res.error_for_status());
+
+                if let Err(err) = res {
+                    tracing::warn!("Webhook to {} failed: {}", webhook.url, err);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_serialization() {
+        let payload = WebhookPayload {
+            timestamp: 1,
+            event: MintEvent::MeltFailed {
+                quote: "quote".to_string(),
+                reason: "Unknown quote".to_string(),
+            },
+        };
+
+        let json = serde_json::to_string(&payload).unwrap();
+        assert_eq!(
+            json,
+            r#"{"timestamp":1,"event":"melt_failed","quote":"quote","reason":"Unknown quote"}"#
+        );
+
+        let decoded: WebhookPayload = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, payload);
+    }
+
+    #[test]
+    fn test_sign() {
+        let webhook = Webhook {
+            url: Url::parse("https://example.com/hook").unwrap(),
+            secret: "key".to_string(),
+        };
+
+        assert_eq!(
+            webhook.sign(b"The quick brown fox jumps over the lazy dog"),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+}
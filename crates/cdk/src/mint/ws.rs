@@ -0,0 +1,590 @@
+//! NUT-17 WebSocket subscriptions
+//!
+//! [`Mint::handle_ws`] serves the subscriptions of one WebSocket connection,
+//! upgraded by the HTTP server of the mint, e.g. with
+//! `tokio_tungstenite::accept_async`. Quote and proof state changes are taken
+//! from the [`EventBus`](super::events::EventBus).
+//!
+//! A connection can not hold more of the mint than its [`WsConfig`] allows:
+//! subscriptions and filters are limited, messages to the wallet are queued up
+//! to [`WsConfig::queue_size`] and a wallet that does not read them in time is
+//! disconnected, as is a wallet that does not answer pings.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::{self, Instant};
+use tokio_tungstenite::tungstenite::{self, Message};
+
+use super::events::MintEvent;
+use super::Mint;
+use crate::nuts::nut17::{Params, WsError, WsMethod};
+use crate::nuts::{
+    CheckStateRequest, MintQuoteBolt11Response, NotificationPayload, PublicKey, SubscriptionKind,
+    WsNotification, WsRequest, WsResponse,
+};
+
+/// Limits and timers of a WebSocket connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WsConfig {
+    /// Time between pings to the wallet
+    pub ping_interval: Duration,
+    /// Connection is closed when nothing is received for this long
+    pub idle_timeout: Duration,
+    /// Most subscriptions of a connection
+    pub max_subscriptions: usize,
+    /// Most filters of all subscriptions of a connection
+    pub max_filters: usize,
+    /// Most messages queued for the wallet before it is disconnected
+    pub queue_size: usize,
+}
+
+impl Default for WsConfig {
+    fn default() -> Self {
+        Self {
+            ping_interval: Duration::from_secs(30),
+            idle_timeout: Duration::from_secs(90),
+            max_subscriptions: 100,
+            max_filters: 1000,
+            queue_size: 256,
+        }
+    }
+}
+
+/// Why a connection was closed by the mint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WsClose {
+    /// Wallet closed the connection
+    Closed,
+    /// Nothing received within [`WsConfig::idle_timeout`]
+    Idle,
+    /// Queue of messages to the wallet was full
+    SlowConsumer,
+    /// Reading from or writing to the socket failed
+    Error,
+}
+
+impl Mint {
+    /// Serve NUT-17 subscriptions over `socket` until the connection is closed
+    pub async fn handle_ws<S>(&self, socket: S, config: WsConfig) -> WsClose
+    where
+        S: Stream<Item = Result<Message, tungstenite::Error>>
+            + Sink<Message, Error = tungstenite::Error>
+            + Unpin,
+    {
+        let (mut sink, mut stream) = socket.split();
+        let (sender, mut receiver) = mpsc::channel::<Message>(config.queue_size.max(1));
+
+        let writer = async {
+            while let Some(message) = receiver.recv().await {
+                if let Err(err) = sink.send(message).await {
+                    tracing::debug!("Could not write to WebSocket: {}", err);
+                    return WsClose::Error;
+                }
+            }
+            WsClose::Closed
+        };
+
+        let reader = async {
+            let mut connection = Connection {
+                mint: self,
+                config,
+                sender,
+                subscriptions: HashMap::new(),
+            };
+            let mut events = self.events.subscribe();
+            let mut ping =
+                time::interval_at(Instant::now() + config.ping_interval, config.ping_interval);
+            let mut last_seen = Instant::now();
+
+            loop {
+                tokio::select! {
+                    message = stream.next() => {
+                        last_seen = Instant::now();
+                        let close = match message {
+                            Some(Ok(Message::Text(text))) => connection.handle_request(&text).await,
+                            Some(Ok(Message::Close(_))) | None => Err(WsClose::Closed),
+                            Some(Ok(_)) => Ok(()),
+                            Some(Err(err)) => {
+                                tracing::debug!("Could not read from WebSocket: {}", err);
+                                Err(WsClose::Error)
+                            }
+                        };
+                        if let Err(close) = close {
+                            return close;
+                        }
+                    }
+                    event = events.recv() => match event {
+                        Ok(event) => {
+                            if let Err(close) = connection.handle_event(&event).await {
+                                return close;
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(missed)) => {
+                            tracing::warn!("WebSocket connection missed {} mint events", missed);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return WsClose::Closed,
+                    },
+                    _ = ping.tick() => {
+                        if last_seen.elapsed() >= config.idle_timeout {
+                            return WsClose::Idle;
+                        }
+                        if let Err(close) = connection.send(Message::Ping(Vec::new())) {
+                            return close;
+                        }
+                    }
+                }
+            }
+        };
+
+        let close = tokio::select! {
+            close = writer => close,
+            close = reader => close,
+        };
+
+        tracing::debug!("Closing WebSocket connection: {:?}", close);
+        let _ = sink.send(Message::Close(None)).await;
+
+        close
+    }
+}
+
+/// Subscriptions of one connection
+struct Connection<'a> {
+    mint: &'a Mint,
+    config: WsConfig,
+    sender: mpsc::Sender<Message>,
+    /// Kind and filters by subscription id
+    subscriptions: HashMap<String, (SubscriptionKind, Vec<String>)>,
+}
+
+impl<'a> Connection<'a> {
+    /// Queue `message` for the wallet
+    fn send(&self, message: Message) -> Result<(), WsClose> {
+        self.sender.try_send(message).map_err(|err| match err {
+            mpsc::error::TrySendError::Full(_) => WsClose::SlowConsumer,
+            mpsc::error::TrySendError::Closed(_) => WsClose::Error,
+        })
+    }
+
+    fn send_json<T>(&self, value: &T) -> Result<(), WsClose>
+    where
+        T: serde::Serialize,
+    {
+        match serde_json::to_string(value) {
+            Ok(json) => self.send(Message::Text(json)),
+            Err(err) => {
+                tracing::error!("Could not serialize WebSocket message: {}", err);
+                Ok(())
+            }
+        }
+    }
+
+    async fn handle_request(&mut self, text: &str) -> Result<(), WsClose> {
+        let limits = self.mint.config.read().await.limits;
+        if let Err(err) = limits.check_body_size(text.len()) {
+            return self.send_json(&WsResponse::error(
+                None,
+                WsError::INVALID_REQUEST,
+                err.to_string(),
+            ));
+        }
+
+        let request: WsRequest = match serde_json::from_str(text) {
+            Ok(request) => request,
+            Err(err) => {
+                let code = match serde_json::from_str::<serde_json::Value>(text) {
+                    Ok(_) => WsError::INVALID_REQUEST,
+                    Err(_) => WsError::PARSE_ERROR,
+                };
+                return self.send_json(&WsResponse::error(None, code, err.to_string()));
+            }
+        };
+
+        match request.method {
+            WsMethod::Subscribe(params) => match self.check_limits(&params) {
+                Ok(()) => {
+                    let sub_id = params.sub_id.clone();
+                    self.send_json(&WsResponse::ok(request.id, sub_id.clone()))?;
+
+                    // Current state of every filter right away
+                    for filter in &params.filters {
+                        if let Some(payload) = self.payload(params.kind, filter).await {
+                            self.send_json(&WsNotification::new(sub_id.clone(), payload))?;
+                        }
+                    }
+
+                    self.subscriptions
+                        .insert(sub_id, (params.kind, params.filters));
+                    Ok(())
+                }
+                Err(message) => self.send_json(&WsResponse::error(
+                    Some(request.id),
+                    WsError::INVALID_PARAMS,
+                    message,
+                )),
+            },
+            WsMethod::Unsubscribe(params) => match self.subscriptions.remove(&params.sub_id) {
+                Some(_) => self.send_json(&WsResponse::ok(request.id, params.sub_id)),
+                None => self.send_json(&WsResponse::error(
+                    Some(request.id),
+                    WsError::INVALID_PARAMS,
+                    format!("Unknown subscription `{}`", params.sub_id),
+                )),
+            },
+        }
+    }
+
+    /// Check that `params` fit into the limits of the connection
+    fn check_limits(&self, params: &Params) -> Result<(), String> {
+        let replaced = self.subscriptions.get(&params.sub_id);
+        let subscriptions = self.subscriptions.len() + usize::from(replaced.is_none());
+        if subscriptions > self.config.max_subscriptions {
+            return Err(format!(
+                "More than {} subscriptions",
+                self.config.max_subscriptions
+            ));
+        }
+
+        if params.kind == SubscriptionKind::ProofState {
+            if let Some(filter) = params
+                .filters
+                .iter()
+                .find(|filter| PublicKey::from_hex(filter).is_err())
+            {
+                return Err(format!("Invalid proof Y `{}`", filter));
+            }
+        }
+
+        let filters: usize = self
+            .subscriptions
+            .iter()
+            .filter(|(sub_id, _)| **sub_id != params.sub_id)
+            .map(|(_, (_, filters))| filters.len())
+            .sum();
+        if filters + params.filters.len() > self.config.max_filters {
+            return Err(format!("More than {} filters", self.config.max_filters));
+        }
+
+        Ok(())
+    }
+
+    async fn handle_event(&self, event: &MintEvent) -> Result<(), WsClose> {
+        let (kind, ids) = match event {
+            MintEvent::MintQuotePaid { quote, .. } | MintEvent::MintQuoteIssued { quote, .. } => {
+                (SubscriptionKind::Bolt11MintQuote, vec![quote.clone()])
+            }
+            MintEvent::MeltCompleted { quote, .. } | MintEvent::MeltFailed { quote, .. } => {
+                (SubscriptionKind::Bolt11MeltQuote, vec![quote.clone()])
+            }
+            MintEvent::ProofsSpent { ys }
+            | MintEvent::ProofsPending { ys }
+            | MintEvent::ProofsReleased { ys } => (
+                SubscriptionKind::ProofState,
+                ys.iter().map(|y| y.to_hex()).collect(),
+            ),
+            _ => return Ok(()),
+        };
+
+        for id in &ids {
+            let sub_ids: Vec<&String> = self
+                .subscriptions
+                .iter()
+                .filter(|(_, (k, filters))| *k == kind && filters.contains(id))
+                .map(|(sub_id, _)| sub_id)
+                .collect();
+            if sub_ids.is_empty() {
+                continue;
+            }
+
+            if let Some(payload) = self.payload(kind, id).await {
+                for sub_id in sub_ids {
+                    self.send_json(&WsNotification::new(sub_id.clone(), payload.clone()))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Current state of the object `filter` of `kind`
+    async fn payload(&self, kind: SubscriptionKind, filter: &str) -> Option<NotificationPayload> {
+        let payload = match kind {
+            SubscriptionKind::Bolt11MintQuote => self
+                .mint
+                .localstore
+                .get_mint_quote(filter)
+                .await
+                .map(|quote| {
+                    quote.map(|q| NotificationPayload::MintQuote(MintQuoteBolt11Response::from(q)))
+                })
+                .map_err(|err| err.to_string()),
+            SubscriptionKind::Bolt11MeltQuote => match self.mint.check_melt_quote(filter).await {
+                Ok(response) => Ok(Some(NotificationPayload::MeltQuote(response))),
+                Err(super::Error::UnknownQuote) => Ok(None),
+                Err(err) => Err(err.to_string()),
+            },
+            SubscriptionKind::ProofState => match PublicKey::from_hex(filter) {
+                Ok(y) => self
+                    .mint
+                    .check_state(&CheckStateRequest { ys: vec![y] })
+                    .await
+                    .map(|response| {
+                        response
+                            .states
+                            .into_iter()
+                            .next()
+                            .map(NotificationPayload::ProofState)
+                    })
+                    .map_err(|err| err.to_string()),
+                Err(err) => Err(err.to_string()),
+            },
+        };
+
+        payload.unwrap_or_else(|err| {
+            tracing::warn!("Could not get state of {:?} {}: {}", kind, filter, err);
+            None
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use tokio_tungstenite::tungstenite::protocol::Role;
+    use tokio_tungstenite::WebSocketStream;
+
+    use super::*;
+    use crate::amount::SplitTarget;
+    use crate::mint::tests::{active_keyset_id, create_mint, mint_proofs};
+    use crate::mint_url::MintUrl;
+    use crate::nuts::nut17::WsOutcome;
+    use crate::nuts::{CurrencyUnit, PreMintSecrets, ProofState, State, SwapRequest};
+    use crate::Amount;
+
+    async fn connect(
+        mint: &Mint,
+        config: WsConfig,
+        buffer: usize,
+    ) -> (
+        WebSocketStream<tokio::io::DuplexStream>,
+        tokio::task::JoinHandle<WsClose>,
+    ) {
+        let (client, server) = tokio::io::duplex(buffer);
+        let server = WebSocketStream::from_raw_socket(server, Role::Server, None).await;
+        let client = WebSocketStream::from_raw_socket(client, Role::Client, None).await;
+
+        let mint = mint.clone();
+        let handle = tokio::spawn(async move { mint.handle_ws(server, config).await });
+
+        (client, handle)
+    }
+
+    async fn request(client: &mut WebSocketStream<tokio::io::DuplexStream>, json: &str) {
+        client.send(Message::Text(json.to_string())).await.unwrap();
+    }
+
+    async fn next_text(client: &mut WebSocketStream<tokio::io::DuplexStream>) -> String {
+        loop {
+            match client.next().await.unwrap().unwrap() {
+                Message::Text(text) => return text,
+                _ => continue,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ws_subscribe_mint_quote() {
+        let mint = create_mint().await;
+        let quote = mint
+            .new_mint_quote(
+                MintUrl::from_str("https://mint.example").unwrap(),
+                "lnbc".to_string(),
+                CurrencyUnit::Sat,
+                Amount::from(8),
+                u64::MAX,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let (mut client, _) = connect(&mint, WsConfig::default(), 64 * 1024).await;
+        request(
+            &mut client,
+            &format!(
+                r#"{{"jsonrpc":"2.0","id":0,"method":"subscribe","params":{{"kind":"bolt11_mint_quote","subId":"s1","filters":["{}"]}}}}"#,
+                quote.id
+            ),
+        )
+        .await;
+
+        let response: WsResponse = serde_json::from_str(&next_text(&mut client).await).unwrap();
+        assert_eq!(response, WsResponse::ok(0, "s1".to_string()));
+
+        // Current state first, then every change
+        let notification: WsNotification =
+            serde_json::from_str(&next_text(&mut client).await).unwrap();
+        assert!(matches!(
+            notification.params.payload,
+            NotificationPayload::MintQuote(MintQuoteBolt11Response { paid: false, .. })
+        ));
+
+        let mut paid = quote.clone();
+        paid.paid = true;
+        mint.update_mint_quote(paid).await.unwrap();
+
+        let notification: WsNotification =
+            serde_json::from_str(&next_text(&mut client).await).unwrap();
+        assert_eq!(notification.params.sub_id, "s1");
+        assert!(matches!(
+            notification.params.payload,
+            NotificationPayload::MintQuote(MintQuoteBolt11Response { paid: true, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_ws_subscribe_proof_state() {
+        let mint = create_mint().await;
+        let proofs = mint_proofs(&mint, Amount::from(8)).await;
+        let y = proofs[0].y().unwrap();
+
+        let (mut client, _) = connect(&mint, WsConfig::default(), 64 * 1024).await;
+        request(
+            &mut client,
+            &format!(
+                r#"{{"jsonrpc":"2.0","id":0,"method":"subscribe","params":{{"kind":"proof_state","subId":"s1","filters":["{}"]}}}}"#,
+                y
+            ),
+        )
+        .await;
+        let response: WsResponse = serde_json::from_str(&next_text(&mut client).await).unwrap();
+        assert_eq!(response, WsResponse::ok(0, "s1".to_string()));
+
+        let notification: WsNotification =
+            serde_json::from_str(&next_text(&mut client).await).unwrap();
+        assert!(matches!(
+            notification.params.payload,
+            NotificationPayload::ProofState(ProofState {
+                state: State::Unspent,
+                ..
+            })
+        ));
+
+        let keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+        let premint =
+            PreMintSecrets::random(keyset_id, Amount::from(8), &SplitTarget::None).unwrap();
+        mint.process_swap_request(SwapRequest::new(proofs, premint.blinded_messages()))
+            .await
+            .unwrap();
+
+        let notification: WsNotification =
+            serde_json::from_str(&next_text(&mut client).await).unwrap();
+        match notification.params.payload {
+            NotificationPayload::ProofState(state) => {
+                assert_eq!(state.y, y);
+                assert_eq!(state.state, State::Spent);
+            }
+            payload => panic!("Unexpected payload {:?}", payload),
+        }
+
+        request(
+            &mut client,
+            r#"{"jsonrpc":"2.0","id":1,"method":"subscribe","params":{"kind":"proof_state","subId":"s2","filters":["not a key"]}}"#,
+        )
+        .await;
+        let response: WsResponse = serde_json::from_str(&next_text(&mut client).await).unwrap();
+        assert!(matches!(response.outcome, WsOutcome::Error(_)));
+    }
+
+    #[tokio::test]
+    async fn test_ws_limits() {
+        let mint = create_mint().await;
+        let config = WsConfig {
+            max_subscriptions: 1,
+            ..Default::default()
+        };
+        let (mut client, _) = connect(&mint, config, 64 * 1024).await;
+
+        let subscribe = |id: u64, sub_id: &str| {
+            format!(
+                r#"{{"jsonrpc":"2.0","id":{},"method":"subscribe","params":{{"kind":"bolt11_melt_quote","subId":"{}","filters":["q"]}}}}"#,
+                id, sub_id
+            )
+        };
+
+        request(&mut client, &subscribe(0, "s1")).await;
+        let response: WsResponse = serde_json::from_str(&next_text(&mut client).await).unwrap();
+        assert!(matches!(response.outcome, WsOutcome::Result(_)));
+
+        request(&mut client, &subscribe(1, "s2")).await;
+        let response: WsResponse = serde_json::from_str(&next_text(&mut client).await).unwrap();
+        assert!(matches!(
+            response.outcome,
+            WsOutcome::Error(WsError {
+                code: WsError::INVALID_PARAMS,
+                ..
+            })
+        ));
+
+        request(&mut client, "not json").await;
+        let response: WsResponse = serde_json::from_str(&next_text(&mut client).await).unwrap();
+        assert!(matches!(
+            response.outcome,
+            WsOutcome::Error(WsError {
+                code: WsError::PARSE_ERROR,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_ws_idle_and_slow_consumer() {
+        let mint = create_mint().await;
+
+        // Wallet reads nothing and answers no pings
+        let config = WsConfig {
+            ping_interval: Duration::from_millis(10),
+            idle_timeout: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let (_client, handle) = connect(&mint, config, 64 * 1024).await;
+        assert_eq!(handle.await.unwrap(), WsClose::Idle);
+
+        // Wallet does not read its notifications
+        let quote = mint
+            .new_mint_quote(
+                MintUrl::from_str("https://mint.example").unwrap(),
+                "lnbc".to_string(),
+                CurrencyUnit::Sat,
+                Amount::from(8),
+                u64::MAX,
+                None,
+            )
+            .await
+            .unwrap();
+        let config = WsConfig {
+            queue_size: 1,
+            ..Default::default()
+        };
+        let (mut client, handle) = connect(&mint, config, 1024).await;
+        request(
+            &mut client,
+            &format!(
+                r#"{{"jsonrpc":"2.0","id":0,"method":"subscribe","params":{{"kind":"bolt11_mint_quote","subId":"s1","filters":["{}"]}}}}"#,
+                quote.id
+            ),
+        )
+        .await;
+        for _ in 0..64 {
+            mint.events.publish(MintEvent::MintQuotePaid {
+                quote: quote.id.clone(),
+                amount: quote.amount,
+                unit: CurrencyUnit::Sat,
+            });
+        }
+        assert_eq!(handle.await.unwrap(), WsClose::SlowConsumer);
+    }
+}
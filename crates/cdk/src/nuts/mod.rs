@@ -13,10 +13,12 @@ pub mod nut11;
 pub mod nut12;
 pub mod nut13;
 pub mod nut14;
+pub mod nut17;
+pub mod nut18;
 
 pub use nut00::{
-    BlindSignature, BlindedMessage, CurrencyUnit, MintProofs, PaymentMethod, PreMint,
-    PreMintSecrets, Proof, Proofs, Token, Witness,
+    BlindSignature, BlindedMessage, CurrencyUnit, ForeignToken, MintProofs, PaymentMethod, PreMint,
+    PreMintSecrets, Proof, Proofs, Token, TokenV4, Witness,
 };
 pub use nut01::{Keys, KeysResponse, PublicKey, SecretKey};
 #[cfg(feature = "mint")]
@@ -31,12 +33,16 @@ pub use nut04::{
 };
 pub use nut05::{
     MeltBolt11Request, MeltBolt11Response, MeltMethodSettings, MeltQuoteBolt11Request,
-    MeltQuoteBolt11Response, Settings as NUT05Settings,
+    MeltQuoteBolt11Response, MeltQuoteState, Settings as NUT05Settings,
 };
-pub use nut06::{MintInfo, MintVersion, Nuts};
+pub use nut06::{MintInfo, MintInfoBuilder, MintVersion, Nuts};
 pub use nut07::{CheckStateRequest, CheckStateResponse, ProofState, State};
 pub use nut09::{RestoreRequest, RestoreResponse};
-pub use nut10::{Kind, Secret as Nut10Secret, SecretData};
+pub use nut10::{
+    ConditionRegistry, ConditionVerifier, CustomSecret, Kind, Secret as Nut10Secret, SecretData,
+};
 pub use nut11::{Conditions, P2PKWitness, SigFlag, SpendingConditions};
 pub use nut12::{BlindSignatureDleq, ProofDleq};
 pub use nut14::HTLCWitness;
+pub use nut17::{NotificationPayload, SubscriptionKind, WsNotification, WsRequest, WsResponse};
+pub use nut18::{PaymentRequest, PaymentRequestPayload, Transport, TransportType};
@@ -0,0 +1,288 @@
+//! Tokens of other wallets
+//!
+//! Wallets built on other implementations, mostly older ones, encode tokens
+//! that [`Token`] rejects. [`ForeignToken`] accepts them and normalizes them
+//! into a [`Token`]:
+//!
+//! - V2 tokens, base64 JSON with `proofs` and `mints` and without a prefix
+//! - units in any casing
+//! - `null` or empty strings for the optional memo, unit, witness and DLEQ
+//! - legacy base64 keyset ids, which are resolved to the hex id of the keyset
+//!   with [`legacy_keyset_id`](crate::nuts::nut02::legacy_keyset_id) and the
+//!   keys of the mint
+
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use serde_json::{json, Map, Value};
+
+use super::token::{decode_token_base64, strip_token_uri};
+use super::{Error, Token, TokenV4};
+use crate::mint_url::MintUrl;
+use crate::nuts::Id;
+
+/// Token of another wallet, see the [module docs](self)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignToken {
+    /// V3 JSON of the token, normalized except for legacy keyset ids
+    token: Value,
+    legacy_keyset_ids: HashMap<MintUrl, HashSet<String>>,
+}
+
+impl ForeignToken {
+    /// Legacy keyset ids of the proofs by mint
+    pub fn legacy_keyset_ids(&self) -> &HashMap<MintUrl, HashSet<String>> {
+        &self.legacy_keyset_ids
+    }
+
+    /// Token with the legacy keyset ids replaced by their hex id in
+    /// `keyset_ids`
+    pub fn into_token(mut self, keyset_ids: &HashMap<String, Id>) -> Result<Token, Error> {
+        for proof in proofs_mut(&mut self.token) {
+            if let Some(Value::String(id)) = proof.get_mut("id") {
+                if Id::from_str(id).is_err() {
+                    let keyset_id = keyset_ids
+                        .get(id.as_str())
+                        .ok_or_else(|| Error::UnknownLegacyKeysetId(id.clone()))?;
+                    *id = keyset_id.to_string();
+                }
+            }
+        }
+
+        Ok(serde_json::from_value(self.token)?)
+    }
+
+    fn normalize(mut token: Value) -> Result<Self, Error> {
+        let object = token.as_object_mut().ok_or(Error::UnsupportedToken)?;
+        remove_empty(object, &["memo", "unit"]);
+        if let Some(Value::String(unit)) = object.get_mut("unit") {
+            *unit = unit.to_lowercase();
+        }
+
+        let mut legacy_keyset_ids: HashMap<MintUrl, HashSet<String>> = HashMap::new();
+        let mint_proofs = object
+            .get_mut("token")
+            .and_then(Value::as_array_mut)
+            .ok_or(Error::UnsupportedToken)?;
+        for mint_proofs in mint_proofs {
+            let mint = mint_proofs
+                .get("mint")
+                .and_then(Value::as_str)
+                .ok_or(Error::UnsupportedToken)?;
+            let mint = MintUrl::from_str(mint).map_err(|_| Error::InvalidUrl)?;
+
+            let proofs = mint_proofs
+                .get_mut("proofs")
+                .and_then(Value::as_array_mut)
+                .ok_or(Error::UnsupportedToken)?;
+            for proof in proofs {
+                let proof = proof.as_object_mut().ok_or(Error::UnsupportedToken)?;
+                remove_empty(proof, &["witness", "dleq"]);
+
+                if let Some(Value::String(id)) = proof.get_mut("id") {
+                    match Id::from_str(id) {
+                        Ok(keyset_id) => *id = keyset_id.to_string(),
+                        Err(_) => {
+                            legacy_keyset_ids
+                                .entry(mint.clone())
+                                .or_default()
+                                .insert(id.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            token,
+            legacy_keyset_ids,
+        })
+    }
+}
+
+impl FromStr for ForeignToken {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = strip_token_uri(s);
+
+        let token = match s.strip_prefix("cashuA") {
+            Some(s) => serde_json::from_slice(&decode_token_base64(s)?)?,
+            None if s.starts_with("cashuB") => {
+                serde_json::to_value(Token::from(TokenV4::from_str(s)?))?
+            }
+            None => v2_to_v3(serde_json::from_slice(&decode_token_base64(s)?)?)?,
+        };
+
+        Self::normalize(token)
+    }
+}
+
+/// V3 JSON of a V2 token
+///
+/// Proofs are assigned to the mint listing their keyset id.
+fn v2_to_v3(token: Value) -> Result<Value, Error> {
+    let proofs = token
+        .get("proofs")
+        .and_then(Value::as_array)
+        .ok_or(Error::UnsupportedToken)?;
+    let mints = token
+        .get("mints")
+        .and_then(Value::as_array)
+        .ok_or(Error::UnsupportedToken)?;
+
+    let mut mint_proofs = Vec::new();
+    let mut assigned = 0;
+    for mint in mints {
+        let url = mint.get("url").ok_or(Error::UnsupportedToken)?;
+        let ids = mint
+            .get("ids")
+            .and_then(Value::as_array)
+            .ok_or(Error::UnsupportedToken)?;
+
+        let proofs: Vec<&Value> = proofs
+            .iter()
+            .filter(|proof| proof.get("id").is_some_and(|id| ids.contains(id)))
+            .collect();
+        if !proofs.is_empty() {
+            assigned += proofs.len();
+            mint_proofs.push(json!({ "mint": url, "proofs": proofs }));
+        }
+    }
+
+    if assigned != proofs.len() {
+        return Err(Error::UnsupportedToken);
+    }
+
+    Ok(json!({ "token": mint_proofs }))
+}
+
+/// Remove the `keys` of `object` that are `null` or an empty string
+fn remove_empty(object: &mut Map<String, Value>, keys: &[&str]) {
+    object.retain(|key, value| {
+        !keys.contains(&key.as_str()) || !(value.is_null() || value.as_str() == Some(""))
+    });
+}
+
+fn proofs_mut(token: &mut Value) -> impl Iterator<Item = &mut Value> {
+    token
+        .get_mut("token")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(|mint_proofs| mint_proofs.get_mut("proofs").and_then(Value::as_array_mut))
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nuts::CurrencyUnit;
+    use crate::Amount;
+
+    /// V3 token as encoded by nutshell, with a legacy keyset id, an upper case
+    /// hex id and unit and empty optional fields
+    const NUTSHELL_V3: &str = "cashuAeyJ0b2tlbiI6IFt7Im1pbnQiOiAiaHR0cHM6Ly84MzMzLnNwYWNlOjMzMzgvIiwgInByb29mcyI6IFt7ImlkIjogIkkyeU4raVJZZmt6VCIsICJhbW91bnQiOiAyLCAic2VjcmV0IjogIjQwNzkxNWJjMjEyYmU2MWE3N2UzZTZkMmFlYjRjNzI3OTgwYmRhNTFjZDA2YTZhZmMyOWUyODYxNzY4YTc4MzciLCAiQyI6ICIwMmJjOTA5Nzk5N2Q4MWFmYjJjYzczNDZiNWU0MzQ1YTkzNDZiZDJhNTA2ZWI3OTU4NTk4YTcyZjBjZjg1MTYzZWEiLCAid2l0bmVzcyI6ICIiLCAiZGxlcSI6IG51bGx9LCB7ImlkIjogIjAwOUExRjI5MzI1M0U0MUUiLCAiYW1vdW50IjogOCwgInNlY3JldCI6ICJmZTE1MTA5MzE0ZTYxZDc3NTZiMGY4ZWUwZjIzYTYyNGFjYWEzZjRlMDQyZjYxNDMzYzcyOGM3MDU3YjkzMWJlIiwgIkMiOiAiMDI5ZThlNTA1MGI4OTBhN2Q2YzA5NjhkYjE2YmMxZDVkNWZhMDQwZWExZGUyODRmNmVjNjlkNjEyOTlmNjcxMDU5IiwgIndpdG5lc3MiOiAiIiwgImRsZXEiOiBudWxsfV19XSwgInVuaXQiOiAiU0FUIiwgIm1lbW8iOiAiIn0=";
+
+    /// V2 token of the same proofs, both with legacy keyset ids
+    const V2: &str = "eyJwcm9vZnMiOiBbeyJpZCI6ICJJMnlOK2lSWWZrelQiLCAiYW1vdW50IjogMiwgInNlY3JldCI6ICI0MDc5MTViYzIxMmJlNjFhNzdlM2U2ZDJhZWI0YzcyNzk4MGJkYTUxY2QwNmE2YWZjMjllMjg2MTc2OGE3ODM3IiwgIkMiOiAiMDJiYzkwOTc5OTdkODFhZmIyY2M3MzQ2YjVlNDM0NWE5MzQ2YmQyYTUwNmViNzk1ODU5OGE3MmYwY2Y4NTE2M2VhIn0sIHsiaWQiOiAieWp6UWh4Z2hQZHJyIiwgImFtb3VudCI6IDgsICJzZWNyZXQiOiAiZmUxNTEwOTMxNGU2MWQ3NzU2YjBmOGVlMGYyM2E2MjRhY2FhM2Y0ZTA0MmY2MTQzM2M3MjhjNzA1N2I5MzFiZSIsICJDIjogIjAyOWU4ZTUwNTBiODkwYTdkNmMwOTY4ZGIxNmJjMWQ1ZDVmYTA0MGVhMWRlMjg0ZjZlYzY5ZDYxMjk5ZjY3MTA1OSJ9XSwgIm1pbnRzIjogW3sidXJsIjogImh0dHBzOi8vODMzMy5zcGFjZTozMzM4IiwgImlkcyI6IFsiSTJ5TitpUllma3pUIiwgInlqelFoeGdoUGRyciJdfV19";
+
+    fn keyset_ids() -> HashMap<String, Id> {
+        HashMap::from([
+            (
+                "I2yN+iRYfkzT".to_string(),
+                Id::from_str("000f01df73ea149a").unwrap(),
+            ),
+            (
+                "yjzQhxghPdrr".to_string(),
+                Id::from_str("00759e3f8b06b36f").unwrap(),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_nutshell_token() {
+        assert!(Token::from_str(NUTSHELL_V3).is_err());
+
+        let foreign = ForeignToken::from_str(NUTSHELL_V3).unwrap();
+        let mint_url = MintUrl::from_str("https://8333.space:3338").unwrap();
+        assert_eq!(
+            foreign.legacy_keyset_ids(),
+            &HashMap::from([(
+                mint_url.clone(),
+                HashSet::from(["I2yN+iRYfkzT".to_string()])
+            )])
+        );
+
+        assert!(matches!(
+            foreign.clone().into_token(&HashMap::new()),
+            Err(Error::UnknownLegacyKeysetId(id)) if id == "I2yN+iRYfkzT"
+        ));
+
+        let token = foreign.into_token(&keyset_ids()).unwrap();
+        assert_eq!(token.unit, Some(CurrencyUnit::Sat));
+        assert_eq!(token.memo, None);
+        assert_eq!(
+            token.token_info().unwrap(),
+            (Amount::from(10), mint_url.to_string())
+        );
+
+        let proofs = &token.token[0].proofs;
+        assert_eq!(
+            proofs[0].keyset_id,
+            Id::from_str("000f01df73ea149a").unwrap()
+        );
+        assert_eq!(
+            proofs[1].keyset_id,
+            Id::from_str("009a1f293253e41e").unwrap()
+        );
+        assert!(proofs
+            .iter()
+            .all(|p| p.witness.is_none() && p.dleq.is_none()));
+
+        // Normalized tokens encode and parse as any other token
+        assert_eq!(Token::from_str(&token.to_string()).unwrap(), token);
+        assert_eq!(
+            ForeignToken::from_str(&token.to_string())
+                .unwrap()
+                .into_token(&HashMap::new())
+                .unwrap(),
+            token
+        );
+    }
+
+    #[test]
+    fn test_v2_token() {
+        assert!(Token::from_str(V2).is_err());
+
+        let foreign = ForeignToken::from_str(V2).unwrap();
+        let token = foreign.into_token(&keyset_ids()).unwrap();
+        assert_eq!(token.unit, None);
+        assert_eq!(token.token.len(), 1);
+        assert_eq!(
+            token.token[0].mint,
+            MintUrl::from_str("https://8333.space:3338").unwrap()
+        );
+
+        let nutshell = ForeignToken::from_str(NUTSHELL_V3)
+            .unwrap()
+            .into_token(&keyset_ids())
+            .unwrap();
+        let secrets = |token: &Token| -> Vec<_> {
+            token.token[0]
+                .proofs
+                .iter()
+                .map(|p| p.secret.clone())
+                .collect()
+        };
+        assert_eq!(secrets(&token), secrets(&nutshell));
+
+        // Proofs of keysets no mint lists
+        let orphan = serde_json::json!({
+            "proofs": [{"id": "I2yN+iRYfkzT", "amount": 2, "secret": "s", "C": "c"}],
+            "mints": [{"url": "https://8333.space:3338", "ids": ["yjzQhxghPdrr"]}],
+        });
+        assert!(v2_to_v3(orphan).is_err());
+        assert!(ForeignToken::from_str("cashuAnotjson").is_err());
+    }
+}
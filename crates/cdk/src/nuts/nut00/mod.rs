@@ -3,16 +3,14 @@
 //! <https://github.com/cashubtc/nuts/blob/main/00.md>
 
 use std::cmp::Ordering;
+use std::convert::Infallible;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 use std::string::FromUtf8Error;
 
-use base64::engine::{general_purpose, GeneralPurpose};
-use base64::{alphabet, Engine as _};
 use serde::{Deserialize, Deserializer, Serialize};
 use thiserror::Error;
-use url::Url;
 
 use super::nut10;
 use super::nut11::SpendingConditions;
@@ -24,9 +22,14 @@ use crate::nuts::nut12::BlindSignatureDleq;
 use crate::nuts::nut14::{serde_htlc_witness, HTLCWitness};
 use crate::nuts::{Id, ProofDleq};
 use crate::secret::Secret;
-use crate::url::UncheckedUrl;
 use crate::Amount;
 
+mod compat;
+mod token;
+
+pub use compat::ForeignToken;
+pub use token::{MintProofs, ProofV4, Token, TokenV4, TokenV4Token, METADATA_SENDER};
+
 /// List of [Proof]
 pub type Proofs = Vec<Proof>;
 
@@ -41,6 +44,15 @@ pub enum Error {
     /// Invalid Url
     #[error("Invalid Url")]
     InvalidUrl,
+    /// Token with proofs from more than one mint
+    #[error("Token V4 can only contain proofs from a single mint")]
+    MultipleMints,
+    /// Legacy keyset id that is not resolved to a keyset
+    #[error("Unknown legacy keyset id `{0}`")]
+    UnknownLegacyKeysetId(String),
+    /// Token does not fit in the requested length
+    #[error("Token longer than `{0}` characters")]
+    TokenTooLong(usize),
     /// Serde Json error
     #[error(transparent)]
     SerdeJsonError(#[from] serde_json::Error),
@@ -59,6 +71,9 @@ pub enum Error {
     /// NUT11 error
     #[error(transparent)]
     NUT11(#[from] crate::nuts::nut11::Error),
+    /// Ciborium deserialize error
+    #[error(transparent)]
+    CiboriumError(#[from] ciborium::de::Error<std::io::Error>),
 }
 
 /// Blinded Message (also called `output`)
@@ -231,6 +246,7 @@ pub enum CurrencyUnit {
     Sat,
     Msat,
     Usd,
+    Eur,
     Custom(String),
 }
 
@@ -239,21 +255,33 @@ where
     S: AsRef<str>,
 {
     fn from(currency: S) -> Self {
-        match currency.as_ref() {
+        let currency = currency.as_ref();
+
+        match currency.to_lowercase().as_str() {
             "sat" => Self::Sat,
             "usd" => Self::Usd,
             "msat" => Self::Msat,
-            o => Self::Custom(o.to_string()),
+            "eur" => Self::Eur,
+            _ => Self::Custom(currency.to_string()),
         }
     }
 }
 
+impl FromStr for CurrencyUnit {
+    type Err = Infallible;
+
+    fn from_str(currency: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from(currency))
+    }
+}
+
 impl fmt::Display for CurrencyUnit {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             CurrencyUnit::Sat => write!(f, "sat"),
             CurrencyUnit::Msat => write!(f, "msat"),
             CurrencyUnit::Usd => write!(f, "usd"),
+            CurrencyUnit::Eur => write!(f, "eur"),
             CurrencyUnit::Custom(unit) => write!(f, "{unit}"),
         }
     }
@@ -325,7 +353,7 @@ impl<'de> Deserialize<'de> for PaymentMethod {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PreMint {
     /// Blinded message
     pub blinded_message: BlindedMessage,
@@ -349,7 +377,7 @@ impl PartialOrd for PreMint {
     }
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PreMintSecrets {
     pub secrets: Vec<PreMint>,
 }
@@ -361,7 +389,7 @@ impl PreMintSecrets {
         amount: Amount,
         amount_split_target: &SplitTarget,
     ) -> Result<Self, Error> {
-        let amount_split = amount.split_targeted(amount_split_target);
+        let amount_split = amount.split_targeted(amount_split_target)?;
 
         let mut output = Vec::with_capacity(amount_split.len());
 
@@ -434,7 +462,7 @@ impl PreMintSecrets {
         amount_split_target: &SplitTarget,
         conditions: &SpendingConditions,
     ) -> Result<Self, Error> {
-        let amount_split = amount.split_targeted(amount_split_target);
+        let amount_split = amount.split_targeted(amount_split_target)?;
 
         let mut output = Vec::with_capacity(amount_split.len());
 
@@ -457,6 +485,36 @@ impl PreMintSecrets {
         Ok(PreMintSecrets { secrets: output })
     }
 
+    /// Outputs locked to a custom spending condition
+    ///
+    /// Every output gets `condition` with its own nonce.
+    pub fn with_custom_condition(
+        keyset_id: Id,
+        amount: Amount,
+        amount_split_target: &SplitTarget,
+        condition: &nut10::CustomSecret,
+    ) -> Result<Self, Error> {
+        let amount_split = amount.split_targeted(amount_split_target)?;
+
+        let mut output = Vec::with_capacity(amount_split.len());
+
+        for amount in amount_split {
+            let secret: Secret = condition.renew().try_into()?;
+            let (blinded, r) = blind_message(&secret.to_bytes(), None)?;
+
+            let blinded_message = BlindedMessage::new(amount, keyset_id, blinded);
+
+            output.push(PreMint {
+                secret,
+                blinded_message,
+                r,
+                amount,
+            });
+        }
+
+        Ok(PreMintSecrets { secrets: output })
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &PreMint> {
         self.secrets.iter()
     }
@@ -523,93 +581,6 @@ impl PartialOrd for PreMintSecrets {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct Token {
-    pub token: Vec<MintProofs>,
-    /// Memo for token
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub memo: Option<String>,
-    /// Token Unit
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub unit: Option<CurrencyUnit>,
-}
-
-impl Token {
-    pub fn new(
-        mint_url: UncheckedUrl,
-        proofs: Proofs,
-        memo: Option<String>,
-        unit: Option<CurrencyUnit>,
-    ) -> Result<Self, Error> {
-        if proofs.is_empty() {
-            return Err(Error::ProofsRequired);
-        }
-
-        // Check Url is valid
-        let _: Url = (&mint_url).try_into().map_err(|_| Error::InvalidUrl)?;
-
-        Ok(Self {
-            token: vec![MintProofs::new(mint_url, proofs)],
-            memo,
-            unit,
-        })
-    }
-
-    pub fn token_info(&self) -> (Amount, String) {
-        let mut amount = Amount::ZERO;
-
-        for proofs in &self.token {
-            for proof in &proofs.proofs {
-                amount += proof.amount;
-            }
-        }
-
-        (amount, self.token[0].mint.to_string())
-    }
-}
-
-impl FromStr for Token {
-    type Err = Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s = if s.starts_with("cashuA") {
-            s.replace("cashuA", "")
-        } else {
-            return Err(Error::UnsupportedToken);
-        };
-
-        let decode_config = general_purpose::GeneralPurposeConfig::new()
-            .with_decode_padding_mode(base64::engine::DecodePaddingMode::Indifferent);
-        let decoded = GeneralPurpose::new(&alphabet::STANDARD, decode_config).decode(s)?;
-        let decoded_str = String::from_utf8(decoded)?;
-        let token: Token = serde_json::from_str(&decoded_str)?;
-        Ok(token)
-    }
-}
-
-impl fmt::Display for Token {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let json_string = serde_json::to_string(self).map_err(|_| fmt::Error)?;
-        let encoded = general_purpose::STANDARD.encode(json_string);
-        write!(f, "cashuA{}", encoded)
-    }
-}
-
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct MintProofs {
-    pub mint: UncheckedUrl,
-    pub proofs: Proofs,
-}
-
-impl MintProofs {
-    pub fn new(mint_url: UncheckedUrl, proofs: Proofs) -> Self {
-        Self {
-            mint: mint_url,
-            proofs,
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -630,25 +601,20 @@ mod tests {
     }
 
     #[test]
-    fn test_token_str_round_trip() {
-        let token_str = "cashuAeyJ0b2tlbiI6W3sibWludCI6Imh0dHBzOi8vODMzMy5zcGFjZTozMzM4IiwicHJvb2ZzIjpbeyJhbW91bnQiOjIsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6IjQwNzkxNWJjMjEyYmU2MWE3N2UzZTZkMmFlYjRjNzI3OTgwYmRhNTFjZDA2YTZhZmMyOWUyODYxNzY4YTc4MzciLCJDIjoiMDJiYzkwOTc5OTdkODFhZmIyY2M3MzQ2YjVlNDM0NWE5MzQ2YmQyYTUwNmViNzk1ODU5OGE3MmYwY2Y4NTE2M2VhIn0seyJhbW91bnQiOjgsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6ImZlMTUxMDkzMTRlNjFkNzc1NmIwZjhlZTBmMjNhNjI0YWNhYTNmNGUwNDJmNjE0MzNjNzI4YzcwNTdiOTMxYmUiLCJDIjoiMDI5ZThlNTA1MGI4OTBhN2Q2YzA5NjhkYjE2YmMxZDVkNWZhMDQwZWExZGUyODRmNmVjNjlkNjEyOTlmNjcxMDU5In1dfV0sInVuaXQiOiJzYXQiLCJtZW1vIjoiVGhhbmsgeW91LiJ9";
-
-        let token = Token::from_str(token_str).unwrap();
-        assert_eq!(
-            token.token[0].mint,
-            UncheckedUrl::from_str("https://8333.space:3338").unwrap()
-        );
-        assert_eq!(
-            token.token[0].proofs[0].clone().keyset_id,
-            Id::from_str("009a1f293253e41e").unwrap()
-        );
-        assert_eq!(token.unit.clone().unwrap(), CurrencyUnit::Sat);
-
-        let encoded = &token.to_string();
-
-        let token_data = Token::from_str(encoded).unwrap();
+    fn test_currency_unit_serde() {
+        for (unit, json) in [
+            (CurrencyUnit::Sat, "\"sat\""),
+            (CurrencyUnit::Msat, "\"msat\""),
+            (CurrencyUnit::Usd, "\"usd\""),
+            (CurrencyUnit::Eur, "\"eur\""),
+            (CurrencyUnit::Custom("btc".to_string()), "\"btc\""),
+        ] {
+            assert_eq!(serde_json::to_string(&unit).unwrap(), json);
+            assert_eq!(serde_json::from_str::<CurrencyUnit>(json).unwrap(), unit);
+        }
 
-        assert_eq!(token_data, token);
+        assert_eq!(CurrencyUnit::from_str("SAT").unwrap(), CurrencyUnit::Sat);
+        assert_eq!(CurrencyUnit::from("Eur"), CurrencyUnit::Eur);
     }
 
     #[test]
@@ -666,25 +632,4 @@ mod tests {
             .unwrap();
         assert_eq!(b.len(), 1);
     }
-
-    #[test]
-    fn incorrect_tokens() {
-        let incorrect_prefix = "casshuAeyJ0b2tlbiI6W3sibWludCI6Imh0dHBzOi8vODMzMy5zcGFjZTozMzM4IiwicHJvb2ZzIjpbeyJhbW91bnQiOjIsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6IjQwNzkxNWJjMjEyYmU2MWE3N2UzZTZkMmFlYjRjNzI3OTgwYmRhNTFjZDA2YTZhZmMyOWUyODYxNzY4YTc4MzciLCJDIjoiMDJiYzkwOTc5OTdkODFhZmIyY2M3MzQ2YjVlNDM0NWE5MzQ2YmQyYTUwNmViNzk1ODU5OGE3MmYwY2Y4NTE2M2VhIn0seyJhbW91bnQiOjgsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6ImZlMTUxMDkzMTRlNjFkNzc1NmIwZjhlZTBmMjNhNjI0YWNhYTNmNGUwNDJmNjE0MzNjNzI4YzcwNTdiOTMxYmUiLCJDIjoiMDI5ZThlNTA1MGI4OTBhN2Q2YzA5NjhkYjE2YmMxZDVkNWZhMDQwZWExZGUyODRmNmVjNjlkNjEyOTlmNjcxMDU5In1dfV0sInVuaXQiOiJzYXQiLCJtZW1vIjoiVGhhbmsgeW91LiJ9";
-
-        let incorrect_prefix_token = Token::from_str(incorrect_prefix);
-
-        assert!(incorrect_prefix_token.is_err());
-
-        let no_prefix = "eyJ0b2tlbiI6W3sibWludCI6Imh0dHBzOi8vODMzMy5zcGFjZTozMzM4IiwicHJvb2ZzIjpbeyJhbW91bnQiOjIsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6IjQwNzkxNWJjMjEyYmU2MWE3N2UzZTZkMmFlYjRjNzI3OTgwYmRhNTFjZDA2YTZhZmMyOWUyODYxNzY4YTc4MzciLCJDIjoiMDJiYzkwOTc5OTdkODFhZmIyY2M3MzQ2YjVlNDM0NWE5MzQ2YmQyYTUwNmViNzk1ODU5OGE3MmYwY2Y4NTE2M2VhIn0seyJhbW91bnQiOjgsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6ImZlMTUxMDkzMTRlNjFkNzc1NmIwZjhlZTBmMjNhNjI0YWNhYTNmNGUwNDJmNjE0MzNjNzI4YzcwNTdiOTMxYmUiLCJDIjoiMDI5ZThlNTA1MGI4OTBhN2Q2YzA5NjhkYjE2YmMxZDVkNWZhMDQwZWExZGUyODRmNmVjNjlkNjEyOTlmNjcxMDU5In1dfV0sInVuaXQiOiJzYXQiLCJtZW1vIjoiVGhhbmsgeW91LiJ9";
-
-        let no_prefix_token = Token::from_str(no_prefix);
-
-        assert!(no_prefix_token.is_err());
-
-        let correct_token = "cashuAeyJ0b2tlbiI6W3sibWludCI6Imh0dHBzOi8vODMzMy5zcGFjZTozMzM4IiwicHJvb2ZzIjpbeyJhbW91bnQiOjIsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6IjQwNzkxNWJjMjEyYmU2MWE3N2UzZTZkMmFlYjRjNzI3OTgwYmRhNTFjZDA2YTZhZmMyOWUyODYxNzY4YTc4MzciLCJDIjoiMDJiYzkwOTc5OTdkODFhZmIyY2M3MzQ2YjVlNDM0NWE5MzQ2YmQyYTUwNmViNzk1ODU5OGE3MmYwY2Y4NTE2M2VhIn0seyJhbW91bnQiOjgsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6ImZlMTUxMDkzMTRlNjFkNzc1NmIwZjhlZTBmMjNhNjI0YWNhYTNmNGUwNDJmNjE0MzNjNzI4YzcwNTdiOTMxYmUiLCJDIjoiMDI5ZThlNTA1MGI4OTBhN2Q2YzA5NjhkYjE2YmMxZDVkNWZhMDQwZWExZGUyODRmNmVjNjlkNjEyOTlmNjcxMDU5In1dfV0sInVuaXQiOiJzYXQiLCJtZW1vIjoiVGhhbmsgeW91LiJ9";
-
-        let correct_token = Token::from_str(correct_token);
-
-        assert!(correct_token.is_ok());
-    }
 }
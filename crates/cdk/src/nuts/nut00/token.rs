@@ -0,0 +1,791 @@
+//! Cashu Token
+//!
+//! <https://github.com/cashubtc/nuts/blob/main/00.md>
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use base64::engine::{general_purpose, GeneralPurpose};
+use base64::{alphabet, Engine as _};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use url::Url;
+
+use super::{CurrencyUnit, Error, Proof, Proofs, Witness};
+use crate::mint_url::MintUrl;
+use crate::nuts::{Id, ProofDleq, PublicKey, SecretKey};
+use crate::secret::Secret;
+use crate::Amount;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Token {
+    pub token: Vec<MintProofs>,
+    /// Memo for token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    /// Token Unit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<CurrencyUnit>,
+    /// Application specific fields, see [`Token::with_metadata`]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Metadata key of the sender of a token
+pub const METADATA_SENDER: &str = "sender";
+
+impl Token {
+    pub fn new(
+        mint_url: MintUrl,
+        proofs: Proofs,
+        memo: Option<String>,
+        unit: Option<CurrencyUnit>,
+    ) -> Result<Self, Error> {
+        if proofs.is_empty() {
+            return Err(Error::ProofsRequired);
+        }
+
+        // Check Url is valid
+        let _: Url = (&mint_url).try_into().map_err(|_| Error::InvalidUrl)?;
+
+        Ok(Self {
+            token: vec![MintProofs::new(mint_url, proofs)],
+            memo,
+            unit,
+            metadata: BTreeMap::new(),
+        })
+    }
+
+    /// Attach application specific `metadata`
+    ///
+    /// Metadata is an extension of this crate, not part of NUT-00. Other
+    /// wallets ignore it, so it must not be required to redeem the token.
+    pub fn with_metadata(mut self, metadata: BTreeMap<String, String>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Sender named in the metadata
+    pub fn sender(&self) -> Option<&str> {
+        self.metadata.get(METADATA_SENDER).map(|s| s.as_str())
+    }
+
+    /// Amount and first mint of the token
+    ///
+    /// Fails if the amounts of the proofs overflow.
+    pub fn token_info(&self) -> Result<(Amount, String), Error> {
+        let amount = Amount::try_sum(
+            self.token
+                .iter()
+                .flat_map(|t| t.proofs.iter().map(|p| p.amount)),
+        )?;
+
+        Ok((amount, self.token[0].mint.to_string()))
+    }
+
+    /// Fingerprint of the proofs in the token
+    ///
+    /// Hex SHA256 of the sorted `Y`s of all proofs, so the same proofs have the
+    /// same fingerprint regardless of memo, metadata, unit or encoding.
+    pub fn fingerprint(&self) -> Result<String, Error> {
+        let mut ys = self
+            .token
+            .iter()
+            .flat_map(|t| t.proofs.iter())
+            .map(|p| p.y().map(|y| y.to_bytes()))
+            .collect::<Result<Vec<_>, _>>()?;
+        ys.sort();
+
+        let mut engine = sha256::Hash::engine();
+        for y in ys {
+            engine.input(&y);
+        }
+
+        Ok(sha256::Hash::from_engine(engine).to_string())
+    }
+
+    /// Encode token in the most compatible version that fits in `max_len`
+    ///
+    /// The V3 (`cashuA`) encoding is used when it fits, otherwise the token is
+    /// converted to the more compact V4 (`cashuB`) encoding.
+    pub fn preferred_encoding(&self, max_len: usize) -> Result<String, Error> {
+        let token_v3 = self.to_string();
+        if token_v3.len() <= max_len {
+            return Ok(token_v3);
+        }
+
+        let token_v4 = TokenV4::try_from(self.clone())?.to_string();
+        if token_v4.len() <= max_len {
+            return Ok(token_v4);
+        }
+
+        Err(Error::TokenTooLong(max_len))
+    }
+}
+
+impl FromStr for Token {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = strip_token_uri(s);
+
+        if s.starts_with("cashuB") {
+            return Ok(TokenV4::from_str(s)?.into());
+        }
+
+        let s = s.strip_prefix("cashuA").ok_or(Error::UnsupportedToken)?;
+
+        let decoded = decode_token_base64(s)?;
+        let decoded_str = String::from_utf8(decoded)?;
+        let token: Token = serde_json::from_str(&decoded_str)?;
+        Ok(token)
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let json_string = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        let encoded = general_purpose::URL_SAFE.encode(json_string);
+        write!(f, "cashuA{}", encoded)
+    }
+}
+
+/// Strip surrounding whitespace and a `cashu:` URI scheme from a token string
+pub(super) fn strip_token_uri(s: &str) -> &str {
+    let s = s.trim();
+
+    match s.get(..6) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("cashu:") => s[6..].trim_start_matches('/'),
+        _ => s,
+    }
+}
+
+/// Decode token payload that may use either the url-safe or standard base64
+/// alphabet, with or without padding
+pub(super) fn decode_token_base64(s: &str) -> Result<Vec<u8>, Error> {
+    let alphabet = match s.contains(['-', '_']) {
+        true => &alphabet::URL_SAFE,
+        false => &alphabet::STANDARD,
+    };
+
+    let decode_config = general_purpose::GeneralPurposeConfig::new()
+        .with_decode_padding_mode(base64::engine::DecodePaddingMode::Indifferent);
+    Ok(GeneralPurpose::new(alphabet, decode_config).decode(s)?)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MintProofs {
+    pub mint: MintUrl,
+    pub proofs: Proofs,
+}
+
+impl MintProofs {
+    pub fn new(mint_url: MintUrl, proofs: Proofs) -> Self {
+        Self {
+            mint: mint_url,
+            proofs,
+        }
+    }
+}
+
+/// Token V4
+///
+/// Compact `cashuB` token. Proofs are grouped by keyset and the token is
+/// encoded as CBOR with keyset ids and signatures as raw bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenV4 {
+    /// Mint Url
+    #[serde(rename = "m")]
+    pub mint_url: MintUrl,
+    /// Token Unit
+    #[serde(rename = "u")]
+    pub unit: CurrencyUnit,
+    /// Memo for token
+    #[serde(rename = "d", skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    /// Application specific fields, see [`Token::with_metadata`]
+    #[serde(rename = "x", default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+    /// Proofs grouped by keyset id
+    #[serde(rename = "t")]
+    pub token: Vec<TokenV4Token>,
+}
+
+impl TokenV4 {
+    pub fn new(
+        mint_url: MintUrl,
+        proofs: Proofs,
+        memo: Option<String>,
+        unit: CurrencyUnit,
+    ) -> Result<Self, Error> {
+        if proofs.is_empty() {
+            return Err(Error::ProofsRequired);
+        }
+
+        // Check Url is valid
+        let _: Url = (&mint_url).try_into().map_err(|_| Error::InvalidUrl)?;
+
+        let mut token: Vec<TokenV4Token> = Vec::new();
+
+        for proof in proofs {
+            let keyset_id = proof.keyset_id;
+            match token.iter_mut().find(|t| t.keyset_id == keyset_id) {
+                Some(keyset_token) => keyset_token.proofs.push(proof.into()),
+                None => token.push(TokenV4Token {
+                    keyset_id,
+                    proofs: vec![proof.into()],
+                }),
+            }
+        }
+
+        Ok(Self {
+            mint_url,
+            unit,
+            memo,
+            metadata: BTreeMap::new(),
+            token,
+        })
+    }
+
+    /// Proofs of token
+    pub fn proofs(&self) -> Proofs {
+        self.token
+            .iter()
+            .flat_map(|t| t.proofs.iter().map(|p| p.into_proof(&t.keyset_id)))
+            .collect()
+    }
+
+    /// Amount and mint of the token
+    ///
+    /// Fails if the amounts of the proofs overflow.
+    pub fn token_info(&self) -> Result<(Amount, String), Error> {
+        let amount = Amount::try_sum(
+            self.token
+                .iter()
+                .flat_map(|t| t.proofs.iter().map(|p| p.amount)),
+        )?;
+
+        Ok((amount, self.mint_url.to_string()))
+    }
+}
+
+impl FromStr for TokenV4 {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = strip_token_uri(s)
+            .strip_prefix("cashuB")
+            .ok_or(Error::UnsupportedToken)?;
+
+        let decoded = decode_token_base64(s)?;
+        let token: TokenV4 = ciborium::from_reader(&decoded[..])?;
+        Ok(token)
+    }
+}
+
+impl fmt::Display for TokenV4 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut data = Vec::new();
+        ciborium::into_writer(self, &mut data).map_err(|_| fmt::Error)?;
+        let encoded = general_purpose::URL_SAFE.encode(data);
+        write!(f, "cashuB{}", encoded)
+    }
+}
+
+impl From<TokenV4> for Token {
+    fn from(token: TokenV4) -> Self {
+        let proofs = token.proofs();
+
+        Self {
+            token: vec![MintProofs::new(token.mint_url, proofs)],
+            memo: token.memo,
+            unit: Some(token.unit),
+            metadata: token.metadata,
+        }
+    }
+}
+
+impl TryFrom<Token> for TokenV4 {
+    type Error = Error;
+
+    fn try_from(token: Token) -> Result<Self, Self::Error> {
+        let mut mint_proofs = token.token.into_iter();
+
+        let (mint_url, proofs) = match (mint_proofs.next(), mint_proofs.next()) {
+            (Some(mint_proofs), None) => (mint_proofs.mint, mint_proofs.proofs),
+            (Some(_), Some(_)) => return Err(Error::MultipleMints),
+            (None, _) => return Err(Error::ProofsRequired),
+        };
+
+        let mut token_v4 = Self::new(mint_url, proofs, token.memo, token.unit.unwrap_or_default())?;
+        token_v4.metadata = token.metadata;
+
+        Ok(token_v4)
+    }
+}
+
+/// Proofs of a [TokenV4] from a single keyset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenV4Token {
+    /// `Keyset id`
+    #[serde(
+        rename = "i",
+        serialize_with = "serialize_v4_keyset_id",
+        deserialize_with = "deserialize_v4_keyset_id"
+    )]
+    pub keyset_id: Id,
+    /// Proofs
+    #[serde(rename = "p")]
+    pub proofs: Vec<ProofV4>,
+}
+
+/// Proof as stored in a [TokenV4]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofV4 {
+    /// Amount
+    #[serde(rename = "a")]
+    pub amount: Amount,
+    /// Secret message
+    #[serde(rename = "s")]
+    pub secret: Secret,
+    /// Unblinded signature
+    #[serde(
+        rename = "c",
+        serialize_with = "serialize_v4_pubkey",
+        deserialize_with = "deserialize_v4_pubkey"
+    )]
+    pub c: PublicKey,
+    /// Witness
+    #[serde(rename = "w", skip_serializing_if = "Option::is_none")]
+    pub witness: Option<Witness>,
+    /// DLEQ Proof
+    #[serde(
+        rename = "d",
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_v4_dleq",
+        deserialize_with = "deserialize_v4_dleq"
+    )]
+    pub dleq: Option<ProofDleq>,
+}
+
+impl ProofV4 {
+    pub fn into_proof(&self, keyset_id: &Id) -> Proof {
+        Proof {
+            amount: self.amount,
+            keyset_id: *keyset_id,
+            secret: self.secret.clone(),
+            c: self.c,
+            witness: self.witness.clone(),
+            dleq: self.dleq.clone(),
+        }
+    }
+}
+
+impl From<Proof> for ProofV4 {
+    fn from(proof: Proof) -> Self {
+        Self {
+            amount: proof.amount,
+            secret: proof.secret,
+            c: proof.c,
+            witness: proof.witness,
+            dleq: proof.dleq,
+        }
+    }
+}
+
+/// [ProofDleq] with the scalars encoded as bytes
+#[derive(Serialize, Deserialize)]
+struct ProofDleqV4 {
+    #[serde(
+        serialize_with = "serialize_v4_secret_key",
+        deserialize_with = "deserialize_v4_secret_key"
+    )]
+    e: SecretKey,
+    #[serde(
+        serialize_with = "serialize_v4_secret_key",
+        deserialize_with = "deserialize_v4_secret_key"
+    )]
+    s: SecretKey,
+    #[serde(
+        serialize_with = "serialize_v4_secret_key",
+        deserialize_with = "deserialize_v4_secret_key"
+    )]
+    r: SecretKey,
+}
+
+fn serialize_v4_dleq<S>(dleq: &Option<ProofDleq>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    dleq.clone()
+        .map(|dleq| ProofDleqV4 {
+            e: dleq.e,
+            s: dleq.s,
+            r: dleq.r,
+        })
+        .serialize(serializer)
+}
+
+fn deserialize_v4_dleq<'de, D>(deserializer: D) -> Result<Option<ProofDleq>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let dleq: Option<ProofDleqV4> = Option::deserialize(deserializer)?;
+    Ok(dleq.map(|dleq| ProofDleq::new(dleq.e, dleq.s, dleq.r)))
+}
+
+fn serialize_v4_secret_key<S>(key: &SecretKey, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_bytes(key.as_secret_bytes())
+}
+
+fn deserialize_v4_secret_key<'de, D>(deserializer: D) -> Result<SecretKey, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let bytes = deserializer.deserialize_bytes(BytesVisitor)?;
+    SecretKey::from_slice(&bytes).map_err(de::Error::custom)
+}
+
+fn serialize_v4_keyset_id<S>(keyset_id: &Id, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_bytes(&keyset_id.to_bytes())
+}
+
+fn deserialize_v4_keyset_id<'de, D>(deserializer: D) -> Result<Id, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let bytes = deserializer.deserialize_bytes(BytesVisitor)?;
+    Id::from_bytes(&bytes).map_err(de::Error::custom)
+}
+
+fn serialize_v4_pubkey<S>(key: &PublicKey, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_bytes(&key.to_bytes())
+}
+
+fn deserialize_v4_pubkey<'de, D>(deserializer: D) -> Result<PublicKey, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let bytes = deserializer.deserialize_bytes(BytesVisitor)?;
+    PublicKey::from_slice(&bytes).map_err(de::Error::custom)
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a byte string")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut bytes = Vec::new();
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nuts::nut11::P2PKWitness;
+
+    const TOKEN_V3: &str = "cashuAeyJ0b2tlbiI6W3sibWludCI6Imh0dHBzOi8vODMzMy5zcGFjZTozMzM4IiwicHJvb2ZzIjpbeyJhbW91bnQiOjIsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6IjQwNzkxNWJjMjEyYmU2MWE3N2UzZTZkMmFlYjRjNzI3OTgwYmRhNTFjZDA2YTZhZmMyOWUyODYxNzY4YTc4MzciLCJDIjoiMDJiYzkwOTc5OTdkODFhZmIyY2M3MzQ2YjVlNDM0NWE5MzQ2YmQyYTUwNmViNzk1ODU5OGE3MmYwY2Y4NTE2M2VhIn0seyJhbW91bnQiOjgsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6ImZlMTUxMDkzMTRlNjFkNzc1NmIwZjhlZTBmMjNhNjI0YWNhYTNmNGUwNDJmNjE0MzNjNzI4YzcwNTdiOTMxYmUiLCJDIjoiMDI5ZThlNTA1MGI4OTBhN2Q2YzA5NjhkYjE2YmMxZDVkNWZhMDQwZWExZGUyODRmNmVjNjlkNjEyOTlmNjcxMDU5In1dfV0sInVuaXQiOiJzYXQiLCJtZW1vIjoiVGhhbmsgeW91LiJ9";
+
+    #[test]
+    fn test_token_fingerprint() {
+        let token = Token::from_str(TOKEN_V3).unwrap();
+
+        let mut other = token.clone();
+        other.memo = None;
+        other.token[0].proofs.reverse();
+        assert_eq!(token.fingerprint().unwrap(), other.fingerprint().unwrap());
+
+        other.token[0].proofs.pop();
+        assert_ne!(token.fingerprint().unwrap(), other.fingerprint().unwrap());
+    }
+
+    #[test]
+    fn test_token_str_round_trip() {
+        let token_str = "cashuAeyJ0b2tlbiI6W3sibWludCI6Imh0dHBzOi8vODMzMy5zcGFjZTozMzM4IiwicHJvb2ZzIjpbeyJhbW91bnQiOjIsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6IjQwNzkxNWJjMjEyYmU2MWE3N2UzZTZkMmFlYjRjNzI3OTgwYmRhNTFjZDA2YTZhZmMyOWUyODYxNzY4YTc4MzciLCJDIjoiMDJiYzkwOTc5OTdkODFhZmIyY2M3MzQ2YjVlNDM0NWE5MzQ2YmQyYTUwNmViNzk1ODU5OGE3MmYwY2Y4NTE2M2VhIn0seyJhbW91bnQiOjgsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6ImZlMTUxMDkzMTRlNjFkNzc1NmIwZjhlZTBmMjNhNjI0YWNhYTNmNGUwNDJmNjE0MzNjNzI4YzcwNTdiOTMxYmUiLCJDIjoiMDI5ZThlNTA1MGI4OTBhN2Q2YzA5NjhkYjE2YmMxZDVkNWZhMDQwZWExZGUyODRmNmVjNjlkNjEyOTlmNjcxMDU5In1dfV0sInVuaXQiOiJzYXQiLCJtZW1vIjoiVGhhbmsgeW91LiJ9";
+
+        let token = Token::from_str(token_str).unwrap();
+        assert_eq!(
+            token.token[0].mint,
+            MintUrl::from_str("https://8333.space:3338").unwrap()
+        );
+        assert_eq!(
+            token.token[0].proofs[0].clone().keyset_id,
+            Id::from_str("009a1f293253e41e").unwrap()
+        );
+        assert_eq!(token.unit.clone().unwrap(), CurrencyUnit::Sat);
+
+        let encoded = &token.to_string();
+
+        let token_data = Token::from_str(encoded).unwrap();
+
+        assert_eq!(token_data, token);
+    }
+
+    #[test]
+    fn incorrect_tokens() {
+        let incorrect_prefix = "casshuAeyJ0b2tlbiI6W3sibWludCI6Imh0dHBzOi8vODMzMy5zcGFjZTozMzM4IiwicHJvb2ZzIjpbeyJhbW91bnQiOjIsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6IjQwNzkxNWJjMjEyYmU2MWE3N2UzZTZkMmFlYjRjNzI3OTgwYmRhNTFjZDA2YTZhZmMyOWUyODYxNzY4YTc4MzciLCJDIjoiMDJiYzkwOTc5OTdkODFhZmIyY2M3MzQ2YjVlNDM0NWE5MzQ2YmQyYTUwNmViNzk1ODU5OGE3MmYwY2Y4NTE2M2VhIn0seyJhbW91bnQiOjgsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6ImZlMTUxMDkzMTRlNjFkNzc1NmIwZjhlZTBmMjNhNjI0YWNhYTNmNGUwNDJmNjE0MzNjNzI4YzcwNTdiOTMxYmUiLCJDIjoiMDI5ZThlNTA1MGI4OTBhN2Q2YzA5NjhkYjE2YmMxZDVkNWZhMDQwZWExZGUyODRmNmVjNjlkNjEyOTlmNjcxMDU5In1dfV0sInVuaXQiOiJzYXQiLCJtZW1vIjoiVGhhbmsgeW91LiJ9";
+
+        let incorrect_prefix_token = Token::from_str(incorrect_prefix);
+
+        assert!(incorrect_prefix_token.is_err());
+
+        let no_prefix = "eyJ0b2tlbiI6W3sibWludCI6Imh0dHBzOi8vODMzMy5zcGFjZTozMzM4IiwicHJvb2ZzIjpbeyJhbW91bnQiOjIsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6IjQwNzkxNWJjMjEyYmU2MWE3N2UzZTZkMmFlYjRjNzI3OTgwYmRhNTFjZDA2YTZhZmMyOWUyODYxNzY4YTc4MzciLCJDIjoiMDJiYzkwOTc5OTdkODFhZmIyY2M3MzQ2YjVlNDM0NWE5MzQ2YmQyYTUwNmViNzk1ODU5OGE3MmYwY2Y4NTE2M2VhIn0seyJhbW91bnQiOjgsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6ImZlMTUxMDkzMTRlNjFkNzc1NmIwZjhlZTBmMjNhNjI0YWNhYTNmNGUwNDJmNjE0MzNjNzI4YzcwNTdiOTMxYmUiLCJDIjoiMDI5ZThlNTA1MGI4OTBhN2Q2YzA5NjhkYjE2YmMxZDVkNWZhMDQwZWExZGUyODRmNmVjNjlkNjEyOTlmNjcxMDU5In1dfV0sInVuaXQiOiJzYXQiLCJtZW1vIjoiVGhhbmsgeW91LiJ9";
+
+        let no_prefix_token = Token::from_str(no_prefix);
+
+        assert!(no_prefix_token.is_err());
+
+        let correct_token = "cashuAeyJ0b2tlbiI6W3sibWludCI6Imh0dHBzOi8vODMzMy5zcGFjZTozMzM4IiwicHJvb2ZzIjpbeyJhbW91bnQiOjIsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6IjQwNzkxNWJjMjEyYmU2MWE3N2UzZTZkMmFlYjRjNzI3OTgwYmRhNTFjZDA2YTZhZmMyOWUyODYxNzY4YTc4MzciLCJDIjoiMDJiYzkwOTc5OTdkODFhZmIyY2M3MzQ2YjVlNDM0NWE5MzQ2YmQyYTUwNmViNzk1ODU5OGE3MmYwY2Y4NTE2M2VhIn0seyJhbW91bnQiOjgsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6ImZlMTUxMDkzMTRlNjFkNzc1NmIwZjhlZTBmMjNhNjI0YWNhYTNmNGUwNDJmNjE0MzNjNzI4YzcwNTdiOTMxYmUiLCJDIjoiMDI5ZThlNTA1MGI4OTBhN2Q2YzA5NjhkYjE2YmMxZDVkNWZhMDQwZWExZGUyODRmNmVjNjlkNjEyOTlmNjcxMDU5In1dfV0sInVuaXQiOiJzYXQiLCJtZW1vIjoiVGhhbmsgeW91LiJ9";
+
+        let correct_token = Token::from_str(correct_token);
+
+        assert!(correct_token.is_ok());
+    }
+
+    #[test]
+    fn test_token_v4_round_trip() {
+        let token_v3 = Token::from_str(TOKEN_V3).unwrap();
+        let mint_proofs = &token_v3.token[0];
+
+        let token = TokenV4::new(
+            mint_proofs.mint.clone(),
+            mint_proofs.proofs.clone(),
+            token_v3.memo.clone(),
+            CurrencyUnit::Sat,
+        )
+        .unwrap();
+
+        assert_eq!(token.token.len(), 1);
+        assert_eq!(
+            token.token[0].keyset_id,
+            Id::from_str("009a1f293253e41e").unwrap()
+        );
+
+        let encoded = token.to_string();
+        assert!(encoded.starts_with("cashuB"));
+        assert!(encoded.len() < TOKEN_V3.len());
+
+        let decoded = TokenV4::from_str(&encoded).unwrap();
+        assert_eq!(decoded, token);
+        assert_eq!(decoded.proofs(), mint_proofs.proofs);
+        assert_eq!(
+            decoded.token_info().unwrap(),
+            token_v3.token_info().unwrap()
+        );
+
+        // V4 tokens are accepted wherever a token is parsed
+        let token_from_v4 = Token::from_str(&encoded).unwrap();
+        assert_eq!(token_from_v4, token_v3);
+    }
+
+    #[test]
+    fn test_token_v4_groups_keysets() {
+        let mut proofs = Token::from_str(TOKEN_V3).unwrap().token[0].proofs.clone();
+        proofs[1].keyset_id = Id::from_str("00ad268c4d1f5826").unwrap();
+
+        let token = TokenV4::new(
+            MintUrl::from_str("https://8333.space:3338").unwrap(),
+            proofs.clone(),
+            None,
+            CurrencyUnit::Sat,
+        )
+        .unwrap();
+
+        assert_eq!(token.token.len(), 2);
+
+        let decoded = TokenV4::from_str(&token.to_string()).unwrap();
+        let mut decoded_proofs = decoded.proofs();
+        decoded_proofs.sort_by_key(|p| p.secret.clone());
+        proofs.sort_by_key(|p| p.secret.clone());
+        assert_eq!(decoded_proofs, proofs);
+    }
+
+    #[test]
+    fn test_token_version_conversion() {
+        let token_v3 = Token::from_str(TOKEN_V3).unwrap();
+
+        let token_v4 = TokenV4::try_from(token_v3.clone()).unwrap();
+        assert_eq!(token_v4.unit, CurrencyUnit::Sat);
+        assert_eq!(token_v4.memo, token_v3.memo);
+        assert_eq!(Token::from(token_v4), token_v3);
+
+        let mut multi_mint = token_v3.clone();
+        multi_mint.token.push(MintProofs::new(
+            MintUrl::from_str("https://other.mint").unwrap(),
+            token_v3.token[0].proofs.clone(),
+        ));
+        assert!(matches!(
+            TokenV4::try_from(multi_mint),
+            Err(Error::MultipleMints)
+        ));
+    }
+
+    #[test]
+    fn test_token_metadata() {
+        let metadata = BTreeMap::from([
+            (METADATA_SENDER.to_string(), "alice".to_string()),
+            ("order".to_string(), "42".to_string()),
+        ]);
+        let token = Token::from_str(TOKEN_V3)
+            .unwrap()
+            .with_metadata(metadata.clone());
+        assert_eq!(token.sender(), Some("alice"));
+
+        let decoded = Token::from_str(&token.to_string()).unwrap();
+        assert_eq!(decoded.metadata, metadata);
+
+        let token_v4 = TokenV4::try_from(token.clone()).unwrap().to_string();
+        assert_eq!(Token::from_str(&token_v4).unwrap(), token);
+
+        // Tokens without metadata encode as before
+        let plain = Token::from_str(TOKEN_V3).unwrap();
+        assert!(!serde_json::to_string(&plain).unwrap().contains("metadata"));
+    }
+
+    #[test]
+    fn test_preferred_encoding() {
+        let token = Token::from_str(TOKEN_V3).unwrap();
+        let token_v4 = TokenV4::try_from(token.clone()).unwrap().to_string();
+
+        let token_v3 = token.to_string();
+
+        assert_eq!(token.preferred_encoding(usize::MAX).unwrap(), token_v3);
+        assert_eq!(
+            token.preferred_encoding(token_v3.len() - 1).unwrap(),
+            token_v4
+        );
+        assert!(matches!(
+            token.preferred_encoding(token_v4.len() - 1),
+            Err(Error::TokenTooLong(_))
+        ));
+    }
+
+    #[test]
+    fn test_token_dleq_and_witness_round_trip() {
+        let mut proofs = Token::from_str(TOKEN_V3).unwrap().token[0].proofs.clone();
+        proofs[0].dleq = Some(ProofDleq::new(
+            SecretKey::generate(),
+            SecretKey::generate(),
+            SecretKey::generate(),
+        ));
+        proofs[1].witness = Some(Witness::P2PKWitness(P2PKWitness {
+            signatures: vec!["60f3c9b766770b46caac1d27e1ae6b77c8866ebaeba0b9489fe6a15a837eaa6fcd6eaa825499c72ac342983983fd3ba3a8a41f56677cc99ffd73da68b59e1383".to_string()],
+        }));
+
+        let mint_url = MintUrl::from_str("https://8333.space:3338").unwrap();
+
+        let token_v3 = Token::new(mint_url.clone(), proofs.clone(), None, None).unwrap();
+        let decoded = Token::from_str(&token_v3.to_string()).unwrap();
+        assert_eq!(decoded.token[0].proofs, proofs);
+
+        let token_v4 = TokenV4::new(mint_url, proofs.clone(), None, CurrencyUnit::Sat).unwrap();
+        let decoded = TokenV4::from_str(&token_v4.to_string()).unwrap();
+        assert_eq!(decoded.proofs(), proofs);
+    }
+
+    #[test]
+    fn test_token_uri_and_whitespace() {
+        let token = Token::from_str(TOKEN_V3).unwrap();
+
+        for token_str in [
+            format!("cashu:{}", TOKEN_V3),
+            format!("CASHU://{}", TOKEN_V3),
+            format!("  {}\n", TOKEN_V3),
+            format!("\tcashu:{} ", token),
+        ] {
+            assert_eq!(Token::from_str(&token_str).unwrap(), token);
+        }
+
+        let token_v4 = TokenV4::try_from(token.clone()).unwrap().to_string();
+        assert_eq!(
+            Token::from_str(&format!(" cashu:{}", token_v4)).unwrap(),
+            token
+        );
+    }
+
+    #[test]
+    fn test_token_base64_variants() {
+        let token = Token::from_str(TOKEN_V3).unwrap();
+
+        // Canonical encoding is url-safe with padding
+        let json_string = serde_json::to_string(&token).unwrap();
+        assert_eq!(
+            token.to_string(),
+            format!("cashuA{}", general_purpose::URL_SAFE.encode(&json_string))
+        );
+
+        let standard = format!("cashuA{}", general_purpose::STANDARD.encode(&json_string));
+        let url_safe_no_pad = format!(
+            "cashuA{}",
+            general_purpose::URL_SAFE_NO_PAD.encode(&json_string)
+        );
+
+        assert_eq!(Token::from_str(&standard).unwrap(), token);
+        assert_eq!(Token::from_str(&url_safe_no_pad).unwrap(), token);
+    }
+
+    #[test]
+    fn test_token_info_overflow() {
+        let mut token = Token::from_str(TOKEN_V3).unwrap();
+        assert_eq!(
+            token.token_info().unwrap(),
+            (Amount::from(10), "https://8333.space:3338".to_string())
+        );
+
+        token.token[0].proofs[0].amount = Amount::from(u64::MAX);
+        let token_v4 = TokenV4::new(
+            token.token[0].mint.clone(),
+            token.token[0].proofs.clone(),
+            None,
+            CurrencyUnit::Sat,
+        )
+        .unwrap();
+
+        assert!(token.token_info().is_err());
+        assert!(token_v4.token_info().is_err());
+    }
+
+    #[test]
+    fn incorrect_tokens_v4() {
+        assert!(TokenV4::from_str(TOKEN_V3).is_err());
+        assert!(TokenV4::from_str("cashuBnotcbor").is_err());
+    }
+}
@@ -151,7 +151,13 @@ impl<'de> Deserialize<'de> for PublicKey {
         D: Deserializer<'de>,
     {
         let public_key: String = String::deserialize(deserializer)?;
-        Self::from_hex(public_key).map_err(serde::de::Error::custom)
+        match Self::from_hex(&public_key) {
+            Ok(key) => Ok(key),
+            // Hex is canonical, base64 is accepted from other mints
+            Err(err) => super::decode_base64_key(&public_key)
+                .and_then(|bytes| Self::from_slice(&bytes).ok())
+                .ok_or_else(|| serde::de::Error::custom(err)),
+        }
     }
 }
 
@@ -170,6 +176,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_public_key_base64() {
+        let hex = "02194603ffa36356f4a56b7df9371fc3192472351453ec7398b8da8117e7c3e104";
+        let public_key = PublicKey::from_hex(hex).unwrap();
+
+        let base64 = "AhlGA/+jY1b0pWt9+TcfwxkkcjUUU+xzmLjagRfnw+EE";
+        let decoded: PublicKey = serde_json::from_str(&format!("\"{}\"", base64)).unwrap();
+        assert_eq!(decoded, public_key);
+        assert_eq!(
+            serde_json::to_string(&decoded).unwrap(),
+            format!("\"{}\"", hex)
+        );
+
+        assert!(serde_json::from_str::<PublicKey>("\"not a key\"").is_err());
+    }
+
     #[test]
     pub fn test_invalid_public_key_from_hex() {
         // Uncompressed (is valid but is cashu must be compressed?)
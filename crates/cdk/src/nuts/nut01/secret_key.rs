@@ -140,7 +140,13 @@ impl<'de> Deserialize<'de> for SecretKey {
         D: Deserializer<'de>,
     {
         let secret_key: String = String::deserialize(deserializer)?;
-        Self::from_hex(secret_key).map_err(serde::de::Error::custom)
+        match Self::from_hex(&secret_key) {
+            Ok(key) => Ok(key),
+            // Hex is canonical, base64 is accepted from other mints
+            Err(err) => super::decode_base64_key(&secret_key)
+                .and_then(|bytes| Self::from_slice(&bytes).ok())
+                .ok_or_else(|| serde::de::Error::custom(err)),
+        }
     }
 }
 
@@ -7,7 +7,10 @@ use core::str::FromStr;
 use std::array::TryFromSliceError;
 #[cfg(feature = "mint")]
 use std::collections::BTreeMap;
+use std::num::ParseIntError;
 
+use base64::engine::general_purpose;
+use base64::Engine as _;
 #[cfg(feature = "mint")]
 use bitcoin::bip32::{ChildNumber, ExtendedPrivKey};
 use bitcoin::hashes::sha256::Hash as Sha256;
@@ -40,66 +43,145 @@ pub enum Error {
     UnknownVersion,
     #[error(transparent)]
     Slice(#[from] TryFromSliceError),
+    #[error("NUT02: Keys do not match keyset id")]
+    IncorrectKeysetId,
+    /// Key is not indexed by an amount
+    #[error("NUT02: Invalid key amount")]
+    KeyAmount(#[from] ParseIntError),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum KeySetVersion {
     Version00,
+    /// Keyset ID v2 committing to the full keys hash, unit and final expiry
+    Version01,
 }
 
 impl KeySetVersion {
     pub fn to_byte(&self) -> u8 {
         match self {
             Self::Version00 => 0,
+            Self::Version01 => 1,
         }
     }
 
     pub fn from_byte(byte: &u8) -> Result<Self, Error> {
         match byte {
             0 => Ok(Self::Version00),
+            1 => Ok(Self::Version01),
             _ => Err(Error::UnknownVersion),
         }
     }
+
+    /// Length of the id bytes following the version byte
+    fn id_len(&self) -> usize {
+        match self {
+            Self::Version00 => Id::BYTELEN,
+            Self::Version01 => Id::BYTELEN_V2,
+        }
+    }
 }
 
 impl fmt::Display for KeySetVersion {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             KeySetVersion::Version00 => f.write_str("00"),
+            KeySetVersion::Version01 => f.write_str("01"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum IdBytes {
+    V1([u8; Id::BYTELEN]),
+    V2([u8; Id::BYTELEN_V2]),
+}
+
+impl IdBytes {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Self::V1(bytes) => bytes,
+            Self::V2(bytes) => bytes,
         }
     }
 }
 
 /// A keyset ID is an identifier for a specific keyset. It can be derived by
 /// anyone who knows the set of public keys of a mint. The keyset ID **CAN**
-/// be stored in a Cashu token such that the token can be used to identify
+/// be stored in a Cashu token such that the token can be used to identify
 /// which mint or keyset it was generated from.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Id {
     version: KeySetVersion,
-    id: [u8; Self::BYTELEN],
+    id: IdBytes,
 }
 
 impl Id {
     const STRLEN: usize = 14;
     const BYTELEN: usize = 7;
+    const BYTELEN_V2: usize = 32;
+
+    pub fn version(&self) -> KeySetVersion {
+        self.version
+    }
 
     pub fn to_bytes(&self) -> Vec<u8> {
-        [vec![self.version.to_byte()], self.id.to_vec()].concat()
+        [vec![self.version.to_byte()], self.id.as_slice().to_vec()].concat()
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let version = KeySetVersion::from_byte(bytes.first().ok_or(Error::Length)?)?;
+
+        Self::from_version_bytes(version, &bytes[1..])
+    }
+
+    fn from_version_bytes(version: KeySetVersion, bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != version.id_len() {
+            return Err(Error::Length);
+        }
+
+        let id = match version {
+            KeySetVersion::Version00 => IdBytes::V1(bytes.try_into()?),
+            KeySetVersion::Version01 => IdBytes::V2(bytes.try_into()?),
+        };
+
+        Ok(Self { version, id })
+    }
+
+    /// Keyset ID v2 of `keys`
+    ///
+    /// The id is the full sha256 of the sorted public keys followed by
+    /// `unit:<unit>` and, when set, `final_expiry:<unix timestamp>`. Fails if a
+    /// key is not indexed by an amount.
+    pub fn v2_from_data(
+        keys: &Keys,
+        unit: &CurrencyUnit,
+        final_expiry: Option<u64>,
+    ) -> Result<Self, Error> {
+        let mut data = sorted_pubkeys_concat(keys)?;
+        data.extend(format!("unit:{}", unit.to_string().to_lowercase()).as_bytes());
+
+        if let Some(final_expiry) = final_expiry {
+            data.extend(format!("final_expiry:{}", final_expiry).as_bytes());
+        }
+
+        let hash = Sha256::hash(&data);
+
         Ok(Self {
-            version: KeySetVersion::from_byte(&bytes[0])?,
-            id: bytes[1..].try_into()?,
+            version: KeySetVersion::Version01,
+            id: IdBytes::V2(hash.to_byte_array()),
         })
     }
 }
 
 impl TryFrom<Id> for u64 {
     type Error = Error;
+
+    /// Integer of the first 8 bytes of the id, used as the NUT-13 keyset index
     fn try_from(value: Id) -> Result<Self, Self::Error> {
-        let hex_bytes: [u8; 8] = value.to_bytes().try_into().map_err(|_| Error::Length)?;
+        let hex_bytes: [u8; 8] = value.to_bytes()[..8]
+            .try_into()
+            .map_err(|_| Error::Length)?;
 
         let int = u64::from_be_bytes(hex_bytes);
 
@@ -109,7 +191,11 @@ impl TryFrom<Id> for u64 {
 
 impl fmt::Display for Id {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(&format!("{}{}", self.version, hex::encode(self.id)))
+        f.write_str(&format!(
+            "{}{}",
+            self.version,
+            hex::encode(self.id.as_slice())
+        ))
     }
 }
 
@@ -118,16 +204,11 @@ impl FromStr for Id {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         // Check if the string length is valid
-        if s.len() != 16 {
+        if s.len() != Self::STRLEN + 2 && s.len() != 2 * Self::BYTELEN_V2 + 2 {
             return Err(Error::Length);
         }
 
-        Ok(Self {
-            version: KeySetVersion::Version00,
-            id: hex::decode(&s[2..])?
-                .try_into()
-                .map_err(|_| Error::Length)?,
-        })
+        Self::from_bytes(&hex::decode(s)?)
     }
 }
 
@@ -151,7 +232,7 @@ impl<'de> Deserialize<'de> for Id {
             type Value = Id;
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("Expecting a 14 char hex string")
+                formatter.write_str("Expecting a 16 or 66 char hex string")
             }
 
             fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
@@ -160,9 +241,10 @@ impl<'de> Deserialize<'de> for Id {
             {
                 Id::from_str(v).map_err(|e| match e {
                     Error::Length => E::custom(format!(
-                        "Invalid Length: Expected {}, got {}:
+                        "Invalid Length: Expected {} or {}, got {}:
                         {}",
-                        Id::STRLEN,
+                        Id::STRLEN + 2,
+                        2 * Id::BYTELEN_V2 + 2,
                         v.len(),
                         v
                     )),
@@ -175,8 +257,49 @@ impl<'de> Deserialize<'de> for Id {
     }
 }
 
-impl From<&Keys> for Id {
-    fn from(map: &Keys) -> Self {
+/// Public keys sorted by amount
+///
+/// Fails if a key is not indexed by an amount, keys of a mint are untrusted.
+fn sorted_pubkeys(map: &Keys) -> Result<Vec<&super::PublicKey>, Error> {
+    let mut keys = map
+        .iter()
+        .map(|(k, v)| Ok((u64::from_str(k)?, v)))
+        .collect::<Result<Vec<(u64, &super::PublicKey)>, Error>>()?;
+
+    keys.sort_by_key(|(amount, _v)| *amount);
+
+    Ok(keys.into_iter().map(|(_, pubkey)| pubkey).collect())
+}
+
+/// Public keys sorted by amount and concatenated
+fn sorted_pubkeys_concat(map: &Keys) -> Result<Vec<u8>, Error> {
+    Ok(sorted_pubkeys(map)?
+        .iter()
+        .map(|pubkey| pubkey.to_bytes())
+        .collect::<Vec<[u8; 33]>>()
+        .concat())
+}
+
+/// Legacy keyset id of `keys`
+///
+/// Keysets created before ids were hex encoded are identified by the first 12
+/// characters of the base64 SHA256 of the concatenated hex public keys, sorted
+/// by amount. Tokens of older wallets still carry these ids. Fails if a key is
+/// not indexed by an amount.
+pub fn legacy_keyset_id(keys: &Keys) -> Result<String, Error> {
+    let pubkeys_concat: String = sorted_pubkeys(keys)?
+        .iter()
+        .map(|pubkey| pubkey.to_hex())
+        .collect();
+    let hash = Sha256::hash(pubkeys_concat.as_bytes());
+
+    Ok(general_purpose::STANDARD.encode(hash.to_byte_array())[..12].to_string())
+}
+
+impl TryFrom<&Keys> for Id {
+    type Error = Error;
+
+    fn try_from(map: &Keys) -> Result<Self, Self::Error> {
         // REVIEW: Is it 16 or 14 bytes
         /* NUT-02
             1 - sort public keys by their amount in ascending order
@@ -186,26 +309,18 @@ impl From<&Keys> for Id {
             5 - prefix it with a keyset ID version byte
         */
 
-        let mut keys: Vec<(&String, &super::PublicKey)> = map.iter().collect();
-
-        keys.sort_by_key(|(k, _v)| u64::from_str(k).unwrap());
-
-        let pubkeys_concat: Vec<u8> = keys
-            .iter()
-            .map(|(_, pubkey)| pubkey.to_bytes())
-            .collect::<Vec<[u8; 33]>>()
-            .concat();
-
-        let hash = Sha256::hash(&pubkeys_concat);
+        let hash = Sha256::hash(&sorted_pubkeys_concat(map)?);
         let hex_of_hash = hex::encode(hash.to_byte_array());
 
-        Self {
+        Ok(Self {
             version: KeySetVersion::Version00,
-            id: hex::decode(&hex_of_hash[0..Self::STRLEN])
-                .expect("Keys hash could not be hex decoded")
-                .try_into()
-                .expect("Invalid length of hex id"),
-        }
+            id: IdBytes::V1(
+                hex::decode(&hex_of_hash[0..Self::STRLEN])
+                    .expect("Keys hash could not be hex decoded")
+                    .try_into()
+                    .expect("Invalid length of hex id"),
+            ),
+        })
     }
 }
 
@@ -232,6 +347,26 @@ pub struct KeySet {
     pub id: Id,
     pub unit: CurrencyUnit,
     pub keys: Keys,
+    /// Unix timestamp after which the keyset can no longer be used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub final_expiry: Option<u64>,
+}
+
+impl KeySet {
+    /// Verify that the keys hash to the keyset id
+    pub fn verify_id(&self) -> Result<(), Error> {
+        let keys_id = match self.id.version {
+            KeySetVersion::Version00 => Id::try_from(&self.keys)?,
+            KeySetVersion::Version01 => {
+                Id::v2_from_data(&self.keys, &self.unit, self.final_expiry)?
+            }
+        };
+
+        match keys_id == self.id {
+            true => Ok(()),
+            false => Err(Error::IncorrectKeysetId),
+        }
+    }
 }
 
 #[cfg(feature = "mint")]
@@ -241,6 +376,7 @@ impl From<MintKeySet> for KeySet {
             id: keyset.id,
             unit: keyset.unit,
             keys: Keys::from(keyset.keys),
+            final_expiry: None,
         }
     }
 }
@@ -250,6 +386,9 @@ pub struct KeySetInfo {
     pub id: Id,
     pub unit: CurrencyUnit,
     pub active: bool,
+    /// Unix time after which proofs of the keyset are no longer redeemed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub final_expiry: Option<u64>,
 }
 
 impl From<KeySet> for KeySetInfo {
@@ -258,6 +397,7 @@ impl From<KeySet> for KeySetInfo {
             id: keyset.id,
             unit: keyset.unit,
             active: false,
+            final_expiry: None,
         }
     }
 }
@@ -313,9 +453,7 @@ impl MintKeySet {
     ) -> Self {
         let xpriv =
             ExtendedPrivKey::new_master(bitcoin::Network::Bitcoin, seed).expect("RNG busted");
-        let max_order = info.max_order;
-        let unit = info.unit;
-        Self::generate(secp, xpriv, unit, max_order)
+        Self::generate_from_xpriv(secp, xpriv, info)
     }
 
     pub fn generate_from_xpriv<C: secp256k1::Signing>(
@@ -323,9 +461,14 @@ impl MintKeySet {
         xpriv: ExtendedPrivKey,
         info: MintKeySetInfo,
     ) -> Self {
-        let max_order = info.max_order;
-        let unit = info.unit;
-        Self::generate(secp, xpriv, unit, max_order)
+        Self::generate(
+            secp,
+            xpriv
+                .derive_priv(secp, &info.derivation_path)
+                .expect("RNG busted"),
+            info.unit,
+            info.max_order,
+        )
     }
 }
 
@@ -334,7 +477,7 @@ impl From<MintKeySet> for Id {
     fn from(keyset: MintKeySet) -> Id {
         let keys: super::KeySet = keyset.into();
 
-        Id::from(&keys.keys)
+        Id::try_from(&keys.keys).expect("Mint keys are indexed by amount")
     }
 }
 
@@ -343,7 +486,7 @@ impl From<&MintKeys> for Id {
     fn from(map: &MintKeys) -> Self {
         let keys: super::Keys = map.clone().into();
 
-        Id::from(&keys)
+        Id::try_from(&keys).expect("Mint keys are indexed by amount")
     }
 }
 
@@ -352,8 +495,9 @@ mod test {
 
     use std::str::FromStr;
 
-    use super::{KeySetInfo, Keys, KeysetResponse};
+    use super::{legacy_keyset_id, Error, KeySet, KeySetInfo, KeySetVersion, Keys, KeysetResponse};
     use crate::nuts::nut02::Id;
+    use crate::nuts::CurrencyUnit;
     use crate::nuts::KeysResponse;
 
     const SHORT_KEYSET_ID: &str = "00456a94ab4e1c46";
@@ -442,17 +586,81 @@ mod test {
 
         let keys: Keys = serde_json::from_str(SHORT_KEYSET).unwrap();
 
-        let id: Id = (&keys).into();
+        let id = Id::try_from(&keys).unwrap();
 
         assert_eq!(id, Id::from_str(SHORT_KEYSET_ID).unwrap());
 
         let keys: Keys = serde_json::from_str(KEYSET).unwrap();
 
-        let id: Id = (&keys).into();
+        let id = Id::try_from(&keys).unwrap();
 
         assert_eq!(id, Id::from_str(KEYSET_ID).unwrap());
     }
 
+    #[test]
+    fn test_keyset_id_v2() {
+        let keys: Keys = serde_json::from_str(SHORT_KEYSET).unwrap();
+
+        let id = Id::v2_from_data(&keys, &CurrencyUnit::Sat, None).unwrap();
+        assert_eq!(id.version(), KeySetVersion::Version01);
+        assert_eq!(id.to_string().len(), 66);
+        assert!(id.to_string().starts_with("01"));
+        assert_eq!(id.to_bytes().len(), 33);
+
+        assert_eq!(Id::from_str(&id.to_string()).unwrap(), id);
+        assert_eq!(Id::from_bytes(&id.to_bytes()).unwrap(), id);
+
+        // Unit and final expiry are part of the id
+        assert_ne!(
+            id,
+            Id::v2_from_data(&keys, &CurrencyUnit::Usd, None).unwrap()
+        );
+        assert_ne!(
+            id,
+            Id::v2_from_data(&keys, &CurrencyUnit::Sat, Some(1896187313)).unwrap()
+        );
+
+        assert!(u64::try_from(id).is_ok());
+    }
+
+    #[test]
+    fn test_keyset_verify_id() {
+        let keys: Keys = serde_json::from_str(SHORT_KEYSET).unwrap();
+
+        let mut keyset = KeySet {
+            id: Id::from_str(SHORT_KEYSET_ID).unwrap(),
+            unit: CurrencyUnit::Sat,
+            keys: keys.clone(),
+            final_expiry: None,
+        };
+        assert!(keyset.verify_id().is_ok());
+
+        keyset.final_expiry = Some(1896187313);
+        keyset.id = Id::v2_from_data(&keys, &CurrencyUnit::Sat, keyset.final_expiry).unwrap();
+        assert!(keyset.verify_id().is_ok());
+
+        keyset.final_expiry = None;
+        assert!(keyset.verify_id().is_err());
+
+        keyset.id = Id::from_str(KEYSET_ID).unwrap();
+        assert!(keyset.verify_id().is_err());
+        // Keys of a mint are untrusted, a key not indexed by an amount is an
+        // error
+        let mut keys = keys.keys().clone();
+        let pubkey = *keys.values().next().unwrap();
+        keys.insert("one".to_string(), pubkey);
+        keyset.keys = Keys::new(keys);
+        assert!(matches!(keyset.verify_id(), Err(Error::KeyAmount(_))));
+        assert!(legacy_keyset_id(&keyset.keys).is_err());
+    }
+
+    #[test]
+    fn test_invalid_keyset_id() {
+        assert!(Id::from_str("02456a94ab4e1c46").is_err());
+        assert!(Id::from_str("01456a94ab4e1c46").is_err());
+        assert!(Id::from_bytes(&[]).is_err());
+    }
+
     #[test]
     fn test_deserialization_keyset_info() {
         let h = r#"{"id":"009a1f293253e41e","unit":"sat","active":true}"#;
@@ -495,5 +703,16 @@ mod test {
         let keys_response: KeysResponse = serde_json::from_str(keys).unwrap();
 
         assert_eq!(keys_response.keysets.len(), 2);
+
+        // Legacy ids of the keysets the mint also lists with a hex id
+        let keysets: serde_json::Value = serde_json::from_str(keys).unwrap();
+        for keyset in keysets["keysets"].as_array().unwrap() {
+            let keys: Keys = serde_json::from_value(keyset["keys"].clone()).unwrap();
+            let legacy_id = legacy_keyset_id(&keys).unwrap();
+            match keyset["id"].as_str().unwrap() {
+                "000f01df73ea149a" | "I2yN+iRYfkzT" => assert_eq!(legacy_id, "I2yN+iRYfkzT"),
+                _ => assert_eq!(legacy_id, "yjzQhxghPdrr"),
+            }
+        }
     }
 }
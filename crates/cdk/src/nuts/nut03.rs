@@ -5,12 +5,18 @@
 use serde::{Deserialize, Serialize};
 
 use super::nut00::{BlindSignature, BlindedMessage, PreMintSecrets, Proofs};
+use crate::error::Error;
 use crate::Amount;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct PreSwap {
     pub pre_mint_secrets: PreMintSecrets,
     pub swap_request: SwapRequest,
+    /// Number of outputs derived from the keyset counter
+    ///
+    /// The counter is incremented by this many once the swap succeeds,
+    /// outputs with spending conditions have random secrets.
+    pub derived_count: u32,
 }
 
 /// Split Request [NUT-06]
@@ -28,13 +34,13 @@ impl SwapRequest {
     }
 
     /// Total value of proofs in `SplitRequest`
-    pub fn input_amount(&self) -> Amount {
-        self.inputs.iter().map(|proof| proof.amount).sum()
+    pub fn input_amount(&self) -> Result<Amount, Error> {
+        Amount::try_sum(self.inputs.iter().map(|proof| proof.amount))
     }
 
     /// Total value of outputs in `SplitRequest`
-    pub fn output_amount(&self) -> Amount {
-        self.outputs.iter().map(|proof| proof.amount).sum()
+    pub fn output_amount(&self) -> Result<Amount, Error> {
+        Amount::try_sum(self.outputs.iter().map(|proof| proof.amount))
     }
 }
 
@@ -15,6 +15,9 @@ pub struct MintQuoteBolt11Request {
     pub amount: Amount,
     /// Unit wallet would like to pay with
     pub unit: CurrencyUnit,
+    /// Description of the invoice to pay
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
 }
 
 /// Mint quote response [NUT-04]
@@ -28,6 +31,9 @@ pub struct MintQuoteBolt11Response {
     pub paid: bool,
     /// Unix timestamp until the quote is valid
     pub expiry: Option<u64>,
+    /// Price of one bitcoin in the quote unit used for the invoice
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange_rate: Option<u64>,
 }
 
 impl From<MintQuote> for MintQuoteBolt11Response {
@@ -37,6 +43,7 @@ impl From<MintQuote> for MintQuoteBolt11Response {
             request: mint_quote.request,
             paid: mint_quote.paid,
             expiry: Some(mint_quote.expiry),
+            exchange_rate: mint_quote.exchange_rate,
         }
     }
 }
@@ -70,20 +77,23 @@ pub struct MintBolt11Response {
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct MintMethodSettings {
     /// Payment Method e.g. bolt11
-    method: PaymentMethod,
+    pub method: PaymentMethod,
     /// Currency Unit e.g. sat
-    unit: CurrencyUnit,
+    pub unit: CurrencyUnit,
     /// Min Amount
     #[serde(skip_serializing_if = "Option::is_none")]
-    min_amount: Option<Amount>,
+    pub min_amount: Option<Amount>,
     /// Max Amount
     #[serde(skip_serializing_if = "Option::is_none")]
-    max_amount: Option<Amount>,
+    pub max_amount: Option<Amount>,
+    /// Quote requests may set the invoice description
+    #[serde(default)]
+    pub description: bool,
 }
 
 /// Mint Settings
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Settings {
-    methods: Vec<MintMethodSettings>,
-    disabled: bool,
+    pub methods: Vec<MintMethodSettings>,
+    pub disabled: bool,
 }
@@ -2,12 +2,67 @@
 //!
 //! <https://github.com/cashubtc/nuts/blob/main/05.md>
 
+use std::fmt;
+use std::str::FromStr;
+
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use super::nut00::{BlindSignature, BlindedMessage, CurrencyUnit, PaymentMethod, Proofs};
 use crate::types::MeltQuote;
 use crate::{Amount, Bolt11Invoice};
 
+/// NUT05 Error
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum Error {
+    /// Unknown Quote State
+    #[error("Unknown Quote State")]
+    UnknownState,
+}
+
+/// State of a melt quote
+///
+/// A quote is `Pending` while the mint tries to pay the request, its inputs
+/// can not be spent in that time. When the payment definitively failed the
+/// quote is `Failed`, the inputs are spendable again and the quote can be
+/// melted again.
+#[derive(Debug, Default, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum MeltQuoteState {
+    #[default]
+    Unpaid,
+    Pending,
+    Paid,
+    Failed,
+}
+
+impl fmt::Display for MeltQuoteState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            MeltQuoteState::Unpaid => "UNPAID",
+            MeltQuoteState::Pending => "PENDING",
+            MeltQuoteState::Paid => "PAID",
+            MeltQuoteState::Failed => "FAILED",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for MeltQuoteState {
+    type Err = Error;
+
+    fn from_str(state: &str) -> Result<Self, Self::Err> {
+        match state {
+            "UNPAID" => Ok(Self::Unpaid),
+            "PENDING" => Ok(Self::Pending),
+            "PAID" => Ok(Self::Paid),
+            "FAILED" => Ok(Self::Failed),
+            _ => Err(Error::UnknownState),
+        }
+    }
+}
+
 /// Melt quote request [NUT-05]
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MeltQuoteBolt11Request {
@@ -19,6 +74,7 @@ pub struct MeltQuoteBolt11Request {
 
 /// Melt quote response [NUT-05]
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "MeltQuoteBolt11ResponseWire")]
 pub struct MeltQuoteBolt11Response {
     /// Quote Id
     pub quote: String,
@@ -28,8 +84,52 @@ pub struct MeltQuoteBolt11Response {
     pub fee_reserve: Amount,
     /// Whether the the request haas be paid
     pub paid: bool,
+    /// State of the quote
+    pub state: MeltQuoteState,
     /// Unix timestamp until the quote is valid
     pub expiry: u64,
+    /// Price of one bitcoin in the quote unit used for the amount
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange_rate: Option<u64>,
+    /// Bolt11 preimage once the quote is paid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_preimage: Option<String>,
+}
+
+/// [`MeltQuoteBolt11Response`] of mints that may only send `paid`
+#[derive(Deserialize)]
+struct MeltQuoteBolt11ResponseWire {
+    quote: String,
+    amount: Amount,
+    fee_reserve: Amount,
+    paid: bool,
+    state: Option<MeltQuoteState>,
+    expiry: u64,
+    #[serde(default)]
+    exchange_rate: Option<u64>,
+    #[serde(default)]
+    payment_preimage: Option<String>,
+}
+
+impl From<MeltQuoteBolt11ResponseWire> for MeltQuoteBolt11Response {
+    fn from(response: MeltQuoteBolt11ResponseWire) -> Self {
+        let state = match (response.state, response.paid) {
+            (Some(state), _) => state,
+            (None, true) => MeltQuoteState::Paid,
+            (None, false) => MeltQuoteState::Unpaid,
+        };
+
+        Self {
+            quote: response.quote,
+            amount: response.amount,
+            fee_reserve: response.fee_reserve,
+            paid: response.paid,
+            state,
+            expiry: response.expiry,
+            exchange_rate: response.exchange_rate,
+            payment_preimage: response.payment_preimage,
+        }
+    }
 }
 
 impl From<MeltQuote> for MeltQuoteBolt11Response {
@@ -38,8 +138,11 @@ impl From<MeltQuote> for MeltQuoteBolt11Response {
             quote: melt_quote.id,
             amount: melt_quote.amount,
             fee_reserve: melt_quote.fee_reserve,
-            paid: melt_quote.paid,
+            paid: melt_quote.state == MeltQuoteState::Paid,
+            state: melt_quote.state,
             expiry: melt_quote.expiry,
+            exchange_rate: melt_quote.exchange_rate,
+            payment_preimage: None,
         }
     }
 }
@@ -57,8 +160,8 @@ pub struct MeltBolt11Request {
 }
 
 impl MeltBolt11Request {
-    pub fn proofs_amount(&self) -> Amount {
-        self.inputs.iter().map(|proof| proof.amount).sum()
+    pub fn proofs_amount(&self) -> Result<Amount, crate::error::Error> {
+        Amount::try_sum(self.inputs.iter().map(|proof| proof.amount))
     }
 }
 
@@ -77,20 +180,48 @@ pub struct MeltBolt11Response {
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct MeltMethodSettings {
     /// Payment Method e.g. bolt11
-    method: PaymentMethod,
+    pub method: PaymentMethod,
     /// Currency Unit e.g. sat
-    unit: CurrencyUnit,
+    pub unit: CurrencyUnit,
     /// Min Amount
     #[serde(skip_serializing_if = "Option::is_none")]
-    min_amount: Option<Amount>,
+    pub min_amount: Option<Amount>,
     /// Max Amount
     #[serde(skip_serializing_if = "Option::is_none")]
-    max_amount: Option<Amount>,
+    pub max_amount: Option<Amount>,
 }
 
 /// Melt Settings
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Settings {
-    methods: Vec<MeltMethodSettings>,
-    disabled: bool,
+    pub methods: Vec<MeltMethodSettings>,
+    pub disabled: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_melt_quote_state() {
+        let response: MeltQuoteBolt11Response = serde_json::from_str(
+            r#"{"quote":"q","amount":10,"fee_reserve":2,"paid":true,"expiry":1701704757}"#,
+        )
+        .unwrap();
+        assert_eq!(response.state, MeltQuoteState::Paid);
+
+        let response: MeltQuoteBolt11Response = serde_json::from_str(
+            r#"{"quote":"q","amount":10,"fee_reserve":2,"paid":false,"state":"PENDING","expiry":1701704757}"#,
+        )
+        .unwrap();
+        assert_eq!(response.state, MeltQuoteState::Pending);
+
+        let json = serde_json::to_string(&response).unwrap();
+        assert!(json.contains(r#""state":"PENDING""#));
+
+        assert_eq!(
+            MeltQuoteState::from_str(&MeltQuoteState::Failed.to_string()),
+            Ok(MeltQuoteState::Failed)
+        );
+    }
 }
@@ -67,6 +67,111 @@ pub struct MintInfo {
     /// message of the day that the wallet must display to the user
     #[serde(skip_serializing_if = "Option::is_none")]
     pub motd: Option<String>,
+    /// URL of the mint icon
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon_url: Option<String>,
+    /// server unix timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time: Option<u64>,
+}
+
+/// Builder for [`MintInfo`]
+#[derive(Debug, Default, Clone)]
+pub struct MintInfoBuilder {
+    mint_info: MintInfo,
+}
+
+impl MintInfoBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn name<S>(mut self, name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.mint_info.name = Some(name.into());
+        self
+    }
+
+    pub fn pubkey(mut self, pubkey: PublicKey) -> Self {
+        self.mint_info.pubkey = Some(pubkey);
+        self
+    }
+
+    pub fn version(mut self, version: MintVersion) -> Self {
+        self.mint_info.version = Some(version);
+        self
+    }
+
+    pub fn description<S>(mut self, description: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.mint_info.description = Some(description.into());
+        self
+    }
+
+    pub fn description_long<S>(mut self, description_long: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.mint_info.description_long = Some(description_long.into());
+        self
+    }
+
+    /// Add contact method e.g. `("email", "contact@me.com")`
+    pub fn contact<M, I>(mut self, method: M, info: I) -> Self
+    where
+        M: Into<String>,
+        I: Into<String>,
+    {
+        self.mint_info
+            .contact
+            .get_or_insert_with(Vec::new)
+            .push(vec![method.into(), info.into()]);
+        self
+    }
+
+    pub fn motd<S>(mut self, motd: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.mint_info.motd = Some(motd.into());
+        self
+    }
+
+    pub fn icon_url<S>(mut self, icon_url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.mint_info.icon_url = Some(icon_url.into());
+        self
+    }
+
+    pub fn time(mut self, time: u64) -> Self {
+        self.mint_info.time = Some(time);
+        self
+    }
+
+    pub fn nuts(mut self, nuts: Nuts) -> Self {
+        self.mint_info.nuts = nuts;
+        self
+    }
+
+    pub fn nut04(mut self, settings: nut04::Settings) -> Self {
+        self.mint_info.nuts.nut04 = settings;
+        self
+    }
+
+    pub fn nut05(mut self, settings: nut05::Settings) -> Self {
+        self.mint_info.nuts.nut05 = settings;
+        self
+    }
+
+    pub fn build(self) -> MintInfo {
+        self.mint_info
+    }
 }
 
 /// Supported nuts and settings
@@ -116,6 +221,18 @@ impl Default for SupportedSettings {
     }
 }
 
+impl SupportedSettings {
+    /// Settings of a NUT the mint does not support
+    pub fn unsupported() -> Self {
+        Self { supported: false }
+    }
+
+    /// Check if the NUT is supported
+    pub fn is_supported(&self) -> bool {
+        self.supported
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -182,4 +299,30 @@ mod tests {
 }"#;
         let _info: MintInfo = serde_json::from_str(mint_info_str).unwrap();
     }
+
+    #[test]
+    fn test_mint_info_builder() {
+        let mint_info = MintInfoBuilder::new()
+            .name("Bob's Cashu mint")
+            .description("The short mint description")
+            .contact("email", "contact@me.com")
+            .contact("twitter", "@me")
+            .motd("Message to display to users.")
+            .icon_url("https://example.com/icon.png")
+            .time(1702888816)
+            .build();
+
+        assert_eq!(mint_info.name, Some("Bob's Cashu mint".to_string()));
+        assert_eq!(
+            mint_info.contact,
+            Some(vec![
+                vec!["email".to_string(), "contact@me.com".to_string()],
+                vec!["twitter".to_string(), "@me".to_string()],
+            ])
+        );
+
+        let json = serde_json::to_string(&mint_info).unwrap();
+        let info: MintInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(info, mint_info);
+    }
 }
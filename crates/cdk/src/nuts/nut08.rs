@@ -3,20 +3,23 @@
 //! <https://github.com/cashubtc/nuts/blob/main/08.md>
 
 use super::nut05::{MeltBolt11Request, MeltBolt11Response};
+use crate::error::Error;
 use crate::Amount;
 
 impl MeltBolt11Request {
-    pub fn output_amount(&self) -> Option<Amount> {
+    pub fn output_amount(&self) -> Result<Option<Amount>, Error> {
         self.outputs
             .as_ref()
-            .map(|o| o.iter().map(|proof| proof.amount).sum())
+            .map(|o| Amount::try_sum(o.iter().map(|proof| proof.amount)))
+            .transpose()
     }
 }
 
 impl MeltBolt11Response {
-    pub fn change_amount(&self) -> Option<Amount> {
+    pub fn change_amount(&self) -> Result<Option<Amount>, Error> {
         self.change
             .as_ref()
-            .map(|c| c.iter().map(|b| b.amount).sum())
+            .map(|c| Amount::try_sum(c.iter().map(|b| b.amount)))
+            .transpose()
     }
 }
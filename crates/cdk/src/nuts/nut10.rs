@@ -2,11 +2,15 @@
 //!
 //! <https://github.com/cashubtc/nuts/blob/main/10.md>
 
+use std::collections::HashMap;
+use std::fmt;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use serde::ser::SerializeTuple;
 use serde::{Deserialize, Serialize, Serializer};
 
+use super::Proof;
 use crate::error::Error;
 
 ///  NUT10 Secret Kind
@@ -25,7 +29,7 @@ pub struct SecretData {
     /// Expresses the spending condition specific to each kind
     pub data: String,
     /// Additional data committed to and can be used for feature extensions
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub tags: Vec<Vec<String>>,
 }
 
@@ -80,6 +84,137 @@ impl TryFrom<Secret> for crate::secret::Secret {
     }
 }
 
+/// Secret of a spending condition kind not defined by a NUT
+///
+/// Serialized like [`Secret`], with any string as kind. The mint enforces it
+/// if a [`ConditionVerifier`] for the kind is registered, otherwise the
+/// secret is treated as a plain secret.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub struct CustomSecret {
+    /// Kind of the spending condition
+    pub kind: String,
+    /// Secret Data
+    pub secret_data: SecretData,
+}
+
+impl CustomSecret {
+    pub fn new<K, S, V>(kind: K, data: S, tags: V) -> Self
+    where
+        K: Into<String>,
+        S: Into<String>,
+        V: Into<Vec<Vec<String>>>,
+    {
+        let nonce = crate::secret::Secret::generate().to_string();
+        let secret_data = SecretData {
+            nonce,
+            data: data.into(),
+            tags: tags.into(),
+        };
+
+        Self {
+            kind: kind.into(),
+            secret_data,
+        }
+    }
+
+    /// Same condition with a new nonce
+    pub fn renew(&self) -> Self {
+        Self::new(
+            self.kind.clone(),
+            self.secret_data.data.clone(),
+            self.secret_data.tags.clone(),
+        )
+    }
+}
+
+impl Serialize for CustomSecret {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut s = serializer.serialize_tuple(2)?;
+
+        s.serialize_element(&self.kind)?;
+        s.serialize_element(&self.secret_data)?;
+        s.end()
+    }
+}
+
+impl TryFrom<CustomSecret> for crate::secret::Secret {
+    type Error = Error;
+    fn try_from(secret: CustomSecret) -> Result<crate::secret::Secret, Self::Error> {
+        Ok(crate::secret::Secret::from_str(&serde_json::to_string(
+            &secret,
+        )?)?)
+    }
+}
+
+impl TryFrom<&crate::secret::Secret> for CustomSecret {
+    type Error = Error;
+    fn try_from(secret: &crate::secret::Secret) -> Result<CustomSecret, Self::Error> {
+        Ok(serde_json::from_str(&secret.to_string())?)
+    }
+}
+
+/// Verification of a custom spending condition kind
+pub trait ConditionVerifier {
+    /// Kind handled by the verifier
+    fn kind(&self) -> &str;
+
+    /// Check that `proof`, locked to `secret`, meets the condition
+    ///
+    /// The signature of the mint is verified separately.
+    fn verify(&self, secret: &CustomSecret, proof: &Proof) -> Result<(), Error>;
+}
+
+/// Custom spending condition kinds enforced by the mint
+///
+/// P2PK and HTLC are always enforced and cannot be replaced.
+#[derive(Clone, Default)]
+pub struct ConditionRegistry {
+    verifiers: HashMap<String, Arc<dyn ConditionVerifier + Send + Sync>>,
+}
+
+impl ConditionRegistry {
+    /// Enforce the kind of `verifier`, replacing a verifier of the same kind
+    pub fn register(&mut self, verifier: Arc<dyn ConditionVerifier + Send + Sync>) {
+        self.verifiers.insert(verifier.kind().to_string(), verifier);
+    }
+
+    /// Verifier of `kind`
+    pub fn get(&self, kind: &str) -> Option<&Arc<dyn ConditionVerifier + Send + Sync>> {
+        self.verifiers.get(kind)
+    }
+
+    /// Registered kinds
+    pub fn kinds(&self) -> Vec<String> {
+        self.verifiers.keys().cloned().collect()
+    }
+
+    /// Verify `proof` if its secret is of a registered kind
+    ///
+    /// Proofs without or with an unregistered custom kind pass.
+    pub fn verify(&self, proof: &Proof) -> Result<(), Error> {
+        let secret = match CustomSecret::try_from(&proof.secret) {
+            Ok(secret) => secret,
+            Err(_) => return Ok(()),
+        };
+
+        match self.get(&secret.kind) {
+            Some(verifier) => verifier.verify(&secret, proof),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for ConditionRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConditionRegistry")
+            .field("kinds", &self.kinds())
+            .finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::assert_eq;
@@ -106,4 +241,53 @@ mod tests {
 
         assert_eq!(serde_json::to_string(&secret).unwrap(), secret_str);
     }
+
+    /// Condition met by proofs with the data as witness preimage
+    struct Password;
+
+    impl ConditionVerifier for Password {
+        fn kind(&self) -> &str {
+            "PASSWORD"
+        }
+
+        fn verify(&self, secret: &CustomSecret, proof: &Proof) -> Result<(), Error> {
+            match proof.witness.as_ref().and_then(|w| w.preimage()) {
+                Some(preimage) if preimage == secret.secret_data.data => Ok(()),
+                _ => Err(Error::TokenNotVerified),
+            }
+        }
+    }
+
+    #[test]
+    fn test_condition_registry() {
+        use crate::nuts::nut00::Witness;
+        use crate::nuts::nut14::HTLCWitness;
+        use crate::nuts::{Id, PublicKey};
+        use crate::Amount;
+
+        let locked = CustomSecret::new("PASSWORD", "hunter2", vec![]);
+        let mut proof = Proof::new(
+            Amount::from(1),
+            Id::from_str("009a1f293253e41e").unwrap(),
+            locked.clone().try_into().unwrap(),
+            PublicKey::from_hex(
+                "02698c4e2b5f9534cd0687d87513c759790cf829aa5739184a3e3735471fbda904",
+            )
+            .unwrap(),
+        );
+        assert_eq!(CustomSecret::try_from(&proof.secret).unwrap(), locked);
+
+        let mut registry = ConditionRegistry::default();
+        // Unregistered kinds are plain secrets
+        assert!(registry.verify(&proof).is_ok());
+
+        registry.register(Arc::new(Password));
+        assert!(registry.verify(&proof).is_err());
+
+        proof.witness = Some(Witness::HTLCWitness(HTLCWitness {
+            preimage: "hunter2".to_string(),
+            signatures: None,
+        }));
+        assert!(registry.verify(&proof).is_ok());
+    }
 }
@@ -2,7 +2,7 @@
 //!
 //! <https://github.com/cashubtc/nuts/blob/main/11.md>
 
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::str::FromStr;
 use std::{fmt, vec};
 
@@ -16,7 +16,7 @@ use thiserror::Error;
 
 use super::nut00::Witness;
 use super::nut01::PublicKey;
-use super::{Kind, Nut10Secret, Proof, Proofs, SecretKey};
+use super::{Kind, Nut10Secret, Proof, Proofs, SecretData, SecretKey};
 use crate::nuts::nut00::BlindedMessage;
 use crate::secret::Secret;
 use crate::util::{hex, unix_time};
@@ -43,6 +43,12 @@ pub enum Error {
     /// Unknown Sigflag
     #[error("Unknown Sigflag")]
     UnknownSigFlag,
+    /// Tag appears more than once
+    #[error("Duplicate tag `{0}`")]
+    DuplicateTag(TagKind),
+    /// Tag value missing or invalid
+    #[error("Malformed tag `{0}`")]
+    MalformedTag(TagKind),
     /// P2PK Spend conditions not meet
     #[error("P2PK Spend conditions are not met")]
     SpendConditionsNotMet,
@@ -391,6 +397,10 @@ impl Conditions {
             }
         }
 
+        if num_sigs == Some(0) {
+            return Err(Error::MalformedTag(TagKind::NSigs));
+        }
+
         Ok(Self {
             locktime,
             pubkeys,
@@ -434,61 +444,71 @@ impl From<Conditions> for Vec<Vec<String>> {
 
 impl TryFrom<Vec<Vec<String>>> for Conditions {
     type Error = Error;
+
+    /// Parse the tags of a P2PK or HTLC secret
+    ///
+    /// Fails on repeated or malformed tags. Tags not defined by NUT-11 are
+    /// ignored.
     fn try_from(tags: Vec<Vec<String>>) -> Result<Conditions, Self::Error> {
-        let tags: HashMap<TagKind, Tag> = tags
-            .into_iter()
-            .map(|t| Tag::try_from(t).unwrap())
-            .map(|t| (t.kind(), t))
-            .collect();
-
-        let pubkeys = match tags.get(&TagKind::Pubkeys) {
-            Some(Tag::PubKeys(pubkeys)) => Some(pubkeys.clone()),
-            _ => None,
-        };
+        let mut conditions = Conditions::default();
+        let mut seen = HashSet::new();
 
-        let locktime = if let Some(tag) = tags.get(&TagKind::Locktime) {
-            match tag {
-                Tag::LockTime(locktime) => Some(*locktime),
-                _ => None,
-            }
-        } else {
-            None
-        };
+        for tag in tags {
+            let kind = match tag.first() {
+                Some(kind) => TagKind::from(kind),
+                None => return Err(Error::KindNotFound),
+            };
 
-        let refund_keys = if let Some(tag) = tags.get(&TagKind::Refund) {
-            match tag {
-                Tag::Refund(keys) => Some(keys.clone()),
-                _ => None,
+            if let TagKind::Custom(_) = kind {
+                continue;
             }
-        } else {
-            None
-        };
 
-        let sig_flag = if let Some(tag) = tags.get(&TagKind::SigFlag) {
-            match tag {
-                Tag::SigFlag(sigflag) => sigflag.clone(),
-                _ => SigFlag::SigInputs,
+            if !seen.insert(kind.clone()) {
+                return Err(Error::DuplicateTag(kind));
             }
-        } else {
-            SigFlag::SigInputs
-        };
 
-        let num_sigs = if let Some(tag) = tags.get(&TagKind::NSigs) {
-            match tag {
-                Tag::NSigs(num_sigs) => Some(*num_sigs),
-                _ => None,
+            match Tag::try_from(tag)? {
+                Tag::SigFlag(sig_flag) => conditions.sig_flag = sig_flag,
+                Tag::NSigs(num_sigs) => conditions.num_sigs = Some(num_sigs),
+                Tag::LockTime(locktime) => conditions.locktime = Some(locktime),
+                Tag::Refund(refund_keys) => conditions.refund_keys = Some(refund_keys),
+                Tag::PubKeys(pubkeys) => conditions.pubkeys = Some(pubkeys),
             }
-        } else {
-            None
-        };
+        }
 
-        Ok(Conditions {
-            locktime,
-            pubkeys,
-            refund_keys,
-            num_sigs,
-            sig_flag,
-        })
+        Ok(conditions)
+    }
+}
+
+impl SecretData {
+    /// Spending conditions of the tags
+    pub fn conditions(&self) -> Result<Conditions, Error> {
+        self.tags.clone().try_into()
+    }
+
+    /// Signature flag, [`SigFlag::SigInputs`] if not set
+    pub fn sig_flag(&self) -> Result<SigFlag, Error> {
+        Ok(self.conditions()?.sig_flag)
+    }
+
+    /// Number of signatures required
+    pub fn num_sigs(&self) -> Result<Option<u64>, Error> {
+        Ok(self.conditions()?.num_sigs)
+    }
+
+    /// Unix time after which the refund keys can spend
+    pub fn locktime(&self) -> Result<Option<u64>, Error> {
+        Ok(self.conditions()?.locktime)
+    }
+
+    /// Keys that can spend after the locktime
+    pub fn refund_keys(&self) -> Result<Option<Vec<PublicKey>>, Error> {
+        Ok(self.conditions()?.refund_keys)
+    }
+
+    /// Additional keys that can sign
+    pub fn pubkeys(&self) -> Result<Option<Vec<PublicKey>>, Error> {
+        Ok(self.conditions()?.pubkeys)
     }
 }
 
@@ -631,28 +651,33 @@ where
             None => return Err(Error::KindNotFound),
         };
 
-        match tag_kind {
-            TagKind::SigFlag => Ok(Tag::SigFlag(SigFlag::from_str(tag[1].as_ref())?)),
-            TagKind::NSigs => Ok(Tag::NSigs(tag[1].as_ref().parse()?)),
-            TagKind::Locktime => Ok(Tag::LockTime(tag[1].as_ref().parse()?)),
-            TagKind::Refund => {
-                let pubkeys = tag
-                    .iter()
-                    .skip(1)
-                    .flat_map(|p| PublicKey::from_str(p.as_ref()))
-                    .collect();
-
-                Ok(Self::Refund(pubkeys))
-            }
-            TagKind::Pubkeys => {
-                let pubkeys = tag
-                    .iter()
-                    .skip(1)
-                    .flat_map(|p| PublicKey::from_str(p.as_ref()))
-                    .collect();
-
-                Ok(Self::PubKeys(pubkeys))
+        let malformed = || Error::MalformedTag(tag_kind.clone());
+        let value = || match tag.as_slice() {
+            [_, value] => Ok(value.as_ref()),
+            _ => Err(malformed()),
+        };
+        let pubkeys = || -> Result<Vec<PublicKey>, Error> {
+            let pubkeys = tag
+                .iter()
+                .skip(1)
+                .map(|p| PublicKey::from_str(p.as_ref()).map_err(|_| malformed()))
+                .collect::<Result<Vec<PublicKey>, Error>>()?;
+
+            match pubkeys.is_empty() {
+                true => Err(malformed()),
+                false => Ok(pubkeys),
             }
+        };
+
+        match tag_kind {
+            TagKind::SigFlag => Ok(Tag::SigFlag(SigFlag::from_str(value()?)?)),
+            TagKind::NSigs => match value()?.parse().map_err(|_| malformed())? {
+                0 => Err(malformed()),
+                num_sigs => Ok(Tag::NSigs(num_sigs)),
+            },
+            TagKind::Locktime => Ok(Tag::LockTime(value()?.parse().map_err(|_| malformed())?)),
+            TagKind::Refund => Ok(Self::Refund(pubkeys()?)),
+            TagKind::Pubkeys => Ok(Self::PubKeys(pubkeys()?)),
             _ => Err(Error::UnknownTag),
         }
     }
@@ -854,4 +879,53 @@ mod tests {
 
         assert!(invalid_proof.verify_p2pk().is_err());
     }
+
+    #[test]
+    fn test_parse_tags() {
+        let pubkey = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+        let tags = |tags: &[&[&str]]| -> Vec<Vec<String>> {
+            tags.iter()
+                .map(|tag| tag.iter().map(|t| t.to_string()).collect())
+                .collect()
+        };
+
+        let secret_data = SecretData {
+            nonce: "5d11913ee0f92fefdc82a6764fd2457a".to_string(),
+            data: pubkey.to_string(),
+            tags: tags(&[
+                &["sigflag", "SIG_ALL"],
+                &["n_sigs", "2"],
+                &["locktime", "21"],
+                &["refund", pubkey],
+                &["pubkeys", pubkey, pubkey],
+                &["memo", "ignored"],
+            ]),
+        };
+        assert_eq!(secret_data.sig_flag().unwrap(), SigFlag::SigAll);
+        assert_eq!(secret_data.num_sigs().unwrap(), Some(2));
+        assert_eq!(secret_data.locktime().unwrap(), Some(21));
+        assert_eq!(secret_data.refund_keys().unwrap().unwrap().len(), 1);
+        assert_eq!(secret_data.pubkeys().unwrap().unwrap().len(), 2);
+
+        assert!(matches!(
+            Conditions::try_from(tags(&[&["n_sigs", "1"], &["n_sigs", "2"]])),
+            Err(Error::DuplicateTag(TagKind::NSigs))
+        ));
+
+        for malformed in [
+            tags(&[&["n_sigs"]]),
+            tags(&[&["n_sigs", "0"]]),
+            tags(&[&["locktime", "soon"]]),
+            tags(&[&["locktime", "21", "22"]]),
+            tags(&[&["pubkeys"]]),
+            tags(&[&["refund", pubkey, "not a key"]]),
+        ] {
+            assert!(matches!(
+                Conditions::try_from(malformed),
+                Err(Error::MalformedTag(_))
+            ));
+        }
+
+        assert!(Conditions::new(None, None, None, Some(0), None).is_err());
+    }
 }
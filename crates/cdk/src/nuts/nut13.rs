@@ -51,9 +51,7 @@ impl PreMintSecrets {
     ) -> Result<Self, Error> {
         let mut pre_mint_secrets = PreMintSecrets::default();
 
-        let mut counter = counter;
-
-        for amount in amount.split_targeted(amount_split_target) {
+        for (counter, amount) in (counter..).zip(amount.split_targeted(amount_split_target)?) {
             let secret = Secret::from_xpriv(xpriv, keyset_id, counter)?;
             let blinding_factor = SecretKey::from_xpriv(xpriv, keyset_id, counter)?;
 
@@ -71,7 +69,6 @@ impl PreMintSecrets {
             };
 
             pre_mint_secrets.secrets.push(pre_mint);
-            counter += 1;
         }
 
         Ok(pre_mint_secrets)
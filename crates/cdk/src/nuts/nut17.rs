@@ -0,0 +1,247 @@
+//! NUT-17: WebSocket subscriptions
+//!
+//! <https://github.com/cashubtc/nuts/blob/main/17.md>
+//!
+//! Wallets subscribe to state changes of quotes and proofs over a JSON-RPC
+//! 2.0 WebSocket connection instead of polling the mint.
+
+use serde::{Deserialize, Serialize};
+
+use super::{MeltQuoteBolt11Response, MintQuoteBolt11Response, ProofState};
+
+/// JSON-RPC version of every message
+pub const JSON_RPC_VERSION: &str = "2.0";
+
+/// What a subscription is notified about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionKind {
+    /// State of mint quotes, filtered by quote id
+    Bolt11MintQuote,
+    /// State of melt quotes, filtered by quote id
+    Bolt11MeltQuote,
+    /// State of proofs, filtered by hex encoded `Y`
+    ProofState,
+}
+
+/// Params of a subscribe request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Params {
+    pub kind: SubscriptionKind,
+    /// Ids of the objects of `kind` to notify about
+    pub filters: Vec<String>,
+    /// Id chosen by the wallet, sent with every notification
+    #[serde(rename = "subId")]
+    pub sub_id: String,
+}
+
+/// Params of an unsubscribe request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnsubscribeParams {
+    #[serde(rename = "subId")]
+    pub sub_id: String,
+}
+
+/// Method and params of a [`WsRequest`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "lowercase")]
+pub enum WsMethod {
+    Subscribe(Params),
+    Unsubscribe(UnsubscribeParams),
+}
+
+/// Request sent by the wallet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WsRequest {
+    pub jsonrpc: String,
+    #[serde(flatten)]
+    pub method: WsMethod,
+    pub id: u64,
+}
+
+impl WsRequest {
+    /// Create new [`WsRequest`]
+    pub fn new(method: WsMethod, id: u64) -> Self {
+        Self {
+            jsonrpc: JSON_RPC_VERSION.to_string(),
+            method,
+            id,
+        }
+    }
+}
+
+/// Result of a successful request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WsResult {
+    /// `OK`
+    pub status: String,
+    #[serde(rename = "subId")]
+    pub sub_id: String,
+}
+
+/// Error of a failed request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WsError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl WsError {
+    /// Message is not valid JSON
+    pub const PARSE_ERROR: i32 = -32700;
+    /// Message is not a valid request
+    pub const INVALID_REQUEST: i32 = -32600;
+    /// Params are invalid or exceed the limits of the mint
+    pub const INVALID_PARAMS: i32 = -32602;
+}
+
+/// Outcome of a [`WsRequest`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WsOutcome {
+    Result(WsResult),
+    Error(WsError),
+}
+
+/// Response of the mint to a [`WsRequest`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WsResponse {
+    pub jsonrpc: String,
+    #[serde(flatten)]
+    pub outcome: WsOutcome,
+    /// Id of the request, `None` if it could not be parsed
+    pub id: Option<u64>,
+}
+
+impl WsResponse {
+    /// Successful response to request `id` for subscription `sub_id`
+    pub fn ok(id: u64, sub_id: String) -> Self {
+        Self {
+            jsonrpc: JSON_RPC_VERSION.to_string(),
+            outcome: WsOutcome::Result(WsResult {
+                status: "OK".to_string(),
+                sub_id,
+            }),
+            id: Some(id),
+        }
+    }
+
+    /// Failed response to request `id`
+    pub fn error(id: Option<u64>, code: i32, message: String) -> Self {
+        Self {
+            jsonrpc: JSON_RPC_VERSION.to_string(),
+            outcome: WsOutcome::Error(WsError { code, message }),
+            id,
+        }
+    }
+}
+
+/// State sent to a subscription
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum NotificationPayload {
+    ProofState(ProofState),
+    MeltQuote(MeltQuoteBolt11Response),
+    MintQuote(MintQuoteBolt11Response),
+}
+
+impl NotificationPayload {
+    /// Id of the object the state is of, matching a filter of the subscription
+    pub fn id(&self) -> String {
+        match self {
+            Self::ProofState(state) => state.y.to_hex(),
+            Self::MeltQuote(quote) => quote.quote.clone(),
+            Self::MintQuote(quote) => quote.quote.clone(),
+        }
+    }
+}
+
+/// Params of a [`WsNotification`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationParams {
+    #[serde(rename = "subId")]
+    pub sub_id: String,
+    pub payload: NotificationPayload,
+}
+
+/// Notification sent by the mint when the state of a subscribed object changes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WsNotification {
+    pub jsonrpc: String,
+    /// `subscribe`
+    pub method: String,
+    pub params: NotificationParams,
+}
+
+impl WsNotification {
+    /// Create new [`WsNotification`]
+    pub fn new(sub_id: String, payload: NotificationPayload) -> Self {
+        Self {
+            jsonrpc: JSON_RPC_VERSION.to_string(),
+            method: "subscribe".to_string(),
+            params: NotificationParams { sub_id, payload },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ws_messages() {
+        let request: WsRequest = serde_json::from_str(
+            r#"{"jsonrpc":"2.0","id":0,"method":"subscribe","params":{"kind":"bolt11_mint_quote","subId":"9bfe","filters":["quote1","quote2"]}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            request,
+            WsRequest::new(
+                WsMethod::Subscribe(Params {
+                    kind: SubscriptionKind::Bolt11MintQuote,
+                    filters: vec!["quote1".to_string(), "quote2".to_string()],
+                    sub_id: "9bfe".to_string(),
+                }),
+                0
+            )
+        );
+
+        let request: WsRequest = serde_json::from_str(
+            r#"{"jsonrpc":"2.0","id":1,"method":"unsubscribe","params":{"subId":"9bfe"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(request.method, WsMethod::Unsubscribe(_)));
+
+        assert_eq!(
+            serde_json::to_string(&WsResponse::ok(0, "9bfe".to_string())).unwrap(),
+            r#"{"jsonrpc":"2.0","result":{"status":"OK","subId":"9bfe"},"id":0}"#
+        );
+        let error: WsResponse = serde_json::from_str(
+            r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Too many subscriptions"},"id":2}"#,
+        )
+        .unwrap();
+        assert!(matches!(error.outcome, WsOutcome::Error(_)));
+
+        let notification: WsNotification = serde_json::from_str(
+            r#"{"jsonrpc":"2.0","method":"subscribe","params":{"subId":"9bfe","payload":{"quote":"quote1","request":"lnbc1","paid":true,"expiry":1701704757}}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            notification.params.payload,
+            NotificationPayload::MintQuote(_)
+        ));
+
+        let notification: WsNotification = serde_json::from_str(
+            r#"{"jsonrpc":"2.0","method":"subscribe","params":{"subId":"9bfe","payload":{"Y":"02599b9ea0a1ad4143706c2a5a4a568ce442dd4313e1cf1f7f0b58a317c1a355ee","state":"SPENT","witness":null}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            notification.params.payload.id(),
+            "02599b9ea0a1ad4143706c2a5a4a568ce442dd4313e1cf1f7f0b58a317c1a355ee"
+        );
+        assert!(matches!(
+            notification.params.payload,
+            NotificationPayload::ProofState(_)
+        ));
+    }
+}
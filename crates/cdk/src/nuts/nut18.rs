@@ -0,0 +1,182 @@
+//! NUT-18: Payment Requests
+//!
+//! <https://github.com/cashubtc/nuts/blob/main/18.md>
+
+use std::fmt;
+use std::str::FromStr;
+
+use base64::engine::{general_purpose, GeneralPurpose};
+use base64::{alphabet, Engine as _};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{CurrencyUnit, Proofs};
+use crate::mint_url::MintUrl;
+use crate::Amount;
+
+/// Prefix of an encoded [`PaymentRequest`]
+const PAYMENT_REQUEST_PREFIX: &str = "creqA";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Payment request does not start with `creqA`
+    #[error("Invalid payment request prefix")]
+    InvalidPrefix,
+    /// Base64 error
+    #[error(transparent)]
+    Base64Error(#[from] base64::DecodeError),
+    /// Ciborium error
+    #[error(transparent)]
+    CiboriumError(#[from] ciborium::de::Error<std::io::Error>),
+}
+
+/// Transport type of a [`Transport`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TransportType {
+    /// NIP-17 direct message to the `nprofile` target
+    #[serde(rename = "nostr")]
+    Nostr,
+    /// HTTP POST of the payload to the url target
+    #[serde(rename = "post")]
+    HttpPost,
+}
+
+impl fmt::Display for TransportType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Nostr => write!(f, "nostr"),
+            Self::HttpPost => write!(f, "post"),
+        }
+    }
+}
+
+/// Way to deliver the payment of a [`PaymentRequest`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Transport {
+    /// Type
+    #[serde(rename = "t")]
+    pub _type: TransportType,
+    /// `nprofile` or url the payment is sent to
+    #[serde(rename = "a")]
+    pub target: String,
+    /// Tags
+    #[serde(rename = "g", skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<Vec<String>>>,
+}
+
+/// Payment Request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaymentRequest {
+    /// Payment id
+    #[serde(rename = "i", skip_serializing_if = "Option::is_none")]
+    pub payment_id: Option<String>,
+    /// Amount
+    #[serde(rename = "a", skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Amount>,
+    /// Unit
+    #[serde(rename = "u", skip_serializing_if = "Option::is_none")]
+    pub unit: Option<CurrencyUnit>,
+    /// Single use
+    #[serde(rename = "s", skip_serializing_if = "Option::is_none")]
+    pub single_use: Option<bool>,
+    /// Mints the payment is accepted from, any mint if not set
+    #[serde(rename = "m", skip_serializing_if = "Option::is_none")]
+    pub mints: Option<Vec<MintUrl>>,
+    /// Description
+    #[serde(rename = "d", skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Transports, in order of preference
+    #[serde(rename = "t", default)]
+    pub transports: Vec<Transport>,
+}
+
+impl PaymentRequest {
+    /// Check if a payment from `mint_url` is accepted
+    pub fn accepts_mint(&self, mint_url: &MintUrl) -> bool {
+        match &self.mints {
+            Some(mints) => mints.contains(mint_url),
+            None => true,
+        }
+    }
+}
+
+impl fmt::Display for PaymentRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut data = Vec::new();
+        ciborium::into_writer(self, &mut data).map_err(|_| fmt::Error)?;
+        let encoded = general_purpose::URL_SAFE.encode(data);
+        write!(f, "{}{}", PAYMENT_REQUEST_PREFIX, encoded)
+    }
+}
+
+impl FromStr for PaymentRequest {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s
+            .trim()
+            .strip_prefix(PAYMENT_REQUEST_PREFIX)
+            .ok_or(Error::InvalidPrefix)?;
+
+        let decode_config = general_purpose::GeneralPurposeConfig::new()
+            .with_decode_padding_mode(base64::engine::DecodePaddingMode::Indifferent);
+        let decoded = GeneralPurpose::new(&alphabet::URL_SAFE, decode_config).decode(s)?;
+
+        Ok(ciborium::from_reader(&decoded[..])?)
+    }
+}
+
+/// Payment sent to a [`Transport`] of a [`PaymentRequest`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaymentRequestPayload {
+    /// Payment id of the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Memo
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    /// Mint of the proofs
+    pub mint: MintUrl,
+    /// Unit of the proofs
+    pub unit: CurrencyUnit,
+    /// Proofs
+    pub proofs: Proofs,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payment_request_round_trip() {
+        let request = PaymentRequest {
+            payment_id: Some("b7a90176".to_string()),
+            amount: Some(Amount::from(10)),
+            unit: Some(CurrencyUnit::Sat),
+            single_use: None,
+            mints: Some(vec![MintUrl::from_str(
+                "https://nofees.testnut.cashu.space",
+            )
+            .unwrap()]),
+            description: None,
+            transports: vec![Transport {
+                _type: TransportType::HttpPost,
+                target: "https://shop.example.com/pay".to_string(),
+                tags: None,
+            }],
+        };
+
+        let encoded = request.to_string();
+        assert!(encoded.starts_with("creqA"));
+        assert_eq!(PaymentRequest::from_str(&encoded).unwrap(), request);
+
+        assert!(
+            request.accepts_mint(&MintUrl::from_str("https://nofees.testnut.cashu.space").unwrap())
+        );
+        assert!(!request.accepts_mint(&MintUrl::from_str("https://other.mint").unwrap()));
+        assert!(matches!(
+            PaymentRequest::from_str("cashuAxyz"),
+            Err(Error::InvalidPrefix)
+        ));
+    }
+}
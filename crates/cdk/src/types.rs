@@ -1,12 +1,20 @@
 //! Types
 
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::error::Error;
-use crate::nuts::{CurrencyUnit, Proof, Proofs, PublicKey, SpendingConditions, State};
-use crate::url::UncheckedUrl;
-use crate::Amount;
+use crate::mint_url::MintUrl;
+use crate::nuts::{
+    CurrencyUnit, Id, MeltBolt11Response, MeltQuoteState, PreMintSecrets, Proof, Proofs, PublicKey,
+    SpendingConditions, State,
+};
+use crate::util::unix_time;
+use crate::{Amount, Bolt11Invoice};
 
 /// Melt response with proofs
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Default, Serialize, Deserialize)]
@@ -29,17 +37,20 @@ pub enum InvoiceStatus {
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MintQuote {
     pub id: String,
-    pub mint_url: UncheckedUrl,
+    pub mint_url: MintUrl,
     pub amount: Amount,
     pub unit: CurrencyUnit,
     pub request: String,
     pub paid: bool,
     pub expiry: u64,
+    /// Price of one bitcoin in `unit` locked for fiat quotes
+    #[serde(default)]
+    pub exchange_rate: Option<u64>,
 }
 
 impl MintQuote {
     pub fn new(
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         request: String,
         unit: CurrencyUnit,
         amount: Amount,
@@ -55,20 +66,87 @@ impl MintQuote {
             request,
             paid: false,
             expiry,
+            exchange_rate: None,
         }
     }
+
+    /// Payment hash of the bolt11 request, `None` if it is not a bolt11 invoice
+    pub fn payment_hash(&self) -> Option<String> {
+        Bolt11Invoice::from_str(&self.request)
+            .ok()
+            .map(|invoice| invoice.payment_hash().to_string())
+    }
+}
+
+/// Outputs of a mint request that may have been signed
+///
+/// Stored before the outputs are sent to the mint, so that the proofs can be
+/// rebuilt if the wallet stops before it stored them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingMint {
+    pub quote_id: String,
+    pub mint_url: MintUrl,
+    pub unit: CurrencyUnit,
+    pub premint_secrets: PreMintSecrets,
 }
 
 /// Melt Quote Info
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "StoredMeltQuote")]
 pub struct MeltQuote {
     pub id: String,
     pub unit: CurrencyUnit,
     pub amount: Amount,
     pub request: String,
     pub fee_reserve: Amount,
-    pub paid: bool,
+    pub state: MeltQuoteState,
     pub expiry: u64,
+    /// Price of one bitcoin in `unit` locked for fiat quotes
+    #[serde(default)]
+    pub exchange_rate: Option<u64>,
+    /// `Y`s of the inputs the quote is pending with
+    #[serde(default)]
+    pub pending_inputs: Vec<PublicKey>,
+}
+
+/// [`MeltQuote`] as stored before it had a state
+#[derive(Deserialize)]
+struct StoredMeltQuote {
+    id: String,
+    unit: CurrencyUnit,
+    amount: Amount,
+    request: String,
+    fee_reserve: Amount,
+    #[serde(default)]
+    paid: bool,
+    state: Option<MeltQuoteState>,
+    expiry: u64,
+    #[serde(default)]
+    exchange_rate: Option<u64>,
+    #[serde(default)]
+    pending_inputs: Vec<PublicKey>,
+}
+
+impl From<StoredMeltQuote> for MeltQuote {
+    fn from(quote: StoredMeltQuote) -> Self {
+        let state = match (quote.state, quote.paid) {
+            (Some(state), _) => state,
+            (None, true) => MeltQuoteState::Paid,
+            (None, false) => MeltQuoteState::Unpaid,
+        };
+
+        Self {
+            id: quote.id,
+            unit: quote.unit,
+            amount: quote.amount,
+            request: quote.request,
+            fee_reserve: quote.fee_reserve,
+            state,
+            expiry: quote.expiry,
+            exchange_rate: quote.exchange_rate,
+            pending_inputs: quote.pending_inputs,
+        }
+    }
 }
 
 impl MeltQuote {
@@ -87,8 +165,106 @@ impl MeltQuote {
             unit,
             request,
             fee_reserve,
-            paid: false,
+            state: MeltQuoteState::Unpaid,
             expiry,
+            exchange_rate: None,
+            pending_inputs: Vec::new(),
+        }
+    }
+}
+
+/// Completed melt
+///
+/// Kept by the mint so a melt quote is only executed once and retries of the
+/// request get the original response. The fees are used for accounting.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompletedMelt {
+    pub quote_id: String,
+    /// Y of every input
+    pub inputs: Vec<PublicKey>,
+    pub response: MeltBolt11Response,
+    /// Keyset the change is signed with
+    ///
+    /// The active keyset of the quote unit when the melt was executed, not the
+    /// keyset of the inputs, which may differ. The fees are accounted to it.
+    #[serde(default, alias = "keyset_id")]
+    pub change_keyset_id: Option<Id>,
+    /// Lightning fee paid for the invoice
+    #[serde(default)]
+    pub fee_paid: Amount,
+    /// Change kept by the mint because too few outputs were provided
+    #[serde(default)]
+    pub burnt_change: Amount,
+    /// Unix time the melt was executed
+    #[serde(default)]
+    pub time: u64,
+}
+
+/// Kind of wallet transaction
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionKind {
+    /// Ecash minted for a paid invoice
+    Mint,
+    /// Ecash melted to pay an invoice
+    Melt,
+    /// Token sent
+    Send,
+    /// Token received
+    Receive,
+}
+
+impl fmt::Display for TransactionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mint => write!(f, "mint"),
+            Self::Melt => write!(f, "melt"),
+            Self::Send => write!(f, "send"),
+            Self::Receive => write!(f, "receive"),
+        }
+    }
+}
+
+/// Wallet transaction
+///
+/// Recorded by the wallet for every mint, melt, send and receive.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transaction {
+    pub id: String,
+    pub mint_url: MintUrl,
+    pub kind: TransactionKind,
+    /// Amount minted, paid, sent or received, without fees
+    pub amount: Amount,
+    /// Fees paid on top of `amount`
+    pub fee: Amount,
+    pub unit: CurrencyUnit,
+    pub memo: Option<String>,
+    /// Metadata of the sent or received token
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+    /// Unix time of the transaction
+    pub timestamp: u64,
+}
+
+impl Transaction {
+    pub fn new(
+        mint_url: MintUrl,
+        kind: TransactionKind,
+        amount: Amount,
+        fee: Amount,
+        unit: CurrencyUnit,
+        memo: Option<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            mint_url,
+            kind,
+            amount,
+            fee,
+            unit,
+            memo,
+            metadata: BTreeMap::new(),
+            timestamp: unix_time(),
         }
     }
 }
@@ -97,7 +273,7 @@ impl MeltQuote {
 pub struct ProofInfo {
     pub proof: Proof,
     pub y: PublicKey,
-    pub mint_url: UncheckedUrl,
+    pub mint_url: MintUrl,
     pub state: State,
     pub spending_condition: Option<SpendingConditions>,
     pub unit: CurrencyUnit,
@@ -106,7 +282,7 @@ pub struct ProofInfo {
 impl ProofInfo {
     pub fn new(
         proof: Proof,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         state: State,
         unit: CurrencyUnit,
     ) -> Result<Self, Error> {
@@ -128,7 +304,7 @@ impl ProofInfo {
 
     pub fn matches_conditions(
         &self,
-        mint_url: &Option<UncheckedUrl>,
+        mint_url: &Option<MintUrl>,
         unit: &Option<CurrencyUnit>,
         state: &Option<Vec<State>>,
         spending_conditions: &Option<Vec<SpendingConditions>>,
@@ -9,6 +9,7 @@ use instant::SystemTime;
 use once_cell::sync::Lazy;
 
 pub mod hex;
+pub mod runtime;
 
 #[cfg(target_arch = "wasm32")]
 const UNIX_EPOCH: SystemTime = SystemTime::UNIX_EPOCH;
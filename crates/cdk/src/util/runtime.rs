@@ -0,0 +1,86 @@
+//! Async runtime shim
+//!
+//! Timers and background tasks of the wallet go through [`sleep`],
+//! [`Instant`] and [`spawn`], backed by tokio on native targets and by the JavaScript event
+//! loop on WASM, so the wallet does not need a tokio runtime in the browser.
+//! Only the runtime independent `sync` primitives of tokio are used on WASM.
+
+use std::future::Future;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+pub use std::time::Instant;
+
+#[cfg(target_arch = "wasm32")]
+pub use instant::Instant;
+
+/// Wait for `duration`
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await
+}
+
+/// Wait for `duration`
+///
+/// Resolves a promise with the `setTimeout` of the global scope, so it works
+/// in windows and workers.
+#[cfg(target_arch = "wasm32")]
+pub async fn sleep(duration: Duration) {
+    use wasm_bindgen::JsCast;
+
+    let millis = duration.as_millis().min(i32::MAX as u128) as i32;
+    let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+        let global = js_sys::global();
+        let set_timeout = js_sys::Reflect::get(&global, &"setTimeout".into())
+            .ok()
+            .and_then(|f| f.dyn_into::<js_sys::Function>().ok());
+
+        match set_timeout {
+            Some(set_timeout) => {
+                let _ = set_timeout.call2(&global, &resolve, &millis.into());
+            }
+            None => {
+                tracing::warn!("No setTimeout in global scope, not sleeping");
+                let _ = resolve.call0(&global);
+            }
+        }
+    });
+
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+/// Run `future` in the background
+///
+/// Must be called from within a tokio runtime on native targets.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(future);
+}
+
+/// Run `future` in the background
+#[cfg(target_arch = "wasm32")]
+pub fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + 'static,
+{
+    wasm_bindgen_futures::spawn_local(future);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_spawn_sleep() {
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+
+        spawn(async move {
+            sleep(Duration::from_millis(10)).await;
+            let _ = sender.send(42);
+        });
+
+        assert_eq!(receiver.await.unwrap(), 42);
+    }
+}
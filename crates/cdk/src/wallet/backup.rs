@@ -0,0 +1,443 @@
+//! Encrypted wallet backup
+//!
+//! [`Wallet::export_backup`] collects the wallet database into a
+//! [`WalletBackup`] and encrypts it with ChaCha20-Poly1305 under a key derived
+//! from a passphrase with PBKDF2-HMAC-SHA256. The encoded blob is
+//! `version || salt || nonce || ciphertext` as url safe base64.
+
+use std::collections::{HashMap, HashSet};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use bitcoin::secp256k1::rand::{self, RngCore};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use pbkdf2::pbkdf2_hmac;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use thiserror::Error;
+use tracing::instrument;
+use zeroize::Zeroize;
+
+use super::Wallet;
+use crate::cdk_database;
+use crate::mint_url::MintUrl;
+use crate::nuts::{Id, KeySetInfo, MintInfo, PublicKey, State};
+use crate::types::{MintQuote, ProofInfo, Transaction};
+
+/// Current backup format version
+pub const BACKUP_VERSION: u8 = 1;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const PBKDF2_ROUNDS: u32 = 100_000;
+
+/// Backup Error
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Backup was created by a newer version
+    #[error("Unsupported backup version `{0}`")]
+    UnsupportedVersion(u8),
+    /// Backup is too short to be valid
+    #[error("Invalid backup")]
+    InvalidBackup,
+    /// Wrong passphrase or corrupted backup
+    #[error("Could not decrypt backup")]
+    Decrypt,
+    /// Encryption failed
+    #[error("Could not encrypt backup")]
+    Encrypt,
+    /// Base64 Error
+    #[error(transparent)]
+    Base64(#[from] base64::DecodeError),
+    /// Serde Error
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+    /// Database Error
+    #[error(transparent)]
+    Database(#[from] cdk_database::Error),
+}
+
+/// Wallet data needed to move a wallet to another device
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalletBackup {
+    /// Mints and their last known info
+    pub mints: HashMap<MintUrl, Option<MintInfo>>,
+    /// Keysets of each mint
+    pub keysets: HashMap<MintUrl, Vec<KeySetInfo>>,
+    /// Derivation counters per keyset
+    pub counters: HashMap<Id, u32>,
+    /// All proofs with their state
+    pub proofs: Vec<ProofInfo>,
+    /// Mint quotes that have not been claimed yet
+    pub mint_quotes: Vec<MintQuote>,
+    /// Transaction history, empty in backups made before it was included
+    #[serde(default)]
+    pub transactions: Vec<Transaction>,
+}
+
+impl WalletBackup {
+    /// Encrypt backup with `passphrase`
+    pub fn encrypt(&self, passphrase: &str) -> Result<String, Error> {
+        let mut rng = rand::thread_rng();
+
+        let mut salt = [0u8; SALT_LEN];
+        rng.fill_bytes(&mut salt);
+        let mut nonce = [0u8; NONCE_LEN];
+        rng.fill_bytes(&mut nonce);
+
+        let mut plaintext = serde_json::to_vec(self)?;
+
+        let cipher = cipher(passphrase, &salt);
+        let ciphertext = cipher.encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &plaintext,
+                aad: &[BACKUP_VERSION],
+            },
+        );
+        plaintext.zeroize();
+
+        let ciphertext = ciphertext.map_err(|_| Error::Encrypt)?;
+
+        let mut blob = Vec::with_capacity(1 + SALT_LEN + NONCE_LEN + ciphertext.len());
+        blob.push(BACKUP_VERSION);
+        blob.extend_from_slice(&salt);
+        blob.extend_from_slice(&nonce);
+        blob.extend_from_slice(&ciphertext);
+
+        Ok(URL_SAFE_NO_PAD.encode(blob))
+    }
+
+    /// Decrypt backup created with [`WalletBackup::encrypt`]
+    pub fn decrypt(backup: &str, passphrase: &str) -> Result<Self, Error> {
+        let blob = URL_SAFE_NO_PAD.decode(backup.trim())?;
+
+        let (version, rest) = blob.split_first().ok_or(Error::InvalidBackup)?;
+        if *version != BACKUP_VERSION {
+            return Err(Error::UnsupportedVersion(*version));
+        }
+
+        if rest.len() < SALT_LEN + NONCE_LEN {
+            return Err(Error::InvalidBackup);
+        }
+        let (salt, rest) = rest.split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+        let cipher = cipher(passphrase, salt);
+        let mut plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &[*version],
+                },
+            )
+            .map_err(|_| Error::Decrypt)?;
+
+        let backup = serde_json::from_slice(&plaintext);
+        plaintext.zeroize();
+
+        Ok(backup?)
+    }
+}
+
+/// Cipher keyed with PBKDF2-HMAC-SHA256 of `passphrase`
+fn cipher(passphrase: &str, salt: &[u8]) -> ChaCha20Poly1305 {
+    let mut key = [0u8; 32];
+    pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    key.zeroize();
+
+    cipher
+}
+
+impl Wallet {
+    /// Collect wallet data into a [`WalletBackup`]
+    #[instrument(skip(self))]
+    pub async fn backup(&self) -> Result<WalletBackup, Error> {
+        let mut backup = WalletBackup {
+            mints: self.localstore.get_mints().await?,
+            proofs: self
+                .localstore
+                .get_proofs(None, None, None, None)
+                .await?
+                .unwrap_or_default(),
+            mint_quotes: self.localstore.get_mint_quotes().await?,
+            transactions: self.localstore.get_transactions().await?,
+            ..Default::default()
+        };
+
+        for mint_url in backup.mints.keys() {
+            if let Some(keysets) = self.localstore.get_mint_keysets(mint_url.clone()).await? {
+                for keyset in keysets.iter() {
+                    if let Some(counter) = self.localstore.get_keyset_counter(&keyset.id).await? {
+                        backup.counters.insert(keyset.id, counter);
+                    }
+                }
+
+                backup.keysets.insert(mint_url.clone(), keysets);
+            }
+        }
+
+        Ok(backup)
+    }
+
+    /// Export wallet as an encrypted backup
+    #[instrument(skip_all)]
+    pub async fn export_backup(&self, passphrase: &str) -> Result<String, Error> {
+        self.backup().await?.encrypt(passphrase)
+    }
+
+    /// Import wallet data from an encrypted backup
+    ///
+    /// Data is merged into the wallet: keyset counters only move forward,
+    /// proofs already in the wallet keep their state, unless the backup has
+    /// them spent, and transactions already in the history are skipped.
+    #[instrument(skip_all)]
+    pub async fn import_backup(&self, backup: &str, passphrase: &str) -> Result<(), Error> {
+        let backup = WalletBackup::decrypt(backup, passphrase)?;
+
+        for (mint_url, mint_info) in backup.mints {
+            self.localstore.add_mint(mint_url, mint_info).await?;
+        }
+
+        for (mint_url, keysets) in backup.keysets {
+            self.localstore.add_mint_keysets(mint_url, keysets).await?;
+        }
+
+        for (keyset_id, counter) in backup.counters {
+            let current = self
+                .localstore
+                .get_keyset_counter(&keyset_id)
+                .await?
+                .unwrap_or_default();
+
+            if counter > current {
+                self.localstore
+                    .increment_keyset_counter(&keyset_id, counter - current)
+                    .await?;
+            }
+        }
+
+        for quote in backup.mint_quotes {
+            self.localstore.add_mint_quote(quote).await?;
+        }
+
+        let stored: HashMap<PublicKey, State> = self
+            .localstore
+            .get_proofs(None, None, None, None)
+            .await?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| (p.y, p.state))
+            .collect();
+
+        let mut proofs = Vec::new();
+        for proof in backup.proofs {
+            match stored.get(&proof.y) {
+                None => proofs.push(proof),
+                // Spent is final, any other backed up state may be outdated
+                Some(state) if proof.state == State::Spent && *state != State::Spent => {
+                    self.localstore
+                        .set_proof_state(proof.y, State::Spent)
+                        .await?;
+                }
+                Some(_) => (),
+            }
+        }
+        self.localstore.add_proofs(proofs).await?;
+
+        let stored: HashSet<String> = self
+            .localstore
+            .get_transactions()
+            .await?
+            .into_iter()
+            .map(|t| t.id)
+            .collect();
+        for transaction in backup.transactions {
+            if !stored.contains(&transaction.id) {
+                self.localstore.add_transaction(transaction).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::cdk_database::WalletMemoryDatabase;
+    use crate::nuts::{CurrencyUnit, Token};
+    use crate::Amount;
+
+    const TOKEN: &str = "cashuAeyJ0b2tlbiI6W3sibWludCI6Imh0dHBzOi8vODMzMy5zcGFjZTozMzM4IiwicHJvb2ZzIjpbeyJhbW91bnQiOjIsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6IjQwNzkxNWJjMjEyYmU2MWE3N2UzZTZkMmFlYjRjNzI3OTgwYmRhNTFjZDA2YTZhZmMyOWUyODYxNzY4YTc4MzciLCJDIjoiMDJiYzkwOTc5OTdkODFhZmIyY2M3MzQ2YjVlNDM0NWE5MzQ2YmQyYTUwNmViNzk1ODU5OGE3MmYwY2Y4NTE2M2VhIn0seyJhbW91bnQiOjgsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6ImZlMTUxMDkzMTRlNjFkNzc1NmIwZjhlZTBmMjNhNjI0YWNhYTNmNGUwNDJmNjE0MzNjNzI4YzcwNTdiOTMxYmUiLCJDIjoiMDI5ZThlNTA1MGI4OTBhN2Q2YzA5NjhkYjE2YmMxZDVkNWZhMDQwZWExZGUyODRmNmVjNjlkNjEyOTlmNjcxMDU5In1dfV0sInVuaXQiOiJzYXQiLCJtZW1vIjoiVGhhbmsgeW91LiJ9";
+
+    fn create_wallet() -> Wallet {
+        let localstore = WalletMemoryDatabase::new(
+            vec![],
+            vec![],
+            vec![],
+            HashMap::new(),
+            #[cfg(feature = "nostr")]
+            HashMap::new(),
+        );
+
+        Wallet::new(Arc::new(localstore), &[0u8; 32], vec![])
+    }
+
+    #[tokio::test]
+    async fn test_export_import_backup() {
+        let wallet = create_wallet();
+
+        let token = Token::from_str(TOKEN).unwrap();
+        let mint_url = token.token[0].mint.clone();
+        let keyset = KeySetInfo {
+            id: token.token[0].proofs[0].keyset_id,
+            unit: CurrencyUnit::Sat,
+            active: true,
+            final_expiry: None,
+        };
+
+        wallet
+            .localstore
+            .add_mint(mint_url.clone(), None)
+            .await
+            .unwrap();
+        wallet
+            .localstore
+            .add_mint_keysets(mint_url.clone(), vec![keyset.clone()])
+            .await
+            .unwrap();
+        wallet
+            .localstore
+            .increment_keyset_counter(&keyset.id, 7)
+            .await
+            .unwrap();
+        let proofs = token.token[0]
+            .proofs
+            .iter()
+            .cloned()
+            .flat_map(|p| ProofInfo::new(p, mint_url.clone(), State::Unspent, CurrencyUnit::Sat))
+            .collect();
+        wallet.localstore.add_proofs(proofs).await.unwrap();
+        wallet
+            .localstore
+            .add_transaction(Transaction::new(
+                mint_url.clone(),
+                crate::types::TransactionKind::Receive,
+                Amount::from(10),
+                Amount::ZERO,
+                CurrencyUnit::Sat,
+                None,
+            ))
+            .await
+            .unwrap();
+
+        let exported = wallet.export_backup("correct horse").await.unwrap();
+
+        let other = create_wallet();
+        assert!(matches!(
+            other.import_backup(&exported, "wrong horse").await,
+            Err(Error::Decrypt)
+        ));
+
+        other
+            .import_backup(&exported, "correct horse")
+            .await
+            .unwrap();
+
+        let mut expected = wallet.backup().await.unwrap();
+        let mut imported = other.backup().await.unwrap();
+        expected.proofs.sort_by_key(|p| p.proof.amount);
+        imported.proofs.sort_by_key(|p| p.proof.amount);
+        assert_eq!(imported, expected);
+        assert_eq!(imported.counters.get(&keyset.id), Some(&7));
+        assert_eq!(imported.transactions.len(), 1);
+
+        // Importing again does not duplicate the history
+        other
+            .import_backup(&exported, "correct horse")
+            .await
+            .unwrap();
+        assert_eq!(other.localstore.get_transactions().await.unwrap().len(), 1);
+
+        // Backups made before the history was included
+        let old: WalletBackup = serde_json::from_str(
+            r#"{"mints":{},"keysets":{},"counters":{},"proofs":[],"mint_quotes":[]}"#,
+        )
+        .unwrap();
+        assert!(old.transactions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_import_keeps_stored_state() {
+        let token = Token::from_str(TOKEN).unwrap();
+        let mint_url = token.token[0].mint.clone();
+        let proof_info = |index: usize, state: State| {
+            ProofInfo::new(
+                token.token[0].proofs[index].clone(),
+                mint_url.clone(),
+                state,
+                CurrencyUnit::Sat,
+            )
+            .unwrap()
+        };
+
+        let backup = WalletBackup {
+            proofs: vec![proof_info(0, State::Unspent), proof_info(1, State::Spent)],
+            ..Default::default()
+        }
+        .encrypt("passphrase")
+        .unwrap();
+
+        // Proofs used since the backup was made
+        let wallet = create_wallet();
+        wallet
+            .localstore
+            .add_proofs(vec![
+                proof_info(0, State::Pending),
+                proof_info(1, State::Reserved),
+            ])
+            .await
+            .unwrap();
+
+        wallet.import_backup(&backup, "passphrase").await.unwrap();
+
+        let states: HashMap<PublicKey, State> = wallet
+            .localstore
+            .get_proofs(None, None, None, None)
+            .await
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|p| (p.y, p.state))
+            .collect();
+        assert_eq!(
+            states.get(&proof_info(0, State::Unspent).y),
+            Some(&State::Pending)
+        );
+        assert_eq!(
+            states.get(&proof_info(1, State::Unspent).y),
+            Some(&State::Spent)
+        );
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut blob = URL_SAFE_NO_PAD
+            .decode(WalletBackup::default().encrypt("passphrase").unwrap())
+            .unwrap();
+        blob[0] = BACKUP_VERSION + 1;
+
+        assert!(matches!(
+            WalletBackup::decrypt(&URL_SAFE_NO_PAD.encode(blob), "passphrase"),
+            Err(Error::UnsupportedVersion(_))
+        ));
+    }
+}
@@ -0,0 +1,215 @@
+//! Blocking wallet
+//!
+//! [`Wallet`] wraps an async [`super::Wallet`] and drives it on its own tokio
+//! runtime, for callers without an async runtime such as CLI tools, GUI event
+//! loops and scripts.
+//!
+//! The methods block the current thread until the operation finished, they
+//! panic if called from within an async runtime.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+
+use tokio::runtime::{Builder, Runtime};
+
+use super::Error;
+use crate::amount::SplitTarget;
+use crate::cdk_database::{self, WalletDatabase};
+use crate::mint_url::MintUrl;
+use crate::nuts::{
+    CurrencyUnit, MeltQuoteBolt11Response, MintInfo, MintQuoteBolt11Response, SecretKey,
+    SpendingConditions,
+};
+use crate::types::{MeltQuote, Melted, MintQuote};
+use crate::Amount;
+
+/// Synchronous wrapper of a [`super::Wallet`]
+#[derive(Clone)]
+pub struct Wallet {
+    inner: super::Wallet,
+    runtime: Arc<Runtime>,
+}
+
+impl Wallet {
+    /// Create new [`Wallet`]
+    pub fn new(
+        localstore: Arc<dyn WalletDatabase<Err = cdk_database::Error> + Send + Sync>,
+        seed: &[u8],
+        p2pk_signing_keys: Vec<SecretKey>,
+    ) -> Result<Self, Error> {
+        Self::from_wallet(super::Wallet::new(localstore, seed, p2pk_signing_keys))
+    }
+
+    /// Wrap an existing async wallet
+    pub fn from_wallet(wallet: super::Wallet) -> Result<Self, Error> {
+        let runtime = Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .map_err(|err| Error::Custom(format!("Could not start runtime: {}", err)))?;
+
+        Ok(Self {
+            inner: wallet,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// Async wallet driven by this wallet
+    pub fn inner(&self) -> &super::Wallet {
+        &self.inner
+    }
+
+    /// Run `future` to completion on the runtime of the wallet
+    pub fn block_on<F>(&self, future: F) -> F::Output
+    where
+        F: Future,
+    {
+        self.runtime.block_on(future)
+    }
+
+    /// See [`super::Wallet::unit_balance`]
+    pub fn unit_balance(&self, unit: CurrencyUnit) -> Result<Amount, Error> {
+        self.block_on(self.inner.unit_balance(unit))
+    }
+
+    /// See [`super::Wallet::total_balance`]
+    pub fn total_balance(&self) -> Result<HashMap<CurrencyUnit, Amount>, Error> {
+        self.block_on(self.inner.total_balance())
+    }
+
+    /// See [`super::Wallet::add_mint`]
+    pub fn add_mint(&self, mint_url: MintUrl) -> Result<Option<MintInfo>, Error> {
+        self.block_on(self.inner.add_mint(mint_url))
+    }
+
+    /// See [`super::Wallet::check_all_pending_proofs`]
+    pub fn check_all_pending_proofs(
+        &self,
+        mint_url: Option<MintUrl>,
+        unit: Option<CurrencyUnit>,
+    ) -> Result<Amount, Error> {
+        self.block_on(self.inner.check_all_pending_proofs(mint_url, unit))
+    }
+
+    /// See [`super::Wallet::mint_quote`]
+    pub fn mint_quote(
+        &self,
+        mint_url: MintUrl,
+        amount: Amount,
+        unit: CurrencyUnit,
+        description: Option<String>,
+    ) -> Result<MintQuote, Error> {
+        self.block_on(self.inner.mint_quote(mint_url, amount, unit, description))
+    }
+
+    /// See [`super::Wallet::mint_quote_status`]
+    pub fn mint_quote_status(
+        &self,
+        mint_url: MintUrl,
+        quote_id: &str,
+    ) -> Result<MintQuoteBolt11Response, Error> {
+        self.block_on(self.inner.mint_quote_status(mint_url, quote_id))
+    }
+
+    /// See [`super::Wallet::mint`]
+    pub fn mint(
+        &self,
+        mint_url: MintUrl,
+        quote_id: &str,
+        amount_split_target: SplitTarget,
+        spending_conditions: Option<SpendingConditions>,
+    ) -> Result<Amount, Error> {
+        self.block_on(
+            self.inner
+                .mint(mint_url, quote_id, amount_split_target, spending_conditions),
+        )
+    }
+
+    /// See [`super::Wallet::send`]
+    pub fn send(
+        &self,
+        mint_url: &MintUrl,
+        unit: CurrencyUnit,
+        memo: Option<String>,
+        amount: Amount,
+        amount_split_target: &SplitTarget,
+        conditions: Option<SpendingConditions>,
+    ) -> Result<String, Error> {
+        self.block_on(self.inner.send(
+            mint_url,
+            unit,
+            memo,
+            amount,
+            amount_split_target,
+            conditions,
+        ))
+    }
+
+    /// See [`super::Wallet::melt_quote`]
+    pub fn melt_quote(
+        &self,
+        mint_url: MintUrl,
+        unit: CurrencyUnit,
+        request: String,
+    ) -> Result<MeltQuote, Error> {
+        self.block_on(self.inner.melt_quote(mint_url, unit, request))
+    }
+
+    /// See [`super::Wallet::melt_quote_status`]
+    pub fn melt_quote_status(
+        &self,
+        mint_url: MintUrl,
+        quote_id: &str,
+    ) -> Result<MeltQuoteBolt11Response, Error> {
+        self.block_on(self.inner.melt_quote_status(mint_url, quote_id))
+    }
+
+    /// See [`super::Wallet::melt`]
+    pub fn melt(
+        &self,
+        mint_url: &MintUrl,
+        quote_id: &str,
+        amount_split_target: SplitTarget,
+    ) -> Result<Melted, Error> {
+        self.block_on(self.inner.melt(mint_url, quote_id, amount_split_target))
+    }
+
+    /// See [`super::Wallet::receive`]
+    pub fn receive(
+        &self,
+        encoded_token: &str,
+        amount_split_target: &SplitTarget,
+        preimages: Option<Vec<String>>,
+    ) -> Result<Amount, Error> {
+        self.block_on(
+            self.inner
+                .receive(encoded_token, amount_split_target, preimages),
+        )
+    }
+
+    /// See [`super::Wallet::restore`]
+    pub fn restore(&self, mint_url: MintUrl) -> Result<Amount, Error> {
+        self.block_on(self.inner.restore(mint_url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cdk_database::WalletMemoryDatabase;
+
+    #[test]
+    fn test_blocking_wallet() {
+        let localstore = WalletMemoryDatabase::default();
+        let wallet = Wallet::new(Arc::new(localstore), &[0u8; 32], vec![]).unwrap();
+
+        assert!(wallet.total_balance().unwrap().is_empty());
+        assert_eq!(
+            wallet.unit_balance(CurrencyUnit::Sat).unwrap(),
+            Amount::ZERO
+        );
+        assert!(wallet
+            .receive("cashuAinvalid", &SplitTarget::None, None)
+            .is_err());
+    }
+}
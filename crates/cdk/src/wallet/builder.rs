@@ -0,0 +1,183 @@
+//! Wallet builder
+//!
+//! [`WalletBuilder`] creates a [`Wallet`] from named settings with defaults,
+//! and checks them in [`WalletBuilder::build`].
+
+use std::sync::Arc;
+
+use lightning_invoice::Currency;
+use zeroize::Zeroize;
+
+use super::client::Client;
+use super::policy::SpendPolicy;
+use super::{Error, Wallet};
+use crate::cdk_database::{self, WalletDatabase, WalletMemoryDatabase};
+use crate::exchange_rate::ExchangeRate;
+use crate::mint_url::MintUrl;
+use crate::nuts::SecretKey;
+use crate::Mnemonic;
+
+/// Seed lengths allowed by BIP-32
+const SEED_LEN: std::ops::RangeInclusive<usize> = 16..=64;
+
+/// Builder for [`Wallet`]
+///
+/// Only the seed is required. The wallet stores its data in memory unless a
+/// [`WalletBuilder::localstore`] is set, and talks to mints over HTTP unless a
+/// [`WalletBuilder::client`] is set.
+#[derive(Default)]
+pub struct WalletBuilder {
+    seed: Option<Vec<u8>>,
+    localstore: Option<Arc<dyn WalletDatabase<Err = cdk_database::Error> + Send + Sync>>,
+    client: Option<Arc<dyn Client + Send + Sync>>,
+    mint_url: Option<MintUrl>,
+    p2pk_signing_keys: Vec<SecretKey>,
+    spend_policy: Option<Arc<SpendPolicy>>,
+    exchange_rate: Option<Arc<dyn ExchangeRate + Send + Sync>>,
+    network: Option<Currency>,
+    swap_inactive_keysets: bool,
+}
+
+impl WalletBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn seed(mut self, seed: &[u8]) -> Self {
+        if let Some(mut old) = self.seed.replace(seed.to_vec()) {
+            old.zeroize();
+        }
+        self
+    }
+
+    /// Derive the seed from a BIP-39 mnemonic
+    pub fn mnemonic(self, mnemonic: &Mnemonic) -> Self {
+        let mut seed = mnemonic.to_seed_normalized("");
+        let builder = self.seed(&seed);
+        seed.zeroize();
+        builder
+    }
+
+    pub fn localstore(
+        mut self,
+        localstore: Arc<dyn WalletDatabase<Err = cdk_database::Error> + Send + Sync>,
+    ) -> Self {
+        self.localstore = Some(localstore);
+        self
+    }
+
+    pub fn client(mut self, client: Arc<dyn Client + Send + Sync>) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Mint the wallet uses, added to the database without contacting it
+    pub fn mint_url(mut self, mint_url: MintUrl) -> Self {
+        self.mint_url = Some(mint_url);
+        self
+    }
+
+    /// Key used to sign P2PK locked proofs on receive
+    pub fn p2pk_signing_key(mut self, secret_key: SecretKey) -> Self {
+        self.p2pk_signing_keys.push(secret_key);
+        self
+    }
+
+    pub fn spend_policy(mut self, spend_policy: SpendPolicy) -> Self {
+        self.spend_policy = Some(Arc::new(spend_policy));
+        self
+    }
+
+    pub fn exchange_rate(mut self, exchange_rate: Arc<dyn ExchangeRate + Send + Sync>) -> Self {
+        self.exchange_rate = Some(exchange_rate);
+        self
+    }
+
+    /// Only pay invoices of `network`
+    pub fn network(mut self, network: Currency) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    /// See [`Wallet::swap_inactive_keysets`]
+    pub fn swap_inactive_keysets(mut self, swap: bool) -> Self {
+        self.swap_inactive_keysets = swap;
+        self
+    }
+
+    /// Check the settings and create the [`Wallet`]
+    pub async fn build(mut self) -> Result<Wallet, Error> {
+        let mut seed = self
+            .seed
+            .take()
+            .ok_or_else(|| Error::InvalidBuilder("Seed required".to_string()))?;
+        if !SEED_LEN.contains(&seed.len()) {
+            let len = seed.len();
+            seed.zeroize();
+            return Err(Error::InvalidBuilder(format!(
+                "Seed of {} bytes, expected 16 to 64",
+                len
+            )));
+        }
+
+        let localstore = match self.localstore {
+            Some(localstore) => localstore,
+            None => Arc::new(WalletMemoryDatabase::default()),
+        };
+
+        if let Some(mint_url) = self.mint_url {
+            if !localstore.get_mints().await?.contains_key(&mint_url) {
+                localstore.add_mint(mint_url, None).await?;
+            }
+        }
+
+        let mut wallet = Wallet::new(localstore, &seed, self.p2pk_signing_keys);
+        seed.zeroize();
+
+        if let Some(client) = self.client {
+            wallet.client = client;
+        }
+        wallet.spend_policy = self.spend_policy;
+        wallet.exchange_rate = self.exchange_rate;
+        wallet.network = self.network;
+        wallet.swap_inactive_keysets = self.swap_inactive_keysets;
+
+        Ok(wallet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wallet_builder() {
+        let mint_url = MintUrl::from_str("https://mint.example.com").unwrap();
+        let wallet = WalletBuilder::new()
+            .seed(&[1u8; 32])
+            .mint_url(mint_url.clone())
+            .spend_policy(SpendPolicy::new())
+            .build()
+            .await
+            .unwrap();
+
+        assert!(wallet
+            .localstore
+            .get_mints()
+            .await
+            .unwrap()
+            .contains_key(&mint_url));
+        assert!(wallet.spend_policy.is_some());
+
+        assert!(matches!(
+            WalletBuilder::new().build().await,
+            Err(Error::InvalidBuilder(_))
+        ));
+        assert!(matches!(
+            WalletBuilder::new().seed(&[1u8; 8]).build().await,
+            Err(Error::InvalidBuilder(_))
+        ));
+    }
+}
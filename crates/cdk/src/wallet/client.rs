@@ -1,6 +1,14 @@
 //! Wallet client
 
-use reqwest::Client;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE, USER_AGENT as USER_AGENT_HEADER,
+};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json::Value;
 use tracing::instrument;
 use url::Url;
@@ -11,9 +19,10 @@ use crate::nuts::{
     BlindedMessage, CheckStateRequest, CheckStateResponse, CurrencyUnit, Id, KeySet, KeysResponse,
     KeysetResponse, MeltBolt11Request, MeltBolt11Response, MeltQuoteBolt11Request,
     MeltQuoteBolt11Response, MintBolt11Request, MintBolt11Response, MintInfo,
-    MintQuoteBolt11Request, MintQuoteBolt11Response, PreMintSecrets, Proof, PublicKey,
-    RestoreRequest, RestoreResponse, SwapRequest, SwapResponse,
+    MintQuoteBolt11Request, MintQuoteBolt11Response, PaymentRequestPayload, PreMintSecrets, Proof,
+    PublicKey, RestoreRequest, RestoreResponse, SwapRequest, SwapResponse,
 };
+use crate::wire::{self, ContentType};
 use crate::{Amount, Bolt11Invoice};
 
 fn join_url(url: Url, paths: &[&str]) -> Result<Url, Error> {
@@ -34,9 +43,358 @@ fn join_url(url: Url, paths: &[&str]) -> Result<Url, Error> {
     Ok(url)
 }
 
+/// Connection to a mint
+///
+/// [`HttpClient`] talks to a mint over its HTTP api, other implementations
+/// can connect the wallet to a mint over any transport. `Client` is
+/// implemented for every type implementing all connector traits.
+///
+/// Only [`KeysConnector`] is required, the methods of the other connectors
+/// fail with [`Error::UnsupportedOperation`] unless implemented, so a
+/// transport implements only what it supports and callers can detect missing
+/// capabilities by that error.
+pub trait Client:
+    KeysConnector
+    + QuoteConnector
+    + IssueConnector
+    + MeltConnector
+    + SwapConnector
+    + CheckStateConnector
+    + RestoreConnector
+    + PaymentRequestConnector
+{
+}
+
+impl<T> Client for T where
+    T: KeysConnector
+        + QuoteConnector
+        + IssueConnector
+        + MeltConnector
+        + SwapConnector
+        + CheckStateConnector
+        + RestoreConnector
+        + PaymentRequestConnector
+{
+}
+
+/// Keys and info of a mint
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait KeysConnector {
+    /// Get Active Mint Keys [NUT-01]
+    async fn get_mint_keys(&self, mint_url: Url) -> Result<Vec<KeySet>, Error>;
+    /// Get Keyset Keys [NUT-01]
+    async fn get_mint_keyset(&self, mint_url: Url, keyset_id: Id) -> Result<KeySet, Error>;
+    /// Get Keysets [NUT-02]
+    async fn get_mint_keysets(&self, mint_url: Url) -> Result<KeysetResponse, Error>;
+    /// Get Mint Info [NUT-06]
+    async fn get_mint_info(&self, mint_url: Url) -> Result<MintInfo, Error>;
+}
+
+/// Mint and melt quotes
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait QuoteConnector {
+    /// Mint Quote [NUT-04]
+    async fn post_mint_quote(
+        &self,
+        _mint_url: Url,
+        _amount: Amount,
+        _unit: CurrencyUnit,
+        _description: Option<String>,
+    ) -> Result<MintQuoteBolt11Response, Error> {
+        Err(Error::UnsupportedOperation("mint quote".to_string()))
+    }
+    /// Mint Quote status
+    async fn get_mint_quote_status(
+        &self,
+        _mint_url: Url,
+        _quote_id: &str,
+    ) -> Result<MintQuoteBolt11Response, Error> {
+        Err(Error::UnsupportedOperation("mint quote".to_string()))
+    }
+    /// Melt Quote [NUT-05]
+    async fn post_melt_quote(
+        &self,
+        _mint_url: Url,
+        _unit: CurrencyUnit,
+        _request: Bolt11Invoice,
+    ) -> Result<MeltQuoteBolt11Response, Error> {
+        Err(Error::UnsupportedOperation("melt quote".to_string()))
+    }
+    /// Melt Quote Status
+    async fn get_melt_quote_status(
+        &self,
+        _mint_url: Url,
+        _quote_id: &str,
+    ) -> Result<MeltQuoteBolt11Response, Error> {
+        Err(Error::UnsupportedOperation("melt quote".to_string()))
+    }
+}
+
+/// Minting of paid quotes
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait IssueConnector {
+    /// Mint Tokens [NUT-04]
+    async fn post_mint(
+        &self,
+        _mint_url: Url,
+        _quote: &str,
+        _premint_secrets: PreMintSecrets,
+    ) -> Result<MintBolt11Response, Error> {
+        Err(Error::UnsupportedOperation("mint".to_string()))
+    }
+}
+
+/// Melting of quotes
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait MeltConnector {
+    /// Melt [NUT-05]
+    /// [Nut-08] Lightning fee return if outputs defined
+    async fn post_melt(
+        &self,
+        _mint_url: Url,
+        _quote: String,
+        _inputs: Vec<Proof>,
+        _outputs: Option<Vec<BlindedMessage>>,
+    ) -> Result<MeltBolt11Response, Error> {
+        Err(Error::UnsupportedOperation("melt".to_string()))
+    }
+}
+
+/// Swaps
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait SwapConnector {
+    /// Split Token [NUT-06]
+    async fn post_swap(
+        &self,
+        _mint_url: Url,
+        _swap_request: SwapRequest,
+    ) -> Result<SwapResponse, Error> {
+        Err(Error::UnsupportedOperation("swap".to_string()))
+    }
+}
+
+/// Proof state checks
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait CheckStateConnector {
+    /// Spendable check [NUT-07]
+    async fn post_check_state(
+        &self,
+        _mint_url: Url,
+        _ys: Vec<PublicKey>,
+    ) -> Result<CheckStateResponse, Error> {
+        Err(Error::UnsupportedOperation("check state".to_string()))
+    }
+}
+
+/// Restore of signatures
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait RestoreConnector {
+    /// Restore [NUT-09]
+    async fn post_restore(
+        &self,
+        _mint_url: Url,
+        _request: RestoreRequest,
+    ) -> Result<RestoreResponse, Error> {
+        Err(Error::UnsupportedOperation("restore".to_string()))
+    }
+}
+
+/// Delivery of payments to the receivers of payment requests
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait PaymentRequestConnector {
+    /// Post a payment to the `http_post` transport of a request [NUT-18]
+    async fn post_payment_request(
+        &self,
+        _url: Url,
+        _payload: PaymentRequestPayload,
+    ) -> Result<(), Error> {
+        Err(Error::UnsupportedOperation("payment request".to_string()))
+    }
+}
+
+/// Transport of [`HttpClient`]
+///
+/// [`HttpClient`] builds the urls and request bodies of the mint api and
+/// decodes the responses, a transport only sends JSON requests. Every
+/// transport therefore supports the same endpoints with the same error
+/// handling. [`ReqwestTransport`] works on native and WASM targets.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait Transport {
+    /// Send a GET request and return the JSON response body
+    async fn get(&self, url: Url) -> Result<Value, Error>;
+
+    /// Send a POST request with a JSON `body` and return the JSON response body
+    async fn post(&self, url: Url, body: Value) -> Result<Value, Error>;
+
+    /// Send a POST request with a JSON `body` to a server other than a mint
+    ///
+    /// The response body is not a mint response and is ignored.
+    async fn send(&self, url: Url, body: Value) -> Result<(), Error> {
+        self.post(url, body).await.map(|_| ())
+    }
+}
+
+/// Default `User-Agent` of [`ReqwestTransport`]
+pub const USER_AGENT: &str = concat!("cdk/", env!("CARGO_PKG_VERSION"));
+
+/// [`Transport`] using [`reqwest`]
+///
+/// Every request carries the default headers, a `User-Agent` of
+/// [`USER_AGENT`] unless set with [`ReqwestTransport::user_agent`].
+/// Bodies are JSON unless CBOR is chosen with
+/// [`ReqwestTransport::content_type`].
 #[derive(Debug, Clone)]
-pub struct HttpClient {
-    inner: Client,
+pub struct ReqwestTransport {
+    inner: reqwest::Client,
+    headers: HeaderMap,
+    content_type: ContentType,
+}
+
+impl Default for ReqwestTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReqwestTransport {
+    /// Create new [`ReqwestTransport`]
+    pub fn new() -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT_HEADER, HeaderValue::from_static(USER_AGENT));
+
+        Self {
+            inner: reqwest::Client::new(),
+            headers,
+            content_type: ContentType::Json,
+        }
+    }
+
+    /// Set the `User-Agent` of requests
+    ///
+    /// E.g. `my-wallet/1.0 cdk/0.3.0` to identify the application to mints
+    /// and reverse proxies.
+    pub fn user_agent(self, user_agent: &str) -> Result<Self, Error> {
+        self.header(USER_AGENT_HEADER.as_str(), user_agent)
+    }
+
+    /// Add a header sent with every request
+    ///
+    /// Replaces an earlier value of the header.
+    pub fn header(mut self, name: &str, value: &str) -> Result<Self, Error> {
+        let name =
+            HeaderName::from_str(name).map_err(|_| Error::InvalidHeader(name.to_string()))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|_| Error::InvalidHeader(format!("{}: {}", name, value)))?;
+
+        self.headers.insert(name, value);
+
+        Ok(self)
+    }
+
+    /// Format of request bodies, and the preferred format of responses
+    ///
+    /// With [`ContentType::Cbor`] mints that do not support CBOR keep
+    /// answering with JSON, and a request body they reject with `415
+    /// Unsupported Media Type` is sent again as JSON.
+    pub fn content_type(mut self, content_type: ContentType) -> Self {
+        self.content_type = content_type;
+        self
+    }
+
+    fn accept(&self) -> &'static str {
+        match self.content_type {
+            ContentType::Json => wire::JSON,
+            ContentType::Cbor => "application/cbor, application/json;q=0.5",
+        }
+    }
+
+    fn post_request(
+        &self,
+        url: Url,
+        body: &Value,
+        content_type: ContentType,
+    ) -> Result<reqwest::RequestBuilder, Error> {
+        Ok(self
+            .inner
+            .post(url)
+            .headers(self.headers.clone())
+            .header(ACCEPT, self.accept())
+            .header(CONTENT_TYPE, content_type.media_type())
+            .body(content_type.encode(body)?))
+    }
+}
+
+/// Decode a response body in the format of its `Content-Type`, JSON if unset
+async fn decode_body(response: reqwest::Response) -> Result<Value, Error> {
+    let content_type = match response.headers().get(CONTENT_TYPE) {
+        Some(value) => ContentType::from_header(value.to_str().unwrap_or_default())?,
+        None => ContentType::Json,
+    };
+    let bytes = response.bytes().await?;
+
+    Ok(content_type.decode(&bytes)?)
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Transport for ReqwestTransport {
+    async fn get(&self, url: Url) -> Result<Value, Error> {
+        let response = self
+            .inner
+            .get(url)
+            .headers(self.headers.clone())
+            .header(ACCEPT, self.accept())
+            .send()
+            .await?;
+
+        decode_body(response).await
+    }
+
+    async fn post(&self, url: Url, body: Value) -> Result<Value, Error> {
+        let mut response = self
+            .post_request(url.clone(), &body, self.content_type)?
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::UNSUPPORTED_MEDIA_TYPE
+            && self.content_type != ContentType::Json
+        {
+            tracing::debug!("{} does not accept {}", url, self.content_type.media_type());
+            response = self
+                .post_request(url, &body, ContentType::Json)?
+                .send()
+                .await?;
+        }
+
+        decode_body(response).await
+    }
+
+    async fn send(&self, url: Url, body: Value) -> Result<(), Error> {
+        self.inner
+            .post(url)
+            .headers(self.headers.clone())
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// Client for the HTTP api of a mint
+#[derive(Debug, Clone)]
+pub struct HttpClient<T = ReqwestTransport> {
+    transport: T,
 }
 
 impl Default for HttpClient {
@@ -48,123 +406,142 @@ impl Default for HttpClient {
 impl HttpClient {
     pub fn new() -> Self {
         Self {
-            inner: Client::new(),
+            transport: ReqwestTransport::default(),
+        }
+    }
+
+    /// Create new [`HttpClient`] sending `user_agent` and `headers` with every
+    /// request
+    pub fn with_headers(user_agent: &str, headers: &[(&str, &str)]) -> Result<Self, Error> {
+        let mut transport = ReqwestTransport::new().user_agent(user_agent)?;
+        for (name, value) in headers {
+            transport = transport.header(name, value)?;
         }
+
+        Ok(Self::with_transport(transport))
+    }
+}
+
+impl<T> HttpClient<T>
+where
+    T: Transport,
+{
+    /// Create new [`HttpClient`] sending requests over `transport`
+    pub fn with_transport(transport: T) -> Self {
+        Self { transport }
+    }
+
+    async fn get<R>(&self, url: Url) -> Result<R, Error>
+    where
+        R: DeserializeOwned,
+    {
+        decode_response(self.transport.get(url).await?)
+    }
+
+    async fn post<B, R>(&self, url: Url, body: &B) -> Result<R, Error>
+    where
+        B: Serialize,
+        R: DeserializeOwned,
+    {
+        let body = serde_json::to_value(body)?;
+        decode_response(self.transport.post(url, body).await?)
+    }
+}
+
+/// Decode a response body, or the error response of the mint
+fn decode_response<R>(res: Value) -> Result<R, Error>
+where
+    R: DeserializeOwned,
+{
+    match serde_json::from_value::<R>(res.clone()) {
+        Ok(response) => Ok(response),
+        Err(_) => Err(ErrorResponse::from_value(res)?.into()),
     }
+}
 
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T> KeysConnector for HttpClient<T>
+where
+    T: Transport + Send + Sync,
+{
     /// Get Active Mint Keys [NUT-01]
     #[instrument(skip(self), fields(mint_url = %mint_url))]
-    pub async fn get_mint_keys(&self, mint_url: Url) -> Result<Vec<KeySet>, Error> {
+    async fn get_mint_keys(&self, mint_url: Url) -> Result<Vec<KeySet>, Error> {
         let url = join_url(mint_url, &["v1", "keys"])?;
-        let keys = self.inner.get(url).send().await?.json::<Value>().await?;
+        let keys_response: KeysResponse = self.get(url).await?;
 
-        match serde_json::from_value::<KeysResponse>(keys.clone()) {
-            Ok(keys_response) => Ok(keys_response.keysets),
-            Err(_) => Err(ErrorResponse::from_value(keys)?.into()),
-        }
+        Ok(keys_response.keysets)
     }
 
     /// Get Keyset Keys [NUT-01]
     #[instrument(skip(self), fields(mint_url = %mint_url))]
-    pub async fn get_mint_keyset(&self, mint_url: Url, keyset_id: Id) -> Result<KeySet, Error> {
+    async fn get_mint_keyset(&self, mint_url: Url, keyset_id: Id) -> Result<KeySet, Error> {
         let url = join_url(mint_url, &["v1", "keys", &keyset_id.to_string()])?;
-        let keys = self.inner.get(url).send().await?.json::<Value>().await?;
+        let keys_response: KeysResponse = self.get(url).await?;
 
-        match serde_json::from_value::<KeysResponse>(keys.clone()) {
-            Ok(keys_response) => Ok(keys_response.keysets[0].clone()),
-            Err(_) => Err(ErrorResponse::from_value(keys)?.into()),
-        }
+        keys_response
+            .keysets
+            .into_iter()
+            .next()
+            .ok_or(Error::KeysetNotFound)
     }
 
     /// Get Keysets [NUT-02]
     #[instrument(skip(self), fields(mint_url = %mint_url))]
-    pub async fn get_mint_keysets(&self, mint_url: Url) -> Result<KeysetResponse, Error> {
+    async fn get_mint_keysets(&self, mint_url: Url) -> Result<KeysetResponse, Error> {
         let url = join_url(mint_url, &["v1", "keysets"])?;
-        let res = self.inner.get(url).send().await?.json::<Value>().await?;
+        self.get(url).await
+    }
 
-        match serde_json::from_value::<KeysetResponse>(res.clone()) {
-            Ok(keyset_response) => Ok(keyset_response),
-            Err(_) => Err(ErrorResponse::from_value(res)?.into()),
-        }
+    /// Get Mint Info [NUT-06]
+    #[instrument(skip(self), fields(mint_url = %mint_url))]
+    async fn get_mint_info(&self, mint_url: Url) -> Result<MintInfo, Error> {
+        let url = join_url(mint_url, &["v1", "info"])?;
+        self.get(url).await
     }
+}
 
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T> QuoteConnector for HttpClient<T>
+where
+    T: Transport + Send + Sync,
+{
     /// Mint Quote [NUT-04]
     #[instrument(skip(self), fields(mint_url = %mint_url))]
-    pub async fn post_mint_quote(
+    async fn post_mint_quote(
         &self,
         mint_url: Url,
         amount: Amount,
         unit: CurrencyUnit,
+        description: Option<String>,
     ) -> Result<MintQuoteBolt11Response, Error> {
         let url = join_url(mint_url, &["v1", "mint", "quote", "bolt11"])?;
 
-        let request = MintQuoteBolt11Request { amount, unit };
-
-        let res = self
-            .inner
-            .post(url)
-            .json(&request)
-            .send()
-            .await?
-            .json::<Value>()
-            .await?;
+        let request = MintQuoteBolt11Request {
+            amount,
+            unit,
+            description,
+        };
 
-        match serde_json::from_value::<MintQuoteBolt11Response>(res.clone()) {
-            Ok(mint_quote_response) => Ok(mint_quote_response),
-            Err(_) => Err(ErrorResponse::from_value(res)?.into()),
-        }
+        self.post(url, &request).await
     }
 
     /// Mint Quote status
     #[instrument(skip(self), fields(mint_url = %mint_url))]
-    pub async fn get_mint_quote_status(
+    async fn get_mint_quote_status(
         &self,
         mint_url: Url,
         quote_id: &str,
     ) -> Result<MintQuoteBolt11Response, Error> {
         let url = join_url(mint_url, &["v1", "mint", "quote", "bolt11", quote_id])?;
-
-        let res = self.inner.get(url).send().await?.json::<Value>().await?;
-
-        match serde_json::from_value::<MintQuoteBolt11Response>(res.clone()) {
-            Ok(mint_quote_response) => Ok(mint_quote_response),
-            Err(_) => Err(ErrorResponse::from_value(res)?.into()),
-        }
-    }
-
-    /// Mint Tokens [NUT-04]
-    #[instrument(skip(self, quote, premint_secrets), fields(mint_url = %mint_url))]
-    pub async fn post_mint(
-        &self,
-        mint_url: Url,
-        quote: &str,
-        premint_secrets: PreMintSecrets,
-    ) -> Result<MintBolt11Response, Error> {
-        let url = join_url(mint_url, &["v1", "mint", "bolt11"])?;
-
-        let request = MintBolt11Request {
-            quote: quote.to_string(),
-            outputs: premint_secrets.blinded_messages(),
-        };
-
-        let res = self
-            .inner
-            .post(url)
-            .json(&request)
-            .send()
-            .await?
-            .json::<Value>()
-            .await?;
-
-        match serde_json::from_value::<MintBolt11Response>(res.clone()) {
-            Ok(mint_quote_response) => Ok(mint_quote_response),
-            Err(_) => Err(ErrorResponse::from_value(res)?.into()),
-        }
+        self.get(url).await
     }
 
     /// Melt Quote [NUT-05]
     #[instrument(skip(self), fields(mint_url = %mint_url))]
-    pub async fn post_melt_quote(
+    async fn post_melt_quote(
         &self,
         mint_url: Url,
         unit: CurrencyUnit,
@@ -174,42 +551,56 @@ impl HttpClient {
 
         let request = MeltQuoteBolt11Request { request, unit };
 
-        let res = self
-            .inner
-            .post(url)
-            .json(&request)
-            .send()
-            .await?
-            .json::<Value>()
-            .await?;
-
-        match serde_json::from_value::<MeltQuoteBolt11Response>(res.clone()) {
-            Ok(melt_quote_response) => Ok(melt_quote_response),
-            Err(_) => Err(ErrorResponse::from_value(res)?.into()),
-        }
+        self.post(url, &request).await
     }
 
     /// Melt Quote Status
     #[instrument(skip(self), fields(mint_url = %mint_url))]
-    pub async fn get_melt_quote_status(
+    async fn get_melt_quote_status(
         &self,
         mint_url: Url,
         quote_id: &str,
     ) -> Result<MeltQuoteBolt11Response, Error> {
         let url = join_url(mint_url, &["v1", "melt", "quote", "bolt11", quote_id])?;
+        self.get(url).await
+    }
+}
 
-        let res = self.inner.get(url).send().await?.json::<Value>().await?;
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T> IssueConnector for HttpClient<T>
+where
+    T: Transport + Send + Sync,
+{
+    /// Mint Tokens [NUT-04]
+    #[instrument(skip(self, quote, premint_secrets), fields(mint_url = %mint_url))]
+    async fn post_mint(
+        &self,
+        mint_url: Url,
+        quote: &str,
+        premint_secrets: PreMintSecrets,
+    ) -> Result<MintBolt11Response, Error> {
+        let url = join_url(mint_url, &["v1", "mint", "bolt11"])?;
 
-        match serde_json::from_value::<MeltQuoteBolt11Response>(res.clone()) {
-            Ok(melt_quote_response) => Ok(melt_quote_response),
-            Err(_) => Err(ErrorResponse::from_value(res)?.into()),
-        }
+        let request = MintBolt11Request {
+            quote: quote.to_string(),
+            outputs: premint_secrets.blinded_messages(),
+        };
+
+        self.post(url, &request).await
     }
+}
 
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T> MeltConnector for HttpClient<T>
+where
+    T: Transport + Send + Sync,
+{
     /// Melt [NUT-05]
     /// [Nut-08] Lightning fee return if outputs defined
     #[instrument(skip(self, quote, inputs, outputs), fields(mint_url = %mint_url))]
-    pub async fn post_melt(
+    async fn post_melt(
         &self,
         mint_url: Url,
         quote: String,
@@ -224,61 +615,37 @@ impl HttpClient {
             outputs,
         };
 
-        let res = self
-            .inner
-            .post(url)
-            .json(&request)
-            .send()
-            .await?
-            .json::<Value>()
-            .await?;
-
-        match serde_json::from_value::<MeltBolt11Response>(res.clone()) {
-            Ok(melt_quote_response) => Ok(melt_quote_response),
-            Err(_) => Err(ErrorResponse::from_value(res)?.into()),
-        }
+        self.post(url, &request).await
     }
+}
 
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T> SwapConnector for HttpClient<T>
+where
+    T: Transport + Send + Sync,
+{
     /// Split Token [NUT-06]
     #[instrument(skip(self, swap_request), fields(mint_url = %mint_url))]
-    pub async fn post_swap(
+    async fn post_swap(
         &self,
         mint_url: Url,
         swap_request: SwapRequest,
     ) -> Result<SwapResponse, Error> {
         let url = join_url(mint_url, &["v1", "swap"])?;
-
-        let res = self
-            .inner
-            .post(url)
-            .json(&swap_request)
-            .send()
-            .await?
-            .json::<Value>()
-            .await?;
-
-        match serde_json::from_value::<SwapResponse>(res.clone()) {
-            Ok(melt_quote_response) => Ok(melt_quote_response),
-            Err(_) => Err(ErrorResponse::from_value(res)?.into()),
-        }
-    }
-
-    /// Get Mint Info [NUT-06]
-    #[instrument(skip(self), fields(mint_url = %mint_url))]
-    pub async fn get_mint_info(&self, mint_url: Url) -> Result<MintInfo, Error> {
-        let url = join_url(mint_url, &["v1", "info"])?;
-
-        let res = self.inner.get(url).send().await?.json::<Value>().await?;
-
-        match serde_json::from_value::<MintInfo>(res.clone()) {
-            Ok(melt_quote_response) => Ok(melt_quote_response),
-            Err(_) => Err(ErrorResponse::from_value(res)?.into()),
-        }
+        self.post(url, &swap_request).await
     }
+}
 
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T> CheckStateConnector for HttpClient<T>
+where
+    T: Transport + Send + Sync,
+{
     /// Spendable check [NUT-07]
     #[instrument(skip(self), fields(mint_url = %mint_url))]
-    pub async fn post_check_state(
+    async fn post_check_state(
         &self,
         mint_url: Url,
         ys: Vec<PublicKey>,
@@ -286,41 +653,222 @@ impl HttpClient {
         let url = join_url(mint_url, &["v1", "checkstate"])?;
         let request = CheckStateRequest { ys };
 
-        let res = self
-            .inner
-            .post(url)
-            .json(&request)
-            .send()
-            .await?
-            .json::<Value>()
-            .await?;
-
-        match serde_json::from_value::<CheckStateResponse>(res.clone()) {
-            Ok(melt_quote_response) => Ok(melt_quote_response),
-            Err(_) => Err(ErrorResponse::from_value(res)?.into()),
-        }
+        self.post(url, &request).await
     }
+}
 
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T> RestoreConnector for HttpClient<T>
+where
+    T: Transport + Send + Sync,
+{
     #[instrument(skip(self, request), fields(mint_url = %mint_url))]
-    pub async fn post_restore(
+    async fn post_restore(
         &self,
         mint_url: Url,
         request: RestoreRequest,
     ) -> Result<RestoreResponse, Error> {
         let url = join_url(mint_url, &["v1", "restore"])?;
+        self.post(url, &request).await
+    }
+}
 
-        let res = self
-            .inner
-            .post(url)
-            .json(&request)
-            .send()
-            .await?
-            .json::<Value>()
-            .await?;
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T> PaymentRequestConnector for HttpClient<T>
+where
+    T: Transport + Send + Sync,
+{
+    /// Post payment [NUT-18]
+    #[instrument(skip(self, payload), fields(url = %url))]
+    async fn post_payment_request(
+        &self,
+        url: Url,
+        payload: PaymentRequestPayload,
+    ) -> Result<(), Error> {
+        self.transport
+            .send(url, serde_json::to_value(payload)?)
+            .await
+    }
+}
 
-        match serde_json::from_value::<RestoreResponse>(res.clone()) {
-            Ok(melt_quote_response) => Ok(melt_quote_response),
-            Err(_) => Err(ErrorResponse::from_value(res)?.into()),
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// Connector that only serves keys
+    struct KeysOnly;
+
+    #[async_trait]
+    impl KeysConnector for KeysOnly {
+        async fn get_mint_keys(&self, _mint_url: Url) -> Result<Vec<KeySet>, Error> {
+            Ok(vec![])
+        }
+
+        async fn get_mint_keyset(&self, _mint_url: Url, _keyset_id: Id) -> Result<KeySet, Error> {
+            Err(Error::KeysetNotFound)
+        }
+
+        async fn get_mint_keysets(&self, _mint_url: Url) -> Result<KeysetResponse, Error> {
+            Ok(KeysetResponse { keysets: vec![] })
         }
+
+        async fn get_mint_info(&self, _mint_url: Url) -> Result<MintInfo, Error> {
+            Ok(MintInfo::default())
+        }
+    }
+
+    impl QuoteConnector for KeysOnly {}
+    impl IssueConnector for KeysOnly {}
+    impl MeltConnector for KeysOnly {}
+    impl SwapConnector for KeysOnly {}
+    impl CheckStateConnector for KeysOnly {}
+    impl RestoreConnector for KeysOnly {}
+    impl PaymentRequestConnector for KeysOnly {}
+
+    /// Transport answering every request with the same body
+    struct CannedTransport(Value);
+
+    #[async_trait]
+    impl Transport for CannedTransport {
+        async fn get(&self, _url: Url) -> Result<Value, Error> {
+            Ok(self.0.clone())
+        }
+
+        async fn post(&self, _url: Url, _body: Value) -> Result<Value, Error> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_http_client_transport() {
+        let mint_url = Url::parse("https://mint.example.com").unwrap();
+
+        let client = HttpClient::with_transport(CannedTransport(serde_json::json!({
+            "keysets": [{"id": "009a1f293253e41e", "unit": "sat", "active": true}]
+        })));
+        let keysets = client.get_mint_keysets(mint_url.clone()).await.unwrap();
+        assert_eq!(keysets.keysets.len(), 1);
+
+        // Error responses of the mint are decoded for every endpoint
+        let client = HttpClient::with_transport(CannedTransport(serde_json::json!({
+            "code": 20001,
+            "detail": "quote not paid"
+        })));
+        assert!(matches!(
+            client.post_check_state(mint_url, vec![]).await,
+            Err(Error::QuoteNotePaid)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_partial_connector() {
+        let client: Arc<dyn Client + Send + Sync> = Arc::new(KeysOnly);
+        let mint_url = Url::parse("https://mint.example.com").unwrap();
+
+        assert!(client
+            .get_mint_keys(mint_url.clone())
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            client.post_check_state(mint_url, vec![]).await,
+            Err(Error::UnsupportedOperation(_))
+        ));
+    }
+
+    #[test]
+    fn test_reqwest_transport_headers() {
+        let transport = ReqwestTransport::new();
+        assert_eq!(transport.headers[USER_AGENT_HEADER], USER_AGENT);
+
+        let transport = transport
+            .user_agent("my-wallet/1.0")
+            .unwrap()
+            .header("X-Api-Key", "secret")
+            .unwrap();
+        assert_eq!(transport.headers[USER_AGENT_HEADER], "my-wallet/1.0");
+        assert_eq!(transport.headers["x-api-key"], "secret");
+
+        assert!(matches!(
+            ReqwestTransport::new().header("bad header", "value"),
+            Err(Error::InvalidHeader(_))
+        ));
+        assert!(matches!(
+            ReqwestTransport::new().header("x-api-key", "bad\nvalue"),
+            Err(Error::InvalidHeader(_))
+        ));
+    }
+
+    /// Answer requests on a local port like a mint that sends CBOR but only
+    /// accepts JSON bodies, returns the url and the received request heads
+    async fn cbor_mint() -> (Url, tokio::sync::mpsc::UnboundedReceiver<String>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                while !String::from_utf8_lossy(&request).contains("\r\n\r\n") {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                }
+                let head = String::from_utf8_lossy(&request).to_lowercase();
+                let _ = sender.send(head.clone());
+
+                let response = match head.contains("content-type: application/cbor") {
+                    true => "HTTP/1.1 415 Unsupported Media Type\r\nconnection: close\r\ncontent-length: 0\r\n\r\n".as_bytes().to_vec(),
+                    false => {
+                        let body = ContentType::Cbor
+                            .encode(&serde_json::json!({"keysets": [], "states": []}))
+                            .unwrap();
+                        let mut response = format!(
+                            "HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-type: application/cbor\r\ncontent-length: {}\r\n\r\n",
+                            body.len()
+                        )
+                        .into_bytes();
+                        response.extend_from_slice(&body);
+                        response
+                    }
+                };
+                stream.write_all(&response).await.unwrap();
+            }
+        });
+
+        (url, receiver)
+    }
+
+    #[tokio::test]
+    async fn test_reqwest_transport_cbor() {
+        let (mint_url, mut requests) = cbor_mint().await;
+        let client =
+            HttpClient::with_transport(ReqwestTransport::new().content_type(ContentType::Cbor));
+
+        let keysets = client.get_mint_keysets(mint_url.clone()).await.unwrap();
+        assert!(keysets.keysets.is_empty());
+        let head = requests.recv().await.unwrap();
+        assert!(head.contains("accept: application/cbor, application/json;q=0.5"));
+
+        // CBOR body rejected, sent again as JSON
+        let states = client.post_check_state(mint_url, vec![]).await.unwrap();
+        assert!(states.states.is_empty());
+        assert!(requests
+            .recv()
+            .await
+            .unwrap()
+            .contains("content-type: application/cbor"));
+        assert!(requests
+            .recv()
+            .await
+            .unwrap()
+            .contains("content-type: application/json"));
     }
 }
@@ -3,7 +3,7 @@ use std::num::ParseIntError;
 use thiserror::Error;
 
 use crate::cdk_database;
-use crate::error::{ErrorCode, ErrorResponse};
+use crate::error::{ErrorCategory, ErrorCode, ErrorResponse};
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -39,27 +39,60 @@ pub enum Error {
     /// Unknown Keyset
     #[error("Url Path segments could not be joined")]
     UrlPathSegments,
+    /// Header name or value of a client is not valid
+    #[error("Invalid header: `{0}`")]
+    InvalidHeader(String),
     /// Quote not paid
     #[error("Quote not paid")]
     QuoteNotePaid,
+    /// Quote is being paid
+    #[error("Quote pending")]
+    QuotePending,
+    /// Operation did not finish in time
+    #[error("Timeout")]
+    Timeout,
+    /// Invoice has expired
+    #[error("Invoice expired")]
+    InvoiceExpired,
+    /// Invoice is of another network than the wallet
+    #[error("Invoice for network `{found}`, expected `{expected}`")]
+    InvoiceNetworkMismatch {
+        expected: lightning_invoice::Currency,
+        found: lightning_invoice::Currency,
+    },
+    /// Invoice has no amount
+    #[error("Invoice amount undefined")]
+    InvoiceAmountUndefined,
     /// Token Already spent error
     #[error("Token Already Spent Error")]
     TokenAlreadySpent,
     /// Keyset Not Found
     #[error("Keyset Not Found")]
     KeysetNotFound,
+    /// Wallet is locked
+    #[error("Wallet is locked")]
+    WalletLocked,
+    /// Connector does not support the operation
+    #[error("Operation not supported by connector: `{0}`")]
+    UnsupportedOperation(String),
+    /// Request exceeds the mint's input or output limits
+    #[error("Request too large: `{0}`")]
+    RequestTooLarge(String),
+    /// Mint does not support the request
+    #[error("Not supported by mint: `{0}`")]
+    EndpointDisabled(String),
     /// From hex error
     #[error(transparent)]
     ReqwestError(#[from] reqwest::Error),
     ///  Unknown error response
     #[error("Unknown Error response: `{0}`")]
-    UnknownErrorResponse(String),
+    UnknownErrorResponse(ErrorResponse),
     /// CDK Error
     #[error(transparent)]
     Cashu(#[from] crate::error::Error),
     /// Cashu Url Error
     #[error(transparent)]
-    CashuUrl(#[from] crate::url::Error),
+    CashuUrl(#[from] crate::mint_url::Error),
     /// Database Error
     #[error(transparent)]
     Database(#[from] crate::cdk_database::Error),
@@ -69,21 +102,37 @@ pub enum Error {
     /// NUT01 Error
     #[error(transparent)]
     NUT01(#[from] crate::nuts::nut01::Error),
+    /// NUT02 Error
+    #[error(transparent)]
+    NUT02(#[from] crate::nuts::nut02::Error),
     /// NUT11 Error
     #[error(transparent)]
     NUT11(#[from] crate::nuts::nut11::Error),
     /// NUT12 Error
     #[error(transparent)]
     NUT12(#[from] crate::nuts::nut12::Error),
+    /// Bip32 Error
+    #[error(transparent)]
+    Bip32(#[from] bitcoin::bip32::Error),
     /// Parse int
     #[error(transparent)]
     ParseInt(#[from] ParseIntError),
+    /// Keys returned by the mint are not those of the keyset
+    #[error("Keys of keyset `{0}` do not match the keyset")]
+    KeysetMismatch(crate::nuts::Id),
     /// Parse invoice error
     #[error(transparent)]
     Invoice(#[from] lightning_invoice::ParseOrSemanticError),
     /// Serde Error
     #[error(transparent)]
     Serde(#[from] serde_json::Error),
+    /// Wire format Error
+    #[error(transparent)]
+    Wire(#[from] crate::wire::Error),
+    /// WebSocket Error
+    #[cfg(all(feature = "ws", not(target_arch = "wasm32")))]
+    #[error(transparent)]
+    WebSocket(Box<tokio_tungstenite::tungstenite::Error>),
     /// Nostr Client Error
     #[cfg(feature = "nostr")]
     #[error(transparent)]
@@ -92,11 +141,128 @@ pub enum Error {
     #[cfg(feature = "nostr")]
     #[error(transparent)]
     NostrKey(#[from] nostr_sdk::key::Error),
+    /// Nostr Event Builder Error
+    #[cfg(feature = "nostr")]
+    #[error(transparent)]
+    NostrEventBuilder(#[from] nostr_sdk::event::builder::Error),
+    /// Nostr NIP-04 Error
+    #[cfg(feature = "nostr")]
+    #[error(transparent)]
+    NostrNip04(#[from] nostr_sdk::nips::nip04::Error),
+    /// Nostr NIP-47 Error
+    #[cfg(feature = "nostr")]
+    #[error(transparent)]
+    NostrNip47(#[from] nostr_sdk::nips::nip47::Error),
+    /// Nostr NIP-59 Error
+    #[cfg(feature = "nostr")]
+    #[error(transparent)]
+    NostrNip59(#[from] nostr_sdk::nips::nip59::Error),
+    /// Spend Policy Error
+    #[error(transparent)]
+    SpendPolicy(#[from] super::policy::Error),
+    /// Proofs do not belong to a keyset of the unit
+    #[error("Proofs are not all of unit `{0}`")]
+    UnitMismatch(crate::nuts::CurrencyUnit),
+    /// Exchange Rate Error
+    #[error(transparent)]
+    ExchangeRate(#[from] crate::exchange_rate::Error),
+    /// Token has already been received by this wallet
+    #[error("Token already received")]
+    AlreadyReceived,
+    /// Setting of a [`WalletBuilder`](super::WalletBuilder) missing or invalid
+    #[error("Invalid wallet builder: `{0}`")]
+    InvalidBuilder(String),
     /// Custom Error
     #[error("`{0}`")]
     Custom(String),
 }
 
+#[cfg(all(feature = "ws", not(target_arch = "wasm32")))]
+impl From<tokio_tungstenite::tungstenite::Error> for Error {
+    fn from(err: tokio_tungstenite::tungstenite::Error) -> Self {
+        Self::WebSocket(Box::new(err))
+    }
+}
+
+impl Error {
+    /// NUT-00 error code of the error, [`ErrorCode::Unknown`] if none applies
+    ///
+    /// Errors returned by the mint keep the code of its response.
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            Self::QuoteNotePaid => ErrorCode::QuoteNotPaid,
+            Self::QuotePending => ErrorCode::QuotePending,
+            Self::QuoteExpired => ErrorCode::QuoteExpired,
+            Self::TokenAlreadySpent => ErrorCode::TokenAlreadySpent,
+            Self::KeysetNotFound => ErrorCode::KeysetNotFound,
+            Self::RequestTooLarge(_) => ErrorCode::RequestTooLarge,
+            Self::EndpointDisabled(_) => ErrorCode::EndpointDisabled,
+            Self::CouldNotVerifyDleq | Self::NUT11(_) | Self::NUT12(_) => {
+                ErrorCode::TokenNotVerified
+            }
+            Self::UnknownErrorResponse(response) => response.code,
+            Self::Cashu(err) => err.error_code(),
+            _ => ErrorCode::Unknown(9999),
+        }
+    }
+
+    /// Kind of failure
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Self::InsufficientFunds => ErrorCategory::InsufficientFunds,
+            Self::ReqwestError(_) | Self::Timeout => ErrorCategory::Network,
+            #[cfg(feature = "nostr")]
+            Self::NostrClient(_) => ErrorCategory::Network,
+            #[cfg(all(feature = "ws", not(target_arch = "wasm32")))]
+            Self::WebSocket(_) => ErrorCategory::Network,
+            Self::QuoteExpired
+            | Self::QuoteUnknown
+            | Self::QuoteNotePaid
+            | Self::QuotePending
+            | Self::NoActiveKeyset
+            | Self::KeysetNotFound
+            | Self::KeysetMismatch(_)
+            | Self::UnsupportedOperation(_)
+            | Self::RequestTooLarge(_)
+            | Self::EndpointDisabled(_)
+            | Self::Serde(_)
+            | Self::Wire(_) => ErrorCategory::Protocol,
+            Self::UnknownErrorResponse(response) => response.code.category(),
+            Self::TokenAlreadySpent
+            | Self::CouldNotVerifyDleq
+            | Self::P2PKConditionsNotMet(_)
+            | Self::InvalidSpendConditions(_)
+            | Self::PreimageNotProvided
+            | Self::LocktimeNotProvided
+            | Self::UnknownKey
+            | Self::UnitMismatch(_)
+            | Self::AlreadyReceived
+            | Self::NUT00(_)
+            | Self::NUT11(_)
+            | Self::NUT12(_) => ErrorCategory::InvalidToken,
+            Self::UrlPathSegments
+            | Self::InvalidHeader(_)
+            | Self::WalletLocked
+            | Self::CashuUrl(_)
+            | Self::ParseInt(_)
+            | Self::Invoice(_)
+            | Self::InvoiceExpired
+            | Self::InvoiceNetworkMismatch { .. }
+            | Self::InvoiceAmountUndefined
+            | Self::SpendPolicy(_)
+            | Self::InvalidBuilder(_) => ErrorCategory::InvalidInput,
+            Self::ExchangeRate(crate::exchange_rate::Error::NoFreshRate(_)) => {
+                ErrorCategory::Network
+            }
+            #[cfg(feature = "exchange-rate")]
+            Self::ExchangeRate(crate::exchange_rate::Error::Reqwest(_)) => ErrorCategory::Network,
+            Self::Database(_) => ErrorCategory::Database,
+            Self::Cashu(err) => err.category(),
+            _ => ErrorCategory::Internal,
+        }
+    }
+}
+
 impl From<Error> for cdk_database::Error {
     fn from(e: Error) -> Self {
         Self::Database(Box::new(e))
@@ -107,9 +273,49 @@ impl From<ErrorResponse> for Error {
     fn from(err: ErrorResponse) -> Error {
         match err.code {
             ErrorCode::QuoteNotPaid => Self::QuoteNotePaid,
+            ErrorCode::QuotePending => Self::QuotePending,
             ErrorCode::TokenAlreadySpent => Self::TokenAlreadySpent,
             ErrorCode::KeysetNotFound => Self::KeysetNotFound,
-            _ => Self::UnknownErrorResponse(err.to_string()),
+            ErrorCode::RequestTooLarge | ErrorCode::SecretTooLong => {
+                Self::RequestTooLarge(err.to_string())
+            }
+            ErrorCode::MintingDisabled | ErrorCode::EndpointDisabled => {
+                Self::EndpointDisabled(err.to_string())
+            }
+            _ => Self::UnknownErrorResponse(err),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_response_code_kept() {
+        let response = ErrorResponse {
+            code: ErrorCode::DuplicateInputs,
+            error: None,
+            detail: Some("Duplicate inputs".to_string()),
+        };
+        let err = Error::from(response);
+
+        assert!(matches!(err, Error::UnknownErrorResponse(_)));
+        assert_eq!(err.error_code(), ErrorCode::DuplicateInputs);
+        assert_eq!(err.category(), ErrorCategory::InvalidToken);
+
+        assert_eq!(
+            Error::InsufficientFunds.category(),
+            ErrorCategory::InsufficientFunds
+        );
+        assert_eq!(
+            Error::from(ErrorResponse {
+                code: ErrorCode::QuoteNotPaid,
+                error: None,
+                detail: None,
+            })
+            .error_code(),
+            ErrorCode::QuoteNotPaid
+        );
+    }
+}
@@ -0,0 +1,69 @@
+//! Wallet events
+//!
+//! The [`Wallet`](super::Wallet) publishes an event on its [`EventBus`] for
+//! changes it made on its own, such as handling a keyset rotation of a mint,
+//! so that applications can tell their users.
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::mint_url::MintUrl;
+use crate::nuts::{CurrencyUnit, Id};
+use crate::Amount;
+
+/// Events buffered per subscriber before it starts lagging
+const DEFAULT_CAPACITY: usize = 64;
+
+/// Wallet event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WalletEvent {
+    /// Mint replaced the active keyset of a unit
+    KeysetRotated {
+        mint_url: MintUrl,
+        unit: CurrencyUnit,
+        /// Keyset deactivated by the mint
+        inactive: Id,
+        /// New active keyset
+        active: Id,
+        /// Amount of the proofs swapped from `inactive` into `active`, `None`
+        /// if the wallet does not swap them
+        swapped: Option<Amount>,
+    },
+}
+
+/// In process publish/subscribe channel for [`WalletEvent`]s
+///
+/// Every subscriber receives every event published after it subscribed.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<WalletEvent>,
+}
+
+impl EventBus {
+    /// Create new [`EventBus`] buffering `capacity` events per subscriber
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+
+        Self { sender }
+    }
+
+    /// Publish event to all current subscribers
+    pub fn publish(&self, event: WalletEvent) {
+        tracing::trace!("Publishing wallet event: {:?}", event);
+
+        // Sending only fails when there are no subscribers
+        let _ = self.sender.send(event);
+    }
+
+    /// Receive events published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<WalletEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
@@ -0,0 +1,173 @@
+//! Transaction history
+//!
+//! The wallet records a [`Transaction`] for every mint, melt, send and
+//! receive. [`Wallet::export_history`] writes them as CSV or JSON for
+//! bookkeeping.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Error, Wallet};
+use crate::types::Transaction;
+
+/// Format of an exported history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryFormat {
+    /// One row per transaction with a header row
+    Csv,
+    /// Array of transactions
+    Json,
+}
+
+impl FromStr for HistoryFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(Error::Custom(format!("Unknown history format: {}", s))),
+        }
+    }
+}
+
+impl fmt::Display for HistoryFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Csv => write!(f, "csv"),
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl Wallet {
+    /// Transactions from `start` until `end`, oldest first
+    pub async fn transactions(&self, start: u64, end: u64) -> Result<Vec<Transaction>, Error> {
+        let mut transactions: Vec<Transaction> = self
+            .localstore
+            .get_transactions()
+            .await?
+            .into_iter()
+            .filter(|t| t.timestamp >= start && t.timestamp < end)
+            .collect();
+
+        transactions.sort_by_key(|t| t.timestamp);
+
+        Ok(transactions)
+    }
+
+    /// Export the transactions from `start` until `end` in `format`
+    pub async fn export_history(
+        &self,
+        format: HistoryFormat,
+        start: u64,
+        end: u64,
+    ) -> Result<String, Error> {
+        let transactions = self.transactions(start, end).await?;
+
+        match format {
+            HistoryFormat::Csv => Ok(to_csv(&transactions)),
+            HistoryFormat::Json => Ok(serde_json::to_string(&transactions)?),
+        }
+    }
+
+    /// Store `transaction`
+    ///
+    /// Called once the operation succeeded, so a storage error is only logged.
+    pub(crate) async fn record_transaction(&self, transaction: Transaction) {
+        if let Err(err) = self.localstore.add_transaction(transaction).await {
+            tracing::warn!("Could not record transaction: {}", err);
+        }
+    }
+}
+
+const CSV_HEADER: &str = "id,timestamp,kind,mint_url,unit,amount,fee,memo";
+
+fn to_csv(transactions: &[Transaction]) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    csv.push('\n');
+
+    for t in transactions {
+        let row = [
+            t.id.clone(),
+            t.timestamp.to_string(),
+            t.kind.to_string(),
+            t.mint_url.to_string(),
+            t.unit.to_string(),
+            t.amount.to_string(),
+            t.fee.to_string(),
+            t.memo.clone().unwrap_or_default(),
+        ];
+
+        let row: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+
+    csv
+}
+
+/// Quote `field` if it contains a separator, quote or line break
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::mint_url::MintUrl;
+    use crate::nuts::CurrencyUnit;
+    use crate::types::TransactionKind;
+    use crate::Amount;
+
+    fn transaction(kind: TransactionKind, memo: Option<&str>, timestamp: u64) -> Transaction {
+        let mut transaction = Transaction::new(
+            MintUrl::from_str("https://mint.example.com").unwrap(),
+            kind,
+            Amount::from(10),
+            Amount::from(1),
+            CurrencyUnit::Sat,
+            memo.map(|m| m.to_string()),
+        );
+        transaction.id = format!("tx{}", timestamp);
+        transaction.timestamp = timestamp;
+        transaction
+    }
+
+    #[test]
+    fn test_history_csv() {
+        let csv = to_csv(&[
+            transaction(TransactionKind::Send, Some("coffee, \"large\""), 100),
+            transaction(TransactionKind::Receive, None, 200),
+        ]);
+
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows[0], CSV_HEADER);
+        assert_eq!(
+            rows[1],
+            "tx100,100,send,https://mint.example.com,sat,10,1,\"coffee, \"\"large\"\"\""
+        );
+        assert_eq!(
+            rows[2],
+            "tx200,200,receive,https://mint.example.com,sat,10,1,"
+        );
+    }
+
+    #[test]
+    fn test_history_format() {
+        assert_eq!(HistoryFormat::from_str("CSV").unwrap(), HistoryFormat::Csv);
+        assert_eq!(
+            HistoryFormat::from_str("json").unwrap(),
+            HistoryFormat::Json
+        );
+        assert!(HistoryFormat::from_str("xml").is_err());
+    }
+}
@@ -0,0 +1,118 @@
+//! Bolt11 invoice checks
+//!
+//! Invoices are checked before a melt quote is requested, so that expired
+//! invoices, invoices of another network and invoices without an amount are
+//! rejected without contacting the mint.
+
+use std::str::FromStr;
+
+use lightning_invoice::Bolt11Invoice;
+
+use super::{Error, Wallet};
+use crate::inspect::Bolt11Inspection;
+use crate::util::unix_time;
+
+impl Wallet {
+    /// Decode and check a bolt11 invoice the wallet is asked to pay
+    ///
+    /// Returns the details of the invoice to show before paying it.
+    pub fn check_invoice(&self, request: &str) -> Result<Bolt11Inspection, Error> {
+        let invoice = self.parse_invoice(request)?;
+        Ok((&invoice).into())
+    }
+
+    /// Decode `request` and check it can be paid by the wallet
+    pub(crate) fn parse_invoice(&self, request: &str) -> Result<Bolt11Invoice, Error> {
+        let invoice = Bolt11Invoice::from_str(request.trim())?;
+
+        if let Some(network) = &self.network {
+            if invoice.currency() != *network {
+                return Err(Error::InvoiceNetworkMismatch {
+                    expected: network.clone(),
+                    found: invoice.currency(),
+                });
+            }
+        }
+
+        if invoice.amount_milli_satoshis().is_none() {
+            return Err(Error::InvoiceAmountUndefined);
+        }
+
+        // `Bolt11Invoice::is_expired` reads the system clock, not available on WASM
+        let expires_at = invoice
+            .duration_since_epoch()
+            .as_secs()
+            .saturating_add(invoice.expiry_time().as_secs());
+        if expires_at <= unix_time() {
+            return Err(Error::InvoiceExpired);
+        }
+
+        Ok(invoice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use bitcoin::hashes::{sha256, Hash};
+    use bitcoin::secp256k1::SecretKey;
+    use lightning_invoice::{Currency, InvoiceBuilder, PaymentSecret};
+
+    use super::*;
+    use crate::cdk_database::WalletMemoryDatabase;
+    use crate::SECP256K1;
+
+    fn invoice(currency: Currency, amount_msat: Option<u64>, timestamp: Duration) -> String {
+        let private_key = SecretKey::from_slice(&[42; 32]).unwrap();
+        let builder = InvoiceBuilder::new(currency)
+            .description("coffee".to_string())
+            .payment_hash(sha256::Hash::from_slice(&[1; 32]).unwrap())
+            .payment_secret(PaymentSecret([2; 32]))
+            .duration_since_epoch(timestamp)
+            .expiry_time(Duration::from_secs(600))
+            .min_final_cltv_expiry_delta(144);
+        let builder = match amount_msat {
+            Some(amount_msat) => builder.amount_milli_satoshis(amount_msat),
+            None => builder,
+        };
+
+        builder
+            .build_signed(|hash| SECP256K1.sign_ecdsa_recoverable(hash, &private_key))
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_check_invoice() {
+        let mut wallet = Wallet::new(Arc::new(WalletMemoryDatabase::default()), &[0; 32], vec![]);
+        wallet.network = Some(Currency::Regtest);
+        let now = Duration::from_secs(unix_time());
+
+        let details = wallet
+            .check_invoice(&invoice(Currency::Regtest, Some(21_000), now))
+            .unwrap();
+        assert_eq!(details.amount_msat, Some(21_000));
+        assert_eq!(details.description.as_deref(), Some("coffee"));
+        assert_eq!(details.payment_hash, "01".repeat(32));
+
+        assert!(matches!(
+            wallet.check_invoice(&invoice(Currency::Bitcoin, Some(21_000), now)),
+            Err(Error::InvoiceNetworkMismatch { .. })
+        ));
+        assert!(matches!(
+            wallet.check_invoice(&invoice(Currency::Regtest, None, now)),
+            Err(Error::InvoiceAmountUndefined)
+        ));
+        assert!(matches!(
+            wallet.check_invoice(&invoice(
+                Currency::Regtest,
+                Some(21_000),
+                now - Duration::from_secs(601)
+            )),
+            Err(Error::InvoiceExpired)
+        ));
+        assert!(wallet.check_invoice("lnbc1invalid").is_err());
+    }
+}
@@ -1,51 +1,125 @@
 //! Cashu Wallet
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ops::Deref;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use bitcoin::bip32::ExtendedPrivKey;
 use bitcoin::hashes::sha256::Hash as Sha256Hash;
 use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::rand::{self, seq::SliceRandom};
 use bitcoin::secp256k1::XOnlyPublicKey;
 use bitcoin::Network;
+use client::Client;
 use error::Error;
-#[cfg(feature = "nostr")]
-use nostr_sdk::nips::nip04;
-#[cfg(feature = "nostr")]
-use nostr_sdk::{Filter, Timestamp};
+use futures::stream::{self, Stream, StreamExt};
+use lightning_invoice::Currency;
+use policy::{SpendKind, SpendPolicy, SpendRequest};
+use privacy::PrivacyMode;
+use reservation::{ProofReservations, Reservation};
 use tokio::sync::RwLock;
 use tracing::instrument;
 
 use crate::amount::SplitTarget;
 use crate::cdk_database::{self, WalletDatabase};
-use crate::dhke::{construct_proofs, hash_to_curve};
+use crate::dhke::construct_proofs;
+use crate::error::{ErrorCategory, ErrorCode};
+use crate::exchange_rate::{self, ExchangeRate};
+use crate::mint_url::MintUrl;
 use crate::nuts::{
-    nut10, nut12, Conditions, CurrencyUnit, Id, KeySet, KeySetInfo, Keys, Kind,
-    MeltQuoteBolt11Response, MintInfo, MintQuoteBolt11Response, PreMintSecrets, PreSwap, Proof,
-    ProofState, Proofs, PublicKey, RestoreRequest, SecretKey, SigFlag, SpendingConditions, State,
-    SwapRequest, Token,
+    nut02, nut10, nut12, BlindSignature, Conditions, CurrencyUnit, ForeignToken, Id, KeySet,
+    KeySetInfo, Keys, Kind, MeltQuoteBolt11Response, MeltQuoteState, MintInfo,
+    MintQuoteBolt11Response, PreMintSecrets, PreSwap, Proof, ProofState, Proofs, PublicKey,
+    RestoreRequest, SecretKey, SigFlag, SpendingConditions, State, SwapRequest, Token,
 };
-use crate::types::{MeltQuote, Melted, MintQuote, ProofInfo};
-use crate::url::UncheckedUrl;
-use crate::util::{hex, unix_time};
-use crate::{Amount, Bolt11Invoice, HttpClient};
+use crate::types::{
+    MeltQuote, Melted, MintQuote, PendingMint, ProofInfo, Transaction, TransactionKind,
+};
+use crate::util::{hex, runtime, unix_time};
+use crate::{Amount, HttpClient};
 
+pub mod backup;
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+pub mod blocking;
+pub mod builder;
 pub mod client;
 pub mod error;
+pub mod events;
+pub mod history;
+pub mod invoice;
+#[cfg(feature = "nostr")]
+pub mod nostr;
+#[cfg(feature = "nostr")]
+pub mod nwc;
+pub mod payment_request;
+pub mod paywall;
+pub mod policy;
+pub mod privacy;
+pub mod reservation;
+pub mod rotation;
+pub mod stats;
+#[cfg(all(feature = "ws", not(target_arch = "wasm32")))]
+pub mod subscription;
+
+pub use builder::WalletBuilder;
+
+/// Most proofs [`Wallet::check_proofs_spent`] sends in one request
+pub const CHECK_STATE_CHUNK_SIZE: usize = 100;
+
+/// Most requests [`Wallet::check_proofs_spent`] runs at once
+pub const CHECK_STATE_CONCURRENCY: usize = 4;
+
+/// Time between two state checks of [`Wallet::wait_for_melt`]
+pub const MELT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Record fields on the current span
+///
+/// Only compiled with the `wallet-tracing` feature, the fields have to be
+/// declared as [`tracing::field::Empty`] in the `instrument` attribute.
+macro_rules! record {
+    ($($field:literal = $value:expr),+ $(,)?) => {
+        #[cfg(feature = "wallet-tracing")]
+        {
+            let span = tracing::Span::current();
+            $(span.record($field, $value);)+
+        }
+    };
+}
 
+/// Cashu wallet
+///
+/// The wallet is `Send + Sync` and every operation takes `&self`. Clones are
+/// cheap and share storage, client, keys and proof reservations, so a wallet
+/// can be cloned into tokio tasks and used concurrently.
 #[derive(Clone)]
 pub struct Wallet {
-    pub client: HttpClient,
+    pub client: Arc<dyn Client + Send + Sync>,
     pub localstore: Arc<dyn WalletDatabase<Err = cdk_database::Error> + Send + Sync>,
-    xpriv: ExtendedPrivKey,
+    xpriv: Arc<RwLock<Option<ExtendedPrivKey>>>,
     p2pk_signing_keys: Arc<RwLock<HashMap<XOnlyPublicKey, SecretKey>>>,
+    /// Policy every send and melt is checked against
+    pub spend_policy: Option<Arc<SpendPolicy>>,
+    /// Provider used to value balances in fiat units
+    pub exchange_rate: Option<Arc<dyn ExchangeRate + Send + Sync>>,
+    /// Lightning network of the invoices the wallet pays, any if `None`
+    pub network: Option<Currency>,
+    /// Swap proofs of keysets the mint deactivated into its active keyset
+    pub swap_inactive_keysets: bool,
+    /// Events published by the wallet
+    pub events: events::EventBus,
+    reservations: ProofReservations,
     #[cfg(feature = "nostr")]
     nostr_client: nostr_sdk::Client,
 }
 
 impl Wallet {
+    /// Builder with defaults for everything but the seed
+    pub fn builder() -> WalletBuilder {
+        WalletBuilder::new()
+    }
+
     pub fn new(
         localstore: Arc<dyn WalletDatabase<Err = cdk_database::Error> + Send + Sync>,
         seed: &[u8],
@@ -55,20 +129,60 @@ impl Wallet {
             .expect("Could not create master key");
 
         Self {
-            client: HttpClient::new(),
+            client: Arc::new(HttpClient::new()),
             localstore,
-            xpriv,
+            xpriv: Arc::new(RwLock::new(Some(xpriv))),
             p2pk_signing_keys: Arc::new(RwLock::new(
                 p2pk_signing_keys
                     .into_iter()
                     .map(|s| (s.public_key().x_only_public_key(), s))
                     .collect(),
             )),
+            spend_policy: None,
+            exchange_rate: None,
+            network: None,
+            swap_inactive_keysets: false,
+            events: events::EventBus::default(),
+            reservations: ProofReservations::default(),
             #[cfg(feature = "nostr")]
             nostr_client: nostr_sdk::Client::default(),
         }
     }
 
+    /// Lock wallet
+    ///
+    /// Wipes the master key and any P2PK signing keys held in memory. Operations
+    /// that need to derive secrets fail with [`Error::WalletLocked`] until the
+    /// wallet is unlocked again with [`Wallet::unlock`].
+    #[instrument(skip_all)]
+    pub async fn lock(&self) {
+        if let Some(mut xpriv) = self.xpriv.write().await.take() {
+            xpriv.private_key.non_secure_erase();
+        }
+
+        // [`SecretKey`] erases itself on drop
+        self.p2pk_signing_keys.write().await.clear();
+    }
+
+    /// Unlock wallet with seed
+    #[instrument(skip_all)]
+    pub async fn unlock(&self, seed: &[u8]) -> Result<(), Error> {
+        let xpriv = ExtendedPrivKey::new_master(Network::Bitcoin, seed)?;
+        *self.xpriv.write().await = Some(xpriv);
+        Ok(())
+    }
+
+    /// Check if wallet is locked
+    #[instrument(skip(self))]
+    pub async fn is_locked(&self) -> bool {
+        self.xpriv.read().await.is_none()
+    }
+
+    /// Master key of the wallet
+    async fn xpriv(&self) -> Result<ExtendedPrivKey, Error> {
+        self.xpriv.read().await.ok_or(Error::WalletLocked)
+    }
+
     /// Add P2PK signing key to wallet
     #[instrument(skip_all)]
     pub async fn add_p2pk_signing_key(&self, signing_key: SecretKey) {
@@ -183,7 +297,7 @@ impl Wallet {
     #[instrument(skip(self))]
     pub async fn mint_balances(
         &self,
-    ) -> Result<HashMap<UncheckedUrl, HashMap<CurrencyUnit, Amount>>, Error> {
+    ) -> Result<HashMap<MintUrl, HashMap<CurrencyUnit, Amount>>, Error> {
         let mints = self.localstore.get_mints().await?;
 
         let mut mint_balances = HashMap::new();
@@ -212,8 +326,70 @@ impl Wallet {
         Ok(mint_balances)
     }
 
+    /// Value of `amount` of `unit` in `fiat`
+    ///
+    /// Returns `None` when no [`Wallet::exchange_rate`] provider is set. The
+    /// value is for display only, mints may quote a different rate.
+    #[instrument(skip(self))]
+    pub async fn fiat_value(
+        &self,
+        amount: Amount,
+        unit: &CurrencyUnit,
+        fiat: &CurrencyUnit,
+    ) -> Result<Option<Amount>, Error> {
+        let provider = match &self.exchange_rate {
+            Some(provider) => provider,
+            None => return Ok(None),
+        };
+
+        if unit == fiat {
+            return Ok(Some(amount));
+        }
+
+        let sats = match unit {
+            CurrencyUnit::Sat => amount,
+            CurrencyUnit::Msat => Amount::from(u64::from(amount) / 1000),
+            unit => {
+                let rate = provider.rate(unit).await?;
+                exchange_rate::unit_to_sat(amount, rate.per_btc)?
+            }
+        };
+
+        let value = match fiat {
+            CurrencyUnit::Sat => sats,
+            CurrencyUnit::Msat => Amount::from(
+                u64::from(sats)
+                    .checked_mul(1000)
+                    .ok_or(exchange_rate::Error::Overflow)?,
+            ),
+            fiat => {
+                let rate = provider.rate(fiat).await?;
+                exchange_rate::sat_to_unit(sats, rate.per_btc)?
+            }
+        };
+
+        Ok(Some(value))
+    }
+
+    /// Value of the unspent balance of all units in `fiat`
+    ///
+    /// Returns `None` when no [`Wallet::exchange_rate`] provider is set.
+    #[instrument(skip(self))]
+    pub async fn total_fiat_balance(&self, fiat: &CurrencyUnit) -> Result<Option<Amount>, Error> {
+        if self.exchange_rate.is_none() {
+            return Ok(None);
+        }
+
+        let mut values = Vec::new();
+        for (unit, amount) in self.total_balance().await? {
+            values.extend(self.fiat_value(amount, &unit, fiat).await?);
+        }
+
+        Ok(Some(Amount::try_sum(values)?))
+    }
+
     #[instrument(skip(self), fields(mint_url = %mint_url))]
-    pub async fn get_proofs(&self, mint_url: UncheckedUrl) -> Result<Option<Proofs>, Error> {
+    pub async fn get_proofs(&self, mint_url: MintUrl) -> Result<Option<Proofs>, Error> {
         Ok(self
             .localstore
             .get_proofs(Some(mint_url), None, Some(vec![State::Unspent]), None)
@@ -222,7 +398,7 @@ impl Wallet {
     }
 
     #[instrument(skip(self), fields(mint_url = %mint_url))]
-    pub async fn add_mint(&self, mint_url: UncheckedUrl) -> Result<Option<MintInfo>, Error> {
+    pub async fn add_mint(&self, mint_url: MintUrl) -> Result<Option<MintInfo>, Error> {
         let mint_info = match self
             .client
             .get_mint_info(mint_url.clone().try_into()?)
@@ -243,11 +419,7 @@ impl Wallet {
     }
 
     #[instrument(skip(self), fields(mint_url = %mint_url))]
-    pub async fn get_keyset_keys(
-        &self,
-        mint_url: &UncheckedUrl,
-        keyset_id: Id,
-    ) -> Result<Keys, Error> {
+    pub async fn get_keyset_keys(&self, mint_url: &MintUrl, keyset_id: Id) -> Result<Keys, Error> {
         let keys = if let Some(keys) = self.localstore.get_keys(&keyset_id).await? {
             keys
         } else {
@@ -256,6 +428,8 @@ impl Wallet {
                 .get_mint_keyset(mint_url.try_into()?, keyset_id)
                 .await?;
 
+            verify_keyset(&keys, keyset_id, None)?;
+
             self.localstore.add_keys(keys.keys.clone()).await?;
 
             keys.keys
@@ -265,10 +439,7 @@ impl Wallet {
     }
 
     #[instrument(skip(self), fields(mint_url = %mint_url))]
-    pub async fn get_mint_keysets(
-        &self,
-        mint_url: &UncheckedUrl,
-    ) -> Result<Vec<KeySetInfo>, Error> {
+    pub async fn get_mint_keysets(&self, mint_url: &MintUrl) -> Result<Vec<KeySetInfo>, Error> {
         let keysets = self.client.get_mint_keysets(mint_url.try_into()?).await?;
 
         self.localstore
@@ -280,18 +451,16 @@ impl Wallet {
 
     /// Get active mint keyset
     #[instrument(skip(self), fields(mint_url = %mint_url))]
-    pub async fn get_active_mint_keys(
-        &self,
-        mint_url: &UncheckedUrl,
-    ) -> Result<Vec<KeySet>, Error> {
+    pub async fn get_active_mint_keys(&self, mint_url: &MintUrl) -> Result<Vec<KeySet>, Error> {
         let keysets = self.client.get_mint_keys(mint_url.try_into()?).await?;
+        let k = self.client.get_mint_keysets(mint_url.try_into()?).await?;
 
         for keyset in keysets.clone() {
+            let info = k.keysets.iter().find(|info| info.id == keyset.id);
+            verify_keyset(&keyset, keyset.id, info)?;
             self.localstore.add_keys(keyset.keys).await?;
         }
 
-        let k = self.client.get_mint_keysets(mint_url.try_into()?).await?;
-
         self.localstore
             .add_mint_keysets(mint_url.clone(), k.keysets)
             .await?;
@@ -301,7 +470,7 @@ impl Wallet {
 
     /// Refresh Mint keys
     #[instrument(skip(self), fields(mint_url = %mint_url))]
-    pub async fn refresh_mint_keys(&self, mint_url: &UncheckedUrl) -> Result<(), Error> {
+    pub async fn refresh_mint_keys(&self, mint_url: &MintUrl) -> Result<(), Error> {
         let current_mint_keysets_info = self
             .client
             .get_mint_keysets(mint_url.try_into()?)
@@ -319,6 +488,8 @@ impl Wallet {
                         .get_mint_keyset(mint_url.try_into()?, keyset.id)
                         .await?;
 
+                    verify_keyset(&keys, keyset.id, Some(&keyset))?;
+
                     self.localstore.add_keys(keys.keys).await?;
                 }
             }
@@ -326,6 +497,10 @@ impl Wallet {
                 let mint_keys = self.client.get_mint_keys(mint_url.try_into()?).await?;
 
                 for keys in mint_keys {
+                    let info = current_mint_keysets_info
+                        .iter()
+                        .find(|info| info.id == keys.id);
+                    verify_keyset(&keys, keys.id, info)?;
                     self.localstore.add_keys(keys.keys).await?;
                 }
             }
@@ -338,33 +513,87 @@ impl Wallet {
         Ok(())
     }
 
-    /// Check if a proof is spent
+    /// Check a spend against the spend policy, if one is set
+    async fn check_spend_policy(&self, request: &SpendRequest) -> Result<(), Error> {
+        if let Some(policy) = &self.spend_policy {
+            policy.check(request).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Count a completed spend towards the limits of the spend policy
+    async fn record_spend(&self, request: &SpendRequest) {
+        if let Some(policy) = &self.spend_policy {
+            policy.record(request).await;
+        }
+    }
+
+    /// Set the state of proofs stored in the wallet
+    async fn set_proofs_state(&self, proofs: &Proofs, state: State) -> Result<(), Error> {
+        for proof in proofs {
+            self.localstore.set_proof_state(proof.y()?, state).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Check the state of proofs with the mint
+    ///
+    /// The proofs are checked in requests of at most
+    /// [`CHECK_STATE_CHUNK_SIZE`] proofs, up to [`CHECK_STATE_CONCURRENCY`] at
+    /// once. States are returned in the order of `proofs`, and stored proofs
+    /// the mint reports as spent are marked spent.
     #[instrument(skip(self, proofs), fields(mint_url = %mint_url))]
     pub async fn check_proofs_spent(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         proofs: Proofs,
     ) -> Result<Vec<ProofState>, Error> {
-        let spendable = self
-            .client
-            .post_check_state(
-                mint_url.try_into()?,
-                proofs
-                    .into_iter()
-                    // Find Y for the secret
-                    .flat_map(|p| hash_to_curve(p.secret.as_bytes()))
-                    .collect::<Vec<PublicKey>>(),
-            )
-            .await?;
+        let url: url::Url = mint_url.clone().try_into()?;
+        let ys = proofs
+            .iter()
+            .map(|p| p.y())
+            .collect::<Result<Vec<PublicKey>, _>>()?;
+
+        let stored: HashSet<PublicKey> = self
+            .localstore
+            .get_proofs(Some(mint_url), None, None, None)
+            .await?
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|p| p.state != State::Spent)
+            .map(|p| p.y)
+            .collect();
+
+        let responses = stream::iter(ys.chunks(CHECK_STATE_CHUNK_SIZE))
+            .map(|chunk| self.client.post_check_state(url.clone(), chunk.to_vec()))
+            .buffered(CHECK_STATE_CONCURRENCY);
+        futures::pin_mut!(responses);
 
-        Ok(spendable.states)
+        let mut states = Vec::with_capacity(ys.len());
+        while let Some(response) = responses.next().await {
+            let response = response?;
+
+            for state in &response.states {
+                if state.state == State::Spent && stored.contains(&state.y) {
+                    self.localstore
+                        .set_proof_state(state.y, State::Spent)
+                        .await?;
+                }
+            }
+
+            states.extend(response.states);
+        }
+
+        Ok(states)
     }
 
     /// Checks pending proofs for spent status
     #[instrument(skip(self))]
     pub async fn check_all_pending_proofs(
         &self,
-        mint_url: Option<UncheckedUrl>,
+        mint_url: Option<MintUrl>,
         unit: Option<CurrencyUnit>,
     ) -> Result<Amount, Error> {
         let mints = match mint_url {
@@ -419,16 +648,24 @@ impl Wallet {
     }
 
     /// Mint Quote
+    ///
+    /// `description` is put in the invoice if the mint supports it.
     #[instrument(skip(self), fields(mint_url = %mint_url))]
     pub async fn mint_quote(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         amount: Amount,
         unit: CurrencyUnit,
+        description: Option<String>,
     ) -> Result<MintQuote, Error> {
         let quote_res = self
             .client
-            .post_mint_quote(mint_url.clone().try_into()?, amount, unit.clone())
+            .post_mint_quote(
+                mint_url.clone().try_into()?,
+                amount,
+                unit.clone(),
+                description,
+            )
             .await?;
 
         let quote = MintQuote {
@@ -439,6 +676,7 @@ impl Wallet {
             request: quote_res.request,
             paid: quote_res.paid,
             expiry: quote_res.expiry.unwrap_or(0),
+            exchange_rate: quote_res.exchange_rate,
         };
 
         self.localstore.add_mint_quote(quote.clone()).await?;
@@ -450,7 +688,7 @@ impl Wallet {
     #[instrument(skip(self, quote_id), fields(mint_url = %mint_url))]
     pub async fn mint_quote_status(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         quote_id: &str,
     ) -> Result<MintQuoteBolt11Response, Error> {
         let response = self
@@ -474,26 +712,56 @@ impl Wallet {
     }
 
     /// Check status of pending mint quotes
+    ///
+    /// Claims the tokens of every stored quote that is paid but was never
+    /// issued, e.g. because the app was closed after paying the invoice, and
+    /// removes unpaid quotes that expired. Should be called on startup.
     pub async fn check_all_mint_quotes(&self) -> Result<Amount, Error> {
+        self.claim_paid_mint_quotes(None).await
+    }
+
+    /// Claim paid mint quotes, of `mint_url` only if set
+    ///
+    /// A quote that cannot be checked or claimed is logged and kept so it is
+    /// retried on the next call.
+    #[instrument(skip(self))]
+    async fn claim_paid_mint_quotes(&self, mint_url: Option<&MintUrl>) -> Result<Amount, Error> {
         let mint_quotes = self.localstore.get_mint_quotes().await?;
         let mut total_amount = Amount::ZERO;
 
         for mint_quote in mint_quotes {
-            let mint_quote_response = self
+            if mint_url.is_some_and(|mint_url| mint_quote.mint_url.ne(mint_url)) {
+                continue;
+            }
+
+            let mint_quote_response = match self
                 .mint_quote_status(mint_quote.mint_url.clone(), &mint_quote.id)
-                .await?;
+                .await
+            {
+                Ok(response) => response,
+                Err(err) => {
+                    tracing::warn!("Could not check mint quote {}: {}", mint_quote.id, err);
+                    continue;
+                }
+            };
 
             if mint_quote_response.paid {
-                let amount = self
+                match self
                     .mint(
                         mint_quote.mint_url,
                         &mint_quote.id,
                         SplitTarget::default(),
                         None,
                     )
-                    .await?;
-                total_amount += amount;
+                    .await
+                {
+                    Ok(amount) => total_amount += amount,
+                    Err(err) => {
+                        tracing::warn!("Could not claim mint quote {}: {}", mint_quote.id, err)
+                    }
+                }
             } else if mint_quote.expiry.le(&unix_time()) {
+                self.localstore.remove_pending_mint(&mint_quote.id).await?;
                 self.localstore.remove_mint_quote(&mint_quote.id).await?;
             }
         }
@@ -503,7 +771,7 @@ impl Wallet {
     #[instrument(skip(self), fields(mint_url = %mint_url))]
     async fn active_mint_keyset(
         &self,
-        mint_url: &UncheckedUrl,
+        mint_url: &MintUrl,
         unit: &CurrencyUnit,
     ) -> Result<Id, Error> {
         if let Some(keysets) = self.localstore.get_mint_keysets(mint_url.clone()).await? {
@@ -534,7 +802,7 @@ impl Wallet {
     #[instrument(skip(self), fields(mint_url = %mint_url))]
     async fn active_keys(
         &self,
-        mint_url: &UncheckedUrl,
+        mint_url: &MintUrl,
         unit: &CurrencyUnit,
     ) -> Result<Option<Keys>, Error> {
         let active_keyset_id = self.active_mint_keyset(mint_url, unit).await?;
@@ -557,14 +825,24 @@ impl Wallet {
     }
 
     /// Mint
-    #[instrument(skip(self, quote_id), fields(mint_url = %mint_url))]
+    #[instrument(
+        skip(self, quote_id),
+        fields(
+            mint_url = %mint_url,
+            quote_id = tracing::field::Empty,
+            amount = tracing::field::Empty,
+            outputs = tracing::field::Empty,
+        )
+    )]
     pub async fn mint(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         quote_id: &str,
         amount_split_target: SplitTarget,
         spending_conditions: Option<SpendingConditions>,
     ) -> Result<Amount, Error> {
+        record!("quote_id" = quote_id);
+
         // Check that mint is in store of mints
         if self.localstore.get_mint(mint_url.clone()).await?.is_none() {
             self.add_mint(mint_url.clone()).await?;
@@ -573,7 +851,8 @@ impl Wallet {
         let quote_info = self.localstore.get_mint_quote(quote_id).await?;
 
         let quote_info = if let Some(quote) = quote_info {
-            if quote.expiry.le(&unix_time()) && quote.expiry.ne(&0) {
+            // A paid quote can still be claimed after it expired
+            if !quote.paid && quote.expiry.le(&unix_time()) && quote.expiry.ne(&0) {
                 return Err(Error::QuoteExpired);
             }
 
@@ -582,6 +861,12 @@ impl Wallet {
             return Err(Error::QuoteUnknown);
         };
 
+        // A previous attempt is resumed with its outputs, the mint may have
+        // signed them already
+        if let Some(pending_mint) = self.localstore.get_pending_mint(quote_id).await? {
+            return self.resume_pending_mint(pending_mint).await;
+        }
+
         let active_keyset_id = self.active_mint_keyset(&mint_url, &quote_info.unit).await?;
 
         let count = self
@@ -601,27 +886,162 @@ impl Wallet {
             None => PreMintSecrets::from_xpriv(
                 active_keyset_id,
                 count,
-                self.xpriv,
+                self.xpriv().await?,
                 quote_info.amount,
                 false,
                 &amount_split_target,
             )?,
         };
 
-        let mint_res = self
+        // Stored before the request, so that the proofs can be rebuilt if the
+        // wallet stops before storing them
+        let pending_mint = PendingMint {
+            quote_id: quote_info.id,
+            mint_url,
+            unit: quote_info.unit,
+            premint_secrets,
+        };
+        self.localstore
+            .add_pending_mint(pending_mint.clone())
+            .await?;
+
+        if spending_conditions.is_none() {
+            // Update counter for keyset
+            self.localstore
+                .increment_keyset_counter(
+                    &active_keyset_id,
+                    pending_mint.premint_secrets.len() as u32,
+                )
+                .await?;
+        }
+
+        self.issue_pending_mint(pending_mint).await
+    }
+
+    /// Rebuild the proofs of mint requests interrupted before they were stored
+    ///
+    /// Requests that still fail, e.g. because the quote is not paid yet, are
+    /// kept for the next attempt. Returns the amount recovered.
+    #[instrument(skip(self))]
+    pub async fn recover_pending_mints(&self) -> Result<Amount, Error> {
+        let mut recovered = Amount::ZERO;
+
+        for pending_mint in self.localstore.get_pending_mints().await? {
+            let quote_id = pending_mint.quote_id.clone();
+            match self.resume_pending_mint(pending_mint).await {
+                Ok(amount) => recovered += amount,
+                Err(err) => {
+                    tracing::warn!("Could not recover mint of quote {}: {}", quote_id, err)
+                }
+            }
+        }
+
+        Ok(recovered)
+    }
+
+    /// Complete `pending_mint` with the signatures the mint already made
+    ///
+    /// The signatures are looked up with a restore (NUT-09). If the mint has
+    /// none the outputs were not signed and the mint request is sent again,
+    /// mints caching responses (NUT-19) answer it even if they were.
+    async fn resume_pending_mint(&self, pending_mint: PendingMint) -> Result<Amount, Error> {
+        let restore_request = RestoreRequest {
+            outputs: pending_mint.premint_secrets.blinded_messages(),
+        };
+
+        let response = match self
+            .client
+            .post_restore(pending_mint.mint_url.clone().try_into()?, restore_request)
+            .await
+        {
+            Ok(response) if !response.signatures.is_empty() => response,
+            Ok(_) => return self.issue_pending_mint(pending_mint).await,
+            Err(err) => {
+                tracing::warn!(
+                    "Could not restore outputs of quote {}: {}",
+                    pending_mint.quote_id,
+                    err
+                );
+                return self.issue_pending_mint(pending_mint).await;
+            }
+        };
+
+        let secrets = response
+            .outputs
+            .iter()
+            .map(|output| {
+                pending_mint
+                    .premint_secrets
+                    .secrets
+                    .iter()
+                    .find(|p| p.blinded_message.eq(output))
+                    .cloned()
+                    .ok_or(Error::UnknownKey)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.store_pending_mint(
+            pending_mint,
+            response.signatures,
+            PreMintSecrets { secrets },
+        )
+        .await
+    }
+
+    /// Send the outputs of `pending_mint` to the mint
+    async fn issue_pending_mint(&self, pending_mint: PendingMint) -> Result<Amount, Error> {
+        let mint_res = match self
             .client
             .post_mint(
-                mint_url.clone().try_into()?,
-                quote_id,
-                premint_secrets.clone(),
+                pending_mint.mint_url.clone().try_into()?,
+                &pending_mint.quote_id,
+                pending_mint.premint_secrets.clone(),
             )
-            .await?;
+            .await
+        {
+            Ok(mint_res) => mint_res,
+            Err(err) => {
+                // Outputs of a keyset the mint does not sign with are not
+                // resumed, the next attempt builds them for the active keyset
+                if let ErrorCode::KeysetInactive | ErrorCode::KeysetNotFound = err.error_code() {
+                    self.localstore
+                        .remove_pending_mint(&pending_mint.quote_id)
+                        .await?;
+                }
+                self.handle_keyset_error(&pending_mint.mint_url, &err).await;
+                return Err(err);
+            }
+        };
+
+        let premint_secrets = pending_mint.premint_secrets.clone();
+        self.store_pending_mint(pending_mint, mint_res.signatures, premint_secrets)
+            .await
+    }
+
+    /// Store the proofs of the `signatures` of `premint_secrets` and remove
+    /// `pending_mint` with its quote
+    async fn store_pending_mint(
+        &self,
+        pending_mint: PendingMint,
+        signatures: Vec<BlindSignature>,
+        premint_secrets: PreMintSecrets,
+    ) -> Result<Amount, Error> {
+        let PendingMint {
+            quote_id,
+            mint_url,
+            unit,
+            ..
+        } = pending_mint;
 
-        let keys = self.get_keyset_keys(&mint_url, active_keyset_id).await?;
+        let keyset_id = match premint_secrets.secrets.first() {
+            Some(premint) => premint.blinded_message.keyset_id,
+            None => return Err(Error::UnknownKey),
+        };
+        let keys = self.get_keyset_keys(&mint_url, keyset_id).await?;
 
         // Verify the signature DLEQ is valid
         {
-            for (sig, premint) in mint_res.signatures.iter().zip(&premint_secrets.secrets) {
+            for (sig, premint) in signatures.iter().zip(&premint_secrets.secrets) {
                 let keys = self.get_keyset_keys(&mint_url, sig.keyset_id).await?;
                 let key = keys.amount_key(sig.amount).ok_or(Error::UnknownKey)?;
                 match sig.verify_dleq(key, premint.blinded_message.blinded_secret) {
@@ -632,54 +1052,88 @@ impl Wallet {
         }
 
         let proofs = construct_proofs(
-            mint_res.signatures,
+            signatures,
             premint_secrets.rs(),
             premint_secrets.secrets(),
             &keys,
         )?;
 
         let minted_amount = proofs.iter().map(|p| p.amount).sum();
-
-        // Remove filled quote from store
-        self.localstore.remove_mint_quote(&quote_info.id).await?;
-
-        if spending_conditions.is_none() {
-            // Update counter for keyset
-            self.localstore
-                .increment_keyset_counter(&active_keyset_id, proofs.len() as u32)
-                .await?;
-        }
+        record!(
+            "amount" = u64::from(minted_amount),
+            "outputs" = proofs.len()
+        );
 
         let proofs = proofs
             .into_iter()
-            .flat_map(|proof| {
-                ProofInfo::new(
-                    proof,
-                    mint_url.clone(),
-                    State::Unspent,
-                    quote_info.unit.clone(),
-                )
-            })
+            .flat_map(|proof| ProofInfo::new(proof, mint_url.clone(), State::Unspent, unit.clone()))
             .collect();
 
         // Add new proofs to store
         self.localstore.add_proofs(proofs).await?;
 
+        // Remove filled quote from store
+        self.localstore.remove_pending_mint(&quote_id).await?;
+        self.localstore.remove_mint_quote(&quote_id).await?;
+
+        self.record_transaction(Transaction::new(
+            mint_url,
+            TransactionKind::Mint,
+            minted_amount,
+            Amount::ZERO,
+            unit,
+            None,
+        ))
+        .await;
+
         Ok(minted_amount)
     }
 
     /// Swap
-    #[instrument(skip(self, input_proofs), fields(mint_url = %mint_url))]
     pub async fn swap(
         &self,
-        mint_url: &UncheckedUrl,
+        mint_url: &MintUrl,
+        unit: &CurrencyUnit,
+        amount: Option<Amount>,
+        amount_split_target: &SplitTarget,
+        input_proofs: Proofs,
+        spending_conditions: Option<SpendingConditions>,
+    ) -> Result<Option<Proofs>, Error> {
+        self.swap_inner(
+            mint_url,
+            unit,
+            amount,
+            amount_split_target,
+            input_proofs,
+            spending_conditions,
+            None,
+        )
+        .await
+    }
+
+    /// Swap, with the outputs of `privacy` if set
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(
+        skip(self, input_proofs),
+        fields(
+            mint_url = %mint_url,
+            inputs = tracing::field::Empty,
+            outputs = tracing::field::Empty,
+        )
+    )]
+    async fn swap_inner(
+        &self,
+        mint_url: &MintUrl,
         unit: &CurrencyUnit,
         amount: Option<Amount>,
         amount_split_target: &SplitTarget,
         input_proofs: Proofs,
         spending_conditions: Option<SpendingConditions>,
+        privacy: Option<PrivacyMode>,
     ) -> Result<Option<Proofs>, Error> {
-        let pre_swap = self
+        record!("inputs" = input_proofs.len());
+
+        let pre_swap = match self
             .create_swap(
                 mint_url,
                 unit,
@@ -687,13 +1141,33 @@ impl Wallet {
                 amount_split_target,
                 input_proofs.clone(),
                 spending_conditions,
+                privacy,
             )
-            .await?;
+            .await
+        {
+            Ok(pre_swap) => pre_swap,
+            Err(err) => {
+                // Inputs selected by a send are already pending
+                self.set_proofs_state(&input_proofs, State::Unspent).await?;
+                return Err(err);
+            }
+        };
 
-        let swap_response = self
+        // Inputs stay pending until the swap outputs are stored
+        self.set_proofs_state(&input_proofs, State::Pending).await?;
+
+        let swap_response = match self
             .client
             .post_swap(mint_url.clone().try_into()?, pre_swap.swap_request)
-            .await?;
+            .await
+        {
+            Ok(swap_response) => swap_response,
+            Err(err) => {
+                self.set_proofs_state(&input_proofs, State::Unspent).await?;
+                self.handle_keyset_error(mint_url, &err).await;
+                return Err(err);
+            }
+        };
 
         let active_keys = self.active_keys(mint_url, unit).await?.unwrap();
 
@@ -703,19 +1177,22 @@ impl Wallet {
             pre_swap.pre_mint_secrets.secrets(),
             &active_keys,
         )?;
+        record!("outputs" = post_swap_proofs.len());
 
         let active_keyset_id = self.active_mint_keyset(mint_url, unit).await?;
 
-        // FIXME: Should not increment keyset counter for condition proofs
         self.localstore
-            .increment_keyset_counter(&active_keyset_id, post_swap_proofs.len() as u32)
+            .increment_keyset_counter(&active_keyset_id, pre_swap.derived_count)
             .await?;
 
         let mut keep_proofs = Proofs::new();
+        let mut added_proofs = Vec::new();
         let proofs_to_send;
 
         match amount {
             Some(amount) => {
+                // Largest proofs first, outputs may have been shuffled
+                post_swap_proofs.sort_by_key(|p| p.amount);
                 post_swap_proofs.reverse();
 
                 let mut left_proofs = vec![];
@@ -748,15 +1225,9 @@ impl Wallet {
                     );
                 }
 
-                let send_proofs_info = send_proofs
-                    .clone()
-                    .into_iter()
-                    .flat_map(|proof| {
-                        ProofInfo::new(proof, mint_url.clone(), State::Reserved, unit.clone())
-                    })
-                    .collect();
-
-                self.localstore.add_proofs(send_proofs_info).await?;
+                added_proofs.extend(send_proofs.clone().into_iter().flat_map(|proof| {
+                    ProofInfo::new(proof, mint_url.clone(), State::Reserved, unit.clone())
+                }));
 
                 proofs_to_send = Some(send_proofs);
             }
@@ -766,41 +1237,53 @@ impl Wallet {
             }
         }
 
-        for proof in input_proofs {
-            self.localstore
-                .set_proof_state(proof.y()?, State::Reserved)
-                .await?;
-        }
+        added_proofs.extend(keep_proofs.into_iter().flat_map(|proof| {
+            ProofInfo::new(proof, mint_url.clone(), State::Unspent, unit.clone())
+        }));
 
-        let keep_proofs = keep_proofs
-            .into_iter()
-            .flat_map(|proof| ProofInfo::new(proof, mint_url.clone(), State::Unspent, unit.clone()))
-            .collect();
+        let input_ys = input_proofs
+            .iter()
+            .map(|p| p.y())
+            .collect::<Result<Vec<PublicKey>, _>>()?;
 
-        self.localstore.add_proofs(keep_proofs).await?;
+        self.localstore
+            .update_proofs(added_proofs, input_ys)
+            .await?;
 
         Ok(proofs_to_send)
     }
 
     /// Create Swap Payload
+    #[allow(clippy::too_many_arguments)]
     #[instrument(skip(self, proofs), fields(mint_url = %mint_url))]
     async fn create_swap(
         &self,
-        mint_url: &UncheckedUrl,
+        mint_url: &MintUrl,
         unit: &CurrencyUnit,
         amount: Option<Amount>,
         amount_split_target: &SplitTarget,
         proofs: Proofs,
         spending_conditions: Option<SpendingConditions>,
+        privacy: Option<PrivacyMode>,
     ) -> Result<PreSwap, Error> {
+        self.ensure_proofs_unit(mint_url, unit, &proofs).await?;
+
         let active_keyset_id = self.active_mint_keyset(mint_url, unit).await.unwrap();
 
         // Desired amount is either amount passwed or value of all proof
-        let proofs_total = proofs.iter().map(|p| p.amount).sum();
+        let proofs_total = Amount::try_sum(proofs.iter().map(|p| p.amount))?;
 
         let desired_amount = amount.unwrap_or(proofs_total);
-        let change_amount = proofs_total - desired_amount;
+        let change_amount = proofs_total
+            .checked_sub(desired_amount)
+            .ok_or(Error::InsufficientFunds)?;
+        let change_split_target = match privacy {
+            Some(privacy) => privacy.change_split(change_amount),
+            None => amount_split_target.clone(),
+        };
 
+        // Change is always derived from the counter so a restore from the seed
+        // recovers it, only outputs with spending conditions are random
         let (mut desired_messages, change_messages) = match spending_conditions {
             Some(conditions) => {
                 let count = self
@@ -813,10 +1296,10 @@ impl Wallet {
                 let change_premint_secrets = PreMintSecrets::from_xpriv(
                     active_keyset_id,
                     count,
-                    self.xpriv,
+                    self.xpriv().await?,
                     change_amount,
                     false,
-                    amount_split_target,
+                    &change_split_target,
                 )?;
 
                 (
@@ -840,7 +1323,7 @@ impl Wallet {
                 let premint_secrets = PreMintSecrets::from_xpriv(
                     active_keyset_id,
                     count,
-                    self.xpriv,
+                    self.xpriv().await?,
                     desired_amount,
                     false,
                     amount_split_target,
@@ -851,46 +1334,161 @@ impl Wallet {
                 let change_premint_secrets = PreMintSecrets::from_xpriv(
                     active_keyset_id,
                     count,
-                    self.xpriv,
+                    self.xpriv().await?,
                     change_amount,
                     false,
-                    amount_split_target,
+                    &change_split_target,
                 )?;
 
                 (premint_secrets, change_premint_secrets)
             }
         };
 
+        let derived_count = desired_messages
+            .iter()
+            .chain(change_messages.iter())
+            .filter(|p| nut10::Secret::try_from(p.secret.clone()).is_err())
+            .count() as u32;
+
         // Combine the BlindedMessages totaling the desired amount with change
         desired_messages.combine(change_messages);
-        // Sort the premint secrets to avoid finger printing
-        desired_messages.sort_secrets();
+        // Sort the premint secrets to avoid finger printing, or shuffle them so
+        // that the position of an output does not tell whether it is change
+        match privacy {
+            Some(_) => desired_messages.secrets.shuffle(&mut rand::thread_rng()),
+            None => desired_messages.sort_secrets(),
+        }
 
         let swap_request = SwapRequest::new(proofs, desired_messages.blinded_messages());
 
         Ok(PreSwap {
             pre_mint_secrets: desired_messages,
             swap_request,
+            derived_count,
         })
     }
 
+    /// Check that all `proofs` are from keysets of `unit`
+    ///
+    /// A swap or melt can only spend proofs of a single unit. Keysets missing
+    /// from the local store are fetched from the mint before giving up.
+    async fn ensure_proofs_unit(
+        &self,
+        mint_url: &MintUrl,
+        unit: &CurrencyUnit,
+        proofs: &Proofs,
+    ) -> Result<(), Error> {
+        let unit_keysets = |keysets: Vec<KeySetInfo>| -> (HashSet<Id>, HashSet<Id>) {
+            let known = keysets.iter().map(|k| k.id).collect();
+            let matching = keysets
+                .iter()
+                .filter(|k| k.unit == *unit)
+                .map(|k| k.id)
+                .collect();
+            (known, matching)
+        };
+
+        let (mut known, mut matching) = unit_keysets(
+            self.localstore
+                .get_mint_keysets(mint_url.clone())
+                .await?
+                .unwrap_or_default(),
+        );
+
+        if proofs.iter().any(|p| !known.contains(&p.keyset_id)) {
+            (known, matching) = unit_keysets(self.get_mint_keysets(mint_url).await?);
+        }
+
+        for proof in proofs {
+            if !known.contains(&proof.keyset_id) {
+                return Err(Error::KeysetNotFound);
+            }
+            if !matching.contains(&proof.keyset_id) {
+                return Err(Error::UnitMismatch(unit.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
     /// Send
-    #[instrument(skip(self), fields(mint_url = %mint_url))]
     pub async fn send(
         &self,
-        mint_url: &UncheckedUrl,
+        mint_url: &MintUrl,
         unit: CurrencyUnit,
         memo: Option<String>,
         amount: Amount,
         amount_split_target: &SplitTarget,
         conditions: Option<SpendingConditions>,
     ) -> Result<String, Error> {
-        let (condition_input_proofs, input_proofs) = self
-            .select_proofs(
-                mint_url.clone(),
-                unit.clone(),
-                amount,
-                conditions.clone().map(|p| vec![p]),
+        self.send_with_metadata(
+            mint_url,
+            unit,
+            memo,
+            BTreeMap::new(),
+            amount,
+            amount_split_target,
+            conditions,
+        )
+        .await
+    }
+
+    /// Send token carrying application specific `metadata`
+    ///
+    /// Like [`Wallet::send`], see [`Token::with_metadata`]. The metadata is
+    /// recorded in the transaction history.
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip(self, metadata), fields(mint_url = %mint_url, proofs = tracing::field::Empty))]
+    pub async fn send_with_metadata(
+        &self,
+        mint_url: &MintUrl,
+        unit: CurrencyUnit,
+        memo: Option<String>,
+        metadata: BTreeMap<String, String>,
+        amount: Amount,
+        amount_split_target: &SplitTarget,
+        conditions: Option<SpendingConditions>,
+    ) -> Result<String, Error> {
+        self.send_inner(
+            mint_url,
+            unit,
+            memo,
+            metadata,
+            amount,
+            amount_split_target,
+            conditions,
+            None,
+        )
+        .await
+    }
+
+    /// Send, always swapping the selected proofs with `privacy` if set
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn send_inner(
+        &self,
+        mint_url: &MintUrl,
+        unit: CurrencyUnit,
+        memo: Option<String>,
+        metadata: BTreeMap<String, String>,
+        amount: Amount,
+        amount_split_target: &SplitTarget,
+        conditions: Option<SpendingConditions>,
+        privacy: Option<PrivacyMode>,
+    ) -> Result<String, Error> {
+        let spend = SpendRequest {
+            kind: SpendKind::Send,
+            mint_url: mint_url.clone(),
+            unit: unit.clone(),
+            amount,
+        };
+        self.check_spend_policy(&spend).await?;
+
+        let (condition_input_proofs, input_proofs, _reservation) = self
+            .reserve_proofs(
+                mint_url.clone(),
+                unit.clone(),
+                amount,
+                conditions.clone().map(|p| vec![p]),
             )
             .await?;
 
@@ -905,15 +1503,25 @@ impl Wallet {
 
                 let top_up_proofs = match needed_amount > Amount::ZERO {
                     true => {
-                        self.swap(
-                            mint_url,
-                            &unit,
-                            Some(needed_amount),
-                            amount_split_target,
-                            input_proofs,
-                            conditions,
-                        )
-                        .await?
+                        match self
+                            .swap_inner(
+                                mint_url,
+                                &unit,
+                                Some(needed_amount),
+                                amount_split_target,
+                                input_proofs,
+                                conditions,
+                                privacy,
+                            )
+                            .await
+                        {
+                            Ok(top_up_proofs) => top_up_proofs,
+                            Err(err) => {
+                                self.set_proofs_state(&condition_input_proofs, State::Unspent)
+                                    .await?;
+                                return Err(err);
+                            }
+                        }
                     }
                     false => Some(vec![]),
                 };
@@ -933,15 +1541,16 @@ impl Wallet {
                     .sum::<Amount>()
                     .eq(&amount)
                 {
-                    true => Some(input_proofs),
-                    false => {
-                        self.swap(
+                    true if privacy.is_none() => Some(input_proofs),
+                    _ => {
+                        self.swap_inner(
                             mint_url,
                             &unit,
                             Some(amount),
                             amount_split_target,
                             input_proofs,
                             conditions,
+                            privacy,
                         )
                         .await?
                     }
@@ -950,22 +1559,241 @@ impl Wallet {
         };
 
         let send_proofs = send_proofs.ok_or(Error::InsufficientFunds)?;
+        record!("proofs" = send_proofs.len());
         for proof in send_proofs.iter() {
             self.localstore
                 .set_proof_state(proof.y()?, State::Reserved)
                 .await?;
         }
 
-        Ok(self
-            .proof_to_token(mint_url.clone(), send_proofs, memo, Some(unit.clone()))?
-            .to_string())
+        let token = Token::new(
+            mint_url.clone(),
+            send_proofs,
+            memo.clone(),
+            Some(unit.clone()),
+        )?
+        .with_metadata(metadata.clone())
+        .to_string();
+
+        self.record_spend(&spend).await;
+        let mut transaction = Transaction::new(
+            mint_url.clone(),
+            TransactionKind::Send,
+            amount,
+            Amount::ZERO,
+            unit,
+            memo,
+        );
+        transaction.metadata = metadata;
+        self.record_transaction(transaction).await;
+
+        Ok(token)
+    }
+
+    /// Send to several recipients with a single swap
+    ///
+    /// Each recipient is an amount with optional spending conditions and memo.
+    /// The inputs are swapped once for the outputs of all recipients and the
+    /// change, and one token is returned per recipient, in order.
+    #[instrument(skip(self, recipients), fields(mint_url = %mint_url, recipients = recipients.len()))]
+    pub async fn send_many(
+        &self,
+        mint_url: &MintUrl,
+        unit: CurrencyUnit,
+        recipients: Vec<(Amount, Option<SpendingConditions>, Option<String>)>,
+        amount_split_target: &SplitTarget,
+    ) -> Result<Vec<String>, Error> {
+        if recipients.is_empty() {
+            return Err(Error::Custom("No recipients".to_string()));
+        }
+        if recipients
+            .iter()
+            .any(|(amount, _, _)| amount.eq(&Amount::ZERO))
+        {
+            return Err(Error::Custom(
+                "Recipient amount must not be zero".to_string(),
+            ));
+        }
+
+        let amount = Amount::try_sum(recipients.iter().map(|(amount, _, _)| *amount))?;
+        let spend = SpendRequest {
+            kind: SpendKind::Send,
+            mint_url: mint_url.clone(),
+            unit: unit.clone(),
+            amount,
+        };
+        self.check_spend_policy(&spend).await?;
+
+        let (_, input_proofs, _reservation) = self
+            .reserve_proofs(mint_url.clone(), unit.clone(), amount, None)
+            .await?;
+
+        let send_proofs = match self
+            .swap_for_recipients(
+                mint_url,
+                &unit,
+                &recipients,
+                amount_split_target,
+                &input_proofs,
+            )
+            .await
+        {
+            Ok(send_proofs) => send_proofs,
+            Err(err) => {
+                self.set_proofs_state(&input_proofs, State::Unspent).await?;
+                return Err(err);
+            }
+        };
+
+        let mut tokens = Vec::with_capacity(recipients.len());
+        for ((amount, _, memo), proofs) in recipients.into_iter().zip(send_proofs) {
+            tokens.push(
+                Token::new(mint_url.clone(), proofs, memo.clone(), Some(unit.clone()))?.to_string(),
+            );
+            self.record_transaction(Transaction::new(
+                mint_url.clone(),
+                TransactionKind::Send,
+                amount,
+                Amount::ZERO,
+                unit.clone(),
+                memo,
+            ))
+            .await;
+        }
+        self.record_spend(&spend).await;
+
+        Ok(tokens)
+    }
+
+    /// Swap `input_proofs` for the proofs of each recipient and change
+    ///
+    /// Recipient proofs are stored reserved, change unspent.
+    async fn swap_for_recipients(
+        &self,
+        mint_url: &MintUrl,
+        unit: &CurrencyUnit,
+        recipients: &[(Amount, Option<SpendingConditions>, Option<String>)],
+        amount_split_target: &SplitTarget,
+        input_proofs: &Proofs,
+    ) -> Result<Vec<Proofs>, Error> {
+        let active_keyset_id = self.active_mint_keyset(mint_url, unit).await?;
+        let input_total = Amount::try_sum(input_proofs.iter().map(|p| p.amount))?;
+        let send_total = Amount::try_sum(recipients.iter().map(|(amount, _, _)| *amount))?;
+        let change_amount = input_total
+            .checked_sub(send_total)
+            .ok_or(Error::InsufficientFunds)?;
+
+        let start_count = self
+            .localstore
+            .get_keyset_counter(&active_keyset_id)
+            .await?
+            .map_or(0, |c| c + 1);
+        let mut count = start_count;
+
+        // Index of the recipient by secret, change has none
+        let mut recipient_by_secret = HashMap::new();
+        let mut pre_mint_secrets = PreMintSecrets::default();
+
+        for (index, (amount, conditions, _)) in recipients.iter().enumerate() {
+            let recipient_secrets = match conditions {
+                Some(conditions) => PreMintSecrets::with_conditions(
+                    active_keyset_id,
+                    *amount,
+                    amount_split_target,
+                    conditions,
+                )?,
+                None => {
+                    let secrets = PreMintSecrets::from_xpriv(
+                        active_keyset_id,
+                        count,
+                        self.xpriv().await?,
+                        *amount,
+                        false,
+                        amount_split_target,
+                    )?;
+                    count += secrets.len() as u32;
+                    secrets
+                }
+            };
+
+            for secret in recipient_secrets.secrets() {
+                recipient_by_secret.insert(secret, index);
+            }
+            pre_mint_secrets.combine(recipient_secrets);
+        }
+
+        let change_secrets = PreMintSecrets::from_xpriv(
+            active_keyset_id,
+            count,
+            self.xpriv().await?,
+            change_amount,
+            false,
+            amount_split_target,
+        )?;
+        count += change_secrets.len() as u32;
+        pre_mint_secrets.combine(change_secrets);
+
+        // Sort the premint secrets to avoid finger printing
+        pre_mint_secrets.sort_secrets();
+
+        let swap_request =
+            SwapRequest::new(input_proofs.clone(), pre_mint_secrets.blinded_messages());
+
+        self.set_proofs_state(input_proofs, State::Pending).await?;
+        let swap_response = self
+            .client
+            .post_swap(mint_url.clone().try_into()?, swap_request)
+            .await?;
+
+        let active_keys = self
+            .active_keys(mint_url, unit)
+            .await?
+            .ok_or(Error::NoActiveKeyset)?;
+        let proofs = construct_proofs(
+            swap_response.signatures,
+            pre_mint_secrets.rs(),
+            pre_mint_secrets.secrets(),
+            &active_keys,
+        )?;
+
+        self.localstore
+            .increment_keyset_counter(&active_keyset_id, count - start_count)
+            .await?;
+
+        let mut send_proofs = vec![Proofs::new(); recipients.len()];
+        let mut added_proofs = Vec::with_capacity(proofs.len());
+        for proof in proofs {
+            let state = match recipient_by_secret.get(&proof.secret) {
+                Some(index) => {
+                    send_proofs[*index].push(proof.clone());
+                    State::Reserved
+                }
+                None => State::Unspent,
+            };
+            added_proofs.push(ProofInfo::new(
+                proof,
+                mint_url.clone(),
+                state,
+                unit.clone(),
+            )?);
+        }
+
+        let input_ys = input_proofs
+            .iter()
+            .map(|p| p.y())
+            .collect::<Result<Vec<PublicKey>, _>>()?;
+        self.localstore
+            .update_proofs(added_proofs, input_ys)
+            .await?;
+
+        Ok(send_proofs)
     }
 
     /// Melt Quote
     #[instrument(skip(self), fields(mint_url = %mint_url))]
     pub async fn melt_quote(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         unit: CurrencyUnit,
         request: String,
     ) -> Result<MeltQuote, Error> {
@@ -974,7 +1802,7 @@ impl Wallet {
             .post_melt_quote(
                 mint_url.clone().try_into()?,
                 unit.clone(),
-                Bolt11Invoice::from_str(&request.clone())?,
+                self.parse_invoice(&request)?,
             )
             .await?;
 
@@ -984,8 +1812,10 @@ impl Wallet {
             request,
             unit,
             fee_reserve: quote_res.fee_reserve,
-            paid: quote_res.paid,
+            state: quote_res.state,
             expiry: quote_res.expiry,
+            exchange_rate: quote_res.exchange_rate,
+            pending_inputs: Vec::new(),
         };
 
         self.localstore.add_melt_quote(quote.clone()).await?;
@@ -994,22 +1824,40 @@ impl Wallet {
     }
 
     /// Melt quote status
+    ///
+    /// When the payment of the quote failed the inputs of the melt are
+    /// released and can be spent again.
     #[instrument(skip(self, quote_id), fields(mint_url = %mint_url))]
     pub async fn melt_quote_status(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         quote_id: &str,
     ) -> Result<MeltQuoteBolt11Response, Error> {
         let response = self
             .client
-            .get_melt_quote_status(mint_url.try_into()?, quote_id)
+            .get_melt_quote_status(mint_url.clone().try_into()?, quote_id)
             .await?;
 
         match self.localstore.get_melt_quote(quote_id).await? {
             Some(quote) => {
                 let mut quote = quote;
 
-                quote.paid = response.paid;
+                if response.state == MeltQuoteState::Failed {
+                    match self.release_pending_proofs(&mint_url, &quote.unit).await {
+                        Ok(_) => (),
+                        // Inputs can only be released once their state is known
+                        Err(Error::UnsupportedOperation(operation)) => {
+                            tracing::warn!(
+                                "Connector does not support {}, keeping inputs of quote {} pending",
+                                operation,
+                                quote_id
+                            );
+                        }
+                        Err(err) => return Err(err),
+                    }
+                }
+
+                quote.state = response.state;
                 self.localstore.add_melt_quote(quote).await?;
             }
             None => {
@@ -1020,11 +1868,161 @@ impl Wallet {
         Ok(response)
     }
 
-    // Select proofs
+    /// Wait for the payment of a melt quote to settle
+    ///
+    /// Yields the status of the quote whenever its state changes, starting
+    /// with the current one, and ends once it is [`MeltQuoteState::Paid`],
+    /// with the preimage if the mint returns it, or [`MeltQuoteState::Failed`].
+    /// The mint is polled every [`MELT_POLL_INTERVAL`]. Network errors are
+    /// retried, other errors and [`Error::Timeout`] after `timeout` are
+    /// yielded and end the stream.
+    pub fn wait_for_melt(
+        &self,
+        mint_url: MintUrl,
+        quote_id: &str,
+        timeout: Duration,
+    ) -> impl Stream<Item = Result<MeltQuoteBolt11Response, Error>> + '_ {
+        struct Poll {
+            mint_url: MintUrl,
+            quote_id: String,
+            deadline: runtime::Instant,
+            state: Option<MeltQuoteState>,
+            polled: bool,
+            done: bool,
+        }
+
+        let poll = Poll {
+            mint_url,
+            quote_id: quote_id.to_string(),
+            deadline: runtime::Instant::now() + timeout,
+            state: None,
+            polled: false,
+            done: false,
+        };
+
+        stream::unfold(poll, move |mut poll| async move {
+            if poll.done {
+                return None;
+            }
+
+            loop {
+                if poll.polled {
+                    let remaining = poll
+                        .deadline
+                        .saturating_duration_since(runtime::Instant::now());
+                    if remaining.is_zero() {
+                        poll.done = true;
+                        return Some((Err(Error::Timeout), poll));
+                    }
+                    runtime::sleep(remaining.min(MELT_POLL_INTERVAL)).await;
+                }
+                poll.polled = true;
+
+                match self
+                    .melt_quote_status(poll.mint_url.clone(), &poll.quote_id)
+                    .await
+                {
+                    Ok(response) if poll.state == Some(response.state) => {}
+                    Ok(response) => {
+                        poll.state = Some(response.state);
+                        poll.done = matches!(
+                            response.state,
+                            MeltQuoteState::Paid | MeltQuoteState::Failed
+                        );
+                        return Some((Ok(response), poll));
+                    }
+                    Err(err) if err.category() == ErrorCategory::Network => {
+                        tracing::warn!("Could not check melt quote {}: {}", poll.quote_id, err);
+                    }
+                    Err(err) => {
+                        poll.done = true;
+                        return Some((Err(err), poll));
+                    }
+                }
+            }
+        })
+    }
+
+    /// Mark pending proofs the mint reports as unspent as unspent again
+    ///
+    /// Reserved proofs of sent tokens are left alone.
+    async fn release_pending_proofs(
+        &self,
+        mint_url: &MintUrl,
+        unit: &CurrencyUnit,
+    ) -> Result<Amount, Error> {
+        let proofs: Proofs = self
+            .localstore
+            .get_proofs(
+                Some(mint_url.clone()),
+                Some(unit.clone()),
+                Some(vec![State::Pending]),
+                None,
+            )
+            .await?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| p.proof)
+            .collect();
+
+        if proofs.is_empty() {
+            return Ok(Amount::ZERO);
+        }
+
+        let unspent: HashSet<PublicKey> = self
+            .check_proofs_spent(mint_url.clone(), proofs.clone())
+            .await?
+            .into_iter()
+            .filter(|s| s.state == State::Unspent)
+            .map(|s| s.y)
+            .collect();
+
+        let mut released = Proofs::new();
+        for proof in proofs {
+            if unspent.contains(&proof.y()?) {
+                released.push(proof);
+            }
+        }
+
+        self.set_proofs_state(&released, State::Unspent).await?;
+
+        Ok(released.iter().map(|p| p.amount).sum())
+    }
+
+    /// Select proofs and claim them for an operation
+    ///
+    /// The selected proofs are marked pending in storage and claimed in memory
+    /// until the returned [`Reservation`] is dropped, so concurrent operations
+    /// never select the same proofs.
+    async fn reserve_proofs(
+        &self,
+        mint_url: MintUrl,
+        unit: CurrencyUnit,
+        amount: Amount,
+        conditions: Option<Vec<SpendingConditions>>,
+    ) -> Result<(Proofs, Proofs, Reservation), Error> {
+        loop {
+            let (condition_proofs, proofs) = self
+                .select_proofs(mint_url.clone(), unit.clone(), amount, conditions.clone())
+                .await?;
+
+            let selected = [condition_proofs.clone(), proofs.clone()].concat();
+
+            // Another operation claimed some of the proofs since they were selected
+            if let Some(reservation) = self.reservations.reserve(&selected)? {
+                self.set_proofs_state(&selected, State::Pending).await?;
+                return Ok((condition_proofs, proofs, reservation));
+            }
+        }
+    }
+
+    /// Select proofs
+    ///
+    /// Proofs claimed by a send or melt in progress are not selected.
     #[instrument(skip(self), fields(mint_url = %mint_url))]
     pub async fn select_proofs(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         unit: CurrencyUnit,
         amount: Amount,
         conditions: Option<Vec<SpendingConditions>>,
@@ -1043,6 +2041,7 @@ impl Wallet {
                 .await?
                 .unwrap_or_default()
                 .into_iter()
+                .filter(|p| !self.reservations.contains(&p.y))
                 .map(|p| p.proof)
                 .collect();
         }
@@ -1103,6 +2102,7 @@ impl Wallet {
             .await?
             .ok_or(Error::InsufficientFunds)?
             .into_iter()
+            .filter(|p| !self.reservations.contains(&p.y))
             .map(|p| p.proof)
             .collect();
 
@@ -1123,13 +2123,13 @@ impl Wallet {
         let mut selected_proofs: Proofs = Vec::new();
 
         for proof in [inactive_proofs, active_proofs].concat() {
+            // Stop once the amount is covered so no proofs are claimed needlessly
             if selected_proofs.iter().map(|p| p.amount).sum::<Amount>() + condition_proof_total
-                <= amount
+                >= amount
             {
-                selected_proofs.push(proof);
-            } else {
                 break;
             }
+            selected_proofs.push(proof);
         }
 
         if selected_proofs.iter().map(|p| p.amount).sum::<Amount>() + condition_proof_total < amount
@@ -1143,13 +2143,29 @@ impl Wallet {
     }
 
     /// Melt
-    #[instrument(skip(self, quote_id), fields(mint_url = %mint_url))]
+    ///
+    /// If the melt fails while the mint is still paying the quote the inputs
+    /// stay pending. Check the quote with [`Wallet::melt_quote_status`] to
+    /// release them once the payment failed.
+    #[instrument(
+        skip(self, quote_id),
+        fields(
+            mint_url = %mint_url,
+            quote_id = tracing::field::Empty,
+            amount = tracing::field::Empty,
+            fee_reserve = tracing::field::Empty,
+            inputs = tracing::field::Empty,
+            change = tracing::field::Empty,
+        )
+    )]
     pub async fn melt(
         &self,
-        mint_url: &UncheckedUrl,
+        mint_url: &MintUrl,
         quote_id: &str,
         amount_split_target: SplitTarget,
     ) -> Result<Melted, Error> {
+        record!("quote_id" = quote_id);
+
         let quote_info = self.localstore.get_melt_quote(quote_id).await?;
 
         let quote_info = if let Some(quote) = quote_info {
@@ -1157,42 +2173,61 @@ impl Wallet {
                 return Err(Error::QuoteExpired);
             }
 
+            if quote.state == MeltQuoteState::Pending {
+                return Err(Error::QuotePending);
+            }
+
             quote.clone()
         } else {
             return Err(Error::QuoteUnknown);
         };
 
-        let proofs = self
-            .select_proofs(
+        record!(
+            "amount" = u64::from(quote_info.amount),
+            "fee_reserve" = u64::from(quote_info.fee_reserve)
+        );
+
+        let spend = SpendRequest {
+            kind: SpendKind::Melt,
+            mint_url: mint_url.clone(),
+            unit: quote_info.unit.clone(),
+            amount: quote_info
+                .amount
+                .checked_add(quote_info.fee_reserve)
+                .ok_or(crate::error::Error::AmountOverflow)?,
+        };
+        self.check_spend_policy(&spend).await?;
+
+        // Inputs stay pending until the change is stored
+        let (_, proofs, _reservation) = self
+            .reserve_proofs(
                 mint_url.clone(),
                 quote_info.unit.clone(),
                 quote_info.amount,
                 None,
             )
-            .await?
-            .1;
-
-        let proofs_amount = proofs.iter().map(|p| p.amount).sum();
-
-        let active_keyset_id = self.active_mint_keyset(mint_url, &quote_info.unit).await?;
-
-        let count = self
-            .localstore
-            .get_keyset_counter(&active_keyset_id)
             .await?;
 
-        let count = count.map_or(0, |c| c + 1);
+        let proofs_amount = proofs.iter().map(|p| p.amount).sum();
+        record!("inputs" = proofs.len());
 
-        let premint_secrets = PreMintSecrets::from_xpriv(
-            active_keyset_id,
-            count,
-            self.xpriv,
-            proofs_amount,
-            true,
-            &amount_split_target,
-        )?;
+        let (active_keyset_id, premint_secrets) = match self
+            .melt_change_secrets(
+                mint_url,
+                &quote_info.unit,
+                proofs_amount,
+                &amount_split_target,
+            )
+            .await
+        {
+            Ok(change_secrets) => change_secrets,
+            Err(err) => {
+                self.set_proofs_state(&proofs, State::Unspent).await?;
+                return Err(err);
+            }
+        };
 
-        let melt_response = self
+        let melt_response = match self
             .client
             .post_melt(
                 mint_url.clone().try_into()?,
@@ -1200,7 +2235,45 @@ impl Wallet {
                 proofs.clone(),
                 Some(premint_secrets.blinded_messages()),
             )
-            .await?;
+            .await
+        {
+            Ok(melt_response) => melt_response,
+            Err(err) => {
+                // Only release the inputs if the mint is not paying the quote
+                match self
+                    .client
+                    .get_melt_quote_status(mint_url.clone().try_into()?, quote_id)
+                    .await
+                {
+                    Ok(status)
+                        if matches!(
+                            status.state,
+                            MeltQuoteState::Unpaid | MeltQuoteState::Failed
+                        ) =>
+                    {
+                        self.set_proofs_state(&proofs, State::Unspent).await?;
+                    }
+                    Ok(status) => {
+                        tracing::warn!(
+                            "Melt of quote {} failed but quote is {}, keeping inputs pending",
+                            quote_id,
+                            status.state
+                        );
+                        let mut quote_info = quote_info;
+                        quote_info.state = status.state;
+                        self.localstore.add_melt_quote(quote_info).await?;
+                    }
+                    Err(status_err) => {
+                        tracing::warn!(
+                            "Could not check state of quote {}, keeping inputs pending: {}",
+                            quote_id,
+                            status_err
+                        );
+                    }
+                }
+                return Err(err);
+            }
+        };
 
         let change_proofs = match melt_response.change {
             Some(change) => Some(construct_proofs(
@@ -1221,7 +2294,11 @@ impl Wallet {
             change: change_proofs.clone(),
         };
 
+        let change_amount: Amount = change_proofs.iter().flatten().map(|p| p.amount).sum();
+        let mut change_proofs_info = Vec::new();
+
         if let Some(change_proofs) = change_proofs {
+            record!("change" = change_proofs.len());
             tracing::debug!(
                 "Change amount returned from melt: {}",
                 change_proofs.iter().map(|p| p.amount).sum::<Amount>()
@@ -1232,7 +2309,7 @@ impl Wallet {
                 .increment_keyset_counter(&active_keyset_id, change_proofs.len() as u32)
                 .await?;
 
-            let change_proofs_info = change_proofs
+            change_proofs_info = change_proofs
                 .into_iter()
                 .flat_map(|proof| {
                     ProofInfo::new(
@@ -1243,30 +2320,133 @@ impl Wallet {
                     )
                 })
                 .collect();
-
-            self.localstore.add_proofs(change_proofs_info).await?;
         }
 
+        let input_ys = proofs
+            .iter()
+            .map(|p| p.y())
+            .collect::<Result<Vec<PublicKey>, _>>()?;
+
+        self.localstore
+            .update_proofs(change_proofs_info, input_ys)
+            .await?;
+
         self.localstore.remove_melt_quote(&quote_info.id).await?;
 
-        self.localstore.remove_proofs(&proofs).await?;
+        self.record_spend(&spend).await;
+        self.record_transaction(Transaction::new(
+            mint_url.clone(),
+            TransactionKind::Melt,
+            quote_info.amount,
+            proofs_amount
+                .checked_sub(quote_info.amount + change_amount)
+                .unwrap_or_default(),
+            quote_info.unit,
+            None,
+        ))
+        .await;
 
         Ok(melted)
     }
 
+    /// Blinded messages for the change of a melt
+    async fn melt_change_secrets(
+        &self,
+        mint_url: &MintUrl,
+        unit: &CurrencyUnit,
+        proofs_amount: Amount,
+        amount_split_target: &SplitTarget,
+    ) -> Result<(Id, PreMintSecrets), Error> {
+        let active_keyset_id = self.active_mint_keyset(mint_url, unit).await?;
+
+        let count = self
+            .localstore
+            .get_keyset_counter(&active_keyset_id)
+            .await?;
+
+        let count = count.map_or(0, |c| c + 1);
+
+        let premint_secrets = PreMintSecrets::from_xpriv(
+            active_keyset_id,
+            count,
+            self.xpriv().await?,
+            proofs_amount,
+            true,
+            amount_split_target,
+        )?;
+
+        Ok((active_keyset_id, premint_secrets))
+    }
+
+    /// Decode `encoded_token`, also in the formats of other wallets
+    ///
+    /// Tokens that do not parse as a [`Token`] are normalized with
+    /// [`ForeignToken`], legacy keyset ids are resolved with the keysets of
+    /// the mint.
+    pub async fn decode_token(&self, encoded_token: &str) -> Result<Token, Error> {
+        let err = match Token::from_str(encoded_token) {
+            Ok(token) => return Ok(token),
+            Err(err) => err,
+        };
+        let foreign = match ForeignToken::from_str(encoded_token) {
+            Ok(foreign) => foreign,
+            Err(_) => return Err(err.into()),
+        };
+
+        let mut keyset_ids = HashMap::new();
+        for (mint_url, legacy_ids) in foreign.legacy_keyset_ids() {
+            for keyset in self.get_mint_keysets(mint_url).await? {
+                if legacy_ids.iter().all(|id| keyset_ids.contains_key(id)) {
+                    break;
+                }
+
+                let keys = self.get_keyset_keys(mint_url, keyset.id).await?;
+                let legacy_id = nut02::legacy_keyset_id(&keys)?;
+                if legacy_ids.contains(&legacy_id) {
+                    tracing::debug!("Legacy keyset id {} is {}", legacy_id, keyset.id);
+                    keyset_ids.insert(legacy_id, keyset.id);
+                }
+            }
+        }
+
+        Ok(foreign.into_token(&keyset_ids)?)
+    }
+
     /// Receive
-    #[instrument(skip_all)]
+    ///
+    /// Accepts the tokens of other wallets, see [`Wallet::decode_token`].
+    #[instrument(
+        skip_all,
+        fields(
+            mints = tracing::field::Empty,
+            amount = tracing::field::Empty,
+            proofs = tracing::field::Empty,
+        )
+    )]
     pub async fn receive(
         &self,
         encoded_token: &str,
         amount_split_target: &SplitTarget,
         preimages: Option<Vec<String>>,
     ) -> Result<Amount, Error> {
-        let token_data = Token::from_str(encoded_token)?;
+        let token_data = self.decode_token(encoded_token).await?;
+
+        let fingerprint = token_data.fingerprint()?;
+        if self.localstore.is_token_received(&fingerprint).await? {
+            return Err(Error::AlreadyReceived);
+        }
 
         let unit = token_data.unit.unwrap_or_default();
+        record!(
+            "mints" = token_data.token.len(),
+            "proofs" = token_data
+                .token
+                .iter()
+                .map(|t| t.proofs.len())
+                .sum::<usize>()
+        );
 
-        let mut received_proofs: HashMap<UncheckedUrl, Proofs> = HashMap::new();
+        let mut received_proofs: HashMap<MintUrl, Proofs> = HashMap::new();
         for token in token_data.token {
             if token.proofs.is_empty() {
                 continue;
@@ -1287,7 +2467,7 @@ impl Wallet {
             let keys = self.get_keyset_keys(&token.mint, active_keyset_id).await?;
 
             // Sum amount of all proofs
-            let amount: Amount = token.proofs.iter().map(|p| p.amount).sum();
+            let amount = Amount::try_sum(token.proofs.iter().map(|p| p.amount))?;
 
             let mut proofs = token.proofs;
 
@@ -1361,6 +2541,7 @@ impl Wallet {
                     amount_split_target,
                     proofs,
                     None,
+                    None,
                 )
                 .await?;
 
@@ -1387,117 +2568,47 @@ impl Wallet {
             let mint_proofs = received_proofs.entry(token.mint).or_default();
 
             self.localstore
-                .increment_keyset_counter(&active_keyset_id, p.len() as u32)
+                .increment_keyset_counter(&active_keyset_id, pre_swap.derived_count)
                 .await?;
 
             mint_proofs.extend(p);
         }
 
         let mut total_amount = Amount::ZERO;
+        let mut transactions = Vec::with_capacity(received_proofs.len());
         for (mint, proofs) in received_proofs {
-            total_amount += proofs.iter().map(|p| p.amount).sum();
+            let amount = proofs.iter().map(|p| p.amount).sum();
+            total_amount += amount;
             let proofs = proofs
                 .into_iter()
                 .flat_map(|proof| ProofInfo::new(proof, mint.clone(), State::Unspent, unit.clone()))
                 .collect();
             self.localstore.add_proofs(proofs).await?;
+            let mut transaction = Transaction::new(
+                mint,
+                TransactionKind::Receive,
+                amount,
+                Amount::ZERO,
+                unit.clone(),
+                token_data.memo.clone(),
+            );
+            transaction.metadata = token_data.metadata.clone();
+            transactions.push(transaction);
         }
 
-        Ok(total_amount)
-    }
-
-    #[cfg(feature = "nostr")]
-    #[instrument(skip_all)]
-    pub async fn nostr_receive(
-        &self,
-        nostr_signing_key: SecretKey,
-        since: Option<u64>,
-        amount_split_target: SplitTarget,
-    ) -> Result<Amount, Error> {
-        use nostr_sdk::{Keys, Kind};
-
-        let verifying_key = nostr_signing_key.public_key();
-
-        let x_only_pubkey = verifying_key.x_only_public_key();
-
-        let nostr_pubkey = nostr_sdk::PublicKey::from_hex(x_only_pubkey.to_string())?;
-
-        let keys = Keys::from_str(&(nostr_signing_key).to_secret_hex())?;
-        self.add_p2pk_signing_key(nostr_signing_key).await;
-
-        let since = match since {
-            Some(since) => Some(Timestamp::from(since)),
-            None => self
-                .localstore
-                .get_nostr_last_checked(&verifying_key)
-                .await?
-                .map(|s| Timestamp::from(s as u64)),
-        };
-
-        let filter = match since {
-            Some(since) => Filter::new()
-                .pubkey(nostr_pubkey)
-                .kind(Kind::EncryptedDirectMessage)
-                .since(since),
-            None => Filter::new()
-                .pubkey(nostr_pubkey)
-                .kind(Kind::EncryptedDirectMessage),
-        };
-
-        self.nostr_client.connect().await;
-
-        let events = self.nostr_client.get_events_of(vec![filter], None).await?;
-
-        let mut tokens: HashSet<String> = HashSet::new();
-
-        for event in events {
-            if event.kind() == Kind::EncryptedDirectMessage {
-                if let Ok(msg) =
-                    nip04::decrypt(keys.secret_key()?, event.author_ref(), event.content())
-                {
-                    if let Some(token) = Self::token_from_text(&msg) {
-                        tokens.insert(token.to_string());
-                    }
-                } else {
-                    tracing::error!("Impossible to decrypt direct message");
-                }
-            }
-        }
-
-        let mut total_received = Amount::ZERO;
-        for token in tokens.iter() {
-            match self.receive(token, &amount_split_target, None).await {
-                Ok(amount) => total_received += amount,
-                Err(err) => {
-                    tracing::error!("Could not receive token: {}", err);
-                }
-            }
-        }
-
-        self.localstore
-            .add_nostr_last_checked(verifying_key, unix_time() as u32)
-            .await?;
-
-        Ok(total_received)
-    }
-
-    #[cfg(feature = "nostr")]
-    fn token_from_text(text: &str) -> Option<&str> {
-        let text = text.trim();
-        if let Some(start) = text.find("cashu") {
-            match text[start..].find(' ') {
-                Some(end) => return Some(&text[start..(end + start)]),
-                None => return Some(&text[start..]),
-            }
+        self.localstore.add_received_token(fingerprint).await?;
+        for transaction in transactions {
+            self.record_transaction(transaction).await;
         }
+        record!("amount" = u64::from(total_amount));
 
-        None
+        Ok(total_amount)
     }
 
     #[instrument(skip(self, proofs), fields(mint_url = %mint_url))]
     pub fn proof_to_token(
         &self,
-        mint_url: UncheckedUrl,
+        mint_url: MintUrl,
         proofs: Proofs,
         memo: Option<String>,
         unit: Option<CurrencyUnit>,
@@ -1505,8 +2616,11 @@ impl Wallet {
         Ok(Token::new(mint_url, proofs, memo, unit)?.to_string())
     }
 
+    /// Restore proofs from seed (NUT-09)
+    ///
+    /// Also claims paid mint quotes of the mint that were never issued.
     #[instrument(skip(self), fields(mint_url = %mint_url))]
-    pub async fn restore(&self, mint_url: UncheckedUrl) -> Result<Amount, Error> {
+    pub async fn restore(&self, mint_url: MintUrl) -> Result<Amount, Error> {
         // Check that mint is in store of mints
         if self.localstore.get_mint(mint_url.clone()).await?.is_none() {
             self.add_mint(mint_url.clone()).await?;
@@ -1524,7 +2638,7 @@ impl Wallet {
             while empty_batch.lt(&3) {
                 let premint_secrets = PreMintSecrets::restore_batch(
                     keyset.id,
-                    self.xpriv,
+                    self.xpriv().await?,
                     start_counter,
                     start_counter + 100,
                 )?;
@@ -1607,6 +2721,10 @@ impl Wallet {
                 start_counter += 100;
             }
         }
+
+        // Quotes paid before the wallet was restored
+        restored_value += self.claim_paid_mint_quotes(Some(&mint_url)).await?;
+
         Ok(restored_value)
     }
 
@@ -1762,20 +2880,143 @@ impl Wallet {
     }
 }
 
-#[cfg(feature = "nostr")]
+/// Check that `keyset` returned by a mint is keyset `id`
+///
+/// The keys have to hash to the id, for both id versions, so a mint or a man
+/// in the middle can not substitute the keys of a keyset. `info` is the
+/// keyset as listed by the mint, its unit has to match.
+fn verify_keyset(keyset: &KeySet, id: Id, info: Option<&KeySetInfo>) -> Result<(), Error> {
+    if keyset.id != id {
+        tracing::warn!("Mint returned keyset {} for keyset {}", keyset.id, id);
+        return Err(Error::KeysetMismatch(id));
+    }
+
+    if let Some(info) = info {
+        if info.unit != keyset.unit {
+            tracing::warn!(
+                "Keyset {} has unit {}, listed as {}",
+                id,
+                keyset.unit,
+                info.unit
+            );
+            return Err(Error::KeysetMismatch(id));
+        }
+    }
+
+    if keyset.verify_id().is_err() {
+        tracing::warn!("Keys of keyset {} do not hash to its id", id);
+        return Err(Error::KeysetMismatch(id));
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
 
+    fn keyset(unit: CurrencyUnit) -> KeySet {
+        let keys: BTreeMap<String, PublicKey> = (0..4)
+            .map(|i| (2u64.pow(i).to_string(), SecretKey::generate().public_key()))
+            .collect();
+        let keys = Keys::new(keys);
+
+        KeySet {
+            id: Id::try_from(&keys).unwrap(),
+            unit,
+            keys,
+            final_expiry: None,
+        }
+    }
+
     #[test]
-    fn test_token_from_text() {
-        let text = " Here is some ecash: cashuAeyJ0b2tlbiI6W3sicHJvb2ZzIjpbeyJhbW91bnQiOjIsInNlY3JldCI6ImI2Zjk1ODIxYmZlNjUyYjYwZGQ2ZjYwMDU4N2UyZjNhOTk4MzVhMGMyNWI4MTQzODNlYWIwY2QzOWFiNDFjNzUiLCJDIjoiMDI1YWU4ZGEyOTY2Y2E5OGVmYjA5ZDcwOGMxM2FiZmEwZDkxNGUwYTk3OTE4MmFjMzQ4MDllMjYxODY5YTBhNDJlIiwicmVzZXJ2ZWQiOmZhbHNlLCJpZCI6IjAwOWExZjI5MzI1M2U0MWUifSx7ImFtb3VudCI6Miwic2VjcmV0IjoiZjU0Y2JjNmNhZWZmYTY5MTUyOTgyM2M1MjU1MDkwYjRhMDZjNGQ3ZDRjNzNhNDFlZTFkNDBlM2ExY2EzZGZhNyIsIkMiOiIwMjMyMTIzN2JlYjcyMWU3NGI1NzcwNWE5MjJjNjUxMGQwOTYyYzAzNzlhZDM0OTJhMDYwMDliZTAyNjA5ZjA3NTAiLCJyZXNlcnZlZCI6ZmFsc2UsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSJ9LHsiYW1vdW50IjoxLCJzZWNyZXQiOiJhNzdhM2NjODY4YWM4ZGU3YmNiOWMxMzJmZWI3YzEzMDY4Nzg3ODk5Yzk3YTk2NWE2ZThkZTFiMzliMmQ2NmQ3IiwiQyI6IjAzMTY0YTMxNWVhNjM0NGE5NWI2NzM1NzBkYzg0YmZlMTQ2NDhmMTQwM2EwMDJiZmJlMDhlNWFhMWE0NDQ0YWE0MCIsInJlc2VydmVkIjpmYWxzZSwiaWQiOiIwMDlhMWYyOTMyNTNlNDFlIn1dLCJtaW50IjoiaHR0cHM6Ly90ZXN0bnV0LmNhc2h1LnNwYWNlIn1dLCJ1bml0Ijoic2F0In0= fdfdfg
-        sdfs";
-        let token = Wallet::token_from_text(text).unwrap();
+    fn test_verify_keyset() {
+        let sat = keyset(CurrencyUnit::Sat);
+        let info = KeySetInfo {
+            id: sat.id,
+            unit: CurrencyUnit::Sat,
+            active: true,
+            final_expiry: None,
+        };
+        assert!(verify_keyset(&sat, sat.id, Some(&info)).is_ok());
+
+        // Keys of another keyset
+        let other = keyset(CurrencyUnit::Sat);
+        assert!(matches!(
+            verify_keyset(&other, sat.id, None),
+            Err(Error::KeysetMismatch(_))
+        ));
+
+        // Substituted keys under the advertised id
+        let mut substituted = other.clone();
+        substituted.id = sat.id;
+        assert!(matches!(
+            verify_keyset(&substituted, sat.id, Some(&info)),
+            Err(Error::KeysetMismatch(_))
+        ));
+
+        let mut usd = sat.clone();
+        usd.unit = CurrencyUnit::Usd;
+        assert!(verify_keyset(&usd, sat.id, None).is_ok());
+        assert!(matches!(
+            verify_keyset(&usd, sat.id, Some(&info)),
+            Err(Error::KeysetMismatch(_))
+        ));
+
+        // Version 01 ids commit to the unit and final expiry
+        let mut v2 = sat.clone();
+        v2.final_expiry = Some(2_000_000_000);
+        v2.id = Id::v2_from_data(&v2.keys, &v2.unit, v2.final_expiry).unwrap();
+        assert!(verify_keyset(&v2, v2.id, None).is_ok());
+        let mut expiry = v2.clone();
+        expiry.final_expiry = None;
+        assert!(matches!(
+            verify_keyset(&expiry, v2.id, None),
+            Err(Error::KeysetMismatch(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_lock_wallet() {
+        use crate::cdk_database::WalletMemoryDatabase;
 
-        let token_str = "cashuAeyJ0b2tlbiI6W3sicHJvb2ZzIjpbeyJhbW91bnQiOjIsInNlY3JldCI6ImI2Zjk1ODIxYmZlNjUyYjYwZGQ2ZjYwMDU4N2UyZjNhOTk4MzVhMGMyNWI4MTQzODNlYWIwY2QzOWFiNDFjNzUiLCJDIjoiMDI1YWU4ZGEyOTY2Y2E5OGVmYjA5ZDcwOGMxM2FiZmEwZDkxNGUwYTk3OTE4MmFjMzQ4MDllMjYxODY5YTBhNDJlIiwicmVzZXJ2ZWQiOmZhbHNlLCJpZCI6IjAwOWExZjI5MzI1M2U0MWUifSx7ImFtb3VudCI6Miwic2VjcmV0IjoiZjU0Y2JjNmNhZWZmYTY5MTUyOTgyM2M1MjU1MDkwYjRhMDZjNGQ3ZDRjNzNhNDFlZTFkNDBlM2ExY2EzZGZhNyIsIkMiOiIwMjMyMTIzN2JlYjcyMWU3NGI1NzcwNWE5MjJjNjUxMGQwOTYyYzAzNzlhZDM0OTJhMDYwMDliZTAyNjA5ZjA3NTAiLCJyZXNlcnZlZCI6ZmFsc2UsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSJ9LHsiYW1vdW50IjoxLCJzZWNyZXQiOiJhNzdhM2NjODY4YWM4ZGU3YmNiOWMxMzJmZWI3YzEzMDY4Nzg3ODk5Yzk3YTk2NWE2ZThkZTFiMzliMmQ2NmQ3IiwiQyI6IjAzMTY0YTMxNWVhNjM0NGE5NWI2NzM1NzBkYzg0YmZlMTQ2NDhmMTQwM2EwMDJiZmJlMDhlNWFhMWE0NDQ0YWE0MCIsInJlc2VydmVkIjpmYWxzZSwiaWQiOiIwMDlhMWYyOTMyNTNlNDFlIn1dLCJtaW50IjoiaHR0cHM6Ly90ZXN0bnV0LmNhc2h1LnNwYWNlIn1dLCJ1bml0Ijoic2F0In0=";
+        let localstore = WalletMemoryDatabase::new(
+            vec![],
+            vec![],
+            vec![],
+            HashMap::new(),
+            #[cfg(feature = "nostr")]
+            HashMap::new(),
+        );
+        let seed = [1u8; 32];
+        let wallet = Wallet::new(Arc::new(localstore), &seed, vec![SecretKey::generate()]);
+
+        assert!(!wallet.is_locked().await);
+
+        wallet.lock().await;
+
+        assert!(wallet.is_locked().await);
+        assert!(matches!(wallet.xpriv().await, Err(Error::WalletLocked)));
+        assert!(wallet.available_p2pk_signing_keys().await.is_empty());
+
+        wallet.unlock(&seed).await.unwrap();
+
+        assert!(!wallet.is_locked().await);
+        assert_eq!(
+            wallet.xpriv().await.unwrap(),
+            ExtendedPrivKey::new_master(Network::Bitcoin, &seed).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_wallet_send_sync() {
+        fn assert_send_sync<T>()
+        where
+            T: Send + Sync + 'static,
+        {
+        }
 
-        assert_eq!(token, token_str)
+        assert_send_sync::<Wallet>();
     }
 }
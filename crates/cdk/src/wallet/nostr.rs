@@ -0,0 +1,347 @@
+//! Nostr token transport
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use nostr_sdk::nips::{nip04, nip59};
+use nostr_sdk::{Event, EventBuilder, EventId, Filter, Keys, Kind, Timestamp};
+use tracing::instrument;
+
+use super::error::Error;
+use super::Wallet;
+use crate::amount::SplitTarget;
+use crate::mint_url::MintUrl;
+use crate::nuts::{Conditions, CurrencyUnit, PublicKey, SecretKey, SpendingConditions};
+use crate::util::unix_time;
+use crate::Amount;
+
+/// Nostr direct message protocol used to deliver tokens
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum NostrDirectMessage {
+    /// NIP-17 gift wrapped private direct message
+    ///
+    /// Falls back to [`NostrDirectMessage::Nip04`] if no relay accepts the
+    /// gift wrapped event
+    #[default]
+    Nip17,
+    /// NIP-04 encrypted direct message
+    Nip04,
+}
+
+/// Token claimed from a nostr direct message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NostrReceived {
+    /// Event the token was delivered in
+    pub event_id: EventId,
+    /// Sender of the token
+    pub sender: nostr_sdk::PublicKey,
+    /// Amount claimed
+    pub amount: Amount,
+}
+
+/// P2PK spending condition locking proofs to a nostr pubkey
+///
+/// `receiver` may be an `npub` or a hex encoded pubkey. Nostr pubkeys are
+/// x-only, proofs are locked to the even key which verifies the signatures of
+/// the `nsec` whatever the parity of its full pubkey.
+pub fn nostr_p2pk_conditions(
+    receiver: &str,
+    conditions: Conditions,
+) -> Result<SpendingConditions, Error> {
+    let receiver = nostr_sdk::PublicKey::parse(receiver)?;
+    let pubkey = PublicKey::from_hex(format!("02{}", receiver.to_hex()))?;
+
+    Ok(SpendingConditions::new_p2pk(pubkey, conditions))
+}
+
+/// Signing key of an `nsec` or hex encoded nostr secret key
+pub fn nostr_signing_key(secret_key: &str) -> Result<SecretKey, Error> {
+    let secret_key = nostr_sdk::SecretKey::parse(secret_key)?;
+
+    Ok(SecretKey::from_hex(secret_key.to_secret_hex())?)
+}
+
+impl Wallet {
+    /// Send token locked to a nostr pubkey
+    ///
+    /// Only the owner of the `nsec` of `receiver` can claim the token, after
+    /// adding it with [`Wallet::add_nostr_signing_key`]. The token is returned
+    /// and can be delivered with [`Wallet::nostr_send`] or any other way.
+    #[instrument(skip(self, amount_split_target), fields(mint_url = %mint_url))]
+    pub async fn send_to_nostr_pubkey(
+        &self,
+        mint_url: &MintUrl,
+        unit: CurrencyUnit,
+        memo: Option<String>,
+        amount: Amount,
+        amount_split_target: &SplitTarget,
+        receiver: &str,
+    ) -> Result<String, Error> {
+        let conditions = nostr_p2pk_conditions(receiver, Conditions::default())?;
+
+        self.send(
+            mint_url,
+            unit,
+            memo,
+            amount,
+            amount_split_target,
+            Some(conditions),
+        )
+        .await
+    }
+
+    /// Add nostr secret key as P2PK signing key
+    ///
+    /// `secret_key` may be an `nsec` or hex encoded. Received tokens locked to
+    /// its pubkey are signed with it.
+    #[instrument(skip_all)]
+    pub async fn add_nostr_signing_key(&self, secret_key: &str) -> Result<(), Error> {
+        self.add_p2pk_signing_key(nostr_signing_key(secret_key)?)
+            .await;
+
+        Ok(())
+    }
+
+    /// Send token to a nostr pubkey as an encrypted direct message
+    ///
+    /// `receiver` may be an `npub` or a hex encoded pubkey. The message is
+    /// published to the relays added with [`Wallet::add_nostr_relays`] and the
+    /// id of the published event is returned.
+    #[instrument(skip(self, nostr_signing_key, token))]
+    pub async fn nostr_send(
+        &self,
+        nostr_signing_key: &SecretKey,
+        receiver: &str,
+        token: &str,
+        protocol: NostrDirectMessage,
+    ) -> Result<EventId, Error> {
+        let keys = Keys::from_str(&nostr_signing_key.to_secret_hex())?;
+        let receiver = nostr_sdk::PublicKey::parse(receiver)?;
+
+        self.nostr_client.connect().await;
+
+        if protocol == NostrDirectMessage::Nip17 {
+            let rumor = EventBuilder::private_msg_rumor(receiver, token, None)
+                .to_unsigned_event(keys.public_key());
+            let event = EventBuilder::gift_wrap(&keys, &receiver, rumor, None)?;
+
+            match self.nostr_client.send_event(event).await {
+                Ok(event_id) => return Ok(event_id),
+                Err(err) => {
+                    tracing::warn!(
+                        "Could not publish NIP-17 message, falling back to NIP-04: {err}"
+                    )
+                }
+            }
+        }
+
+        let event =
+            EventBuilder::encrypted_direct_msg(&keys, receiver, token, None)?.to_event(&keys)?;
+
+        Ok(self.nostr_client.send_event(event).await?)
+    }
+
+    /// Receive tokens sent to a nostr pubkey
+    ///
+    /// Fetches NIP-04 and NIP-17 direct messages for the pubkey of
+    /// `nostr_signing_key` from the configured relays, and claims every token
+    /// found in them by swapping it with the mint. Messages are fetched from
+    /// `since`, or from the last time the pubkey was checked.
+    #[instrument(skip_all)]
+    pub async fn nostr_receive(
+        &self,
+        nostr_signing_key: SecretKey,
+        since: Option<u64>,
+        amount_split_target: SplitTarget,
+    ) -> Result<Vec<NostrReceived>, Error> {
+        let verifying_key = nostr_signing_key.public_key();
+
+        let x_only_pubkey = verifying_key.x_only_public_key();
+
+        let nostr_pubkey = nostr_sdk::PublicKey::from_hex(x_only_pubkey.to_string())?;
+
+        let keys = Keys::from_str(&(nostr_signing_key).to_secret_hex())?;
+        self.add_p2pk_signing_key(nostr_signing_key).await;
+
+        let since = match since {
+            Some(since) => Some(since),
+            None => self
+                .localstore
+                .get_nostr_last_checked(&verifying_key)
+                .await?
+                .map(|s| s as u64),
+        };
+
+        let direct_messages = Filter::new()
+            .pubkey(nostr_pubkey)
+            .kind(Kind::EncryptedDirectMessage);
+        let gift_wraps = Filter::new().pubkey(nostr_pubkey).kind(Kind::GiftWrap);
+
+        let filters = match since {
+            // Gift wraps are backdated by up to two days to hide when they were sent
+            Some(since) => vec![
+                direct_messages.since(Timestamp::from(since)),
+                gift_wraps.since(Timestamp::from(
+                    since.saturating_sub(nip59::RANGE_RANDOM_TIMESTAMP_TWEAK.end),
+                )),
+            ],
+            None => vec![direct_messages, gift_wraps],
+        };
+
+        self.nostr_client.connect().await;
+
+        let events = self.nostr_client.get_events_of(filters, None).await?;
+
+        let mut tokens: HashMap<String, (EventId, nostr_sdk::PublicKey)> = HashMap::new();
+
+        for event in events {
+            match Self::decrypt_nostr_message(&keys, &event) {
+                Ok((sender, msg)) => {
+                    if let Some(token) = Self::token_from_text(&msg) {
+                        tokens
+                            .entry(token.to_string())
+                            .or_insert((event.id(), sender));
+                    }
+                }
+                Err(err) => {
+                    tracing::error!("Impossible to decrypt direct message: {}", err);
+                }
+            }
+        }
+
+        let mut received = Vec::new();
+        for (token, (event_id, sender)) in tokens.iter() {
+            match self.receive(token, &amount_split_target, None).await {
+                Ok(amount) => {
+                    tracing::info!("Received {} from {} in event {}", amount, sender, event_id);
+
+                    received.push(NostrReceived {
+                        event_id: *event_id,
+                        sender: *sender,
+                        amount,
+                    });
+                }
+                Err(err) => {
+                    tracing::error!("Could not receive token: {}", err);
+                }
+            }
+        }
+
+        self.localstore
+            .add_nostr_last_checked(verifying_key, unix_time() as u32)
+            .await?;
+
+        Ok(received)
+    }
+
+    /// Decrypt a NIP-04 or NIP-17 direct message, returning sender and content
+    fn decrypt_nostr_message(
+        keys: &Keys,
+        event: &Event,
+    ) -> Result<(nostr_sdk::PublicKey, String), Error> {
+        match event.kind() {
+            Kind::EncryptedDirectMessage => {
+                let msg = nip04::decrypt(keys.secret_key()?, event.author_ref(), event.content())?;
+
+                Ok((event.author(), msg))
+            }
+            Kind::GiftWrap => {
+                let unwrapped = nip59::extract_rumor(keys, event)?;
+
+                match unwrapped.rumor.kind {
+                    Kind::PrivateDirectMessage => Ok((unwrapped.sender, unwrapped.rumor.content)),
+                    kind => Err(Error::Custom(format!("Unexpected rumor kind {}", kind))),
+                }
+            }
+            kind => Err(Error::Custom(format!("Unexpected event kind {}", kind))),
+        }
+    }
+
+    /// Find a token in a text message
+    fn token_from_text(text: &str) -> Option<&str> {
+        let text = text.trim();
+        if let Some(start) = text.find("cashu") {
+            match text[start..].find(' ') {
+                Some(end) => return Some(&text[start..(end + start)]),
+                None => return Some(&text[start..]),
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_token_from_text() {
+        let text = " Here is some ecash: cashuAeyJ0b2tlbiI6W3sicHJvb2ZzIjpbeyJhbW91bnQiOjIsInNlY3JldCI6ImI2Zjk1ODIxYmZlNjUyYjYwZGQ2ZjYwMDU4N2UyZjNhOTk4MzVhMGMyNWI4MTQzODNlYWIwY2QzOWFiNDFjNzUiLCJDIjoiMDI1YWU4ZGEyOTY2Y2E5OGVmYjA5ZDcwOGMxM2FiZmEwZDkxNGUwYTk3OTE4MmFjMzQ4MDllMjYxODY5YTBhNDJlIiwicmVzZXJ2ZWQiOmZhbHNlLCJpZCI6IjAwOWExZjI5MzI1M2U0MWUifSx7ImFtb3VudCI6Miwic2VjcmV0IjoiZjU0Y2JjNmNhZWZmYTY5MTUyOTgyM2M1MjU1MDkwYjRhMDZjNGQ3ZDRjNzNhNDFlZTFkNDBlM2ExY2EzZGZhNyIsIkMiOiIwMjMyMTIzN2JlYjcyMWU3NGI1NzcwNWE5MjJjNjUxMGQwOTYyYzAzNzlhZDM0OTJhMDYwMDliZTAyNjA5ZjA3NTAiLCJyZXNlcnZlZCI6ZmFsc2UsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSJ9LHsiYW1vdW50IjoxLCJzZWNyZXQiOiJhNzdhM2NjODY4YWM4ZGU3YmNiOWMxMzJmZWI3YzEzMDY4Nzg3ODk5Yzk3YTk2NWE2ZThkZTFiMzliMmQ2NmQ3IiwiQyI6IjAzMTY0YTMxNWVhNjM0NGE5NWI2NzM1NzBkYzg0YmZlMTQ2NDhmMTQwM2EwMDJiZmJlMDhlNWFhMWE0NDQ0YWE0MCIsInJlc2VydmVkIjpmYWxzZSwiaWQiOiIwMDlhMWYyOTMyNTNlNDFlIn1dLCJtaW50IjoiaHR0cHM6Ly90ZXN0bnV0LmNhc2h1LnNwYWNlIn1dLCJ1bml0Ijoic2F0In0= fdfdfg
+        sdfs";
+        let token = Wallet::token_from_text(text).unwrap();
+
+        let token_str = "cashuAeyJ0b2tlbiI6W3sicHJvb2ZzIjpbeyJhbW91bnQiOjIsInNlY3JldCI6ImI2Zjk1ODIxYmZlNjUyYjYwZGQ2ZjYwMDU4N2UyZjNhOTk4MzVhMGMyNWI4MTQzODNlYWIwY2QzOWFiNDFjNzUiLCJDIjoiMDI1YWU4ZGEyOTY2Y2E5OGVmYjA5ZDcwOGMxM2FiZmEwZDkxNGUwYTk3OTE4MmFjMzQ4MDllMjYxODY5YTBhNDJlIiwicmVzZXJ2ZWQiOmZhbHNlLCJpZCI6IjAwOWExZjI5MzI1M2U0MWUifSx7ImFtb3VudCI6Miwic2VjcmV0IjoiZjU0Y2JjNmNhZWZmYTY5MTUyOTgyM2M1MjU1MDkwYjRhMDZjNGQ3ZDRjNzNhNDFlZTFkNDBlM2ExY2EzZGZhNyIsIkMiOiIwMjMyMTIzN2JlYjcyMWU3NGI1NzcwNWE5MjJjNjUxMGQwOTYyYzAzNzlhZDM0OTJhMDYwMDliZTAyNjA5ZjA3NTAiLCJyZXNlcnZlZCI6ZmFsc2UsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSJ9LHsiYW1vdW50IjoxLCJzZWNyZXQiOiJhNzdhM2NjODY4YWM4ZGU3YmNiOWMxMzJmZWI3YzEzMDY4Nzg3ODk5Yzk3YTk2NWE2ZThkZTFiMzliMmQ2NmQ3IiwiQyI6IjAzMTY0YTMxNWVhNjM0NGE5NWI2NzM1NzBkYzg0YmZlMTQ2NDhmMTQwM2EwMDJiZmJlMDhlNWFhMWE0NDQ0YWE0MCIsInJlc2VydmVkIjpmYWxzZSwiaWQiOiIwMDlhMWYyOTMyNTNlNDFlIn1dLCJtaW50IjoiaHR0cHM6Ly90ZXN0bnV0LmNhc2h1LnNwYWNlIn1dLCJ1bml0Ijoic2F0In0=";
+
+        assert_eq!(token, token_str)
+    }
+
+    #[test]
+    fn test_decrypt_nostr_message() {
+        let sender = Keys::generate();
+        let receiver = Keys::generate();
+        let token = "cashuAeyJ0b2tlbiI6W119";
+
+        let event = EventBuilder::encrypted_direct_msg(&sender, receiver.public_key(), token, None)
+            .unwrap()
+            .to_event(&sender)
+            .unwrap();
+        assert_eq!(
+            Wallet::decrypt_nostr_message(&receiver, &event).unwrap(),
+            (sender.public_key(), token.to_string())
+        );
+
+        let rumor = EventBuilder::private_msg_rumor(receiver.public_key(), token, None)
+            .to_unsigned_event(sender.public_key());
+        let event = EventBuilder::gift_wrap(&sender, &receiver.public_key(), rumor, None).unwrap();
+        assert_eq!(
+            Wallet::decrypt_nostr_message(&receiver, &event).unwrap(),
+            (sender.public_key(), token.to_string())
+        );
+
+        assert!(Wallet::decrypt_nostr_message(&Keys::generate(), &event).is_err());
+    }
+
+    #[test]
+    fn test_nostr_p2pk_conditions() {
+        use crate::nuts::{nut10, Id, Proof};
+        use crate::secret::Secret;
+
+        // Key with an odd pubkey
+        let keys = loop {
+            let keys = Keys::generate();
+            let secret_key =
+                nostr_signing_key(&keys.secret_key().unwrap().to_secret_hex()).unwrap();
+            if secret_key.public_key().to_bytes()[0] == 0x03 {
+                break keys;
+            }
+        };
+        let npub = nostr_sdk::ToBech32::to_bech32(&keys.public_key()).unwrap();
+        let nsec = nostr_sdk::ToBech32::to_bech32(keys.secret_key().unwrap()).unwrap();
+
+        let conditions = nostr_p2pk_conditions(&npub, Conditions::default()).unwrap();
+        let mut proof = Proof::new(
+            Amount::from(1),
+            Id::from_str("009a1f293253e41e").unwrap(),
+            Secret::try_from(nut10::Secret::from(conditions)).unwrap(),
+            PublicKey::from_hex(
+                "02698c4e2b5f9534cd0687d87513c759790cf829aa5739184a3e3735471fbda904",
+            )
+            .unwrap(),
+        );
+        proof.sign_p2pk(nostr_signing_key(&nsec).unwrap()).unwrap();
+
+        assert!(proof.verify_p2pk().is_ok());
+    }
+}
@@ -0,0 +1,366 @@
+//! Nostr Wallet Connect (NIP-47) service
+
+use std::str::FromStr;
+
+use nostr_sdk::nips::nip04;
+use nostr_sdk::nips::nip47::{
+    ErrorCode, GetBalanceResponseResult, GetInfoResponseResult, MakeInvoiceRequestParams,
+    MakeInvoiceResponseResult, Method, NIP47Error, NostrWalletConnectURI, PayInvoiceRequestParams,
+    PayInvoiceResponseResult, Request, RequestParams, Response, ResponseResult,
+};
+use nostr_sdk::{
+    Event, EventBuilder, Filter, JsonUtil, Keys, Kind, RelayPoolNotification, Tag, Timestamp, Url,
+};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::instrument;
+
+use super::error::Error;
+use super::Wallet;
+use crate::amount::SplitTarget;
+use crate::mint_url::MintUrl;
+use crate::nuts::{CurrencyUnit, SecretKey};
+use crate::{Amount, Bolt11Invoice};
+
+/// Methods answered by [`NwcService`]
+const METHODS: [Method; 4] = [
+    Method::PayInvoice,
+    Method::GetBalance,
+    Method::MakeInvoice,
+    Method::GetInfo,
+];
+
+/// Nostr Wallet Connect service backed by a [`Wallet`]
+///
+/// Answers NIP-47 requests from a single connected app by melting and minting
+/// sat ecash against one mint. Invoices created with `make_invoice` are claimed
+/// the next time the balance is requested.
+#[derive(Clone)]
+pub struct NwcService {
+    wallet: Wallet,
+    mint_url: MintUrl,
+    service_keys: Keys,
+    app_keys: Keys,
+}
+
+impl NwcService {
+    /// Create new [`NwcService`]
+    ///
+    /// `service_key` identifies the wallet service, `app_secret` is the
+    /// connection secret handed to the app in the connection uri.
+    pub fn new(
+        wallet: Wallet,
+        mint_url: MintUrl,
+        service_key: &SecretKey,
+        app_secret: &SecretKey,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            wallet,
+            mint_url,
+            service_keys: Keys::from_str(&service_key.to_secret_hex())?,
+            app_keys: Keys::from_str(&app_secret.to_secret_hex())?,
+        })
+    }
+
+    /// Connection uri for the app
+    pub fn uri(&self, relay_url: Url) -> Result<NostrWalletConnectURI, Error> {
+        Ok(NostrWalletConnectURI::new(
+            self.service_keys.public_key(),
+            relay_url,
+            self.app_keys.secret_key()?.clone(),
+            None,
+        ))
+    }
+
+    /// Serve requests
+    ///
+    /// Publishes the service info event and answers requests received on the
+    /// relays added with [`Wallet::add_nostr_relays`] until the nostr client
+    /// is shut down.
+    #[instrument(skip(self))]
+    pub async fn run(&self) -> Result<(), Error> {
+        let client = &self.wallet.nostr_client;
+
+        client.connect().await;
+
+        let methods: Vec<String> = METHODS.iter().map(|m| m.to_string()).collect();
+        let info = EventBuilder::new(Kind::WalletConnectInfo, methods.join(" "), [])
+            .to_event(&self.service_keys)?;
+        client.send_event(info).await?;
+
+        let filter = Filter::new()
+            .kind(Kind::WalletConnectRequest)
+            .author(self.app_keys.public_key())
+            .pubkey(self.service_keys.public_key())
+            .since(Timestamp::now());
+
+        let mut notifications = client.notifications();
+        client.subscribe(vec![filter], None).await;
+
+        loop {
+            match notifications.recv().await {
+                Ok(RelayPoolNotification::Event { event, .. }) => {
+                    if event.kind() != Kind::WalletConnectRequest {
+                        continue;
+                    }
+
+                    match self.handle_event(&event).await {
+                        Ok(response) => {
+                            if let Err(err) = client.send_event(response).await {
+                                tracing::error!("Could not publish NWC response: {}", err);
+                            }
+                        }
+                        Err(err) => tracing::warn!("Ignoring NWC request {}: {}", event.id(), err),
+                    }
+                }
+                Ok(RelayPoolNotification::Shutdown) | Err(RecvError::Closed) => break,
+                Ok(_) => (),
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Skipped {} nostr notifications", skipped)
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Answer a request event with a response event
+    #[instrument(skip_all, fields(event_id = %event.id()))]
+    pub async fn handle_event(&self, event: &Event) -> Result<Event, Error> {
+        let app_pubkey = self.app_keys.public_key();
+
+        if event.author_ref() != &app_pubkey {
+            return Err(Error::Custom("Request not from connected app".to_string()));
+        }
+
+        let request = nip04::decrypt(
+            self.service_keys.secret_key()?,
+            &app_pubkey,
+            event.content(),
+        )?;
+        let request = Request::from_json(request)?;
+
+        let response = self.handle_request(request).await;
+
+        let content = nip04::encrypt(
+            self.service_keys.secret_key()?,
+            &app_pubkey,
+            response.as_json(),
+        )?;
+
+        Ok(EventBuilder::new(
+            Kind::WalletConnectResponse,
+            content,
+            [Tag::public_key(app_pubkey), Tag::event(event.id())],
+        )
+        .to_event(&self.service_keys)?)
+    }
+
+    /// Answer a request
+    #[instrument(skip(self))]
+    pub async fn handle_request(&self, request: Request) -> Response {
+        let result = match request.params {
+            RequestParams::PayInvoice(params) => self.pay_invoice(params).await,
+            RequestParams::GetBalance => self.get_balance().await,
+            RequestParams::MakeInvoice(params) => self.make_invoice(params).await,
+            RequestParams::GetInfo => self.get_info(),
+            _ => Err(NIP47Error {
+                code: ErrorCode::NotImplemented,
+                message: format!("{} is not supported", request.method),
+            }),
+        };
+
+        match result {
+            Ok(result) => Response {
+                result_type: request.method,
+                error: None,
+                result: Some(result),
+            },
+            Err(error) => Response {
+                result_type: request.method,
+                error: Some(error),
+                result: None,
+            },
+        }
+    }
+
+    async fn pay_invoice(
+        &self,
+        params: PayInvoiceRequestParams,
+    ) -> Result<ResponseResult, NIP47Error> {
+        let quote = self
+            .wallet
+            .melt_quote(self.mint_url.clone(), CurrencyUnit::Sat, params.invoice)
+            .await
+            .map_err(nip47_error)?;
+
+        let melted = self
+            .wallet
+            .melt(&self.mint_url, &quote.id, SplitTarget::default())
+            .await
+            .map_err(nip47_error)?;
+
+        match (melted.paid, melted.preimage) {
+            (true, preimage) => Ok(ResponseResult::PayInvoice(PayInvoiceResponseResult {
+                preimage: preimage.unwrap_or_default(),
+            })),
+            (false, _) => Err(NIP47Error {
+                code: ErrorCode::PaymentFailed,
+                message: "Mint could not pay invoice".to_string(),
+            }),
+        }
+    }
+
+    async fn get_balance(&self) -> Result<ResponseResult, NIP47Error> {
+        // Claim ecash for invoices created with `make_invoice` that have been paid
+        if let Err(err) = self.wallet.check_all_mint_quotes().await {
+            tracing::warn!("Could not check pending mint quotes: {}", err);
+        }
+
+        let balances = self.wallet.mint_balances().await.map_err(nip47_error)?;
+
+        let balance = balances
+            .get(&self.mint_url)
+            .and_then(|b| b.get(&CurrencyUnit::Sat))
+            .copied()
+            .unwrap_or_default();
+
+        Ok(ResponseResult::GetBalance(GetBalanceResponseResult {
+            balance: msat_from_sat(balance)?,
+        }))
+    }
+
+    async fn make_invoice(
+        &self,
+        params: MakeInvoiceRequestParams,
+    ) -> Result<ResponseResult, NIP47Error> {
+        let amount = Amount::from(params.amount / 1000);
+
+        let quote = self
+            .wallet
+            .mint_quote(
+                self.mint_url.clone(),
+                amount,
+                CurrencyUnit::Sat,
+                params.description,
+            )
+            .await
+            .map_err(nip47_error)?;
+
+        let invoice = Bolt11Invoice::from_str(&quote.request).map_err(|err| NIP47Error {
+            code: ErrorCode::Internal,
+            message: err.to_string(),
+        })?;
+
+        Ok(ResponseResult::MakeInvoice(MakeInvoiceResponseResult {
+            invoice: quote.request,
+            payment_hash: invoice.payment_hash().to_string(),
+        }))
+    }
+
+    fn get_info(&self) -> Result<ResponseResult, NIP47Error> {
+        Ok(ResponseResult::GetInfo(GetInfoResponseResult {
+            alias: self.mint_url.to_string(),
+            color: String::new(),
+            pubkey: self.service_keys.public_key().to_string(),
+            network: "bitcoin".to_string(),
+            block_height: 0,
+            block_hash: String::new(),
+            methods: METHODS.iter().map(|m| m.to_string()).collect(),
+        }))
+    }
+}
+
+fn msat_from_sat(amount: Amount) -> Result<u64, NIP47Error> {
+    amount
+        .checked_mul(Amount::from(1000))
+        .map(u64::from)
+        .ok_or(NIP47Error {
+            code: ErrorCode::Internal,
+            message: "Balance overflow".to_string(),
+        })
+}
+
+fn nip47_error(err: Error) -> NIP47Error {
+    let code = match err {
+        Error::InsufficientFunds => ErrorCode::InsufficientBalance,
+        Error::QuoteExpired | Error::QuoteUnknown => ErrorCode::NotFound,
+        _ => ErrorCode::Other,
+    };
+
+    NIP47Error {
+        code,
+        message: err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use nostr_sdk::nips::nip47::PayKeysendRequestParams;
+
+    use super::*;
+    use crate::cdk_database::WalletMemoryDatabase;
+
+    fn create_service(app_secret: &SecretKey) -> NwcService {
+        let localstore =
+            WalletMemoryDatabase::new(vec![], vec![], vec![], HashMap::new(), HashMap::new());
+        let wallet = Wallet::new(Arc::new(localstore), &[0u8; 32], vec![]);
+
+        NwcService::new(
+            wallet,
+            MintUrl::from_str("https://mint.example").unwrap(),
+            &SecretKey::generate(),
+            app_secret,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_handle_event() {
+        let app_secret = SecretKey::generate();
+        let service = create_service(&app_secret);
+        let uri = service
+            .uri(Url::parse("wss://relay.example").unwrap())
+            .unwrap();
+
+        let request = Request::get_balance().to_event(&uri).unwrap();
+        let response = service.handle_event(&request).await.unwrap();
+        let response = Response::from_event(&uri, &response).unwrap();
+
+        assert_eq!(response.to_get_balance().unwrap().balance, 0);
+
+        let request = Request::get_info().to_event(&uri).unwrap();
+        let response = service.handle_event(&request).await.unwrap();
+        let response = Response::from_event(&uri, &response).unwrap();
+
+        assert_eq!(response.to_get_info().unwrap().methods.len(), METHODS.len());
+
+        let other_uri = create_service(&SecretKey::generate())
+            .uri(Url::parse("wss://relay.example").unwrap())
+            .unwrap();
+        let request = Request::get_balance().to_event(&other_uri).unwrap();
+        assert!(service.handle_event(&request).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_method() {
+        let service = create_service(&SecretKey::generate());
+
+        let response = service
+            .handle_request(Request {
+                method: Method::PayKeysend,
+                params: RequestParams::PayKeysend(PayKeysendRequestParams {
+                    id: None,
+                    amount: 1000,
+                    pubkey: String::new(),
+                    preimage: None,
+                    tlv_records: vec![],
+                }),
+            })
+            .await;
+
+        assert_eq!(response.error.unwrap().code, ErrorCode::NotImplemented);
+    }
+}
@@ -0,0 +1,212 @@
+//! Paying NUT-18 payment requests
+//!
+//! [`Wallet::pay_request`] creates a token from a mint the request accepts and
+//! delivers it as a [`PaymentRequestPayload`] to the transports of the
+//! request, in order, until one accepts it.
+
+use std::str::FromStr;
+
+use thiserror::Error;
+use tracing::instrument;
+use url::Url;
+
+use super::Wallet;
+use crate::amount::SplitTarget;
+use crate::mint_url::MintUrl;
+use crate::nuts::{
+    CurrencyUnit, PaymentRequest, PaymentRequestPayload, Token, Transport, TransportType,
+};
+use crate::Amount;
+
+/// Payment Request Error
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Neither the request nor the caller set an amount
+    #[error("Payment request has no amount")]
+    AmountUndefined,
+    /// Amount given differs from the amount of the request
+    #[error("Payment request is for `{0}`, not `{1}`")]
+    AmountMismatch(Amount, Amount),
+    /// No accepted mint holds enough funds of the unit
+    #[error("No accepted mint with `{0}` {1}")]
+    NoAcceptedMint(Amount, CurrencyUnit),
+    /// Delivery over nostr needs the `nostr` feature
+    #[error("Transport `{0}` not supported")]
+    UnsupportedTransport(TransportType),
+    /// Url Error
+    #[error(transparent)]
+    Url(#[from] url::ParseError),
+    /// Serde Error
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+    /// NUT00 Error
+    #[error(transparent)]
+    NUT00(#[from] crate::nuts::nut00::Error),
+    /// Nostr NIP-19 Error
+    #[cfg(feature = "nostr")]
+    #[error(transparent)]
+    NostrNip19(#[from] nostr_sdk::nips::nip19::Error),
+    /// Wallet Error
+    #[error(transparent)]
+    Wallet(#[from] super::error::Error),
+}
+
+/// Delivery of the payment to one transport
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransportAttempt {
+    pub transport: Transport,
+    /// Why the delivery failed, `None` if it succeeded
+    pub error: Option<String>,
+}
+
+impl TransportAttempt {
+    /// Check if the transport accepted the payment
+    pub fn delivered(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Outcome of [`Wallet::pay_request`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentRequestOutcome {
+    /// Mint the token is from
+    pub mint_url: MintUrl,
+    pub amount: Amount,
+    pub unit: CurrencyUnit,
+    /// Token sent, to deliver it another way if no transport accepted it
+    pub token: String,
+    /// Transports tried, in order
+    pub attempts: Vec<TransportAttempt>,
+}
+
+impl PaymentRequestOutcome {
+    /// Check if a transport accepted the payment
+    pub fn delivered(&self) -> bool {
+        self.attempts.iter().any(|attempt| attempt.delivered())
+    }
+}
+
+impl Wallet {
+    /// Pay a NUT-18 payment request
+    ///
+    /// `amount` is required if the request does not set one. The token is
+    /// created from the first accepted mint holding enough funds of the unit
+    /// of the request, sat if it sets none, and stays reserved if no transport
+    /// accepts it. A request without transports is paid in-band: the token is
+    /// returned with no attempts.
+    #[instrument(skip(self, request))]
+    pub async fn pay_request(
+        &self,
+        request: &PaymentRequest,
+        amount: Option<Amount>,
+    ) -> Result<PaymentRequestOutcome, Error> {
+        let amount = match (request.amount, amount) {
+            (Some(requested), Some(amount)) if requested.ne(&amount) => {
+                return Err(Error::AmountMismatch(requested, amount))
+            }
+            (Some(amount), _) | (None, Some(amount)) => amount,
+            (None, None) => return Err(Error::AmountUndefined),
+        };
+        let unit = request.unit.clone().unwrap_or_default();
+
+        let mint_url = self
+            .mint_balances()
+            .await?
+            .into_iter()
+            .filter(|(mint_url, _)| request.accepts_mint(mint_url))
+            .filter(|(_, balances)| balances.get(&unit).is_some_and(|b| b.ge(&amount)))
+            .map(|(mint_url, _)| mint_url)
+            .min()
+            .ok_or_else(|| Error::NoAcceptedMint(amount, unit.clone()))?;
+
+        let token = self
+            .send(
+                &mint_url,
+                unit.clone(),
+                request.description.clone(),
+                amount,
+                &SplitTarget::default(),
+                None,
+            )
+            .await?;
+
+        let payload = PaymentRequestPayload {
+            id: request.payment_id.clone(),
+            memo: request.description.clone(),
+            mint: mint_url.clone(),
+            unit: unit.clone(),
+            proofs: Token::from_str(&token)?
+                .token
+                .into_iter()
+                .flat_map(|t| t.proofs)
+                .collect(),
+        };
+
+        let mut attempts = Vec::new();
+        for transport in &request.transports {
+            let error = match self.deliver_payment(transport, &payload).await {
+                Ok(()) => None,
+                Err(err) => {
+                    tracing::warn!("Could not deliver payment to {}: {}", transport.target, err);
+                    Some(err.to_string())
+                }
+            };
+            let delivered = error.is_none();
+
+            attempts.push(TransportAttempt {
+                transport: transport.clone(),
+                error,
+            });
+
+            if delivered {
+                break;
+            }
+        }
+
+        Ok(PaymentRequestOutcome {
+            mint_url,
+            amount,
+            unit,
+            token,
+            attempts,
+        })
+    }
+
+    async fn deliver_payment(
+        &self,
+        transport: &Transport,
+        payload: &PaymentRequestPayload,
+    ) -> Result<(), Error> {
+        match transport._type {
+            TransportType::HttpPost => {
+                let url = Url::parse(&transport.target)?;
+                self.client
+                    .post_payment_request(url, payload.clone())
+                    .await?;
+
+                Ok(())
+            }
+            #[cfg(feature = "nostr")]
+            TransportType::Nostr => {
+                use nostr_sdk::nips::nip19::{FromBech32, Nip19Profile};
+
+                let profile = Nip19Profile::from_bech32(&transport.target)?;
+                self.add_nostr_relays(profile.relays.iter().map(|r| r.to_string()).collect())
+                    .await?;
+
+                // The payload is sent from a new key, it does not identify the payer
+                self.nostr_send(
+                    &crate::nuts::SecretKey::generate(),
+                    &profile.public_key.to_hex(),
+                    &serde_json::to_string(payload)?,
+                    super::nostr::NostrDirectMessage::Nip17,
+                )
+                .await?;
+
+                Ok(())
+            }
+            #[cfg(not(feature = "nostr"))]
+            TransportType::Nostr => Err(Error::UnsupportedTransport(transport._type)),
+        }
+    }
+}
@@ -0,0 +1,286 @@
+//! HTTP 402 paywall
+//!
+//! Helpers to charge ecash for HTTP requests. The server answers unpaid
+//! requests with `402 Payment Required` and a [`PaymentChallenge`] in the
+//! [`X_CASHU`] header, the client retries with a token in the same header.
+
+use std::str::FromStr;
+
+use http::{HeaderMap, HeaderValue, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::instrument;
+
+use super::Wallet;
+use crate::amount::SplitTarget;
+use crate::mint_url::MintUrl;
+use crate::nuts::{CurrencyUnit, Token};
+use crate::Amount;
+
+/// Header carrying the payment challenge and the token
+pub const X_CASHU: &str = "X-Cashu";
+
+/// Paywall Error
+#[derive(Debug, Error)]
+pub enum Error {
+    /// No token attached to request
+    #[error("Payment required")]
+    PaymentRequired,
+    /// Token from mint that is not accepted
+    #[error("Mint `{0}` not accepted")]
+    MintNotAccepted(MintUrl),
+    /// Token unit is not accepted
+    #[error("Unit `{0}` not accepted")]
+    UnitNotAccepted(CurrencyUnit),
+    /// Token value is below price
+    #[error("Payment of `{0}` is less than `{1}`")]
+    InsufficientPayment(Amount, Amount),
+    /// Invalid header value
+    #[error(transparent)]
+    InvalidHeader(#[from] http::header::InvalidHeaderValue),
+    /// Header is not visible ASCII
+    #[error(transparent)]
+    HeaderToStr(#[from] http::header::ToStrError),
+    /// NUT00 Error
+    #[error(transparent)]
+    NUT00(#[from] crate::nuts::nut00::Error),
+    /// Serde Error
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+    /// Wallet Error
+    #[error(transparent)]
+    Wallet(#[from] super::error::Error),
+}
+
+/// Payment a server requires for a request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaymentChallenge {
+    /// Price of the request
+    pub amount: Amount,
+    /// Unit of price
+    pub unit: CurrencyUnit,
+    /// Mints tokens are accepted from
+    pub mints: Vec<MintUrl>,
+}
+
+impl PaymentChallenge {
+    /// Read challenge from the headers of a `402` response
+    pub fn from_headers(headers: &HeaderMap) -> Result<Self, Error> {
+        let challenge = headers.get(X_CASHU).ok_or(Error::PaymentRequired)?;
+
+        Ok(serde_json::from_str(challenge.to_str()?)?)
+    }
+}
+
+/// Server side of the paywall
+///
+/// Tokens attached to requests are claimed into `wallet` before the request is
+/// served.
+#[derive(Clone)]
+pub struct Paywall {
+    wallet: Wallet,
+    challenge: PaymentChallenge,
+}
+
+impl Paywall {
+    /// Create new [`Paywall`] charging `amount` per request
+    pub fn new(wallet: Wallet, amount: Amount, unit: CurrencyUnit, mints: Vec<MintUrl>) -> Self {
+        Self {
+            wallet,
+            challenge: PaymentChallenge {
+                amount,
+                unit,
+                mints,
+            },
+        }
+    }
+
+    /// Payment required by this paywall
+    pub fn challenge(&self) -> &PaymentChallenge {
+        &self.challenge
+    }
+
+    /// `402 Payment Required` response carrying the challenge
+    pub fn challenge_response(&self) -> Result<Response<String>, Error> {
+        let challenge = serde_json::to_string(&self.challenge)?;
+
+        let mut response = Response::new(challenge.clone());
+        *response.status_mut() = StatusCode::PAYMENT_REQUIRED;
+        response
+            .headers_mut()
+            .insert(X_CASHU, HeaderValue::from_str(&challenge)?);
+
+        Ok(response)
+    }
+
+    /// Check the token attached to a request and claim it
+    ///
+    /// Returns the amount received.
+    #[instrument(skip_all)]
+    pub async fn verify(&self, headers: &HeaderMap) -> Result<Amount, Error> {
+        let encoded_token = headers
+            .get(X_CASHU)
+            .ok_or(Error::PaymentRequired)?
+            .to_str()?;
+
+        let token = Token::from_str(encoded_token)?;
+        self.check_token(&token)?;
+
+        Ok(self
+            .wallet
+            .receive(encoded_token, &SplitTarget::default(), None)
+            .await?)
+    }
+
+    /// Check a request, returning the response to answer it with when it is
+    /// not paid
+    ///
+    /// This is the hook for server middleware: serve the request on `None`,
+    /// otherwise return the response.
+    #[instrument(skip_all)]
+    pub async fn check(&self, headers: &HeaderMap) -> Option<Response<String>> {
+        match self.verify(headers).await {
+            Ok(_) => None,
+            Err(err) => {
+                tracing::debug!("Request not paid: {}", err);
+
+                match self.challenge_response() {
+                    Ok(response) => Some(response),
+                    Err(err) => {
+                        tracing::error!("Could not create payment challenge: {}", err);
+
+                        let mut response = Response::new(String::new());
+                        *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+                        Some(response)
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_token(&self, token: &Token) -> Result<(), Error> {
+        let unit = token.unit.clone().unwrap_or_default();
+        if unit != self.challenge.unit {
+            return Err(Error::UnitNotAccepted(unit));
+        }
+
+        for mint_proofs in token.token.iter() {
+            if !self.challenge.mints.contains(&mint_proofs.mint) {
+                return Err(Error::MintNotAccepted(mint_proofs.mint.clone()));
+            }
+        }
+
+        let (amount, _) = token.token_info()?;
+        if amount < self.challenge.amount {
+            return Err(Error::InsufficientPayment(amount, self.challenge.amount));
+        }
+
+        Ok(())
+    }
+}
+
+impl Wallet {
+    /// Attach a token paying `challenge` to an outgoing request
+    ///
+    /// The token is created from the first mint in the challenge the wallet
+    /// holds enough funds at.
+    #[instrument(skip(self, request))]
+    pub async fn attach_payment(
+        &self,
+        request: reqwest::RequestBuilder,
+        challenge: &PaymentChallenge,
+    ) -> Result<reqwest::RequestBuilder, Error> {
+        let balances = self.mint_balances().await?;
+
+        let mint_url = challenge
+            .mints
+            .iter()
+            .find(|mint_url| {
+                balances
+                    .get(*mint_url)
+                    .and_then(|b| b.get(&challenge.unit))
+                    .is_some_and(|balance| *balance >= challenge.amount)
+            })
+            .ok_or(super::error::Error::InsufficientFunds)?;
+
+        let token = self
+            .send(
+                mint_url,
+                challenge.unit.clone(),
+                None,
+                challenge.amount,
+                &SplitTarget::default(),
+                None,
+            )
+            .await?;
+
+        Ok(request.header(X_CASHU, token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::cdk_database::WalletMemoryDatabase;
+
+    const TOKEN: &str = "cashuAeyJ0b2tlbiI6W3sibWludCI6Imh0dHBzOi8vODMzMy5zcGFjZTozMzM4IiwicHJvb2ZzIjpbeyJhbW91bnQiOjIsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6IjQwNzkxNWJjMjEyYmU2MWE3N2UzZTZkMmFlYjRjNzI3OTgwYmRhNTFjZDA2YTZhZmMyOWUyODYxNzY4YTc4MzciLCJDIjoiMDJiYzkwOTc5OTdkODFhZmIyY2M3MzQ2YjVlNDM0NWE5MzQ2YmQyYTUwNmViNzk1ODU5OGE3MmYwY2Y4NTE2M2VhIn0seyJhbW91bnQiOjgsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6ImZlMTUxMDkzMTRlNjFkNzc1NmIwZjhlZTBmMjNhNjI0YWNhYTNmNGUwNDJmNjE0MzNjNzI4YzcwNTdiOTMxYmUiLCJDIjoiMDI5ZThlNTA1MGI4OTBhN2Q2YzA5NjhkYjE2YmMxZDVkNWZhMDQwZWExZGUyODRmNmVjNjlkNjEyOTlmNjcxMDU5In1dfV0sInVuaXQiOiJzYXQiLCJtZW1vIjoiVGhhbmsgeW91LiJ9";
+
+    fn create_paywall(amount: u64, mint_url: &str) -> Paywall {
+        let localstore = WalletMemoryDatabase::new(
+            vec![],
+            vec![],
+            vec![],
+            HashMap::new(),
+            #[cfg(feature = "nostr")]
+            HashMap::new(),
+        );
+        let wallet = Wallet::new(Arc::new(localstore), &[0u8; 32], vec![]);
+
+        Paywall::new(
+            wallet,
+            Amount::from(amount),
+            CurrencyUnit::Sat,
+            vec![MintUrl::from_str(mint_url).unwrap()],
+        )
+    }
+
+    #[test]
+    fn test_challenge_response() {
+        let paywall = create_paywall(10, "https://8333.space:3338");
+
+        let response = paywall.challenge_response().unwrap();
+        assert_eq!(response.status(), StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(
+            &PaymentChallenge::from_headers(response.headers()).unwrap(),
+            paywall.challenge()
+        );
+    }
+
+    #[test]
+    fn test_check_token() {
+        let token = Token::from_str(TOKEN).unwrap();
+
+        assert!(create_paywall(10, "https://8333.space:3338")
+            .check_token(&token)
+            .is_ok());
+        assert!(matches!(
+            create_paywall(11, "https://8333.space:3338").check_token(&token),
+            Err(Error::InsufficientPayment(_, _))
+        ));
+        assert!(matches!(
+            create_paywall(10, "https://mint.example").check_token(&token),
+            Err(Error::MintNotAccepted(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_check_without_token() {
+        let paywall = create_paywall(10, "https://8333.space:3338");
+
+        let response = paywall.check(&HeaderMap::new()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYMENT_REQUIRED);
+    }
+}
@@ -0,0 +1,237 @@
+//! Spend policy
+//!
+//! A [`SpendPolicy`] set on a [`Wallet`](super::Wallet) is checked before
+//! every send and melt. It can cap the amount of a single spend, the amount
+//! spent per unit in the last 24 hours, restrict spending to a set of mints and
+//! ask a [`SpendApproval`] callback to confirm each spend.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+use crate::mint_url::MintUrl;
+use crate::nuts::CurrencyUnit;
+use crate::util::unix_time;
+use crate::Amount;
+
+/// Length of the window the daily limit applies to
+const DAY: u64 = 24 * 60 * 60;
+
+/// Spend Policy Error
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Mint is not in the allow list
+    #[error("Spending from mint `{0}` is not allowed")]
+    MintNotAllowed(MintUrl),
+    /// Spend is larger than the per spend limit
+    #[error("Spend of `{0}` exceeds limit of `{1}`")]
+    AmountExceeded(Amount, Amount),
+    /// Spend would exceed the daily limit
+    #[error("Spend of `{0}` exceeds remaining daily limit of `{1}`")]
+    DailyLimitExceeded(Amount, Amount),
+    /// Spend was rejected by the approval callback
+    #[error("Spend not approved")]
+    NotApproved,
+}
+
+/// Kind of spend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpendKind {
+    /// Token created with [`Wallet::send`](super::Wallet::send)
+    Send,
+    /// Invoice paid with [`Wallet::melt`](super::Wallet::melt)
+    Melt,
+}
+
+/// Spend checked against a [`SpendPolicy`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpendRequest {
+    pub kind: SpendKind,
+    pub mint_url: MintUrl,
+    pub unit: CurrencyUnit,
+    /// Amount leaving the wallet, melts include the fee reserve
+    pub amount: Amount,
+}
+
+/// Callback confirming spends, e.g. by asking the user
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait SpendApproval {
+    /// Return `true` to allow the spend
+    async fn approve(&self, request: &SpendRequest) -> bool;
+}
+
+/// Limits applied to wallet spends
+///
+/// Limits apply per unit. Spends are counted towards the daily limit once they
+/// succeeded.
+#[derive(Default)]
+pub struct SpendPolicy {
+    max_amount: Option<Amount>,
+    daily_limit: Option<Amount>,
+    allowed_mints: Option<HashSet<MintUrl>>,
+    approval: Option<Arc<dyn SpendApproval + Send + Sync>>,
+    /// Time, unit and amount of recent spends
+    spent: Mutex<Vec<(u64, CurrencyUnit, Amount)>>,
+}
+
+impl SpendPolicy {
+    /// Create new [`SpendPolicy`] without any limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the amount of a single spend
+    pub fn max_amount(mut self, max_amount: Amount) -> Self {
+        self.max_amount = Some(max_amount);
+        self
+    }
+
+    /// Limit the amount spent in any 24 hours
+    pub fn daily_limit(mut self, daily_limit: Amount) -> Self {
+        self.daily_limit = Some(daily_limit);
+        self
+    }
+
+    /// Only allow spending from `mints`
+    pub fn allowed_mints<I>(mut self, mints: I) -> Self
+    where
+        I: IntoIterator<Item = MintUrl>,
+    {
+        self.allowed_mints = Some(mints.into_iter().collect());
+        self
+    }
+
+    /// Ask `approval` to confirm every spend that is within the limits
+    pub fn approval(mut self, approval: Arc<dyn SpendApproval + Send + Sync>) -> Self {
+        self.approval = Some(approval);
+        self
+    }
+
+    /// Check that a spend is allowed
+    pub async fn check(&self, request: &SpendRequest) -> Result<(), Error> {
+        if let Some(allowed_mints) = &self.allowed_mints {
+            if !allowed_mints.contains(&request.mint_url) {
+                return Err(Error::MintNotAllowed(request.mint_url.clone()));
+            }
+        }
+
+        if let Some(max_amount) = self.max_amount {
+            if request.amount > max_amount {
+                return Err(Error::AmountExceeded(request.amount, max_amount));
+            }
+        }
+
+        if let Some(daily_limit) = self.daily_limit {
+            let spent_today = self.spent_today(&request.unit).await;
+            let remaining = daily_limit.checked_sub(spent_today).unwrap_or_default();
+
+            if request.amount > remaining {
+                return Err(Error::DailyLimitExceeded(request.amount, remaining));
+            }
+        }
+
+        if let Some(approval) = &self.approval {
+            if !approval.approve(request).await {
+                return Err(Error::NotApproved);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Count a completed spend towards the daily limit
+    pub async fn record(&self, request: &SpendRequest) {
+        let now = unix_time();
+
+        let mut spent = self.spent.lock().await;
+        spent.retain(|(time, _, _)| time + DAY > now);
+        spent.push((now, request.unit.clone(), request.amount));
+    }
+
+    /// Amount of `unit` spent in the last 24 hours
+    pub async fn spent_today(&self, unit: &CurrencyUnit) -> Amount {
+        let now = unix_time();
+
+        self.spent
+            .lock()
+            .await
+            .iter()
+            .filter(|(time, u, _)| time + DAY > now && u == unit)
+            .map(|(_, _, amount)| *amount)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    struct Reject;
+
+    #[async_trait]
+    impl SpendApproval for Reject {
+        async fn approve(&self, _request: &SpendRequest) -> bool {
+            false
+        }
+    }
+
+    fn request(mint_url: &str, amount: u64) -> SpendRequest {
+        SpendRequest {
+            kind: SpendKind::Send,
+            mint_url: MintUrl::from_str(mint_url).unwrap(),
+            unit: CurrencyUnit::Sat,
+            amount: Amount::from(amount),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_limits() {
+        let policy = SpendPolicy::new()
+            .max_amount(Amount::from(100))
+            .daily_limit(Amount::from(150))
+            .allowed_mints([MintUrl::from_str("https://mint.example").unwrap()]);
+
+        assert!(matches!(
+            policy.check(&request("https://other.example", 10)).await,
+            Err(Error::MintNotAllowed(_))
+        ));
+        assert!(matches!(
+            policy.check(&request("https://mint.example", 101)).await,
+            Err(Error::AmountExceeded(_, _))
+        ));
+
+        let spend = request("https://mint.example", 100);
+        policy.check(&spend).await.unwrap();
+        policy.record(&spend).await;
+
+        assert!(matches!(
+            policy.check(&spend).await,
+            Err(Error::DailyLimitExceeded(_, _))
+        ));
+        policy
+            .check(&request("https://mint.example", 50))
+            .await
+            .unwrap();
+
+        // Limits are per unit
+        let mut usd = spend.clone();
+        usd.unit = CurrencyUnit::Usd;
+        policy.check(&usd).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_approval() {
+        let policy = SpendPolicy::new().approval(Arc::new(Reject));
+
+        assert!(matches!(
+            policy.check(&request("https://mint.example", 1)).await,
+            Err(Error::NotApproved)
+        ));
+    }
+}
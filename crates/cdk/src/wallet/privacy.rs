@@ -0,0 +1,93 @@
+//! Privacy mode of sends
+//!
+//! A send that hands over proofs the wallet received earlier lets the mint
+//! link the payment to the one that created them, and splits into other
+//! amounts than powers of two make the outputs of a swap recognizable. With a
+//! [`PrivacyMode`] a send always swaps, into the standard power of two
+//! denominations, and requests the outputs in random order. The change can be
+//! split into decoy proofs of half the amount, so that the sent proofs do not
+//! stand out from the change. Both cost fees and proofs for unlinkability.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Error, Wallet};
+use crate::amount::SplitTarget;
+use crate::mint_url::MintUrl;
+use crate::nuts::{CurrencyUnit, SpendingConditions};
+use crate::Amount;
+
+/// Privacy settings of a send
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrivacyMode {
+    /// Split every change proof above 1 into two proofs of half its amount
+    pub decoy_change: bool,
+}
+
+impl PrivacyMode {
+    /// Split of the `change` of a swap
+    pub(crate) fn change_split(&self, change: Amount) -> SplitTarget {
+        match self.decoy_change {
+            true => SplitTarget::Values(
+                change
+                    .split()
+                    .into_iter()
+                    .flat_map(|part| match u64::from(part) {
+                        1 => vec![part],
+                        part => vec![Amount::from(part / 2); 2],
+                    })
+                    .collect(),
+            ),
+            false => SplitTarget::None,
+        }
+    }
+}
+
+impl Wallet {
+    /// Send with `privacy`
+    ///
+    /// Like [`Wallet::send`], but the selected proofs are always swapped into
+    /// standard denominations, even if they add up to `amount`.
+    pub async fn send_private(
+        &self,
+        mint_url: &MintUrl,
+        unit: CurrencyUnit,
+        memo: Option<String>,
+        amount: Amount,
+        conditions: Option<SpendingConditions>,
+        privacy: PrivacyMode,
+    ) -> Result<String, Error> {
+        self.send_inner(
+            mint_url,
+            unit,
+            memo,
+            BTreeMap::new(),
+            amount,
+            &SplitTarget::None,
+            conditions,
+            Some(privacy),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_change_split() {
+        let change = Amount::from(13);
+        assert_eq!(
+            PrivacyMode::default().change_split(change),
+            SplitTarget::None
+        );
+
+        let split = PrivacyMode { decoy_change: true }.change_split(change);
+        let mut parts = change.split_targeted(&split).unwrap();
+        parts.sort();
+        let parts: Vec<u64> = parts.into_iter().map(u64::from).collect();
+        assert_eq!(parts, vec![1, 2, 2, 4, 4]);
+    }
+}
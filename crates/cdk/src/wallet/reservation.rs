@@ -0,0 +1,107 @@
+//! Proof reservation
+//!
+//! Sends and melts select their inputs from the unspent proofs in storage and
+//! only mark them pending once selected, so two operations of the same wallet
+//! running concurrently could select the same proofs. [`ProofReservations`]
+//! claims the selected proofs in memory: a claim fails if any of the proofs is
+//! already claimed and is released when the returned [`Reservation`] is
+//! dropped, i.e. once the operation completes or its future is cancelled.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use crate::nuts::{Proofs, PublicKey};
+
+use super::Error;
+
+/// Proofs claimed by operations in progress
+#[derive(Debug, Clone, Default)]
+pub struct ProofReservations {
+    reserved: Arc<Mutex<HashSet<PublicKey>>>,
+}
+
+impl ProofReservations {
+    /// Check if the proof with `y` is claimed
+    pub fn contains(&self, y: &PublicKey) -> bool {
+        self.reserved.lock().expect("Lock poisoned").contains(y)
+    }
+
+    /// Claim `proofs`
+    ///
+    /// Returns `None` without claiming anything if one of the proofs is
+    /// already claimed.
+    pub fn reserve(&self, proofs: &Proofs) -> Result<Option<Reservation>, Error> {
+        let ys = proofs
+            .iter()
+            .map(|p| p.y())
+            .collect::<Result<Vec<PublicKey>, _>>()?;
+
+        let mut reserved = self.reserved.lock().expect("Lock poisoned");
+        if ys.iter().any(|y| reserved.contains(y)) {
+            return Ok(None);
+        }
+        reserved.extend(ys.iter().cloned());
+
+        Ok(Some(Reservation {
+            ys,
+            reserved: self.reserved.clone(),
+        }))
+    }
+}
+
+/// Claim on proofs, released on drop
+#[derive(Debug)]
+pub struct Reservation {
+    ys: Vec<PublicKey>,
+    reserved: Arc<Mutex<HashSet<PublicKey>>>,
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if let Ok(mut reserved) = self.reserved.lock() {
+            for y in &self.ys {
+                reserved.remove(y);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::nuts::{Id, Proof};
+    use crate::secret::Secret;
+    use crate::Amount;
+
+    fn proof(amount: u64) -> Proof {
+        Proof::new(
+            Amount::from(amount),
+            Id::from_str("009a1f293253e41e").unwrap(),
+            Secret::generate(),
+            PublicKey::from_hex(
+                "02698c4e2b5f9534cd0687d87513c759790cf829aa5739184a3e3735471fbda904",
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_reservation() {
+        let reservations = ProofReservations::default();
+        let first = vec![proof(1), proof(2)];
+        let second = vec![first[1].clone(), proof(4)];
+
+        let reservation = reservations.reserve(&first).unwrap().unwrap();
+        assert!(reservations.contains(&first[0].y().unwrap()));
+
+        // Overlapping claims fail and claim nothing
+        assert!(reservations.reserve(&second).unwrap().is_none());
+        assert!(!reservations.contains(&second[1].y().unwrap()));
+
+        drop(reservation);
+        assert!(!reservations.contains(&first[0].y().unwrap()));
+        assert!(reservations.reserve(&second).unwrap().is_some());
+    }
+}
@@ -0,0 +1,169 @@
+//! Keyset rotation
+//!
+//! A mint rotates the keyset of a unit by activating a new keyset and
+//! deactivating the old one. Proofs of the inactive keyset are still redeemed,
+//! possibly only until its final expiry, but outputs are only signed with the
+//! active keyset.
+//!
+//! [`Wallet::check_keyset_rotation`] refreshes the keysets of a mint and
+//! publishes a [`WalletEvent::KeysetRotated`] for every keyset deactivated
+//! since the last refresh. With [`Wallet::swap_inactive_keysets`] set the
+//! unspent proofs of the inactive keyset are swapped into the active one.
+//! Requests the mint rejects for an inactive or unknown keyset refresh the
+//! keysets too, so that a retry uses the active keyset.
+
+use super::events::WalletEvent;
+use super::{Error, Wallet};
+use crate::amount::SplitTarget;
+use crate::error::ErrorCode;
+use crate::mint_url::MintUrl;
+use crate::nuts::{CurrencyUnit, Id, Proofs, State};
+use crate::Amount;
+
+/// Keyset deactivated by a mint
+struct Rotation {
+    unit: CurrencyUnit,
+    inactive: Id,
+    active: Id,
+}
+
+impl Wallet {
+    /// Handle keysets `mint_url` deactivated since they were last refreshed
+    ///
+    /// Returns the published [`WalletEvent::KeysetRotated`] events.
+    pub async fn check_keyset_rotation(
+        &self,
+        mint_url: &MintUrl,
+    ) -> Result<Vec<WalletEvent>, Error> {
+        let mut events = Vec::new();
+
+        for rotation in self.detect_rotations(mint_url).await? {
+            let swapped = match self.swap_inactive_keysets {
+                true => Some(self.swap_keyset_proofs(mint_url, &rotation).await?),
+                false => None,
+            };
+
+            let event = rotation.into_event(mint_url, swapped);
+            self.events.publish(event.clone());
+            events.push(event);
+        }
+
+        Ok(events)
+    }
+
+    /// Refresh the keysets of `mint_url` if `err` was caused by a rotation
+    pub(crate) async fn handle_keyset_error(&self, mint_url: &MintUrl, err: &Error) {
+        match err.error_code() {
+            ErrorCode::KeysetInactive | ErrorCode::KeysetNotFound => (),
+            _ => return,
+        }
+
+        match self.detect_rotations(mint_url).await {
+            Ok(rotations) => {
+                for rotation in rotations {
+                    self.events.publish(rotation.into_event(mint_url, None));
+                }
+            }
+            Err(err) => tracing::warn!("Could not refresh keysets of {}: {}", mint_url, err),
+        }
+    }
+
+    /// Refresh the keysets of `mint_url` and compare them to the stored ones
+    async fn detect_rotations(&self, mint_url: &MintUrl) -> Result<Vec<Rotation>, Error> {
+        let stored = self
+            .localstore
+            .get_mint_keysets(mint_url.clone())
+            .await?
+            .unwrap_or_default();
+
+        self.refresh_mint_keys(mint_url).await?;
+
+        let current = self
+            .localstore
+            .get_mint_keysets(mint_url.clone())
+            .await?
+            .unwrap_or_default();
+
+        let mut rotations = Vec::new();
+        for keyset in stored.iter().filter(|k| k.active) {
+            let deactivated = current.iter().any(|k| k.id == keyset.id && !k.active);
+            if !deactivated {
+                continue;
+            }
+
+            match current.iter().find(|k| k.unit == keyset.unit && k.active) {
+                Some(active) => rotations.push(Rotation {
+                    unit: keyset.unit.clone(),
+                    inactive: keyset.id,
+                    active: active.id,
+                }),
+                None => tracing::warn!(
+                    "{} deactivated keyset {} without an active {} keyset",
+                    mint_url,
+                    keyset.id,
+                    keyset.unit
+                ),
+            }
+        }
+
+        Ok(rotations)
+    }
+
+    /// Swap the unspent proofs of the inactive keyset of `rotation`
+    ///
+    /// Proofs claimed by an operation in progress are left to it.
+    async fn swap_keyset_proofs(
+        &self,
+        mint_url: &MintUrl,
+        rotation: &Rotation,
+    ) -> Result<Amount, Error> {
+        let proofs: Proofs = self
+            .localstore
+            .get_proofs(
+                Some(mint_url.clone()),
+                Some(rotation.unit.clone()),
+                Some(vec![State::Unspent]),
+                None,
+            )
+            .await?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| p.proof)
+            .filter(|p| p.keyset_id == rotation.inactive)
+            .filter(|p| p.y().is_ok_and(|y| !self.reservations.contains(&y)))
+            .collect();
+        if proofs.is_empty() {
+            return Ok(Amount::ZERO);
+        }
+
+        let _reservation = match self.reservations.reserve(&proofs)? {
+            Some(reservation) => reservation,
+            None => return Ok(Amount::ZERO),
+        };
+
+        let amount = Amount::try_sum(proofs.iter().map(|p| p.amount))?;
+        self.swap(
+            mint_url,
+            &rotation.unit,
+            None,
+            &SplitTarget::default(),
+            proofs,
+            None,
+        )
+        .await?;
+
+        Ok(amount)
+    }
+}
+
+impl Rotation {
+    fn into_event(self, mint_url: &MintUrl, swapped: Option<Amount>) -> WalletEvent {
+        WalletEvent::KeysetRotated {
+            mint_url: mint_url.clone(),
+            unit: self.unit,
+            inactive: self.inactive,
+            active: self.active,
+            swapped,
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Denomination statistics
+//!
+//! [`DenominationStats`] describes how the unspent proofs of a mint and unit
+//! are split into denominations, for wallets to decide when a swap is worth
+//! it and to hint it to their users.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Error, Wallet};
+use crate::mint_url::MintUrl;
+use crate::nuts::{CurrencyUnit, State};
+use crate::Amount;
+
+/// Fragmentation above which [`DenominationStats::swap_recommended`] is `true`
+pub const FRAGMENTATION_THRESHOLD: f64 = 0.5;
+
+/// Denominations of the unspent proofs of a mint and unit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DenominationStats {
+    /// Number of proofs by amount
+    pub counts: BTreeMap<Amount, usize>,
+    /// Number of proofs
+    pub proofs: usize,
+    /// Sum of the proofs
+    pub total: Amount,
+    /// Every amount up to this one can be sent without a swap
+    pub max_offline_amount: Amount,
+    /// Share of proofs beyond the fewest that add up to `total`, from 0 for
+    /// an optimal split to close to 1 for many small proofs
+    pub fragmentation: f64,
+}
+
+impl DenominationStats {
+    /// Statistics of proofs of `amounts`
+    pub fn from_amounts<I>(amounts: I) -> Self
+    where
+        I: IntoIterator<Item = Amount>,
+    {
+        let mut counts = BTreeMap::new();
+        for amount in amounts {
+            *counts.entry(amount).or_insert(0) += 1;
+        }
+
+        let proofs: usize = counts.values().sum();
+        let total = counts
+            .iter()
+            .map(|(amount, count)| u64::from(*amount).saturating_mul(*count as u64))
+            .fold(0u64, u64::saturating_add);
+
+        // Amounts up to `reachable` can be paid exactly with the smaller proofs,
+        // a proof larger than `reachable + 1` leaves a gap
+        let mut reachable = 0u64;
+        'amounts: for (amount, count) in &counts {
+            for _ in 0..*count {
+                if u64::from(*amount) > reachable.saturating_add(1) {
+                    break 'amounts;
+                }
+                reachable = reachable.saturating_add(u64::from(*amount));
+            }
+        }
+
+        let fragmentation = match proofs {
+            0 => 0.0,
+            proofs => {
+                let optimal = Amount::from(total).split().len();
+                proofs.saturating_sub(optimal) as f64 / proofs as f64
+            }
+        };
+
+        Self {
+            counts,
+            proofs,
+            total: Amount::from(total),
+            max_offline_amount: Amount::from(reachable),
+            fragmentation,
+        }
+    }
+
+    /// Whether swapping the proofs into fewer would be worth it
+    pub fn swap_recommended(&self) -> bool {
+        self.fragmentation > FRAGMENTATION_THRESHOLD
+    }
+}
+
+impl Wallet {
+    /// Denominations of the unspent proofs of `mint_url` in `unit`
+    pub async fn denomination_stats(
+        &self,
+        mint_url: &MintUrl,
+        unit: &CurrencyUnit,
+    ) -> Result<DenominationStats, Error> {
+        let proofs = self
+            .localstore
+            .get_proofs(
+                Some(mint_url.clone()),
+                Some(unit.clone()),
+                Some(vec![State::Unspent]),
+                None,
+            )
+            .await?
+            .unwrap_or_default();
+
+        Ok(DenominationStats::from_amounts(
+            proofs.iter().map(|p| p.proof.amount),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(amounts: &[u64]) -> DenominationStats {
+        DenominationStats::from_amounts(amounts.iter().copied().map(Amount::from))
+    }
+
+    #[test]
+    fn test_denomination_stats() {
+        let empty = stats(&[]);
+        assert_eq!(empty.total, Amount::ZERO);
+        assert_eq!(empty.fragmentation, 0.0);
+        assert!(!empty.swap_recommended());
+
+        // 1 + 2 + 4 + 8 pays every amount up to 15
+        let optimal = stats(&[8, 1, 4, 2]);
+        assert_eq!(optimal.proofs, 4);
+        assert_eq!(optimal.total, Amount::from(15));
+        assert_eq!(optimal.max_offline_amount, Amount::from(15));
+        assert_eq!(optimal.fragmentation, 0.0);
+
+        // Nothing below 4 can be paid without a swap
+        let gap = stats(&[4, 16]);
+        assert_eq!(gap.max_offline_amount, Amount::ZERO);
+        let gap = stats(&[1, 1, 4]);
+        assert_eq!(gap.max_offline_amount, Amount::from(2));
+
+        let fragmented = stats(&[1; 8]);
+        assert_eq!(fragmented.counts[&Amount::from(1)], 8);
+        assert_eq!(fragmented.max_offline_amount, Amount::from(8));
+        assert_eq!(fragmented.fragmentation, 7.0 / 8.0);
+        assert!(fragmented.swap_recommended());
+    }
+}
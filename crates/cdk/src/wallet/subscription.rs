@@ -0,0 +1,746 @@
+//! NUT-17 subscriptions of a wallet
+//!
+//! [`SubscriptionManager`] multiplexes any number of subscriptions over one
+//! WebSocket connection per mint. A connection is opened with the first
+//! subscription to a mint and closed with the last one.
+//!
+//! When a connection drops it is opened again and every active subscription
+//! is sent again. States missed while disconnected are looked up with the
+//! [`Client`] of the wallet first, and a subscription only receives a state
+//! that differs from the last one it received.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use tokio::sync::{mpsc, Mutex};
+use tokio_tungstenite::tungstenite::{self, Message};
+
+use super::client::Client;
+use super::{Error, Wallet};
+use crate::mint_url::MintUrl;
+use crate::nuts::nut17::{Params, UnsubscribeParams, WsMethod, WsOutcome};
+use crate::nuts::{
+    NotificationPayload, Proofs, PublicKey, SubscriptionKind, WsNotification, WsRequest, WsResponse,
+};
+
+/// Default time between attempts to connect to a mint
+pub const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// WebSocket connection to a mint
+pub trait WsSocket:
+    Stream<Item = Result<Message, tungstenite::Error>>
+    + Sink<Message, Error = tungstenite::Error>
+    + Send
+    + Unpin
+{
+}
+
+impl<T> WsSocket for T where
+    T: Stream<Item = Result<Message, tungstenite::Error>>
+        + Sink<Message, Error = tungstenite::Error>
+        + Send
+        + Unpin
+{
+}
+
+/// Opens WebSocket connections to mints
+#[async_trait]
+pub trait WsConnector {
+    /// Connect to the NUT-17 endpoint of `mint_url`
+    async fn connect(&self, mint_url: &MintUrl) -> Result<Box<dyn WsSocket>, Error>;
+}
+
+/// [`WsConnector`] connecting to `<mint url>/v1/ws`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TungsteniteConnector;
+
+#[async_trait]
+impl WsConnector for TungsteniteConnector {
+    async fn connect(&self, mint_url: &MintUrl) -> Result<Box<dyn WsSocket>, Error> {
+        let (socket, _) = tokio_tungstenite::connect_async(ws_url(mint_url)).await?;
+        Ok(Box::new(socket))
+    }
+}
+
+/// Url of the NUT-17 endpoint of `mint_url`
+fn ws_url(mint_url: &MintUrl) -> String {
+    let url = mint_url.to_string();
+    let url = match url.strip_prefix("https://") {
+        Some(rest) => format!("wss://{}", rest),
+        None => format!("ws://{}", url.trim_start_matches("http://")),
+    };
+
+    format!("{}/v1/ws", url)
+}
+
+/// Request of a [`Subscription`] to the task of its mint
+enum Command {
+    Subscribe {
+        params: Params,
+        sender: mpsc::UnboundedSender<NotificationPayload>,
+    },
+    Unsubscribe {
+        sub_id: String,
+    },
+}
+
+/// Subscriptions of a wallet to its mints
+///
+/// Clones share their connections.
+#[derive(Clone)]
+pub struct SubscriptionManager {
+    client: Arc<dyn Client + Send + Sync>,
+    connector: Arc<dyn WsConnector + Send + Sync>,
+    reconnect_delay: Duration,
+    mints: Arc<Mutex<HashMap<MintUrl, mpsc::UnboundedSender<Command>>>>,
+    next_sub_id: Arc<AtomicU64>,
+}
+
+impl SubscriptionManager {
+    /// Create new [`SubscriptionManager`]
+    ///
+    /// `client` looks up the states missed while disconnected.
+    pub fn new(
+        client: Arc<dyn Client + Send + Sync>,
+        connector: Arc<dyn WsConnector + Send + Sync>,
+    ) -> Self {
+        Self {
+            client,
+            connector,
+            reconnect_delay: RECONNECT_DELAY,
+            mints: Arc::new(Mutex::new(HashMap::new())),
+            next_sub_id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Time between attempts to connect, [`RECONNECT_DELAY`] if not set
+    pub fn reconnect_delay(mut self, delay: Duration) -> Self {
+        self.reconnect_delay = delay;
+        self
+    }
+
+    /// Subscribe to the states of the `filters` of `kind` at `mint_url`
+    ///
+    /// The subscription receives the current state of every filter and then
+    /// every change, until it is dropped.
+    pub async fn subscribe(
+        &self,
+        mint_url: MintUrl,
+        kind: SubscriptionKind,
+        filters: Vec<String>,
+    ) -> Subscription {
+        let sub_id = format!("{:x}", self.next_sub_id.fetch_add(1, Ordering::Relaxed));
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        let mut mints = self.mints.lock().await;
+        let commands = match mints.get(&mint_url) {
+            Some(commands) if !commands.is_closed() => commands.clone(),
+            _ => {
+                let (commands, receiver) = mpsc::unbounded_channel();
+                let task = MintTask {
+                    mint_url: mint_url.clone(),
+                    client: self.client.clone(),
+                    connector: self.connector.clone(),
+                    reconnect_delay: self.reconnect_delay,
+                    commands: receiver,
+                    subscriptions: HashMap::new(),
+                    pending: HashMap::new(),
+                    next_request_id: 0,
+                };
+                tokio::spawn(task.run());
+                mints.insert(mint_url, commands.clone());
+                commands
+            }
+        };
+
+        let _ = commands.send(Command::Subscribe {
+            params: Params {
+                kind,
+                filters,
+                sub_id: sub_id.clone(),
+            },
+            sender,
+        });
+
+        Subscription {
+            sub_id,
+            receiver,
+            commands,
+        }
+    }
+
+    /// Subscribe to the states of `proofs` at `mint_url`
+    ///
+    /// E.g. for the recipient of a locked token to learn when it is spent.
+    pub async fn subscribe_proofs(
+        &self,
+        mint_url: MintUrl,
+        proofs: &Proofs,
+    ) -> Result<Subscription, Error> {
+        let filters = proofs
+            .iter()
+            .map(|proof| proof.y().map(|y| y.to_hex()))
+            .collect::<Result<Vec<String>, _>>()?;
+
+        Ok(self
+            .subscribe(mint_url, SubscriptionKind::ProofState, filters)
+            .await)
+    }
+}
+
+/// Subscription of a [`SubscriptionManager`], unsubscribed when dropped
+pub struct Subscription {
+    sub_id: String,
+    receiver: mpsc::UnboundedReceiver<NotificationPayload>,
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl Subscription {
+    /// Id of the subscription sent to the mint
+    pub fn id(&self) -> &str {
+        &self.sub_id
+    }
+
+    /// Next state, `None` once the mint rejected the subscription
+    pub async fn recv(&mut self) -> Option<NotificationPayload> {
+        self.receiver.recv().await
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let _ = self.commands.send(Command::Unsubscribe {
+            sub_id: std::mem::take(&mut self.sub_id),
+        });
+    }
+}
+
+/// Active subscription of a [`MintTask`]
+struct Active {
+    params: Params,
+    sender: mpsc::UnboundedSender<NotificationPayload>,
+    /// Last state sent by filter
+    last: HashMap<String, NotificationPayload>,
+}
+
+/// How [`MintTask::serve`] ended
+enum Served {
+    /// Every subscription and the manager were dropped
+    Closed,
+    /// No subscription left
+    Idle,
+    /// Connection dropped
+    Dropped,
+}
+
+/// Connection to one mint, driven until every sender of commands is dropped
+struct MintTask {
+    mint_url: MintUrl,
+    client: Arc<dyn Client + Send + Sync>,
+    connector: Arc<dyn WsConnector + Send + Sync>,
+    reconnect_delay: Duration,
+    commands: mpsc::UnboundedReceiver<Command>,
+    subscriptions: HashMap<String, Active>,
+    /// Subscription id by id of its subscribe request
+    pending: HashMap<u64, String>,
+    next_request_id: u64,
+}
+
+impl MintTask {
+    async fn run(mut self) {
+        let mut missed = false;
+
+        loop {
+            if self.subscriptions.is_empty() {
+                match self.commands.recv().await {
+                    Some(command) => {
+                        self.apply(command);
+                    }
+                    None => return,
+                }
+                missed = false;
+                continue;
+            }
+
+            let mut socket = match self.connector.connect(&self.mint_url).await {
+                Ok(socket) => socket,
+                Err(err) => {
+                    tracing::warn!("Could not connect to {}: {}", self.mint_url, err);
+                    missed = true;
+                    if !self.wait(self.reconnect_delay).await {
+                        return;
+                    }
+                    continue;
+                }
+            };
+
+            if missed {
+                self.resume().await;
+            }
+
+            match self.serve(&mut socket).await {
+                Served::Closed => {
+                    let _ = socket.close().await;
+                    return;
+                }
+                Served::Idle => {
+                    let _ = socket.close().await;
+                    missed = false;
+                }
+                Served::Dropped => {
+                    tracing::debug!("Connection to {} dropped", self.mint_url);
+                    missed = true;
+                    if !self.wait(self.reconnect_delay).await {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Apply the commands received within `delay`, `false` if none can follow
+    async fn wait(&mut self, delay: Duration) -> bool {
+        let sleep = tokio::time::sleep(delay);
+        tokio::pin!(sleep);
+
+        loop {
+            tokio::select! {
+                _ = &mut sleep => return true,
+                command = self.commands.recv() => match command {
+                    Some(command) => {
+                        self.apply(command);
+                    }
+                    None => return false,
+                },
+            }
+        }
+    }
+
+    /// Send every subscription, then forward requests and notifications
+    async fn serve(&mut self, socket: &mut Box<dyn WsSocket>) -> Served {
+        self.pending.clear();
+        let sub_ids: Vec<String> = self.subscriptions.keys().cloned().collect();
+        for sub_id in sub_ids {
+            let request = self.subscribe_request(&sub_id);
+            if let Some(request) = request {
+                if self.send(socket, &request).await.is_err() {
+                    return Served::Dropped;
+                }
+            }
+        }
+
+        loop {
+            if self.subscriptions.is_empty() {
+                return Served::Idle;
+            }
+
+            tokio::select! {
+                command = self.commands.recv() => match command {
+                    Some(command) => {
+                        if let Some(request) = self.apply(command) {
+                            if self.send(socket, &request).await.is_err() {
+                                return Served::Dropped;
+                            }
+                        }
+                    }
+                    None => return Served::Closed,
+                },
+                message = socket.next() => match message {
+                    Some(Ok(Message::Text(text))) => self.handle_message(&text),
+                    Some(Ok(Message::Close(_))) | None => return Served::Dropped,
+                    Some(Ok(_)) => (),
+                    Some(Err(err)) => {
+                        tracing::debug!("Could not read from {}: {}", self.mint_url, err);
+                        return Served::Dropped;
+                    }
+                },
+            }
+        }
+    }
+
+    /// Update the subscriptions, returns the request to send if connected
+    fn apply(&mut self, command: Command) -> Option<WsRequest> {
+        match command {
+            Command::Subscribe { params, sender } => {
+                let sub_id = params.sub_id.clone();
+                self.subscriptions.insert(
+                    sub_id.clone(),
+                    Active {
+                        params,
+                        sender,
+                        last: HashMap::new(),
+                    },
+                );
+                self.subscribe_request(&sub_id)
+            }
+            Command::Unsubscribe { sub_id } => {
+                self.subscriptions.remove(&sub_id)?;
+                Some(WsRequest::new(
+                    WsMethod::Unsubscribe(UnsubscribeParams { sub_id }),
+                    self.request_id(),
+                ))
+            }
+        }
+    }
+
+    fn subscribe_request(&mut self, sub_id: &str) -> Option<WsRequest> {
+        let params = self.subscriptions.get(sub_id)?.params.clone();
+        let id = self.request_id();
+        self.pending.insert(id, sub_id.to_string());
+
+        Some(WsRequest::new(WsMethod::Subscribe(params), id))
+    }
+
+    fn request_id(&mut self) -> u64 {
+        let id = self.next_request_id;
+        self.next_request_id += 1;
+        id
+    }
+
+    async fn send(&self, socket: &mut Box<dyn WsSocket>, request: &WsRequest) -> Result<(), Error> {
+        let json = serde_json::to_string(request)?;
+        socket.send(Message::Text(json)).await?;
+        Ok(())
+    }
+
+    fn handle_message(&mut self, text: &str) {
+        if let Ok(notification) = serde_json::from_str::<WsNotification>(text) {
+            self.deliver(&notification.params.sub_id, notification.params.payload);
+            return;
+        }
+
+        match serde_json::from_str::<WsResponse>(text) {
+            Ok(response) => {
+                let sub_id = response.id.and_then(|id| self.pending.remove(&id));
+                if let (WsOutcome::Error(error), Some(sub_id)) = (response.outcome, sub_id) {
+                    tracing::warn!(
+                        "{} rejected subscription {}: {}",
+                        self.mint_url,
+                        sub_id,
+                        error.message
+                    );
+                    self.subscriptions.remove(&sub_id);
+                }
+            }
+            Err(err) => tracing::debug!("Unknown message from {}: {}", self.mint_url, err),
+        }
+    }
+
+    /// Send `payload` to the subscription unless it already has that state
+    fn deliver(&mut self, sub_id: &str, payload: NotificationPayload) {
+        let Some(active) = self.subscriptions.get_mut(sub_id) else {
+            return;
+        };
+        let id = payload.id();
+        if active.last.get(&id) == Some(&payload) {
+            return;
+        }
+
+        active.last.insert(id, payload.clone());
+        let _ = active.sender.send(payload);
+    }
+
+    /// Look up the state of every filter, before subscribing again
+    async fn resume(&mut self) {
+        let filters: Vec<(String, SubscriptionKind, String)> = self
+            .subscriptions
+            .iter()
+            .flat_map(|(sub_id, active)| {
+                active
+                    .params
+                    .filters
+                    .iter()
+                    .map(|filter| (sub_id.clone(), active.params.kind, filter.clone()))
+            })
+            .collect();
+
+        for (sub_id, kind, filter) in filters {
+            match self.lookup(kind, &filter).await {
+                Ok(payload) => self.deliver(&sub_id, payload),
+                Err(err) => tracing::debug!("Could not look up {:?} {}: {}", kind, filter, err),
+            }
+        }
+    }
+
+    async fn lookup(
+        &self,
+        kind: SubscriptionKind,
+        filter: &str,
+    ) -> Result<NotificationPayload, Error> {
+        let mint_url = self.mint_url.clone().try_into()?;
+        let payload = match kind {
+            SubscriptionKind::Bolt11MintQuote => NotificationPayload::MintQuote(
+                self.client.get_mint_quote_status(mint_url, filter).await?,
+            ),
+            SubscriptionKind::Bolt11MeltQuote => NotificationPayload::MeltQuote(
+                self.client.get_melt_quote_status(mint_url, filter).await?,
+            ),
+            SubscriptionKind::ProofState => {
+                let y = PublicKey::from_hex(filter)?;
+                let state = self
+                    .client
+                    .post_check_state(mint_url, vec![y])
+                    .await?
+                    .states
+                    .into_iter()
+                    .find(|state| state.y == y)
+                    .ok_or_else(|| Error::Custom(format!("No state of proof {}", y)))?;
+                NotificationPayload::ProofState(state)
+            }
+        };
+
+        Ok(payload)
+    }
+}
+
+impl Wallet {
+    /// [`SubscriptionManager`] using the client of the wallet
+    pub fn subscription_manager(&self) -> SubscriptionManager {
+        SubscriptionManager::new(self.client.clone(), Arc::new(TungsteniteConnector))
+    }
+}
+
+#[cfg(all(test, feature = "mint"))]
+mod tests {
+    use std::str::FromStr;
+
+    use tokio::task::JoinHandle;
+    use tokio_tungstenite::tungstenite::protocol::Role;
+    use tokio_tungstenite::WebSocketStream;
+    use url::Url;
+
+    use super::*;
+    use crate::amount::SplitTarget;
+    use crate::mint::tests::create_mint;
+    use crate::mint::tests::{active_keyset_id, mint_proofs};
+    use crate::mint::ws::{WsClose, WsConfig};
+    use crate::mint::Mint;
+    use crate::nuts::{
+        CheckStateRequest, CheckStateResponse, CurrencyUnit, Id, KeySet, KeysetResponse,
+        MeltQuoteBolt11Response, MintInfo, MintQuoteBolt11Response, PreMintSecrets, State,
+        SwapRequest,
+    };
+    use crate::wallet::client::{
+        CheckStateConnector, IssueConnector, KeysConnector, MeltConnector, PaymentRequestConnector,
+        QuoteConnector, RestoreConnector, SwapConnector,
+    };
+    use crate::Amount;
+
+    /// Connects to the handler of a mint in memory
+    struct MintConnector {
+        mint: Mint,
+        connections: std::sync::Mutex<Vec<JoinHandle<WsClose>>>,
+    }
+
+    #[async_trait]
+    impl WsConnector for MintConnector {
+        async fn connect(&self, _mint_url: &MintUrl) -> Result<Box<dyn WsSocket>, Error> {
+            let (client, server) = tokio::io::duplex(64 * 1024);
+            let server = WebSocketStream::from_raw_socket(server, Role::Server, None).await;
+            let client = WebSocketStream::from_raw_socket(client, Role::Client, None).await;
+
+            let mint = self.mint.clone();
+            let handle =
+                tokio::spawn(async move { mint.handle_ws(server, WsConfig::default()).await });
+            self.connections.lock().unwrap().push(handle);
+
+            Ok(Box::new(client))
+        }
+    }
+
+    /// Looks up quotes of a mint in memory
+    struct MintClient {
+        mint: Mint,
+    }
+
+    #[async_trait]
+    impl KeysConnector for MintClient {
+        async fn get_mint_keys(&self, _mint_url: Url) -> Result<Vec<KeySet>, Error> {
+            Err(Error::UnsupportedOperation("keys".to_string()))
+        }
+
+        async fn get_mint_keyset(&self, _mint_url: Url, _keyset_id: Id) -> Result<KeySet, Error> {
+            Err(Error::UnsupportedOperation("keys".to_string()))
+        }
+
+        async fn get_mint_keysets(&self, _mint_url: Url) -> Result<KeysetResponse, Error> {
+            Err(Error::UnsupportedOperation("keys".to_string()))
+        }
+
+        async fn get_mint_info(&self, _mint_url: Url) -> Result<MintInfo, Error> {
+            Err(Error::UnsupportedOperation("info".to_string()))
+        }
+    }
+
+    #[async_trait]
+    impl QuoteConnector for MintClient {
+        async fn get_mint_quote_status(
+            &self,
+            _mint_url: Url,
+            quote_id: &str,
+        ) -> Result<MintQuoteBolt11Response, Error> {
+            self.mint
+                .check_mint_quote(quote_id)
+                .await
+                .map_err(|err| Error::Custom(err.to_string()))
+        }
+
+        async fn get_melt_quote_status(
+            &self,
+            _mint_url: Url,
+            quote_id: &str,
+        ) -> Result<MeltQuoteBolt11Response, Error> {
+            self.mint
+                .check_melt_quote(quote_id)
+                .await
+                .map_err(|err| Error::Custom(err.to_string()))
+        }
+    }
+
+    impl IssueConnector for MintClient {}
+    impl MeltConnector for MintClient {}
+    impl SwapConnector for MintClient {}
+    #[async_trait]
+    impl CheckStateConnector for MintClient {
+        async fn post_check_state(
+            &self,
+            _mint_url: Url,
+            ys: Vec<PublicKey>,
+        ) -> Result<CheckStateResponse, Error> {
+            self.mint
+                .check_state(&CheckStateRequest { ys })
+                .await
+                .map_err(|err| Error::Custom(err.to_string()))
+        }
+    }
+    impl RestoreConnector for MintClient {}
+    impl PaymentRequestConnector for MintClient {}
+
+    fn paid(payload: Option<NotificationPayload>) -> bool {
+        match payload {
+            Some(NotificationPayload::MintQuote(quote)) => quote.paid,
+            payload => panic!("Unexpected payload {:?}", payload),
+        }
+    }
+
+    #[test]
+    fn test_ws_url() {
+        assert_eq!(
+            ws_url(&MintUrl::from_str("https://mint.example/cashu").unwrap()),
+            "wss://mint.example/cashu/v1/ws"
+        );
+        assert_eq!(
+            ws_url(&MintUrl::from_str("http://localhost:3338").unwrap()),
+            "ws://localhost:3338/v1/ws"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscription_manager_reconnects() {
+        let mint = create_mint().await;
+        let mint_url = MintUrl::from_str("https://mint.example").unwrap();
+        let quote = mint
+            .new_mint_quote(
+                mint_url.clone(),
+                "lnbc".to_string(),
+                CurrencyUnit::Sat,
+                Amount::from(8),
+                u64::MAX,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let connector = Arc::new(MintConnector {
+            mint: mint.clone(),
+            connections: std::sync::Mutex::new(Vec::new()),
+        });
+        let manager = SubscriptionManager::new(
+            Arc::new(MintClient { mint: mint.clone() }),
+            connector.clone(),
+        )
+        .reconnect_delay(Duration::from_millis(10));
+
+        let mut first = manager
+            .subscribe(
+                mint_url.clone(),
+                SubscriptionKind::Bolt11MintQuote,
+                vec![quote.id.clone()],
+            )
+            .await;
+        let mut second = manager
+            .subscribe(
+                mint_url.clone(),
+                SubscriptionKind::Bolt11MintQuote,
+                vec![quote.id.clone()],
+            )
+            .await;
+        assert_ne!(first.id(), second.id());
+        assert!(!paid(first.recv().await));
+        assert!(!paid(second.recv().await));
+        assert_eq!(connector.connections.lock().unwrap().len(), 1);
+
+        // Quote paid while the connection is down
+        connector.connections.lock().unwrap()[0].abort();
+        let mut paid_quote = quote.clone();
+        paid_quote.paid = true;
+        mint.update_mint_quote(paid_quote).await.unwrap();
+
+        assert!(paid(first.recv().await));
+        assert!(paid(second.recv().await));
+        assert_eq!(connector.connections.lock().unwrap().len(), 2);
+
+        // State sent again on subscribe is not delivered twice
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), first.recv())
+                .await
+                .is_err()
+        );
+
+        drop(first);
+        drop(second);
+        let handle = connector.connections.lock().unwrap().pop().unwrap();
+        assert_eq!(
+            tokio::time::timeout(Duration::from_secs(1), handle)
+                .await
+                .unwrap()
+                .unwrap(),
+            WsClose::Closed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_proofs() {
+        let mint = create_mint().await;
+        let mint_url = MintUrl::from_str("https://mint.example").unwrap();
+        let proofs = mint_proofs(&mint, Amount::from(8)).await;
+
+        let manager = SubscriptionManager::new(
+            Arc::new(MintClient { mint: mint.clone() }),
+            Arc::new(MintConnector {
+                mint: mint.clone(),
+                connections: std::sync::Mutex::new(Vec::new()),
+            }),
+        );
+        let mut subscription = manager.subscribe_proofs(mint_url, &proofs).await.unwrap();
+
+        let state = |payload: Option<NotificationPayload>| match payload {
+            Some(NotificationPayload::ProofState(state)) => state.state,
+            payload => panic!("Unexpected payload {:?}", payload),
+        };
+        assert_eq!(state(subscription.recv().await), State::Unspent);
+
+        let keyset_id = active_keyset_id(&mint, &CurrencyUnit::Sat).await;
+        let premint =
+            PreMintSecrets::random(keyset_id, Amount::from(8), &SplitTarget::None).unwrap();
+        mint.process_swap_request(SwapRequest::new(proofs, premint.blinded_messages()))
+            .await
+            .unwrap();
+
+        assert_eq!(state(subscription.recv().await), State::Spent);
+    }
+}
@@ -0,0 +1,173 @@
+//! Wire formats of the HTTP api
+//!
+//! Requests and responses of the v1 endpoints are JSON, and optionally CBOR
+//! for clients that ask for it. CBOR carries the same data model in fewer
+//! bytes, which matters for proof-heavy requests like restores and large
+//! swaps. Clients send CBOR bodies with `Content-Type: application/cbor` and
+//! ask for CBOR responses with an `Accept` header, a mint that does not
+//! support CBOR answers with JSON.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+
+/// Media type of JSON bodies
+pub const JSON: &str = "application/json";
+/// Media type of CBOR bodies
+pub const CBOR: &str = "application/cbor";
+
+/// Wire format error
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Media type is neither JSON nor CBOR
+    #[error("Unsupported content type `{0}`")]
+    UnsupportedContentType(String),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    CborSerialize(#[from] ciborium::ser::Error<std::io::Error>),
+    #[error(transparent)]
+    CborDeserialize(#[from] ciborium::de::Error<std::io::Error>),
+}
+
+/// Format of a request or response body
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ContentType {
+    #[default]
+    Json,
+    Cbor,
+}
+
+impl ContentType {
+    /// Media type of the format
+    pub fn media_type(&self) -> &'static str {
+        match self {
+            Self::Json => JSON,
+            Self::Cbor => CBOR,
+        }
+    }
+
+    /// Format of a `Content-Type` header, parameters are ignored
+    pub fn from_header(value: &str) -> Result<Self, Error> {
+        let media_type = value.split(';').next().unwrap_or_default().trim();
+
+        match media_type.to_ascii_lowercase().as_str() {
+            JSON => Ok(Self::Json),
+            CBOR => Ok(Self::Cbor),
+            _ => Err(Error::UnsupportedContentType(value.to_string())),
+        }
+    }
+
+    /// Format of the response to a request with `accept` as `Accept` header
+    ///
+    /// The supported format with the highest quality, JSON if the header is
+    /// missing, accepts any format or none that is supported.
+    pub fn negotiate(accept: Option<&str>) -> Self {
+        let mut best = (Self::Json, 0.0);
+
+        for range in accept.unwrap_or_default().split(',') {
+            let mut parts = range.split(';');
+            let media_type = parts.next().unwrap_or_default().trim();
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            // JSON wins ties, e.g. for `*/*`
+            let content_type = match Self::from_header(media_type) {
+                Ok(content_type) => content_type,
+                Err(_) => continue,
+            };
+            if quality > best.1 || (quality == best.1 && content_type == Self::Json) {
+                best = (content_type, quality);
+            }
+        }
+
+        best.0
+    }
+
+    /// Encode `value` in the format
+    pub fn encode<T>(&self, value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize,
+    {
+        match self {
+            Self::Json => Ok(serde_json::to_vec(value)?),
+            Self::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(value, &mut bytes)?;
+                Ok(bytes)
+            }
+        }
+    }
+
+    /// Decode `bytes` in the format
+    pub fn decode<T>(&self, bytes: &[u8]) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        match self {
+            Self::Json => Ok(serde_json::from_slice(bytes)?),
+            Self::Cbor => Ok(ciborium::from_reader(bytes)?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nuts::Proof;
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(ContentType::negotiate(None), ContentType::Json);
+        assert_eq!(ContentType::negotiate(Some("*/*")), ContentType::Json);
+        assert_eq!(
+            ContentType::negotiate(Some("application/cbor")),
+            ContentType::Cbor
+        );
+        assert_eq!(
+            ContentType::negotiate(Some("application/json;q=0.5, application/cbor")),
+            ContentType::Cbor
+        );
+        assert_eq!(
+            ContentType::negotiate(Some("application/cbor;q=0.1, application/json")),
+            ContentType::Json
+        );
+        assert_eq!(
+            ContentType::negotiate(Some("application/cbor, application/json")),
+            ContentType::Json
+        );
+        assert_eq!(
+            ContentType::from_header("application/json; charset=utf-8").unwrap(),
+            ContentType::Json
+        );
+        assert!(ContentType::from_header("text/html").is_err());
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let proof: Proof = serde_json::from_str(
+            r#"{"amount":2,"id":"009a1f293253e41e","secret":"407915bc212be61a77e3e6d2aeb4c727980bda51cd06a6afc29e2861768a7837","C":"02bc9097997d81afb2cc7346b5e4345a9346bd2a506eb7958598a72f0cf85163ea"}"#,
+        )
+        .unwrap();
+        let proofs = vec![proof; 10];
+
+        for content_type in [ContentType::Json, ContentType::Cbor] {
+            let bytes = content_type.encode(&proofs).unwrap();
+            let decoded: Vec<Proof> = content_type.decode(&bytes).unwrap();
+            assert_eq!(decoded, proofs);
+        }
+
+        // Decoded through the JSON data model, as clients do
+        let value = serde_json::to_value(&proofs).unwrap();
+        let bytes = ContentType::Cbor.encode(&value).unwrap();
+        let value: serde_json::Value = ContentType::Cbor.decode(&bytes).unwrap();
+        assert_eq!(serde_json::from_value::<Vec<Proof>>(value).unwrap(), proofs);
+
+        assert!(
+            ContentType::Cbor.encode(&proofs).unwrap().len()
+                < ContentType::Json.encode(&proofs).unwrap().len()
+        );
+    }
+}